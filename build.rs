@@ -0,0 +1,32 @@
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    // Generates the gRPC services of `grpc` from the protos.
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto");
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().expect("protoc is vendored"));
+        tonic_build::compile_protos("proto/battle_monsters.proto").expect("Failed to compile the protos");
+    }
+
+    // Embeds the up.sql of every migration, in order, for `Database::migrate` to apply them to each schema.
+    println!("cargo:rerun-if-changed=migrations");
+    let mut migrations: Vec<_> = fs::read_dir("migrations")
+        .expect("Failed to read the migrations")
+        .map(|entry| entry.expect("Failed to read the migrations").path())
+        .filter(|migration| migration.join("up.sql").is_file())
+        .collect();
+    migrations.sort();
+    let embedded: String = migrations
+        .iter()
+        .map(|migration| {
+            let name = migration.file_name().and_then(|name| name.to_str()).expect("Migration names are UTF-8");
+            let up = fs::canonicalize(migration.join("up.sql")).expect("Failed to read the migrations");
+            format!("    ({:?}, include_str!({:?})),\n", name, up)
+        })
+        .collect();
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    fs::write(Path::new(&out_dir).join("migrations.rs"), format!("&[\n{}]\n", embedded)).expect("Failed to embed the migrations");
+    println!("cargo:rerun-if-changed=build.rs");
+}
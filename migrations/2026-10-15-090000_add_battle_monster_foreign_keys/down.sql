@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE battles
+    DROP CONSTRAINT battles_monster_a_fkey,
+    DROP CONSTRAINT battles_monster_b_fkey,
+    DROP CONSTRAINT battles_winner_fkey;
+
+ALTER TABLE battles
+    ADD CONSTRAINT battles_winner_fkey FOREIGN KEY (winner) REFERENCES monsters(id) ON DELETE CASCADE;
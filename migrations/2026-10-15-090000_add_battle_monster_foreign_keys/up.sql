@@ -0,0 +1,28 @@
+-- Your SQL goes here
+-- Battles of monsters that no longer exist are reported rather than deleted, for them to be fixed or removed by hand.
+DO $$
+DECLARE
+    orphans bigint;
+    examples text;
+BEGIN
+    SELECT count(*), string_agg(id, ', ' ORDER BY id) FILTER (WHERE rank <= 20)
+    INTO orphans, examples
+    FROM (
+        SELECT id, row_number() OVER (ORDER BY id) AS rank
+        FROM battles
+        WHERE monster_a NOT IN (SELECT id FROM monsters)
+           OR monster_b NOT IN (SELECT id FROM monsters)
+    ) orphaned;
+    IF orphans > 0 THEN
+        RAISE EXCEPTION '% battles reference monsters that do not exist, like %', orphans, examples
+            USING HINT = 'Restore their monsters or delete the battles, then run the migration again.';
+    END IF;
+END
+$$;
+
+ALTER TABLE battles DROP CONSTRAINT battles_winner_fkey;
+
+ALTER TABLE battles
+    ADD CONSTRAINT battles_monster_a_fkey FOREIGN KEY (monster_a) REFERENCES monsters(id),
+    ADD CONSTRAINT battles_monster_b_fkey FOREIGN KEY (monster_b) REFERENCES monsters(id),
+    ADD CONSTRAINT battles_winner_fkey FOREIGN KEY (winner) REFERENCES monsters(id);
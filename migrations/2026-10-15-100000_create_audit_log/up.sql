@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE audit_log (
+    id varchar PRIMARY KEY,
+    entity_type varchar NOT NULL,
+    entity_id varchar NOT NULL,
+    action varchar NOT NULL,
+    actor varchar,
+    diff jsonb NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX audit_log_entity_idx ON audit_log (entity_type, entity_id);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX monsters_name_trgm_idx;
+DROP INDEX monsters_name_fts_idx;
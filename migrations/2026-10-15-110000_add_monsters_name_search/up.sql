@@ -0,0 +1,5 @@
+-- Your SQL goes here
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX monsters_name_fts_idx ON monsters USING gin (to_tsvector('simple', name));
+CREATE INDEX monsters_name_trgm_idx ON monsters USING gin (name gin_trgm_ops);
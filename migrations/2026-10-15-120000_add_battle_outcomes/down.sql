@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM battles WHERE winner IS NULL;
+
+ALTER TABLE battles
+    DROP CONSTRAINT battles_draw_winner_check,
+    DROP CONSTRAINT battles_outcome_check,
+    DROP COLUMN manual,
+    DROP COLUMN outcome,
+    ALTER COLUMN winner SET NOT NULL;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE battles
+    ALTER COLUMN winner DROP NOT NULL,
+    ADD COLUMN outcome VARCHAR NOT NULL DEFAULT 'win',
+    ADD COLUMN manual BOOLEAN NOT NULL DEFAULT false,
+    ADD CONSTRAINT battles_outcome_check CHECK (outcome IN ('win', 'draw', 'forfeit')),
+    ADD CONSTRAINT battles_draw_winner_check CHECK ((outcome = 'draw') = (winner IS NULL));
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+ALTER TABLE monsters ADD COLUMN last_battle_at TIMESTAMP;
+
+UPDATE monsters SET last_battle_at = (
+    SELECT max(battles.created_at)
+    FROM battles
+    WHERE battles.monster_a = monsters.id OR battles.monster_b = monsters.id
+);
@@ -0,0 +1,14 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE battles
+    DROP CONSTRAINT battles_monster_a_fkey,
+    DROP CONSTRAINT battles_monster_b_fkey,
+    DROP CONSTRAINT battles_winner_fkey;
+
+ALTER TABLE battles
+    ADD CONSTRAINT battles_monster_a_fkey FOREIGN KEY (monster_a) REFERENCES monsters(id),
+    ADD CONSTRAINT battles_monster_b_fkey FOREIGN KEY (monster_b) REFERENCES monsters(id),
+    ADD CONSTRAINT battles_winner_fkey FOREIGN KEY (winner) REFERENCES monsters(id);
+
+ALTER TABLE battles DROP CONSTRAINT battles_winner_participant_check;
+
+ALTER TABLE monsters DROP CONSTRAINT monsters_name_not_empty_check;
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+UPDATE monsters SET name = 'Monster ' || left(id, 8) WHERE btrim(name) = '';
+
+ALTER TABLE monsters
+    ADD CONSTRAINT monsters_name_not_empty_check CHECK (btrim(name) <> '');
+
+ALTER TABLE battles
+    ADD CONSTRAINT battles_winner_participant_check CHECK (winner IS NULL OR winner IN (monster_a, monster_b));
+
+-- Battles are history: a monster that fought can only go once its battles are deleted first.
+ALTER TABLE battles
+    DROP CONSTRAINT battles_monster_a_fkey,
+    DROP CONSTRAINT battles_monster_b_fkey,
+    DROP CONSTRAINT battles_winner_fkey;
+
+ALTER TABLE battles
+    ADD CONSTRAINT battles_monster_a_fkey FOREIGN KEY (monster_a) REFERENCES monsters(id) ON DELETE RESTRICT,
+    ADD CONSTRAINT battles_monster_b_fkey FOREIGN KEY (monster_b) REFERENCES monsters(id) ON DELETE RESTRICT,
+    ADD CONSTRAINT battles_winner_fkey FOREIGN KEY (winner) REFERENCES monsters(id) ON DELETE RESTRICT;
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE webhooks (
+    id varchar PRIMARY KEY,
+    target_url text NOT NULL,
+    event_types text[] NOT NULL,
+    secret varchar NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP,
+    CONSTRAINT webhooks_target_url_check CHECK (target_url ~ '^https?://'),
+    CONSTRAINT webhooks_event_types_check CHECK (cardinality(event_types) > 0)
+);
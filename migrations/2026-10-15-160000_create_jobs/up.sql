@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE jobs (
+    id varchar PRIMARY KEY,
+    kind varchar NOT NULL,
+    status varchar NOT NULL DEFAULT 'queued',
+    total integer NOT NULL,
+    processed integer NOT NULL DEFAULT 0,
+    failed integer NOT NULL DEFAULT 0,
+    result jsonb,
+    error text,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP,
+    finished_at TIMESTAMP,
+    CONSTRAINT jobs_status_check CHECK (status IN ('queued', 'running', 'succeeded', 'failed'))
+);
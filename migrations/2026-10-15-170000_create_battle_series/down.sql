@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE battle_series_games;
+DROP TABLE battle_series;
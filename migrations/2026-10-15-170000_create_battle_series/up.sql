@@ -0,0 +1,19 @@
+-- Your SQL goes here
+CREATE TABLE battle_series (
+    id varchar PRIMARY KEY,
+    monster_a varchar NOT NULL REFERENCES monsters(id) ON DELETE CASCADE,
+    monster_b varchar NOT NULL REFERENCES monsters(id) ON DELETE CASCADE,
+    best_of integer NOT NULL,
+    winner varchar NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    CONSTRAINT battle_series_best_of_check CHECK (best_of > 0 AND best_of % 2 = 1),
+    CONSTRAINT battle_series_winner_check CHECK (winner IN (monster_a, monster_b))
+);
+
+CREATE TABLE battle_series_games (
+    series_id varchar NOT NULL REFERENCES battle_series(id) ON DELETE CASCADE,
+    game integer NOT NULL,
+    battle_id varchar NOT NULL REFERENCES battles(id) ON DELETE CASCADE,
+    seed bigint NOT NULL,
+    PRIMARY KEY (series_id, game)
+);
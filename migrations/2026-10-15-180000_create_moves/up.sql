@@ -0,0 +1,25 @@
+-- Your SQL goes here
+CREATE TABLE moves (
+    id varchar PRIMARY KEY,
+    name varchar NOT NULL,
+    power integer NOT NULL,
+    accuracy integer NOT NULL,
+    element varchar,
+    effect varchar,
+    effect_chance integer NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP,
+    CONSTRAINT moves_power_check CHECK (power > 0),
+    CONSTRAINT moves_accuracy_check CHECK (accuracy BETWEEN 1 AND 100),
+    CONSTRAINT moves_effect_check CHECK (effect IN ('poison', 'stun', 'burn')),
+    CONSTRAINT moves_effect_chance_check CHECK (effect_chance BETWEEN 0 AND 100)
+);
+
+CREATE TABLE monster_moves (
+    monster_id varchar NOT NULL REFERENCES monsters(id) ON DELETE CASCADE,
+    slot integer NOT NULL,
+    move_id varchar NOT NULL REFERENCES moves(id) ON DELETE CASCADE,
+    PRIMARY KEY (monster_id, slot),
+    CONSTRAINT monster_moves_move_unique UNIQUE (monster_id, move_id),
+    CONSTRAINT monster_moves_slot_check CHECK (slot BETWEEN 1 AND 4)
+);
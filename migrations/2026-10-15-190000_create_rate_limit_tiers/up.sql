@@ -0,0 +1,24 @@
+-- Your SQL goes here
+CREATE TABLE rate_limit_tiers (
+    name varchar PRIMARY KEY,
+    standard_per_minute integer NOT NULL,
+    expensive_per_minute integer NOT NULL,
+    streaming_per_minute integer NOT NULL,
+    updated_at TIMESTAMP,
+    CONSTRAINT rate_limit_tiers_budget_check CHECK (standard_per_minute >= 0 AND expensive_per_minute >= 0 AND streaming_per_minute >= 0)
+);
+
+-- Same budgets as `RateLimitTier::defaults`.
+INSERT INTO rate_limit_tiers (name, standard_per_minute, expensive_per_minute, streaming_per_minute) VALUES
+    ('anonymous', 60, 5, 2),
+    ('free', 120, 10, 5),
+    ('partner', 1200, 100, 20),
+    ('admin', 6000, 1000, 100);
+
+CREATE TABLE api_keys (
+    id varchar PRIMARY KEY,
+    name varchar NOT NULL,
+    key_hash varchar NOT NULL UNIQUE,
+    tier varchar NOT NULL REFERENCES rate_limit_tiers(name),
+    created_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+CREATE TABLE interactive_battles (
+    id varchar PRIMARY KEY,
+    monster_a varchar NOT NULL REFERENCES monsters(id) ON DELETE CASCADE,
+    monster_b varchar NOT NULL REFERENCES monsters(id) ON DELETE CASCADE,
+    monster_a_hp integer NOT NULL,
+    monster_b_hp integer NOT NULL,
+    turn integer NOT NULL,
+    monster_a_ready boolean NOT NULL DEFAULT false,
+    monster_a_move varchar,
+    monster_b_ready boolean NOT NULL DEFAULT false,
+    monster_b_move varchar,
+    turns jsonb NOT NULL DEFAULT '[]',
+    finished boolean NOT NULL DEFAULT false,
+    winner varchar,
+    battle_id varchar REFERENCES battles(id) ON DELETE SET NULL,
+    turn_deadline TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP,
+    CONSTRAINT interactive_battles_hp_check CHECK (monster_a_hp >= 0 AND monster_b_hp >= 0)
+);
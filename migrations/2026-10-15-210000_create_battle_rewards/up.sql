@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE TABLE battle_rewards (
+    id varchar PRIMARY KEY,
+    battle_id varchar NOT NULL REFERENCES battles(id) ON DELETE CASCADE,
+    monster_id varchar NOT NULL REFERENCES monsters(id) ON DELETE CASCADE,
+    stage varchar NOT NULL,
+    kind varchar NOT NULL,
+    name varchar,
+    amount integer NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    CONSTRAINT battle_rewards_kind_check CHECK (kind IN ('xp', 'currency', 'achievement', 'item')),
+    CONSTRAINT battle_rewards_amount_check CHECK (amount > 0)
+);
+
+CREATE INDEX battle_rewards_battle_id_idx ON battle_rewards (battle_id);
+CREATE INDEX battle_rewards_monster_id_idx ON battle_rewards (monster_id);
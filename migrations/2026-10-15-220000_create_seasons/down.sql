@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE battles DROP COLUMN season_id;
+DROP TABLE season_standings;
+DROP TABLE seasons;
@@ -0,0 +1,26 @@
+-- Your SQL goes here
+CREATE TABLE seasons (
+    id varchar PRIMARY KEY,
+    name varchar NOT NULL,
+    started_at TIMESTAMP NOT NULL,
+    ended_at TIMESTAMP,
+    CONSTRAINT seasons_name_check CHECK (btrim(name) <> '')
+);
+
+-- At most one season is open at a time.
+CREATE UNIQUE INDEX seasons_open_idx ON seasons ((ended_at IS NULL)) WHERE ended_at IS NULL;
+
+CREATE TABLE season_standings (
+    season_id varchar NOT NULL REFERENCES seasons(id) ON DELETE CASCADE,
+    monster_id varchar NOT NULL REFERENCES monsters(id) ON DELETE CASCADE,
+    rank integer NOT NULL,
+    points integer NOT NULL,
+    wins integer NOT NULL,
+    losses integer NOT NULL,
+    draws integer NOT NULL,
+    PRIMARY KEY (season_id, monster_id)
+);
+
+ALTER TABLE battles ADD COLUMN season_id varchar REFERENCES seasons(id) ON DELETE SET NULL;
+
+CREATE INDEX battles_season_id_idx ON battles (season_id);
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE monsters
+    ADD COLUMN level integer NOT NULL DEFAULT 1,
+    ADD COLUMN xp integer NOT NULL DEFAULT 0,
+    ADD CONSTRAINT monsters_level_check CHECK (level >= 1),
+    ADD CONSTRAINT monsters_xp_check CHECK (xp >= 0);
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+-- Monsters are named after their species, each species evolving into at most one other.
+CREATE TABLE evolutions (
+    species varchar PRIMARY KEY,
+    evolves_to varchar NOT NULL,
+    min_level integer NOT NULL DEFAULT 1,
+    min_wins integer NOT NULL DEFAULT 0,
+    image_url varchar NOT NULL,
+    attack integer NOT NULL,
+    defense integer NOT NULL,
+    hp integer NOT NULL,
+    speed integer NOT NULL,
+    updated_at TIMESTAMP,
+    CONSTRAINT evolutions_forms_check CHECK (species <> evolves_to AND trim(evolves_to) <> ''),
+    CONSTRAINT evolutions_requirements_check CHECK (min_level >= 1 AND min_wins >= 0),
+    CONSTRAINT evolutions_stats_check CHECK (hp > 0 AND attack >= 0 AND defense >= 0 AND speed >= 0)
+);
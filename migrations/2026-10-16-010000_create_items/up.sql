@@ -0,0 +1,27 @@
+-- Your SQL goes here
+CREATE TABLE items (
+    id varchar PRIMARY KEY,
+    name varchar NOT NULL,
+    attack_bonus integer NOT NULL DEFAULT 0,
+    defense_bonus integer NOT NULL DEFAULT 0,
+    speed_bonus integer NOT NULL DEFAULT 0,
+    consumable boolean NOT NULL DEFAULT false,
+    revive_hp integer NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP,
+    CONSTRAINT items_bonus_check CHECK (attack_bonus >= -100 AND defense_bonus >= -100 AND speed_bonus >= -100),
+    -- Consumables only revive, equipment only grants bonuses.
+    CONSTRAINT items_effect_check CHECK (
+        (consumable AND revive_hp BETWEEN 1 AND 100 AND attack_bonus = 0 AND defense_bonus = 0 AND speed_bonus = 0)
+        OR (NOT consumable AND revive_hp = 0)
+    )
+);
+
+CREATE TABLE monster_items (
+    monster_id varchar NOT NULL REFERENCES monsters(id) ON DELETE CASCADE,
+    slot integer NOT NULL,
+    item_id varchar NOT NULL REFERENCES items(id) ON DELETE CASCADE,
+    PRIMARY KEY (monster_id, slot),
+    CONSTRAINT monster_items_item_unique UNIQUE (monster_id, item_id),
+    CONSTRAINT monster_items_slot_check CHECK (slot BETWEEN 1 AND 3)
+);
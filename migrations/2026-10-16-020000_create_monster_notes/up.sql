@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE monster_notes (
+    id varchar PRIMARY KEY,
+    monster_id varchar NOT NULL REFERENCES monsters(id) ON DELETE CASCADE,
+    -- The API key that wrote the note, the only one to see it.
+    owner varchar NOT NULL REFERENCES api_keys(id) ON DELETE CASCADE,
+    body text NOT NULL,
+    pinned boolean NOT NULL DEFAULT false,
+    battle_ids varchar[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP,
+    CONSTRAINT monster_notes_body_check CHECK (length(body) > 0)
+);
+
+CREATE INDEX monster_notes_owner_idx ON monster_notes (owner, monster_id);
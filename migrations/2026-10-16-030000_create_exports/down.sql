@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE export_rows;
+DROP TABLE exports;
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+CREATE TABLE exports (
+    id varchar PRIMARY KEY,
+    kind varchar NOT NULL,
+    total integer NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    CONSTRAINT exports_kind_check CHECK (kind IN ('monsters', 'battles'))
+);
+
+-- The rows as they were when the export was taken, numbered from 1.
+CREATE TABLE export_rows (
+    export_id varchar NOT NULL REFERENCES exports(id) ON DELETE CASCADE,
+    position integer NOT NULL,
+    data jsonb NOT NULL,
+    PRIMARY KEY (export_id, position)
+);
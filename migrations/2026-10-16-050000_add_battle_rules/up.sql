@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- The rules simulated battles were played with, classic ones having none.
+ALTER TABLE battles ADD COLUMN rules jsonb;
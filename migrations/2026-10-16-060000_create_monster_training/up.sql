@@ -0,0 +1,23 @@
+-- Your SQL goes here
+CREATE TABLE monster_training (
+    monster_id varchar PRIMARY KEY REFERENCES monsters(id) ON DELETE CASCADE,
+    iv_attack integer NOT NULL DEFAULT 0,
+    iv_defense integer NOT NULL DEFAULT 0,
+    iv_hp integer NOT NULL DEFAULT 0,
+    iv_speed integer NOT NULL DEFAULT 0,
+    tp_attack integer NOT NULL DEFAULT 0,
+    tp_defense integer NOT NULL DEFAULT 0,
+    tp_hp integer NOT NULL DEFAULT 0,
+    tp_speed integer NOT NULL DEFAULT 0,
+    -- Points earned from battles not allocated to a stat yet.
+    unspent_points integer NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP NOT NULL,
+    CONSTRAINT monster_training_individual_values_check CHECK (
+        iv_attack BETWEEN 0 AND 31 AND iv_defense BETWEEN 0 AND 31 AND iv_hp BETWEEN 0 AND 31 AND iv_speed BETWEEN 0 AND 31
+    ),
+    CONSTRAINT monster_training_points_check CHECK (
+        tp_attack BETWEEN 0 AND 252 AND tp_defense BETWEEN 0 AND 252 AND tp_hp BETWEEN 0 AND 252 AND tp_speed BETWEEN 0 AND 252
+        AND unspent_points >= 0
+        AND tp_attack + tp_defense + tp_hp + tp_speed + unspent_points <= 510
+    )
+);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE trades;
+ALTER TABLE battles DROP COLUMN trainer_b;
+ALTER TABLE battles DROP COLUMN trainer_a;
+ALTER TABLE monsters DROP COLUMN owner_id;
+DROP TABLE trainers;
@@ -0,0 +1,33 @@
+-- Your SQL goes here
+CREATE TABLE trainers (
+    id varchar PRIMARY KEY,
+    name varchar NOT NULL,
+    -- The API key the trainer authenticates with, issued at registration.
+    api_key_id varchar NOT NULL UNIQUE REFERENCES api_keys(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL,
+    CONSTRAINT trainers_name_key UNIQUE (name),
+    CONSTRAINT trainers_name_not_empty_check CHECK (length(trim(name)) > 0)
+);
+
+-- Monsters created before trainers, or by clients without one, belong to nobody.
+ALTER TABLE monsters ADD COLUMN owner_id varchar REFERENCES trainers(id) ON DELETE SET NULL;
+CREATE INDEX monsters_owner_id_idx ON monsters (owner_id);
+
+-- The trainers owning the monsters when the battle was stored.
+ALTER TABLE battles ADD COLUMN trainer_a varchar REFERENCES trainers(id) ON DELETE SET NULL;
+ALTER TABLE battles ADD COLUMN trainer_b varchar REFERENCES trainers(id) ON DELETE SET NULL;
+
+CREATE TABLE trades (
+    id varchar PRIMARY KEY,
+    proposer varchar NOT NULL REFERENCES trainers(id) ON DELETE CASCADE,
+    recipient varchar NOT NULL REFERENCES trainers(id) ON DELETE CASCADE,
+    offered_monster varchar NOT NULL REFERENCES monsters(id) ON DELETE CASCADE,
+    requested_monster varchar NOT NULL REFERENCES monsters(id) ON DELETE CASCADE,
+    status varchar NOT NULL DEFAULT 'pending',
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP,
+    CONSTRAINT trades_status_check CHECK (status IN ('pending', 'accepted', 'declined', 'cancelled'))
+);
+
+CREATE INDEX trades_proposer_idx ON trades (proposer);
+CREATE INDEX trades_recipient_idx ON trades (recipient);
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+-- Battles the winner won without losing any HP.
+ALTER TABLE battles ADD COLUMN flawless boolean NOT NULL DEFAULT false;
+
+CREATE TABLE achievements (
+    id varchar PRIMARY KEY,
+    kind varchar NOT NULL,
+    -- Achievements of monsters keep the trainer owning the monster when it was earned, the ones of trainers have no monster.
+    monster_id varchar REFERENCES monsters(id) ON DELETE CASCADE,
+    trainer_id varchar REFERENCES trainers(id) ON DELETE CASCADE,
+    -- The battle the achievement was earned in, if any.
+    battle_id varchar REFERENCES battles(id) ON DELETE SET NULL,
+    earned_at TIMESTAMP NOT NULL,
+    CONSTRAINT achievements_kind_check CHECK (kind IN ('first_win', 'win_streak', 'flawless_victory', 'collector')),
+    CONSTRAINT achievements_earner_check CHECK (monster_id IS NOT NULL OR trainer_id IS NOT NULL)
+);
+
+-- Each achievement is earned once, by the monster or else by the trainer.
+CREATE UNIQUE INDEX achievements_monster_kind_key ON achievements (monster_id, kind) WHERE monster_id IS NOT NULL;
+CREATE UNIQUE INDEX achievements_trainer_kind_key ON achievements (trainer_id, kind) WHERE monster_id IS NULL;
+CREATE INDEX achievements_trainer_id_idx ON achievements (trainer_id);
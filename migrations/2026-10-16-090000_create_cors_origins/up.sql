@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- The origins allowed cross-origin access in production, edited through the admin API.
+CREATE TABLE cors_origins (
+    origin varchar PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+-- Nightly snapshots of the Postgres statistics of each table. Scan counters are cumulative, trends are the differences between snapshots.
+CREATE TABLE db_table_stats (
+    taken_at TIMESTAMP NOT NULL,
+    table_name varchar NOT NULL,
+    live_rows bigint NOT NULL,
+    dead_rows bigint NOT NULL,
+    seq_scans bigint NOT NULL,
+    index_scans bigint NOT NULL,
+    total_bytes bigint NOT NULL,
+    last_vacuum TIMESTAMP,
+    last_autovacuum TIMESTAMP,
+    PRIMARY KEY (taken_at, table_name)
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE challenge_attempts;
+DROP TABLE daily_challenges;
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+-- The featured opponent and rule preset of each day, picked on the first request of the day.
+CREATE TABLE daily_challenges (
+    challenge_date DATE PRIMARY KEY,
+    opponent varchar NOT NULL REFERENCES monsters(id) ON DELETE CASCADE,
+    preset varchar NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
+
+-- The daily leaderboard, one row per monster that took on the day's challenge.
+CREATE TABLE challenge_attempts (
+    challenge_date DATE NOT NULL REFERENCES daily_challenges(challenge_date) ON DELETE CASCADE,
+    monster_id varchar NOT NULL REFERENCES monsters(id) ON DELETE CASCADE,
+    attempts integer NOT NULL,
+    wins integer NOT NULL,
+    last_attempt_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (challenge_date, monster_id)
+);
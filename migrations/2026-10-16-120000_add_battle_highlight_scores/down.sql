@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX battles_highlight_score_idx;
+ALTER TABLE battles DROP COLUMN highlight_score;
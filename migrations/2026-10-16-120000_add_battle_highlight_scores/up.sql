@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- How exciting simulated battles were, 0 for the ones played elsewhere, powering the featured replays.
+ALTER TABLE battles ADD COLUMN highlight_score integer NOT NULL DEFAULT 0;
+CREATE INDEX battles_highlight_score_idx ON battles (highlight_score DESC, created_at DESC) WHERE highlight_score > 0;
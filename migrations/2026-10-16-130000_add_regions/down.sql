@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX battles_region_idx;
+DROP INDEX trainers_region_idx;
+ALTER TABLE battles DROP COLUMN region;
+ALTER TABLE trainers DROP COLUMN region;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- The region trainers registered in, and the region of the trainers who fought each battle.
+ALTER TABLE trainers ADD COLUMN region varchar;
+ALTER TABLE battles ADD COLUMN region varchar;
+CREATE INDEX trainers_region_idx ON trainers (region);
+CREATE INDEX battles_region_idx ON battles (region);
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- The version of balance.toml simulated and interactive battles were played with.
+ALTER TABLE battles ADD COLUMN balance_version varchar;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE monster_thumbnails;
+ALTER TABLE monsters DROP COLUMN image_broken;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+ALTER TABLE monsters ADD COLUMN image_broken boolean NOT NULL DEFAULT false;
+
+-- The image each monster's thumbnails were last made from, or failed to be.
+CREATE TABLE monster_thumbnails (
+    monster_id varchar PRIMARY KEY REFERENCES monsters(id) ON DELETE CASCADE,
+    image_url varchar NOT NULL,
+    error text,
+    fetched_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX monsters_lower_name_idx;
+ALTER TABLE monsters DROP COLUMN external_id;
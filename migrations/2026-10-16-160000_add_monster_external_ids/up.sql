@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- The id of the monster in the system it was imported from, matched by imports in upsert mode.
+ALTER TABLE monsters ADD COLUMN external_id varchar UNIQUE;
+
+-- Imports match the monsters without an external id by name, whatever its case.
+CREATE INDEX monsters_lower_name_idx ON monsters (lower(name));
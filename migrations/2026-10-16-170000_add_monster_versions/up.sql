@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- Bumped by every update, which only applies to the version it was read at.
+ALTER TABLE monsters ADD COLUMN version integer NOT NULL DEFAULT 1;
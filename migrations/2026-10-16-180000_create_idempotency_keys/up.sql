@@ -0,0 +1,15 @@
+-- Your SQL goes here
+-- Responses to POSTs sent with an Idempotency-Key, by client. The status is null while the first request runs.
+CREATE TABLE idempotency_keys (
+    client varchar NOT NULL,
+    key varchar NOT NULL,
+    method varchar NOT NULL,
+    path varchar NOT NULL,
+    status integer,
+    content_type varchar,
+    body bytea,
+    created_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (client, key)
+);
+
+CREATE INDEX idempotency_keys_created_at_idx ON idempotency_keys (created_at);
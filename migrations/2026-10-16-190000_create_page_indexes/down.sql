@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX battles_page_idx;
+DROP INDEX monsters_page_idx;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- Keyset pages of monsters and battles, in the order of `monster_repository::page_order`.
+CREATE INDEX monsters_page_idx ON monsters ((coalesce(created_at, '-infinity'::timestamp)), id);
+CREATE INDEX battles_page_idx ON battles ((coalesce(created_at, '-infinity'::timestamp)), id);
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE monsters DROP CONSTRAINT monsters_stats_range_check;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- Left NOT VALID so rows stored before it are reported by `bmctl validate` instead of failing the migration.
+ALTER TABLE monsters
+    ADD CONSTRAINT monsters_stats_range_check
+    CHECK (hp BETWEEN 1 AND 10000 AND attack BETWEEN 0 AND 10000 AND defense BETWEEN 0 AND 10000 AND speed BETWEEN 0 AND 10000)
+    NOT VALID;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE battles DROP COLUMN moves_b;
+ALTER TABLE battles DROP COLUMN moves_a;
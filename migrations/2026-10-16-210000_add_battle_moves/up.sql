@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- The moves each monster used in the battle, empty for manual battles and the ones stored before.
+ALTER TABLE battles ADD COLUMN moves_a text[] NOT NULL DEFAULT '{}';
+ALTER TABLE battles ADD COLUMN moves_b text[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- The SHA-256 of the body of the request that claimed the key, null for the keys claimed before it was kept.
+ALTER TABLE idempotency_keys ADD COLUMN request_hash varchar;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+-- The well-known keys are not brought back.
+SELECT 1;
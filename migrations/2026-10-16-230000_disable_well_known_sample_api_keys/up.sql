@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- The sample keys seeded before they were random are publicly known, they stop authenticating.
+UPDATE api_keys
+SET key_hash = 'unissued:' || id
+WHERE key_hash IN (encode(sha256('bm_sample_player'::bytea), 'hex'), encode(sha256('bm_sample_partner'::bytea), 'hex'));
@@ -0,0 +1,9 @@
+-- Sample monsters to battle with.
+INSERT INTO monsters (id, name, image_url, attack, defense, hp, speed, created_at, updated_at, level, xp) VALUES
+    ('seed-monster-1', 'Dead Unicorn', 'https://loremflickr.com/640/480', 60, 40, 10, 80, now(), now(), 1, 0),
+    ('seed-monster-2', 'Old Shark', 'https://loremflickr.com/640/480', 50, 20, 80, 90, now(), now(), 1, 0),
+    ('seed-monster-3', 'Red Dragon', 'https://loremflickr.com/640/480', 90, 80, 90, 70, now(), now(), 1, 0),
+    ('seed-monster-4', 'Robot Bear', 'https://loremflickr.com/640/480', 50, 40, 80, 60, now(), now(), 1, 0),
+    ('seed-monster-5', 'Angry Snake', 'https://loremflickr.com/640/480', 80, 20, 70, 80, now(), now(), 1, 0),
+    ('seed-monster-6', 'Blue Golem', 'https://loremflickr.com/640/480', 40, 90, 100, 20, now(), now(), 1, 0)
+ON CONFLICT (id) DO NOTHING;
@@ -0,0 +1,5 @@
+-- Sample API keys. No key hashes to these placeholders, each is replaced by the hash of a random key once the seed ran.
+INSERT INTO api_keys (id, name, key_hash, tier, created_at) VALUES
+    ('seed-key-player', 'Sample player', 'unissued:seed-key-player', 'free', now()),
+    ('seed-key-partner', 'Sample partner', 'unissued:seed-key-partner', 'partner', now())
+ON CONFLICT DO NOTHING;
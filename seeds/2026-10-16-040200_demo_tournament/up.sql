@@ -0,0 +1,17 @@
+-- A closed season the sample monsters fought, with the standings closing it would have snapshotted.
+INSERT INTO seasons (id, name, started_at, ended_at) VALUES
+    ('seed-season-1', 'Demo Cup', now() - interval '7 days', now() - interval '1 day')
+ON CONFLICT (id) DO NOTHING;
+
+INSERT INTO battles (id, monster_a, monster_b, winner, created_at, updated_at, outcome, manual, season_id) VALUES
+    ('seed-battle-1', 'seed-monster-3', 'seed-monster-4', 'seed-monster-3', now() - interval '6 days', now() - interval '6 days', 'win', false, 'seed-season-1'),
+    ('seed-battle-2', 'seed-monster-2', 'seed-monster-5', 'seed-monster-2', now() - interval '5 days', now() - interval '5 days', 'win', false, 'seed-season-1'),
+    ('seed-battle-3', 'seed-monster-3', 'seed-monster-2', 'seed-monster-3', now() - interval '3 days', now() - interval '3 days', 'win', false, 'seed-season-1')
+ON CONFLICT (id) DO NOTHING;
+
+INSERT INTO season_standings (season_id, monster_id, rank, points, wins, losses, draws) VALUES
+    ('seed-season-1', 'seed-monster-3', 1, 6, 2, 0, 0),
+    ('seed-season-1', 'seed-monster-2', 2, 3, 1, 1, 0),
+    ('seed-season-1', 'seed-monster-4', 3, 0, 0, 1, 0),
+    ('seed-season-1', 'seed-monster-5', 3, 0, 0, 1, 0)
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,108 @@
+use actix_web::{web, get, HttpResponse};
+use crate::repository::achievement_repository::AchievementRepository;
+use crate::repository::monster_repository::MonsterRepository;
+use crate::repository::trainer_repository::TrainerRepository;
+
+#[get("/monsters/{id}/achievements")]
+pub async fn get_monster_achievements(monster_repository: web::Data<dyn MonsterRepository>, achievement_repository: web::Data<dyn AchievementRepository>, id: web::Path<String>) -> HttpResponse {
+    match monster_repository.get_monster_by_id(&id) {
+        Some(monster) => HttpResponse::Ok().json(achievement_repository.get_monster_achievements(&monster.id)),
+        None => HttpResponse::NotFound().json("Monster not found"),
+    }
+}
+
+// The achievements of the trainer along with the ones its monsters earned while it owned them.
+#[get("/trainers/{id}/achievements")]
+pub async fn get_trainer_achievements(trainer_repository: web::Data<dyn TrainerRepository>, achievement_repository: web::Data<dyn AchievementRepository>, id: web::Path<String>) -> HttpResponse {
+    match trainer_repository.get_trainer_by_id(&id) {
+        Some(trainer) => HttpResponse::Ok().json(achievement_repository.get_trainer_achievements(&trainer.id)),
+        None => HttpResponse::NotFound().json("Trainer not found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use chrono::Utc;
+    use crate::api::config::repositories;
+    use crate::models::achievement::{Achievement, AchievementKind, COLLECTOR_MONSTERS, WIN_STREAK_LENGTH};
+    use crate::models::battle::{Battle, BattleOutcome};
+    use crate::models::monster::{Monster, Stats};
+    use crate::models::rate_limit::ApiKey;
+    use crate::models::trainer::{Trainer, TRAINER_TIER};
+    use crate::repository::battle_repository::BattleRepository;
+    use crate::repository::database::Database;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_earn_achievements_winning_battles_and_collecting_monsters() {
+        let db = Arc::new(Database::new().unwrap());
+        let name = format!("collector-{}", uuid::Uuid::new_v4());
+        let created_at = Utc::now().naive_utc();
+        let api_key = ApiKey { id: String::new(), name: format!("trainer:{}", name), key_hash: uuid::Uuid::new_v4().to_string(), tier: TRAINER_TIER.to_string(), created_at };
+        let (trainer, _) = db.create_trainer(Trainer { id: String::new(), name, api_key_id: String::new(), created_at, region: None }, api_key).unwrap();
+        let new_monster = |index: i64| Monster {
+            id: String::new(),
+            name: format!("monster-{}", index),
+            image_url: "https://loremflickr.com/640/480".to_string(),
+            stats: Stats { attack: 40, defense: 20, hp: 50, speed: 80 },
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: Some(trainer.id.clone()),
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        };
+        let monsters: Vec<Monster> = db.create_monsters((0..COLLECTOR_MONSTERS).map(new_monster).collect()).unwrap().into_iter().map(Result::unwrap).collect();
+        let (winner, loser) = (&monsters[0], &monsters[1]);
+        for battle in 0..WIN_STREAK_LENGTH {
+            db.create_battle(Battle {
+                id: String::new(),
+                monster_a: winner.id.clone(),
+                monster_b: loser.id.clone(),
+                winner: Some(winner.id.clone()),
+                created_at: None,
+                updated_at: None,
+                outcome: BattleOutcome::Win,
+                manual: false,
+                season_id: None,
+                rules: None,
+                trainer_a: None,
+                trainer_b: None,
+                flawless: battle == 0,
+                highlight_score: 0,
+                region: None,
+                balance_version: None,
+                moves_a: Vec::new(),
+                moves_b: Vec::new(),
+            }).unwrap();
+        }
+        let app = App::new().configure(repositories(db.clone())).service(get_monster_achievements).service(get_trainer_achievements);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri(&format!("/monsters/{}/achievements", winner.id)).to_request();
+        let achievements: Vec<Achievement> = test::call_and_read_body_json(&app, req).await;
+        let kinds: Vec<AchievementKind> = achievements.iter().map(|achievement| achievement.kind).collect();
+        assert_eq!(kinds.len(), 3);
+        assert!([AchievementKind::FirstWin, AchievementKind::FlawlessVictory, AchievementKind::WinStreak].iter().all(|kind| kinds.contains(kind)));
+        assert!(achievements.iter().all(|achievement| achievement.trainer_id.as_ref() == Some(&trainer.id)));
+
+        let req = test::TestRequest::get().uri(&format!("/monsters/{}/achievements", loser.id)).to_request();
+        let achievements: Vec<Achievement> = test::call_and_read_body_json(&app, req).await;
+        assert!(achievements.is_empty());
+
+        let req = test::TestRequest::get().uri(&format!("/trainers/{}/achievements", trainer.id)).to_request();
+        let achievements: Vec<Achievement> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(achievements.len(), 4);
+        assert!(achievements.iter().any(|achievement| achievement.kind == AchievementKind::Collector && achievement.monster_id.is_none()));
+
+        let req = test::TestRequest::get().uri("/trainers/123/achievements").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+    }
+}
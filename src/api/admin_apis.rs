@@ -0,0 +1,168 @@
+use actix_web::{web, post, HttpResponse};
+use serde::Deserialize;
+use crate::config::AppConfig;
+use crate::models::monster::{ImportMode, ImportOutcome, Monster, Stats};
+use crate::repository::backup_repository::BackupRepository;
+use crate::repository::monster_repository::MonsterRepository;
+use super::error::repository_error_response;
+
+const STARTER_IMAGE_URL: &str = "https://loremflickr.com/640/480";
+// Name, attack, defense, hp and speed of the starter monsters, balanced for no one to win every battle.
+const STARTER_MONSTERS: &[(&str, i32, i32, i32, i32)] = &[
+    ("Ember Fox", 70, 40, 80, 85),
+    ("Tide Turtle", 45, 85, 110, 30),
+    ("Thorn Stag", 65, 60, 90, 60),
+    ("Volt Hare", 60, 35, 70, 95),
+    ("Stone Golem", 55, 90, 120, 20),
+    ("Frost Owl", 75, 45, 75, 80),
+    ("Shadow Lynx", 85, 40, 70, 90),
+    ("Iron Boar", 80, 70, 100, 40),
+];
+
+/*
+Whether the data reset and seed endpoints are served, for demo environments to start over. They are off unless
+ENABLE_ADMIN_ENDPOINTS is `true`, and answer 404 then, the admin token alone not being enough to wipe the data.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AdminEndpoints {
+    pub enabled: bool,
+}
+
+impl AdminEndpoints {
+    pub fn from_config(config: &AppConfig) -> Result<Self, String> {
+        AdminEndpoints::from_vars(|name| config.var(name))
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        match var("ENABLE_ADMIN_ENDPOINTS").as_deref().map(str::trim) {
+            None | Some("") | Some("false") => Ok(AdminEndpoints { enabled: false }),
+            Some("true") => Ok(AdminEndpoints { enabled: true }),
+            Some(other) => Err(format!("ENABLE_ADMIN_ENDPOINTS must be true or false, got {:?}", other)),
+        }
+    }
+
+    fn enabled(admin_endpoints: Option<web::Data<AdminEndpoints>>) -> bool {
+        admin_endpoints.is_some_and(|admin_endpoints| admin_endpoints.enabled)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedPreset {
+    #[default]
+    Starter,
+}
+
+impl SeedPreset {
+    // Monsters with an external id, so seeding again skips the ones already there.
+    fn monsters(&self) -> Vec<Monster> {
+        match self {
+            SeedPreset::Starter => STARTER_MONSTERS
+                .iter()
+                .enumerate()
+                .map(|(index, &(name, attack, defense, hp, speed))| Monster {
+                    id: String::new(),
+                    name: name.to_string(),
+                    image_url: STARTER_IMAGE_URL.to_string(),
+                    stats: Stats { attack, defense, hp, speed },
+                    created_at: None,
+                    updated_at: None,
+                    last_battle_at: None,
+                    level: 1,
+                    xp: 0,
+                    owner_id: None,
+                    image_broken: false,
+                    external_id: Some(format!("starter-{}", index + 1)),
+                    version: 1,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SeedQuery {
+    preset: Option<SeedPreset>,
+}
+
+fn disabled() -> HttpResponse {
+    HttpResponse::NotFound().json("Admin endpoints are disabled")
+}
+
+// Deletes every monster and battle in one transaction, along with everything belonging to them.
+#[post("/admin/reset")]
+pub async fn reset_data(backup_repository: web::Data<dyn BackupRepository>, admin_endpoints: Option<web::Data<AdminEndpoints>>) -> HttpResponse {
+    if !AdminEndpoints::enabled(admin_endpoints) {
+        return disabled();
+    }
+    match backup_repository.reset() {
+        Ok((monsters, battles)) => {
+            tracing::warn!(monsters, battles, "Reset the data");
+            HttpResponse::Ok().json(serde_json::json!({ "monsters": monsters, "battles": battles }))
+        }
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+// Loads the monsters of the preset, `starter` by default, skipping the ones seeded before.
+#[post("/admin/seed")]
+pub async fn seed_data(monster_repository: web::Data<dyn MonsterRepository>, admin_endpoints: Option<web::Data<AdminEndpoints>>, query: web::Query<SeedQuery>) -> HttpResponse {
+    if !AdminEndpoints::enabled(admin_endpoints) {
+        return disabled();
+    }
+    let outcomes = match monster_repository.import_monsters(query.preset.unwrap_or_default().monsters(), ImportMode::SkipDuplicates) {
+        Ok(outcomes) => outcomes,
+        Err(err) => return repository_error_response(&err),
+    };
+    let mut created = Vec::new();
+    let mut skipped = 0;
+    for outcome in outcomes {
+        match outcome {
+            Ok(ImportOutcome::Created(monster)) => created.push(monster),
+            Ok(ImportOutcome::Updated(_)) | Ok(ImportOutcome::Skipped) => skipped += 1,
+            Err(err) => return repository_error_response(&err),
+        }
+    }
+    HttpResponse::Ok().json(serde_json::json!({ "created": created, "skipped": skipped }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use crate::api::config::repositories;
+    use crate::repository::memory_repository::InMemoryRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_seed_and_reset_the_data_only_when_enabled() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let app = |enabled: bool| {
+            App::new()
+                .app_data(web::Data::new(AdminEndpoints { enabled }))
+                .configure(repositories(repository.clone()))
+                .service(reset_data)
+                .service(seed_data)
+        };
+        let disabled_app = test::init_service(app(false)).await;
+        let req = test::TestRequest::post().uri("/admin/seed?preset=starter").to_request();
+        assert_eq!(test::call_service(&disabled_app, req).await.status(), http::StatusCode::NOT_FOUND);
+        assert!(repository.get_monsters().is_empty());
+
+        let app = test::init_service(app(true)).await;
+        let seed = || test::TestRequest::post().uri("/admin/seed?preset=starter").to_request();
+        let seeded: serde_json::Value = test::call_and_read_body_json(&app, seed()).await;
+        assert_eq!(seeded["created"].as_array().unwrap().len(), STARTER_MONSTERS.len());
+        let seeded_again: serde_json::Value = test::call_and_read_body_json(&app, seed()).await;
+        assert_eq!((seeded_again["created"].as_array().unwrap().len(), seeded_again["skipped"].as_u64()), (0, Some(STARTER_MONSTERS.len() as u64)));
+        let req = test::TestRequest::post().uri("/admin/seed?preset=legendary").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::post().uri("/admin/reset").to_request();
+        let reset: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(reset["monsters"].as_u64(), Some(STARTER_MONSTERS.len() as u64));
+        assert!(repository.get_monsters().is_empty());
+        assert!(AdminEndpoints::from_vars(|name| (name == "ENABLE_ADMIN_ENDPOINTS").then(|| "yes".to_string())).is_err());
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+use actix_web::{web, get, HttpResponse};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use crate::models::analytics::{ElementUsage, MetaSnapshot, MonsterMover, MonsterUsage, MoveUsage};
+use crate::models::battle::Battle;
+use crate::models::moves::Move;
+use crate::repository::battle_repository::BattleRepository;
+use crate::repository::monster_repository::MonsterRepository;
+use crate::repository::move_repository::MoveRepository;
+use crate::repository::request_scope::RequestScope;
+
+pub const DEFAULT_META_WINDOW_DAYS: i64 = 7;
+pub const MAX_META_WINDOW_DAYS: i64 = 365;
+// Snapshots kept at most, the expired ones and then the oldest making room for new ones.
+const MAX_META_SNAPSHOTS: usize = 64;
+const MOST_USED_LIMIT: usize = 10;
+const BIGGEST_MOVERS_LIMIT: usize = 5;
+
+// The database schema of the request, the window size and the manual filter a snapshot was built for.
+type MetaKey = (Option<String>, i64, Option<bool>);
+
+/*
+Snapshots are kept per database schema, window size and manual filter, and considered fresh for `ttl`.
+The scheduled refresh job keeps the default window of each schema warm, other windows are built on demand.
+*/
+pub struct MetaCache {
+    ttl: Duration,
+    snapshots: RwLock<HashMap<MetaKey, MetaSnapshot>>,
+}
+
+impl MetaCache {
+    pub fn new(ttl: Duration) -> Self {
+        MetaCache { ttl, snapshots: RwLock::new(HashMap::new()) }
+    }
+
+    pub fn get(&self, days: i64, manual: Option<bool>) -> Option<MetaSnapshot> {
+        let snapshots = self.snapshots.read().expect("Meta cache lock poisoned");
+        snapshots
+            .get(&(RequestScope::current().schema, days, manual))
+            .filter(|snapshot| Utc::now().naive_utc() - snapshot.generated_at < self.ttl)
+            .cloned()
+    }
+
+    pub fn set(&self, snapshot: MetaSnapshot) {
+        let mut snapshots = self.snapshots.write().expect("Meta cache lock poisoned");
+        let key = (RequestScope::current().schema, snapshot.days, snapshot.manual);
+        if !snapshots.contains_key(&key) && snapshots.len() >= MAX_META_SNAPSHOTS {
+            let now = Utc::now().naive_utc();
+            snapshots.retain(|_, cached| now - cached.generated_at < self.ttl);
+            if snapshots.len() >= MAX_META_SNAPSHOTS {
+                let oldest = snapshots.iter().min_by_key(|(_, cached)| cached.generated_at).map(|(oldest, _)| oldest.clone());
+                snapshots.remove(&oldest.expect("The meta cache is full"));
+            }
+        }
+        snapshots.insert(key, snapshot);
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MetaQuery {
+    days: Option<i64>,
+    // Only counts manual (true) or simulated (false) battles, both when omitted.
+    manual: Option<bool>,
+}
+
+/*
+The most used monsters, moves and elements of the last `days`, with their win rates and how their usage moved
+against the prior period. Moves and elements count the battles their monsters used them in, manual battles and
+the ones stored before battles recorded their moves having none. There is no ranked flag, `manual` being the only
+filter of the battles. `days` goes up to a year.
+*/
+#[get("/analytics/meta")]
+pub async fn get_meta(
+    battle_repository: web::Data<dyn BattleRepository>,
+    monster_repository: web::Data<dyn MonsterRepository>,
+    move_repository: web::Data<dyn MoveRepository>,
+    cache: web::Data<MetaCache>,
+    query: web::Query<MetaQuery>,
+) -> HttpResponse {
+    let days = query.days.unwrap_or(DEFAULT_META_WINDOW_DAYS);
+    if !(1..=MAX_META_WINDOW_DAYS).contains(&days) {
+        return HttpResponse::BadRequest().json(format!("Days must be between 1 and {}", MAX_META_WINDOW_DAYS));
+    }
+
+    if let Some(snapshot) = cache.get(days, query.manual) {
+        return HttpResponse::Ok().json(snapshot);
+    }
+
+    let snapshot = build_meta_snapshot(battle_repository.as_ref(), monster_repository.as_ref(), move_repository.as_ref(), days, query.manual);
+    cache.set(snapshot.clone());
+    HttpResponse::Ok().json(snapshot)
+}
+
+pub fn build_meta_snapshot(battle_repository: &dyn BattleRepository, monster_repository: &dyn MonsterRepository, move_repository: &dyn MoveRepository, days: i64, manual: Option<bool>) -> MetaSnapshot {
+    let now = Utc::now().naive_utc();
+    let window = Duration::days(days);
+    let battles_between = |from, to| -> Vec<Battle> {
+        battle_repository.get_battles_created_between(from, to)
+            .into_iter()
+            .filter(|battle| manual.is_none_or(|manual| battle.manual == manual))
+            .collect()
+    };
+    let (current_battles, prior_battles) = (battles_between(now - window, now), battles_between(now - window - window, now - window));
+    let (current, prior) = (usage_by_monster(&current_battles), usage_by_monster(&prior_battles));
+    let total_battles = current.values().map(|(battles, _)| battles).sum::<i64>() / 2;
+    let prior_total_battles = prior.values().map(|(battles, _)| battles).sum::<i64>() / 2;
+
+    let mut monster_ids: Vec<String> = current.keys().chain(prior.keys()).cloned().collect();
+    monster_ids.sort();
+    monster_ids.dedup();
+    let names: HashMap<String, String> = monster_repository.get_monsters_by_ids(&monster_ids)
+        .into_iter()
+        .map(|monster| (monster.id, monster.name))
+        .collect();
+    let name_of = |monster_id: &str| names.get(monster_id).cloned().unwrap_or_default();
+
+    let mut most_used: Vec<MonsterUsage> = current
+        .iter()
+        .map(|(monster_id, &(battles, wins))| MonsterUsage {
+            monster_id: monster_id.clone(),
+            name: name_of(monster_id),
+            battles,
+            wins,
+            usage_rate: rate(battles, total_battles),
+            win_rate: rate(wins, battles),
+        })
+        .collect();
+    most_used.sort_by(|a, b| b.battles.cmp(&a.battles).then(b.win_rate.total_cmp(&a.win_rate)));
+    most_used.truncate(MOST_USED_LIMIT);
+
+    let mut biggest_movers: Vec<MonsterMover> = current
+        .keys()
+        .chain(prior.keys().filter(|monster_id| !current.contains_key(*monster_id)))
+        .map(|monster_id| {
+            let (battles, wins) = current.get(monster_id).copied().unwrap_or((0, 0));
+            let (prior_battles, prior_wins) = prior.get(monster_id).copied().unwrap_or((0, 0));
+            MonsterMover {
+                monster_id: monster_id.clone(),
+                name: name_of(monster_id),
+                usage_rate_delta: rate(battles, total_battles) - rate(prior_battles, prior_total_battles),
+                win_rate_delta: rate(wins, battles) - rate(prior_wins, prior_battles),
+            }
+        })
+        .collect();
+    biggest_movers.sort_by(|a, b| b.usage_rate_delta.abs().total_cmp(&a.usage_rate_delta.abs()));
+    biggest_movers.truncate(BIGGEST_MOVERS_LIMIT);
+
+    let moves: HashMap<String, Move> = move_repository.get_moves().into_iter().map(|known_move| (known_move.id.clone(), known_move)).collect();
+    let move_ids = |used_moves: &[String]| used_moves.to_vec();
+    let elements = |used_moves: &[String]| used_moves.iter().filter_map(|move_id| moves.get(move_id).and_then(|used_move| used_move.element.clone())).collect();
+
+    let usage_rate_delta = |label: &str, battles: i64, prior: &HashMap<String, (i64, i64)>| {
+        rate(battles, total_battles) - rate(prior.get(label).map_or(0, |(battles, _)| *battles), prior_total_battles)
+    };
+    let (current_moves, prior_moves) = (usage_by_label(&current_battles, move_ids), usage_by_label(&prior_battles, move_ids));
+    let mut most_used_moves: Vec<MoveUsage> = current_moves
+        .iter()
+        .map(|(move_id, &(battles, wins))| MoveUsage {
+            move_id: move_id.clone(),
+            name: moves.get(move_id).map(|known_move| known_move.name.clone()).unwrap_or_default(),
+            element: moves.get(move_id).and_then(|known_move| known_move.element.clone()),
+            battles,
+            wins,
+            usage_rate: rate(battles, total_battles),
+            win_rate: rate(wins, battles),
+            usage_rate_delta: usage_rate_delta(move_id, battles, &prior_moves),
+        })
+        .collect();
+    most_used_moves.sort_by(|a, b| b.battles.cmp(&a.battles).then(b.win_rate.total_cmp(&a.win_rate)));
+    most_used_moves.truncate(MOST_USED_LIMIT);
+
+    let (current_elements, prior_elements) = (usage_by_label(&current_battles, elements), usage_by_label(&prior_battles, elements));
+    let mut most_used_elements: Vec<ElementUsage> = current_elements
+        .iter()
+        .map(|(element, &(battles, wins))| ElementUsage {
+            element: element.clone(),
+            battles,
+            wins,
+            usage_rate: rate(battles, total_battles),
+            win_rate: rate(wins, battles),
+            usage_rate_delta: usage_rate_delta(element, battles, &prior_elements),
+        })
+        .collect();
+    most_used_elements.sort_by(|a, b| b.battles.cmp(&a.battles).then(b.win_rate.total_cmp(&a.win_rate)));
+    most_used_elements.truncate(MOST_USED_LIMIT);
+
+    MetaSnapshot {
+        days,
+        manual,
+        total_battles,
+        most_used,
+        biggest_movers,
+        most_used_moves,
+        most_used_elements,
+        generated_at: now,
+    }
+}
+
+pub fn refresh_meta_snapshot(battle_repository: &dyn BattleRepository, monster_repository: &dyn MonsterRepository, move_repository: &dyn MoveRepository, cache: &MetaCache) {
+    cache.set(build_meta_snapshot(battle_repository, monster_repository, move_repository, DEFAULT_META_WINDOW_DAYS, None));
+}
+
+// Maps each monster id to its (battles, wins) count, draws count as battles without a win.
+fn usage_by_monster(battles: &[Battle]) -> HashMap<String, (i64, i64)> {
+    let mut usage: HashMap<String, (i64, i64)> = HashMap::new();
+    for battle in battles {
+        for monster_id in [&battle.monster_a, &battle.monster_b] {
+            let entry = usage.entry(monster_id.clone()).or_insert((0, 0));
+            entry.0 += 1;
+            if battle.winner.as_ref() == Some(monster_id) {
+                entry.1 += 1;
+            }
+        }
+    }
+    usage
+}
+
+/*
+Maps each label of the moves the monsters used, like their ids or elements, to its (battles, wins) count. A label
+counts once per monster and battle, however many of the moves it used share it.
+*/
+fn usage_by_label(battles: &[Battle], labels: impl Fn(&[String]) -> Vec<String>) -> HashMap<String, (i64, i64)> {
+    let mut usage: HashMap<String, (i64, i64)> = HashMap::new();
+    for battle in battles {
+        for (monster_id, used_moves) in [(&battle.monster_a, &battle.moves_a), (&battle.monster_b, &battle.moves_b)] {
+            let mut monster_labels = labels(used_moves);
+            monster_labels.sort();
+            monster_labels.dedup();
+            for label in monster_labels {
+                let entry = usage.entry(label).or_insert((0, 0));
+                entry.0 += 1;
+                if battle.winner.as_ref() == Some(monster_id) {
+                    entry.1 += 1;
+                }
+            }
+        }
+    }
+    usage
+}
+
+fn rate(count: i64, total: i64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, http, App};
+    use actix_web::web::Data;
+    use std::sync::Arc;
+    use crate::api::config::repositories;
+    use crate::models::battle::BattleOutcome;
+    use crate::repository::database::Database;
+    use crate::utils::test_utils::init_test_battle;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_get_meta_snapshot_correctly() {
+        let db = Database::new().unwrap();
+        let _test_battle = init_test_battle(&db).await;
+        let app = App::new()
+            .configure(repositories(Arc::new(db)))
+            .app_data(Data::new(MetaCache::new(Duration::minutes(5))))
+            .service(get_meta);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/analytics/meta?days=1").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let snapshot: MetaSnapshot = test::read_body_json(resp).await;
+        assert_eq!(snapshot.days, 1);
+        assert!(snapshot.total_battles >= 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_get_a_bad_request_response_if_days_is_out_of_range() {
+        let db = Database::new().unwrap();
+        let app = App::new()
+            .configure(repositories(Arc::new(db)))
+            .app_data(Data::new(MetaCache::new(Duration::minutes(5))))
+            .service(get_meta);
+
+        let app = test::init_service(app).await;
+
+        for days in ["0", "366", "9223372036854775807"] {
+            let req = test::TestRequest::get().uri(&format!("/analytics/meta?days={}", days)).to_request();
+            let resp = test::call_service(&app, req).await;
+
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_keep_a_bounded_number_of_snapshots() {
+        let cache = MetaCache::new(Duration::minutes(5));
+        let snapshot = |days: i64| MetaSnapshot {
+            days,
+            manual: None,
+            total_battles: 0,
+            most_used: Vec::new(),
+            biggest_movers: Vec::new(),
+            most_used_moves: Vec::new(),
+            most_used_elements: Vec::new(),
+            generated_at: Utc::now().naive_utc() + Duration::seconds(days),
+        };
+
+        for days in 1..=MAX_META_SNAPSHOTS as i64 + 1 {
+            cache.set(snapshot(days));
+        }
+
+        assert_eq!(cache.snapshots.read().unwrap().len(), MAX_META_SNAPSHOTS);
+        assert!(cache.get(1, None).is_none());
+        assert!(cache.get(MAX_META_SNAPSHOTS as i64 + 1, None).is_some());
+        // Each schema has its own snapshots.
+        let scope = RequestScope { schema: Some("scope_test".to_string()), actor: None };
+        assert!(scope.clone().sync_scope(|| cache.get(MAX_META_SNAPSHOTS as i64 + 1, None)).is_none());
+        scope.clone().sync_scope(|| cache.set(snapshot(MAX_META_SNAPSHOTS as i64 + 2)));
+        assert!(scope.sync_scope(|| cache.get(MAX_META_SNAPSHOTS as i64 + 2, None)).is_some());
+        assert!(cache.get(MAX_META_SNAPSHOTS as i64 + 2, None).is_none());
+    }
+
+    fn new_battle() -> Battle {
+        Battle {
+            id: "battle".to_string(),
+            monster_a: "a".to_string(),
+            monster_b: "b".to_string(),
+            winner: Some("a".to_string()),
+            created_at: None,
+            updated_at: None,
+            outcome: BattleOutcome::Win,
+            manual: false,
+            season_id: None,
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_count_usage_and_wins_per_monster() {
+        let battle = new_battle();
+        let draw = Battle { winner: None, outcome: BattleOutcome::Draw, ..battle.clone() };
+
+        let usage = usage_by_monster(&[battle.clone(), battle, draw]);
+
+        assert_eq!(usage.get("a"), Some(&(3, 2)));
+        assert_eq!(usage.get("b"), Some(&(3, 0)));
+    }
+
+    #[actix_rt::test]
+    async fn test_should_count_usage_and_wins_per_label_of_the_moves_used() {
+        let battle = Battle {
+            moves_a: vec!["ember".to_string(), "flame".to_string()],
+            moves_b: vec!["ember".to_string(), "splash".to_string()],
+            ..new_battle()
+        };
+        let without_moves = new_battle();
+        let elements = |used_moves: &[String]| used_moves.iter().map(|move_id| if move_id == "splash" { "water" } else { "fire" }.to_string()).collect();
+
+        let moves = usage_by_label(&[battle.clone(), battle.clone(), without_moves.clone()], |used_moves| used_moves.to_vec());
+        let elements = usage_by_label(&[battle.clone(), battle, without_moves], elements);
+
+        assert_eq!(moves.get("ember"), Some(&(4, 2)));
+        assert_eq!(moves.get("splash"), Some(&(2, 0)));
+        assert_eq!(elements.get("fire"), Some(&(4, 2)));
+        assert_eq!(elements.get("water"), Some(&(2, 0)));
+    }
+}
@@ -0,0 +1,83 @@
+use actix_web::{web, get, HttpResponse};
+use serde::Deserialize;
+use crate::repository::audit_repository;
+use crate::repository::database::Database;
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    entity: Option<String>,
+    id: Option<String>,
+}
+
+#[get("/audit")]
+pub async fn get_audit_entries(db: web::Data<Database>, query: web::Query<AuditQuery>) -> HttpResponse {
+    let entity = match &query.entity {
+        Some(entity) => entity,
+        None => return HttpResponse::BadRequest().json("Entity is required"),
+    };
+
+    let entries = audit_repository::get_audit_entries(&db, entity, query.id.as_deref());
+    HttpResponse::Ok().json(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, http, App};
+    use actix_web::web::Data;
+    use crate::api::auth;
+    use crate::api::config::config;
+    use crate::config::AppConfig;
+    use crate::models::audit::AuditEntry;
+    use crate::models::monster::{Monster, Stats};
+    use crate::repository::monster_repository::MonsterRepository;
+    use crate::utils::test_utils::init_test_monsters;
+
+    use super::*;
+
+    const TEST_ADMIN_TOKEN: &str = "test-admin-token";
+
+    #[actix_rt::test]
+    async fn test_should_get_the_audit_entries_of_a_monster() {
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
+        let monster = audit_repository::with_actor("tester".to_string(), async {
+            db.create_monster(test_monsters[0].clone()).unwrap()
+        }).await;
+        db.update_monster_by_id(&monster.id, Monster { stats: Stats { attack: monster.stats.attack + 1, ..monster.stats }, ..monster.clone() }).unwrap();
+
+        let app = App::new()
+            .app_data(Data::new(db))
+            .app_data(Data::new(AppConfig::env().with_var("ADMIN_TOKEN", TEST_ADMIN_TOKEN)))
+            .configure(config);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/audit?entity=monster&id={}", monster.id))
+            .insert_header((auth::ADMIN_TOKEN_HEADER, TEST_ADMIN_TOKEN))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let entries: Vec<AuditEntry> = test::read_body_json(resp).await;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].action, "update");
+        assert_eq!(entries[0].actor, None);
+        assert_eq!(entries[0].diff["attack"]["to"], monster.stats.attack + 1);
+        assert_eq!(entries[1].action, "create");
+        assert_eq!(entries[1].actor.as_deref(), Some("tester"));
+    }
+
+    #[actix_rt::test]
+    async fn test_should_get_403_error_without_the_admin_token() {
+        let db = Database::new().unwrap();
+        let app = App::new().app_data(Data::new(db)).configure(config);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/api/audit?entity=monster").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), http::StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,115 @@
+use actix_web::{web, HttpRequest};
+use sha2::{Digest, Sha256};
+use crate::config::AppConfig;
+use crate::models::rate_limit::{hash_api_key, ApiKey, ADMIN_TIER};
+use crate::models::trainer::Trainer;
+use crate::rate_limit::API_KEY_HEADER;
+use crate::repository::audit_repository::ACTOR_HEADER;
+use crate::repository::rate_limit_repository::RateLimitRepository;
+use crate::repository::trainer_repository::TrainerRepository;
+
+pub const ADMIN_TOKEN_HEADER: &str = "X-Admin-Token";
+
+/*
+Admin endpoints are only reachable when ADMIN_TOKEN is set and sent back in the X-Admin-Token header. The token
+is read from the `AppConfig` of the app, or from the environment when it has none.
+*/
+pub fn is_admin(req: &HttpRequest) -> bool {
+    let admin_token = match req.app_data::<web::Data<AppConfig>>() {
+        Some(config) => config.var("ADMIN_TOKEN"),
+        None => std::env::var("ADMIN_TOKEN").ok(),
+    };
+    let admin_token = match admin_token {
+        Some(admin_token) if !admin_token.is_empty() => admin_token,
+        _ => return false,
+    };
+    req.headers()
+        .get(ADMIN_TOKEN_HEADER)
+        .is_some_and(|token| constant_time_eq(token.as_bytes(), admin_token.as_bytes()))
+}
+
+// Compares the SHA-256 of both values byte by byte without stopping early, so timings leak neither the token nor its length.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let (a, b) = (Sha256::digest(a), Sha256::digest(b));
+    a.iter().zip(b.iter()).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+}
+
+/*
+The API key sent in the X-Api-Key header, which owns the private data of its clients like notes. None when
+no key is sent or it is unknown.
+*/
+pub fn api_key(req: &HttpRequest, rate_limit_repository: &dyn RateLimitRepository) -> Option<ApiKey> {
+    let key = req.headers().get(API_KEY_HEADER)?.to_str().ok()?;
+    rate_limit_repository.get_api_key_by_hash(&hash_api_key(key))
+}
+
+/*
+Who sent the request, for the data kept per client like idempotency keys: the admin, the API key sent in
+X-Api-Key, or else the peer address, named the way `RateLimiter` names them.
+*/
+pub fn client(req: &HttpRequest, rate_limit_repository: &dyn RateLimitRepository) -> String {
+    if is_admin(req) {
+        return ADMIN_TIER.to_string();
+    }
+    match api_key(req, rate_limit_repository) {
+        Some(api_key) => format!("key:{}", api_key.id),
+        None => format!("peer:{}", req.peer_addr().map(|address| address.ip().to_string()).unwrap_or_default()),
+    }
+}
+
+// The trainer the API key was issued to, None for keys of other clients.
+pub fn trainer(req: &HttpRequest, rate_limit_repository: &dyn RateLimitRepository, trainer_repository: &dyn TrainerRepository) -> Option<Trainer> {
+    let api_key = api_key(req, rate_limit_repository)?;
+    trainer_repository.get_trainer_by_api_key(&api_key.id)
+}
+
+/*
+Who the audit log records for the writes of the request, from its credentials: the admin, the trainer the API
+key was issued to or the key itself. X-Actor names the person behind them and is kept next to the identity,
+requests without credentials recording no actor so none can be forged.
+*/
+pub fn actor(req: &HttpRequest, rate_limit_repository: &dyn RateLimitRepository, trainer_repository: &dyn TrainerRepository) -> Option<String> {
+    let identity = if is_admin(req) {
+        ADMIN_TIER.to_string()
+    } else {
+        let api_key = api_key(req, rate_limit_repository)?;
+        match trainer_repository.get_trainer_by_api_key(&api_key.id) {
+            Some(trainer) => format!("trainer:{}", trainer.id),
+            None => format!("key:{}", api_key.id),
+        }
+    };
+    let name = req.headers()
+        .get(ACTOR_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|name| !name.is_empty());
+    Some(match name {
+        Some(name) => format!("{} ({})", identity, name),
+        None => identity,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use crate::repository::memory_repository::InMemoryRepository;
+
+    use super::*;
+
+    #[test]
+    fn test_should_take_the_audit_actor_from_the_credentials_of_the_request() {
+        let repository = InMemoryRepository::new();
+        let api_key = repository
+            .create_api_key(ApiKey { id: String::new(), name: "CI".to_string(), key_hash: hash_api_key("bm_test"), tier: "free".to_string(), created_at: chrono::Utc::now().naive_utc() })
+            .unwrap();
+        let config = web::Data::new(AppConfig::env().with_var("ADMIN_TOKEN", "test-admin-token"));
+        let actor = |req: TestRequest| actor(&req.app_data(config.clone()).to_http_request(), &repository, &repository);
+
+        assert_eq!(actor(TestRequest::default().insert_header((ACTOR_HEADER, "alice"))), None);
+        assert_eq!(actor(TestRequest::default().insert_header((API_KEY_HEADER, "bm_test"))), Some(format!("key:{}", api_key.id)));
+        let admin = TestRequest::default().insert_header((ADMIN_TOKEN_HEADER, "test-admin-token")).insert_header((ACTOR_HEADER, "alice"));
+        assert_eq!(actor(admin), Some("admin (alice)".to_string()));
+        let wrong_token = TestRequest::default().insert_header((ADMIN_TOKEN_HEADER, "test-admin-tokem"));
+        assert!(!is_admin(&wrong_token.app_data(config.clone()).to_http_request()));
+    }
+}
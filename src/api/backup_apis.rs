@@ -0,0 +1,171 @@
+use std::io::Write;
+use actix_web::{web, get, post, http::header, HttpResponse, Error};
+use futures::TryStreamExt;
+use serde::Deserialize;
+use tempfile::NamedTempFile;
+use crate::backup;
+use crate::models::backup::RestoreMode;
+use crate::repository::backup_repository::BackupRepository;
+use super::error::repository_error_response;
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupFormat {
+    #[default]
+    Json,
+    // Gzipped NDJSON.
+    Ndjson,
+}
+
+#[derive(Deserialize)]
+pub struct BackupQuery {
+    format: Option<BackupFormat>,
+}
+
+#[derive(Deserialize)]
+pub struct RestoreQuery {
+    mode: Option<RestoreMode>,
+}
+
+// Every monster and battle as one JSON document, or as gzipped NDJSON with `?format=ndjson`, to restore with `POST /admin/restore`.
+#[get("/admin/backup")]
+pub async fn get_backup(backup_repository: web::Data<dyn BackupRepository>, query: web::Query<BackupQuery>) -> Result<HttpResponse, Error> {
+    let backup = match backup_repository.get_backup() {
+        Ok(backup) => backup,
+        Err(err) => return Ok(repository_error_response(&err)),
+    };
+    let file_name = format!("backup-{}", backup.created_at.format("%Y%m%dT%H%M%S"));
+    Ok(match query.format.unwrap_or_default() {
+        BackupFormat::Json => HttpResponse::Ok()
+            .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.json\"", file_name)))
+            .json(backup),
+        BackupFormat::Ndjson => {
+            let body = web::block(move || backup::to_ndjson_gz(&backup)).await?;
+            HttpResponse::Ok()
+                .content_type("application/gzip")
+                .insert_header(header::ContentEncoding::Identity)
+                .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.ndjson.gz\"", file_name)))
+                .body(body)
+        }
+    })
+}
+
+/*
+Restores a backup of either format, spooled to disk as it streams in. With `?mode=replace` every monster and battle
+is deleted first, otherwise the ones of the backup are merged in by id. Backups with battles between monsters they
+don't hold are rejected before anything changes.
+
+Backups only hold monsters and battles, so replacing answers 409 RESTORE_DELETES_DEPENDENTS while moves, items,
+notes, training, trades, rewards, achievements or thumbnails belong to them, which deleting them would lose.
+`?mode=force_replace` deletes those along with them.
+*/
+#[post("/admin/restore")]
+pub async fn restore_backup(backup_repository: web::Data<dyn BackupRepository>, query: web::Query<RestoreQuery>, mut payload: web::Payload) -> Result<HttpResponse, Error> {
+    let mut temp_file = NamedTempFile::new()?;
+    while let Some(chunk) = payload.try_next().await? {
+        temp_file.write_all(&chunk)?;
+    }
+    let backup = match web::block(move || backup::read_backup(temp_file.path())).await? {
+        Ok(backup) => backup,
+        Err(err) => return Ok(HttpResponse::BadRequest().json(err)),
+    };
+    if let Err(err) = backup.check() {
+        return Ok(HttpResponse::BadRequest().json(err));
+    }
+
+    let mode = query.mode.unwrap_or_default();
+    Ok(match backup_repository.restore_backup(&backup, mode) {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({ "mode": mode, "monsters": backup.monsters.len(), "battles": backup.battles.len() })),
+        Err(err) => repository_error_response(&err),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use crate::api::config::repositories;
+    use crate::models::battle::{Battle, BattleOutcome};
+    use crate::api::error::ApiError;
+    use crate::models::monster::{Monster, Stats};
+    use crate::models::note::MonsterNote;
+    use crate::repository::battle_repository::BattleRepository;
+    use crate::repository::memory_repository::InMemoryRepository;
+    use crate::repository::monster_repository::MonsterRepository;
+    use crate::repository::note_repository::NoteRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_back_up_and_restore_monsters_and_battles() {
+        let source = Arc::new(InMemoryRepository::new());
+        let create_monster = |name: &str| source.create_monster(Monster {
+            id: String::new(),
+            name: name.to_string(),
+            image_url: "https://example.com/monster.png".to_string(),
+            stats: Stats { attack: 40, defense: 20, hp: 50, speed: 80 },
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        }).unwrap();
+        let (monster_a, monster_b) = (create_monster("backed-a"), create_monster("backed-b"));
+        let battle = source.create_battle(Battle {
+            id: String::new(),
+            monster_a: monster_a.id.clone(),
+            monster_b: monster_b.id.clone(),
+            winner: Some(monster_a.id.clone()),
+            created_at: None,
+            updated_at: None,
+            outcome: BattleOutcome::Win,
+            manual: true,
+            season_id: None,
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        }).unwrap();
+        let source_app = test::init_service(App::new().configure(repositories(source.clone())).service(get_backup)).await;
+        let req = test::TestRequest::get().uri("/admin/backup?format=ndjson").to_request();
+        let resp = test::call_service(&source_app, req).await;
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "application/gzip");
+        let archive = test::read_body(resp).await;
+
+        let target = Arc::new(InMemoryRepository::new());
+        let leftover = target.create_monster(Monster { name: "leftover".to_string(), ..monster_a.clone() }).unwrap();
+        let note = MonsterNote { id: String::new(), monster_id: leftover.id.clone(), owner: "key:1".to_string(), body: "Kept apart".to_string(), pinned: false, battle_ids: vec![], created_at: chrono::Utc::now().naive_utc(), updated_at: None };
+        target.create_note(note).unwrap();
+        let target_app = test::init_service(App::new().configure(repositories(target.clone())).service(restore_backup)).await;
+        // The note isn't in the backup, replacing refuses to delete it along with its monster unless forced.
+        let req = test::TestRequest::post().uri("/admin/restore?mode=replace").set_payload(archive.clone()).to_request();
+        let resp = test::call_service(&target_app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let error: ApiError = test::read_body_json(resp).await;
+        assert_eq!(error.code, "RESTORE_DELETES_DEPENDENTS");
+        assert!(target.get_monster_by_id(&leftover.id).is_some());
+        let req = test::TestRequest::post().uri("/admin/restore?mode=force_replace").set_payload(archive).to_request();
+        let report: serde_json::Value = test::call_and_read_body_json(&target_app, req).await;
+        assert_eq!((report["monsters"].as_i64(), report["battles"].as_i64()), (Some(2), Some(1)));
+        assert!(target.get_monster_by_id(&leftover.id).is_none());
+        assert_eq!(target.get_monster_by_id(&monster_a.id).unwrap().xp, source.get_monster_by_id(&monster_a.id).unwrap().xp);
+        assert_eq!(target.get_battle_by_id(&battle.id).unwrap().winner, Some(monster_a.id.clone()));
+
+        // A battle whose monster isn't in the backup rejects the whole backup.
+        let req = test::TestRequest::get().uri("/admin/backup").to_request();
+        let mut backup: serde_json::Value = test::call_and_read_body_json(&source_app, req).await;
+        backup["monsters"].as_array_mut().unwrap().retain(|monster| monster["id"] != monster_b.id.as_str());
+        let req = test::TestRequest::post().uri("/admin/restore").set_json(&backup).to_request();
+        assert_eq!(test::call_service(&target_app, req).await.status(), http::StatusCode::BAD_REQUEST);
+        assert!(target.get_monster_by_id(&monster_b.id).is_some());
+    }
+}
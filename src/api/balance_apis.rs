@@ -0,0 +1,38 @@
+use actix_web::{web, post, HttpResponse};
+use crate::balance::BalanceStore;
+
+// Reads balance.toml again, the battles started from then on playing with it. An invalid file keeps the balance in play.
+#[post("/admin/balance/reload")]
+pub async fn reload_balance(balance: web::Data<BalanceStore>) -> HttpResponse {
+    match balance.reload() {
+        Ok(balance) => HttpResponse::Ok().json(balance.as_ref()),
+        Err(message) => HttpResponse::UnprocessableEntity().json(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, http, App};
+    use crate::balance::Balance;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_keep_the_balance_in_play_when_the_reloaded_file_is_invalid() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let balance = web::Data::new(BalanceStore::new());
+        let app = test::init_service(App::new().app_data(balance.clone()).service(reload_balance)).await;
+        let reload = || test::TestRequest::post().uri("/admin/balance/reload").to_request();
+        assert_eq!(test::call_service(&app, reload()).await.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+
+        std::fs::write(file.path(), "version = \"2\"\n[growth]\nbattle_xp = 80\n").unwrap();
+        assert_eq!(balance.load(file.path().to_path_buf()).unwrap().growth.battle_xp, 80);
+        std::fs::write(file.path(), "version = \"3\"\n[growth]\nbattle_xp = 120\n").unwrap();
+        let reloaded: Balance = test::call_and_read_body_json(&app, reload()).await;
+        assert_eq!((reloaded.version.as_str(), reloaded.growth.battle_xp), ("3", 120));
+
+        std::fs::write(file.path(), "version = \"4\"\n[growth]\nbattle_xp = -1\n").unwrap();
+        assert_eq!(test::call_service(&app, reload()).await.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(balance.current().version, "3");
+    }
+}
@@ -1,272 +1,1842 @@
-use actix_web::{web, get, post, delete, HttpResponse};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use actix_web::{web, get, post, delete, http::header, Error, HttpRequest, HttpResponse};
+use actix_ws::{Closed, Message, Session};
+use futures::StreamExt;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use serde::{Serialize, Deserialize};
-use uuid::Uuid;
-use crate::{models::battle::Battle, repository::database::Database};
-use crate::models::monster::{self, Monster};
-use crate::repository::battle_repository;
-use crate::repository::monster_repository;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use crate::balance;
+use crate::battle_engine::{BattleEngine, BattleResult, BattleSetup, ClassicEngine, TurnEvent};
+use crate::battle_events::{BattleEvent, BattleFeed, BATTLE_EVENTS};
+use crate::config::AppConfig;
+use crate::jobs::JobQueue;
+use crate::metrics::METRICS;
+use crate::models::battle::{Battle, BattleOutcome};
+use crate::models::decay::StatDecay;
+use crate::models::item::Item;
+use crate::models::job::BATTLE_BATCH;
+use crate::models::monster::{Monster, Stats};
+use crate::models::moves::Move;
+use crate::models::page::{self, Page, PageCursor};
+use crate::models::reward::Reward;
+use crate::models::rules::{BattleRules, PRESETS};
+use crate::models::series::{BattleSeries, SeriesGame};
+use crate::models::status_effect::StatusEffectRules;
+use crate::models::strategy::Strategy;
+use crate::repository::battle_repository::BattleRepository;
+use crate::repository::error::RepositoryError;
+use crate::repository::item_repository::ItemRepository;
+use crate::repository::monster_repository::MonsterRepository;
+use crate::repository::move_repository::MoveRepository;
+use crate::repository::request_scope::RequestScope;
+use crate::repository::training_repository::TrainingRepository;
+use crate::rewards::{RewardContext, RewardPipeline};
+use super::error::{repository_error_response, ApiError};
+use super::negotiation::ListFormat;
+
+const DEFAULT_TURN_DELAY_MS: u64 = 500;
+const DEFAULT_STREAM_HEARTBEAT_MS: u64 = 15_000;
+// Sockets not heard from, not even a pong, for this many heartbeats while idle are closed.
+const MISSED_HEARTBEATS: u32 = 3;
+const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+const BATTLE_FEED_CAPACITY: usize = 256;
+const MAX_BATCH_BATTLES: usize = 1000;
+const BULK_SIMULATION_WORKERS: usize = 4;
+const MAX_SERIES_GAMES: i32 = 9;
+const DEFAULT_EVENTS_WAIT: Duration = Duration::from_secs(30);
+const MAX_EVENTS_WAIT: Duration = Duration::from_secs(60);
+const DEFAULT_HIGHLIGHT_DAYS: i64 = 7;
+const MAX_HIGHLIGHT_DAYS: i64 = 30;
+const DEFAULT_HIGHLIGHTS: i64 = 10;
+const MAX_HIGHLIGHTS: i64 = 50;
+
+/*
+Battles stored through the APIs are published here and pushed to `GET /battles/stream` subscribers.
+A subscriber falling more than BATTLE_FEED_CAPACITY battles behind skips the ones it missed.
+*/
+pub static BATTLE_FEED: LazyLock<BattleFeed> = LazyLock::new(|| BattleFeed::new(BATTLE_FEED_CAPACITY));
 
 #[derive(Serialize, Deserialize)]
 pub struct CreateBattleRequest {
     monster_a: Option<String>,
     monster_b: Option<String>,
+    // How each monster picks its moves, `greedy-damage` when left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    monster_a_strategy: Option<Strategy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    monster_b_strategy: Option<Strategy>,
+    // Ranked battles grant the rewards of the reward pipeline.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    ranked: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rules: Option<RulesRequest>,
+}
+
+// Starts from the named preset, `classic` by default, each rule set replacing the one of the preset.
+#[derive(Serialize, Deserialize, Default)]
+pub struct RulesRequest {
+    preset: Option<String>,
+    max_turns: Option<u32>,
+    crits: Option<bool>,
+    monster_a_hp_percent: Option<i32>,
+    monster_b_hp_percent: Option<i32>,
+    damage_floor: Option<i32>,
+}
+
+impl RulesRequest {
+    fn to_rules(&self) -> Result<BattleRules, String> {
+        let preset = self.preset.as_deref().unwrap_or("classic");
+        let Some(preset_rules) = BattleRules::preset(preset) else {
+            return Err(format!("Unknown rules preset {:?}, expected one of {}", preset, PRESETS.join(", ")));
+        };
+        let rules = BattleRules {
+            preset: self.preset.is_some().then(|| preset.to_string()),
+            max_turns: self.max_turns.or(preset_rules.max_turns),
+            crits: self.crits.unwrap_or(preset_rules.crits),
+            monster_a_hp_percent: self.monster_a_hp_percent.unwrap_or(preset_rules.monster_a_hp_percent),
+            monster_b_hp_percent: self.monster_b_hp_percent.unwrap_or(preset_rules.monster_b_hp_percent),
+            damage_floor: self.damage_floor.unwrap_or(preset_rules.damage_floor),
+        };
+        rules.validate()?;
+        Ok(rules)
+    }
+}
+
+// A ranked battle, with what the reward pipeline granted for it.
+#[derive(Serialize, Deserialize)]
+pub struct RankedBattle {
+    #[serde(flatten)]
+    battle: Battle,
+    rewards: BattleRewards,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BattleRewards {
+    granted: Vec<Reward>,
+}
+
+impl CreateBattleRequest {
+    fn strategies(&self) -> (Strategy, Strategy) {
+        (self.monster_a_strategy.unwrap_or_default(), self.monster_b_strategy.unwrap_or_default())
+    }
+
+    fn rules(&self) -> Result<Option<BattleRules>, String> {
+        self.rules.as_ref().map(RulesRequest::to_rules).transpose()
+    }
 }
 
 #[post("/battles")]
-pub async fn create_battle(db: web::Data<Database>, battle_request: web::Json<CreateBattleRequest>) -> HttpResponse {
-    let monster_a_id = match &battle_request.monster_a {
-        Some(id) => id,
-        None => return HttpResponse::BadRequest().json("Monster A id is required")
+#[allow(clippy::too_many_arguments)]
+pub async fn create_battle(
+    monster_repository: web::Data<dyn MonsterRepository>,
+    battle_repository: web::Data<dyn BattleRepository>,
+    move_repository: web::Data<dyn MoveRepository>,
+    item_repository: web::Data<dyn ItemRepository>,
+    training_repository: web::Data<dyn TrainingRepository>,
+    decay: Option<web::Data<StatDecay>>,
+    status_effects: Option<web::Data<StatusEffectRules>>,
+    reward_pipeline: Option<web::Data<RewardPipeline>>,
+    engine: Option<web::Data<dyn BattleEngine>>,
+    battle_request: web::Json<CreateBattleRequest>,
+) -> HttpResponse {
+    let (monster_a, monster_b) = match find_monsters(monster_repository.as_ref(), &battle_request.monster_a, &battle_request.monster_b) {
+        Ok(monsters) => monsters,
+        Err(message) => return HttpResponse::BadRequest().json(message),
     };
-    let monster_b_id = match &battle_request.monster_b {
-        Some(id) => id,
-        None => return HttpResponse::BadRequest().json("Monster B id is required")
+    let rules = match battle_request.rules() {
+        Ok(rules) => rules,
+        Err(message) => return HttpResponse::BadRequest().json(message),
     };
-    
-    let monster_a = match monster_repository::get_monster_by_id(&db, &monster_a_id) {
-        Some(monster) => monster,
-        None => return HttpResponse::BadRequest().json("Monster A id not found") 
+
+    let moves = monster_moves(move_repository.as_ref(), &monster_a, &monster_b);
+    let items = monster_items(item_repository.as_ref(), &monster_a, &monster_b);
+    let hidden_stats = monster_hidden_stats(training_repository.as_ref(), &monster_a, &monster_b);
+    let monsters = battle_request.ranked.then(|| (monster_a.clone(), monster_b.clone()));
+    let setup = BattleSetup {
+        moves,
+        items,
+        hidden_stats,
+        strategies: battle_request.strategies(),
+        status_effects: status_effects.as_ref().map(|status_effects| status_effects.get_ref()),
+        rules,
+        ..BattleSetup::new(monster_a, monster_b)
     };
-    let monster_b = match monster_repository::get_monster_by_id(&db, &monster_b_id) {
-        Some(monster) => monster,
-        None => return HttpResponse::BadRequest().json("Monster B id not found") 
+    let battle = new_simulated_battle(battle_engine(engine).as_ref(), setup, decay.as_ref().map(|decay| decay.get_ref()));
+    let Some(monsters) = monsters else {
+        return match store_battle(battle_repository.as_ref(), battle) {
+            Ok(battle) => HttpResponse::Created().json(battle),
+            Err(err) => repository_error_response(&err),
+        };
     };
+    match store_ranked_battle(battle_repository.as_ref(), battle, monsters, reward_pipeline.as_ref().map(|reward_pipeline| reward_pipeline.get_ref())) {
+        Ok(battle) => HttpResponse::Created().json(battle),
+        Err(err) => repository_error_response(&err),
+    }
+}
 
-    let winner =  simulate_battle(monster_a, monster_b).id;
-    let battle = Battle {
+// Stores the battle with the rewards the pipeline grants for it, ranked battles grant none without a pipeline.
+fn store_ranked_battle(battle_repository: &dyn BattleRepository, battle: Battle, (monster_a, monster_b): (Monster, Monster), reward_pipeline: Option<&RewardPipeline>) -> Result<RankedBattle, RepositoryError> {
+    let rewards = match (reward_pipeline, battle.winner.as_deref()) {
+        (Some(reward_pipeline), Some(winner_id)) => {
+            let (winner, loser) = if winner_id == monster_a.id { (&monster_a, &monster_b) } else { (&monster_b, &monster_a) };
+            let winner_wins = battle_repository.get_battles_by_monster(winner_id).iter().filter(|past_battle| past_battle.winner.as_deref() == Some(winner_id)).count() + 1;
+            reward_pipeline.run(&RewardContext { battle: &battle, winner, loser, winner_wins }, &mut rand::thread_rng())
+        }
+        _ => Vec::new(),
+    };
+    let (battle, rewards) = battle_repository.create_rewarded_battle(battle, rewards)?;
+    BATTLE_FEED.publish(battle.clone());
+    Ok(RankedBattle { battle, rewards: BattleRewards { granted: rewards } })
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BatchBattlesRequest {
+    battles: Vec<CreateBattleRequest>,
+}
+
+/*
+Simulates up to MAX_BATCH_BATTLES battles in a background job and answers 202 with the job to poll at
+`GET /jobs/{id}`. Battles whose monsters can't be found count as failed, the others' ids are the job result.
+*/
+#[post("/battles/batch")]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_battles_batch(
+    monster_repository: web::Data<dyn MonsterRepository>,
+    battle_repository: web::Data<dyn BattleRepository>,
+    move_repository: web::Data<dyn MoveRepository>,
+    item_repository: web::Data<dyn ItemRepository>,
+    training_repository: web::Data<dyn TrainingRepository>,
+    decay: Option<web::Data<StatDecay>>,
+    status_effects: Option<web::Data<StatusEffectRules>>,
+    engine: Option<web::Data<dyn BattleEngine>>,
+    job_queue: web::Data<JobQueue>,
+    request: web::Json<BatchBattlesRequest>,
+) -> HttpResponse {
+    let battles = request.into_inner().battles;
+    if battles.is_empty() || battles.len() > MAX_BATCH_BATTLES {
+        return HttpResponse::BadRequest().json(format!("A batch takes between 1 and {} battles", MAX_BATCH_BATTLES));
+    }
+    let (monster_repository, battle_repository, move_repository, item_repository, training_repository) = (monster_repository.into_inner(), battle_repository.into_inner(), move_repository.into_inner(), item_repository.into_inner(), training_repository.into_inner());
+    let decay = decay.map(|decay| decay.into_inner());
+    let status_effects = status_effects.map(|status_effects| status_effects.into_inner());
+    let engine = battle_engine(engine);
+
+    let job = job_queue.enqueue(BATTLE_BATCH, battles.len(), move |progress| {
+        let (mut battle_ids, mut failed) = (Vec::new(), 0);
+        for (index, request) in battles.iter().enumerate() {
+            match simulate_pairing(monster_repository.as_ref(), battle_repository.as_ref(), move_repository.as_ref(), item_repository.as_ref(), training_repository.as_ref(), decay.as_deref(), status_effects.as_deref(), engine.as_ref(), request) {
+                Ok(battle) => battle_ids.push(battle.id),
+                Err(_) => failed += 1,
+            }
+            progress.update(index + 1, failed);
+        }
+        Ok(serde_json::json!({ "battles": battle_ids }))
+    });
+
+    match job {
+        Ok(job) => HttpResponse::Accepted()
+            .insert_header((header::LOCATION, format!("/api/jobs/{}", job.id)))
+            .json(job),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BulkBattleResult {
+    index: usize,
+    status: String,
+    battle: Option<Battle>,
+    error: Option<String>,
+}
+
+/*
+Simulates the pairings like `POST /battles/batch` but within the request, streaming an NDJSON line per pairing
+as soon as its battle is stored. The pairings are shared by BULK_SIMULATION_WORKERS blocking tasks, so lines
+come in completion order and carry the `index` of their pairing.
+*/
+#[post("/battles/bulk")]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_battles_bulk(
+    monster_repository: web::Data<dyn MonsterRepository>,
+    battle_repository: web::Data<dyn BattleRepository>,
+    move_repository: web::Data<dyn MoveRepository>,
+    item_repository: web::Data<dyn ItemRepository>,
+    training_repository: web::Data<dyn TrainingRepository>,
+    decay: Option<web::Data<StatDecay>>,
+    status_effects: Option<web::Data<StatusEffectRules>>,
+    engine: Option<web::Data<dyn BattleEngine>>,
+    request: web::Json<BatchBattlesRequest>,
+) -> HttpResponse {
+    let pairings = request.into_inner().battles;
+    if pairings.is_empty() || pairings.len() > MAX_BATCH_BATTLES {
+        return HttpResponse::BadRequest().json(format!("A batch takes between 1 and {} battles", MAX_BATCH_BATTLES));
+    }
+    let (monster_repository, battle_repository, move_repository, item_repository, training_repository) = (monster_repository.into_inner(), battle_repository.into_inner(), move_repository.into_inner(), item_repository.into_inner(), training_repository.into_inner());
+    let decay = decay.map(|decay| decay.into_inner());
+    let status_effects = status_effects.map(|status_effects| status_effects.into_inner());
+    let engine = battle_engine(engine);
+    let (pairings, next_pairing) = (Arc::new(pairings), Arc::new(AtomicUsize::new(0)));
+    let (sender, receiver) = mpsc::channel(BULK_SIMULATION_WORKERS);
+    let scope = RequestScope::current();
+
+    for _ in 0..BULK_SIMULATION_WORKERS {
+        let (monster_repository, battle_repository, move_repository, item_repository, training_repository) = (monster_repository.clone(), battle_repository.clone(), move_repository.clone(), item_repository.clone(), training_repository.clone());
+        let (decay, status_effects, engine) = (decay.clone(), status_effects.clone(), engine.clone());
+        let (pairings, next_pairing, sender, scope) = (pairings.clone(), next_pairing.clone(), sender.clone(), scope.clone());
+        actix_rt::task::spawn_blocking(move || scope.sync_scope(|| loop {
+            let index = next_pairing.fetch_add(1, Ordering::Relaxed);
+            let Some(pairing) = pairings.get(index) else { return };
+            let result = match simulate_pairing(monster_repository.as_ref(), battle_repository.as_ref(), move_repository.as_ref(), item_repository.as_ref(), training_repository.as_ref(), decay.as_deref(), status_effects.as_deref(), engine.as_ref(), pairing) {
+                Ok(battle) => BulkBattleResult { index, status: "created".to_string(), battle: Some(battle), error: None },
+                Err(message) => BulkBattleResult { index, status: "failed".to_string(), battle: None, error: Some(message) },
+            };
+            // The client went away, the remaining pairings are dropped.
+            if sender.blocking_send(result).is_err() {
+                return;
+            }
+        }));
+    }
+
+    let lines = futures::stream::unfold(receiver, |mut receiver| async move {
+        let result = receiver.recv().await?;
+        let line = serde_json::to_string(&result).expect("Bulk results are serializable") + "\n";
+        Some((Ok::<_, Error>(web::Bytes::from(line)), receiver))
+    });
+    HttpResponse::Ok().content_type("application/x-ndjson").insert_header(header::ContentEncoding::Identity).streaming(lines)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn simulate_pairing(monster_repository: &dyn MonsterRepository, battle_repository: &dyn BattleRepository, move_repository: &dyn MoveRepository, item_repository: &dyn ItemRepository, training_repository: &dyn TrainingRepository, decay: Option<&StatDecay>, status_effects: Option<&StatusEffectRules>, engine: &dyn BattleEngine, pairing: &CreateBattleRequest) -> Result<Battle, String> {
+    let (monster_a, monster_b) = find_monsters(monster_repository, &pairing.monster_a, &pairing.monster_b)?;
+    let rules = pairing.rules()?;
+    let moves = monster_moves(move_repository, &monster_a, &monster_b);
+    let items = monster_items(item_repository, &monster_a, &monster_b);
+    let hidden_stats = monster_hidden_stats(training_repository, &monster_a, &monster_b);
+    let setup = BattleSetup { moves, items, hidden_stats, strategies: pairing.strategies(), status_effects, rules, ..BattleSetup::new(monster_a, monster_b) };
+    store_battle(battle_repository, new_simulated_battle(engine, setup, decay)).map_err(|err| ApiError::from(&err).message)
+}
+
+// The engine registered as app data, the classic one when none is.
+pub(crate) fn battle_engine(engine: Option<web::Data<dyn BattleEngine>>) -> Arc<dyn BattleEngine> {
+    engine.map(|engine| engine.into_inner()).unwrap_or_else(|| Arc::new(ClassicEngine))
+}
+
+/*
+Simulates the battle with the engine and builds the record to store, shared by the REST and GraphQL APIs.
+Monsters fight with their decayed stats when the decay rule is enabled, and hits inflict statuses when the
+status effects rule is. Equipment bonuses apply on top of the decayed stats. Battles are played with the
+classic rules unless others are given, which are stored with the battle.
+*/
+pub(crate) fn new_simulated_battle(engine: &dyn BattleEngine, battle: BattleSetup<'_>, decay: Option<&StatDecay>) -> Battle {
+    let (monster_a, monster_b) = with_decayed_stats(battle.monster_a, battle.monster_b, decay);
+    let (monster_a_id, monster_b_id, rules, moves) = (monster_a.id.clone(), monster_b.id.clone(), battle.rules.clone(), battle.moves.clone());
+    let result = engine.simulate(BattleSetup { monster_a, monster_b, ..battle });
+    simulated_battle(monster_a_id, monster_b_id, result, rules, &moves)
+}
+
+// The moves each monster fights with, in slot order.
+pub(crate) fn monster_moves(move_repository: &dyn MoveRepository, monster_a: &Monster, monster_b: &Monster) -> (Vec<Move>, Vec<Move>) {
+    (move_repository.get_monster_moves(&monster_a.id), move_repository.get_monster_moves(&monster_b.id))
+}
+
+// The items each monster carries, in slot order.
+pub(crate) fn monster_items(item_repository: &dyn ItemRepository, monster_a: &Monster, monster_b: &Monster) -> (Vec<Item>, Vec<Item>) {
+    (item_repository.get_monster_items(&monster_a.id), item_repository.get_monster_items(&monster_b.id))
+}
+
+// What the individual values and training of each monster add to its stats.
+pub(crate) fn monster_hidden_stats(training_repository: &dyn TrainingRepository, monster_a: &Monster, monster_b: &Monster) -> (Stats, Stats) {
+    let hidden_stats = |monster: &Monster| training_repository.get_training(&monster.id).map(|training| training.hidden_stats(&balance::current())).unwrap_or_default();
+    (hidden_stats(monster_a), hidden_stats(monster_b))
+}
+
+// Stores the battle and publishes it to the battle feed.
+pub(crate) fn store_battle(battle_repository: &dyn BattleRepository, battle: Battle) -> Result<Battle, RepositoryError> {
+    let battle = battle_repository.create_battle(battle)?;
+    BATTLE_FEED.publish(battle.clone());
+    Ok(battle)
+}
+
+fn with_decayed_stats(mut monster_a: Monster, mut monster_b: Monster, decay: Option<&StatDecay>) -> (Monster, Monster) {
+    if let Some(decay) = decay {
+        let now = chrono::Utc::now().naive_utc();
+        monster_a.stats = decay.effective_stats(&monster_a, now);
+        monster_b.stats = decay.effective_stats(&monster_b, now);
+    }
+    (monster_a, monster_b)
+}
+
+// Only battles reaching their turn limit end without a winner, in a draw. `moves` are the ones the monsters knew.
+fn simulated_battle(monster_a: String, monster_b: String, result: BattleResult, rules: Option<BattleRules>, (monster_a_moves, monster_b_moves): &(Vec<Move>, Vec<Move>)) -> Battle {
+    METRICS.battles_simulated.inc();
+    let (moves_a, moves_b) = (result.moves_used_by(&monster_a, monster_a_moves), result.moves_used_by(&monster_b, monster_b_moves));
+    let BattleResult { winner, flawless, highlight_score, balance_version, .. } = result;
+    Battle {
         id: uuid::Uuid::new_v4().to_string(),
-        monster_a: monster_a_id.clone(),
-        monster_b: monster_b_id.clone(),
-        winner: winner,
+        monster_a,
+        monster_b,
+        outcome: if winner.is_some() { BattleOutcome::Win } else { BattleOutcome::Draw },
+        winner,
         created_at: None,
-        updated_at: None
+        updated_at: None,
+        manual: false,
+        season_id: None,
+        rules,
+        trainer_a: None,
+        trainer_b: None,
+        flawless,
+        highlight_score,
+        region: None,
+        balance_version,
+        moves_a,
+        moves_b,
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateSeriesRequest {
+    monster_a: Option<String>,
+    monster_b: Option<String>,
+    best_of: Option<i32>,
+    // Seeds the games' seeds, making the whole series reproducible.
+    seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    monster_a_strategy: Option<Strategy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    monster_b_strategy: Option<Strategy>,
+}
+
+/*
+Plays seeded games until a monster wins the majority of `best_of` (odd, up to MAX_SERIES_GAMES), then stores
+the battle of every game played along with the series.
+*/
+#[post("/battles/series")]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_series(monster_repository: web::Data<dyn MonsterRepository>, battle_repository: web::Data<dyn BattleRepository>, move_repository: web::Data<dyn MoveRepository>, item_repository: web::Data<dyn ItemRepository>, training_repository: web::Data<dyn TrainingRepository>, decay: Option<web::Data<StatDecay>>, status_effects: Option<web::Data<StatusEffectRules>>, engine: Option<web::Data<dyn BattleEngine>>, series_request: web::Json<CreateSeriesRequest>) -> HttpResponse {
+    let best_of = match series_request.best_of {
+        Some(best_of) if best_of > 0 && best_of <= MAX_SERIES_GAMES && best_of % 2 == 1 => best_of,
+        Some(_) => return HttpResponse::BadRequest().json(format!("Best of must be an odd number of games up to {}", MAX_SERIES_GAMES)),
+        None => return HttpResponse::BadRequest().json("Best of is required"),
+    };
+    let (monster_a, monster_b) = match find_monsters(monster_repository.as_ref(), &series_request.monster_a, &series_request.monster_b) {
+        Ok(monsters) => monsters,
+        Err(message) => return HttpResponse::BadRequest().json(message),
     };
+    if monster_a.id == monster_b.id {
+        return HttpResponse::BadRequest().json("A monster cannot battle itself");
+    }
 
-    match battle_repository::create_battle(&db, battle) {
-        Ok(battle) => HttpResponse::Created().json(battle),
-        Err(e) => HttpResponse::InternalServerError().json(e.to_string())
+    let (monster_a, monster_b) = with_decayed_stats(monster_a, monster_b, decay.as_ref().map(|decay| decay.get_ref()));
+    let moves = monster_moves(move_repository.as_ref(), &monster_a, &monster_b);
+    let items = monster_items(item_repository.as_ref(), &monster_a, &monster_b);
+    let hidden_stats = monster_hidden_stats(training_repository.as_ref(), &monster_a, &monster_b);
+    let strategies = (series_request.monster_a_strategy.unwrap_or_default(), series_request.monster_b_strategy.unwrap_or_default());
+    let (series, games) = play_series(battle_engine(engine).as_ref(), monster_a, monster_b, moves, items, hidden_stats, strategies, best_of, series_request.seed.unwrap_or_else(rand::random), status_effects.as_ref().map(|status_effects| status_effects.get_ref()));
+    match battle_repository.create_series(series, games) {
+        Ok(series) => {
+            for game in &series.games {
+                BATTLE_FEED.publish(game.battle.clone());
+            }
+            HttpResponse::Created().json(series)
+        }
+        Err(err) => repository_error_response(&err),
     }
 }
 
-#[get("/battles")]
-pub async fn get_battles(db: web::Data<Database>) -> HttpResponse {
-    let battles = battle_repository::get_battles(&db);
-    HttpResponse::Ok().json(battles)
+#[get("/battles/series/{id}")]
+pub async fn get_series_by_id(battle_repository: web::Data<dyn BattleRepository>, id: web::Path<String>) -> HttpResponse {
+    match battle_repository.get_series_by_id(&id) {
+        Some(series) => HttpResponse::Ok().json(series),
+        None => HttpResponse::NotFound().json("Series not found"),
+    }
 }
 
-#[get("/battles/{id}")]
-pub async fn get_battle_by_id(db: web::Data<Database>, id: web::Path<String>) -> HttpResponse {
-    let battle = battle_repository::get_battle_by_id(&db, &id);
-    match battle {
-        Some(battle) => HttpResponse::Ok().json(battle),
-        None => HttpResponse::NotFound().json("Battle not found"),
+#[allow(clippy::too_many_arguments)]
+fn play_series(engine: &dyn BattleEngine, monster_a: Monster, monster_b: Monster, moves: (Vec<Move>, Vec<Move>), items: (Vec<Item>, Vec<Item>), hidden_stats: (Stats, Stats), strategies: (Strategy, Strategy), best_of: i32, seed: u64, status_effects: Option<&StatusEffectRules>) -> (BattleSeries, Vec<SeriesGame>) {
+    let mut seeds = ChaCha8Rng::seed_from_u64(seed);
+    let wins_needed = best_of / 2 + 1;
+    let (mut monster_a_wins, mut monster_b_wins) = (0, 0);
+    let mut games = Vec::new();
+    while monster_a_wins < wins_needed && monster_b_wins < wins_needed {
+        let game_seed = seeds.next_u64();
+        let game = BattleSetup { moves: moves.clone(), items: items.clone(), hidden_stats, strategies, status_effects, seed: Some(game_seed), ..BattleSetup::new(monster_a.clone(), monster_b.clone()) };
+        let result = engine.simulate(game);
+        let winner = result.winner.clone().expect("Battles without a turn limit end in a knockout");
+        if winner == monster_a.id {
+            monster_a_wins += 1;
+        } else {
+            monster_b_wins += 1;
+        }
+        games.push(SeriesGame {
+            game: games.len() as i32 + 1,
+            // Stored as a bigint, the bits are kept as they are.
+            seed: game_seed as i64,
+            battle: simulated_battle(monster_a.id.clone(), monster_b.id.clone(), result, None, &moves),
+        });
     }
+
+    let winner = if monster_a_wins == wins_needed { monster_a.id.clone() } else { monster_b.id.clone() };
+    let series = BattleSeries {
+        id: uuid::Uuid::new_v4().to_string(),
+        monster_a: monster_a.id,
+        monster_b: monster_b.id,
+        best_of,
+        winner,
+        created_at: chrono::Utc::now().naive_utc(),
+    };
+    (series, games)
 }
 
-#[delete("/battles/{id}")]
-pub async fn delete_battle_by_id(db: web::Data<Database>, id: web::Path<String>) -> HttpResponse {
-    match battle_repository::delete_battle_by_id(&db, &id) {
-        Some(_) => HttpResponse::NoContent().finish(),
-        None => HttpResponse::NotFound().json("Battle not found"),
+// Pause between the turns sent by `GET /battles/ws`, read from BATTLE_TURN_DELAY_MS.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TurnDelay(pub Duration);
+
+impl Default for TurnDelay {
+    fn default() -> Self {
+        TurnDelay(Duration::from_millis(DEFAULT_TURN_DELAY_MS))
+    }
+}
+
+impl TurnDelay {
+    pub fn from_config(config: &AppConfig) -> Result<Self, String> {
+        match config.var("BATTLE_TURN_DELAY_MS") {
+            Some(delay) => delay
+                .trim()
+                .parse()
+                .map(|delay| TurnDelay(Duration::from_millis(delay)))
+                .map_err(|_| format!("BATTLE_TURN_DELAY_MS must be a non-negative integer, got {:?}", delay)),
+            None => Ok(TurnDelay::default()),
+        }
     }
 }
 
 /*
-- The monster with the highest speed makes the first attack, if both speeds are equal, the monster with the higher attack goes first.
-- For calculating the damage, subtract the defense from the attack (attack - defense); the difference is the damage; 
-- if the attack is equal to or lower than the defense, the damage is 1.
-Subtract the damage from the HP (HP = HP - damage).
-Monsters will battle in turns until one wins; all turns should be calculated in the same request; for that reason, the battle endpoint should return winner data in just one call.
-Who wins the battle is the monster who subtracted the enemy’s HP to zero
+Interval of the keepalives sent on the realtime streams, pings on sockets and comments on server-sent events,
+so idle connections aren't dropped by proxies and mobile networks. Read from STREAM_HEARTBEAT_MS.
 */
-fn simulate_battle(mut monster_a: Monster, mut monster_b: Monster) -> Monster {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamHeartbeat(pub Duration);
+
+impl Default for StreamHeartbeat {
+    fn default() -> Self {
+        StreamHeartbeat(Duration::from_millis(DEFAULT_STREAM_HEARTBEAT_MS))
+    }
+}
 
-    let mut monster_a_turn = if monster_a.speed > monster_b.speed || 
-                                (monster_a.speed == monster_b.speed && monster_a.attack > monster_b.attack) {
-        true
-    } else {
-        false
+impl StreamHeartbeat {
+    pub fn from_config(config: &AppConfig) -> Result<Self, String> {
+        match config.var("STREAM_HEARTBEAT_MS") {
+            Some(heartbeat) => match heartbeat.trim().parse::<u64>() {
+                Ok(heartbeat) if heartbeat > 0 => Ok(StreamHeartbeat(Duration::from_millis(heartbeat))),
+                _ => Err(format!("STREAM_HEARTBEAT_MS must be a positive integer, got {:?}", heartbeat)),
+            },
+            None => Ok(StreamHeartbeat::default()),
+        }
+    }
+
+    fn client_timeout(&self) -> Duration {
+        self.0 * MISSED_HEARTBEATS
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BattleStreamMessage {
+    Started { battle_id: String, monster_a: String, monster_b: String },
+    Turn(TurnEvent),
+    Finished { battle_id: String, winner: String },
+    Error { message: String },
+}
+
+/*
+Streams a simulated battle: the client sends `{"monster_a": .., "monster_b": ..}` and receives a `started`
+message with the battle id, a `turn` message per turn, then a `finished` message before the socket is closed.
+Invalid requests get an `error` message. Every message from `started` on is also published to BATTLE_EVENTS
+for `GET /battles/{id}/events` spectators, and carries its `cursor` and a `resume_token`.
+
+A client losing its connection doesn't abandon the battle, which is fought to the end and stored. The client
+reconnects and sends `{"resume_token": ..}` with the token of the last message it received to get the ones it
+missed, then the rest of the battle. Idle sockets are pinged every heartbeat.
+*/
+#[get("/battles/ws")]
+#[allow(clippy::too_many_arguments)]
+pub async fn battle_ws(
+    req: HttpRequest,
+    body: web::Payload,
+    monster_repository: web::Data<dyn MonsterRepository>,
+    battle_repository: web::Data<dyn BattleRepository>,
+    move_repository: web::Data<dyn MoveRepository>,
+    item_repository: web::Data<dyn ItemRepository>,
+    training_repository: web::Data<dyn TrainingRepository>,
+    decay: Option<web::Data<StatDecay>>,
+    status_effects: Option<web::Data<StatusEffectRules>>,
+    engine: Option<web::Data<dyn BattleEngine>>,
+    turn_delay: Option<web::Data<TurnDelay>>,
+    heartbeat: Option<web::Data<StreamHeartbeat>>,
+) -> Result<HttpResponse, Error> {
+    let (response, mut session, mut stream) = actix_ws::handle(&req, body)?;
+    let turn_delay = turn_delay.map(|turn_delay| *turn_delay.into_inner()).unwrap_or_default();
+    let heartbeat = heartbeat.map(|heartbeat| *heartbeat.into_inner()).unwrap_or_default();
+
+    // Pings until the socket is closed, battles keep it busy without reading the pongs.
+    let mut pinged_session = session.clone();
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval_at(actix_web::rt::time::Instant::now() + heartbeat.0, heartbeat.0);
+        loop {
+            interval.tick().await;
+            if pinged_session.ping(b"").await.is_err() {
+                return;
+            }
+        }
+    });
+
+    let engine = battle_engine(engine);
+    // The battles are stored in the schema of the upgrade request, under its actor.
+    let scope = RequestScope::current();
+    actix_web::rt::spawn(scope.scope(async move {
+        let decay = decay.as_ref().map(|decay| decay.get_ref());
+        let status_effects = status_effects.as_ref().map(|status_effects| status_effects.get_ref());
+        while let Ok(Some(Ok(message))) = actix_web::rt::time::timeout(heartbeat.client_timeout(), stream.recv()).await {
+            let fought = match message {
+                Message::Text(text) => match serde_json::from_str::<ResumeBattleRequest>(&text) {
+                    Ok(request) => resume_battle(&mut session, &request.resume_token, heartbeat).await,
+                    Err(_) => stream_battle(&mut session, monster_repository.as_ref(), battle_repository.as_ref(), move_repository.as_ref(), item_repository.as_ref(), training_repository.as_ref(), decay, status_effects, engine.as_ref(), turn_delay, &text).await,
+                },
+                Message::Ping(bytes) => session.pong(&bytes).await.map(|_| false),
+                Message::Close(_) => return,
+                _ => Ok(false),
+            };
+            match fought {
+                Ok(false) => continue,
+                Ok(true) => break,
+                Err(Closed) => return,
+            }
+        }
+        let _ = session.close(None).await;
+    }));
+
+    Ok(response)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ResumeBattleRequest {
+    resume_token: String,
+}
+
+// A message of a started battle as sent over the socket, the token resuming the battle right after it.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BattleSocketMessage {
+    #[serde(flatten)]
+    event: BattleEvent,
+    resume_token: String,
+}
+
+fn resume_token(battle_id: &str, cursor: usize) -> String {
+    format!("{}:{}", battle_id, cursor)
+}
+
+fn parse_resume_token(resume_token: &str) -> Option<(&str, usize)> {
+    let (battle_id, cursor) = resume_token.rsplit_once(':')?;
+    Some((battle_id, cursor.parse().ok()?))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn stream_battle(
+    session: &mut Session,
+    monster_repository: &dyn MonsterRepository,
+    battle_repository: &dyn BattleRepository,
+    move_repository: &dyn MoveRepository,
+    item_repository: &dyn ItemRepository,
+    training_repository: &dyn TrainingRepository,
+    decay: Option<&StatDecay>,
+    status_effects: Option<&StatusEffectRules>,
+    engine: &dyn BattleEngine,
+    turn_delay: TurnDelay,
+    request: &str,
+) -> Result<bool, Closed> {
+    let request: CreateBattleRequest = match serde_json::from_str(request) {
+        Ok(request) => request,
+        Err(_) => return send_error(session, "Invalid battle request").await,
     };
+    let (monster_a, monster_b) = match find_monsters(monster_repository, &request.monster_a, &request.monster_b) {
+        Ok(monsters) => monsters,
+        Err(message) => return send_error(session, message).await,
+    };
+
+    let moves = monster_moves(move_repository, &monster_a, &monster_b);
+    let items = monster_items(item_repository, &monster_a, &monster_b);
+    let hidden_stats = monster_hidden_stats(training_repository, &monster_a, &monster_b);
+    let (monster_a, monster_b) = with_decayed_stats(monster_a, monster_b, decay);
+    let (monster_a_id, monster_b_id) = (monster_a.id.clone(), monster_b.id.clone());
+    let battle_id = uuid::Uuid::new_v4().to_string();
+    let mut connected = true;
+    let started = BattleStreamMessage::Started { battle_id: battle_id.clone(), monster_a: monster_a_id.clone(), monster_b: monster_b_id.clone() };
+    publish(session, &mut connected, &battle_id, started).await;
+    let mut winner = String::new();
+    let mut played = Vec::new();
+    let setup = BattleSetup { moves: moves.clone(), items, hidden_stats, strategies: request.strategies(), status_effects, ..BattleSetup::new(monster_a, monster_b) };
+    let balance = setup.balance.clone();
+    for turn in engine.turns(setup) {
+        if turn.turn > 1 {
+            actix_web::rt::time::sleep(turn_delay.0).await;
+        }
+        if let Some(turn_winner) = turn.winner() {
+            winner = turn_winner.to_string();
+        }
+        played.push(turn.clone());
+        publish(session, &mut connected, &battle_id, BattleStreamMessage::Turn(turn)).await;
+    }
+
+    let result = BattleResult::from_turns(Some(winner.clone()), &played, &balance);
+    let battle = Battle { id: battle_id.clone(), ..simulated_battle(monster_a_id, monster_b_id, result, None, &moves) };
+    let stored = match store_battle(battle_repository, battle) {
+        Ok(battle) => {
+            publish(session, &mut connected, &battle_id, BattleStreamMessage::Finished { battle_id: battle.id, winner }).await;
+            true
+        }
+        Err(err) => {
+            publish(session, &mut connected, &battle_id, BattleStreamMessage::Error { message: ApiError::from(&err).message }).await;
+            false
+        }
+    };
+    if connected { Ok(stored) } else { Err(Closed) }
+}
 
+// Sends the messages of a battle past the cursor of the token, waiting for the next ones until it ends.
+async fn resume_battle(session: &mut Session, resume_token: &str, heartbeat: StreamHeartbeat) -> Result<bool, Closed> {
+    let Some((battle_id, mut after)) = parse_resume_token(resume_token) else {
+        return send_error(session, "Invalid resume token").await;
+    };
     loop {
-        let (attacker, defender) = if monster_a_turn {
-            (&mut monster_a, &mut monster_b)
-        } else {
-            (&mut monster_b, &mut monster_a)
+        let Some(events) = BATTLE_EVENTS.wait_for_events(battle_id, after, heartbeat.0).await else {
+            return send_error(session, "The battle can't be resumed anymore").await;
         };
+        if events.is_empty() && BATTLE_EVENTS.has_ended(battle_id) {
+            return Ok(true);
+        }
+        for event in events {
+            after = event.cursor;
+            send_event(session, battle_id, event).await?;
+        }
+    }
+}
 
-        let damage = if attacker.attack > defender.defense {
-            attacker.attack - defender.defense
-        } else {
-            1
+// Publishes the message of a started battle to its spectators, and sends it to the client while it is connected.
+async fn publish(session: &mut Session, connected: &mut bool, battle_id: &str, message: BattleStreamMessage) {
+    let event = BATTLE_EVENTS.publish(battle_id, message);
+    if *connected {
+        *connected = send_event(session, battle_id, event).await.is_ok();
+    }
+}
+
+async fn send_event(session: &mut Session, battle_id: &str, event: BattleEvent) -> Result<(), Closed> {
+    let message = BattleSocketMessage { resume_token: resume_token(battle_id, event.cursor), event };
+    session.text(serde_json::to_string(&message).expect("Battle messages are serializable")).await
+}
+
+async fn send_error(session: &mut Session, message: &str) -> Result<bool, Closed> {
+    let message = BattleStreamMessage::Error { message: message.to_string() };
+    session.text(serde_json::to_string(&message).expect("Battle messages are serializable")).await.map(|_| false)
+}
+
+#[derive(Deserialize)]
+pub struct BattleEventsQuery {
+    wait: Option<String>,
+    after: Option<usize>,
+}
+
+// `wait` takes milliseconds or seconds, as in `500ms` or `30s`.
+fn parse_wait(wait: &str) -> Result<Duration, &'static str> {
+    let wait = wait.trim();
+    let (value, unit) = match wait.strip_suffix("ms") {
+        Some(value) => (value, Duration::from_millis(1)),
+        None => (wait.strip_suffix('s').unwrap_or(wait), Duration::from_secs(1)),
+    };
+    match value.parse::<u32>() {
+        Ok(value) if unit * value <= MAX_EVENTS_WAIT => Ok(unit * value),
+        Ok(_) => Err("Wait can't be longer than 60s"),
+        Err(_) => Err("Wait must be a duration like 500ms or 30s"),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BattleEventsPage {
+    events: Vec<BattleEvent>,
+    // Sent back as `after` to get the next events.
+    cursor: usize,
+    finished: bool,
+}
+
+/*
+Long-polling alternative to the WebSocket for spectators: answers with the events of the battle past the
+`after` cursor, waiting up to `wait` (30s by default) for one to arrive. Stored battles whose events aren't
+logged anymore, or that were never streamed, answer as finished without events.
+*/
+#[get("/battles/{id}/events")]
+pub async fn get_battle_events(battle_repository: web::Data<dyn BattleRepository>, id: web::Path<String>, query: web::Query<BattleEventsQuery>) -> HttpResponse {
+    let wait = match query.wait.as_deref().map(parse_wait).transpose() {
+        Ok(wait) => wait.unwrap_or(DEFAULT_EVENTS_WAIT),
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+    let after = query.after.unwrap_or(0);
+
+    match BATTLE_EVENTS.wait_for_events(&id, after, wait).await {
+        Some(events) => HttpResponse::Ok().json(BattleEventsPage {
+            cursor: events.last().map_or(after, |event| event.cursor),
+            finished: BATTLE_EVENTS.has_ended(&id),
+            events,
+        }),
+        None if battle_repository.get_battle_by_id(&id).is_some() => {
+            HttpResponse::Ok().json(BattleEventsPage { events: Vec::new(), cursor: after, finished: true })
+        }
+        None => HttpResponse::NotFound().json("Battle not found"),
+    }
+}
+
+/*
+Result of a manual battle from monster A's point of view, `forfeit` meaning monster A forfeited.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ManualOutcome {
+    Win,
+    Loss,
+    Draw,
+    Forfeit,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateManualBattleRequest {
+    monster_a: Option<String>,
+    monster_b: Option<String>,
+    outcome: Option<ManualOutcome>,
+}
+
+#[post("/battles/manual")]
+pub async fn create_manual_battle(monster_repository: web::Data<dyn MonsterRepository>, battle_repository: web::Data<dyn BattleRepository>, battle_request: web::Json<CreateManualBattleRequest>) -> HttpResponse {
+    let outcome = match battle_request.outcome {
+        Some(outcome) => outcome,
+        None => return HttpResponse::BadRequest().json("Outcome is required"),
+    };
+    let (monster_a, monster_b) = match find_monsters(monster_repository.as_ref(), &battle_request.monster_a, &battle_request.monster_b) {
+        Ok(monsters) => monsters,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+    if monster_a.id == monster_b.id {
+        return HttpResponse::BadRequest().json("A monster cannot battle itself");
+    }
+
+    match store_battle(battle_repository.as_ref(), new_manual_battle(monster_a.id, monster_b.id, outcome)) {
+        Ok(battle) => HttpResponse::Created().json(battle),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+pub(crate) fn new_manual_battle(monster_a: String, monster_b: String, outcome: ManualOutcome) -> Battle {
+    let (winner, outcome) = match outcome {
+        ManualOutcome::Win => (Some(monster_a.clone()), BattleOutcome::Win),
+        ManualOutcome::Loss => (Some(monster_b.clone()), BattleOutcome::Win),
+        ManualOutcome::Draw => (None, BattleOutcome::Draw),
+        ManualOutcome::Forfeit => (Some(monster_b.clone()), BattleOutcome::Forfeit),
+    };
+    Battle {
+        id: uuid::Uuid::new_v4().to_string(),
+        monster_a,
+        monster_b,
+        winner,
+        created_at: None,
+        updated_at: None,
+        outcome,
+        manual: true,
+        season_id: None,
+        rules: None,
+        trainer_a: None,
+        trainer_b: None,
+        flawless: false,
+        highlight_score: 0,
+        region: None,
+        balance_version: None,
+        moves_a: Vec::new(),
+        moves_b: Vec::new(),
+    }
+}
+
+pub(crate) fn find_monsters(monster_repository: &dyn MonsterRepository, monster_a_id: &Option<String>, monster_b_id: &Option<String>) -> Result<(Monster, Monster), &'static str> {
+    let monster_a_id = monster_a_id.as_deref().ok_or("Monster A id is required")?;
+    let monster_b_id = monster_b_id.as_deref().ok_or("Monster B id is required")?;
+    let monster_a = monster_repository.get_monster_by_id(monster_a_id).ok_or("Monster A id not found")?;
+    let monster_b = monster_repository.get_monster_by_id(monster_b_id).ok_or("Monster B id not found")?;
+    Ok((monster_a, monster_b))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BattleQuery {
+    expand: Option<String>,
+    // `csv` for CSV instead of JSON, see `ListFormat::negotiate`.
+    format: Option<String>,
+    // With either, a page of battles in its envelope, see `page::requested_page`.
+    after: Option<String>,
+    limit: Option<i64>,
+}
+
+impl BattleQuery {
+    fn expand_monsters(&self) -> Result<bool, &'static str> {
+        match self.expand.as_deref() {
+            None => Ok(false),
+            Some("monsters") => Ok(true),
+            Some(_) => Err("Only monsters can be expanded"),
+        }
+    }
+}
+
+#[get("/battles")]
+pub async fn get_battles(req: HttpRequest, battle_repository: web::Data<dyn BattleRepository>, query: web::Query<BattleQuery>) -> HttpResponse {
+    let format = match ListFormat::negotiate(&req, query.format.as_deref()) {
+        Ok(format) => format,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let page = match page::requested_page(query.after.as_deref(), query.limit) {
+        Ok(page) => page,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+    match (query.expand_monsters(), page) {
+        (Ok(true), Some((after, limit))) => {
+            let battles = battle_repository.get_expanded_battles_page(after.as_ref(), limit + 1);
+            format.respond_page(&Page::new(battles, limit, |battle| PageCursor { created_at: battle.created_at, id: battle.id.clone() }))
+        }
+        (Ok(false), Some((after, limit))) => {
+            let battles = battle_repository.get_battles_page(after.as_ref(), limit + 1);
+            format.respond_page(&Page::new(battles, limit, |battle| PageCursor { created_at: battle.created_at, id: battle.id.clone() }))
+        }
+        (Ok(true), None) => format.respond(&battle_repository.get_expanded_battles()),
+        (Ok(false), None) => format.respond(&battle_repository.get_battles()),
+        (Err(message), _) => HttpResponse::BadRequest().json(message),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HighlightsQuery {
+    // Days back the battles were stored.
+    days: Option<i64>,
+    limit: Option<i64>,
+}
+
+// The most exciting recent battles by highlight score, for the featured replays.
+#[get("/battles/highlights")]
+pub async fn get_battle_highlights(battle_repository: web::Data<dyn BattleRepository>, query: web::Query<HighlightsQuery>) -> HttpResponse {
+    let days = query.days.unwrap_or(DEFAULT_HIGHLIGHT_DAYS);
+    if !(1..=MAX_HIGHLIGHT_DAYS).contains(&days) {
+        return HttpResponse::BadRequest().json(format!("Days must be between 1 and {}", MAX_HIGHLIGHT_DAYS));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_HIGHLIGHTS);
+    if !(1..=MAX_HIGHLIGHTS).contains(&limit) {
+        return HttpResponse::BadRequest().json(format!("Limit must be between 1 and {}", MAX_HIGHLIGHTS));
+    }
+    let since = chrono::Utc::now().naive_utc() - chrono::Duration::days(days);
+    HttpResponse::Ok().json(battle_repository.get_highlight_battles(since, limit))
+}
+
+// The named presets a battle's `rules` can start from.
+#[get("/battles/rule-presets")]
+pub async fn get_rule_presets() -> HttpResponse {
+    let presets: Vec<BattleRules> = PRESETS.iter().filter_map(|name| BattleRules::preset(name)).collect();
+    HttpResponse::Ok().json(presets)
+}
+
+#[get("/battles/{id}")]
+pub async fn get_battle_by_id(battle_repository: web::Data<dyn BattleRepository>, id: web::Path<String>, query: web::Query<BattleQuery>) -> HttpResponse {
+    let expand_monsters = match query.expand_monsters() {
+        Ok(expand_monsters) => expand_monsters,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+    if expand_monsters {
+        return match battle_repository.get_expanded_battle_by_id(&id) {
+            Some(battle) => HttpResponse::Ok().json(battle),
+            None => HttpResponse::NotFound().json("Battle not found"),
         };
+    }
 
-        if defender.hp > damage {
-            defender.hp -= damage;
-        } else {
-            defender.hp = 0;
-            return attacker.clone()
+    let battle = battle_repository.get_battle_by_id(&id);
+    match battle {
+        Some(battle) => HttpResponse::Ok().json(battle),
+        None => HttpResponse::NotFound().json("Battle not found"),
+    }
+}
+
+/*
+Server-sent events feed of every battle created from now on in the schema of the request, one `battle` event per
+battle with the battle id as event id. Clients reconnecting with a `Last-Event-ID` still buffered by BATTLE_FEED first get the battles
+they missed. A `keepalive` comment is sent every heartbeat.
+*/
+#[get("/battles/stream")]
+pub async fn stream_battles(req: HttpRequest, heartbeat: Option<web::Data<StreamHeartbeat>>) -> HttpResponse {
+    let heartbeat = heartbeat.map(|heartbeat| *heartbeat.into_inner()).unwrap_or_default();
+    // Subscribing first so no battle is missed between the replay and the live ones.
+    let receiver = BATTLE_FEED.subscribe();
+    let schema = RequestScope::current().schema;
+    let missed = req.headers()
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|last_event_id| last_event_id.to_str().ok())
+        .and_then(|last_event_id| BATTLE_FEED.battles_after(&schema, last_event_id))
+        .unwrap_or_default();
+    let replayed: HashSet<String> = missed.iter().map(|battle| battle.id.clone()).collect();
+
+    let missed = futures::stream::iter(missed).map(|battle| Ok::<_, Error>(battle_event(&battle)));
+    let live = futures::stream::unfold((receiver, schema, replayed), |(mut receiver, schema, replayed)| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(published) if published.schema != schema || replayed.contains(&published.battle.id) => continue,
+                Ok(published) => return Some((Ok::<_, Error>(battle_event(&published.battle)), (receiver, schema, replayed))),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
         }
+    });
+    let start = actix_web::rt::time::Instant::now() + heartbeat.0;
+    let keepalives = futures::stream::unfold(actix_web::rt::time::interval_at(start, heartbeat.0), |mut interval| async move {
+        interval.tick().await;
+        Some((Ok::<_, Error>(web::Bytes::from_static(b": keepalive\n\n")), interval))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .insert_header(header::ContentEncoding::Identity)
+        .streaming(missed.chain(futures::stream::select(live, keepalives)))
+}
 
-        monster_a_turn = !monster_a_turn;
+fn battle_event(battle: &Battle) -> web::Bytes {
+    let data = serde_json::to_string(battle).expect("Battles are serializable");
+    web::Bytes::from(format!("event: battle\nid: {}\ndata: {}\n\n", battle.id, data))
+}
+
+#[delete("/battles/{id}")]
+pub async fn delete_battle_by_id(battle_repository: web::Data<dyn BattleRepository>, id: web::Path<String>) -> HttpResponse {
+    match battle_repository.delete_battle_by_id(&id) {
+        Some(_) => HttpResponse::NoContent().finish(),
+        None => HttpResponse::NotFound().json("Battle not found"),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use actix_web::{test, http, App};
-    use actix_web::web::Data;
+    use std::sync::Arc;
+    use crate::api::config::repositories;
+    use crate::models::battle::ExpandedBattle;
+    use crate::models::monster::Stats;
+    use crate::repository::database::Database;
+    use crate::repository::memory_repository::InMemoryRepository;
+    use crate::repository::monster_repository::MonsterRepository;
     use crate::{
         utils::test_utils::init_test_battle,
         utils::test_utils::init_test_monsters
     };
-    use serde_json;
 
     use super::*;
 
     #[actix_rt::test]
     async fn test_should_get_all_battles_correctly() {
-        let db = Database::new();
-        let app = App::new().app_data(Data::new(db)).service(get_battles);
+        let db = Database::new().unwrap();
+        let app = App::new().configure(repositories(Arc::new(db))).service(get_battles);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let req = test::TestRequest::get().uri("/battles").to_request();
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
         
         assert!(resp.status().is_success());
     }
 
     #[actix_rt::test]
     async fn test_should_get_404_error_if_battle_does_not_exists() {
-        let db = Database::new();
-        let app = App::new().app_data(Data::new(db)).service(get_battle_by_id);
+        let db = Database::new().unwrap();
+        let app = App::new().configure(repositories(Arc::new(db))).service(get_battle_by_id);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let req = test::TestRequest::get().uri("/battles/123").to_request();
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
         
         assert_eq!(resp.status(), http::StatusCode::NOT_FOUND)
     }
 
     #[actix_rt::test]
     async fn test_should_get_a_single_battle_correctly() {
-        let mut db = Database::new();
-        let test_battle = init_test_battle(&mut db).await;
-        let app = App::new().app_data(Data::new(db)).service(get_battle_by_id);
+        let db = Database::new().unwrap();
+        let test_battle = init_test_battle(&db).await;
+        let app = App::new().configure(repositories(Arc::new(db))).service(get_battle_by_id);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let req = test::TestRequest::get().uri(&format!("/battles/{}", test_battle.id)).to_request();
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
         
         assert!(resp.status().is_success());
     }
 
+    #[actix_rt::test]
+    async fn test_should_get_a_single_battle_with_expanded_monsters() {
+        let db = Database::new().unwrap();
+        let test_battle = init_test_battle(&db).await;
+        let app = App::new().configure(repositories(Arc::new(db))).service(get_battle_by_id);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri(&format!("/battles/{}?expand=monsters", test_battle.id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let battle: ExpandedBattle = test::read_body_json(resp).await;
+        assert_eq!(battle.monster_a.map(|monster| monster.id), Some(test_battle.monster_a));
+        assert_eq!(battle.monster_b.map(|monster| monster.id), Some(test_battle.monster_b));
+        assert_eq!(battle.winner.map(|monster| monster.id), test_battle.winner);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_get_all_battles_with_expanded_monsters() {
+        let db = Database::new().unwrap();
+        let _test_battle = init_test_battle(&db).await;
+        let app = App::new().configure(repositories(Arc::new(db))).service(get_battles);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/battles?expand=monsters").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let battles: Vec<ExpandedBattle> = test::read_body_json(resp).await;
+        assert!(!battles.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_should_get_a_bad_request_response_if_expand_is_not_supported() {
+        let db = Database::new().unwrap();
+        let app = App::new().configure(repositories(Arc::new(db))).service(get_battles);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/battles?expand=winners").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+
     #[actix_rt::test]
     async fn test_should_delete_a_battle_correctly() {
-        let mut db = Database::new();
-        let test_battle = init_test_battle(&mut db).await;
-        let app = App::new().app_data(Data::new(db)).service(delete_battle_by_id);
+        let db = Database::new().unwrap();
+        let test_battle = init_test_battle(&db).await;
+        let app = App::new().configure(repositories(Arc::new(db))).service(delete_battle_by_id);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let req = test::TestRequest::delete().uri(&format!("/battles/{}", test_battle.id)).to_request();
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
         
         assert_eq!(resp.status(), http::StatusCode::NO_CONTENT);
     }
 
     #[actix_rt::test]
     async fn test_should_create_a_battle_with_404_error_if_one_parameter_has_a_monster_id_does_not_exists() {
-        let mut db = Database::new();
-        let test_monsters = init_test_monsters(&mut db).await;
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
 
-        let app = App::new().app_data(Data::new(db)).service(create_battle);
+        let app = App::new().configure(repositories(Arc::new(db))).service(create_battle);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let battle_request = CreateBattleRequest {
             monster_a: Some("123".to_string()),
             monster_b: Some(test_monsters[0].id.clone()),
+            monster_a_strategy: None,
+            monster_b_strategy: None,
+            ranked: false,
+            rules: None,
         };
         let req = test::TestRequest::post()
             .uri("/battles")
             .set_json(&battle_request)
             .to_request();
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
         
         assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
     }
 
     #[actix_rt::test]
     async fn test_should_create_a_battle_with_a_bad_request_response_if_one_parameter_is_null() {
-        let mut db = Database::new();
-        let test_monsters = init_test_monsters(&mut db).await;
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
 
-        let app = App::new().app_data(Data::new(db)).service(create_battle);
+        let app = App::new().configure(repositories(Arc::new(db))).service(create_battle);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let battle_request = CreateBattleRequest {
             monster_a: None,
             monster_b: Some(test_monsters[0].id.clone()),
+            monster_a_strategy: None,
+            monster_b_strategy: None,
+            ranked: false,
+            rules: None,
         };
         let req = test::TestRequest::post()
             .uri("/battles")
             .set_json(&battle_request)
             .to_request();
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
         
         assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
     }
 
     #[actix_rt::test]
     async fn test_should_create_battle_correctly_with_monster_a_winning() {
-        let mut db = Database::new();
-        let test_monsters = init_test_monsters(&mut db).await;
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
 
-        let app = App::new().app_data(Data::new(db)).service(create_battle);
+        let app = App::new().configure(repositories(Arc::new(db))).service(create_battle);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let battle_request = CreateBattleRequest {
             monster_a: Some(test_monsters[6].id.clone()),
             monster_b: Some(test_monsters[5].id.clone()),
+            monster_a_strategy: None,
+            monster_b_strategy: None,
+            ranked: false,
+            rules: None,
         };
         let req = test::TestRequest::post()
             .uri("/battles")
             .set_json(&battle_request)
             .to_request();
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
         let battle: Battle = test::read_body_json(resp).await;
 
-        assert_eq!(battle.monster_a, battle.winner);
+        assert_eq!(Some(battle.monster_a), battle.winner);
     }
 
     #[actix_rt::test]
     async fn test_should_create_battle_correctly_with_monster_b_winning_if_theirs_speeds_same_and_monster_b_has_higher_attack() {
-        let mut db = Database::new();
-        let test_monsters = init_test_monsters(&mut db).await;
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
 
-        let app = App::new().app_data(Data::new(db)).service(create_battle);
+        let app = App::new().configure(repositories(Arc::new(db))).service(create_battle);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let battle_request = CreateBattleRequest {
             monster_a: Some(test_monsters[4].id.clone()),
             monster_b: Some(test_monsters[3].id.clone()),
+            monster_a_strategy: None,
+            monster_b_strategy: None,
+            ranked: false,
+            rules: None,
         };
         let req = test::TestRequest::post()
             .uri("/battles")
             .set_json(&battle_request)
             .to_request();
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
         let battle: Battle = test::read_body_json(resp).await;
 
-        assert_eq!(battle.monster_b, battle.winner);
+        assert_eq!(Some(battle.monster_b), battle.winner);
     }
 
-}
+    #[actix_rt::test]
+    async fn test_should_create_and_expand_a_battle_without_a_database() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let new_monster = |name: &str, stats: Stats| Monster {
+            id: String::new(),
+            name: name.to_string(),
+            image_url: "https://loremflickr.com/640/480".to_string(),
+            stats,
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        };
+        let monster_a = repository.create_monster(new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 })).unwrap();
+        let monster_b = repository.create_monster(new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 50, speed: 80 })).unwrap();
+
+        let app = App::new().configure(repositories(repository)).service(create_battle).service(get_battle_by_id);
 
+        let app = test::init_service(app).await;
+
+        let battle_request = CreateBattleRequest {
+            monster_a: Some(monster_a.id.clone()),
+            monster_b: Some(monster_b.id.clone()),
+            monster_a_strategy: None,
+            monster_b_strategy: None,
+            ranked: false,
+            rules: None,
+        };
+        let req = test::TestRequest::post()
+            .uri("/battles")
+            .set_json(&battle_request)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let battle: Battle = test::read_body_json(resp).await;
+        assert_eq!(battle.winner, Some(monster_a.id));
+
+        let req = test::TestRequest::get().uri(&format!("/battles/{}?expand=monsters", battle.id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        let battle: ExpandedBattle = test::read_body_json(resp).await;
+        assert_eq!(battle.monster_b.map(|monster| monster.name), Some("monster-b".to_string()));
+    }
+
+    #[actix_rt::test]
+    async fn test_should_list_the_most_exciting_battles_first() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let new_monster = |name: &str, stats: Stats| Monster { id: String::new(), ..new_monster(name, stats) };
+        let monster_a = repository.create_monster(new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 })).unwrap();
+        let monster_b = repository.create_monster(new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 50, speed: 80 })).unwrap();
+        for highlight_score in [40, 0, 80] {
+            let result = BattleResult { winner: Some(monster_a.id.clone()), highlight_score, ..BattleResult::default() };
+            repository.create_battle(simulated_battle(monster_a.id.clone(), monster_b.id.clone(), result, None, &Default::default())).unwrap();
+        }
+        let app = App::new().configure(repositories(repository)).service(get_battle_highlights);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/battles/highlights").to_request();
+        let battles: Vec<Battle> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(battles.iter().map(|battle| battle.highlight_score).collect::<Vec<_>>(), vec![80, 40]);
+        let req = test::TestRequest::get().uri("/battles/highlights?limit=1").to_request();
+        let battles: Vec<Battle> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(battles.len(), 1);
+        let req = test::TestRequest::get().uri("/battles/highlights?days=31").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_play_a_battle_with_the_rules_of_the_request() {
+        let db = Arc::new(Database::new().unwrap());
+        let new_monster = |name: &str, stats: Stats| Monster { id: String::new(), ..new_monster(name, stats) };
+        let monster_a = db.create_monster(new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 })).unwrap();
+        let monster_b = db.create_monster(new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 50, speed: 80 })).unwrap();
+        let app = App::new().configure(repositories(db)).service(create_battle).service(get_rule_presets).service(get_battle_by_id);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/battles/rule-presets").to_request();
+        let presets: Vec<BattleRules> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(presets.iter().map(|rules| rules.preset.as_deref().unwrap()).collect::<Vec<_>>(), PRESETS);
+
+        let battle_request = |rules: serde_json::Value| serde_json::json!({ "monster_a": monster_a.id, "monster_b": monster_b.id, "rules": rules });
+        let req = test::TestRequest::post().uri("/battles").set_json(battle_request(serde_json::json!({ "preset": "rumble" }))).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        // Monster A knocks monster B out on turn 2 with the classic rules, but the battle is decided on turn 1 here.
+        let req = test::TestRequest::post().uri("/battles").set_json(battle_request(serde_json::json!({ "max_turns": 1, "monster_b_hp_percent": 1000 }))).to_request();
+        let battle: Battle = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(battle.winner, Some(monster_b.id.clone()));
+        let req = test::TestRequest::get().uri(&format!("/battles/{}", battle.id)).to_request();
+        let battle: Battle = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(battle.rules, Some(BattleRules { max_turns: Some(1), monster_b_hp_percent: 1000, ..BattleRules::default() }));
+
+        let rules = BattleRules { damage_floor: 10, ..BattleRules::default() };
+        let turns: Vec<TurnEvent> = ClassicEngine.turns(BattleSetup { rules: Some(rules), ..BattleSetup::new(monster_a, monster_b) }).collect();
+        assert_eq!((turns[0].damage, turns[0].defender_hp), (10, 90));
+    }
+
+    // Monster B wins every battle in a single turn.
+    struct UpsetEngine;
+
+    impl BattleEngine for UpsetEngine {
+        fn turns(&self, battle: BattleSetup<'_>) -> Box<dyn Iterator<Item = TurnEvent>> {
+            let turn = TurnEvent { turn: 1, attacker: battle.monster_b.id, defender: battle.monster_a.id, damage: battle.monster_a.stats.hp, ..TurnEvent::default() };
+            Box::new(std::iter::once(turn))
+        }
+
+        fn simulate(&self, battle: BattleSetup<'_>) -> BattleResult {
+            BattleResult { winner: Some(battle.monster_b.id), ..BattleResult::default() }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_play_battles_with_the_engine_of_the_app() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let monster_a = repository.create_monster(new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 })).unwrap();
+        let monster_b = repository.create_monster(new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 50, speed: 80 })).unwrap();
+        let engine: Arc<dyn BattleEngine> = Arc::new(UpsetEngine);
+        let app = App::new().configure(repositories(repository)).app_data(web::Data::from(engine)).service(create_battle);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::post().uri("/battles").set_json(serde_json::json!({ "monster_a": monster_a.id, "monster_b": monster_b.id })).to_request();
+        let battle: Battle = test::call_and_read_body_json(&app, req).await;
+
+        // The classic engine has monster A win.
+        assert_eq!(battle.winner, Some(monster_b.id));
+    }
+
+    #[actix_rt::test]
+    async fn test_should_grant_the_rewards_of_ranked_battles() {
+        use crate::models::reward::RewardKind;
+        use crate::rewards::{CurrencyStage, XpStage};
+
+        let repository = Arc::new(InMemoryRepository::new());
+        let new_monster = |name: &str, stats: Stats| Monster { id: String::new(), name: name.to_string(), image_url: String::new(), stats, created_at: None, updated_at: None, last_battle_at: None, level: 1, xp: 0, owner_id: None, image_broken: false, external_id: None, version: 1 };
+        let monster_a = repository.create_monster(new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 })).unwrap();
+        let monster_b = repository.create_monster(new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 50, speed: 80 })).unwrap();
+        let reward_pipeline = RewardPipeline::new(vec![Box::new(XpStage { base: 100 }), Box::new(CurrencyStage { win: 50, loss: 0 })]);
+        let app = App::new().configure(repositories(repository)).app_data(web::Data::new(reward_pipeline)).service(create_battle);
+        let app = test::init_service(app).await;
+
+        let battle_request = |ranked: bool| CreateBattleRequest { monster_a: Some(monster_a.id.clone()), monster_b: Some(monster_b.id.clone()), monster_a_strategy: None, monster_b_strategy: None, ranked, rules: None };
+        let req = test::TestRequest::post().uri("/battles").set_json(battle_request(false)).to_request();
+        let battle: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(battle.get("rewards").is_none());
+
+        let req = test::TestRequest::post().uri("/battles").set_json(battle_request(true)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let ranked: RankedBattle = test::read_body_json(resp).await;
+        assert_eq!(ranked.battle.winner, Some(monster_a.id.clone()));
+        let granted: Vec<_> = ranked.rewards.granted.into_iter().map(|reward| (reward.monster_id, reward.stage, reward.kind, reward.amount)).collect();
+        assert_eq!(granted, vec![
+            (monster_a.id.clone(), "xp".to_string(), RewardKind::Xp, 62),
+            (monster_b.id.clone(), "xp".to_string(), RewardKind::Xp, 25),
+            (monster_a.id.clone(), "currency".to_string(), RewardKind::Currency, 50),
+        ]);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_record_a_manual_draw_without_a_winner() {
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
+
+        let app = App::new().configure(repositories(Arc::new(db))).service(create_manual_battle);
+
+        let app = test::init_service(app).await;
+
+        let battle_request = CreateManualBattleRequest {
+            monster_a: Some(test_monsters[0].id.clone()),
+            monster_b: Some(test_monsters[1].id.clone()),
+            outcome: Some(ManualOutcome::Draw),
+        };
+        let req = test::TestRequest::post()
+            .uri("/battles/manual")
+            .set_json(&battle_request)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+
+        let battle: Battle = test::read_body_json(resp).await;
+        assert_eq!(battle.winner, None);
+        assert_eq!(battle.outcome, BattleOutcome::Draw);
+        assert!(battle.manual);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_give_a_manual_forfeit_to_monster_b() {
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
+
+        let app = App::new().configure(repositories(Arc::new(db))).service(create_manual_battle);
+
+        let app = test::init_service(app).await;
+
+        let battle_request = CreateManualBattleRequest {
+            monster_a: Some(test_monsters[0].id.clone()),
+            monster_b: Some(test_monsters[1].id.clone()),
+            outcome: Some(ManualOutcome::Forfeit),
+        };
+        let req = test::TestRequest::post()
+            .uri("/battles/manual")
+            .set_json(&battle_request)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let battle: Battle = test::read_body_json(resp).await;
+
+        assert_eq!(battle.winner, Some(test_monsters[1].id.clone()));
+        assert_eq!(battle.outcome, BattleOutcome::Forfeit);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_create_a_manual_battle_with_a_bad_request_response_if_it_is_invalid() {
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
+
+        let app = App::new().configure(repositories(Arc::new(db))).service(create_manual_battle);
+
+        let app = test::init_service(app).await;
+
+        let invalid_requests = [
+            CreateManualBattleRequest {
+                monster_a: Some(test_monsters[0].id.clone()),
+                monster_b: Some(test_monsters[1].id.clone()),
+                outcome: None,
+            },
+            CreateManualBattleRequest {
+                monster_a: Some(test_monsters[0].id.clone()),
+                monster_b: Some(test_monsters[0].id.clone()),
+                outcome: Some(ManualOutcome::Win),
+            },
+            CreateManualBattleRequest {
+                monster_a: Some("123".to_string()),
+                monster_b: Some(test_monsters[0].id.clone()),
+                outcome: Some(ManualOutcome::Loss),
+            },
+        ];
+        for battle_request in invalid_requests {
+            let req = test::TestRequest::post()
+                .uri("/battles/manual")
+                .set_json(&battle_request)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        }
+    }
+
+    fn new_monster(id: &str, stats: Stats) -> Monster {
+        Monster {
+            id: id.to_string(),
+            name: id.to_string(),
+            image_url: "https://loremflickr.com/640/480".to_string(),
+            stats,
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_stream_battle_turns_over_a_websocket() {
+        use futures::{SinkExt, StreamExt};
+
+        let repository = Arc::new(InMemoryRepository::new());
+        let monster_a = repository.create_monster(new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 })).unwrap();
+        let monster_b = repository.create_monster(new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 50, speed: 80 })).unwrap();
+
+        let server_repository = repository.clone();
+        let server = actix_test::start(move || App::new()
+            .configure(repositories(server_repository.clone()))
+            .app_data(web::Data::new(TurnDelay(Duration::ZERO)))
+            .service(battle_ws));
+        let (_, mut connection) = awc::Client::new().ws(server.url("/battles/ws")).connect().await.unwrap();
+
+        connection.send(awc::ws::Message::Text(r#"{"monster_a": "123"}"#.into())).await.unwrap();
+        let request = serde_json::to_string(&CreateBattleRequest { monster_a: Some(monster_a.id.clone()), monster_b: Some(monster_b.id.clone()), monster_a_strategy: None, monster_b_strategy: None, ranked: false, rules: None }).unwrap();
+        connection.send(awc::ws::Message::Text(request.into())).await.unwrap();
+
+        let mut messages = Vec::new();
+        while let Some(Ok(awc::ws::Frame::Text(text))) = connection.next().await {
+            messages.push(serde_json::from_slice::<BattleStreamMessage>(&text).unwrap());
+        }
+
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[0], BattleStreamMessage::Error { message: "Monster B id is required".to_string() });
+        assert!(matches!(&messages[1], BattleStreamMessage::Started { monster_a: started, .. } if *started == monster_a.id));
+        assert!(matches!(&messages[3], BattleStreamMessage::Turn(turn) if turn.defender_hp == 0));
+        let battle_id = match &messages[4] {
+            BattleStreamMessage::Finished { battle_id, winner } if *winner == monster_a.id => battle_id,
+            message => panic!("Unexpected message {:?}", message),
+        };
+        assert_eq!(repository.get_battle_by_id(battle_id).and_then(|battle| battle.winner), Some(monster_a.id.clone()));
+        let events = BATTLE_EVENTS.events_after(battle_id, 0).unwrap();
+        assert_eq!(events.into_iter().map(|event| event.message).collect::<Vec<_>>(), messages[1..]);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_resume_a_battle_on_a_new_websocket() {
+        use futures::{SinkExt, StreamExt};
+
+        let repository = Arc::new(InMemoryRepository::new());
+        let monster_a = repository.create_monster(new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 })).unwrap();
+        let monster_b = repository.create_monster(new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 50, speed: 80 })).unwrap();
+
+        let server_repository = repository.clone();
+        let server = actix_test::start(move || App::new()
+            .configure(repositories(server_repository.clone()))
+            .app_data(web::Data::new(TurnDelay(Duration::from_millis(50))))
+            .service(battle_ws));
+        let (_, mut connection) = awc::Client::new().ws(server.url("/battles/ws")).connect().await.unwrap();
+        let request = serde_json::json!({ "monster_a": monster_a.id, "monster_b": monster_b.id }).to_string();
+        connection.send(awc::ws::Message::Text(request.into())).await.unwrap();
+        let started = match connection.next().await {
+            Some(Ok(awc::ws::Frame::Text(text))) => serde_json::from_slice::<BattleSocketMessage>(&text).unwrap(),
+            frame => panic!("Unexpected frame {:?}", frame),
+        };
+        assert_eq!(started.event.cursor, 1);
+        drop(connection);
+
+        let (_, mut connection) = awc::Client::new().ws(server.url("/battles/ws")).connect().await.unwrap();
+        let request = serde_json::json!({ "resume_token": started.resume_token }).to_string();
+        connection.send(awc::ws::Message::Text(request.into())).await.unwrap();
+        let mut messages = Vec::new();
+        while let Some(Ok(awc::ws::Frame::Text(text))) = connection.next().await {
+            messages.push(serde_json::from_slice::<BattleSocketMessage>(&text).unwrap());
+        }
+
+        assert_eq!(messages.iter().map(|message| message.event.cursor).collect::<Vec<_>>(), vec![2, 3, 4]);
+        let battle_id = match &messages[2].event.message {
+            BattleStreamMessage::Finished { battle_id, winner } if *winner == monster_a.id => battle_id,
+            message => panic!("Unexpected message {:?}", message),
+        };
+        assert_eq!(messages[2].resume_token, format!("{}:4", battle_id));
+        assert!(repository.get_battle_by_id(battle_id).is_some());
+
+        let (_, mut connection) = awc::Client::new().ws(server.url("/battles/ws")).connect().await.unwrap();
+        connection.send(awc::ws::Message::Text(r#"{"resume_token": "123:1"}"#.into())).await.unwrap();
+        let message = match connection.next().await {
+            Some(Ok(awc::ws::Frame::Text(text))) => serde_json::from_slice::<BattleStreamMessage>(&text).unwrap(),
+            frame => panic!("Unexpected frame {:?}", frame),
+        };
+        assert_eq!(message, BattleStreamMessage::Error { message: "The battle can't be resumed anymore".to_string() });
+    }
+
+    #[actix_rt::test]
+    async fn test_should_replay_missed_battles_and_send_keepalives_on_the_stream() {
+        use actix_web::body::MessageBody;
+
+        use crate::repository::database::Database;
+
+        // The middle battle is stored in another schema, which the subscriber doesn't see.
+        let battles: Vec<Battle> = (0..3).map(|_| simulated_battle("monster-a".to_string(), "monster-b".to_string(), BattleResult::default(), None, &Default::default())).collect();
+        BATTLE_FEED.publish(battles[0].clone());
+        Database::with_schema("tenant_b".to_string(), async { BATTLE_FEED.publish(battles[1].clone()) }).await;
+        BATTLE_FEED.publish(battles[2].clone());
+        let app = App::new().app_data(web::Data::new(StreamHeartbeat(Duration::from_millis(20)))).service(stream_battles);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/battles/stream").insert_header((LAST_EVENT_ID_HEADER, battles[0].id.as_str())).to_request();
+        let resp = test::call_service(&app, req).await;
+        let mut events = std::pin::pin!(resp.into_body());
+        let mut next_event = async || String::from_utf8(futures::future::poll_fn(|cx| events.as_mut().poll_next(cx)).await.unwrap().unwrap().to_vec()).unwrap();
+
+        // Other tests publish battles too, skip their events.
+        loop {
+            let event = next_event().await;
+            assert!(!event.contains(&battles[0].id) && !event.contains(&battles[1].id));
+            if event.contains(&battles[2].id) {
+                assert!(event.starts_with(&format!("event: battle\nid: {}\n", battles[2].id)));
+                break;
+            }
+        }
+        while next_event().await != ": keepalive\n\n" {}
+    }
+
+    #[actix_rt::test]
+    async fn test_should_push_created_battles_to_the_stream() {
+        use actix_web::body::MessageBody;
+
+        let repository = Arc::new(InMemoryRepository::new());
+        let monster_a = repository.create_monster(new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 })).unwrap();
+        let monster_b = repository.create_monster(new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 50, speed: 80 })).unwrap();
+        let app = App::new().configure(repositories(repository)).service(create_battle).service(stream_battles);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/battles/stream").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "text/event-stream");
+        let mut events = std::pin::pin!(resp.into_body());
+
+        let battle_request = CreateBattleRequest { monster_a: Some(monster_a.id.clone()), monster_b: Some(monster_b.id.clone()), monster_a_strategy: None, monster_b_strategy: None, ranked: false, rules: None };
+        let req = test::TestRequest::post().uri("/battles").set_json(&battle_request).to_request();
+        let battle: Battle = test::call_and_read_body_json(&app, req).await;
+
+        // Other tests create battles too, skip their events.
+        loop {
+            let event = futures::future::poll_fn(|cx| events.as_mut().poll_next(cx)).await.unwrap().unwrap();
+            let event = String::from_utf8(event.to_vec()).unwrap();
+            if event.contains(&battle.id) {
+                assert!(event.starts_with(&format!("event: battle\nid: {}\ndata: {{", battle.id)));
+                break;
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_simulate_a_batch_of_battles_in_a_job() {
+        use crate::api::job_apis::get_job_by_id;
+        use crate::models::job::{Job, JobStatus};
+
+        let repository = Arc::new(InMemoryRepository::new());
+        let monster_a = repository.create_monster(new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 })).unwrap();
+        let monster_b = repository.create_monster(new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 50, speed: 80 })).unwrap();
+
+        let app = App::new()
+            .configure(repositories(repository.clone()))
+            .app_data(web::Data::new(JobQueue::new(repository.clone(), 1)))
+            .service(create_battles_batch)
+            .service(get_job_by_id);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::post()
+            .uri("/battles/batch")
+            .set_json(serde_json::json!({ "battles": [
+                { "monster_a": monster_a.id, "monster_b": monster_b.id },
+                { "monster_a": monster_a.id, "monster_b": "123" },
+            ] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::ACCEPTED);
+        let job: Job = test::read_body_json(resp).await;
+        assert_eq!(job.total, 2);
+
+        let mut job = job;
+        for _ in 0..100 {
+            if matches!(job.status, JobStatus::Succeeded | JobStatus::Failed) {
+                break;
+            }
+            actix_rt::time::sleep(Duration::from_millis(20)).await;
+            let req = test::TestRequest::get().uri(&format!("/jobs/{}", job.id)).to_request();
+            job = test::call_and_read_body_json(&app, req).await;
+        }
+
+        assert_eq!(job.status, JobStatus::Succeeded);
+        assert_eq!((job.processed, job.failed), (2, 1));
+        let battle_id = job.result.unwrap()["battles"][0].as_str().unwrap().to_string();
+        assert!(repository.get_battle_by_id(&battle_id).is_some());
+
+        let req = test::TestRequest::post().uri("/battles/batch").set_json(serde_json::json!({ "battles": [] })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_long_poll_the_events_of_a_battle() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let app = App::new().configure(repositories(repository)).service(get_battle_events);
+        let app = test::init_service(app).await;
+        let battle_id = uuid::Uuid::new_v4().to_string();
+        BATTLE_EVENTS.publish(&battle_id, BattleStreamMessage::Started { battle_id: battle_id.clone(), monster_a: "a".to_string(), monster_b: "b".to_string() });
+
+        // Waits for the finished event published while the request is pending.
+        let publisher_battle_id = battle_id.clone();
+        actix_rt::spawn(async move {
+            actix_rt::time::sleep(Duration::from_millis(50)).await;
+            BATTLE_EVENTS.publish(&publisher_battle_id, BattleStreamMessage::Finished { battle_id: publisher_battle_id.clone(), winner: "a".to_string() });
+        });
+        let req = test::TestRequest::get().uri(&format!("/battles/{}/events?after=1&wait=5s", battle_id)).to_request();
+        let page: BattleEventsPage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((page.events.len(), page.cursor, page.finished), (1, 2, true));
+        assert!(matches!(&page.events[0].message, BattleStreamMessage::Finished { winner, .. } if winner == "a"));
+
+        let req = test::TestRequest::get().uri(&format!("/battles/{}/events?after=2&wait=10ms", battle_id)).to_request();
+        let page: BattleEventsPage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((page.events.len(), page.cursor, page.finished), (0, 2, true));
+
+        let req = test::TestRequest::get().uri("/battles/123/events?wait=10ms").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::get().uri(&format!("/battles/{}/events?wait=2m", battle_id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_parse_the_long_polling_wait() {
+        assert_eq!(parse_wait("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_wait("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_wait("5"), Ok(Duration::from_secs(5)));
+        assert!(parse_wait("61s").is_err());
+        assert!(parse_wait("soon").is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_should_play_a_series_until_a_monster_wins_the_majority() {
+        let monster_a = new_monster("monster-a", Stats { attack: 50, defense: 20, hp: 100, speed: 50 });
+        let monster_b = new_monster("monster-b", Stats { attack: 50, defense: 20, hp: 100, speed: 50 });
+
+        let (series, games) = play_series(&ClassicEngine, monster_a.clone(), monster_b.clone(), Default::default(), Default::default(), Default::default(), Default::default(), 5, 42, None);
+        let winner_wins = games.iter().filter(|game| game.battle.winner.as_ref() == Some(&series.winner)).count();
+        assert_eq!(winner_wins, 3);
+        assert!(games.len() >= 3 && games.len() <= 5);
+        assert_eq!(games.last().unwrap().battle.winner, Some(series.winner.clone()));
+
+        let (replayed, replayed_games) = play_series(&ClassicEngine, monster_a, monster_b, Default::default(), Default::default(), Default::default(), Default::default(), 5, 42, None);
+        assert_eq!(replayed.winner, series.winner);
+        assert_eq!(replayed_games.iter().map(|game| game.seed).collect::<Vec<_>>(), games.iter().map(|game| game.seed).collect::<Vec<_>>());
+    }
+
+    #[actix_rt::test]
+    async fn test_should_create_and_get_a_battle_series() {
+        use crate::models::series::ExpandedBattleSeries;
+
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
+        let app = App::new().configure(repositories(Arc::new(db))).service(create_series).service(get_series_by_id);
+        let app = test::init_service(app).await;
+
+        let series_request = CreateSeriesRequest {
+            monster_a: Some(test_monsters[0].id.clone()),
+            monster_b: Some(test_monsters[1].id.clone()),
+            best_of: Some(5),
+            seed: Some(42),
+            monster_a_strategy: Some(Strategy::Defensive),
+            monster_b_strategy: None,
+        };
+        let req = test::TestRequest::post().uri("/battles/series").set_json(&series_request).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let series: ExpandedBattleSeries = test::read_body_json(resp).await;
+        assert!(series.games.len() >= 3 && series.games.len() <= 5);
+
+        let req = test::TestRequest::get().uri(&format!("/battles/series/{}", series.series.id)).to_request();
+        let stored: ExpandedBattleSeries = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(stored.series.winner, series.series.winner);
+        assert_eq!(
+            stored.games.iter().map(|game| (game.game, game.seed, game.battle.id.clone())).collect::<Vec<_>>(),
+            series.games.iter().map(|game| (game.game, game.seed, game.battle.id.clone())).collect::<Vec<_>>()
+        );
+
+        let req = test::TestRequest::post()
+            .uri("/battles/series")
+            .set_json(&CreateSeriesRequest { best_of: Some(4), ..series_request })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_stream_bulk_battle_results_as_ndjson() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let monster_a = repository.create_monster(new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 })).unwrap();
+        let monster_b = repository.create_monster(new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 50, speed: 80 })).unwrap();
+        let app = App::new().configure(repositories(repository.clone())).service(create_battles_bulk);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::post()
+            .uri("/battles/bulk")
+            .set_json(serde_json::json!({ "battles": [
+                { "monster_a": monster_a.id, "monster_b": monster_b.id },
+                { "monster_a": monster_a.id },
+                { "monster_a": monster_b.id, "monster_b": monster_a.id },
+            ] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "application/x-ndjson");
+
+        let body = test::read_body(resp).await;
+        let mut results: Vec<BulkBattleResult> = std::str::from_utf8(&body)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        results.sort_by_key(|result| result.index);
+
+        assert_eq!(results.iter().map(|result| result.status.as_str()).collect::<Vec<_>>(), ["created", "failed", "created"]);
+        assert_eq!(results[1].error.as_deref(), Some("Monster B id is required"));
+        let battle_id = &results[2].battle.as_ref().unwrap().id;
+        assert!(repository.get_battle_by_id(battle_id).is_some());
+    }
+}
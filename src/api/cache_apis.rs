@@ -0,0 +1,61 @@
+use std::time::Instant;
+use actix_web::{web, post, HttpResponse};
+use serde::{Deserialize, Serialize};
+use crate::repository::battle_repository::BattleRepository;
+use crate::repository::monster_repository::MonsterRepository;
+use crate::repository::move_repository::MoveRepository;
+use super::analytics_apis::{build_meta_snapshot, MetaCache, DEFAULT_META_WINDOW_DAYS};
+
+// Manual filters of the meta snapshots warmed, all battles, manual ones and simulated ones.
+const WARMED_META_FILTERS: [Option<bool>; 3] = [None, Some(true), Some(false)];
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WarmupReport {
+    pub meta_snapshots: usize,
+    pub duration_ms: u128,
+}
+
+/*
+Fills the caches a fresh instance would otherwise fill on its first requests, all of them hitting the
+database at once: the meta snapshots of the default window, whose most used monsters are the leaderboard
+and featured lists of the frontend. Runs before the server starts listening and on `POST /admin/cache/warm`.
+*/
+pub fn warm_caches(battle_repository: &dyn BattleRepository, monster_repository: &dyn MonsterRepository, move_repository: &dyn MoveRepository, meta_cache: &MetaCache) -> WarmupReport {
+    let start = Instant::now();
+    for manual in WARMED_META_FILTERS {
+        meta_cache.set(build_meta_snapshot(battle_repository, monster_repository, move_repository, DEFAULT_META_WINDOW_DAYS, manual));
+    }
+    WarmupReport { meta_snapshots: WARMED_META_FILTERS.len(), duration_ms: start.elapsed().as_millis() }
+}
+
+#[post("/admin/cache/warm")]
+pub async fn warm(battle_repository: web::Data<dyn BattleRepository>, monster_repository: web::Data<dyn MonsterRepository>, move_repository: web::Data<dyn MoveRepository>, meta_cache: web::Data<MetaCache>) -> HttpResponse {
+    HttpResponse::Ok().json(warm_caches(battle_repository.as_ref(), monster_repository.as_ref(), move_repository.as_ref(), &meta_cache))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use crate::api::config::repositories;
+    use crate::repository::memory_repository::InMemoryRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_warm_the_meta_snapshots() {
+        let meta_cache = web::Data::new(MetaCache::new(chrono::Duration::minutes(5)));
+        let app = App::new().configure(repositories(Arc::new(InMemoryRepository::new()))).app_data(meta_cache.clone()).service(warm);
+        let app = test::init_service(app).await;
+        assert!(meta_cache.get(DEFAULT_META_WINDOW_DAYS, None).is_none());
+
+        let req = test::TestRequest::post().uri("/admin/cache/warm").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let report: WarmupReport = test::read_body_json(resp).await;
+        assert_eq!(report.meta_snapshots, 3);
+        for manual in WARMED_META_FILTERS {
+            assert!(meta_cache.get(DEFAULT_META_WINDOW_DAYS, manual).is_some());
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use actix_web::{web, get, post, HttpResponse};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use crate::battle_engine::{BattleEngine, BattleSetup};
+use crate::models::battle::Battle;
+use crate::models::challenge::{leaderboard, pick_daily_challenge, ChallengeStanding, DailyChallenge, LEADERBOARD_SIZE};
+use crate::models::decay::StatDecay;
+use crate::models::monster::Monster;
+use crate::models::rules::BattleRules;
+use crate::models::status_effect::StatusEffectRules;
+use crate::repository::battle_repository::BattleRepository;
+use crate::repository::challenge_repository::ChallengeRepository;
+use crate::repository::error::RepositoryError;
+use crate::repository::item_repository::ItemRepository;
+use crate::repository::monster_repository::MonsterRepository;
+use crate::repository::move_repository::MoveRepository;
+use crate::repository::training_repository::TrainingRepository;
+use super::battle_apis::{battle_engine, find_monsters, monster_hidden_stats, monster_items, monster_moves, new_simulated_battle, store_battle};
+use super::error::repository_error_response;
+
+#[derive(Serialize, Deserialize)]
+pub struct DailyChallengeResponse {
+    #[serde(flatten)]
+    pub challenge: DailyChallenge,
+    #[serde(rename = "opponentMonster")]
+    pub opponent_monster: Monster,
+    pub rules: BattleRules,
+    pub leaderboard: Vec<ChallengeStanding>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChallengeAttemptRequest {
+    challenger: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChallengeAttemptResponse {
+    pub battle: Battle,
+    pub won: bool,
+    // The challenger on the leaderboard of the day after this attempt.
+    pub standing: ChallengeStanding,
+}
+
+// The challenge of today, picked and stored on the first request of the day. None while there are no monsters.
+fn todays_challenge(monster_repository: &dyn MonsterRepository, challenge_repository: &dyn ChallengeRepository) -> Result<Option<(DailyChallenge, Monster)>, RepositoryError> {
+    let now = Utc::now().naive_utc();
+    let challenge = match challenge_repository.get_daily_challenge(now.date()) {
+        Some(challenge) => challenge,
+        None => {
+            let monster_ids: Vec<String> = monster_repository.get_monsters().into_iter().map(|monster| monster.id).collect();
+            let Some(challenge) = pick_daily_challenge(now.date(), &monster_ids, now) else { return Ok(None) };
+            challenge_repository.save_daily_challenge(challenge)?
+        }
+    };
+    Ok(monster_repository.get_monster_by_id(&challenge.opponent).map(|opponent| (challenge, opponent)))
+}
+
+fn challenge_rules(challenge: &DailyChallenge) -> BattleRules {
+    BattleRules::preset(&challenge.preset).unwrap_or_default()
+}
+
+/*
+The featured opponent and rule preset of the day, the same for every client until midnight UTC, with the
+challengers that beat it most.
+*/
+#[get("/challenges/today")]
+pub async fn get_todays_challenge(monster_repository: web::Data<dyn MonsterRepository>, challenge_repository: web::Data<dyn ChallengeRepository>) -> HttpResponse {
+    match todays_challenge(monster_repository.as_ref(), challenge_repository.as_ref()) {
+        Ok(Some((challenge, opponent_monster))) => {
+            let mut leaderboard = leaderboard(challenge_repository.get_challenge_attempts(challenge.challenge_date));
+            leaderboard.truncate(LEADERBOARD_SIZE);
+            let rules = challenge_rules(&challenge);
+            HttpResponse::Ok().json(DailyChallengeResponse { challenge, opponent_monster, rules, leaderboard })
+        }
+        Ok(None) => HttpResponse::NotFound().json("No monsters to challenge today"),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+// Fights the opponent of the day with the challenger, storing the battle and counting the attempt on the leaderboard.
+#[post("/challenges/today/attempt")]
+#[allow(clippy::too_many_arguments)]
+pub async fn attempt_todays_challenge(
+    monster_repository: web::Data<dyn MonsterRepository>,
+    battle_repository: web::Data<dyn BattleRepository>,
+    move_repository: web::Data<dyn MoveRepository>,
+    item_repository: web::Data<dyn ItemRepository>,
+    training_repository: web::Data<dyn TrainingRepository>,
+    challenge_repository: web::Data<dyn ChallengeRepository>,
+    decay: Option<web::Data<StatDecay>>,
+    status_effects: Option<web::Data<StatusEffectRules>>,
+    engine: Option<web::Data<dyn BattleEngine>>,
+    request: web::Json<ChallengeAttemptRequest>,
+) -> HttpResponse {
+    let (challenge, _) = match todays_challenge(monster_repository.as_ref(), challenge_repository.as_ref()) {
+        Ok(Some(challenge)) => challenge,
+        Ok(None) => return HttpResponse::NotFound().json("No monsters to challenge today"),
+        Err(err) => return repository_error_response(&err),
+    };
+    if request.challenger.as_ref() == Some(&challenge.opponent) {
+        return HttpResponse::BadRequest().json("The opponent of the day cannot challenge itself");
+    }
+    let (challenger, opponent) = match find_monsters(monster_repository.as_ref(), &request.challenger, &Some(challenge.opponent.clone())) {
+        Ok(monsters) => monsters,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+
+    let moves = monster_moves(move_repository.as_ref(), &challenger, &opponent);
+    let items = monster_items(item_repository.as_ref(), &challenger, &opponent);
+    let hidden_stats = monster_hidden_stats(training_repository.as_ref(), &challenger, &opponent);
+    let challenger_id = challenger.id.clone();
+    let setup = BattleSetup {
+        moves,
+        items,
+        hidden_stats,
+        status_effects: status_effects.as_ref().map(|status_effects| status_effects.get_ref()),
+        rules: Some(challenge_rules(&challenge)),
+        ..BattleSetup::new(challenger, opponent)
+    };
+    let battle = new_simulated_battle(battle_engine(engine).as_ref(), setup, decay.as_ref().map(|decay| decay.get_ref()));
+    let battle = match store_battle(battle_repository.as_ref(), battle) {
+        Ok(battle) => battle,
+        Err(err) => return repository_error_response(&err),
+    };
+    let won = battle.winner.as_ref() == Some(&challenger_id);
+    if let Err(err) = challenge_repository.record_challenge_attempt(challenge.challenge_date, &challenger_id, won) {
+        return repository_error_response(&err);
+    }
+    let standing = leaderboard(challenge_repository.get_challenge_attempts(challenge.challenge_date))
+        .into_iter()
+        .find(|standing| standing.attempts.monster_id == challenger_id)
+        .expect("The attempt was just recorded");
+    HttpResponse::Created().json(ChallengeAttemptResponse { battle, won, standing })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use serde_json::json;
+    use crate::api::config::repositories;
+    use crate::models::monster::Stats;
+    use crate::repository::memory_repository::InMemoryRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_fight_the_challenge_of_the_day_and_rank_the_challengers() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let app = App::new().configure(repositories(repository.clone())).service(get_todays_challenge).service(attempt_todays_challenge);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/challenges/today").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::NOT_FOUND);
+
+        let monsters: Vec<Monster> = (0..3)
+            .map(|index| repository.create_monster(Monster {
+                id: String::new(),
+                name: format!("challenger-{}", index),
+                image_url: "https://loremflickr.com/640/480".to_string(),
+                stats: Stats { attack: 40 + index * 20, defense: 20, hp: 50, speed: 80 },
+                created_at: None,
+                updated_at: None,
+                last_battle_at: None,
+                level: 1,
+                xp: 0,
+                owner_id: None,
+                image_broken: false,
+                external_id: None,
+                version: 1,
+            }).unwrap())
+            .collect();
+        let req = test::TestRequest::get().uri("/challenges/today").to_request();
+        let challenge: DailyChallengeResponse = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(challenge.challenge.challenge_date, Utc::now().date_naive());
+        assert_eq!(challenge.opponent_monster.id, challenge.challenge.opponent);
+        assert_eq!(challenge.rules.preset.as_ref(), Some(&challenge.challenge.preset));
+        assert!(challenge.leaderboard.is_empty());
+
+        let req = test::TestRequest::post().uri("/challenges/today/attempt").set_json(json!({ "challenger": challenge.challenge.opponent })).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::BAD_REQUEST);
+        let challengers: Vec<&Monster> = monsters.iter().filter(|monster| monster.id != challenge.challenge.opponent).collect();
+        for challenger in [challengers[0], challengers[0], challengers[1]] {
+            let req = test::TestRequest::post().uri("/challenges/today/attempt").set_json(json!({ "challenger": challenger.id })).to_request();
+            let attempt: ChallengeAttemptResponse = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(attempt.battle.monster_b, challenge.challenge.opponent);
+            assert_eq!(attempt.battle.rules.as_ref().and_then(|rules| rules.preset.as_ref()), Some(&challenge.challenge.preset));
+            assert_eq!(attempt.standing.attempts.monster_id, challenger.id);
+        }
+
+        let req = test::TestRequest::get().uri("/challenges/today").to_request();
+        let again: DailyChallengeResponse = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(again.challenge.opponent, challenge.challenge.opponent);
+        assert_eq!(again.leaderboard.iter().map(|standing| standing.rank).collect::<Vec<_>>(), vec![1, 2]);
+        let first = again.leaderboard.iter().find(|standing| standing.attempts.monster_id == challengers[0].id).unwrap();
+        assert_eq!(first.attempts.attempts, 2);
+    }
+}
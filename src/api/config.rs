@@ -1,19 +1,251 @@
-use actix_web::web;
-use super::monster_apis::{get_monsters, get_monster_by_id, create_monster, update_monster_by_id, delete_monster_by_id, import_csv};
-use super::battle_apis::{get_battles, get_battle_by_id, delete_battle_by_id, create_battle};
+use std::sync::{Arc, LazyLock};
+use actix_web::{http::Method, web};
+use crate::repository::backup_repository::BackupRepository;
+use crate::repository::battle_repository::BattleRepository;
+use crate::repository::evolution_repository::EvolutionRepository;
+use crate::repository::export_repository::ExportRepository;
+use crate::repository::idempotency_repository::IdempotencyRepository;
+use crate::repository::item_repository::ItemRepository;
+use crate::repository::job_repository::JobRepository;
+use crate::repository::monster_repository::MonsterRepository;
+use crate::repository::move_repository::MoveRepository;
+use crate::repository::note_repository::NoteRepository;
+use crate::repository::rate_limit_repository::RateLimitRepository;
+use crate::repository::season_repository::SeasonRepository;
+use crate::repository::trainer_repository::TrainerRepository;
+use crate::repository::achievement_repository::AchievementRepository;
+use crate::repository::challenge_repository::ChallengeRepository;
+use crate::repository::cors_repository::CorsRepository;
+use crate::repository::db_health_repository::DbHealthRepository;
+use crate::repository::thumbnail_repository::ThumbnailRepository;
+use crate::repository::training_repository::TrainingRepository;
+use crate::repository::webhook_repository::WebhookRepository;
+use super::monster_apis::{get_monsters, get_monster_by_id, create_monster, update_monster_by_id, delete_monster_by_id, import_csv, import_json, bulk_create_monsters, bulk_delete_monsters, get_similar_monsters, search_monsters, get_monster_card, scan_duplicate_monsters, matchmake, level_up_monster};
+use super::battle_apis::{get_battles, get_battle_by_id, delete_battle_by_id, create_battle, create_manual_battle, create_battles_batch, create_battles_bulk, battle_ws, stream_battles, get_battle_events, create_series, get_series_by_id, get_rule_presets, get_battle_highlights};
+use super::analytics_apis::get_meta;
+use super::leaderboard_apis::get_leaderboard;
+use super::region_apis::get_regions;
+use super::audit_apis::get_audit_entries;
+use super::cache_apis::warm;
+use super::balance_apis::reload_balance;
+use super::backup_apis::{get_backup, restore_backup};
+use super::admin_apis::{reset_data, seed_data};
+use super::evolution_apis::{get_evolutions, save_evolution, delete_evolution, evolve_monster};
+use super::export_apis::{create_export, get_export_page};
+use super::graphql_apis::{self, graphql, graphql_playground};
+use super::idempotency::ReplayIdempotentRequests;
+use super::image_apis::{upload_monster_image, get_monster_image, get_monster_thumbnail};
+use super::item_apis::{get_items, get_item_by_id, create_item, update_item_by_id, delete_item_by_id, get_monster_items, set_monster_items};
+use super::interactive_battle_apis::{create_interactive_battle, get_interactive_battle_by_id, submit_turn};
+use super::job_apis::{get_job_by_id, get_import_by_id};
+use super::rate_limit_apis::{get_rate_limit_tiers, save_rate_limit_tier, delete_rate_limit_tier, get_api_keys, create_api_key, delete_api_key_by_id};
+use super::performance_apis::get_slow_routes;
+use super::note_apis::{get_monster_notes, create_monster_note, get_notes, update_note_by_id, delete_note_by_id};
+use super::move_apis::{get_moves, get_move_by_id, create_move, update_move_by_id, delete_move_by_id, get_monster_moves, set_monster_moves};
+use super::trainer_apis::{create_trainer, get_current_trainer, get_trainer_by_id, get_trainer_monsters, transfer_monster, get_trades, create_trade, accept_trade, decline_trade};
+use super::db_health_apis::get_db_health;
+use super::challenge_apis::{get_todays_challenge, attempt_todays_challenge};
+use super::cors_apis::{get_cors_origins, add_cors_origin, delete_cors_origin};
+use super::achievement_apis::{get_monster_achievements, get_trainer_achievements};
+use super::training_apis::{get_monster_training, allocate_training_points, set_individual_values};
+use super::season_apis::{get_seasons, open_season, get_current_season, get_season_by_id, close_season, get_season_standings};
+use super::routes::{enforce_route_metadata, get_openapi, CachePolicy, RateLimitClass, Route};
+use super::webhook_apis::{get_webhooks, get_webhook_by_id, create_webhook, update_webhook_by_id, delete_webhook_by_id};
+
+pub const API_PREFIX: &str = "/api";
+// The meta snapshot changes slowly, clients may reuse it for a minute.
+const META_MAX_AGE_SECONDS: u32 = 60;
+// Cards are only drawn again when their monster changes, shares may reuse them for a few minutes.
+const CARD_MAX_AGE_SECONDS: u32 = 300;
+
+macro_rules! route {
+    ($method:ident $path:literal => $handler:ident) => {
+        Route::new(Method::$method, $path, |cfg| { cfg.service($handler); })
+    };
+}
+
+/*
+Every /api route with its metadata, in registration order. Literal paths like /monsters/bulk come before
+the /monsters/{id} patterns they would otherwise be matched by, whatever their method: requests are
+resolved to a route by their path alone.
+*/
+pub static ROUTES: LazyLock<Vec<Route>> = LazyLock::new(|| vec![
+    route!(GET "/monsters" => get_monsters).cache(CachePolicy::NoStore).tags(&["monsters"]),
+    route!(POST "/monsters" => create_monster).tags(&["monsters"]),
+    route!(POST "/monsters/bulk" => bulk_create_monsters).rate_limit(RateLimitClass::Expensive).tags(&["monsters"]),
+    route!(DELETE "/monsters/bulk" => bulk_delete_monsters).rate_limit(RateLimitClass::Expensive).tags(&["monsters"]),
+    route!(GET "/monsters/similar/{id}" => get_similar_monsters).tags(&["monsters"]),
+    route!(GET "/monsters/search" => search_monsters).budget(500).tags(&["monsters"]),
+    route!(POST "/monsters/duplicates/scan" => scan_duplicate_monsters).admin().rate_limit(RateLimitClass::Expensive).tags(&["admin", "monsters", "jobs"]),
+    route!(POST "/monsters/import_csv" => import_csv).rate_limit(RateLimitClass::Expensive).tags(&["monsters", "imports"]),
+    route!(POST "/monsters/import_json" => import_json).rate_limit(RateLimitClass::Expensive).tags(&["monsters", "imports"]),
+    route!(GET "/monsters/{id}" => get_monster_by_id).tags(&["monsters"]),
+    route!(GET "/monsters/{id}/matchmake" => matchmake).cache(CachePolicy::NoStore).tags(&["monsters", "battles"]),
+    route!(POST "/monsters/{id}/level_up" => level_up_monster).admin().tags(&["admin", "monsters"]),
+    route!(POST "/monsters/{id}/evolve" => evolve_monster).tags(&["monsters", "evolutions"]),
+    route!(GET "/monsters/{id}/card.png" => get_monster_card).rate_limit(RateLimitClass::Expensive).cache(CachePolicy::MaxAge(CARD_MAX_AGE_SECONDS)).tags(&["monsters"]),
+    route!(POST "/monsters/{id}/image" => upload_monster_image).rate_limit(RateLimitClass::Expensive).tags(&["monsters"]),
+    route!(GET "/monsters/{id}/image" => get_monster_image).tags(&["monsters"]),
+    route!(GET "/monsters/{id}/thumbnail" => get_monster_thumbnail).tags(&["monsters"]),
+    route!(DELETE "/monsters/{id}" => delete_monster_by_id).tags(&["monsters"]),
+    route!(PUT "/monsters/{id}" => update_monster_by_id).tags(&["monsters"]),
+    route!(GET "/monsters/{id}/moves" => get_monster_moves).tags(&["monsters", "moves"]),
+    route!(PUT "/monsters/{id}/moves" => set_monster_moves).tags(&["monsters", "moves"]),
+    route!(GET "/monsters/{id}/items" => get_monster_items).tags(&["monsters", "items"]),
+    route!(PUT "/monsters/{id}/items" => set_monster_items).tags(&["monsters", "items"]),
+    route!(GET "/monsters/{id}/notes" => get_monster_notes).cache(CachePolicy::NoStore).tags(&["monsters", "notes"]),
+    route!(POST "/monsters/{id}/notes" => create_monster_note).tags(&["monsters", "notes"]),
+    route!(GET "/monsters/{id}/training" => get_monster_training).tags(&["monsters", "training"]),
+    route!(POST "/monsters/{id}/training/allocate" => allocate_training_points).tags(&["monsters", "training"]),
+    route!(PUT "/monsters/{id}/training/individual_values" => set_individual_values).admin().tags(&["admin", "monsters", "training"]),
+    route!(POST "/monsters/{id}/transfer" => transfer_monster).tags(&["monsters", "trainers"]),
+    route!(GET "/monsters/{id}/achievements" => get_monster_achievements).tags(&["monsters", "achievements"]),
+    route!(POST "/trainers" => create_trainer).tags(&["trainers"]),
+    route!(GET "/trainers/me" => get_current_trainer).cache(CachePolicy::NoStore).tags(&["trainers"]),
+    route!(GET "/trainers/{id}" => get_trainer_by_id).tags(&["trainers"]),
+    route!(GET "/trainers/{id}/monsters" => get_trainer_monsters).tags(&["trainers", "monsters"]),
+    route!(GET "/trainers/{id}/achievements" => get_trainer_achievements).tags(&["trainers", "achievements"]),
+    route!(GET "/trades" => get_trades).cache(CachePolicy::NoStore).tags(&["trainers", "trades"]),
+    route!(POST "/trades" => create_trade).tags(&["trainers", "trades"]),
+    route!(POST "/trades/{id}/accept" => accept_trade).tags(&["trainers", "trades"]),
+    route!(POST "/trades/{id}/decline" => decline_trade).tags(&["trainers", "trades"]),
+    route!(GET "/evolutions" => get_evolutions).tags(&["evolutions"]),
+    route!(PUT "/evolutions/{species}" => save_evolution).admin().tags(&["admin", "evolutions"]),
+    route!(DELETE "/evolutions/{species}" => delete_evolution).admin().tags(&["admin", "evolutions"]),
+    route!(GET "/items" => get_items).tags(&["items"]),
+    route!(POST "/items" => create_item).tags(&["items"]),
+    route!(GET "/items/{id}" => get_item_by_id).tags(&["items"]),
+    route!(PUT "/items/{id}" => update_item_by_id).tags(&["items"]),
+    route!(DELETE "/items/{id}" => delete_item_by_id).tags(&["items"]),
+    route!(GET "/notes" => get_notes).cache(CachePolicy::NoStore).tags(&["notes"]),
+    route!(PUT "/notes/{id}" => update_note_by_id).tags(&["notes"]),
+    route!(DELETE "/notes/{id}" => delete_note_by_id).tags(&["notes"]),
+    route!(GET "/moves" => get_moves).tags(&["moves"]),
+    route!(POST "/moves" => create_move).tags(&["moves"]),
+    route!(GET "/moves/{id}" => get_move_by_id).tags(&["moves"]),
+    route!(PUT "/moves/{id}" => update_move_by_id).tags(&["moves"]),
+    route!(DELETE "/moves/{id}" => delete_move_by_id).tags(&["moves"]),
+    route!(GET "/battles" => get_battles).tags(&["battles"]),
+    route!(POST "/battles" => create_battle).tags(&["battles"]),
+    route!(POST "/battles/manual" => create_manual_battle).tags(&["battles"]),
+    route!(GET "/battles/ws" => battle_ws).rate_limit(RateLimitClass::Streaming).tags(&["battles", "streaming"]),
+    route!(GET "/battles/stream" => stream_battles).rate_limit(RateLimitClass::Streaming).tags(&["battles", "streaming"]),
+    route!(POST "/battles/batch" => create_battles_batch).rate_limit(RateLimitClass::Expensive).tags(&["battles", "jobs"]),
+    route!(POST "/battles/bulk" => create_battles_bulk).rate_limit(RateLimitClass::Expensive).tags(&["battles", "streaming"]),
+    route!(POST "/battles/series" => create_series).rate_limit(RateLimitClass::Expensive).tags(&["battles"]),
+    route!(GET "/battles/series/{id}" => get_series_by_id).tags(&["battles"]),
+    route!(GET "/battles/rule-presets" => get_rule_presets).tags(&["battles"]),
+    route!(GET "/battles/highlights" => get_battle_highlights).tags(&["battles"]),
+    route!(POST "/battles/interactive" => create_interactive_battle).tags(&["battles"]),
+    route!(GET "/battles/interactive/{id}" => get_interactive_battle_by_id).cache(CachePolicy::NoStore).tags(&["battles"]),
+    route!(POST "/battles/{id}/turns" => submit_turn).tags(&["battles"]),
+    route!(GET "/battles/{id}/events" => get_battle_events).rate_limit(RateLimitClass::Streaming).cache(CachePolicy::NoStore).tags(&["battles", "streaming"]),
+    route!(GET "/battles/{id}" => get_battle_by_id).tags(&["battles"]),
+    route!(DELETE "/battles/{id}" => delete_battle_by_id).tags(&["battles"]),
+    route!(GET "/challenges/today" => get_todays_challenge).cache(CachePolicy::NoStore).tags(&["challenges"]),
+    route!(POST "/challenges/today/attempt" => attempt_todays_challenge).tags(&["challenges", "battles"]),
+    route!(GET "/seasons" => get_seasons).tags(&["seasons"]),
+    route!(POST "/seasons" => open_season).admin().tags(&["admin", "seasons"]),
+    route!(GET "/seasons/current" => get_current_season).tags(&["seasons"]),
+    route!(GET "/seasons/{id}" => get_season_by_id).tags(&["seasons"]),
+    route!(POST "/seasons/{id}/close" => close_season).admin().tags(&["admin", "seasons"]),
+    route!(GET "/seasons/{id}/standings" => get_season_standings).cache(CachePolicy::NoStore).tags(&["seasons"]),
+    route!(POST "/exports" => create_export).rate_limit(RateLimitClass::Expensive).tags(&["exports"]),
+    route!(GET "/exports/{id}" => get_export_page).cache(CachePolicy::NoStore).tags(&["exports"]),
+    route!(GET "/leaderboard" => get_leaderboard).budget(1000).tags(&["analytics"]),
+    route!(GET "/regions" => get_regions).tags(&["regions"]),
+    route!(GET "/analytics/meta" => get_meta).cache(CachePolicy::MaxAge(META_MAX_AGE_SECONDS)).budget(1000).tags(&["analytics"]),
+    route!(GET "/audit" => get_audit_entries).admin().tags(&["admin"]),
+    route!(POST "/admin/cache/warm" => warm).admin().rate_limit(RateLimitClass::Expensive).tags(&["admin"]),
+    route!(POST "/admin/balance/reload" => reload_balance).admin().tags(&["admin"]),
+    route!(GET "/admin/performance/slow_routes" => get_slow_routes).admin().tags(&["admin"]),
+    route!(GET "/admin/db/health" => get_db_health).admin().tags(&["admin"]),
+    route!(GET "/admin/backup" => get_backup).admin().rate_limit(RateLimitClass::Expensive).tags(&["admin"]),
+    route!(POST "/admin/restore" => restore_backup).admin().rate_limit(RateLimitClass::Expensive).tags(&["admin"]),
+    route!(POST "/admin/reset" => reset_data).admin().tags(&["admin"]),
+    route!(POST "/admin/seed" => seed_data).admin().tags(&["admin"]),
+    route!(POST "/graphql" => graphql).rate_limit(RateLimitClass::Expensive).tags(&["graphql"]),
+    route!(GET "/graphql" => graphql_playground).tags(&["graphql"]),
+    route!(GET "/webhooks" => get_webhooks).admin().tags(&["admin", "webhooks"]),
+    route!(GET "/webhooks/{id}" => get_webhook_by_id).admin().tags(&["admin", "webhooks"]),
+    route!(POST "/webhooks" => create_webhook).admin().tags(&["admin", "webhooks"]),
+    route!(PUT "/webhooks/{id}" => update_webhook_by_id).admin().tags(&["admin", "webhooks"]),
+    route!(DELETE "/webhooks/{id}" => delete_webhook_by_id).admin().tags(&["admin", "webhooks"]),
+    route!(GET "/rate-limits/tiers" => get_rate_limit_tiers).admin().tags(&["admin", "rate-limits"]),
+    route!(PUT "/rate-limits/tiers/{name}" => save_rate_limit_tier).admin().tags(&["admin", "rate-limits"]),
+    route!(DELETE "/rate-limits/tiers/{name}" => delete_rate_limit_tier).admin().tags(&["admin", "rate-limits"]),
+    route!(GET "/api-keys" => get_api_keys).admin().tags(&["admin", "rate-limits"]),
+    route!(POST "/api-keys" => create_api_key).admin().tags(&["admin", "rate-limits"]),
+    route!(DELETE "/api-keys/{id}" => delete_api_key_by_id).admin().tags(&["admin", "rate-limits"]),
+    route!(GET "/cors/origins" => get_cors_origins).admin().tags(&["admin", "cors"]),
+    route!(POST "/cors/origins" => add_cors_origin).admin().tags(&["admin", "cors"]),
+    route!(DELETE "/cors/origins" => delete_cors_origin).admin().tags(&["admin", "cors"]),
+    route!(GET "/jobs/{id}" => get_job_by_id).cache(CachePolicy::NoStore).tags(&["jobs"]),
+    route!(GET "/imports/{id}" => get_import_by_id).cache(CachePolicy::NoStore).tags(&["jobs", "imports"]),
+    route!(GET "/openapi.json" => get_openapi).tags(&["meta"]),
+]);
 
 pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(web::Data::new(graphql_apis::schema()));
     cfg.service(
-        web::scope("/api")
-            .service(get_monsters)
-            .service(create_monster)
-            .service(get_monster_by_id)
-            .service(delete_monster_by_id)
-            .service(update_monster_by_id)
-            .service(import_csv)
-            .service(get_battles)
-            .service(get_battle_by_id)
-            .service(delete_battle_by_id)
-            .service(create_battle)
+        web::scope(API_PREFIX)
+            .wrap(ReplayIdempotentRequests)
+            .wrap_fn(enforce_route_metadata(&ROUTES, API_PREFIX))
+            .configure(|cfg| ROUTES.iter().for_each(|route| route.register(cfg)))
     );
-}
\ No newline at end of file
+}
+
+/*
+Registers `repository` as the monster, battle, webhook, job, move, item, rate limit, season, evolution, note, export,
+training, trainer, achievement, CORS, database health, challenge, thumbnail, backup and idempotency repository app data, so handlers can extract `web::Data<dyn MonsterRepository>`, `web::Data<dyn BattleRepository>`,
+`web::Data<dyn WebhookRepository>`, `web::Data<dyn JobRepository>`, `web::Data<dyn MoveRepository>`,
+`web::Data<dyn ItemRepository>`, `web::Data<dyn RateLimitRepository>`, `web::Data<dyn SeasonRepository>`,
+`web::Data<dyn EvolutionRepository>`, `web::Data<dyn NoteRepository>`, `web::Data<dyn ExportRepository>`,
+`web::Data<dyn TrainingRepository>`, `web::Data<dyn TrainerRepository>`, `web::Data<dyn AchievementRepository>`,
+`web::Data<dyn CorsRepository>`, `web::Data<dyn DbHealthRepository>`, `web::Data<dyn ChallengeRepository>`,
+`web::Data<dyn ThumbnailRepository>`, `web::Data<dyn BackupRepository>` and `web::Data<dyn IdempotencyRepository>`.
+*/
+pub fn repositories<R: MonsterRepository + BattleRepository + WebhookRepository + JobRepository + MoveRepository + ItemRepository + RateLimitRepository + SeasonRepository + EvolutionRepository + NoteRepository + ExportRepository + TrainingRepository + TrainerRepository + AchievementRepository + CorsRepository + DbHealthRepository + ChallengeRepository + ThumbnailRepository + BackupRepository + IdempotencyRepository + 'static>(repository: Arc<R>) -> impl Fn(&mut web::ServiceConfig) {
+    move |cfg| {
+        let monster_repository: Arc<dyn MonsterRepository> = repository.clone();
+        let battle_repository: Arc<dyn BattleRepository> = repository.clone();
+        let webhook_repository: Arc<dyn WebhookRepository> = repository.clone();
+        let job_repository: Arc<dyn JobRepository> = repository.clone();
+        let move_repository: Arc<dyn MoveRepository> = repository.clone();
+        let item_repository: Arc<dyn ItemRepository> = repository.clone();
+        let rate_limit_repository: Arc<dyn RateLimitRepository> = repository.clone();
+        let season_repository: Arc<dyn SeasonRepository> = repository.clone();
+        let evolution_repository: Arc<dyn EvolutionRepository> = repository.clone();
+        let note_repository: Arc<dyn NoteRepository> = repository.clone();
+        let export_repository: Arc<dyn ExportRepository> = repository.clone();
+        let training_repository: Arc<dyn TrainingRepository> = repository.clone();
+        let trainer_repository: Arc<dyn TrainerRepository> = repository.clone();
+        let achievement_repository: Arc<dyn AchievementRepository> = repository.clone();
+        let cors_repository: Arc<dyn CorsRepository> = repository.clone();
+        let db_health_repository: Arc<dyn DbHealthRepository> = repository.clone();
+        let challenge_repository: Arc<dyn ChallengeRepository> = repository.clone();
+        let thumbnail_repository: Arc<dyn ThumbnailRepository> = repository.clone();
+        let backup_repository: Arc<dyn BackupRepository> = repository.clone();
+        let idempotency_repository: Arc<dyn IdempotencyRepository> = repository.clone();
+        cfg.app_data(web::Data::from(monster_repository))
+            .app_data(web::Data::from(battle_repository))
+            .app_data(web::Data::from(webhook_repository))
+            .app_data(web::Data::from(job_repository))
+            .app_data(web::Data::from(move_repository))
+            .app_data(web::Data::from(item_repository))
+            .app_data(web::Data::from(rate_limit_repository))
+            .app_data(web::Data::from(season_repository))
+            .app_data(web::Data::from(evolution_repository))
+            .app_data(web::Data::from(note_repository))
+            .app_data(web::Data::from(export_repository))
+            .app_data(web::Data::from(training_repository))
+            .app_data(web::Data::from(trainer_repository))
+            .app_data(web::Data::from(achievement_repository))
+            .app_data(web::Data::from(cors_repository))
+            .app_data(web::Data::from(db_health_repository))
+            .app_data(web::Data::from(challenge_repository))
+            .app_data(web::Data::from(thumbnail_repository))
+            .app_data(web::Data::from(backup_repository))
+            .app_data(web::Data::from(idempotency_repository));
+    }
+}
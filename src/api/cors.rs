@@ -0,0 +1,251 @@
+use std::sync::{Arc, RwLock};
+use actix_cors::Cors;
+use actix_web::http::{header, Method};
+use actix_web::web;
+use crate::config::AppConfig;
+use crate::logging::REQUEST_ID_HEADER;
+use crate::models::idempotency::{IDEMPOTENCY_KEY_HEADER, IDEMPOTENT_REPLAYED_HEADER};
+use crate::models::page::NEXT_CURSOR_HEADER;
+use crate::rate_limit::{API_KEY_HEADER, RATE_LIMIT_HEADERS};
+use crate::repository::cors_repository::CorsRepository;
+use crate::repository::database::SCHEMA_HEADER;
+use super::auth::ADMIN_TOKEN_HEADER;
+use super::region::REGION_HEADER;
+
+// Browsers reuse preflight responses for a day, the longest any of them allows.
+const DEFAULT_MAX_AGE_SECONDS: usize = 86400;
+// How often each instance reloads the stored origins, picking up the changes made through other instances.
+pub const ORIGINS_RELOAD_SECONDS: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CorsProfile {
+    Dev,
+    // Picked by APP_ENV=production.
+    Prod,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AllowedOrigins {
+    Any,
+    // Any port on localhost or 127.0.0.1, the development default.
+    Localhost,
+    List(Vec<String>),
+    // The origins stored in the database, the production default.
+    Stored,
+}
+
+/*
+CORS settings of the profile, overridden by the CORS_* env vars. The dev profile allows localhost origins,
+the prod one the origins stored in the database, which admins edit at runtime through `/api/cors/origins`.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorsConfig {
+    pub profile: CorsProfile,
+    pub allowed_origins: AllowedOrigins,
+    pub allowed_methods: Vec<Method>,
+    pub allowed_headers: Vec<String>,
+    pub max_age: usize,
+}
+
+impl CorsConfig {
+    pub fn from_config(config: &AppConfig) -> Result<Self, String> {
+        CorsConfig::from_vars(|name| config.var(name))
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let profile = if var("APP_ENV").is_some_and(|env| env == "production") { CorsProfile::Prod } else { CorsProfile::Dev };
+        let allowed_origins = match (var("CORS_ALLOWED_ORIGINS").as_deref().map(str::trim), profile) {
+            (Some("*"), _) => AllowedOrigins::Any,
+            (Some(origins), _) => AllowedOrigins::List(split_list(origins)),
+            (None, CorsProfile::Prod) => AllowedOrigins::Stored,
+            (None, CorsProfile::Dev) => AllowedOrigins::Localhost,
+        };
+        let allowed_methods = match var("CORS_ALLOWED_METHODS") {
+            Some(methods) => split_list(&methods)
+                .into_iter()
+                .map(|method| Method::from_bytes(method.to_uppercase().as_bytes()).map_err(|_| format!("Invalid CORS method: {}", method)))
+                .collect::<Result<_, _>>()?,
+            None => vec![Method::GET, Method::POST, Method::PUT, Method::DELETE],
+        };
+        let allowed_headers = match var("CORS_ALLOWED_HEADERS") {
+            Some(headers) => split_list(&headers),
+            None => ["Content-Type", "If-Match", "If-None-Match", IDEMPOTENCY_KEY_HEADER, ADMIN_TOKEN_HEADER, API_KEY_HEADER, SCHEMA_HEADER, REQUEST_ID_HEADER]
+                .map(str::to_string)
+                .to_vec(),
+        };
+        let max_age = match var("CORS_MAX_AGE_SECONDS") {
+            Some(max_age) => max_age.trim().parse().map_err(|_| format!("CORS_MAX_AGE_SECONDS must be a non-negative integer, got {:?}", max_age))?,
+            None => DEFAULT_MAX_AGE_SECONDS,
+        };
+        Ok(CorsConfig { profile, allowed_origins, allowed_methods, allowed_headers, max_age })
+    }
+
+    // The stored origins are only checked with `AllowedOrigins::Stored`.
+    pub fn cors(&self, stored_origins: &web::Data<CorsOrigins>) -> Cors {
+        let cors = match &self.allowed_origins {
+            AllowedOrigins::Any => Cors::default().allow_any_origin(),
+            AllowedOrigins::Localhost => Cors::default().allowed_origin_fn(|origin, _| {
+                origin.to_str().is_ok_and(is_localhost)
+            }),
+            AllowedOrigins::List(origins) => origins
+                .iter()
+                .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin)),
+            AllowedOrigins::Stored => {
+                let stored_origins = stored_origins.clone();
+                Cors::default().allowed_origin_fn(move |origin, _| {
+                    origin.to_str().is_ok_and(|origin| stored_origins.allows(origin))
+                })
+            }
+        };
+        cors.allowed_methods(self.allowed_methods.clone())
+            .allowed_headers(self.allowed_headers.iter().map(String::as_str))
+            .expose_headers(["ETag", IDEMPOTENT_REPLAYED_HEADER, NEXT_CURSOR_HEADER, REQUEST_ID_HEADER, REGION_HEADER, header::RETRY_AFTER.as_str()].into_iter().chain(RATE_LIMIT_HEADERS))
+            .max_age(self.max_age)
+    }
+}
+
+/*
+The stored origins, cached so preflights don't query the database. Admin changes reload them on the instance
+making them, the other instances reload them every ORIGINS_RELOAD_SECONDS.
+*/
+pub struct CorsOrigins {
+    repository: Arc<dyn CorsRepository>,
+    origins: RwLock<Vec<String>>,
+}
+
+impl CorsOrigins {
+    pub fn new(repository: Arc<dyn CorsRepository>) -> Self {
+        let cors_origins = CorsOrigins { repository, origins: RwLock::default() };
+        cors_origins.reload();
+        cors_origins
+    }
+
+    pub fn reload(&self) {
+        let origins = self.repository.get_cors_origins().into_iter().map(|cors_origin| cors_origin.origin).collect();
+        *self.origins.write().expect("CORS origins lock poisoned") = origins;
+    }
+
+    // Browsers send origins as they are, stored ones being lowercased.
+    fn allows(&self, origin: &str) -> bool {
+        let origin = origin.to_lowercase();
+        self.origins.read().expect("CORS origins lock poisoned").contains(&origin)
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn is_localhost(origin: &str) -> bool {
+    let host = origin
+        .strip_prefix("http://")
+        .or_else(|| origin.strip_prefix("https://"))
+        .unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default();
+    host == "localhost" || host == "127.0.0.1"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use actix_web::{test, http, App, HttpResponse};
+    use crate::repository::memory_repository::InMemoryRepository;
+
+    use super::*;
+
+    fn cors_config(vars: &[(&str, &str)]) -> Result<CorsConfig, String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        CorsConfig::from_vars(|name| vars.get(name).cloned())
+    }
+
+    async fn preflight(config: &CorsConfig, origin: &str) -> Option<String> {
+        preflight_with(config, &web::Data::new(CorsOrigins::new(Arc::new(InMemoryRepository::new()))), origin).await
+    }
+
+    async fn preflight_with(config: &CorsConfig, stored_origins: &web::Data<CorsOrigins>, origin: &str) -> Option<String> {
+        let app = App::new()
+            .wrap(config.cors(stored_origins))
+            .route("/api/monsters", web::get().to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::default()
+            .method(Method::OPTIONS)
+            .uri("/api/monsters")
+            .insert_header((http::header::ORIGIN, origin))
+            .insert_header((http::header::ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        resp.headers()
+            .get(http::header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .map(|value| value.to_str().unwrap().to_string())
+    }
+
+    #[actix_rt::test]
+    async fn test_should_read_the_cors_config_from_env_vars() {
+        let config = cors_config(&[
+            ("CORS_ALLOWED_ORIGINS", "https://monsters.example, https://admin.example"),
+            ("CORS_ALLOWED_METHODS", "get,post"),
+            ("CORS_ALLOWED_HEADERS", "Content-Type"),
+            ("CORS_MAX_AGE_SECONDS", "60"),
+        ]).unwrap();
+
+        assert_eq!(config.allowed_origins, AllowedOrigins::List(vec!["https://monsters.example".to_string(), "https://admin.example".to_string()]));
+        assert_eq!(config.allowed_methods, vec![Method::GET, Method::POST]);
+        assert_eq!(config.allowed_headers, vec!["Content-Type".to_string()]);
+        assert_eq!(config.max_age, 60);
+        assert!(cors_config(&[("CORS_MAX_AGE_SECONDS", "soon")]).is_err());
+    }
+
+    #[actix_rt::test]
+    async fn test_should_allow_localhost_origins_by_default() {
+        let config = cors_config(&[]).unwrap();
+
+        assert_eq!(preflight(&config, "http://localhost:3000").await.as_deref(), Some("http://localhost:3000"));
+        assert_eq!(preflight(&config, "https://evil.example").await, None);
+
+        // Browsers may send API keys and read the budget left to them.
+        let app = App::new()
+            .wrap(config.cors(&web::Data::new(CorsOrigins::new(Arc::new(InMemoryRepository::new())))))
+            .route("/api/monsters", web::get().to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::default()
+            .method(Method::OPTIONS)
+            .uri("/api/monsters")
+            .insert_header((http::header::ORIGIN, "http://localhost:3000"))
+            .insert_header((http::header::ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+            .insert_header((http::header::ACCESS_CONTROL_REQUEST_HEADERS, "x-api-key"))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let req = test::TestRequest::get().uri("/api/monsters").insert_header((http::header::ORIGIN, "http://localhost:3000")).to_request();
+        let resp = test::call_service(&app, req).await;
+        let exposed = resp.headers().get(http::header::ACCESS_CONTROL_EXPOSE_HEADERS).unwrap().to_str().unwrap().to_lowercase();
+        assert!(exposed.contains("x-ratelimit-remaining") && exposed.contains("retry-after"));
+    }
+
+    #[actix_rt::test]
+    async fn test_should_only_allow_configured_origins_in_production() {
+        let strict = cors_config(&[("APP_ENV", "production")]).unwrap();
+        let configured = cors_config(&[("APP_ENV", "production"), ("CORS_ALLOWED_ORIGINS", "https://monsters.example")]).unwrap();
+
+        assert_eq!(preflight(&strict, "http://localhost:3000").await, None);
+        assert_eq!(preflight(&configured, "https://monsters.example").await.as_deref(), Some("https://monsters.example"));
+    }
+
+    #[actix_rt::test]
+    async fn test_should_allow_the_stored_origins_in_production() {
+        let config = cors_config(&[("APP_ENV", "production")]).unwrap();
+        assert_eq!((config.profile, &config.allowed_origins, config.max_age), (CorsProfile::Prod, &AllowedOrigins::Stored, DEFAULT_MAX_AGE_SECONDS));
+        let repository = Arc::new(InMemoryRepository::new());
+        let stored_origins = web::Data::new(CorsOrigins::new(repository.clone()));
+        assert_eq!(preflight_with(&config, &stored_origins, "https://monsters.example").await, None);
+
+        repository.add_cors_origin("https://monsters.example").unwrap();
+        stored_origins.reload();
+        assert_eq!(preflight_with(&config, &stored_origins, "https://monsters.example").await.as_deref(), Some("https://monsters.example"));
+        assert_eq!(preflight_with(&config, &stored_origins, "https://evil.example").await, None);
+    }
+}
@@ -0,0 +1,104 @@
+use actix_web::{web, get, post, delete, HttpResponse};
+use serde::{Deserialize, Serialize};
+use crate::models::cors::parse_origin;
+use crate::repository::cors_repository::CorsRepository;
+use super::cors::CorsOrigins;
+use super::error::repository_error_response;
+
+#[derive(Serialize, Deserialize)]
+pub struct CorsOriginRequest {
+    origin: Option<String>,
+}
+
+impl CorsOriginRequest {
+    fn origin(&self) -> Result<String, String> {
+        parse_origin(self.origin.as_deref().ok_or("Origin is required")?)
+    }
+}
+
+// The CORS middleware caches the stored origins, changes only apply once it reloaded them.
+fn reload(cors_origins: Option<web::Data<CorsOrigins>>) {
+    if let Some(cors_origins) = cors_origins {
+        cors_origins.reload();
+    }
+}
+
+// Only checked by the prod CORS profile, unless CORS_ALLOWED_ORIGINS overrides them.
+#[get("/cors/origins")]
+pub async fn get_cors_origins(cors_repository: web::Data<dyn CorsRepository>) -> HttpResponse {
+    HttpResponse::Ok().json(cors_repository.get_cors_origins())
+}
+
+#[post("/cors/origins")]
+pub async fn add_cors_origin(cors_repository: web::Data<dyn CorsRepository>, cors_origins: Option<web::Data<CorsOrigins>>, request: web::Json<CorsOriginRequest>) -> HttpResponse {
+    let origin = match request.origin() {
+        Ok(origin) => origin,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+
+    match cors_repository.add_cors_origin(&origin) {
+        Ok(cors_origin) => {
+            reload(cors_origins);
+            HttpResponse::Ok().json(cors_origin)
+        }
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+// Takes the origin as a query parameter, `?origin=https://monsters.example`, origins not fitting in a path.
+#[delete("/cors/origins")]
+pub async fn delete_cors_origin(cors_repository: web::Data<dyn CorsRepository>, cors_origins: Option<web::Data<CorsOrigins>>, query: web::Query<CorsOriginRequest>) -> HttpResponse {
+    let origin = match query.origin() {
+        Ok(origin) => origin,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+
+    match cors_repository.delete_cors_origin(&origin) {
+        Ok(Some(_)) => {
+            reload(cors_origins);
+            HttpResponse::NoContent().finish()
+        }
+        Ok(None) => HttpResponse::NotFound().json("Origin not found"),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use serde_json::json;
+    use crate::api::config::repositories;
+    use crate::models::cors::CorsOrigin;
+    use crate::repository::memory_repository::InMemoryRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_edit_the_stored_origins_at_runtime() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let app = App::new()
+            .configure(repositories(repository.clone()))
+            .app_data(web::Data::new(CorsOrigins::new(repository.clone())))
+            .service(get_cors_origins)
+            .service(add_cors_origin)
+            .service(delete_cors_origin);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::post().uri("/cors/origins").set_json(json!({ "origin": "https://monsters.example/play" })).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::BAD_REQUEST);
+        for _ in 0..2 {
+            let req = test::TestRequest::post().uri("/cors/origins").set_json(json!({ "origin": "https://Monsters.example" })).to_request();
+            let cors_origin: CorsOrigin = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(cors_origin.origin, "https://monsters.example");
+        }
+        let req = test::TestRequest::get().uri("/cors/origins").to_request();
+        let cors_origins: Vec<CorsOrigin> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(cors_origins.len(), 1);
+
+        let req = test::TestRequest::delete().uri("/cors/origins?origin=https%3A%2F%2Fmonsters.example").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::NO_CONTENT);
+        let req = test::TestRequest::delete().uri("/cors/origins?origin=https%3A%2F%2Fmonsters.example").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::NOT_FOUND);
+    }
+}
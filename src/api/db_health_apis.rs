@@ -0,0 +1,59 @@
+use actix_web::{web, get, HttpResponse};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use crate::maintenance::DB_STATS_RETENTION_DAYS;
+use crate::models::db_health::db_health;
+use crate::repository::db_health_repository::DbHealthRepository;
+
+const DEFAULT_HEALTH_DAYS: i64 = 7;
+
+#[derive(Deserialize)]
+pub struct DbHealthQuery {
+    // Days of nightly snapshots the trends cover.
+    days: Option<i64>,
+}
+
+/*
+The latest statistics of every table with their trends over the last nights, warning about hot tables whose
+queries stopped using indexes and tables needing a vacuum.
+*/
+#[get("/admin/db/health")]
+pub async fn get_db_health(db_health_repository: web::Data<dyn DbHealthRepository>, query: web::Query<DbHealthQuery>) -> HttpResponse {
+    let days = query.days.unwrap_or(DEFAULT_HEALTH_DAYS);
+    if !(1..=DB_STATS_RETENTION_DAYS).contains(&days) {
+        return HttpResponse::BadRequest().json(format!("Days must be between 1 and {}", DB_STATS_RETENTION_DAYS));
+    }
+    let since = Utc::now().naive_utc() - Duration::days(days);
+    HttpResponse::Ok().json(db_health(db_health_repository.get_db_stats_since(since)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use crate::api::config::repositories;
+    use crate::maintenance::record_db_stats;
+    use crate::models::db_health::DbHealth;
+    use crate::repository::database::Database;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_report_the_statistics_of_the_nightly_snapshots() {
+        let db = Arc::new(Database::new().unwrap());
+        let result = record_db_stats(db.as_ref()).unwrap();
+        assert!(result["tables"].as_u64().unwrap() > 0);
+        let app = App::new().configure(repositories(db.clone())).service(get_db_health);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/admin/db/health").to_request();
+        let health: DbHealth = test::call_and_read_body_json(&app, req).await;
+        assert!(health.taken_at.is_some());
+        let monsters = health.tables.iter().find(|table| table.latest.table_name == "monsters").expect("The monsters table is snapshotted");
+        assert!(monsters.latest.total_bytes > 0);
+        assert!(health.tables.iter().all(|table| !table.latest.table_name.starts_with("__")));
+
+        let req = test::TestRequest::get().uri("/admin/db/health?days=0").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,77 @@
+use actix_web::HttpResponse;
+use serde::{Deserialize, Serialize};
+use crate::models::monster::MAX_STAT;
+use crate::repository::error::{Constraint, RepositoryError};
+
+/*
+Body of the responses for rejected writes, `code` is stable and meant for clients to match on:
+
+| Status | Code                          | Cause                                              |
+|--------|-------------------------------|----------------------------------------------------|
+| 422    | MONSTER_NAME_EMPTY            | The monster name is empty or only whitespace        |
+| 422    | BATTLE_MONSTER_NOT_FOUND      | A battle references a monster that does not exist  |
+| 422    | BATTLE_WINNER_NOT_PARTICIPANT | The winner is neither monster A nor monster B      |
+| 422    | BATTLE_DRAW_WINNER_MISMATCH   | A draw has a winner, or another outcome has none   |
+| 422    | BATTLE_OUTCOME_INVALID        | The outcome is not win, draw or forfeit            |
+| 422    | MONSTER_MOVE_NOT_FOUND        | A monster is taught a move that does not exist     |
+| 422    | MONSTER_ITEM_NOT_FOUND        | A monster is equipped with an item that does not exist |
+| 422    | API_KEY_TIER_NOT_FOUND        | An API key is given a tier that does not exist     |
+| 422    | TRAINER_NAME_TAKEN            | Another trainer registered with the name           |
+| 422    | MONSTER_OWNER_NOT_FOUND       | A monster is given to a trainer that does not exist |
+| 422    | CHALLENGE_MONSTER_NOT_FOUND   | A daily challenge is fought by a monster that was deleted |
+| 422    | MONSTER_EXTERNAL_ID_TAKEN     | Another monster was imported with the external id  |
+| 422    | MONSTER_STATS_OUT_OF_RANGE    | A stat is over MAX_STAT, negative, or HP is 0      |
+| 409    | MONSTER_VERSION_STALE         | The monster was updated since the version sent     |
+| 409    | RESTORE_DELETES_DEPENDENTS    | A replacing restore would delete rows the backup doesn't hold |
+| 413    | PAYLOAD_TOO_LARGE             | The body is over MAX_JSON_BODY_BYTES or MAX_MULTIPART_BYTES |
+| 500    | DATABASE_ERROR                | Any other storage failure                          |
+*/
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ApiError {
+    pub code: String,
+    pub message: String,
+}
+
+impl ApiError {
+    fn new(code: &str, message: &str) -> Self {
+        ApiError { code: code.to_string(), message: message.to_string() }
+    }
+
+    pub fn payload_too_large(limit: usize) -> Self {
+        ApiError::new("PAYLOAD_TOO_LARGE", &format!("The request body must be at most {} bytes", limit))
+    }
+}
+
+impl From<&RepositoryError> for ApiError {
+    fn from(err: &RepositoryError) -> Self {
+        match err {
+            RepositoryError::Constraint(Constraint::MonsterNameNotEmpty) => ApiError::new("MONSTER_NAME_EMPTY", "Monster name must not be empty"),
+            RepositoryError::Constraint(Constraint::BattleMonsterExists) => ApiError::new("BATTLE_MONSTER_NOT_FOUND", "Battle monsters must exist"),
+            RepositoryError::Constraint(Constraint::BattleWinnerIsParticipant) => ApiError::new("BATTLE_WINNER_NOT_PARTICIPANT", "Winner must be one of the battle monsters"),
+            RepositoryError::Constraint(Constraint::BattleDrawHasNoWinner) => ApiError::new("BATTLE_DRAW_WINNER_MISMATCH", "Only draws have no winner"),
+            RepositoryError::Constraint(Constraint::BattleOutcomeKnown) => ApiError::new("BATTLE_OUTCOME_INVALID", "Outcome must be win, draw or forfeit"),
+            RepositoryError::Constraint(Constraint::MonsterMoveExists) => ApiError::new("MONSTER_MOVE_NOT_FOUND", "Monster moves must exist"),
+            RepositoryError::Constraint(Constraint::MonsterItemExists) => ApiError::new("MONSTER_ITEM_NOT_FOUND", "Monster items must exist"),
+            RepositoryError::Constraint(Constraint::ApiKeyTierExists) => ApiError::new("API_KEY_TIER_NOT_FOUND", "API key tiers must exist"),
+            RepositoryError::Constraint(Constraint::TrainerNameUnique) => ApiError::new("TRAINER_NAME_TAKEN", "Trainer names must be unique"),
+            RepositoryError::Constraint(Constraint::MonsterOwnerExists) => ApiError::new("MONSTER_OWNER_NOT_FOUND", "Monster owners must exist"),
+            RepositoryError::Constraint(Constraint::ChallengeMonsterExists) => ApiError::new("CHALLENGE_MONSTER_NOT_FOUND", "Challenge monsters must exist"),
+            RepositoryError::Constraint(Constraint::MonsterExternalIdUnique) => ApiError::new("MONSTER_EXTERNAL_ID_TAKEN", "Monster external ids must be unique"),
+            RepositoryError::Constraint(Constraint::MonsterStatsInRange) => ApiError::new("MONSTER_STATS_OUT_OF_RANGE", &format!("HP must be between 1 and {}, the other stats between 0 and {}", MAX_STAT, MAX_STAT)),
+            RepositoryError::StaleVersion => ApiError::new("MONSTER_VERSION_STALE", "The monster was updated since this version, read it again"),
+            RepositoryError::HasDependents(tables) => ApiError::new(
+                "RESTORE_DELETES_DEPENDENTS",
+                &format!("Replacing would also delete the rows of {}, restore with mode=force_replace to delete them anyway", tables.join(", ")),
+            ),
+            RepositoryError::Database(err) => ApiError::new("DATABASE_ERROR", &err.to_string()),
+        }
+    }
+}
+
+pub fn repository_error_response(err: &RepositoryError) -> HttpResponse {
+    match err {
+        RepositoryError::Constraint(_) => HttpResponse::UnprocessableEntity().json(ApiError::from(err)),
+        RepositoryError::StaleVersion | RepositoryError::HasDependents(_) => HttpResponse::Conflict().json(ApiError::from(err)),
+        RepositoryError::Database(_) => HttpResponse::InternalServerError().json(ApiError::from(err)),
+    }
+}
@@ -0,0 +1,40 @@
+use actix_web::http::header::{self, EntityTag};
+use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/*
+A strong ETag hashing the JSON of the stored rows a response is made of. Values derived from the clock, like
+effective stats under stat decay, are left out so clients can still use the tag in If-Match.
+*/
+pub fn etag<T: Serialize>(rows: &T) -> EntityTag {
+    let digest = Sha256::digest(serde_json::to_vec(rows).expect("Rows serialize to JSON"));
+    EntityTag::new_strong(digest.iter().take(16).map(|byte| format!("{:02x}", byte)).collect())
+}
+
+// Whether the client already has the representation tagged `etag`, compared weakly as If-None-Match requires.
+pub fn is_fresh(req: &HttpRequest, etag: &EntityTag) -> bool {
+    match req.get_header::<header::IfNoneMatch>() {
+        Some(header::IfNoneMatch::Any) => true,
+        Some(header::IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        None => false,
+    }
+}
+
+// Whether an If-Match header, if any, names `etag`, compared strongly so only an unchanged resource is updated.
+pub fn matches(req: &HttpRequest, etag: &EntityTag) -> bool {
+    match req.get_header::<header::IfMatch>() {
+        Some(header::IfMatch::Items(tags)) => tags.iter().any(|tag| tag.strong_eq(etag)),
+        Some(header::IfMatch::Any) | None => true,
+    }
+}
+
+pub fn tagged(mut response: HttpResponse, etag: &EntityTag) -> HttpResponse {
+    let value = header::HeaderValue::from_str(&etag.to_string()).expect("ETags are valid header values");
+    response.headers_mut().insert(header::ETAG, value);
+    response
+}
+
+pub fn not_modified(etag: EntityTag) -> HttpResponse {
+    HttpResponse::NotModified().insert_header(header::ETag(etag)).finish()
+}
@@ -0,0 +1,181 @@
+use actix_web::{web, get, post, put, delete, HttpResponse};
+use serde::{Deserialize, Serialize};
+use crate::models::evolution::{Evolution, EvolutionRequirement};
+use crate::models::growth::MAX_LEVEL;
+use crate::repository::evolution_repository::{EvolutionRepository, EvolveMonsterError};
+use super::error::repository_error_response;
+
+#[derive(Serialize, Deserialize)]
+pub struct EvolutionRequest {
+    evolves_to: Option<String>,
+    min_level: Option<i32>,
+    min_wins: Option<i32>,
+    image_url: Option<String>,
+    attack: Option<i32>,
+    defense: Option<i32>,
+    hp: Option<i32>,
+    speed: Option<i32>,
+}
+
+impl EvolutionRequest {
+    fn into_evolution(self, species: String) -> Result<Evolution, String> {
+        let evolves_to = match self.evolves_to.map(|evolves_to| evolves_to.trim().to_string()) {
+            Some(evolves_to) if !evolves_to.is_empty() => evolves_to,
+            _ => return Err("The species it evolves to is required".to_string()),
+        };
+        if evolves_to == species {
+            return Err("A species can't evolve into itself".to_string());
+        }
+        let min_level = self.min_level.unwrap_or(1);
+        if !(1..=MAX_LEVEL).contains(&min_level) {
+            return Err(format!("The min level must be between 1 and {}", MAX_LEVEL));
+        }
+        let min_wins = self.min_wins.unwrap_or(0);
+        if min_wins < 0 {
+            return Err("The min wins must not be negative".to_string());
+        }
+        let image_url = match self.image_url {
+            Some(image_url) if !image_url.trim().is_empty() => image_url,
+            _ => return Err("The image URL is required".to_string()),
+        };
+        let stat = |stat: Option<i32>, name: &str, min: i32| match stat {
+            Some(stat) if stat >= min => Ok(stat),
+            Some(_) => Err(format!("The {} must be at least {}", name, min)),
+            None => Err(format!("The {} is required", name)),
+        };
+
+        Ok(Evolution {
+            attack: stat(self.attack, "attack", 0)?,
+            defense: stat(self.defense, "defense", 0)?,
+            hp: stat(self.hp, "hp", 1)?,
+            speed: stat(self.speed, "speed", 0)?,
+            species,
+            evolves_to,
+            min_level,
+            min_wins,
+            image_url,
+            updated_at: None,
+        })
+    }
+}
+
+#[get("/evolutions")]
+pub async fn get_evolutions(evolution_repository: web::Data<dyn EvolutionRepository>) -> HttpResponse {
+    HttpResponse::Ok().json(evolution_repository.get_evolutions())
+}
+
+// Creates the evolution of the species or replaces it.
+#[put("/evolutions/{species}")]
+pub async fn save_evolution(evolution_repository: web::Data<dyn EvolutionRepository>, species: web::Path<String>, request: web::Json<EvolutionRequest>) -> HttpResponse {
+    let evolution = match request.into_inner().into_evolution(species.into_inner()) {
+        Ok(evolution) => evolution,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+
+    match evolution_repository.save_evolution(evolution) {
+        Ok(evolution) => HttpResponse::Ok().json(evolution),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[delete("/evolutions/{species}")]
+pub async fn delete_evolution(evolution_repository: web::Data<dyn EvolutionRepository>, species: web::Path<String>) -> HttpResponse {
+    match evolution_repository.delete_evolution(&species) {
+        Ok(Some(_)) => HttpResponse::NoContent().finish(),
+        Ok(None) => HttpResponse::NotFound().json("Evolution not found"),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+// Evolves the monster into the next form of its species, a 409 telling the requirement it doesn't meet yet.
+#[post("/monsters/{id}/evolve")]
+pub async fn evolve_monster(evolution_repository: web::Data<dyn EvolutionRepository>, id: web::Path<String>) -> HttpResponse {
+    match evolution_repository.evolve_monster(&id) {
+        Ok(monster) => HttpResponse::Ok().json(monster),
+        Err(EvolveMonsterError::NotFound) => HttpResponse::NotFound().json("Monster not found"),
+        Err(EvolveMonsterError::NoEvolution) => HttpResponse::Conflict().json("The species of the monster doesn't evolve"),
+        Err(EvolveMonsterError::Unmet(EvolutionRequirement::Level(level))) => HttpResponse::Conflict().json(format!("The monster must reach level {} to evolve", level)),
+        Err(EvolveMonsterError::Unmet(EvolutionRequirement::Wins(wins))) => HttpResponse::Conflict().json(format!("The monster must win {} battles to evolve", wins)),
+        Err(EvolveMonsterError::Database(err)) => repository_error_response(&err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use serde_json::{json, Value};
+    use crate::api::config::repositories;
+    use crate::models::battle::{Battle, BattleOutcome};
+    use crate::models::monster::{Monster, Stats};
+    use crate::repository::battle_repository::BattleRepository;
+    use crate::repository::memory_repository::InMemoryRepository;
+    use crate::repository::monster_repository::MonsterRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_evolve_monsters_meeting_the_requirements_of_their_species() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let new_monster = |name: &str| Monster { id: String::new(), name: name.to_string(), image_url: "https://images/pup.png".to_string(), stats: Stats { attack: 10, defense: 10, hp: 10, speed: 10 }, created_at: None, updated_at: None, last_battle_at: None, level: 1, xp: 0, owner_id: None, image_broken: false, external_id: None, version: 1 };
+        let pup = repository.create_monster(new_monster("Pup")).unwrap();
+        let opponent = repository.create_monster(new_monster("Rock")).unwrap();
+        let app = App::new()
+            .configure(repositories(repository.clone()))
+            .service(get_evolutions)
+            .service(save_evolution)
+            .service(evolve_monster);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::post().uri(&format!("/monsters/{}/evolve", pup.id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+
+        let evolution = json!({ "evolves_to": "Wolf", "min_wins": 1, "image_url": "https://images/wolf.png", "attack": 30, "defense": 20, "hp": 40, "speed": 25 });
+        let mut into_itself = evolution.clone();
+        into_itself["evolves_to"] = json!("Pup");
+        let req = test::TestRequest::put().uri("/evolutions/Pup").set_json(&into_itself).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let req = test::TestRequest::put().uri("/evolutions/Pup").set_json(&evolution).to_request();
+        let saved: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((saved["species"].as_str(), saved["min_level"].as_i64()), (Some("Pup"), Some(1)));
+
+        let req = test::TestRequest::post().uri(&format!("/monsters/{}/evolve", pup.id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        assert_eq!(test::read_body_json::<Value, _>(resp).await, "The monster must win 1 battles to evolve");
+
+        repository.create_battle(Battle {
+            id: String::new(),
+            monster_a: pup.id.clone(),
+            monster_b: opponent.id.clone(),
+            winner: Some(pup.id.clone()),
+            created_at: None,
+            updated_at: None,
+            outcome: BattleOutcome::Win,
+            manual: true,
+            season_id: None,
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        }).unwrap();
+        let req = test::TestRequest::post().uri(&format!("/monsters/{}/evolve", pup.id)).to_request();
+        let evolved: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((evolved["name"].as_str(), evolved["image_url"].as_str(), evolved["attack"].as_i64()), (Some("Wolf"), Some("https://images/wolf.png"), Some(30)));
+
+        // Wolves don't evolve any further.
+        let req = test::TestRequest::post().uri(&format!("/monsters/{}/evolve", pup.id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let req = test::TestRequest::get().uri("/evolutions").to_request();
+        let evolutions: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(evolutions.as_array().map(Vec::len), Some(1));
+    }
+}
@@ -0,0 +1,112 @@
+use actix_web::{web, get, post, HttpResponse};
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use crate::models::export::{ExportKind, ExportPage};
+use crate::repository::export_repository::ExportRepository;
+use super::error::repository_error_response;
+
+// Exports are kept for an hour, long enough to page through the largest ones.
+const EXPORT_TTL_MINUTES: i64 = 60;
+const DEFAULT_EXPORT_PAGE_SIZE: i64 = 100;
+const MAX_EXPORT_PAGE_SIZE: i64 = 1000;
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportRequest {
+    kind: Option<ExportKind>,
+}
+
+#[derive(Deserialize)]
+pub struct ExportPageQuery {
+    after: Option<i32>,
+    limit: Option<i64>,
+}
+
+/*
+Takes a snapshot of the monsters or battles to page through with GET /exports/{id}. Unlike paging through
+the live tables, rows written, updated or deleted meanwhile are neither repeated, skipped nor changed.
+*/
+#[post("/exports")]
+pub async fn create_export(export_repository: web::Data<dyn ExportRepository>, request: web::Json<ExportRequest>) -> HttpResponse {
+    let Some(kind) = request.kind else {
+        return HttpResponse::BadRequest().json("Kind is required");
+    };
+
+    let expires_at = chrono::Utc::now().naive_utc() + Duration::minutes(EXPORT_TTL_MINUTES);
+    match export_repository.create_export(kind, expires_at) {
+        Ok(export) => HttpResponse::Created().json(export),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+// The rows of the export past the `after` cursor, from the first one when omitted.
+#[get("/exports/{id}")]
+pub async fn get_export_page(export_repository: web::Data<dyn ExportRepository>, id: web::Path<String>, query: web::Query<ExportPageQuery>) -> HttpResponse {
+    let limit = query.limit.unwrap_or(DEFAULT_EXPORT_PAGE_SIZE);
+    if limit <= 0 || limit > MAX_EXPORT_PAGE_SIZE {
+        return HttpResponse::BadRequest().json(format!("Limit must be between 1 and {}", MAX_EXPORT_PAGE_SIZE));
+    }
+    let after = query.after.unwrap_or(0).max(0);
+
+    match export_repository.get_export_page(&id, after, limit) {
+        Some((export, rows)) => {
+            let last = after + rows.len() as i32;
+            let cursor = (last < export.total).then_some(last);
+            HttpResponse::Ok().json(ExportPage { export, rows, cursor })
+        }
+        None => HttpResponse::NotFound().json("Export not found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use serde_json::json;
+    use crate::api::config::repositories;
+    use crate::repository::database::Database;
+    use crate::repository::monster_repository::MonsterRepository;
+    use crate::utils::test_utils::init_test_monsters;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_page_through_a_snapshot_of_the_monsters() {
+        let db = Arc::new(Database::new().unwrap());
+        init_test_monsters(db.as_ref()).await;
+        let app = App::new().configure(repositories(db.clone())).service(create_export).service(get_export_page);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::post().uri("/exports").set_json(json!({})).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let req = test::TestRequest::post().uri("/exports").set_json(json!({ "kind": "monsters" })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let export: serde_json::Value = test::read_body_json(resp).await;
+        let export_id = export["id"].as_str().unwrap();
+
+        let req = test::TestRequest::get().uri(&format!("/exports/{}?limit=2", export_id)).to_request();
+        let first: ExportPage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(first.cursor, Some(2));
+
+        // Deleting a monster the first page returned doesn't shift the next pages.
+        let mut exported: Vec<String> = first.rows.iter().map(|row| row["id"].as_str().unwrap().to_string()).collect();
+        db.delete_monster_by_id(&exported[0], true).unwrap();
+        let mut cursor = first.cursor;
+        while let Some(after) = cursor {
+            let req = test::TestRequest::get().uri(&format!("/exports/{}?after={}&limit=2", export_id, after)).to_request();
+            let page: ExportPage = test::call_and_read_body_json(&app, req).await;
+            exported.extend(page.rows.iter().map(|row| row["id"].as_str().unwrap().to_string()));
+            cursor = page.cursor;
+        }
+        let mut ordered = exported.clone();
+        ordered.sort();
+        ordered.dedup();
+        assert_eq!((exported.len(), &exported), (first.export.total as usize, &ordered));
+        assert!(db.get_monster_by_id(&exported[0]).is_none());
+
+        let req = test::TestRequest::get().uri("/exports/123").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+    }
+}
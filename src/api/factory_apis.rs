@@ -0,0 +1,185 @@
+use actix_web::{web, post, HttpResponse};
+use serde::{Deserialize, Serialize};
+use crate::battle_engine::{BattleEngine, BattleSetup};
+use crate::models::battle::Battle;
+use crate::models::monster::{Monster, Stats, MAX_STAT};
+use crate::repository::battle_repository::BattleRepository;
+use crate::repository::monster_repository::MonsterRepository;
+use super::battle_apis::{battle_engine, new_manual_battle, new_simulated_battle, store_battle, ManualOutcome};
+use super::error::repository_error_response;
+
+const MAX_FACTORY_ITEMS: usize = 1000;
+const DEFAULT_IMAGE_URL: &str = "https://loremflickr.com/640/480";
+const DEFAULT_STATS: Stats = Stats { attack: 50, defense: 50, hp: 100, speed: 50 };
+
+// `count` copies of a monster, unset fields fall back to defaults.
+#[derive(Serialize, Deserialize)]
+pub struct MonsterSpec {
+    count: Option<usize>,
+    name: Option<String>,
+    image_url: Option<String>,
+    attack: Option<i32>,
+    defense: Option<i32>,
+    hp: Option<i32>,
+    speed: Option<i32>,
+}
+
+// Monsters are referenced by their position in the expanded monster list, battles without an outcome are simulated.
+#[derive(Serialize, Deserialize)]
+pub struct BattleSpec {
+    monster_a: usize,
+    monster_b: usize,
+    outcome: Option<ManualOutcome>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FactorySpec {
+    #[serde(default)]
+    monsters: Vec<MonsterSpec>,
+    #[serde(default)]
+    battles: Vec<BattleSpec>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FactoryResult {
+    monsters: Vec<Monster>,
+    battles: Vec<Battle>,
+}
+
+impl FactorySpec {
+    fn new_monsters(&self) -> Vec<Monster> {
+        let mut monsters = Vec::new();
+        for spec in &self.monsters {
+            let count = spec.count.unwrap_or(1);
+            for copy in 1..=count {
+                let position = monsters.len() + 1;
+                let name = match &spec.name {
+                    Some(name) if count > 1 => format!("{}-{}", name, copy),
+                    Some(name) => name.clone(),
+                    None => format!("monster-{}", position),
+                };
+                monsters.push(Monster {
+                    id: String::new(),
+                    name,
+                    image_url: spec.image_url.clone().unwrap_or_else(|| DEFAULT_IMAGE_URL.to_string()),
+                    stats: Stats {
+                        attack: spec.attack.unwrap_or(DEFAULT_STATS.attack),
+                        defense: spec.defense.unwrap_or(DEFAULT_STATS.defense),
+                        hp: spec.hp.unwrap_or(DEFAULT_STATS.hp),
+                        speed: spec.speed.unwrap_or(DEFAULT_STATS.speed),
+                    },
+                    created_at: None,
+                    updated_at: None,
+                    last_battle_at: None,
+                    level: 1,
+                    xp: 0,
+                    owner_id: None,
+                    image_broken: false,
+                    external_id: None,
+                    version: 1,
+                });
+            }
+        }
+        monsters
+    }
+
+    fn validate(&self, monsters: &[Monster]) -> Result<(), String> {
+        let monster_count = monsters.len();
+        if monster_count > MAX_FACTORY_ITEMS || self.battles.len() > MAX_FACTORY_ITEMS {
+            return Err(format!("At most {} monsters and {} battles can be created", MAX_FACTORY_ITEMS, MAX_FACTORY_ITEMS));
+        }
+        if let Some(index) = monsters.iter().position(|monster| !monster.stats.in_range()) {
+            return Err(format!("Monster {} must have an HP between 1 and {} and other stats between 0 and {}", index, MAX_STAT, MAX_STAT));
+        }
+        for (index, battle) in self.battles.iter().enumerate() {
+            if battle.monster_a >= monster_count || battle.monster_b >= monster_count {
+                return Err(format!("Battle {} references a monster that is not in the spec", index));
+            }
+            if battle.monster_a == battle.monster_b {
+                return Err(format!("Battle {} has a monster battling itself", index));
+            }
+        }
+        Ok(())
+    }
+}
+
+/*
+Creates a whole scenario in one call for end-to-end tests: the monsters first, then the battles
+between them. Only registered outside production, see `main`.
+*/
+#[post("/test/factory")]
+pub async fn create_test_data(monster_repository: web::Data<dyn MonsterRepository>, battle_repository: web::Data<dyn BattleRepository>, engine: Option<web::Data<dyn BattleEngine>>, spec: web::Json<FactorySpec>) -> HttpResponse {
+    let new_monsters = spec.new_monsters();
+    if let Err(message) = spec.validate(&new_monsters) {
+        return HttpResponse::BadRequest().json(message);
+    }
+
+    let mut monsters = Vec::with_capacity(new_monsters.len());
+    for monster in new_monsters {
+        match monster_repository.create_monster(monster) {
+            Ok(monster) => monsters.push(monster),
+            Err(err) => return repository_error_response(&err),
+        }
+    }
+
+    let engine = battle_engine(engine);
+    let mut battles = Vec::with_capacity(spec.battles.len());
+    for battle in &spec.battles {
+        let (monster_a, monster_b) = (&monsters[battle.monster_a], &monsters[battle.monster_b]);
+        let battle = match battle.outcome {
+            Some(outcome) => new_manual_battle(monster_a.id.clone(), monster_b.id.clone(), outcome),
+            None => new_simulated_battle(engine.as_ref(), BattleSetup::new(monster_a.clone(), monster_b.clone()), None),
+        };
+        match store_battle(battle_repository.as_ref(), battle) {
+            Ok(battle) => battles.push(battle),
+            Err(err) => return repository_error_response(&err),
+        }
+    }
+
+    HttpResponse::Created().json(FactoryResult { monsters, battles })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use serde_json::json;
+    use crate::api::config::repositories;
+    use crate::models::battle::BattleOutcome;
+    use crate::repository::memory_repository::InMemoryRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_create_monsters_and_battles_from_a_spec() {
+        let app = App::new().configure(repositories(Arc::new(InMemoryRepository::new()))).service(create_test_data);
+        let app = test::init_service(app).await;
+
+        let spec = json!({
+            "monsters": [{ "count": 2, "name": "slime", "hp": 10 }, { "name": "dragon", "attack": 90 }],
+            "battles": [{ "monster_a": 2, "monster_b": 0 }, { "monster_a": 0, "monster_b": 1, "outcome": "draw" }]
+        });
+        let req = test::TestRequest::post().uri("/test/factory").set_json(spec).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+
+        let result: FactoryResult = test::read_body_json(resp).await;
+        let names: Vec<&str> = result.monsters.iter().map(|monster| monster.name.as_str()).collect();
+        assert_eq!(names, vec!["slime-1", "slime-2", "dragon"]);
+        assert_eq!(result.monsters[2].stats, Stats { attack: 90, ..DEFAULT_STATS });
+        assert_eq!(result.battles[0].winner.as_ref(), Some(&result.monsters[2].id));
+        assert_eq!(result.battles[1].outcome, BattleOutcome::Draw);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_reject_battles_with_unknown_monsters() {
+        let app = App::new().configure(repositories(Arc::new(InMemoryRepository::new()))).service(create_test_data);
+        let app = test::init_service(app).await;
+
+        let spec = json!({ "monsters": [{ "count": 2 }], "battles": [{ "monster_a": 0, "monster_b": 2 }] });
+        let req = test::TestRequest::post().uri("/test/factory").set_json(spec).to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,333 @@
+use std::sync::Arc;
+use actix_web::{web, get, post, HttpResponse};
+use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
+use async_graphql::{Context, EmptySubscription, Enum, Object, Result, Schema, ID};
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use chrono::NaiveDateTime;
+use crate::battle_engine::{BattleEngine, BattleSetup};
+use crate::models::battle::{Battle, BattleOutcome};
+use crate::models::decay::StatDecay;
+use crate::models::monster::Monster;
+use crate::models::status_effect::StatusEffectRules;
+use crate::repository::battle_repository::BattleRepository;
+use crate::repository::item_repository::ItemRepository;
+use crate::repository::monster_repository::MonsterRepository;
+use crate::repository::move_repository::MoveRepository;
+use crate::repository::training_repository::TrainingRepository;
+use super::battle_apis::{battle_engine, find_monsters, monster_hidden_stats, monster_items, monster_moves, new_simulated_battle, store_battle};
+
+pub type MonstersSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+// Bounds on the queries, lists counting LIST_MULTIPLIER times their fields, so nesting them can't fan out unbounded.
+pub const MAX_QUERY_DEPTH: usize = 6;
+pub const MAX_QUERY_COMPLEXITY: usize = 1000;
+const LIST_MULTIPLIER: usize = 10;
+
+/*
+The schema holds no data, the repositories registered with `config::repositories`
+are attached to each request so GraphQL reads the same storage as the REST handlers.
+*/
+pub fn schema() -> MonstersSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .limit_complexity(MAX_QUERY_COMPLEXITY)
+        .finish()
+}
+
+#[post("/graphql")]
+#[allow(clippy::too_many_arguments)]
+pub async fn graphql(
+    schema: web::Data<MonstersSchema>,
+    monster_repository: web::Data<dyn MonsterRepository>,
+    battle_repository: web::Data<dyn BattleRepository>,
+    move_repository: web::Data<dyn MoveRepository>,
+    item_repository: web::Data<dyn ItemRepository>,
+    training_repository: web::Data<dyn TrainingRepository>,
+    decay: Option<web::Data<StatDecay>>,
+    status_effects: Option<web::Data<StatusEffectRules>>,
+    engine: Option<web::Data<dyn BattleEngine>>,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    let mut request = request
+        .into_inner()
+        .data(monster_repository.into_inner())
+        .data(battle_repository.into_inner())
+        .data(move_repository.into_inner())
+        .data(item_repository.into_inner())
+        .data(training_repository.into_inner())
+        .data(battle_engine(engine));
+    if let Some(decay) = decay {
+        request = request.data(*decay.into_inner());
+    }
+    if let Some(status_effects) = status_effects {
+        request = request.data(status_effects.into_inner());
+    }
+    schema.execute(request).await.into()
+}
+
+#[get("/graphql")]
+pub async fn graphql_playground() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(playground_source(GraphQLPlaygroundConfig::new("/api/graphql")))
+}
+
+fn monster_repository<'a>(ctx: &Context<'a>) -> &'a Arc<dyn MonsterRepository> {
+    ctx.data_unchecked::<Arc<dyn MonsterRepository>>()
+}
+
+fn battle_repository<'a>(ctx: &Context<'a>) -> &'a Arc<dyn BattleRepository> {
+    ctx.data_unchecked::<Arc<dyn BattleRepository>>()
+}
+
+fn move_repository<'a>(ctx: &Context<'a>) -> &'a Arc<dyn MoveRepository> {
+    ctx.data_unchecked::<Arc<dyn MoveRepository>>()
+}
+
+fn item_repository<'a>(ctx: &Context<'a>) -> &'a Arc<dyn ItemRepository> {
+    ctx.data_unchecked::<Arc<dyn ItemRepository>>()
+}
+
+fn training_repository<'a>(ctx: &Context<'a>) -> &'a Arc<dyn TrainingRepository> {
+    ctx.data_unchecked::<Arc<dyn TrainingRepository>>()
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+#[graphql(name = "BattleOutcome", remote = "BattleOutcome")]
+enum BattleOutcomeValue {
+    Win,
+    Draw,
+    Forfeit,
+}
+
+struct MonsterNode(Monster);
+
+#[Object(name = "Monster")]
+impl MonsterNode {
+    async fn id(&self) -> ID {
+        ID(self.0.id.clone())
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn image_url(&self) -> &str {
+        &self.0.image_url
+    }
+
+    async fn attack(&self) -> i32 {
+        self.0.stats.attack
+    }
+
+    async fn defense(&self) -> i32 {
+        self.0.stats.defense
+    }
+
+    async fn hp(&self) -> i32 {
+        self.0.stats.hp
+    }
+
+    async fn speed(&self) -> i32 {
+        self.0.stats.speed
+    }
+
+    async fn stat_total(&self) -> i64 {
+        self.0.stats.total()
+    }
+
+    async fn power_score(&self) -> i64 {
+        self.0.stats.power_score()
+    }
+
+    async fn created_at(&self) -> Option<NaiveDateTime> {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> Option<NaiveDateTime> {
+        self.0.updated_at
+    }
+
+    // To send back in the `version` of a REST update.
+    async fn version(&self) -> i32 {
+        self.0.version
+    }
+
+    // Battle history, newest first.
+    #[graphql(complexity = "LIST_MULTIPLIER * child_complexity")]
+    async fn battles(&self, ctx: &Context<'_>) -> Vec<BattleNode> {
+        battle_repository(ctx)
+            .get_battles_by_monster(&self.0.id)
+            .into_iter()
+            .map(BattleNode)
+            .collect()
+    }
+}
+
+struct BattleNode(Battle);
+
+#[Object(name = "Battle")]
+impl BattleNode {
+    async fn id(&self) -> ID {
+        ID(self.0.id.clone())
+    }
+
+    async fn monster_a(&self, ctx: &Context<'_>) -> Option<MonsterNode> {
+        monster_repository(ctx).get_monster_by_id(&self.0.monster_a).map(MonsterNode)
+    }
+
+    async fn monster_b(&self, ctx: &Context<'_>) -> Option<MonsterNode> {
+        monster_repository(ctx).get_monster_by_id(&self.0.monster_b).map(MonsterNode)
+    }
+
+    async fn winner(&self, ctx: &Context<'_>) -> Option<MonsterNode> {
+        let winner = self.0.winner.as_deref()?;
+        monster_repository(ctx).get_monster_by_id(winner).map(MonsterNode)
+    }
+
+    async fn outcome(&self) -> BattleOutcomeValue {
+        self.0.outcome.into()
+    }
+
+    async fn manual(&self) -> bool {
+        self.0.manual
+    }
+
+    async fn created_at(&self) -> Option<NaiveDateTime> {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> Option<NaiveDateTime> {
+        self.0.updated_at
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    #[graphql(complexity = "LIST_MULTIPLIER * child_complexity")]
+    async fn monsters(&self, ctx: &Context<'_>) -> Vec<MonsterNode> {
+        monster_repository(ctx).get_monsters().into_iter().map(MonsterNode).collect()
+    }
+
+    async fn monster(&self, ctx: &Context<'_>, id: ID) -> Option<MonsterNode> {
+        monster_repository(ctx).get_monster_by_id(&id).map(MonsterNode)
+    }
+
+    #[graphql(complexity = "LIST_MULTIPLIER * child_complexity")]
+    async fn battles(&self, ctx: &Context<'_>) -> Vec<BattleNode> {
+        battle_repository(ctx).get_battles().into_iter().map(BattleNode).collect()
+    }
+
+    async fn battle(&self, ctx: &Context<'_>, id: ID) -> Option<BattleNode> {
+        battle_repository(ctx).get_battle_by_id(&id).map(BattleNode)
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    // Runs the same simulation as `POST /battles`.
+    async fn create_battle(&self, ctx: &Context<'_>, monster_a: ID, monster_b: ID) -> Result<BattleNode> {
+        let (monster_a, monster_b) = find_monsters(monster_repository(ctx).as_ref(), &Some(monster_a.0), &Some(monster_b.0))?;
+        let moves = monster_moves(move_repository(ctx).as_ref(), &monster_a, &monster_b);
+        let items = monster_items(item_repository(ctx).as_ref(), &monster_a, &monster_b);
+        let hidden_stats = monster_hidden_stats(training_repository(ctx).as_ref(), &monster_a, &monster_b);
+        let setup = BattleSetup { moves, items, hidden_stats, status_effects: ctx.data_opt::<Arc<StatusEffectRules>>().map(Arc::as_ref), ..BattleSetup::new(monster_a, monster_b) };
+        let engine = ctx.data_unchecked::<Arc<dyn BattleEngine>>();
+        let battle = store_battle(battle_repository(ctx).as_ref(), new_simulated_battle(engine.as_ref(), setup, ctx.data_opt::<StatDecay>()))?;
+        Ok(BattleNode(battle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+    use actix_web::web::Data;
+    use serde_json::{json, Value};
+    use crate::api::config::repositories;
+    use crate::models::monster::Stats;
+    use crate::repository::memory_repository::InMemoryRepository;
+
+    use super::*;
+
+    fn new_monster(name: &str, stats: Stats) -> Monster {
+        Monster {
+            id: String::new(),
+            name: name.to_string(),
+            image_url: "https://loremflickr.com/640/480".to_string(),
+            stats,
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_create_a_battle_and_query_it_with_nested_monsters() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let monster_a = repository.create_monster(new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 })).unwrap();
+        let monster_b = repository.create_monster(new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 50, speed: 80 })).unwrap();
+
+        let app = App::new()
+            .configure(repositories(repository))
+            .app_data(Data::new(schema()))
+            .service(graphql);
+        let app = test::init_service(app).await;
+
+        let mutation = format!(r#"mutation {{ createBattle(monsterA: "{}", monsterB: "{}") {{ outcome winner {{ name }} }} }}"#, monster_a.id, monster_b.id);
+        let req = test::TestRequest::post().uri("/graphql").set_json(json!({ "query": mutation })).to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["createBattle"], json!({ "outcome": "WIN", "winner": { "name": "monster-a" } }));
+
+        let query = format!(r#"{{ monster(id: "{}") {{ name statTotal battles {{ monsterA {{ name }} monsterB {{ name }} }} }} }}"#, monster_b.id);
+        let req = test::TestRequest::post().uri("/graphql").set_json(json!({ "query": query })).to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["data"]["monster"]["statTotal"], 160);
+        assert_eq!(body["data"]["monster"]["battles"], json!([{ "monsterA": { "name": "monster-a" }, "monsterB": { "name": "monster-b" } }]));
+    }
+
+    #[actix_rt::test]
+    async fn test_should_return_an_error_when_a_battle_monster_does_not_exist() {
+        let app = App::new()
+            .configure(repositories(Arc::new(InMemoryRepository::new())))
+            .app_data(Data::new(schema()))
+            .service(graphql);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::post()
+            .uri("/graphql")
+            .set_json(json!({ "query": r#"mutation { createBattle(monsterA: "123", monsterB: "456") { id } }"# }))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body["errors"][0]["message"], "Monster A id not found");
+    }
+
+    #[actix_rt::test]
+    async fn test_should_reject_queries_nested_too_deep_or_too_complex() {
+        let app = App::new()
+            .configure(repositories(Arc::new(InMemoryRepository::new())))
+            .app_data(Data::new(schema()))
+            .service(graphql);
+        let app = test::init_service(app).await;
+
+        let deep = r#"{ battle(id: "1") { monsterA { battles { monsterB { battles { monsterA { id } } } } } } }"#;
+        let req = test::TestRequest::post().uri("/graphql").set_json(json!({ "query": deep })).to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["errors"][0]["message"], "Query is nested too deep.");
+
+        let wide = r#"{ battles { monsterA { battles { monsterB { battles { id } } } } } }"#;
+        let req = test::TestRequest::post().uri("/graphql").set_json(json!({ "query": wide })).to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["errors"][0]["message"], "Query is too complex.");
+    }
+}
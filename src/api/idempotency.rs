@@ -0,0 +1,261 @@
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+use actix_web::body::{self, BodySize, BoxBody, MessageBody};
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::{web, Error, HttpMessage, HttpResponse};
+use chrono::Utc;
+use futures::future::{self, LocalBoxFuture, Ready};
+use futures::{FutureExt, StreamExt};
+use sha2::{Digest, Sha256};
+use crate::models::idempotency::{check_idempotency_key, IdempotencyKey, IDEMPOTENCY_KEY_HEADER, IDEMPOTENCY_KEY_TTL_HOURS, IDEMPOTENT_REPLAYED_HEADER};
+use crate::repository::idempotency_repository::IdempotencyRepository;
+use crate::repository::rate_limit_repository::RateLimitRepository;
+use super::auth;
+use super::error::{repository_error_response, ApiError};
+use super::payload::{PayloadLimits, DEFAULT_MULTIPART_BYTES};
+
+pub const IDEMPOTENCY_PURGE_SECONDS: u64 = 3600;
+
+/*
+Makes POSTs sent with an Idempotency-Key safe to retry. The first request with a key claims it for its client,
+see `auth::client`, and its response is stored for a day. Sending the key again replays that response, with
+Idempotent-Replayed set, instead of running the handler. A key still in progress answers 409, and one used for
+another method, path or body 422. Server errors free the key, so the request can be retried for real. Streamed
+responses, like the NDJSON of `POST /battles/bulk`, are passed through as they come instead of being buffered and
+stored, their key staying used and answering 409.
+
+The body is read up front to be hashed, up to the largest of the PayloadLimits, and handed to the handler once the
+key is claimed. Being a `Transform` rather than a `wrap_fn`, it can call the handler after that wait.
+*/
+pub struct ReplayIdempotentRequests;
+
+impl<S, B> Transform<S, ServiceRequest> for ReplayIdempotentRequests
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = ReplayIdempotentRequestsService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        future::ok(ReplayIdempotentRequestsService { service: Rc::new(service) })
+    }
+}
+
+pub struct ReplayIdempotentRequestsService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for ReplayIdempotentRequestsService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<ServiceResponse<BoxBody>, Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        replay_idempotent_requests(req, self.service.clone()).boxed_local()
+    }
+}
+
+async fn replay_idempotent_requests<S, B>(mut req: ServiceRequest, srv: Rc<S>) -> Result<ServiceResponse<BoxBody>, Error>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody + 'static,
+{
+    let repositories = (req.app_data::<web::Data<dyn IdempotencyRepository>>(), req.app_data::<web::Data<dyn RateLimitRepository>>());
+    let (Some(key), (Some(repository), Some(rate_limit_repository))) = (req.headers().get(IDEMPOTENCY_KEY_HEADER), repositories) else {
+        return srv.call(req).await.map(ServiceResponse::map_into_boxed_body);
+    };
+    if req.method() != Method::POST {
+        return srv.call(req).await.map(ServiceResponse::map_into_boxed_body);
+    }
+    let key = match key.to_str().map_err(|_| "The idempotency key must only have visible ASCII characters".to_string()) {
+        Ok(key) => check_idempotency_key(key).map(|()| key.to_string()),
+        Err(message) => Err(message),
+    };
+    let key = match key {
+        Ok(key) => key,
+        Err(message) => return Ok(req.into_response(HttpResponse::BadRequest().json(message))),
+    };
+
+    let repository = repository.clone();
+    let now = Utc::now().naive_utc();
+    let client = auth::client(req.request(), rate_limit_repository.get_ref());
+    let limit = req.app_data::<web::Data<PayloadLimits>>().map_or(DEFAULT_MULTIPART_BYTES, |limits| limits.json_bytes.max(limits.multipart_bytes));
+    let mut payload = req.take_payload();
+    let mut request_body = web::BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        if request_body.len() + chunk.len() > limit {
+            return Ok(req.into_response(HttpResponse::PayloadTooLarge().json(ApiError::payload_too_large(limit))));
+        }
+        request_body.extend_from_slice(&chunk);
+    }
+    let claim = IdempotencyKey {
+        client,
+        key,
+        method: req.method().to_string(),
+        path: req.uri().to_string(),
+        status: None,
+        content_type: None,
+        body: None,
+        created_at: now,
+        request_hash: Some(format!("{:x}", Sha256::digest(&request_body))),
+    };
+    let response = match repository.claim_idempotency_key(&claim, now - chrono::Duration::hours(IDEMPOTENCY_KEY_TTL_HOURS)) {
+        Ok(None) => None,
+        Ok(Some(claimed_key)) if !claimed_key.is_for(&claim.method, &claim.path) => {
+            Some(HttpResponse::UnprocessableEntity().json("The idempotency key was already used for another request"))
+        }
+        Ok(Some(claimed_key)) if !claimed_key.has_body(&claim.request_hash) => {
+            Some(HttpResponse::UnprocessableEntity().json("The idempotency key was already used for a request with another body"))
+        }
+        Ok(Some(IdempotencyKey { status: None, .. })) => Some(HttpResponse::Conflict().json("A request with the idempotency key is still in progress")),
+        Ok(Some(IdempotencyKey { body: None, .. })) => Some(HttpResponse::Conflict().json("The response to the idempotency key was streamed and can't be replayed")),
+        Ok(Some(claimed_key)) => Some(replay(claimed_key)),
+        Err(err) => Some(repository_error_response(&err)),
+    };
+    if let Some(response) = response {
+        return Ok(req.into_response(response));
+    }
+    req.set_payload(Payload::from(request_body.freeze()));
+
+    let release = || {
+        if let Err(err) = repository.release_idempotency_key(&claim.client, &claim.key) {
+            tracing::error!(key = %claim.key, error = %err, "Failed to release idempotency key");
+        }
+    };
+    let response = match srv.call(req).await {
+        Ok(response) if !response.status().is_server_error() => response,
+        response => {
+            release();
+            return response.map(ServiceResponse::map_into_boxed_body);
+        }
+    };
+    let status = i32::from(response.status().as_u16());
+    let content_type = response.headers().get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok()).map(str::to_string);
+    if matches!(response.response().body().size(), BodySize::Stream) {
+        if let Err(err) = repository.complete_idempotency_key(&claim.client, &claim.key, status, content_type.as_deref(), None) {
+            tracing::error!(key = %claim.key, error = %err, "Failed to store idempotent response");
+            release();
+        }
+        return Ok(response.map_into_boxed_body());
+    }
+    let (req, response) = response.into_parts();
+    let (response, response_body) = response.into_parts();
+    let response_body = match body::to_bytes(response_body).await {
+        Ok(response_body) => response_body,
+        Err(err) => {
+            release();
+            let err: Box<dyn std::error::Error> = err.into();
+            return Err(actix_web::error::ErrorInternalServerError(err.to_string()));
+        }
+    };
+    if let Err(err) = repository.complete_idempotency_key(&claim.client, &claim.key, status, content_type.as_deref(), Some(&response_body)) {
+        tracing::error!(key = %claim.key, error = %err, "Failed to store idempotent response");
+        release();
+    }
+    Ok(ServiceResponse::new(req, response.set_body(BoxBody::new(response_body))))
+}
+
+fn replay(claimed_key: IdempotencyKey) -> HttpResponse {
+    let status = claimed_key.status.and_then(|status| StatusCode::from_u16(status as u16).ok()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut response = HttpResponse::build(status);
+    response.insert_header((IDEMPOTENT_REPLAYED_HEADER, "true"));
+    if let Some(content_type) = claimed_key.content_type {
+        response.content_type(content_type);
+    }
+    response.body(claimed_key.body.unwrap_or_default())
+}
+
+// Deletes the expired keys every hour, a key being reclaimed anyway once expired.
+pub async fn purge_expired_idempotency_keys(repository: Arc<dyn IdempotencyRepository>) {
+    let purge_period = Duration::from_secs(IDEMPOTENCY_PURGE_SECONDS);
+    let mut interval = actix_rt::time::interval_at(actix_rt::time::Instant::now() + purge_period, purge_period);
+    loop {
+        interval.tick().await;
+        let expired_before = Utc::now().naive_utc() - chrono::Duration::hours(IDEMPOTENCY_KEY_TTL_HOURS);
+        if let Err(err) = repository.delete_expired_idempotency_keys(expired_before) {
+            tracing::error!(error = %err, "Failed to delete expired idempotency keys");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, http, App};
+    use crate::api::config::{config, repositories};
+    use crate::models::monster::Monster;
+    use crate::repository::memory_repository::InMemoryRepository;
+    use crate::repository::monster_repository::MonsterRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_replay_the_response_of_a_repeated_idempotency_key() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let app = test::init_service(App::new().configure(repositories(repository.clone())).configure(config)).await;
+        let body = serde_json::json!({ "name": "retried", "image_url": "https://example.com/monster.png", "attack": 40, "defense": 20, "hp": 50, "speed": 80 });
+        let create = |key: &str, uri: &str| test::TestRequest::post().uri(uri).insert_header((IDEMPOTENCY_KEY_HEADER, key.to_string())).set_json(&body).to_request();
+
+        let resp = test::call_service(&app, create("retry-1", "/api/monsters")).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        assert!(resp.headers().get(IDEMPOTENT_REPLAYED_HEADER).is_none());
+        let created: Monster = test::read_body_json(resp).await;
+        let resp = test::call_service(&app, create("retry-1", "/api/monsters")).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        assert_eq!(resp.headers().get(IDEMPOTENT_REPLAYED_HEADER).unwrap(), "true");
+        let replayed: Monster = test::read_body_json(resp).await;
+        assert_eq!(replayed.id, created.id);
+        assert_eq!(repository.get_monsters().len(), 1);
+
+        assert_eq!(test::call_service(&app, create("retry-1", "/api/monsters/bulk")).await.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+        // Nor for a request with another body.
+        let mut other_body = body.clone();
+        other_body["name"] = serde_json::json!("another");
+        let req = test::TestRequest::post().uri("/api/monsters").insert_header((IDEMPOTENCY_KEY_HEADER, "retry-1")).set_json(&other_body).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(repository.get_monsters().len(), 1);
+        assert_eq!(test::call_service(&app, create("", "/api/monsters")).await.status(), http::StatusCode::BAD_REQUEST);
+        // Keys belong to the client that sent them.
+        let req = test::TestRequest::post()
+            .uri("/api/monsters")
+            .peer_addr("10.0.0.2:4000".parse().unwrap())
+            .insert_header((IDEMPOTENCY_KEY_HEADER, "retry-1"))
+            .set_json(&body)
+            .to_request();
+        assert!(test::call_service(&app, req).await.headers().get(IDEMPOTENT_REPLAYED_HEADER).is_none());
+        assert_eq!(repository.get_monsters().len(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_pass_streamed_responses_through_and_keep_their_key_used() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let stream = || HttpResponse::Ok().streaming(futures::stream::iter(["first\n", "second\n"].map(|line| Ok::<_, Error>(web::Bytes::from(line)))));
+        let app = test::init_service(
+            App::new()
+                .configure(repositories(repository.clone()))
+                .route("/stream", web::post().to(move || async move { stream() }))
+                .wrap(ReplayIdempotentRequests),
+        )
+        .await;
+
+        let stream_request = || test::TestRequest::post().uri("/stream").insert_header((IDEMPOTENCY_KEY_HEADER, "stream-1")).to_request();
+        let resp = test::call_service(&app, stream_request()).await;
+        assert!(resp.headers().get(IDEMPOTENT_REPLAYED_HEADER).is_none());
+        assert_eq!(test::read_body(resp).await, "first\nsecond\n");
+
+        // The stream wasn't stored, sending the key again doesn't run the handler a second time.
+        assert_eq!(test::call_service(&app, stream_request()).await.status(), http::StatusCode::CONFLICT);
+    }
+}
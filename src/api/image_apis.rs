@@ -0,0 +1,181 @@
+use actix_web::{web, get, post, HttpRequest, HttpResponse, Error};
+use actix_web::web::BytesMut;
+use actix_multipart::Multipart;
+use futures::TryStreamExt;
+use image::ImageFormat;
+use serde::Deserialize;
+use crate::repository::monster_repository::MonsterRepository;
+use crate::storage::Storage;
+use crate::thumbnails::{thumbnail_key, DEFAULT_THUMBNAIL_SIZE, THUMBNAIL_SIZES};
+use super::config::API_PREFIX;
+use super::error::repository_error_response;
+
+// Same cap as the sprites fetched for cards, which these images end up as.
+pub const MAX_IMAGE_BYTES: usize = 2 * 1024 * 1024;
+
+const IMAGE_TYPES: [(&str, ImageFormat); 2] = [("image/png", ImageFormat::Png), ("image/jpeg", ImageFormat::Jpeg)];
+
+#[derive(Deserialize)]
+pub struct ThumbnailQuery {
+    size: Option<u32>,
+}
+
+pub(crate) fn image_key(monster_id: &str) -> String {
+    format!("monsters/{}", monster_id)
+}
+
+/*
+Stores the first file of the form as the monster's image and points its `image_url` at `GET /monsters/{id}/image`.
+Only PNG and JPEG files of at most 2 MiB are taken, and their content has to match the type they are sent as.
+*/
+#[post("/monsters/{id}/image")]
+pub async fn upload_monster_image(
+    req: HttpRequest,
+    monster_repository: web::Data<dyn MonsterRepository>,
+    storage: web::Data<dyn Storage>,
+    id: web::Path<String>,
+    mut payload: Multipart,
+) -> Result<HttpResponse, Error> {
+    if monster_repository.get_monster_by_id(&id).is_none() {
+        return Ok(HttpResponse::NotFound().json("Monster not found"));
+    }
+
+    let Some(mut field) = payload.try_next().await? else {
+        return Ok(HttpResponse::BadRequest().json("No file provided"));
+    };
+    let declared = field.content_type().map(|mime| mime.essence_str().to_string());
+    let Some((content_type, format)) = IMAGE_TYPES.into_iter().find(|(content_type, _)| declared.as_deref() == Some(*content_type)) else {
+        return Ok(HttpResponse::UnsupportedMediaType().json("Images must be image/png or image/jpeg"));
+    };
+    let mut bytes = BytesMut::new();
+    while let Some(chunk) = field.try_next().await? {
+        if bytes.len() + chunk.len() > MAX_IMAGE_BYTES {
+            return Ok(HttpResponse::PayloadTooLarge().json(format!("Images must be at most {} bytes", MAX_IMAGE_BYTES)));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    if image::guess_format(&bytes).ok() != Some(format) {
+        return Ok(HttpResponse::BadRequest().json(format!("The file isn't a valid {} image", content_type)));
+    }
+
+    if let Err(err) = storage.put(&image_key(&id), content_type, bytes.freeze()).await {
+        tracing::error!(monster_id = %id, error = %err, "Failed to store monster image");
+        return Ok(HttpResponse::InternalServerError().json("Failed to store the image"));
+    }
+    let connection = req.connection_info();
+    let image_url = format!("{}://{}{}/monsters/{}/image", connection.scheme(), connection.host(), API_PREFIX, id);
+    Ok(match monster_repository.set_monster_image_url(&id, &image_url) {
+        Ok(Some(monster)) => HttpResponse::Ok().json(monster),
+        Ok(None) => HttpResponse::NotFound().json("Monster not found"),
+        Err(err) => repository_error_response(&err),
+    })
+}
+
+// The uploaded image of the monster, typed from its content.
+#[get("/monsters/{id}/image")]
+pub async fn get_monster_image(monster_repository: web::Data<dyn MonsterRepository>, storage: web::Data<dyn Storage>, id: web::Path<String>) -> HttpResponse {
+    if monster_repository.get_monster_by_id(&id).is_none() {
+        return HttpResponse::NotFound().json("Monster not found");
+    }
+    match storage.get(&image_key(&id)).await {
+        Ok(Some(bytes)) => {
+            let content_type = image::guess_format(&bytes).map_or("application/octet-stream", |format| format.to_mime_type());
+            HttpResponse::Ok().content_type(content_type).body(bytes)
+        }
+        Ok(None) => HttpResponse::NotFound().json("Monster has no uploaded image"),
+        Err(err) => {
+            tracing::error!(monster_id = %id, error = %err, "Failed to read monster image");
+            HttpResponse::InternalServerError().json("Failed to read the image")
+        }
+    }
+}
+
+// A PNG thumbnail of the monster's image, made in the background by the thumbnail task once the image is fetched.
+#[get("/monsters/{id}/thumbnail")]
+pub async fn get_monster_thumbnail(monster_repository: web::Data<dyn MonsterRepository>, storage: web::Data<dyn Storage>, id: web::Path<String>, query: web::Query<ThumbnailQuery>) -> HttpResponse {
+    let size = query.size.unwrap_or(DEFAULT_THUMBNAIL_SIZE);
+    if !THUMBNAIL_SIZES.contains(&size) {
+        return HttpResponse::BadRequest().json(format!("Size must be one of {:?}", THUMBNAIL_SIZES));
+    }
+    let Some(monster) = monster_repository.get_monster_by_id(&id) else {
+        return HttpResponse::NotFound().json("Monster not found");
+    };
+    if monster.image_broken {
+        return HttpResponse::NotFound().json("Monster image is broken");
+    }
+    match storage.get(&thumbnail_key(&id, size)).await {
+        Ok(Some(bytes)) => HttpResponse::Ok().content_type("image/png").body(bytes),
+        Ok(None) => HttpResponse::NotFound().json("Thumbnail not made yet"),
+        Err(err) => {
+            tracing::error!(monster_id = %id, error = %err, "Failed to read monster thumbnail");
+            HttpResponse::InternalServerError().json("Failed to read the thumbnail")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use actix_multipart_test::MultiPartFormDataBuilder;
+    use crate::api::config::repositories;
+    use crate::models::monster::{Monster, Stats};
+    use crate::repository::memory_repository::InMemoryRepository;
+    use crate::storage::DiskStorage;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_upload_and_serve_monster_images() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let monster = repository.create_monster(Monster {
+            id: String::new(),
+            name: "pictured".to_string(),
+            image_url: "https://loremflickr.com/640/480".to_string(),
+            stats: Stats { attack: 40, defense: 20, hp: 50, speed: 80 },
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        }).unwrap();
+        let directory = tempfile::tempdir().unwrap();
+        let storage: Arc<dyn Storage> = Arc::new(DiskStorage::new(directory.path().join("uploads")));
+        let app = App::new()
+            .configure(repositories(repository.clone()))
+            .app_data(web::Data::from(storage))
+            .service(upload_monster_image)
+            .service(get_monster_image);
+        let app = test::init_service(app).await;
+
+        let png_path = directory.path().join("sprite.png");
+        let mut png = Vec::new();
+        image::RgbaImage::new(4, 4).write_to(&mut Cursor::new(&mut png), ImageFormat::Png).unwrap();
+        std::fs::write(&png_path, &png).unwrap();
+        let upload = |content_type: &str| {
+            let mut form = MultiPartFormDataBuilder::new();
+            form.with_file(png_path.clone(), "file", content_type, "sprite.png");
+            let (header, body) = form.build();
+            test::TestRequest::post().uri(&format!("/monsters/{}/image", monster.id)).insert_header(header).set_payload(body).to_request()
+        };
+
+        let resp = test::call_service(&app, upload("image/gif")).await;
+        assert_eq!(resp.status(), http::StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let resp = test::call_service(&app, upload("image/jpeg")).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let req = test::TestRequest::get().uri(&format!("/monsters/{}/image", monster.id)).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::NOT_FOUND);
+
+        let uploaded: Monster = test::call_and_read_body_json(&app, upload("image/png")).await;
+        assert_eq!(uploaded.image_url, format!("http://localhost:8080/api/monsters/{}/image", monster.id));
+        let req = test::TestRequest::get().uri(&format!("/monsters/{}/image", monster.id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(http::header::CONTENT_TYPE).unwrap(), "image/png");
+        assert_eq!(test::read_body(resp).await, png);
+    }
+}
@@ -0,0 +1,305 @@
+use std::time::Duration;
+use actix_web::{web, get, post, HttpResponse};
+use chrono::NaiveDateTime;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use crate::balance;
+use crate::config::AppConfig;
+use crate::models::battle::{Battle, BattleOutcome};
+use crate::models::growth;
+use crate::models::interactive_battle::{InteractiveBattle, TurnSubmissionError};
+use crate::models::monster::Monster;
+use crate::models::moves::Move;
+use crate::repository::battle_repository::BattleRepository;
+use crate::repository::monster_repository::MonsterRepository;
+use crate::repository::move_repository::MoveRepository;
+use crate::battle_engine::{BattleResult, TurnEvent};
+use super::battle_apis::{find_monsters, monster_moves, BATTLE_FEED};
+use super::error::repository_error_response;
+
+const DEFAULT_TURN_TIMEOUT_SECONDS: u64 = 60;
+
+// Time the players of an interactive battle have to submit each turn, read from INTERACTIVE_TURN_TIMEOUT_SECONDS.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TurnTimeout(pub Duration);
+
+impl Default for TurnTimeout {
+    fn default() -> Self {
+        TurnTimeout(Duration::from_secs(DEFAULT_TURN_TIMEOUT_SECONDS))
+    }
+}
+
+impl TurnTimeout {
+    pub fn from_config(config: &AppConfig) -> Result<Self, String> {
+        match config.var("INTERACTIVE_TURN_TIMEOUT_SECONDS") {
+            Some(timeout) => match timeout.trim().parse::<u32>() {
+                Ok(seconds) if seconds > 0 => Ok(TurnTimeout(Duration::from_secs(seconds.into()))),
+                _ => Err(format!("INTERACTIVE_TURN_TIMEOUT_SECONDS must be a positive integer, got {:?}", timeout)),
+            },
+            None => Ok(TurnTimeout::default()),
+        }
+    }
+
+    fn deadline(&self, now: NaiveDateTime) -> NaiveDateTime {
+        now + chrono::Duration::from_std(self.0).expect("Turn timeouts fit in a chrono duration")
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateInteractiveBattleRequest {
+    monster_a: Option<String>,
+    monster_b: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SubmitTurnRequest {
+    monster: Option<String>,
+    // Id of one of the monster's moves, a plain attack when left out.
+    #[serde(default, rename = "move", skip_serializing_if = "Option::is_none")]
+    move_id: Option<String>,
+}
+
+#[post("/battles/interactive")]
+pub async fn create_interactive_battle(monster_repository: web::Data<dyn MonsterRepository>, battle_repository: web::Data<dyn BattleRepository>, turn_timeout: Option<web::Data<TurnTimeout>>, battle_request: web::Json<CreateInteractiveBattleRequest>) -> HttpResponse {
+    let (monster_a, monster_b) = match find_monsters(monster_repository.as_ref(), &battle_request.monster_a, &battle_request.monster_b) {
+        Ok(monsters) => monsters,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+    // Players submit their turns by monster, each side needs its own.
+    if monster_a.id == monster_b.id {
+        return HttpResponse::BadRequest().json("Monsters A and B must be different");
+    }
+
+    let turn_timeout = turn_timeout.map(|turn_timeout| *turn_timeout.into_inner()).unwrap_or_default();
+    let battle = InteractiveBattle::new(&monster_a, &monster_b, turn_timeout.deadline(chrono::Utc::now().naive_utc()));
+    match battle_repository.create_interactive_battle(battle) {
+        Ok(battle) => HttpResponse::Created().json(battle),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+// Battles whose turn timed out are only ended once they are looked at.
+#[get("/battles/interactive/{id}")]
+pub async fn get_interactive_battle_by_id(battle_repository: web::Data<dyn BattleRepository>, id: web::Path<String>) -> HttpResponse {
+    match battle_repository.play_interactive_battle(&id, &mut |battle| battle.expire(chrono::Utc::now().naive_utc())) {
+        Ok(Some((battle, record))) => {
+            publish(record);
+            HttpResponse::Ok().json(battle)
+        }
+        Ok(None) => HttpResponse::NotFound().json("Interactive battle not found"),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+/*
+Submits the move of a monster for the current turn. The first player is answered with 202 until the other
+player submits too, the second one with the resolved turn.
+*/
+#[post("/battles/{id}/turns")]
+pub async fn submit_turn(
+    monster_repository: web::Data<dyn MonsterRepository>,
+    battle_repository: web::Data<dyn BattleRepository>,
+    move_repository: web::Data<dyn MoveRepository>,
+    turn_timeout: Option<web::Data<TurnTimeout>>,
+    id: web::Path<String>,
+    turn_request: web::Json<SubmitTurnRequest>,
+) -> HttpResponse {
+    let Some(monster_id) = turn_request.monster.as_deref() else {
+        return HttpResponse::BadRequest().json("Monster id is required");
+    };
+    let Some(battle) = battle_repository.get_interactive_battle_by_id(&id) else {
+        return HttpResponse::NotFound().json("Interactive battle not found");
+    };
+    if monster_id != battle.monster_a && monster_id != battle.monster_b {
+        return HttpResponse::BadRequest().json("The monster is not in this battle");
+    }
+    // Deleting a monster deletes its interactive battles.
+    let Ok((monster_a, monster_b)) = find_monsters(monster_repository.as_ref(), &Some(battle.monster_a), &Some(battle.monster_b)) else {
+        return HttpResponse::NotFound().json("Interactive battle not found");
+    };
+    let moves = monster_moves(move_repository.as_ref(), &monster_a, &monster_b);
+    let known_moves = if monster_id == monster_a.id { &moves.0 } else { &moves.1 };
+    if turn_request.move_id.as_ref().is_some_and(|move_id| !known_moves.iter().any(|known_move| &known_move.id == move_id)) {
+        return HttpResponse::BadRequest().json("The monster doesn't know this move");
+    }
+
+    let turn_timeout = turn_timeout.map(|turn_timeout| *turn_timeout.into_inner()).unwrap_or_default();
+    let mut rejection = None;
+    let played = battle_repository.play_interactive_battle(&id, &mut |battle| {
+        let now = chrono::Utc::now().naive_utc();
+        if let Some(record) = battle.expire(now) {
+            rejection = Some(TurnSubmissionError::Finished);
+            return Some(record);
+        }
+        if let Err(err) = battle.submit(monster_id, turn_request.move_id.clone()) {
+            rejection = Some(err);
+            return None;
+        }
+        if !battle.both_ready() {
+            return None;
+        }
+        resolve_turn(battle, (&monster_a, &monster_b), &moves, turn_timeout.deadline(now), &mut rand::thread_rng())
+    });
+
+    match (played, rejection) {
+        (Ok(Some((battle, record))), rejection) => {
+            publish(record);
+            match rejection {
+                Some(TurnSubmissionError::Finished) => HttpResponse::Conflict().json("The battle is over"),
+                Some(TurnSubmissionError::AlreadySubmitted) => HttpResponse::Conflict().json("The monster already submitted its move for this turn"),
+                Some(TurnSubmissionError::NotInBattle) => HttpResponse::BadRequest().json("The monster is not in this battle"),
+                None if !battle.finished && (battle.monster_a_ready || battle.monster_b_ready) => HttpResponse::Accepted().json(battle),
+                None => HttpResponse::Ok().json(battle),
+            }
+        }
+        (Ok(None), _) => HttpResponse::NotFound().json("Interactive battle not found"),
+        (Err(err), _) => repository_error_response(&err),
+    }
+}
+
+// Publishes the battle recorded when an interactive battle finished to the battle feed.
+fn publish(record: Option<Battle>) {
+    if let Some(record) = record {
+        BATTLE_FEED.publish(record);
+    }
+}
+
+/*
+Resolves a turn both players submitted. The monsters attack in the order of the simulator with the move
+their player chose, a move hitting for its power in percent of the damage or missing on a failed accuracy
+roll. Move effects and the status effects rule don't apply to interactive battles. Returns the battle to
+record when a monster was knocked out, otherwise opens the next turn.
+*/
+fn resolve_turn(battle: &mut InteractiveBattle, (monster_a, monster_b): (&Monster, &Monster), (monster_a_moves, monster_b_moves): &(Vec<Move>, Vec<Move>), turn_deadline: NaiveDateTime, rolls: &mut dyn RngCore) -> Option<Battle> {
+    let chosen_move = |move_id: &Option<String>, moves: &'_ [Move]| move_id.as_ref().and_then(|move_id| moves.iter().find(|known_move| &known_move.id == move_id)).cloned();
+    let monsters = [monster_a, monster_b];
+    let used_moves = [chosen_move(&battle.monster_a_move, monster_a_moves), chosen_move(&battle.monster_b_move, monster_b_moves)];
+    let known_moves = [monster_a_moves.as_slice(), monster_b_moves.as_slice()];
+    let balance = balance::current();
+    let mut hp = [battle.monster_a_hp, battle.monster_b_hp];
+    let order = if monster_a.stats.attacks_before(&monster_b.stats) { [0, 1] } else { [1, 0] };
+
+    let mut events = Vec::new();
+    for attacker in order {
+        let defender = 1 - attacker;
+        let mut event = TurnEvent {
+            turn: battle.turn as u32,
+            attacker: monsters[attacker].id.clone(),
+            defender: monsters[defender].id.clone(),
+            defender_hp: hp[defender],
+            ..TurnEvent::default()
+        };
+        let base_damage = monsters[attacker].stats.damage_against(&monsters[defender].stats, balance.damage.minimum);
+        let mut damage = growth::scale_damage(base_damage, monsters[attacker].level, monsters[defender].level, &balance);
+        if let Some(used_move) = &used_moves[attacker] {
+            event.used_move = Some(used_move.name.clone());
+            match used_move.hit(damage, rolls) {
+                Some(move_damage) => damage = balance.element_damage(move_damage, used_move, known_moves[defender]),
+                None => {
+                    event.missed = true;
+                    events.push(event);
+                    continue;
+                }
+            }
+        }
+        event.damage = damage;
+        hp[defender] = (hp[defender] - damage).max(0);
+        event.defender_hp = hp[defender];
+        events.push(event);
+        if hp[defender] == 0 {
+            break;
+        }
+    }
+
+    [battle.monster_a_hp, battle.monster_b_hp] = hp;
+    let winner = events.last().and_then(|event| event.winner().map(str::to_string));
+    if let Some(turns) = battle.turns.as_array_mut() {
+        turns.extend(events.iter().map(|event| serde_json::to_value(event).expect("Turn events serialize to JSON")));
+    }
+    match winner {
+        Some(winner) => {
+            let turns: Vec<TurnEvent> = serde_json::from_value(battle.turns.clone()).unwrap_or_default();
+            let result = BattleResult::from_turns(Some(winner), &turns, &balance);
+            let (moves_a, moves_b) = (result.moves_used_by(&monster_a.id, monster_a_moves), result.moves_used_by(&monster_b.id, monster_b_moves));
+            let BattleResult { winner, flawless, highlight_score, balance_version, .. } = result;
+            Some(Battle { flawless, highlight_score, balance_version, moves_a, moves_b, ..battle.finish(winner, BattleOutcome::Win) })
+        }
+        None => {
+            battle.next_turn(turn_deadline);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use serde_json::{json, Value};
+    use crate::api::config::{config, repositories};
+    use crate::models::monster::Stats;
+    use crate::models::moves::Move;
+    use crate::repository::memory_repository::InMemoryRepository;
+    use crate::repository::monster_repository::MonsterRepository;
+    use crate::repository::move_repository::MoveRepository;
+
+    use super::*;
+
+    fn monster(name: &str, stats: Stats) -> Monster {
+        Monster { id: String::new(), image_url: String::new(), stats, created_at: None, updated_at: None, name: name.to_string(), last_battle_at: None, level: 1, xp: 0, owner_id: None, image_broken: false, external_id: None, version: 1 }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_play_an_interactive_battle_turn_by_turn() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let fast = repository.create_monster(monster("Fast", Stats { attack: 30, defense: 10, hp: 40, speed: 50 })).unwrap();
+        let slow = repository.create_monster(monster("Slow", Stats { attack: 20, defense: 10, hp: 35, speed: 10 })).unwrap();
+        let sure_hit = Move { id: String::new(), name: "Sure Hit".to_string(), power: 150, accuracy: 100, element: None, effect: None, effect_chance: 0, created_at: chrono::Utc::now().naive_utc(), updated_at: None };
+        let sure_hit = repository.create_move(sure_hit).unwrap();
+        repository.set_monster_moves(&fast.id, std::slice::from_ref(&sure_hit.id)).unwrap();
+        let app = test::init_service(App::new().configure(repositories(repository)).configure(config)).await;
+
+        let req = test::TestRequest::post().uri("/api/battles/interactive").set_json(json!({ "monster_a": slow.id, "monster_b": fast.id })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let battle: Value = test::read_body_json(resp).await;
+        let turns_uri = format!("/api/battles/{}/turns", battle["id"].as_str().unwrap());
+
+        let req = test::TestRequest::post().uri(&turns_uri).set_json(json!({ "monster": slow.id, "move": sure_hit.id })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let req = test::TestRequest::post().uri(&turns_uri).set_json(json!({ "monster": slow.id })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::ACCEPTED);
+        let waiting: Value = test::read_body_json(resp).await;
+        assert_eq!((waiting["monster_a_ready"].as_bool(), waiting["monster_b_ready"].as_bool()), (Some(true), Some(false)));
+        let req = test::TestRequest::post().uri(&turns_uri).set_json(json!({ "monster": slow.id })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+
+        // The fast monster hits first for 30 with its move, the slow one answers for 10.
+        let req = test::TestRequest::post().uri(&turns_uri).set_json(json!({ "monster": fast.id, "move": sure_hit.id })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let resolved: Value = test::read_body_json(resp).await;
+        assert_eq!((resolved["monster_a_hp"].as_i64(), resolved["monster_b_hp"].as_i64(), resolved["turn"].as_i64()), (Some(5), Some(30), Some(2)));
+        assert_eq!(resolved["turns"][0]["move"], "Sure Hit");
+        assert!(resolved.get("monster_b_move").is_none());
+
+        for monster_id in [&slow.id, &fast.id] {
+            let req = test::TestRequest::post().uri(&turns_uri).set_json(json!({ "monster": monster_id })).to_request();
+            test::call_service(&app, req).await;
+        }
+        let req = test::TestRequest::get().uri(&format!("/api/battles/interactive/{}", battle["id"].as_str().unwrap())).to_request();
+        let finished: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((finished["finished"].as_bool(), finished["winner"].as_str()), (Some(true), Some(fast.id.as_str())));
+        assert_eq!(finished["turns"].as_array().map(Vec::len), Some(3));
+
+        let req = test::TestRequest::get().uri(&format!("/api/battles/{}", finished["battle_id"].as_str().unwrap())).to_request();
+        let record: Battle = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((record.winner, record.outcome, record.manual), (Some(fast.id.clone()), BattleOutcome::Win, false));
+        let req = test::TestRequest::post().uri(&turns_uri).set_json(json!({ "monster": slow.id })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+    }
+}
@@ -0,0 +1,185 @@
+use actix_web::{web, get, post, put, delete, HttpResponse};
+use serde::{Deserialize, Serialize};
+use crate::models::item::{Item, MAX_MONSTER_ITEMS};
+use crate::repository::item_repository::ItemRepository;
+use crate::repository::monster_repository::MonsterRepository;
+use super::error::repository_error_response;
+
+// Bonuses default to 0, items are equipment unless `consumable` is set.
+#[derive(Serialize, Deserialize)]
+pub struct ItemRequest {
+    name: Option<String>,
+    attack_bonus: Option<i32>,
+    defense_bonus: Option<i32>,
+    speed_bonus: Option<i32>,
+    consumable: Option<bool>,
+    revive_hp: Option<i32>,
+}
+
+impl ItemRequest {
+    fn into_item(self) -> Result<Item, String> {
+        let name = match self.name.map(|name| name.trim().to_string()) {
+            Some(name) if !name.is_empty() => name,
+            _ => return Err("Name is required".to_string()),
+        };
+        let (attack_bonus, defense_bonus, speed_bonus) = (self.attack_bonus.unwrap_or(0), self.defense_bonus.unwrap_or(0), self.speed_bonus.unwrap_or(0));
+        if attack_bonus < -100 || defense_bonus < -100 || speed_bonus < -100 {
+            return Err("Bonuses must be at least -100".to_string());
+        }
+        let consumable = self.consumable.unwrap_or(false);
+        let revive_hp = self.revive_hp.unwrap_or(0);
+        if consumable {
+            if !(1..=100).contains(&revive_hp) {
+                return Err("Consumables must revive with between 1 and 100 percent of HP".to_string());
+            }
+            if attack_bonus != 0 || defense_bonus != 0 || speed_bonus != 0 {
+                return Err("Consumables don't grant bonuses".to_string());
+            }
+        } else if revive_hp != 0 {
+            return Err("Only consumables revive".to_string());
+        }
+
+        Ok(Item {
+            id: String::new(),
+            name,
+            attack_bonus,
+            defense_bonus,
+            speed_bonus,
+            consumable,
+            revive_hp,
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: None,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MonsterItemsRequest {
+    items: Vec<String>,
+}
+
+#[get("/items")]
+pub async fn get_items(item_repository: web::Data<dyn ItemRepository>) -> HttpResponse {
+    HttpResponse::Ok().json(item_repository.get_items())
+}
+
+#[get("/items/{id}")]
+pub async fn get_item_by_id(item_repository: web::Data<dyn ItemRepository>, id: web::Path<String>) -> HttpResponse {
+    match item_repository.get_item_by_id(&id) {
+        Some(item) => HttpResponse::Ok().json(item),
+        None => HttpResponse::NotFound().json("Item not found"),
+    }
+}
+
+#[post("/items")]
+pub async fn create_item(item_repository: web::Data<dyn ItemRepository>, request: web::Json<ItemRequest>) -> HttpResponse {
+    let item = match request.into_inner().into_item() {
+        Ok(item) => item,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+
+    match item_repository.create_item(item) {
+        Ok(item) => HttpResponse::Created().json(item),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[put("/items/{id}")]
+pub async fn update_item_by_id(item_repository: web::Data<dyn ItemRepository>, id: web::Path<String>, request: web::Json<ItemRequest>) -> HttpResponse {
+    let item = match request.into_inner().into_item() {
+        Ok(item) => item,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+
+    match item_repository.update_item_by_id(&id, item) {
+        Ok(Some(item)) => HttpResponse::Ok().json(item),
+        Ok(None) => HttpResponse::NotFound().json("Item not found"),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[delete("/items/{id}")]
+pub async fn delete_item_by_id(item_repository: web::Data<dyn ItemRepository>, id: web::Path<String>) -> HttpResponse {
+    match item_repository.delete_item_by_id(&id) {
+        Some(_) => HttpResponse::NoContent().finish(),
+        None => HttpResponse::NotFound().json("Item not found"),
+    }
+}
+
+#[get("/monsters/{id}/items")]
+pub async fn get_monster_items(monster_repository: web::Data<dyn MonsterRepository>, item_repository: web::Data<dyn ItemRepository>, id: web::Path<String>) -> HttpResponse {
+    if monster_repository.get_monster_by_id(&id).is_none() {
+        return HttpResponse::NotFound().json("Monster not found");
+    }
+    HttpResponse::Ok().json(item_repository.get_monster_items(&id))
+}
+
+// Equips the monster with up to MAX_MONSTER_ITEMS items in the given order, replacing the ones it carried.
+#[put("/monsters/{id}/items")]
+pub async fn set_monster_items(
+    monster_repository: web::Data<dyn MonsterRepository>,
+    item_repository: web::Data<dyn ItemRepository>,
+    id: web::Path<String>,
+    request: web::Json<MonsterItemsRequest>,
+) -> HttpResponse {
+    let item_ids = &request.items;
+    if item_ids.len() > MAX_MONSTER_ITEMS {
+        return HttpResponse::BadRequest().json(format!("A monster carries at most {} items", MAX_MONSTER_ITEMS));
+    }
+    if item_ids.iter().enumerate().any(|(index, item_id)| item_ids[..index].contains(item_id)) {
+        return HttpResponse::BadRequest().json("A monster can't carry an item twice");
+    }
+    if monster_repository.get_monster_by_id(&id).is_none() {
+        return HttpResponse::NotFound().json("Monster not found");
+    }
+
+    match item_repository.set_monster_items(&id, item_ids) {
+        Ok(items) => HttpResponse::Ok().json(items),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use serde_json::{json, Value};
+    use crate::api::config::repositories;
+    use crate::repository::database::Database;
+    use crate::utils::test_utils::init_test_monsters;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_equip_items_on_a_monster() {
+        let db = Arc::new(Database::new().unwrap());
+        let monster = init_test_monsters(db.as_ref()).await.remove(0);
+        let app = App::new().configure(repositories(db.clone())).service(create_item).service(get_monster_items).service(set_monster_items);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::post().uri("/items").set_json(json!({ "name": "Phoenix Down", "consumable": true, "revive_hp": 50, "attack_bonus": 10 })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let req = test::TestRequest::post().uri("/items").set_json(json!({ "name": "Phoenix Down", "consumable": true, "revive_hp": 50 })).to_request();
+        let revive: Item = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post().uri("/items").set_json(json!({ "name": "Iron Claws", "attack_bonus": 20, "speed_bonus": -10 })).to_request();
+        let claws: Item = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((claws.attack_bonus, claws.defense_bonus, claws.consumable, claws.revive_hp), (20, 0, false, 0));
+
+        let req = test::TestRequest::put().uri(&format!("/monsters/{}/items", monster.id)).set_json(json!({ "items": [claws.id, revive.id] })).to_request();
+        let items: Vec<Item> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(items.iter().map(|item| item.name.as_str()).collect::<Vec<_>>(), vec!["Iron Claws", "Phoenix Down"]);
+
+        let req = test::TestRequest::put().uri(&format!("/monsters/{}/items", monster.id)).set_json(json!({ "items": [claws.id, "123"] })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "MONSTER_ITEM_NOT_FOUND");
+
+        // Deleting an item takes it away from the monster.
+        db.delete_item_by_id(&claws.id);
+        let req = test::TestRequest::get().uri(&format!("/monsters/{}/items", monster.id)).to_request();
+        let items: Vec<Item> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(items.into_iter().map(|item| item.id).collect::<Vec<_>>(), vec![revive.id]);
+    }
+}
@@ -0,0 +1,46 @@
+use actix_web::{web, get, HttpResponse};
+use crate::models::job::{CSV_IMPORT, JSON_IMPORT};
+use crate::repository::job_repository::JobRepository;
+
+#[get("/jobs/{id}")]
+pub async fn get_job_by_id(job_repository: web::Data<dyn JobRepository>, id: web::Path<String>) -> HttpResponse {
+    match job_repository.get_job_by_id(&id) {
+        Some(job) => HttpResponse::Ok().json(job),
+        None => HttpResponse::NotFound().json("Job not found"),
+    }
+}
+
+// The job of a `POST /monsters/import_csv?async=true` or `POST /monsters/import_json?async=true` upload.
+#[get("/imports/{id}")]
+pub async fn get_import_by_id(job_repository: web::Data<dyn JobRepository>, id: web::Path<String>) -> HttpResponse {
+    match job_repository.get_job_by_id(&id) {
+        Some(job) if job.kind == CSV_IMPORT || job.kind == JSON_IMPORT => HttpResponse::Ok().json(job),
+        _ => HttpResponse::NotFound().json("Import not found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use crate::api::config::repositories;
+    use crate::repository::database::Database;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_get_a_job_by_id() {
+        let db = Arc::new(Database::new().unwrap());
+        let job = db.create_job("test", 3).unwrap();
+        let app = App::new().configure(repositories(db)).service(get_job_by_id);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri(&format!("/jobs/{}", job.id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let req = test::TestRequest::get().uri("/jobs/123").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,153 @@
+use actix_web::{web, get, HttpResponse};
+use serde::Deserialize;
+use crate::models::leaderboard::LeaderboardMode;
+use crate::repository::battle_repository::BattleRepository;
+
+const DEFAULT_LEADERBOARD_SIZE: i64 = 50;
+const MAX_LEADERBOARD_SIZE: i64 = 500;
+
+#[derive(Deserialize)]
+pub struct LeaderboardQuery {
+    #[serde(default)]
+    by: LeaderboardMode,
+    limit: Option<i64>,
+}
+
+// The best monsters over every battle ever stored, ranked `by` wins, win rate, rating or current win streak.
+#[get("/leaderboard")]
+pub async fn get_leaderboard(battle_repository: web::Data<dyn BattleRepository>, query: web::Query<LeaderboardQuery>) -> HttpResponse {
+    let limit = query.limit.unwrap_or(DEFAULT_LEADERBOARD_SIZE);
+    if !(1..=MAX_LEADERBOARD_SIZE).contains(&limit) {
+        return HttpResponse::BadRequest().json(format!("Limit must be between 1 and {}", MAX_LEADERBOARD_SIZE));
+    }
+    HttpResponse::Ok().json(battle_repository.get_leaderboard(query.by, limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use crate::api::config::repositories;
+    use crate::models::battle::{Battle, BattleOutcome};
+    use crate::models::leaderboard::{LeaderboardEntry, MIN_WINRATE_BATTLES};
+    use crate::models::monster::{Monster, Stats};
+    use crate::repository::database::Database;
+    use crate::repository::memory_repository::InMemoryRepository;
+    use crate::repository::monster_repository::MonsterRepository;
+
+    use super::*;
+
+    fn create_monster(repository: &dyn MonsterRepository, name: &str) -> Monster {
+        repository.create_monster(Monster {
+            id: String::new(),
+            name: name.to_string(),
+            image_url: "https://loremflickr.com/640/480".to_string(),
+            stats: Stats { attack: 40, defense: 20, hp: 50, speed: 80 },
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        }).unwrap()
+    }
+
+    fn battle(monster_a: &Monster, monster_b: &Monster, winner: &Monster) -> Battle {
+        Battle {
+            id: uuid::Uuid::new_v4().to_string(),
+            monster_a: monster_a.id.clone(),
+            monster_b: monster_b.id.clone(),
+            winner: Some(winner.id.clone()),
+            created_at: None,
+            updated_at: None,
+            outcome: BattleOutcome::Win,
+            manual: true,
+            season_id: None,
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        }
+    }
+
+    // The veteran wins 12 battles against the rival, the rookie its only 2, then the rival wins its last 8.
+    fn record_battles(repository: &(impl MonsterRepository + BattleRepository)) -> (Monster, Monster, Monster) {
+        let (veteran, rookie, rival) = (create_monster(repository, "veteran"), create_monster(repository, "rookie"), create_monster(repository, "rival"));
+        for _ in 0..12 {
+            repository.create_battle(battle(&veteran, &rival, &veteran)).unwrap();
+        }
+        for _ in 0..2 {
+            repository.create_battle(battle(&rookie, &rival, &rookie)).unwrap();
+        }
+        for _ in 0..8 {
+            repository.create_battle(battle(&veteran, &rival, &rival)).unwrap();
+        }
+        (veteran, rookie, rival)
+    }
+
+    #[actix_rt::test]
+    async fn test_should_rank_monsters_by_each_mode() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let (veteran, rookie, rival) = record_battles(repository.as_ref());
+        let app = App::new().configure(repositories(repository)).service(get_leaderboard);
+        let app = test::init_service(app).await;
+
+        let ranking = |by: &str| test::TestRequest::get().uri(&format!("/leaderboard?by={}", by)).to_request();
+        let entries: Vec<LeaderboardEntry> = test::call_and_read_body_json(&app, ranking("wins")).await;
+        assert_eq!(entries.iter().map(|entry| (entry.rank, entry.monster_id.as_str(), entry.wins)).collect::<Vec<_>>(), vec![
+            (1, veteran.id.as_str(), 12),
+            (2, rival.id.as_str(), 8),
+            (3, rookie.id.as_str(), 2),
+        ]);
+        assert_eq!((entries[1].battles, entries[1].losses), (22, 14));
+
+        // The rookie's perfect record is too short to rank by win rate, and trusted less than the veteran's by rating.
+        let entries: Vec<LeaderboardEntry> = test::call_and_read_body_json(&app, ranking("winrate")).await;
+        assert!(entries.iter().all(|entry| entry.battles >= MIN_WINRATE_BATTLES));
+        assert_eq!(entries[0].monster_id, veteran.id);
+        let entries: Vec<LeaderboardEntry> = test::call_and_read_body_json(&app, ranking("rating")).await;
+        assert_eq!(entries[0].monster_id, veteran.id);
+
+        let entries: Vec<LeaderboardEntry> = test::call_and_read_body_json(&app, ranking("streak")).await;
+        assert_eq!(entries.iter().map(|entry| (entry.monster_id.as_str(), entry.streak)).collect::<Vec<_>>(), vec![
+            (rival.id.as_str(), 8),
+            (rookie.id.as_str(), 2),
+            (veteran.id.as_str(), 0),
+        ]);
+
+        let resp = test::call_service(&app, ranking("elo")).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let req = test::TestRequest::get().uri("/leaderboard?limit=0").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_aggregate_the_leaderboard_like_the_memory_repository() {
+        let db = Arc::new(Database::new().unwrap());
+        let memory = InMemoryRepository::new();
+        let (veteran, rookie, _) = record_battles(db.as_ref());
+        record_battles(&memory);
+
+        for mode in [LeaderboardMode::Wins, LeaderboardMode::Winrate, LeaderboardMode::Rating, LeaderboardMode::Streak] {
+            let entries = db.get_leaderboard(mode, i64::MAX);
+            assert!(entries.windows(2).all(|pair| pair[0].rank + 1 == pair[1].rank));
+            let expected = memory.get_leaderboard(mode, i64::MAX);
+            for monster in [&veteran, &rookie] {
+                let entry = entries.iter().find(|entry| entry.monster_id == monster.id);
+                let expected = expected.iter().find(|entry| entry.name == monster.name);
+                assert_eq!(entry.map(|entry| (entry.battles, entry.wins, entry.streak)), expected.map(|entry| (entry.battles, entry.wins, entry.streak)));
+                if let (Some(entry), Some(expected)) = (entry, expected) {
+                    assert!((entry.rating - expected.rating).abs() < 1e-9);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use actix_web::{get, HttpResponse};
+use prometheus::TEXT_FORMAT;
+use crate::metrics::METRICS;
+
+#[get("/metrics")]
+pub async fn get_metrics() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(TEXT_FORMAT)
+        .body(METRICS.render())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_expose_metrics_in_prometheus_text_format() {
+        METRICS.battles_simulated.inc();
+        let app = test::init_service(App::new().service(get_metrics)).await;
+
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        assert_eq!(resp.headers().get("content-type").unwrap(), TEXT_FORMAT);
+
+        let body = test::read_body(resp).await;
+        assert!(String::from_utf8_lossy(&body).contains("battles_simulated_total"));
+    }
+}
@@ -1,3 +1,41 @@
 pub mod config;
+pub mod error;
+pub mod etag;
 pub mod monster_apis;
-pub mod battle_apis;
\ No newline at end of file
+pub mod negotiation;
+pub mod battle_apis;
+pub mod cache_apis;
+pub mod balance_apis;
+pub mod backup_apis;
+pub mod factory_apis;
+pub mod achievement_apis;
+pub mod admin_apis;
+pub mod analytics_apis;
+pub mod audit_apis;
+pub mod auth;
+pub mod graphql_apis;
+pub mod idempotency;
+pub mod image_apis;
+pub mod interactive_battle_apis;
+pub mod item_apis;
+pub mod job_apis;
+pub mod leaderboard_apis;
+pub mod challenge_apis;
+pub mod cors;
+pub mod cors_apis;
+pub mod db_health_apis;
+pub mod evolution_apis;
+pub mod export_apis;
+pub mod metrics_apis;
+pub mod move_apis;
+pub mod note_apis;
+pub mod payload;
+pub mod performance_apis;
+pub mod rate_limit_apis;
+pub mod region;
+pub mod region_apis;
+pub mod routes;
+pub mod season_apis;
+pub mod trainer_apis;
+pub mod training_apis;
+pub mod webhook_apis;
\ No newline at end of file
@@ -1,58 +1,418 @@
-use actix_web::{web, get, post, delete, put, HttpResponse, Error};
+use std::sync::Arc;
+use actix_web::{web, get, post, delete, put, http::header, HttpRequest, HttpResponse, Error};
 use actix_multipart::Multipart;
 use futures::TryStreamExt;
 use tempfile::NamedTempFile;
 use std::io::Write;
-use crate::{models::monster::Monster, repository::database::Database};
-use crate::repository::monster_repository;
+use serde::{Deserialize, Serialize};
+use crate::battle_engine::{BattleEngine, BattleSetup};
+use crate::cards::{self, CardCache};
+use crate::storage::Storage;
+use crate::import::{self, CsvOptions, ImportFormat};
+use crate::jobs::JobQueue;
+use crate::models::decay::StatDecay;
+use crate::models::duplicates::cluster_duplicates;
+use crate::models::growth::MAX_LEVEL;
+use crate::models::job::DUPLICATE_SCAN;
+use crate::models::battle::Battle;
+use crate::models::monster::{ImportMode, ImportOutcome, MatchmakingCandidate, Monster, Stats};
+use crate::models::page::{self, Page, PageCursor};
+use crate::models::status_effect::StatusEffectRules;
+use crate::repository::battle_repository::BattleRepository;
+use crate::repository::monster_repository::{DeleteMonsterError, MonsterRepository};
+use crate::repository::item_repository::ItemRepository;
+use crate::repository::move_repository::MoveRepository;
+use crate::repository::rate_limit_repository::RateLimitRepository;
+use crate::repository::trainer_repository::TrainerRepository;
+use crate::repository::training_repository::TrainingRepository;
+use super::auth;
+use super::battle_apis::{battle_engine, monster_hidden_stats, monster_items, monster_moves, new_simulated_battle, store_battle};
+use super::error::{repository_error_response, ApiError};
+use super::etag;
+use super::negotiation::ListFormat;
+use super::region::LocalRegion;
+
+const MAX_BULK_ITEMS: usize = 1000;
+const DEFAULT_SIMILAR_MONSTERS: usize = 5;
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+const DEFAULT_DUPLICATE_SIMILARITY: f32 = 0.5;
+const DEFAULT_RECENT_BATTLES: i64 = 5;
+const MAX_IMPORT_OPTION_BYTES: usize = 4096;
+const OWNED_BY_ANOTHER_TRAINER: &str = "The monster belongs to another trainer";
+
+#[derive(Deserialize)]
+pub struct DeleteMonsterQuery {
+    cascade: Option<bool>,
+}
+
+// Trainers only get their own monsters unless `scope` is `all`.
+#[derive(Deserialize)]
+pub struct MonstersQuery {
+    ids: Option<String>,
+    scope: Option<String>,
+    image_broken: Option<bool>,
+    // `csv` for CSV instead of JSON, see `ListFormat::negotiate`.
+    format: Option<String>,
+    // With either, a page of monsters in its envelope, see `page::requested_page`.
+    after: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct SearchMonstersQuery {
+    q: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct ImportCsvQuery {
+    #[serde(rename = "async")]
+    run_async: Option<bool>,
+    mode: Option<ImportMode>,
+}
+
+#[derive(Deserialize)]
+pub struct SimilarMonstersQuery {
+    k: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SimilarMonster {
+    monster: Monster,
+    distance: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BulkDeleteRequest {
+    ids: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BulkCreateResult {
+    index: usize,
+    status: String,
+    monster: Option<Monster>,
+    error: Option<ApiError>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BulkDeleteResult {
+    id: String,
+    status: String,
+}
+
+// Monsters of a trainer are only changed with its key or the admin token, the ones without an owner by anyone.
+fn may_change(req: &HttpRequest, rate_limit_repository: &dyn RateLimitRepository, trainer_repository: &dyn TrainerRepository, monster: &Monster) -> bool {
+    let Some(owner_id) = &monster.owner_id else {
+        return true;
+    };
+    auth::is_admin(req) || auth::trainer(req, rate_limit_repository, trainer_repository).is_some_and(|trainer| trainer.id == *owner_id)
+}
+
+// Adds `effective_stats` to a monster when the stat decay rule is enabled.
+#[derive(Serialize)]
+pub struct MonsterWithEffectiveStats {
+    #[serde(flatten)]
+    monster: Monster,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    effective_stats: Option<Stats>,
+}
+
+fn with_effective_stats(monsters: Vec<Monster>, decay: Option<&StatDecay>) -> Vec<MonsterWithEffectiveStats> {
+    let now = chrono::Utc::now().naive_utc();
+    monsters
+        .into_iter()
+        .map(|monster| MonsterWithEffectiveStats {
+            effective_stats: decay.map(|decay| decay.effective_stats(&monster, now)),
+            monster,
+        })
+        .collect()
+}
 
 #[get("/monsters")]
-pub async fn get_monsters(db: web::Data<Database>) -> HttpResponse {
-    let monsters = monster_repository::get_monsters(&db);
-    HttpResponse::Ok().json(monsters)
+pub async fn get_monsters(req: HttpRequest, monster_repository: web::Data<dyn MonsterRepository>, rate_limit_repository: web::Data<dyn RateLimitRepository>, trainer_repository: web::Data<dyn TrainerRepository>, decay: Option<web::Data<StatDecay>>, query: web::Query<MonstersQuery>) -> HttpResponse {
+    let decay = decay.as_ref().map(|decay| decay.get_ref());
+    let format = match ListFormat::negotiate(&req, query.format.as_deref()) {
+        Ok(format) => format,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let trainer = match query.scope.as_deref() {
+        Some("all") => None,
+        None => auth::trainer(&req, rate_limit_repository.get_ref(), trainer_repository.get_ref()),
+        Some(_) => return HttpResponse::BadRequest().json("Scope must be all when set"),
+    };
+    let page = match page::requested_page(query.after.as_deref(), query.limit) {
+        Ok(page) => page,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+    if let Some((after, limit)) = page {
+        if query.ids.is_some() {
+            return HttpResponse::BadRequest().json("Ids can't be paginated, they are at most a page already");
+        }
+        let owner = trainer.as_ref().map(|trainer| trainer.id.as_str());
+        let monsters = monster_repository.get_monsters_page(owner, query.image_broken, after.as_ref(), limit + 1);
+        let page = Page::new(monsters, limit, |monster| PageCursor { created_at: monster.created_at, id: monster.id.clone() });
+        let etag = etag::etag(&(format == ListFormat::Csv, &page));
+        if etag::is_fresh(&req, &etag) {
+            return etag::not_modified(etag);
+        }
+        return etag::tagged(format.respond_page(&page.map(|monsters| with_effective_stats(monsters, decay))), &etag);
+    }
+    // With `image_broken`, only the monsters whose image the thumbnail task flagged, or didn't.
+    let respond = |mut monsters: Vec<Monster>| {
+        if let Some(image_broken) = query.image_broken {
+            monsters.retain(|monster| monster.image_broken == image_broken);
+        }
+        // The CSV and JSON representations are tagged apart.
+        let etag = etag::etag(&(format == ListFormat::Csv, &monsters));
+        if etag::is_fresh(&req, &etag) {
+            return etag::not_modified(etag);
+        }
+        etag::tagged(format.respond(&with_effective_stats(monsters, decay)), &etag)
+    };
+    let ids = match (&query.ids, &trainer) {
+        (Some(ids), _) => ids,
+        (None, Some(trainer)) => return respond(trainer_repository.get_trainer_monsters(&trainer.id)),
+        (None, None) => return respond(monster_repository.get_monsters()),
+    };
+
+    let ids: Vec<String> = ids
+        .split(',')
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .collect();
+    if ids.is_empty() || ids.len() > MAX_BULK_ITEMS {
+        return HttpResponse::BadRequest().json(format!("Between 1 and {} ids must be sent", MAX_BULK_ITEMS));
+    }
+
+    // Keep the requested order, ids that do not exist are left out.
+    let mut monsters = monster_repository.get_monsters_by_ids(&ids);
+    if let Some(trainer) = &trainer {
+        monsters.retain(|monster| monster.owner_id.as_ref() == Some(&trainer.id));
+    }
+    monsters.sort_by_key(|monster| ids.iter().position(|id| *id == monster.id));
+    respond(monsters)
 }
 
+// Monsters created by trainers belong to them, the others to nobody.
 #[post("/monsters")]
-pub async fn create_monster(db: web::Data<Database>, new_monster: web::Json<Monster>) -> HttpResponse {
-    let monster = monster_repository::create_monster(&db, new_monster.into_inner());
+pub async fn create_monster(req: HttpRequest, monster_repository: web::Data<dyn MonsterRepository>, rate_limit_repository: web::Data<dyn RateLimitRepository>, trainer_repository: web::Data<dyn TrainerRepository>, new_monster: web::Json<Monster>) -> HttpResponse {
+    let owner_id = auth::trainer(&req, rate_limit_repository.get_ref(), trainer_repository.get_ref()).map(|trainer| trainer.id);
+    let monster = monster_repository.create_monster(Monster { owner_id, ..new_monster.into_inner() });
     match monster {
         Ok(monster) => HttpResponse::Created().json(monster),
-        Err(err) => HttpResponse::InternalServerError().json(err.to_string()),
+        Err(err) => repository_error_response(&err),
     }
 }
 
-#[get("/monsters/{id}")]
-pub async fn get_monster_by_id(db: web::Data<Database>, id: web::Path<String>) -> HttpResponse {
-    let monster = monster_repository::get_monster_by_id(&db, &id);
-    match monster {
-        Some(monster) => HttpResponse::Ok().json(monster),
+#[post("/monsters/bulk")]
+pub async fn bulk_create_monsters(req: HttpRequest, monster_repository: web::Data<dyn MonsterRepository>, rate_limit_repository: web::Data<dyn RateLimitRepository>, trainer_repository: web::Data<dyn TrainerRepository>, new_monsters: web::Json<Vec<Monster>>) -> HttpResponse {
+    let new_monsters = new_monsters.into_inner();
+    if new_monsters.is_empty() || new_monsters.len() > MAX_BULK_ITEMS {
+        return HttpResponse::BadRequest().json(format!("Between 1 and {} monsters must be sent", MAX_BULK_ITEMS));
+    }
+    let owner_id = auth::trainer(&req, rate_limit_repository.get_ref(), trainer_repository.get_ref()).map(|trainer| trainer.id);
+    let new_monsters = new_monsters.into_iter().map(|monster| Monster { owner_id: owner_id.clone(), ..monster }).collect();
+
+    match monster_repository.create_monsters(new_monsters) {
+        Ok(results) => {
+            let report: Vec<BulkCreateResult> = results
+                .into_iter()
+                .enumerate()
+                .map(|(index, result)| match result {
+                    Ok(monster) => BulkCreateResult { index, status: "created".to_string(), monster: Some(monster), error: None },
+                    Err(err) => BulkCreateResult { index, status: "failed".to_string(), monster: None, error: Some(ApiError::from(&err)) },
+                })
+                .collect();
+            HttpResponse::Ok().json(report)
+        }
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+// Monsters of other trainers are left alone, reported as `forbidden`.
+#[delete("/monsters/bulk")]
+pub async fn bulk_delete_monsters(req: HttpRequest, monster_repository: web::Data<dyn MonsterRepository>, rate_limit_repository: web::Data<dyn RateLimitRepository>, trainer_repository: web::Data<dyn TrainerRepository>, request: web::Json<BulkDeleteRequest>, query: web::Query<DeleteMonsterQuery>) -> HttpResponse {
+    if request.ids.is_empty() || request.ids.len() > MAX_BULK_ITEMS {
+        return HttpResponse::BadRequest().json(format!("Between 1 and {} ids must be sent", MAX_BULK_ITEMS));
+    }
+    let forbidden: Vec<String> = monster_repository.get_monsters_by_ids(&request.ids)
+        .into_iter()
+        .filter(|monster| !may_change(&req, rate_limit_repository.get_ref(), trainer_repository.get_ref(), monster))
+        .map(|monster| monster.id)
+        .collect();
+    let ids: Vec<String> = request.ids.iter().filter(|id| !forbidden.contains(id)).cloned().collect();
+
+    match monster_repository.delete_monsters(&ids, query.cascade.unwrap_or(false)) {
+        Ok(results) => {
+            let mut results = results.into_iter();
+            let report: Vec<BulkDeleteResult> = request.ids
+                .iter()
+                .map(|id| {
+                    if forbidden.contains(id) {
+                        return BulkDeleteResult { id: id.clone(), status: "forbidden".to_string() };
+                    }
+                    let result = results.next().expect("A result per deleted id");
+                    let status = match result {
+                        Ok(_) => "deleted".to_string(),
+                        Err(DeleteMonsterError::NotFound) => "not_found".to_string(),
+                        Err(DeleteMonsterError::HasBattles) => "has_battles".to_string(),
+                        Err(DeleteMonsterError::Database(err)) => err.to_string(),
+                    };
+                    BulkDeleteResult { id: id.clone(), status }
+                })
+                .collect();
+            HttpResponse::Ok().json(report)
+        }
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[get("/monsters/search")]
+pub async fn search_monsters(monster_repository: web::Data<dyn MonsterRepository>, query: web::Query<SearchMonstersQuery>) -> HttpResponse {
+    let search = match query.q.as_deref().map(str::trim) {
+        Some(search) if !search.is_empty() => search,
+        _ => return HttpResponse::BadRequest().json("Search query is required"),
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    if limit <= 0 || limit > MAX_BULK_ITEMS as i64 {
+        return HttpResponse::BadRequest().json(format!("Limit must be between 1 and {}", MAX_BULK_ITEMS));
+    }
+
+    HttpResponse::Ok().json(monster_repository.search_monsters(search, limit))
+}
+
+#[get("/monsters/similar/{id}")]
+pub async fn get_similar_monsters(monster_repository: web::Data<dyn MonsterRepository>, id: web::Path<String>, query: web::Query<SimilarMonstersQuery>) -> HttpResponse {
+    let k = query.k.unwrap_or(DEFAULT_SIMILAR_MONSTERS);
+    if k == 0 || k > MAX_BULK_ITEMS {
+        return HttpResponse::BadRequest().json(format!("K must be between 1 and {}", MAX_BULK_ITEMS));
+    }
+
+    let monsters = monster_repository.get_monsters();
+    match monsters.iter().find(|monster| monster.id == *id) {
+        Some(target) => HttpResponse::Ok().json(nearest_monsters(target, &monsters, k)),
         None => HttpResponse::NotFound().json("Monster not found"),
     }
 }
 
+// Tagged with an ETag, answering 304 when If-None-Match names the current one.
+#[get("/monsters/{id}")]
+pub async fn get_monster_by_id(req: HttpRequest, monster_repository: web::Data<dyn MonsterRepository>, decay: Option<web::Data<StatDecay>>, id: web::Path<String>) -> HttpResponse {
+    let Some(monster) = monster_repository.get_monster_by_id(&id) else {
+        return HttpResponse::NotFound().json("Monster not found");
+    };
+    let etag = etag::etag(&monster);
+    if etag::is_fresh(&req, &etag) {
+        return etag::not_modified(etag);
+    }
+    etag::tagged(HttpResponse::Ok().json(with_effective_stats(vec![monster], decay.as_ref().map(|decay| decay.get_ref())).pop()), &etag)
+}
+
+// A shareable card of the monster, rendered server-side so link previews don't need a frontend.
+#[get("/monsters/{id}/card.png")]
+pub async fn get_monster_card(monster_repository: web::Data<dyn MonsterRepository>, card_cache: web::Data<CardCache>, storage: Option<web::Data<dyn Storage>>, id: web::Path<String>) -> Result<HttpResponse, Error> {
+    let monster = match monster_repository.get_monster_by_id(&id) {
+        Some(monster) => monster,
+        None => return Ok(HttpResponse::NotFound().json("Monster not found")),
+    };
+    if let Some(card) = card_cache.get(&monster) {
+        return Ok(HttpResponse::Ok().content_type("image/png").body(card));
+    }
+
+    let sprite = cards::fetch_sprite(&monster, storage.as_ref().map(|storage| storage.get_ref())).await;
+    let has_sprite = sprite.is_some();
+    let (monster, card) = web::block(move || {
+        let card = cards::render_card(&monster, sprite.as_ref());
+        (monster, web::Bytes::from(card))
+    })
+    .await?;
+    // Cards drawn with the placeholder aren't kept, the image may be reachable next time.
+    if has_sprite {
+        card_cache.set(&monster, card.clone());
+    }
+    Ok(HttpResponse::Ok().content_type("image/png").body(card))
+}
+
 #[delete("/monsters/{id}")]
-pub async fn delete_monster_by_id(db: web::Data<Database>, id: web::Path<String>) -> HttpResponse {
-    let monster = monster_repository::delete_monster_by_id(&db, &id);
+pub async fn delete_monster_by_id(req: HttpRequest, monster_repository: web::Data<dyn MonsterRepository>, rate_limit_repository: web::Data<dyn RateLimitRepository>, trainer_repository: web::Data<dyn TrainerRepository>, id: web::Path<String>, query: web::Query<DeleteMonsterQuery>) -> HttpResponse {
+    if monster_repository.get_monster_by_id(&id).is_some_and(|monster| !may_change(&req, rate_limit_repository.get_ref(), trainer_repository.get_ref(), &monster)) {
+        return HttpResponse::Forbidden().json(OWNED_BY_ANOTHER_TRAINER);
+    }
+    let monster = monster_repository.delete_monster_by_id(&id, query.cascade.unwrap_or(false));
     match monster {
-        Some(_) => HttpResponse::NoContent().finish(),
-        None => HttpResponse::NotFound().json("Monster not found"),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(DeleteMonsterError::NotFound) => HttpResponse::NotFound().json("Monster not found"),
+        Err(DeleteMonsterError::HasBattles) => HttpResponse::Conflict().json("Monster has battles, use cascade=true to delete them too"),
+        Err(DeleteMonsterError::Database(err)) => repository_error_response(&err),
     }
 }
 
+/*
+The body must carry the `version` the monster was read at, an update made since then makes it fail with 409.
+With If-Match, the monster is also only updated when it still has one of the ETags given, 412 otherwise.
+Monsters of a trainer are only updated with its key, 403 otherwise.
+*/
 #[put("/monsters/{id}")]
-pub async fn update_monster_by_id(db: web::Data<Database>, id: web::Path<String>, updated_monster: web::Json<Monster>) -> HttpResponse {
-    let monster = monster_repository::update_monster_by_id(&db, &id, updated_monster.into_inner());
+pub async fn update_monster_by_id(req: HttpRequest, monster_repository: web::Data<dyn MonsterRepository>, rate_limit_repository: web::Data<dyn RateLimitRepository>, trainer_repository: web::Data<dyn TrainerRepository>, id: web::Path<String>, updated_monster: web::Json<Monster>) -> HttpResponse {
+    if updated_monster.version < 1 {
+        return HttpResponse::BadRequest().json("The version the monster was read at is required");
+    }
+    let Some(existing_monster) = monster_repository.get_monster_by_id(&id) else {
+        return HttpResponse::NotFound().json("Monster not found");
+    };
+    if !may_change(&req, rate_limit_repository.get_ref(), trainer_repository.get_ref(), &existing_monster) {
+        return HttpResponse::Forbidden().json(OWNED_BY_ANOTHER_TRAINER);
+    }
+    if !etag::matches(&req, &etag::etag(&existing_monster)) {
+        return HttpResponse::PreconditionFailed().json("The monster was changed since it was read");
+    }
+    let monster = monster_repository.update_monster_by_id(&id, updated_monster.into_inner());
     match monster {
-        Some(monster) => HttpResponse::Ok().json(monster),
-        None => HttpResponse::NotFound().json("Monster not found"),
+        Ok(Some(monster)) => etag::tagged(HttpResponse::Ok().json(&monster), &etag::etag(&monster)),
+        Ok(None) => HttpResponse::NotFound().json("Monster not found"),
+        Err(err) => repository_error_response(&err),
     }
 }
 
+// Levels the monster up right away, whatever its XP.
+#[post("/monsters/{id}/level_up")]
+pub async fn level_up_monster(monster_repository: web::Data<dyn MonsterRepository>, id: web::Path<String>) -> HttpResponse {
+    match monster_repository.get_monster_by_id(&id) {
+        Some(monster) if monster.level >= MAX_LEVEL => return HttpResponse::Conflict().json(format!("The monster is already at the max level, {}", MAX_LEVEL)),
+        Some(_) => {}
+        None => return HttpResponse::NotFound().json("Monster not found"),
+    }
+
+    match monster_repository.level_up_monster(&id) {
+        Ok(Some(monster)) => HttpResponse::Ok().json(monster),
+        Ok(None) => HttpResponse::NotFound().json("Monster not found"),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+/*
+Besides the file, the form may hold a `delimiter`, detected from the header line otherwise, and `columns`, a JSON
+object naming the monster field of headers that aren't a field or one of its usual aliases (`ATK`, `DEF`...).
+With `?async=true` the rows are imported by a background job instead: the response is a 202 with the job,
+polled at `GET /imports/{id}`. Invalid rows then count as failed instead of rejecting the whole file.
+Like created ones, imported monsters belong to the trainer importing them. `?mode=upsert` updates the trainer's
+monsters with the same `externalId`, or the same name for rows without one, and `?mode=skip_duplicates` leaves
+them alone; both respond with the counts of monsters created, updated and skipped.
+*/
 #[post("/monsters/import_csv")]
-pub async fn import_csv(db: web::Data<Database>, mut payload: Multipart) -> Result<HttpResponse, Error> {
+pub async fn import_csv(
+    req: HttpRequest,
+    monster_repository: web::Data<dyn MonsterRepository>,
+    rate_limit_repository: web::Data<dyn RateLimitRepository>,
+    trainer_repository: web::Data<dyn TrainerRepository>,
+    job_queue: Option<web::Data<JobQueue>>,
+    query: web::Query<ImportCsvQuery>,
+    mut payload: Multipart,
+) -> Result<HttpResponse, Error> {
     let mut file_name: Option<String> = None;
     let mut temp_file: Option<NamedTempFile> = None;
-    let mut new_monsters: Vec<Monster> = Vec::new();
+    let (mut delimiter, mut columns): (Option<String>, Option<String>) = (None, None);
 
     while let Some(mut field) = payload.try_next().await? {
         let content_disposition = field.content_disposition();
@@ -64,96 +424,435 @@ pub async fn import_csv(db: web::Data<Database>, mut payload: Multipart) -> Resu
             while let Some(chunk) = field.try_next().await? {
                 temp_file.as_mut().unwrap().write_all(&chunk).unwrap();
             }
+        } else if matches!(field.name(), "delimiter" | "columns") {
+            let option = if field.name() == "delimiter" { &mut delimiter } else { &mut columns };
+            let mut value = Vec::new();
+            while let Some(chunk) = field.try_next().await? {
+                if value.len() + chunk.len() > MAX_IMPORT_OPTION_BYTES {
+                    return Ok(HttpResponse::BadRequest().json(format!("Import options must be at most {} bytes", MAX_IMPORT_OPTION_BYTES)));
+                }
+                value.extend_from_slice(&chunk);
+            }
+            *option = Some(String::from_utf8_lossy(&value).into_owned());
         } else {
             return Ok(HttpResponse::BadRequest().json("No file name provided"));
         }
     }
+    let format = match CsvOptions::parse(delimiter.as_deref(), columns.as_deref()) {
+        Ok(options) => ImportFormat::Csv(options),
+        Err(err) => return Ok(HttpResponse::BadRequest().json(err)),
+    };
 
-    if let Some(_file_name) = file_name {
-        if let Some(temp_file) = temp_file {
-            let mut reader = csv::ReaderBuilder::new()
-                .has_headers(true)
-                .from_path(temp_file.path())
-                .unwrap();
-
-                
-                for result in reader.deserialize::<Monster>() {
-                    match result {
-                        Ok(monster) => {
-                            new_monsters.push(monster);
-                        }
-                        Err(e) => {
-                            println!("Reader: {:?}", e.to_string());
-                            return Ok(HttpResponse::BadRequest().json("Incomplete data, check your file."));
-                        }
-                    }
-                }
-    
-                if new_monsters.is_empty() {
-                    return Ok(HttpResponse::BadRequest().json("No valid monsters found in the CSV file"));
-                }
+    let owner_id = auth::trainer(&req, rate_limit_repository.get_ref(), trainer_repository.get_ref()).map(|trainer| trainer.id);
+    let mode = query.mode.unwrap_or_default();
+    match (file_name, temp_file) {
+        (Some(_), Some(temp_file)) if query.run_async.unwrap_or(false) => import_in_background(monster_repository.into_inner(), job_queue, temp_file, format, mode, owner_id).await,
+        (Some(_), Some(temp_file)) => import_file(monster_repository.get_ref(), temp_file, format, mode, owner_id).await,
+        _ => Ok(HttpResponse::BadRequest().json("No file uploaded")),
+    }
+}
 
-            let results: Vec<Result<Monster, String>> = new_monsters
-            .iter()
-            .map(|new_monster| {
-                match monster_repository::create_monster(&db, new_monster.clone()) {
-                    Ok(monster) => Ok(monster),
-                    Err(err) => Err(err.to_string()),
-                }
-            })
-            .collect();
-    
+/*
+Imports the monsters of the body, a JSON array of monsters or NDJSON with one monster per line, exactly like
+`POST /monsters/import_csv` imports a CSV file, `?async=true` and `?mode=` included. The body is spooled to disk as it streams in.
+*/
+#[post("/monsters/import_json")]
+pub async fn import_json(
+    req: HttpRequest,
+    monster_repository: web::Data<dyn MonsterRepository>,
+    rate_limit_repository: web::Data<dyn RateLimitRepository>,
+    trainer_repository: web::Data<dyn TrainerRepository>,
+    job_queue: Option<web::Data<JobQueue>>,
+    query: web::Query<ImportCsvQuery>,
+    mut payload: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let mut temp_file = NamedTempFile::new()?;
+    while let Some(chunk) = payload.try_next().await? {
+        temp_file.write_all(&chunk)?;
+    }
 
-            let (successes, _errors): (Vec<_>, Vec<_>) = results.into_iter().partition(Result::is_ok);
+    let owner_id = auth::trainer(&req, rate_limit_repository.get_ref(), trainer_repository.get_ref()).map(|trainer| trainer.id);
+    let mode = query.mode.unwrap_or_default();
+    if query.run_async.unwrap_or(false) {
+        return import_in_background(monster_repository.into_inner(), job_queue, temp_file, ImportFormat::Json, mode, owner_id).await;
+    }
+    import_file(monster_repository.get_ref(), temp_file, ImportFormat::Json, mode, owner_id).await
+}
 
-            let successful_monsters: Vec<Monster> = successes.into_iter().map(Result::unwrap).collect();
+// Imports every monster of the file at once, rejecting the whole file when one of its rows isn't a monster.
+async fn import_file(monster_repository: &dyn MonsterRepository, temp_file: NamedTempFile, format: ImportFormat, mode: ImportMode, owner_id: Option<String>) -> Result<HttpResponse, Error> {
+    let (rows, format) = web::block(move || (format.rows(temp_file.path()).map(|rows| rows.collect::<Vec<_>>()), format)).await?;
+    let new_monsters = match rows.and_then(|rows| import::collect_monsters(rows, &format, owner_id.as_deref())) {
+        Ok(new_monsters) => new_monsters,
+        Err(err) => return Ok(HttpResponse::BadRequest().json(err)),
+    };
 
-            if successful_monsters.is_empty() {
-                return Ok(HttpResponse::InternalServerError().json("Failed to create monsters"));
-            } else {
-                return Ok(HttpResponse::Ok().json(successful_monsters));
+    let results = match monster_repository.import_monsters(new_monsters, mode) {
+        Ok(results) => results,
+        Err(err) => return Ok(repository_error_response(&err)),
+    };
+    let (mut successful_monsters, mut created, mut updated, mut skipped) = (Vec::new(), 0, 0, 0);
+    for outcome in results.into_iter().flatten() {
+        match outcome {
+            ImportOutcome::Created(monster) => {
+                created += 1;
+                successful_monsters.push(monster);
+            }
+            ImportOutcome::Updated(monster) => {
+                updated += 1;
+                successful_monsters.push(monster);
             }
+            ImportOutcome::Skipped => skipped += 1,
+        }
+    }
+    format.rows_imported().inc_by(successful_monsters.len() as u64);
+    if created + updated + skipped == 0 {
+        return Ok(HttpResponse::InternalServerError().json("Failed to create monsters"));
+    }
+    if mode == ImportMode::Create {
+        return Ok(HttpResponse::Ok().json(successful_monsters));
+    }
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "created": created, "updated": updated, "skipped": skipped, "monsters": successful_monsters })))
+}
+
+async fn import_in_background(monster_repository: Arc<dyn MonsterRepository>, job_queue: Option<web::Data<JobQueue>>, temp_file: NamedTempFile, format: ImportFormat, mode: ImportMode, owner_id: Option<String>) -> Result<HttpResponse, Error> {
+    let Some(job_queue) = job_queue else {
+        return Ok(HttpResponse::InternalServerError().json("Background jobs are not available"));
+    };
+    let path = temp_file.path().to_path_buf();
+    let counted_format = format.clone();
+    let total = match web::block(move || counted_format.rows(&path).map(|rows| rows.count())).await? {
+        Ok(total) => total,
+        Err(err) => return Ok(HttpResponse::BadRequest().json(err)),
+    };
+    if total == 0 {
+        return Ok(HttpResponse::BadRequest().json(format!("No valid monsters found in the {} file", format.name())));
+    }
+
+    // The job owns the temporary file, which is removed once the import is done.
+    let kind = format.job_kind();
+    match job_queue.enqueue(kind, total, move |progress| import::import_chunks(monster_repository.as_ref(), temp_file.path(), &format, mode, owner_id.as_deref(), progress)) {
+        Ok(job) => Ok(HttpResponse::Accepted()
+            .insert_header((header::LOCATION, format!("/api/imports/{}", job.id)))
+            .json(job)),
+        Err(err) => Ok(repository_error_response(&err)),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DuplicateScanQuery {
+    // Trigram similarity two names need to be counted as copies, between 0 and 1.
+    min_similarity: Option<f32>,
+}
+
+/*
+Scans the whole monster table in a background job for likely duplicates: monsters with identical stats and
+similar names. The job result lists the clusters found, each one naming the monster to keep and its copies.
+*/
+#[post("/monsters/duplicates/scan")]
+pub async fn scan_duplicate_monsters(monster_repository: web::Data<dyn MonsterRepository>, job_queue: Option<web::Data<JobQueue>>, query: web::Query<DuplicateScanQuery>) -> HttpResponse {
+    let Some(job_queue) = job_queue else {
+        return HttpResponse::InternalServerError().json("Background jobs are not available");
+    };
+    let min_similarity = query.min_similarity.unwrap_or(DEFAULT_DUPLICATE_SIMILARITY);
+    if !(0.0..=1.0).contains(&min_similarity) {
+        return HttpResponse::BadRequest().json("Minimum similarity must be between 0 and 1");
+    }
+
+    let monster_repository = monster_repository.into_inner();
+    let job = job_queue.enqueue(DUPLICATE_SCAN, 1, move |progress| {
+        let pairs = monster_repository.find_duplicate_pairs(min_similarity);
+        let mut monster_ids: Vec<String> = pairs.iter().flat_map(|pair| [pair.monster_a.clone(), pair.monster_b.clone()]).collect();
+        monster_ids.sort();
+        monster_ids.dedup();
+        let clusters = cluster_duplicates(&pairs, &monster_repository.get_monsters_by_ids(&monster_ids));
+        progress.update(1, 0);
+        Ok(serde_json::json!({ "clusters": clusters }))
+    });
+    match job {
+        Ok(job) => HttpResponse::Accepted()
+            .insert_header((header::LOCATION, format!("/api/jobs/{}", job.id)))
+            .json(job),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MatchmakeQuery {
+    // Opponents of the monster's last `recent` battles aren't matched again.
+    recent: Option<i64>,
+    fight: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Matchmaking {
+    opponent: MatchmakingCandidate,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    battle: Option<Battle>,
+}
+
+/*
+Finds the most evenly matched opponent for the monster, the one whose stats differ the least from its own,
+preferring opponents from the region of its trainer, or of the instance, and fights it right away with `?fight=true`.
+*/
+#[get("/monsters/{id}/matchmake")]
+#[allow(clippy::too_many_arguments)]
+pub async fn matchmake(
+    monster_repository: web::Data<dyn MonsterRepository>,
+    battle_repository: web::Data<dyn BattleRepository>,
+    move_repository: web::Data<dyn MoveRepository>,
+    item_repository: web::Data<dyn ItemRepository>,
+    training_repository: web::Data<dyn TrainingRepository>,
+    decay: Option<web::Data<StatDecay>>,
+    status_effects: Option<web::Data<StatusEffectRules>>,
+    engine: Option<web::Data<dyn BattleEngine>>,
+    local_region: Option<web::Data<LocalRegion>>,
+    id: web::Path<String>,
+    query: web::Query<MatchmakeQuery>,
+) -> HttpResponse {
+    let recent = query.recent.unwrap_or(DEFAULT_RECENT_BATTLES);
+    if !(0..=MAX_BULK_ITEMS as i64).contains(&recent) {
+        return HttpResponse::BadRequest().json(format!("Recent must be between 0 and {}", MAX_BULK_ITEMS));
+    }
+    let Some(monster) = monster_repository.get_monster_by_id(&id) else {
+        return HttpResponse::NotFound().json("Monster not found");
+    };
+    let Some(opponent) = monster_repository.find_matchmaking_candidates(&monster.id, recent, local_region.as_ref().and_then(|region| region.name()), 1).pop() else {
+        return HttpResponse::NotFound().json("No opponent available");
+    };
+    if !query.fight.unwrap_or(false) {
+        return HttpResponse::Ok().json(Matchmaking { opponent, battle: None });
+    }
+
+    let moves = monster_moves(move_repository.as_ref(), &monster, &opponent.monster);
+    let items = monster_items(item_repository.as_ref(), &monster, &opponent.monster);
+    let hidden_stats = monster_hidden_stats(training_repository.as_ref(), &monster, &opponent.monster);
+    let setup = BattleSetup {
+        moves,
+        items,
+        hidden_stats,
+        status_effects: status_effects.as_ref().map(|status_effects| status_effects.get_ref()),
+        ..BattleSetup::new(monster, opponent.monster.clone())
+    };
+    let battle = new_simulated_battle(battle_engine(engine).as_ref(), setup, decay.as_ref().map(|decay| decay.get_ref()));
+    match store_battle(battle_repository.as_ref(), battle) {
+        Ok(battle) => HttpResponse::Created().json(Matchmaking { opponent, battle: Some(battle) }),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+/*
+Each stat is min-max normalized over the whole population before measuring the euclidean distance,
+so a stat with a wider range (like hp) does not dominate the others.
+*/
+fn nearest_monsters(target: &Monster, monsters: &[Monster], k: usize) -> Vec<SimilarMonster> {
+    let vector = |monster: &Monster| {
+        let stats = monster.stats;
+        [stats.attack, stats.defense, stats.hp, stats.speed].map(f64::from)
+    };
+    let vectors: Vec<[f64; 4]> = monsters.iter().map(vector).collect();
+    let mut min = [f64::MAX; 4];
+    let mut max = [f64::MIN; 4];
+    for values in &vectors {
+        for stat in 0..4 {
+            min[stat] = min[stat].min(values[stat]);
+            max[stat] = max[stat].max(values[stat]);
         }
     }
+    let normalize = |values: [f64; 4]| {
+        let mut normalized = [0.0; 4];
+        for stat in 0..4 {
+            let range = max[stat] - min[stat];
+            normalized[stat] = if range > 0.0 { (values[stat] - min[stat]) / range } else { 0.0 };
+        }
+        normalized
+    };
 
-    Ok(HttpResponse::BadRequest().json("No file uploaded"))
+    let target_vector = normalize(vector(target));
+    let mut similar: Vec<SimilarMonster> = monsters
+        .iter()
+        .zip(vectors)
+        .filter(|(monster, _)| monster.id != target.id)
+        .map(|(monster, values)| {
+            let distance = normalize(values)
+                .iter()
+                .zip(target_vector)
+                .map(|(value, target_value)| (value - target_value).powi(2))
+                .sum::<f64>()
+                .sqrt();
+            SimilarMonster { monster: monster.clone(), distance }
+        })
+        .collect();
+    similar.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    similar.truncate(k);
+    similar
 }
 
 #[cfg(test)]
 mod tests {
     use actix_web::{test, http, App};
-    use actix_web::web::Data;
     use crate::{
+        utils::test_utils::init_test_battle,
         utils::test_utils::init_test_monsters
     };
+    use std::sync::Arc;
+    use crate::api::config::repositories;
+    use crate::repository::battle_repository::BattleRepository;
+    use crate::repository::database::Database;
+    use crate::repository::memory_repository::InMemoryRepository;
+    use crate::models::fixed_point::Fixed;
+    use crate::models::battle::{Battle, BattleOutcome};
+    use crate::models::monster::MonsterSearchResult;
+    use actix_web::web::Data;
+    use chrono::{Duration, Utc};
+    use serde_json::Value;
 
     use actix_multipart_test::MultiPartFormDataBuilder;
     use super::*;
 
     #[actix_rt::test]
     async fn test_should_get_all_monsters_correctly() {
-        let db = Database::new();
-        let app = App::new().app_data(Data::new(db)).service(get_monsters);
+        let db = Database::new().unwrap();
+        let app = App::new().configure(repositories(Arc::new(db))).service(get_monsters);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let req = test::TestRequest::get().uri("/monsters").to_request();
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
         
         assert!(resp.status().is_success());
     }
 
+    #[actix_rt::test]
+    async fn test_should_get_monsters_by_ids_in_the_requested_order() {
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
+
+        let app = App::new().configure(repositories(Arc::new(db))).service(get_monsters);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get()
+        .uri(format!("/monsters?ids={},99999,{}", test_monsters[2].id, test_monsters[0].id).as_str())
+        .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let monsters: Vec<Monster> = test::read_body_json(resp).await;
+        let ids: Vec<String> = monsters.into_iter().map(|monster| monster.id).collect();
+        assert_eq!(ids, vec![test_monsters[2].id.clone(), test_monsters[0].id.clone()]);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_page_through_monsters_with_a_cursor() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let app = test::init_service(App::new().configure(repositories(repository.clone())).service(get_monsters)).await;
+        let created = |name: &str, created_at: Value| {
+            let monster = serde_json::json!({ "name": name, "image_url": "https://example.com/monster.png", "attack": 40, "defense": 20, "hp": 50, "speed": 80, "createdAt": created_at });
+            repository.create_monster(serde_json::from_value(monster).unwrap()).unwrap().id
+        };
+        let newest = created("newest", Value::from("2026-10-02T00:00:00"));
+        let oldest = created("oldest", Value::from("2026-10-01T00:00:00"));
+        let undated = created("undated", Value::Null);
+
+        let first: Page<Monster> = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/monsters?limit=2").to_request()).await;
+        assert_eq!(first.items.iter().map(|monster| &monster.id).collect::<Vec<_>>(), vec![&undated, &oldest]);
+        // A monster created meanwhile before the cursor is not repeated on the next page.
+        created("late", Value::from("2026-09-01T00:00:00"));
+        let uri = format!("/monsters?limit=2&after={}", first.next_cursor.unwrap());
+        let second: Page<Monster> = test::call_and_read_body_json(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(second.items.iter().map(|monster| &monster.id).collect::<Vec<_>>(), vec![&newest]);
+        assert!(second.next_cursor.is_none());
+
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/monsters?after=not-a-cursor").to_request()).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let all: Vec<Monster> = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/monsters").to_request()).await;
+        assert_eq!(all.len(), 4);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_get_the_most_similar_monsters() {
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
+
+        let app = App::new().configure(repositories(Arc::new(db))).service(get_similar_monsters);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get()
+        .uri(format!("/monsters/similar/{}?k=3", test_monsters[0].id).as_str())
+        .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let similar: Vec<SimilarMonster> = test::read_body_json(resp).await;
+        assert_eq!(similar.len(), 3);
+        assert!(similar.iter().all(|similar| similar.monster.id != test_monsters[0].id));
+        assert!(similar.windows(2).all(|pair| pair[0].distance <= pair[1].distance));
+    }
+
+    #[actix_rt::test]
+    async fn test_should_rank_monsters_by_normalized_stat_distance() {
+        let test_monsters = init_test_monsters(&Database::new().unwrap()).await;
+
+        let similar = nearest_monsters(&test_monsters[0], &test_monsters, 1);
+
+        // monster-3 only differs from monster-1 by 5 defense points.
+        assert_eq!(similar[0].monster.id, test_monsters[2].id);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_search_monsters_by_name_with_typos() {
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
+        let drake = db.create_monster(Monster {
+            name: "Quixotic Flamedrake".to_string(),
+            ..test_monsters[0].clone()
+        }).unwrap();
+
+        let app = App::new().configure(repositories(Arc::new(db))).service(search_monsters);
+
+        let app = test::init_service(app).await;
+
+        for search in ["quixotic+flamedrake", "quixotik"] {
+            let req = test::TestRequest::get()
+            .uri(format!("/monsters/search?q={}", search).as_str())
+            .to_request();
+
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK);
+
+            let results: Vec<MonsterSearchResult> = test::read_body_json(resp).await;
+            assert!(results.iter().any(|result| result.monster.id == drake.id));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_search_with_a_bad_request_response_if_query_is_empty() {
+        let db = Database::new().unwrap();
+        let app = App::new().configure(repositories(Arc::new(db))).service(search_monsters);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/monsters/search?q=+").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+
     #[actix_rt::test]
     async fn test_should_get_404_error_if_monster_does_not_exists() {
         
-        let db = Database::new();
-        let app = App::new().app_data(Data::new(db)).service(get_monster_by_id);
+        let db = Database::new().unwrap();
+        let app = App::new().configure(repositories(Arc::new(db))).service(get_monster_by_id);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let req = test::TestRequest::get().uri("/monsters/999999").to_request();
 
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
         
         assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
     }
@@ -161,40 +860,44 @@ mod tests {
     #[actix_rt::test]
     async fn test_should_get_a_single_monster_correctly() {
         
-        let mut db = Database::new();
-        let test_monsters = init_test_monsters(&mut db).await;
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
 
-        let app = App::new().app_data(Data::new(db)).service(get_monster_by_id);
+        let app = App::new().configure(repositories(Arc::new(db))).service(get_monster_by_id);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let req = test::TestRequest::get()
         .uri(format!("/monsters/{}", test_monsters[0].id).as_str()).to_request();
         
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
  
         assert_eq!(resp.status(), http::StatusCode::OK);
     }
 
     #[actix_rt::test]
     async fn test_should_create_a_new_monster() {
-        let mut db = Database::new();
-        let _test_monsters = init_test_monsters(&mut db).await;
+        let db = Database::new().unwrap();
+        let _test_monsters = init_test_monsters(&db).await;
 
-        let app = App::new().app_data(Data::new(db)).service(create_monster);
+        let app = App::new().configure(repositories(Arc::new(db))).service(create_monster);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let new_monster_data = Monster {
             id: _test_monsters[0].id.clone(),
             name: _test_monsters[0].name.clone(),
             image_url: _test_monsters[0].image_url.clone(),
-            attack: _test_monsters[0].attack.clone(),
-            defense: _test_monsters[0].defense.clone(),
-            speed: _test_monsters[0].speed.clone(),
-            hp: _test_monsters[0].hp.clone(),
-            created_at: _test_monsters[0].created_at.clone(),
-            updated_at: _test_monsters[0].updated_at.clone(),
+            stats: _test_monsters[0].stats,
+            created_at: _test_monsters[0].created_at,
+            updated_at: _test_monsters[0].updated_at,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
         };
 
         let req = test::TestRequest::post()
@@ -202,67 +905,117 @@ mod tests {
         .set_json(&new_monster_data)
         .to_request();
 
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
  
         assert_eq!(resp.status(), http::StatusCode::CREATED);
     }
 
     #[actix_rt::test]
     async fn test_should_update_a_monster_correctly() {
-        let mut db = Database::new();
-        let _test_monsters = init_test_monsters(&mut db).await;
+        let db = Database::new().unwrap();
+        let _test_monsters = init_test_monsters(&db).await;
 
-        let app = App::new().app_data(Data::new(db)).service(update_monster_by_id);
+        let app = App::new().configure(repositories(Arc::new(db))).service(update_monster_by_id);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let update_monster_data = Monster {
             id: _test_monsters[0].id.clone(),
             name: "Update name of monster".to_string(),
             image_url: _test_monsters[0].image_url.clone(),
-            attack: _test_monsters[0].attack.clone(),
-            defense: _test_monsters[0].defense.clone(),
-            speed: _test_monsters[0].speed.clone(),
-            hp: _test_monsters[0].hp.clone(),
-            created_at: _test_monsters[0].created_at.clone(),
-            updated_at: _test_monsters[0].updated_at.clone(),
+            stats: _test_monsters[0].stats,
+            created_at: _test_monsters[0].created_at,
+            updated_at: _test_monsters[0].updated_at,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
         };
         let req = test::TestRequest::put()
         .uri(format!("/monsters/{}", _test_monsters[0].id).as_str())
         .set_json(&update_monster_data)
         .to_request();
         
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
  
         assert_eq!(resp.status(), http::StatusCode::OK);
     }
 
+    #[actix_rt::test]
+    async fn test_should_update_with_422_error_if_the_name_is_empty() {
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
+
+        let app = App::new().configure(repositories(Arc::new(db))).service(update_monster_by_id);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::put()
+        .uri(format!("/monsters/{}", test_monsters[0].id).as_str())
+        .set_json(Monster { name: "   ".to_string(), ..test_monsters[0].clone() })
+        .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+
+        let error: ApiError = test::read_body_json(resp).await;
+        assert_eq!(error.code, "MONSTER_NAME_EMPTY");
+    }
+
+    #[actix_rt::test]
+    async fn test_should_update_with_422_error_if_a_stat_is_out_of_range() {
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
+
+        let app = App::new().configure(repositories(Arc::new(db))).service(update_monster_by_id);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::put()
+        .uri(format!("/monsters/{}", test_monsters[0].id).as_str())
+        .set_json(Monster { stats: Stats { attack: i32::MAX, ..test_monsters[0].stats }, ..test_monsters[0].clone() })
+        .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+
+        let error: ApiError = test::read_body_json(resp).await;
+        assert_eq!(error.code, "MONSTER_STATS_OUT_OF_RANGE");
+    }
+
     #[actix_rt::test]
     async fn test_should_update_with_404_error_if_monster_does_not_exists() {
-        let mut db = Database::new();
-        let _test_monsters = init_test_monsters(&mut db).await;
+        let db = Database::new().unwrap();
+        let _test_monsters = init_test_monsters(&db).await;
 
-        let app = App::new().app_data(Data::new(db)).service(update_monster_by_id);
+        let app = App::new().configure(repositories(Arc::new(db))).service(update_monster_by_id);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let update_monster_data = Monster {
             id: _test_monsters[0].id.clone(),
             name: "Update name of monster".to_string(),
             image_url: _test_monsters[0].image_url.clone(),
-            attack: _test_monsters[0].attack.clone(),
-            defense: _test_monsters[0].defense.clone(),
-            speed: _test_monsters[0].speed.clone(),
-            hp: _test_monsters[0].hp.clone(),
-            created_at: _test_monsters[0].created_at.clone(),
-            updated_at: _test_monsters[0].updated_at.clone(),
+            stats: _test_monsters[0].stats,
+            created_at: _test_monsters[0].created_at,
+            updated_at: _test_monsters[0].updated_at,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
         };
         let req = test::TestRequest::put()
         .uri(format!("/monsters/{}", 99999).as_str())
         .set_json(&update_monster_data)
         .to_request();
         
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
  
         assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
     }
@@ -270,45 +1023,168 @@ mod tests {
     #[actix_rt::test]
     async fn test_should_delete_a_monster_correctly() {
         
-        let mut db = Database::new();
-        let _test_monsters = init_test_monsters(&mut db).await;
+        let db = Database::new().unwrap();
+        let _test_monsters = init_test_monsters(&db).await;
 
-        let app = App::new().app_data(Data::new(db)).service(delete_monster_by_id);
+        let app = App::new().configure(repositories(Arc::new(db))).service(delete_monster_by_id);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let req = test::TestRequest::delete()
         .uri(format!("/monsters/{}", _test_monsters[0].id).as_str()).to_request();
         
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
  
         assert_eq!(resp.status(), http::StatusCode::NO_CONTENT);
     }
 
+    #[actix_rt::test]
+    async fn test_should_delete_with_409_error_if_monster_has_battles() {
+        let db = Database::new().unwrap();
+        let test_battle = init_test_battle(&db).await;
+
+        let app = App::new().configure(repositories(Arc::new(db))).service(delete_monster_by_id);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::delete()
+        .uri(format!("/monsters/{}", test_battle.monster_b).as_str()).to_request();
+
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_delete_a_monster_and_its_battles_with_cascade() {
+        let db = Database::new().unwrap();
+        let test_battle = init_test_battle(&db).await;
+        let db = Arc::new(db);
+
+        let app = App::new().configure(repositories(db.clone())).service(delete_monster_by_id);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::delete()
+        .uri(format!("/monsters/{}?cascade=true", test_battle.monster_b).as_str()).to_request();
+
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), http::StatusCode::NO_CONTENT);
+        assert!(db.get_battle_by_id(&test_battle.id).is_none());
+    }
+
     #[actix_rt::test]
     async fn test_should_delete_with_404_error_if_monster_does_not_exists() {
         
-        let mut db = Database::new();
-        let _test_monsters = init_test_monsters(&mut db).await;
+        let db = Database::new().unwrap();
+        let _test_monsters = init_test_monsters(&db).await;
 
-        let app = App::new().app_data(Data::new(db)).service(delete_monster_by_id);
+        let app = App::new().configure(repositories(Arc::new(db))).service(delete_monster_by_id);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let req = test::TestRequest::delete()
         .uri(format!("/monsters/{}", 99999).as_str()).to_request();
         
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
  
         assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
     }
 
+    #[actix_rt::test]
+    async fn test_should_bulk_create_monsters_with_a_per_item_report() {
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
+
+        let app = App::new().configure(repositories(Arc::new(db))).service(bulk_create_monsters);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::post()
+        .uri("/monsters/bulk")
+        .set_json(&test_monsters[0..3])
+        .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let report: Vec<BulkCreateResult> = test::read_body_json(resp).await;
+        assert_eq!(report.len(), 3);
+        assert!(report.iter().all(|result| result.status == "created"));
+        assert_ne!(report[0].monster.as_ref().unwrap().id, test_monsters[0].id);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_bulk_create_reporting_the_error_code_of_invalid_monsters() {
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
+
+        let app = App::new().configure(repositories(Arc::new(db))).service(bulk_create_monsters);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::post()
+        .uri("/monsters/bulk")
+        .set_json([test_monsters[0].clone(), Monster { name: String::new(), ..test_monsters[1].clone() }])
+        .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let report: Vec<BulkCreateResult> = test::read_body_json(resp).await;
+        assert_eq!(report[0].status, "created");
+        assert_eq!(report[1].status, "failed");
+        assert_eq!(report[1].error.as_ref().map(|error| error.code.as_str()), Some("MONSTER_NAME_EMPTY"));
+    }
+
+    #[actix_rt::test]
+    async fn test_should_bulk_create_with_a_bad_request_response_if_the_list_is_empty() {
+        let db = Database::new().unwrap();
+        let app = App::new().configure(repositories(Arc::new(db))).service(bulk_create_monsters);
+
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::post()
+        .uri("/monsters/bulk")
+        .set_json(Vec::<Monster>::new())
+        .to_request();
+
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_bulk_delete_monsters_with_a_per_item_report() {
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
+        let test_battle = init_test_battle(&db).await;
+
+        let app = App::new().configure(repositories(Arc::new(db))).service(bulk_delete_monsters);
+
+        let app = test::init_service(app).await;
+
+        let ids = vec![test_monsters[0].id.clone(), "99999".to_string(), test_battle.monster_b.clone()];
+        let req = test::TestRequest::delete()
+        .uri("/monsters/bulk")
+        .set_json(BulkDeleteRequest { ids })
+        .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let report: Vec<BulkDeleteResult> = test::read_body_json(resp).await;
+        let statuses: Vec<&str> = report.iter().map(|result| result.status.as_str()).collect();
+        assert_eq!(statuses, vec!["deleted", "not_found", "has_battles"]);
+    }
+
     #[actix_rt::test]
     async fn test_should_import_all_the_csv_objects_into_the_database_successfully() {
-        let db = Database::new();
-        let app = App::new().app_data(Data::new(db)).service(import_csv);
+        let db = Database::new().unwrap();
+        let app = App::new().configure(repositories(Arc::new(db))).service(import_csv);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let mut multipart_form_data_builder = MultiPartFormDataBuilder::new();
         multipart_form_data_builder.with_file("./src/utils/files/monsters-correct.csv", "file", "text/csv", "monsters-correct.csv");
@@ -321,17 +1197,17 @@ mod tests {
             .set_payload(body)
             .to_request();
 
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
         let code = resp.status();
         assert_eq!(code, http::StatusCode::OK);
     }
 
     #[actix_rt::test]
     async fn test_should_fail_when_importing_csv_file_with_inexistent_columns() {
-        let db = Database::new();
-        let app = App::new().app_data(Data::new(db)).service(import_csv);
+        let db = Database::new().unwrap();
+        let app = App::new().configure(repositories(Arc::new(db))).service(import_csv);
 
-        let mut app = test::init_service(app).await;
+        let app = test::init_service(app).await;
 
         let mut multipart_form_data_builder = MultiPartFormDataBuilder::new();
         multipart_form_data_builder.with_file("./src/utils/files/monsters-wrong-column.csv", "file", "text/csv", "monsters-wrong-column.csv");
@@ -344,8 +1220,494 @@ mod tests {
             .set_payload(body)
             .to_request();
 
-        let resp = test::call_service(&mut app, req).await;
+        let resp = test::call_service(&app, req).await;
         let code = resp.status();
         assert_eq!(code, http::StatusCode::BAD_REQUEST);
     }
-}
\ No newline at end of file
+
+    #[actix_rt::test]
+    async fn test_should_import_a_csv_file_in_a_background_job() {
+        use crate::api::job_apis::get_import_by_id;
+        use crate::models::job::{Job, JobStatus};
+
+        let repository = Arc::new(InMemoryRepository::new());
+        let app = App::new()
+            .configure(repositories(repository.clone()))
+            .app_data(Data::new(JobQueue::new(repository.clone(), 1)))
+            .service(import_csv)
+            .service(get_import_by_id);
+        let app = test::init_service(app).await;
+
+        // One row has an empty name and another one a missing column.
+        let mut multipart_form_data_builder = MultiPartFormDataBuilder::new();
+        multipart_form_data_builder.with_file("./src/utils/files/monsters-empty-monster.csv", "file", "text/csv", "monsters-empty-monster.csv");
+        let (header, body) = multipart_form_data_builder.build();
+        let req = test::TestRequest::post()
+            .uri("/monsters/import_csv?async=true")
+            .insert_header(header)
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::ACCEPTED);
+        let mut job: Job = test::read_body_json(resp).await;
+        assert_eq!(job.total, 11);
+
+        for _ in 0..100 {
+            if matches!(job.status, JobStatus::Succeeded | JobStatus::Failed) {
+                break;
+            }
+            actix_rt::time::sleep(std::time::Duration::from_millis(20)).await;
+            let req = test::TestRequest::get().uri(&format!("/imports/{}", job.id)).to_request();
+            job = test::call_and_read_body_json(&app, req).await;
+        }
+
+        assert_eq!(job.status, JobStatus::Succeeded);
+        assert_eq!((job.processed, job.failed), (11, 2));
+        assert_eq!(job.result.unwrap()["imported"], 9);
+        assert_eq!(repository.get_monsters().len(), 9);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_import_a_csv_file_with_a_column_mapping() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let app = test::init_service(App::new().configure(repositories(repository.clone())).service(import_csv)).await;
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "NOME;ATK;DEF;PV;SPD;IMG\nlegado;50;40;100;60;https://example.com/monster.png").unwrap();
+        let path = file.path().to_path_buf();
+        let import = |columns: &str| {
+            let mut multipart_form_data_builder = MultiPartFormDataBuilder::new();
+            multipart_form_data_builder.with_text("delimiter", ";").with_text("columns", columns).with_file(path.clone(), "file", "text/csv", "legacy.csv");
+            let (header, body) = multipart_form_data_builder.build();
+            test::TestRequest::post().uri("/monsters/import_csv").insert_header(header).set_payload(body).to_request()
+        };
+
+        let resp = test::call_service(&app, import(r#"{"NOME": "name", "PV": "health"}"#)).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let monsters: Vec<Monster> = test::call_and_read_body_json(&app, import(r#"{"NOME": "name", "PV": "hp"}"#)).await;
+        assert_eq!((monsters[0].name.as_str(), monsters[0].stats.hp, monsters[0].stats.speed), ("legado", 100, 60));
+    }
+
+    #[actix_rt::test]
+    async fn test_should_import_json_arrays_and_ndjson() {
+        use crate::api::job_apis::get_import_by_id;
+        use crate::models::job::{Job, JobStatus};
+
+        let repository = Arc::new(InMemoryRepository::new());
+        let app = App::new()
+            .configure(repositories(repository.clone()))
+            .app_data(Data::new(JobQueue::new(repository.clone(), 1)))
+            .service(import_json)
+            .service(get_import_by_id);
+        let app = test::init_service(app).await;
+        let row = |name: &str| format!(r#"{{"name": "{}", "image_url": "https://example.com/monster.png", "attack": 50, "defense": 40, "hp": 100, "speed": 60}}"#, name);
+        let import = |uri: &str, body: String| test::TestRequest::post().uri(uri).set_payload(body).to_request();
+
+        let body = format!("  [{}, {}]", row("array-a"), row("array-b"));
+        let monsters: Vec<Monster> = test::call_and_read_body_json(&app, import("/monsters/import_json", body)).await;
+        assert_eq!(monsters.iter().map(|monster| monster.name.as_str()).collect::<Vec<_>>(), ["array-a", "array-b"]);
+
+        // The second line misses its stats and the last one has an empty name.
+        let ndjson = format!("{}\n{{\"name\": \"statless\"}}\n\n{}\n{}\n", row("line-a"), row("line-b"), row(""));
+        let resp = test::call_service(&app, import("/monsters/import_json", ndjson.clone())).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let resp = test::call_service(&app, import("/monsters/import_json?async=true", ndjson)).await;
+        assert_eq!(resp.status(), http::StatusCode::ACCEPTED);
+        let mut job: Job = test::read_body_json(resp).await;
+        assert_eq!(job.total, 4);
+
+        for _ in 0..100 {
+            if matches!(job.status, JobStatus::Succeeded | JobStatus::Failed) {
+                break;
+            }
+            actix_rt::time::sleep(std::time::Duration::from_millis(20)).await;
+            let req = test::TestRequest::get().uri(&format!("/imports/{}", job.id)).to_request();
+            job = test::call_and_read_body_json(&app, req).await;
+        }
+        assert_eq!((job.status, job.processed, job.failed), (JobStatus::Succeeded, 4, 2));
+        assert_eq!(repository.get_monsters().len(), 4);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_answer_conditional_requests_with_etags() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let monster = repository.create_monster(Monster {
+            id: String::new(),
+            name: "tagged".to_string(),
+            image_url: "https://example.com/monster.png".to_string(),
+            stats: Stats { attack: 40, defense: 20, hp: 50, speed: 80 },
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        }).unwrap();
+        let app = App::new().configure(repositories(repository.clone())).service(get_monsters).service(get_monster_by_id).service(update_monster_by_id);
+        let app = test::init_service(app).await;
+        let uri = format!("/monsters/{}", monster.id);
+
+        let resp = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        let etag = resp.headers().get(header::ETAG).unwrap().to_str().unwrap().to_string();
+        let req = test::TestRequest::get().uri(&uri).insert_header((header::IF_NONE_MATCH, etag.clone())).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::NOT_MODIFIED);
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/monsters?scope=all").to_request()).await;
+        let list_etag = resp.headers().get(header::ETAG).unwrap().clone();
+        let req = test::TestRequest::get().uri("/monsters?scope=all").insert_header((header::IF_NONE_MATCH, list_etag.clone())).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::NOT_MODIFIED);
+
+        let update = |if_match: &str, name: &str| test::TestRequest::put()
+            .uri(&uri)
+            .insert_header((header::IF_MATCH, if_match.to_string()))
+            .set_json(Monster { name: name.to_string(), ..monster.clone() })
+            .to_request();
+        let resp = test::call_service(&app, update(&etag, "first")).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert_ne!(resp.headers().get(header::ETAG).unwrap().to_str().unwrap(), etag);
+        // The second writer read the monster before the first update.
+        assert_eq!(test::call_service(&app, update(&etag, "second")).await.status(), http::StatusCode::PRECONDITION_FAILED);
+        assert_eq!(repository.get_monster_by_id(&monster.id).unwrap().name, "first");
+        let req = test::TestRequest::get().uri("/monsters?scope=all").insert_header((header::IF_NONE_MATCH, list_etag)).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_reject_updates_made_from_a_stale_version() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let monster = repository.create_monster(Monster {
+            id: String::new(),
+            name: "versioned".to_string(),
+            image_url: "https://example.com/monster.png".to_string(),
+            stats: Stats { attack: 40, defense: 20, hp: 50, speed: 80 },
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        }).unwrap();
+        let app = test::init_service(App::new().configure(repositories(repository.clone())).service(update_monster_by_id)).await;
+        let update = |name: &str, version: i32| test::TestRequest::put()
+            .uri(&format!("/monsters/{}", monster.id))
+            .set_json(Monster { name: name.to_string(), version, ..monster.clone() })
+            .to_request();
+
+        let updated: Monster = test::call_and_read_body_json(&app, update("first", 1)).await;
+        assert_eq!(updated.version, 2);
+        // Both writers read version 1, the second one must read the monster again.
+        let resp = test::call_service(&app, update("second", 1)).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let error: ApiError = test::read_body_json(resp).await;
+        assert_eq!(error.code, "MONSTER_VERSION_STALE");
+        assert_eq!(test::call_service(&app, update("second", 0)).await.status(), http::StatusCode::BAD_REQUEST);
+        assert_eq!(repository.get_monster_by_id(&monster.id).unwrap().name, "first");
+        assert_eq!(test::call_service(&app, update("second", 2)).await.status(), http::StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_skip_or_update_duplicates_when_importing() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let app = test::init_service(App::new().configure(repositories(repository.clone())).service(import_json)).await;
+        let row = |name: &str, external_id: &str, attack: i32| format!(r#"{{"name": "{}", "externalId": {}, "image_url": "https://example.com/monster.png", "attack": {}, "defense": 40, "hp": 100, "speed": 60}}"#, name, external_id, attack);
+        let import = |mode: &str, body: String| test::TestRequest::post().uri(&format!("/monsters/import_json?mode={}", mode)).set_payload(body).to_request();
+
+        let monsters: Vec<Monster> = test::call_and_read_body_json(&app, import("create", format!("[{}, {}]", row("Dup", "null", 50), row("tagged", r#""ext-1""#, 50)))).await;
+        assert_eq!(monsters.len(), 2);
+
+        let body = format!("[{}, {}, {}]", row(" dup ", "null", 70), row("renamed", r#""ext-1""#, 70), row("fresh", "null", 70));
+        let report: serde_json::Value = test::call_and_read_body_json(&app, import("skip_duplicates", body.clone())).await;
+        assert_eq!((report["created"].as_i64(), report["updated"].as_i64(), report["skipped"].as_i64()), (Some(1), Some(0), Some(2)));
+        let report: serde_json::Value = test::call_and_read_body_json(&app, import("upsert", body)).await;
+        assert_eq!((report["created"].as_i64(), report["updated"].as_i64(), report["skipped"].as_i64()), (Some(0), Some(3), Some(0)));
+
+        let tagged = repository.get_monster_by_id(&monsters[1].id).unwrap();
+        assert_eq!((tagged.name.as_str(), tagged.stats.attack, tagged.external_id.as_deref()), ("renamed", 70, Some("ext-1")));
+        assert_eq!(repository.get_monsters().len(), 3);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_scan_for_duplicate_monsters_in_a_background_job() {
+        use crate::api::job_apis::get_job_by_id;
+        use crate::models::duplicates::DuplicateCluster;
+        use crate::models::job::{Job, JobStatus};
+
+        let repository = Arc::new(InMemoryRepository::new());
+        let stats = Stats { attack: 50, defense: 40, hp: 100, speed: 60 };
+        let monster = |name: &str, stats: Stats, day: u32| Monster {
+            id: String::new(),
+            image_url: "https://example.com/monster.png".to_string(),
+            stats,
+            created_at: chrono::NaiveDate::from_ymd_opt(2026, 10, day).and_then(|date| date.and_hms_opt(0, 0, 0)),
+            updated_at: None,
+            name: name.to_string(),
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        };
+        let copy = repository.create_monster(monster("Dead Unicorn 2", stats, 2)).unwrap();
+        let original = repository.create_monster(monster("Dead Unicorn", stats, 1)).unwrap();
+        repository.create_monster(monster("Dead Unicorn", Stats { hp: 120, ..stats }, 3)).unwrap();
+        repository.create_monster(monster("Red Dragon", stats, 4)).unwrap();
+        let app = App::new()
+            .configure(repositories(repository.clone()))
+            .app_data(Data::new(JobQueue::new(repository.clone(), 1)))
+            .service(scan_duplicate_monsters)
+            .service(get_job_by_id);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::post().uri("/monsters/duplicates/scan?min_similarity=2").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::post().uri("/monsters/duplicates/scan").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::ACCEPTED);
+        let mut job: Job = test::read_body_json(resp).await;
+        for _ in 0..100 {
+            if matches!(job.status, JobStatus::Succeeded | JobStatus::Failed) {
+                break;
+            }
+            actix_rt::time::sleep(std::time::Duration::from_millis(20)).await;
+            let req = test::TestRequest::get().uri(&format!("/jobs/{}", job.id)).to_request();
+            job = test::call_and_read_body_json(&app, req).await;
+        }
+
+        assert_eq!(job.status, JobStatus::Succeeded);
+        let clusters: Vec<DuplicateCluster> = serde_json::from_value(job.result.unwrap()["clusters"].clone()).unwrap();
+        assert_eq!(clusters.len(), 1);
+        assert_eq!((clusters[0].keep.as_str(), clusters[0].duplicates.clone()), (original.id.as_str(), vec![copy.id]));
+        assert!(clusters[0].confidence > 0.7);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_matchmake_the_closest_opponent_not_fought_recently() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let monster = |name: &str, attack: i32| Monster {
+            id: String::new(),
+            image_url: "https://example.com/monster.png".to_string(),
+            stats: Stats { attack, defense: 40, hp: 100, speed: 60 },
+            created_at: None,
+            updated_at: None,
+            name: name.to_string(),
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        };
+        let challenger = repository.create_monster(monster("Challenger", 50)).unwrap();
+        let closest = repository.create_monster(monster("Closest", 52)).unwrap();
+        let runner_up = repository.create_monster(monster("Runner-up", 45)).unwrap();
+        repository.create_monster(monster("Brute", 90)).unwrap();
+        let app = App::new().configure(repositories(repository)).service(matchmake);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri(&format!("/monsters/{}/matchmake", challenger.id)).to_request();
+        let matchmaking: Matchmaking = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((matchmaking.opponent.monster.id.as_str(), matchmaking.opponent.distance), (closest.id.as_str(), 2));
+        assert!(matchmaking.battle.is_none());
+
+        let req = test::TestRequest::get().uri(&format!("/monsters/{}/matchmake?fight=true", challenger.id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let matchmaking: Matchmaking = test::read_body_json(resp).await;
+        let battle = matchmaking.battle.unwrap();
+        assert_eq!((battle.monster_a, battle.monster_b), (challenger.id.clone(), closest.id.clone()));
+
+        let req = test::TestRequest::get().uri(&format!("/monsters/{}/matchmake", challenger.id)).to_request();
+        let matchmaking: Matchmaking = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(matchmaking.opponent.monster.id, runner_up.id);
+        let req = test::TestRequest::get().uri(&format!("/monsters/{}/matchmake?recent=0", challenger.id)).to_request();
+        let matchmaking: Matchmaking = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(matchmaking.opponent.monster.id, closest.id);
+
+        let req = test::TestRequest::get().uri("/monsters/unknown/matchmake").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_render_and_cache_the_card_of_a_monster() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use actix_web::web::Bytes;
+        use futures::future::{FutureExt, LocalBoxFuture};
+
+        // Holds a red sprite as the uploaded image, counting how often it is read.
+        struct SpriteStorage(AtomicUsize);
+        impl Storage for SpriteStorage {
+            fn put<'a>(&'a self, _key: &'a str, _content_type: &'a str, _bytes: Bytes) -> LocalBoxFuture<'a, Result<(), String>> {
+                async { Ok(()) }.boxed_local()
+            }
+            fn get<'a>(&'a self, _key: &'a str) -> LocalBoxFuture<'a, Result<Option<Bytes>, String>> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                let mut sprite = std::io::Cursor::new(Vec::new());
+                image::RgbaImage::from_pixel(8, 8, image::Rgba([255, 0, 0, 255])).write_to(&mut sprite, image::ImageFormat::Png).unwrap();
+                async move { Ok(Some(Bytes::from(sprite.into_inner()))) }.boxed_local()
+            }
+        }
+        let storage = Arc::new(SpriteStorage(AtomicUsize::new(0)));
+        let repository = Arc::new(InMemoryRepository::new());
+        let monster = repository.create_monster(Monster {
+            id: String::new(),
+            name: "card monster".to_string(),
+            image_url: "http://localhost:8080/api/monsters/card/image".to_string(),
+            stats: Stats { attack: 40, defense: 20, hp: 50, speed: 80 },
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        }).unwrap();
+        let monster = repository.set_monster_image_url(&monster.id, &format!("http://localhost:8080/api/monsters/{}/image", monster.id)).unwrap().unwrap();
+
+        let app = App::new()
+            .configure(repositories(repository))
+            .app_data(Data::new(CardCache::new()))
+            .app_data(Data::<dyn Storage>::from(storage.clone() as Arc<dyn Storage>))
+            .service(get_monster_card);
+        let app = test::init_service(app).await;
+
+        for _ in 0..2 {
+            let req = test::TestRequest::get().uri(&format!("/monsters/{}/card.png", monster.id)).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK);
+            assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "image/png");
+            let card = image::load_from_memory(&test::read_body(resp).await).unwrap().into_rgba8();
+            assert_eq!(card.dimensions(), (cards::CARD_WIDTH, cards::CARD_HEIGHT));
+            assert_eq!(card.get_pixel(200, 200), &image::Rgba([255, 0, 0, 255]));
+        }
+        assert_eq!(storage.0.load(Ordering::SeqCst), 1);
+        assert_eq!(cards::tier(&monster.stats), 'C');
+
+        let req = test::TestRequest::get().uri("/monsters/123/card.png").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_get_a_monster_with_decayed_effective_stats() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let monster = repository.create_monster(Monster {
+            id: String::new(),
+            name: "idle monster".to_string(),
+            image_url: "https://loremflickr.com/640/480".to_string(),
+            stats: Stats { attack: 40, defense: 20, hp: 50, speed: 80 },
+            created_at: Some(Utc::now().naive_utc() - Duration::days(30)),
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        }).unwrap();
+        let decay = StatDecay { after_days: 10, daily_rate: Fixed::from_percent(1), max_decay: Fixed::from_percent(50) };
+
+        let app = App::new()
+            .configure(repositories(repository.clone()))
+            .app_data(Data::new(decay))
+            .service(get_monster_by_id);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri(&format!("/monsters/{}", monster.id)).to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["attack"], 40);
+        assert_eq!(body["effective_stats"]["attack"], 32);
+        assert_eq!(body["effective_stats"]["hp"], 50);
+
+        let battle = repository.create_battle(Battle {
+            id: String::new(),
+            monster_a: monster.id.clone(),
+            monster_b: monster.id.clone(),
+            winner: None,
+            created_at: None,
+            updated_at: None,
+            outcome: BattleOutcome::Draw,
+            manual: true,
+            season_id: None,
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        }).unwrap();
+        assert_eq!(repository.get_monster_by_id(&monster.id).unwrap().last_battle_at, battle.created_at);
+
+        let req = test::TestRequest::get().uri(&format!("/monsters/{}", monster.id)).to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["effective_stats"]["attack"], 40);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_level_up_monsters_with_the_xp_of_their_wins() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let new_monster = |name: &str, attack: i32| Monster { id: String::new(), name: name.to_string(), image_url: String::new(), stats: Stats { attack, defense: 20, hp: 50, speed: 80 }, created_at: None, updated_at: None, last_battle_at: None, level: 1, xp: 0, owner_id: None, image_broken: false, external_id: None, version: 1 };
+        let winner = repository.create_monster(new_monster("winner", 40)).unwrap();
+        let loser = repository.create_monster(new_monster("loser", 40)).unwrap();
+        let win = || Battle {
+            id: String::new(),
+            monster_a: winner.id.clone(),
+            monster_b: loser.id.clone(),
+            winner: Some(winner.id.clone()),
+            created_at: None,
+            updated_at: None,
+            outcome: BattleOutcome::Win,
+            manual: true,
+            season_id: None,
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        };
+        repository.create_battle(win()).unwrap();
+        let progressed = repository.get_monster_by_id(&winner.id).unwrap();
+        assert_eq!((progressed.level, progressed.xp), (1, 50));
+        // The second win reaches the 100 XP of level 2, leveling up raises every stat.
+        repository.create_battle(win()).unwrap();
+        let progressed = repository.get_monster_by_id(&winner.id).unwrap();
+        assert_eq!((progressed.level, progressed.xp), (2, 0));
+        assert_eq!(progressed.stats, Stats { attack: 42, defense: 21, hp: 52, speed: 84 });
+        assert_eq!(repository.get_monster_by_id(&loser.id).unwrap().level, 1);
+
+        let app = App::new().configure(repositories(repository.clone())).service(level_up_monster).service(update_monster_by_id);
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::post().uri(&format!("/monsters/{}/level_up", loser.id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!((&body["level"], &body["xp"], &body["attack"]), (&Value::from(2), &Value::from(0), &Value::from(42)));
+
+        // Updates keep the level and XP.
+        let req = test::TestRequest::put().uri(&format!("/monsters/{}", loser.id)).set_json(new_monster("renamed", 40)).to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["level"], 2);
+        let req = test::TestRequest::post().uri("/monsters/unknown/level_up").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,223 @@
+use actix_web::{web, get, post, put, delete, HttpResponse};
+use serde::{Deserialize, Serialize};
+use crate::models::moves::{Move, MAX_MONSTER_MOVES};
+use crate::models::status_effect::StatusEffect;
+use crate::repository::monster_repository::MonsterRepository;
+use crate::repository::move_repository::MoveRepository;
+use super::error::repository_error_response;
+
+// `accuracy` defaults to 100, and `effect_chance` to 100 for moves with an effect.
+#[derive(Serialize, Deserialize)]
+pub struct MoveRequest {
+    name: Option<String>,
+    power: Option<i32>,
+    accuracy: Option<i32>,
+    element: Option<String>,
+    effect: Option<StatusEffect>,
+    effect_chance: Option<i32>,
+}
+
+impl MoveRequest {
+    fn into_move(self) -> Result<Move, String> {
+        let name = match self.name.map(|name| name.trim().to_string()) {
+            Some(name) if !name.is_empty() => name,
+            _ => return Err("Name is required".to_string()),
+        };
+        let power = match self.power {
+            Some(power) if power > 0 => power,
+            Some(_) => return Err("Power must be positive".to_string()),
+            None => return Err("Power is required".to_string()),
+        };
+        let accuracy = self.accuracy.unwrap_or(100);
+        if !(1..=100).contains(&accuracy) {
+            return Err("Accuracy must be between 1 and 100".to_string());
+        }
+        let effect_chance = self.effect_chance.unwrap_or(if self.effect.is_some() { 100 } else { 0 });
+        if !(0..=100).contains(&effect_chance) {
+            return Err("Effect chance must be between 0 and 100".to_string());
+        }
+
+        Ok(Move {
+            id: String::new(),
+            name,
+            power,
+            accuracy,
+            element: self.element.map(|element| element.trim().to_lowercase()).filter(|element| !element.is_empty()),
+            effect: self.effect,
+            effect_chance,
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: None,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MonsterMovesRequest {
+    moves: Vec<String>,
+}
+
+#[get("/moves")]
+pub async fn get_moves(move_repository: web::Data<dyn MoveRepository>) -> HttpResponse {
+    HttpResponse::Ok().json(move_repository.get_moves())
+}
+
+#[get("/moves/{id}")]
+pub async fn get_move_by_id(move_repository: web::Data<dyn MoveRepository>, id: web::Path<String>) -> HttpResponse {
+    match move_repository.get_move_by_id(&id) {
+        Some(found_move) => HttpResponse::Ok().json(found_move),
+        None => HttpResponse::NotFound().json("Move not found"),
+    }
+}
+
+#[post("/moves")]
+pub async fn create_move(move_repository: web::Data<dyn MoveRepository>, request: web::Json<MoveRequest>) -> HttpResponse {
+    let new_move = match request.into_inner().into_move() {
+        Ok(new_move) => new_move,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+
+    match move_repository.create_move(new_move) {
+        Ok(created_move) => HttpResponse::Created().json(created_move),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[put("/moves/{id}")]
+pub async fn update_move_by_id(move_repository: web::Data<dyn MoveRepository>, id: web::Path<String>, request: web::Json<MoveRequest>) -> HttpResponse {
+    let updated_move = match request.into_inner().into_move() {
+        Ok(updated_move) => updated_move,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+
+    match move_repository.update_move_by_id(&id, updated_move) {
+        Ok(Some(updated_move)) => HttpResponse::Ok().json(updated_move),
+        Ok(None) => HttpResponse::NotFound().json("Move not found"),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[delete("/moves/{id}")]
+pub async fn delete_move_by_id(move_repository: web::Data<dyn MoveRepository>, id: web::Path<String>) -> HttpResponse {
+    match move_repository.delete_move_by_id(&id) {
+        Some(_) => HttpResponse::NoContent().finish(),
+        None => HttpResponse::NotFound().json("Move not found"),
+    }
+}
+
+#[get("/monsters/{id}/moves")]
+pub async fn get_monster_moves(monster_repository: web::Data<dyn MonsterRepository>, move_repository: web::Data<dyn MoveRepository>, id: web::Path<String>) -> HttpResponse {
+    if monster_repository.get_monster_by_id(&id).is_none() {
+        return HttpResponse::NotFound().json("Monster not found");
+    }
+    HttpResponse::Ok().json(move_repository.get_monster_moves(&id))
+}
+
+// Teaches the monster up to MAX_MONSTER_MOVES moves in the given order, replacing the ones it knew.
+#[put("/monsters/{id}/moves")]
+pub async fn set_monster_moves(
+    monster_repository: web::Data<dyn MonsterRepository>,
+    move_repository: web::Data<dyn MoveRepository>,
+    id: web::Path<String>,
+    request: web::Json<MonsterMovesRequest>,
+) -> HttpResponse {
+    let move_ids = &request.moves;
+    if move_ids.len() > MAX_MONSTER_MOVES {
+        return HttpResponse::BadRequest().json(format!("A monster knows at most {} moves", MAX_MONSTER_MOVES));
+    }
+    if move_ids.iter().enumerate().any(|(index, move_id)| move_ids[..index].contains(move_id)) {
+        return HttpResponse::BadRequest().json("A monster can't know a move twice");
+    }
+    if monster_repository.get_monster_by_id(&id).is_none() {
+        return HttpResponse::NotFound().json("Monster not found");
+    }
+
+    match move_repository.set_monster_moves(&id, move_ids) {
+        Ok(moves) => HttpResponse::Ok().json(moves),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use serde_json::{json, Value};
+    use crate::api::config::repositories;
+    use crate::repository::database::Database;
+    use crate::utils::test_utils::init_test_monsters;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_create_update_and_delete_a_move() {
+        let app = App::new()
+            .configure(repositories(Arc::new(Database::new().unwrap())))
+            .service(create_move)
+            .service(get_move_by_id)
+            .service(update_move_by_id)
+            .service(delete_move_by_id);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::post().uri("/moves").set_json(json!({ "name": "Venom Fang", "power": 80, "effect": "poison" })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let created: Move = test::read_body_json(resp).await;
+        assert_eq!((created.accuracy, created.effect, created.effect_chance), (100, Some(StatusEffect::Poison), 100));
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/moves/{}", created.id))
+            .set_json(json!({ "name": "Venom Fang", "power": 90, "accuracy": 85, "element": " Poison ", "effect": "poison", "effect_chance": 30 }))
+            .to_request();
+        let updated: Move = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((updated.power, updated.accuracy, updated.element.as_deref(), updated.effect_chance), (90, 85, Some("poison"), 30));
+        assert!(updated.updated_at.is_some());
+
+        let req = test::TestRequest::post().uri("/moves").set_json(json!({ "name": "Tackle", "power": 40, "accuracy": 120 })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::delete().uri(&format!("/moves/{}", created.id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NO_CONTENT);
+
+        let req = test::TestRequest::get().uri(&format!("/moves/{}", created.id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_teach_moves_to_a_monster() {
+        let db = Arc::new(Database::new().unwrap());
+        let monster = init_test_monsters(db.as_ref()).await.remove(0);
+        let tackle = db.create_move(MoveRequest { name: Some("Tackle".to_string()), power: Some(40), accuracy: None, element: None, effect: None, effect_chance: None }.into_move().unwrap()).unwrap();
+        let ember = db.create_move(MoveRequest { name: Some("Ember".to_string()), power: Some(60), accuracy: None, element: None, effect: Some(StatusEffect::Burn), effect_chance: Some(10) }.into_move().unwrap()).unwrap();
+        let app = App::new().configure(repositories(db.clone())).service(get_monster_moves).service(set_monster_moves);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::put().uri(&format!("/monsters/{}/moves", monster.id)).set_json(json!({ "moves": [ember.id, tackle.id] })).to_request();
+        let moves: Vec<Move> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(moves.iter().map(|known_move| known_move.name.as_str()).collect::<Vec<_>>(), vec!["Ember", "Tackle"]);
+
+        let req = test::TestRequest::get().uri(&format!("/monsters/{}/moves", monster.id)).to_request();
+        let moves: Vec<Move> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(moves.iter().map(|known_move| known_move.id.as_str()).collect::<Vec<_>>(), vec![ember.id.as_str(), tackle.id.as_str()]);
+
+        let req = test::TestRequest::put().uri(&format!("/monsters/{}/moves", monster.id)).set_json(json!({ "moves": [tackle.id, "123"] })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "MONSTER_MOVE_NOT_FOUND");
+
+        let req = test::TestRequest::put().uri(&format!("/monsters/{}/moves", monster.id)).set_json(json!({ "moves": [tackle.id, tackle.id] })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        // Deleting a move makes the monster forget it.
+        db.delete_move_by_id(&ember.id);
+        assert_eq!(db.get_monster_moves(&monster.id).into_iter().map(|known_move| known_move.id).collect::<Vec<_>>(), vec![tackle.id]);
+
+        let req = test::TestRequest::get().uri("/monsters/123/moves").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,199 @@
+use actix_web::http::header;
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, HttpResponseBuilder};
+use serde::Serialize;
+use serde_json::Value;
+use crate::models::page::{Page, NEXT_CURSOR_HEADER};
+
+/*
+What list endpoints answer with: `?format=` when given, otherwise the first of JSON and CSV the Accept header
+ranks, JSON when it names neither.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    Json,
+    Csv,
+}
+
+impl ListFormat {
+    pub fn negotiate(req: &HttpRequest, format: Option<&str>) -> Result<Self, String> {
+        match format {
+            Some("json") => return Ok(ListFormat::Json),
+            Some("csv") => return Ok(ListFormat::Csv),
+            Some(format) => return Err(format!("Unknown format {:?}, expected json or csv", format)),
+            None => {}
+        }
+        let ranked = req.get_header::<header::Accept>().map(|accept| accept.ranked()).unwrap_or_default();
+        Ok(ranked
+            .iter()
+            .find_map(|mime| match mime.essence_str() {
+                "text/csv" => Some(ListFormat::Csv),
+                "application/json" | "application/*" | "*/*" => Some(ListFormat::Json),
+                _ => None,
+            })
+            .unwrap_or(ListFormat::Json))
+    }
+
+    /*
+    The rows as a JSON array, or as CSV with a header line naming a column per field. Nested objects are flattened
+    into dotted columns (`monster_a.name`) and arrays written as JSON, rows missing a column leaving it empty.
+    */
+    pub fn respond<T: Serialize>(self, rows: &[T]) -> HttpResponse {
+        self.respond_with(HttpResponse::Ok(), rows)
+    }
+
+    // The page in its envelope as JSON, or its rows as CSV with the next cursor in a header.
+    pub fn respond_page<T: Serialize>(self, page: &Page<T>) -> HttpResponse {
+        let mut response = HttpResponse::Ok();
+        if self == ListFormat::Json {
+            return response.insert_header((header::VARY, "Accept")).json(page);
+        }
+        if let Some(next_cursor) = &page.next_cursor {
+            response.insert_header((NEXT_CURSOR_HEADER, next_cursor.as_str()));
+        }
+        self.respond_with(response, &page.items)
+    }
+
+    fn respond_with<T: Serialize>(self, mut response: HttpResponseBuilder, rows: &[T]) -> HttpResponse {
+        response.insert_header((header::VARY, "Accept"));
+        if self == ListFormat::Json {
+            return response.json(rows);
+        }
+
+        let rows: Vec<Vec<(String, String)>> = rows
+            .iter()
+            .map(|row| {
+                let mut cells = Vec::new();
+                flatten("", serde_json::to_value(row).expect("Rows serialize to JSON"), &mut cells);
+                cells
+            })
+            .collect();
+        let mut columns: Vec<&str> = Vec::new();
+        for (column, _) in rows.iter().flatten() {
+            if !columns.contains(&column.as_str()) {
+                columns.push(column);
+            }
+        }
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&columns).expect("CSV is written in memory");
+        for row in &rows {
+            let record = columns.iter().map(|column| row.iter().find(|(name, _)| name == column).map_or("", |(_, value)| value.as_str()));
+            writer.write_record(record).expect("CSV is written in memory");
+        }
+        response.content_type("text/csv; charset=utf-8").body(writer.into_inner().expect("CSV is written in memory"))
+    }
+}
+
+fn flatten(prefix: &str, value: Value, cells: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(fields) if prefix.is_empty() || !fields.is_empty() => {
+            for (name, value) in fields {
+                let column = if prefix.is_empty() { name } else { format!("{}.{}", prefix, name) };
+                flatten(&column, value, cells);
+            }
+        }
+        Value::Null => cells.push((prefix.to_string(), String::new())),
+        Value::String(value) => cells.push((prefix.to_string(), escape_formula(value))),
+        value => cells.push((prefix.to_string(), value.to_string())),
+    }
+}
+
+/*
+Spreadsheets run text starting with =, +, - or @ as a formula, and some strip a leading tab or carriage return
+first, so such text is prefixed with a quote to be shown as is. Numbers are left alone, negative ones included.
+*/
+fn escape_formula(value: String) -> String {
+    if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use crate::api::battle_apis::get_battles;
+    use crate::api::config::repositories;
+    use crate::api::monster_apis::get_monsters;
+    use crate::models::battle::{Battle, BattleOutcome};
+    use crate::models::monster::{Monster, Stats};
+    use crate::repository::battle_repository::BattleRepository;
+    use crate::repository::memory_repository::InMemoryRepository;
+    use crate::repository::monster_repository::MonsterRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_list_monsters_and_battles_as_csv() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let create_monster = |name: &str| repository.create_monster(Monster {
+            id: String::new(),
+            name: name.to_string(),
+            image_url: "https://example.com/monster.png".to_string(),
+            stats: Stats { attack: 40, defense: 20, hp: 50, speed: 80 },
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        }).unwrap();
+        let (monster_a, monster_b) = (create_monster("spreadsheet, the first"), create_monster("=HYPERLINK(\"https://example.com\")"));
+        let battle = repository.create_battle(Battle {
+            id: String::new(),
+            monster_a: monster_a.id.clone(),
+            monster_b: monster_b.id.clone(),
+            winner: Some(monster_a.id.clone()),
+            created_at: None,
+            updated_at: None,
+            outcome: BattleOutcome::Win,
+            manual: true,
+            season_id: None,
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        }).unwrap();
+        let app = test::init_service(App::new().configure(repositories(repository.clone())).service(get_monsters).service(get_battles)).await;
+
+        let req = test::TestRequest::get().uri("/monsters?scope=all").insert_header((header::ACCEPT, "text/csv, application/json;q=0.5")).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "text/csv; charset=utf-8");
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        let mut reader = csv::Reader::from_reader(body.as_bytes());
+        assert!(["id", "name", "attack", "hp"].iter().all(|column| reader.headers().unwrap().iter().any(|header| header == *column)));
+        let name_column = reader.headers().unwrap().iter().position(|header| header == "name").unwrap();
+        let mut names: Vec<String> = reader.records().map(|record| record.unwrap()[name_column].to_string()).collect();
+        names.sort();
+        assert_eq!(names, ["'=HYPERLINK(\"https://example.com\")", "spreadsheet, the first"]);
+
+        let req = test::TestRequest::get().uri("/battles?format=csv&expand=monsters").to_request();
+        let body = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+        let mut reader = csv::Reader::from_reader(body.as_bytes());
+        let winner_column = reader.headers().unwrap().iter().position(|header| header == "winner.id").unwrap();
+        assert_eq!(reader.records().next().unwrap().unwrap().get(winner_column), battle.winner.as_deref());
+
+        let req = test::TestRequest::get().uri("/battles").insert_header((header::ACCEPT, "application/json, text/csv;q=0.9")).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "application/json");
+        let req = test::TestRequest::get().uri("/battles?format=xml").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_quote_text_that_spreadsheets_would_run_as_a_formula() {
+        let mut cells = Vec::new();
+        flatten("", serde_json::json!({ "a": "+1", "b": "-1", "c": "@SUM(A1)", "d": "\tx", "e": "\r=x", "f": "a=b", "g": -1 }), &mut cells);
+        let values: Vec<&str> = cells.iter().map(|(_, value)| value.as_str()).collect();
+        assert_eq!(values, ["'+1", "'-1", "'@SUM(A1)", "'\tx", "'\r=x", "a=b", "-1"]);
+    }
+}
@@ -0,0 +1,300 @@
+use actix_web::{web, get, post, put, delete, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use crate::models::note::{MonsterNote, NotesPage, MAX_NOTE_LENGTH};
+use crate::models::rate_limit::ApiKey;
+use crate::repository::battle_repository::BattleRepository;
+use crate::repository::monster_repository::MonsterRepository;
+use crate::repository::note_repository::NoteRepository;
+use crate::repository::rate_limit_repository::RateLimitRepository;
+use super::auth;
+use super::error::repository_error_response;
+
+const DEFAULT_NOTES_PER_PAGE: i64 = 20;
+const MAX_NOTES_PER_PAGE: i64 = 100;
+const API_KEY_REQUIRED: &str = "Notes require an API key";
+
+// Notes are plain markdown, unpinned and referencing no battle by default.
+#[derive(Serialize, Deserialize)]
+pub struct NoteRequest {
+    body: Option<String>,
+    pinned: Option<bool>,
+    battles: Option<Vec<String>>,
+}
+
+impl NoteRequest {
+    fn into_note(self, monster_id: &str, owner: &ApiKey) -> Result<MonsterNote, String> {
+        let body = match self.body {
+            Some(body) if !body.trim().is_empty() => body,
+            _ => return Err("Body is required".to_string()),
+        };
+        if body.chars().count() > MAX_NOTE_LENGTH {
+            return Err(format!("Notes are at most {} characters long", MAX_NOTE_LENGTH));
+        }
+        let battle_ids = self.battles.unwrap_or_default();
+        if battle_ids.iter().enumerate().any(|(index, battle_id)| battle_ids[..index].contains(battle_id)) {
+            return Err("A note can't reference a battle twice".to_string());
+        }
+
+        Ok(MonsterNote {
+            id: String::new(),
+            monster_id: monster_id.to_string(),
+            owner: owner.id.clone(),
+            body,
+            pinned: self.pinned.unwrap_or(false),
+            battle_ids,
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: None,
+        })
+    }
+}
+
+// `page` counts from 1, `q` only keeps the notes containing it.
+#[derive(Deserialize)]
+pub struct NotesQuery {
+    q: Option<String>,
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+impl NotesQuery {
+    fn search(&self) -> Option<&str> {
+        self.q.as_deref().map(str::trim).filter(|search| !search.is_empty())
+    }
+
+    fn page(&self) -> Result<(i64, i64), String> {
+        let page = self.page.unwrap_or(1);
+        if page < 1 {
+            return Err("Page must be at least 1".to_string());
+        }
+        let per_page = self.per_page.unwrap_or(DEFAULT_NOTES_PER_PAGE);
+        if !(1..=MAX_NOTES_PER_PAGE).contains(&per_page) {
+            return Err(format!("Per page must be between 1 and {}", MAX_NOTES_PER_PAGE));
+        }
+        Ok((page, per_page))
+    }
+}
+
+// Notes only reference battles their monster fought.
+fn check_battles(battle_repository: &dyn BattleRepository, note: &MonsterNote) -> Result<(), String> {
+    for battle_id in &note.battle_ids {
+        match battle_repository.get_battle_by_id(battle_id) {
+            Some(battle) if battle.monster_a == note.monster_id || battle.monster_b == note.monster_id => {}
+            _ => return Err(format!("Battle {} is not one of the monster", battle_id)),
+        }
+    }
+    Ok(())
+}
+
+fn notes_page(note_repository: &dyn NoteRepository, owner: &ApiKey, monster_id: Option<&str>, query: &NotesQuery) -> HttpResponse {
+    let (page, per_page) = match query.page() {
+        Ok(page) => page,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+    let (notes, total) = note_repository.get_notes(&owner.id, monster_id, query.search(), (page - 1) * per_page, per_page);
+    HttpResponse::Ok().json(NotesPage { notes, page, per_page, total })
+}
+
+// The notes of the API key on the monster, pinned ones first then the newest.
+#[get("/monsters/{id}/notes")]
+pub async fn get_monster_notes(
+    req: HttpRequest,
+    rate_limit_repository: web::Data<dyn RateLimitRepository>,
+    monster_repository: web::Data<dyn MonsterRepository>,
+    note_repository: web::Data<dyn NoteRepository>,
+    id: web::Path<String>,
+    query: web::Query<NotesQuery>,
+) -> HttpResponse {
+    let Some(owner) = auth::api_key(&req, rate_limit_repository.get_ref()) else {
+        return HttpResponse::Unauthorized().json(API_KEY_REQUIRED);
+    };
+    if monster_repository.get_monster_by_id(&id).is_none() {
+        return HttpResponse::NotFound().json("Monster not found");
+    }
+    notes_page(note_repository.get_ref(), &owner, Some(&id), &query)
+}
+
+#[post("/monsters/{id}/notes")]
+pub async fn create_monster_note(
+    req: HttpRequest,
+    rate_limit_repository: web::Data<dyn RateLimitRepository>,
+    monster_repository: web::Data<dyn MonsterRepository>,
+    battle_repository: web::Data<dyn BattleRepository>,
+    note_repository: web::Data<dyn NoteRepository>,
+    id: web::Path<String>,
+    request: web::Json<NoteRequest>,
+) -> HttpResponse {
+    let Some(owner) = auth::api_key(&req, rate_limit_repository.get_ref()) else {
+        return HttpResponse::Unauthorized().json(API_KEY_REQUIRED);
+    };
+    let note = match request.into_inner().into_note(&id, &owner) {
+        Ok(note) => note,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+    if monster_repository.get_monster_by_id(&id).is_none() {
+        return HttpResponse::NotFound().json("Monster not found");
+    }
+    if let Err(message) = check_battles(battle_repository.get_ref(), &note) {
+        return HttpResponse::BadRequest().json(message);
+    }
+
+    match note_repository.create_note(note) {
+        Ok(note) => HttpResponse::Created().json(note),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+// The notes of the API key across its monsters.
+#[get("/notes")]
+pub async fn get_notes(req: HttpRequest, rate_limit_repository: web::Data<dyn RateLimitRepository>, note_repository: web::Data<dyn NoteRepository>, query: web::Query<NotesQuery>) -> HttpResponse {
+    match auth::api_key(&req, rate_limit_repository.get_ref()) {
+        Some(owner) => notes_page(note_repository.get_ref(), &owner, None, &query),
+        None => HttpResponse::Unauthorized().json(API_KEY_REQUIRED),
+    }
+}
+
+// Replaces the body, pin and battles of the note, the notes of other API keys are not found.
+#[put("/notes/{id}")]
+pub async fn update_note_by_id(
+    req: HttpRequest,
+    rate_limit_repository: web::Data<dyn RateLimitRepository>,
+    battle_repository: web::Data<dyn BattleRepository>,
+    note_repository: web::Data<dyn NoteRepository>,
+    id: web::Path<String>,
+    request: web::Json<NoteRequest>,
+) -> HttpResponse {
+    let Some(owner) = auth::api_key(&req, rate_limit_repository.get_ref()) else {
+        return HttpResponse::Unauthorized().json(API_KEY_REQUIRED);
+    };
+    let Some(existing_note) = note_repository.get_note_by_id(&owner.id, &id) else {
+        return HttpResponse::NotFound().json("Note not found");
+    };
+    let note = match request.into_inner().into_note(&existing_note.monster_id, &owner) {
+        Ok(note) => note,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+    if let Err(message) = check_battles(battle_repository.get_ref(), &note) {
+        return HttpResponse::BadRequest().json(message);
+    }
+
+    match note_repository.update_note_by_id(&owner.id, &id, note) {
+        Ok(Some(note)) => HttpResponse::Ok().json(note),
+        Ok(None) => HttpResponse::NotFound().json("Note not found"),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[delete("/notes/{id}")]
+pub async fn delete_note_by_id(req: HttpRequest, rate_limit_repository: web::Data<dyn RateLimitRepository>, note_repository: web::Data<dyn NoteRepository>, id: web::Path<String>) -> HttpResponse {
+    let Some(owner) = auth::api_key(&req, rate_limit_repository.get_ref()) else {
+        return HttpResponse::Unauthorized().json(API_KEY_REQUIRED);
+    };
+    match note_repository.delete_note_by_id(&owner.id, &id) {
+        Some(_) => HttpResponse::NoContent().finish(),
+        None => HttpResponse::NotFound().json("Note not found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use serde_json::json;
+    use crate::api::config::repositories;
+    use crate::models::battle::{Battle, BattleOutcome};
+    use crate::models::rate_limit::hash_api_key;
+    use crate::rate_limit::API_KEY_HEADER;
+    use crate::repository::database::Database;
+    use crate::utils::test_utils::init_test_monsters;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_keep_notes_private_to_their_api_key() {
+        let db = Arc::new(Database::new().unwrap());
+        let monsters = init_test_monsters(db.as_ref()).await;
+        let (monster, opponent) = (&monsters[0], &monsters[1]);
+        let battle = db.create_battle(Battle {
+            id: String::new(),
+            monster_a: monster.id.clone(),
+            monster_b: opponent.id.clone(),
+            winner: Some(monster.id.clone()),
+            created_at: None,
+            updated_at: None,
+            outcome: BattleOutcome::Win,
+            manual: true,
+            season_id: None,
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        }).unwrap();
+        let other_battle = db.create_battle(Battle { id: String::new(), monster_a: monsters[2].id.clone(), winner: Some(monsters[2].id.clone()), ..battle.clone() }).unwrap();
+        let (coach_key, rival_key) = (format!("bm_coach_{}", uuid::Uuid::new_v4()), format!("bm_rival_{}", uuid::Uuid::new_v4()));
+        for key in [&coach_key, &rival_key] {
+            let api_key = ApiKey { id: String::new(), name: key.clone(), key_hash: hash_api_key(key), tier: "anonymous".to_string(), created_at: chrono::Utc::now().naive_utc() };
+            db.create_api_key(api_key).unwrap();
+        }
+        let app = App::new()
+            .configure(repositories(db.clone()))
+            .service(create_monster_note)
+            .service(get_monster_notes)
+            .service(get_notes)
+            .service(update_note_by_id)
+            .service(delete_note_by_id);
+        let app = test::init_service(app).await;
+        let coach = (API_KEY_HEADER, coach_key.as_str());
+        let notes_uri = format!("/monsters/{}/notes", monster.id);
+
+        let req = test::TestRequest::post().uri(&notes_uri).set_json(json!({ "body": "Opens with a **fast** attack" })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::UNAUTHORIZED);
+        let req = test::TestRequest::post().uri(&notes_uri).insert_header(coach).set_json(json!({ "body": "Lost to a tank", "battles": [other_battle.id] })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::post().uri(&notes_uri).insert_header(coach).set_json(json!({ "body": "Opens with a **fast** attack", "battles": [battle.id] })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let opener: MonsterNote = test::read_body_json(resp).await;
+        let req = test::TestRequest::post().uri(&notes_uri).insert_header(coach).set_json(json!({ "body": "Train defense next", "pinned": true })).to_request();
+        let pinned: MonsterNote = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post().uri(&notes_uri).insert_header(coach).set_json(json!({ "body": "Weak to fast openers" })).to_request();
+        let newest: MonsterNote = test::call_and_read_body_json(&app, req).await;
+
+        // Pinned notes come first, then the newest.
+        let req = test::TestRequest::get().uri(&format!("{}?per_page=2", notes_uri)).insert_header(coach).to_request();
+        let page: NotesPage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((page.total, page.notes.iter().map(|note| note.id.clone()).collect::<Vec<_>>()), (3, vec![pinned.id.clone(), newest.id.clone()]));
+        let req = test::TestRequest::get().uri(&format!("{}?per_page=2&page=2", notes_uri)).insert_header(coach).to_request();
+        let page: NotesPage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((page.notes[0].id.as_str(), page.notes[0].battle_ids.clone()), (opener.id.as_str(), vec![battle.id.clone()]));
+
+        let req = test::TestRequest::get().uri("/notes?q=FAST").insert_header(coach).to_request();
+        let page: NotesPage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(page.notes.iter().map(|note| note.id.clone()).collect::<Vec<_>>(), vec![newest.id.clone(), opener.id.clone()]);
+        let req = test::TestRequest::get().uri("/notes?q=100%25").insert_header(coach).to_request();
+        let page: NotesPage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(page.total, 0);
+
+        // Other API keys neither see nor change the notes.
+        let rival = (API_KEY_HEADER, rival_key.as_str());
+        let req = test::TestRequest::get().uri("/notes").insert_header(rival).to_request();
+        let page: NotesPage = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(page.total, 0);
+        let req = test::TestRequest::put().uri(&format!("/notes/{}", pinned.id)).insert_header(rival).set_json(json!({ "body": "Mine now" })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::put().uri(&format!("/notes/{}", pinned.id)).insert_header(coach).set_json(json!({ "body": "Defense trained" })).to_request();
+        let updated: MonsterNote = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((updated.pinned, updated.updated_at.is_some()), (false, true));
+        let req = test::TestRequest::delete().uri(&format!("/notes/{}", pinned.id)).insert_header(coach).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NO_CONTENT);
+    }
+}
@@ -0,0 +1,170 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{Payload, Service, ServiceRequest, ServiceResponse};
+use actix_web::error::{InternalError, JsonPayloadError, PayloadError};
+use actix_web::http::header;
+use actix_web::{web, Error, HttpMessage, HttpResponse};
+use futures::future::{self, Either};
+use futures::StreamExt;
+use crate::config::AppConfig;
+use super::error::ApiError;
+
+pub const DEFAULT_JSON_BODY_BYTES: usize = 2 * 1024 * 1024;
+pub const DEFAULT_MULTIPART_BYTES: usize = 32 * 1024 * 1024;
+
+/*
+The largest request bodies accepted, MAX_JSON_BODY_BYTES for the JSON bodies and MAX_MULTIPART_BYTES for the
+uploads. Larger ones are answered 413 with PAYLOAD_TOO_LARGE, before being read when they declare their length.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PayloadLimits {
+    pub json_bytes: usize,
+    pub multipart_bytes: usize,
+}
+
+impl Default for PayloadLimits {
+    fn default() -> Self {
+        PayloadLimits { json_bytes: DEFAULT_JSON_BODY_BYTES, multipart_bytes: DEFAULT_MULTIPART_BYTES }
+    }
+}
+
+impl PayloadLimits {
+    pub fn from_config(config: &AppConfig) -> Result<Self, String> {
+        PayloadLimits::from_vars(|name| config.var(name))
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let bytes = |name: &str, default: usize| match var(name) {
+            Some(value) => value.trim().parse().ok().filter(|bytes| *bytes > 0).ok_or_else(|| format!("{} must be a positive number of bytes, got {:?}", name, value)),
+            None => Ok(default),
+        };
+        Ok(PayloadLimits { json_bytes: bytes("MAX_JSON_BODY_BYTES", DEFAULT_JSON_BODY_BYTES)?, multipart_bytes: bytes("MAX_MULTIPART_BYTES", DEFAULT_MULTIPART_BYTES)? })
+    }
+
+    // For `web::Json` bodies, the other deserialization errors keeping their 400.
+    pub fn json_config(&self) -> web::JsonConfig {
+        let limit = self.json_bytes;
+        web::JsonConfig::default().limit(limit).error_handler(move |err, _req| match err {
+            JsonPayloadError::Overflow { .. } | JsonPayloadError::OverflowKnownLength { .. } => InternalError::from_response(err, too_large(limit)).into(),
+            err => err.into(),
+        })
+    }
+}
+
+fn too_large(limit: usize) -> HttpResponse {
+    HttpResponse::PayloadTooLarge().json(ApiError::payload_too_large(limit))
+}
+
+/*
+Caps the multipart bodies at the limit of the PayloadLimits app data. A body declaring a larger Content-Length is
+refused unread, a chunked one is cut off once it goes over, the handler's response being replaced with the 413.
+*/
+pub fn limit_multipart<S, B>(mut req: ServiceRequest, srv: &S) -> impl Future<Output = Result<ServiceResponse<BoxBody>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody + 'static,
+{
+    let limit = req.app_data::<web::Data<PayloadLimits>>().map_or(DEFAULT_MULTIPART_BYTES, |limits| limits.multipart_bytes);
+    let multipart = req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.trim_start().to_ascii_lowercase().starts_with("multipart/"));
+    let length = req.headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if multipart && length.is_some_and(|length| length > limit) {
+        return Either::Left(future::ready(Ok(req.into_response(too_large(limit)))));
+    }
+
+    let exceeded = Arc::new(AtomicBool::new(false));
+    if multipart {
+        let (exceeded, mut received) = (exceeded.clone(), 0);
+        let limited = req.take_payload().map(move |chunk| {
+            let chunk = chunk?;
+            received += chunk.len();
+            if received > limit {
+                exceeded.store(true, Ordering::Relaxed);
+                return Err(PayloadError::Overflow);
+            }
+            Ok(chunk)
+        });
+        req.set_payload(Payload::Stream { payload: Box::pin(limited) });
+    }
+    let response = srv.call(req);
+    Either::Right(async move {
+        let response = response.await?;
+        if exceeded.load(Ordering::Relaxed) {
+            return Ok(response.into_response(too_large(limit)));
+        }
+        Ok(response.map_into_boxed_body())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_multipart::Multipart;
+    use actix_web::{test, http, App};
+    use futures::TryStreamExt;
+    use serde_json::json;
+
+    use super::*;
+
+    async fn upload(mut payload: Multipart) -> Result<HttpResponse, Error> {
+        let mut received = 0;
+        while let Some(mut field) = payload.try_next().await? {
+            while let Some(chunk) = field.try_next().await? {
+                received += chunk.len();
+            }
+        }
+        Ok(HttpResponse::Ok().json(received))
+    }
+
+    #[actix_rt::test]
+    async fn test_should_answer_413_with_the_error_envelope_above_the_limits() {
+        let limits = PayloadLimits::from_vars(|name| match name {
+            "MAX_JSON_BODY_BYTES" => Some("32".to_string()),
+            "MAX_MULTIPART_BYTES" => Some("256".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(limits.json_config())
+                .app_data(web::Data::new(limits))
+                .route("/json", web::post().to(|body: web::Json<serde_json::Value>| async move { HttpResponse::Ok().json(body.into_inner()) }))
+                .route("/upload", web::post().to(upload))
+                .wrap_fn(limit_multipart),
+        )
+        .await;
+
+        let req = test::TestRequest::post().uri("/json").set_json(json!({ "name": "Ember" })).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::OK);
+        let req = test::TestRequest::post().uri("/json").set_json(json!({ "name": "Ember Fox the Magnificent" })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+        let error: ApiError = test::read_body_json(resp).await;
+        assert_eq!(error.code, "PAYLOAD_TOO_LARGE");
+
+        let multipart = |size: usize| {
+            let body = format!("--b\r\nContent-Disposition: form-data; name=\"file\"; filename=\"monsters.csv\"\r\n\r\n{}\r\n--b--\r\n", "x".repeat(size));
+            test::TestRequest::post().uri("/upload").insert_header((header::CONTENT_TYPE, "multipart/form-data; boundary=b")).set_payload(body)
+        };
+        let received: usize = test::call_and_read_body_json(&app, multipart(100).to_request()).await;
+        assert_eq!(received, 100);
+        assert_eq!(test::call_service(&app, multipart(1000).to_request()).await.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+        // Without a Content-Length, the body is cut off while the handler reads it.
+        let mut req = test::TestRequest::post().uri("/upload").insert_header((header::CONTENT_TYPE, "multipart/form-data; boundary=b")).to_request();
+        req.headers_mut().remove(header::CONTENT_LENGTH);
+        let head = "--b\r\nContent-Disposition: form-data; name=\"file\"; filename=\"monsters.csv\"\r\n\r\n";
+        let chunks = futures::stream::iter(std::iter::once(head.as_bytes().to_vec()).chain((0..10).map(|_| vec![b'x'; 100])))
+            .map(|chunk| Ok::<_, PayloadError>(web::Bytes::from(chunk)));
+        let payload: Payload = Payload::Stream { payload: Box::pin(chunks) };
+        let (req, _) = req.replace_payload(payload);
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::PAYLOAD_TOO_LARGE);
+
+        assert!(PayloadLimits::from_vars(|name| (name == "MAX_JSON_BODY_BYTES").then(|| "0".to_string())).is_err());
+    }
+}
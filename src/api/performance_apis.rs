@@ -0,0 +1,9 @@
+use actix_web::{get, HttpResponse};
+use crate::latency::LATENCY;
+use super::config::ROUTES;
+
+// The routes whose p95 latency is over their budget as of the last periodic report.
+#[get("/admin/performance/slow_routes")]
+pub async fn get_slow_routes() -> HttpResponse {
+    HttpResponse::Ok().json(LATENCY.latest_report(&ROUTES))
+}
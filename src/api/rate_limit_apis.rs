@@ -0,0 +1,210 @@
+use actix_web::{web, get, post, put, delete, HttpResponse};
+use serde::{Deserialize, Serialize};
+use crate::models::rate_limit::{hash_api_key, new_api_key, ApiKey, RateLimitTier, ADMIN_TIER, ANONYMOUS_TIER};
+use crate::rate_limit::RateLimiter;
+use crate::repository::error::{Constraint, RepositoryError};
+use crate::repository::rate_limit_repository::RateLimitRepository;
+use super::error::repository_error_response;
+
+#[derive(Serialize, Deserialize)]
+pub struct RateLimitTierRequest {
+    standard_per_minute: Option<i32>,
+    expensive_per_minute: Option<i32>,
+    streaming_per_minute: Option<i32>,
+}
+
+impl RateLimitTierRequest {
+    fn into_tier(self, name: String) -> Result<RateLimitTier, String> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_') {
+            return Err("Tier names are made of lowercase letters, digits, dashes and underscores".to_string());
+        }
+        let budget = |budget: Option<i32>, class: &str| match budget {
+            Some(budget) if budget >= 0 => Ok(budget),
+            Some(_) => Err(format!("The {} budget must not be negative", class)),
+            None => Err(format!("The {} budget is required", class)),
+        };
+
+        Ok(RateLimitTier {
+            standard_per_minute: budget(self.standard_per_minute, "standard")?,
+            expensive_per_minute: budget(self.expensive_per_minute, "expensive")?,
+            streaming_per_minute: budget(self.streaming_per_minute, "streaming")?,
+            name,
+            updated_at: None,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ApiKeyRequest {
+    name: Option<String>,
+    tier: Option<String>,
+}
+
+// The only response carrying the key, only its hash is stored.
+#[derive(Serialize, Deserialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    api_key: ApiKey,
+    key: String,
+}
+
+// The rate limiter caches tiers and keys, changes only apply once it reloaded them.
+fn reload(rate_limiter: Option<web::Data<RateLimiter>>) {
+    if let Some(rate_limiter) = rate_limiter {
+        rate_limiter.reload();
+    }
+}
+
+#[get("/rate-limits/tiers")]
+pub async fn get_rate_limit_tiers(rate_limit_repository: web::Data<dyn RateLimitRepository>) -> HttpResponse {
+    HttpResponse::Ok().json(rate_limit_repository.get_rate_limit_tiers())
+}
+
+// Creates the tier or replaces its budgets.
+#[put("/rate-limits/tiers/{name}")]
+pub async fn save_rate_limit_tier(rate_limit_repository: web::Data<dyn RateLimitRepository>, rate_limiter: Option<web::Data<RateLimiter>>, name: web::Path<String>, request: web::Json<RateLimitTierRequest>) -> HttpResponse {
+    let tier = match request.into_inner().into_tier(name.into_inner()) {
+        Ok(tier) => tier,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+
+    match rate_limit_repository.save_rate_limit_tier(tier) {
+        Ok(tier) => {
+            reload(rate_limiter);
+            HttpResponse::Ok().json(tier)
+        }
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+// The anonymous and admin tiers budget clients without a key, they can be changed but not deleted.
+#[delete("/rate-limits/tiers/{name}")]
+pub async fn delete_rate_limit_tier(rate_limit_repository: web::Data<dyn RateLimitRepository>, rate_limiter: Option<web::Data<RateLimiter>>, name: web::Path<String>) -> HttpResponse {
+    if name.as_str() == ANONYMOUS_TIER || name.as_str() == ADMIN_TIER {
+        return HttpResponse::BadRequest().json(format!("The {} tier can't be deleted", name));
+    }
+
+    match rate_limit_repository.delete_rate_limit_tier(&name) {
+        Ok(Some(_)) => {
+            reload(rate_limiter);
+            HttpResponse::NoContent().finish()
+        }
+        Ok(None) => HttpResponse::NotFound().json("Tier not found"),
+        Err(RepositoryError::Constraint(Constraint::ApiKeyTierExists)) => HttpResponse::Conflict().json("The tier is still given to API keys"),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[get("/api-keys")]
+pub async fn get_api_keys(rate_limit_repository: web::Data<dyn RateLimitRepository>) -> HttpResponse {
+    HttpResponse::Ok().json(rate_limit_repository.get_api_keys())
+}
+
+#[post("/api-keys")]
+pub async fn create_api_key(rate_limit_repository: web::Data<dyn RateLimitRepository>, request: web::Json<ApiKeyRequest>) -> HttpResponse {
+    let request = request.into_inner();
+    let name = match request.name.map(|name| name.trim().to_string()) {
+        Some(name) if !name.is_empty() => name,
+        _ => return HttpResponse::BadRequest().json("Name is required"),
+    };
+    let Some(tier) = request.tier else {
+        return HttpResponse::BadRequest().json("Tier is required");
+    };
+    let key = new_api_key();
+    let api_key = ApiKey { id: String::new(), name, key_hash: hash_api_key(&key), tier, created_at: chrono::Utc::now().naive_utc() };
+    match rate_limit_repository.create_api_key(api_key) {
+        Ok(api_key) => HttpResponse::Created().json(CreatedApiKey { api_key, key }),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[delete("/api-keys/{id}")]
+pub async fn delete_api_key_by_id(rate_limit_repository: web::Data<dyn RateLimitRepository>, rate_limiter: Option<web::Data<RateLimiter>>, id: web::Path<String>) -> HttpResponse {
+    match rate_limit_repository.delete_api_key_by_id(&id) {
+        Some(_) => {
+            reload(rate_limiter);
+            HttpResponse::NoContent().finish()
+        }
+        None => HttpResponse::NotFound().json("API key not found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use serde_json::{json, Value};
+    use crate::api::auth::ADMIN_TOKEN_HEADER;
+    use crate::api::config::{config, repositories};
+    use crate::config::AppConfig;
+    use crate::rate_limit::API_KEY_HEADER;
+    use crate::repository::memory_repository::InMemoryRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_budget_requests_by_the_tier_of_their_api_key() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let rate_limiter = web::Data::new(RateLimiter::new(repository.clone()));
+        let app = App::new()
+            .configure(repositories(repository))
+            .app_data(rate_limiter)
+            .app_data(web::Data::new(AppConfig::env().with_var("ADMIN_TOKEN", "test-admin-token")))
+            .configure(config);
+        let app = test::init_service(app).await;
+        let admin = (ADMIN_TOKEN_HEADER, "test-admin-token");
+
+        let req = test::TestRequest::put().uri("/api/rate-limits/tiers/tester").insert_header(admin).set_json(json!({ "standard_per_minute": 2, "expensive_per_minute": 0, "streaming_per_minute": 0 })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert_eq!(resp.headers().get("x-ratelimit-tier").unwrap(), "admin");
+
+        let req = test::TestRequest::post().uri("/api/api-keys").insert_header(admin).set_json(json!({ "name": "CI", "tier": "unknown" })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+        let req = test::TestRequest::post().uri("/api/api-keys").insert_header(admin).set_json(json!({ "name": "CI", "tier": "tester" })).to_request();
+        let created: Value = test::call_and_read_body_json(&app, req).await;
+        let key = created["key"].as_str().unwrap().to_string();
+        assert!(created.get("key_hash").is_none());
+
+        for remaining in ["1", "0"] {
+            let req = test::TestRequest::get().uri("/api/monsters").insert_header((API_KEY_HEADER, key.as_str())).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::OK);
+            assert_eq!(resp.headers().get("x-ratelimit-limit").unwrap(), "2");
+            assert_eq!(resp.headers().get("x-ratelimit-remaining").unwrap(), remaining);
+        }
+        let req = test::TestRequest::get().uri("/api/monsters").insert_header((API_KEY_HEADER, key.as_str())).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::TOO_MANY_REQUESTS);
+        assert!(resp.headers().contains_key(http::header::RETRY_AFTER));
+        // Each class has its own budget.
+        let req = test::TestRequest::post().uri("/api/battles/bulk").insert_header((API_KEY_HEADER, key.as_str())).set_json(json!({ "battles": [] })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::TOO_MANY_REQUESTS);
+
+        // Anonymous clients get the anonymous budget, unknown keys are rejected.
+        let req = test::TestRequest::get().uri("/api/monsters").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("x-ratelimit-tier").unwrap(), "anonymous");
+        assert_eq!(resp.headers().get("x-ratelimit-limit").unwrap(), "60");
+        let req = test::TestRequest::get().uri("/api/monsters").insert_header((API_KEY_HEADER, "bm_unknown")).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::delete().uri("/api/rate-limits/tiers/tester").insert_header(admin).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let req = test::TestRequest::delete().uri("/api/rate-limits/tiers/anonymous").insert_header(admin).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        // Revoked keys stop working right away.
+        let req = test::TestRequest::delete().uri(&format!("/api/api-keys/{}", created["id"].as_str().unwrap())).insert_header(admin).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NO_CONTENT);
+        let req = test::TestRequest::get().uri("/api/monsters").insert_header((API_KEY_HEADER, key.as_str())).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::UNAUTHORIZED);
+    }
+}
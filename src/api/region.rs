@@ -0,0 +1,59 @@
+use actix_web::middleware::DefaultHeaders;
+use crate::config::AppConfig;
+use crate::models::region::parse_region;
+
+// Response header naming the region of the instance that answered.
+pub const REGION_HEADER: &str = "X-Region";
+
+/*
+The region this instance runs in, read from REGION. Responses advertise it in X-Region, trainers registering
+on the instance play from it unless they pick another, and matchmaking prefers opponents from it for monsters
+without a trainer. Instances without a region leave all of it out.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LocalRegion(Option<String>);
+
+impl LocalRegion {
+    pub fn new(region: Option<String>) -> Self {
+        LocalRegion(region)
+    }
+
+    pub fn from_config(config: &AppConfig) -> Result<Self, String> {
+        LocalRegion::from_vars(|name| config.var(name))
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        Ok(LocalRegion(var("REGION").map(|region| parse_region(&region)).transpose()?))
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+
+    pub fn headers(&self) -> DefaultHeaders {
+        match &self.0 {
+            Some(region) => DefaultHeaders::new().add((REGION_HEADER, region.as_str())),
+            None => DefaultHeaders::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App, HttpResponse};
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_advertise_the_region_of_the_instance() {
+        let var = |region: &'static str| move |name: &str| (name == "REGION").then(|| region.to_string());
+        assert_eq!(LocalRegion::from_vars(|_| None), Ok(LocalRegion(None)));
+        assert_eq!(LocalRegion::from_vars(var(" EU-West ")), Ok(LocalRegion(Some("eu-west".to_string()))));
+        assert!(LocalRegion::from_vars(var("eu west")).is_err());
+
+        let region = LocalRegion::from_vars(var("eu-west")).unwrap();
+        let app = test::init_service(App::new().wrap(region.headers()).route("/", web::get().to(HttpResponse::Ok))).await;
+        let resp = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        assert_eq!(resp.headers().get(REGION_HEADER).and_then(|value| value.to_str().ok()), Some("eu-west"));
+    }
+}
@@ -0,0 +1,117 @@
+use actix_web::{web, get, HttpResponse};
+use serde::{Deserialize, Serialize};
+use crate::models::region::RegionStats;
+use crate::repository::trainer_repository::TrainerRepository;
+use super::region::LocalRegion;
+
+#[derive(Serialize, Deserialize)]
+pub struct Regions {
+    local: Option<String>,
+    regions: Vec<RegionStats>,
+}
+
+// Every region with its trainers, their monsters and its battles, the one of the instance listed even before anyone plays from it.
+#[get("/regions")]
+pub async fn get_regions(trainer_repository: web::Data<dyn TrainerRepository>, local_region: Option<web::Data<LocalRegion>>) -> HttpResponse {
+    let local = local_region.as_ref().and_then(|region| region.name()).map(str::to_string);
+    let mut regions = trainer_repository.get_region_stats();
+    if let Some(local) = local.as_deref().filter(|local| !regions.iter().any(|stats| stats.region == *local)) {
+        regions.push(RegionStats::empty(local));
+        regions.sort_by(|a, b| a.region.cmp(&b.region));
+    }
+    HttpResponse::Ok().json(Regions { local, regions })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use serde_json::{json, Value};
+    use crate::api::config::repositories;
+    use crate::api::monster_apis::matchmake;
+    use crate::api::trainer_apis::create_trainer;
+    use crate::models::battle::{Battle, BattleOutcome};
+    use crate::models::monster::{Monster, Stats};
+    use crate::repository::battle_repository::BattleRepository;
+    use crate::repository::memory_repository::InMemoryRepository;
+    use crate::repository::monster_repository::MonsterRepository;
+
+    use super::*;
+
+    fn create_monster(repository: &dyn MonsterRepository, name: &str, attack: i32, owner_id: &str) -> Monster {
+        repository.create_monster(Monster {
+            id: String::new(),
+            name: name.to_string(),
+            image_url: "https://loremflickr.com/640/480".to_string(),
+            stats: Stats { attack, defense: 20, hp: 50, speed: 80 },
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: Some(owner_id.to_string()),
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        }).unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn test_should_match_and_count_monsters_by_region() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let app = App::new()
+            .configure(repositories(repository.clone()))
+            .app_data(web::Data::new(LocalRegion::new(Some("eu-west".to_string()))))
+            .service(create_trainer)
+            .service(matchmake)
+            .service(get_regions);
+        let app = test::init_service(app).await;
+
+        let register = |body: Value| test::TestRequest::post().uri("/trainers").set_json(body).to_request();
+        let local: Value = test::call_and_read_body_json(&app, register(json!({ "name": "local" }))).await;
+        assert_eq!(local["region"], "eu-west");
+        let remote: Value = test::call_and_read_body_json(&app, register(json!({ "name": "remote", "region": "US-East" }))).await;
+        assert_eq!(remote["region"], "us-east");
+        let resp = test::call_service(&app, register(json!({ "name": "lost", "region": "us east" }))).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+
+        // The remote twin is the closest in stats, yet the local opponent is picked.
+        let (local_id, remote_id) = (local["id"].as_str().unwrap(), remote["id"].as_str().unwrap());
+        let monster = create_monster(repository.as_ref(), "monster", 40, local_id);
+        let twin = create_monster(repository.as_ref(), "twin", 40, remote_id);
+        let neighbour = create_monster(repository.as_ref(), "neighbour", 90, local_id);
+        let req = test::TestRequest::get().uri(&format!("/monsters/{}/matchmake", monster.id)).to_request();
+        let matchmaking: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((matchmaking["opponent"]["id"].as_str(), matchmaking["opponent"]["sameRegion"].as_bool()), (Some(neighbour.id.as_str()), Some(true)));
+
+        let battle = repository.create_battle(Battle {
+            id: String::new(),
+            monster_a: twin.id.clone(),
+            monster_b: monster.id.clone(),
+            winner: Some(twin.id.clone()),
+            created_at: None,
+            updated_at: None,
+            outcome: BattleOutcome::Win,
+            manual: true,
+            season_id: None,
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        }).unwrap();
+        assert_eq!(battle.region.as_deref(), Some("us-east"));
+
+        let req = test::TestRequest::get().uri("/regions").to_request();
+        let regions: Regions = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(regions.local.as_deref(), Some("eu-west"));
+        assert_eq!(regions.regions, vec![
+            RegionStats { region: "eu-west".to_string(), trainers: 1, monsters: 2, battles: 0 },
+            RegionStats { region: "us-east".to_string(), trainers: 1, monsters: 1, battles: 1 },
+        ]);
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method};
+use actix_web::{web, get, Error, HttpResponse};
+use futures::future::{self, LocalBoxFuture};
+use futures::FutureExt;
+use serde_json::{json, Value};
+use crate::latency::{self, LATENCY};
+use crate::rate_limit::{RateLimitError, RateLimiter};
+use super::auth;
+use super::config::{API_PREFIX, ROUTES};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Auth {
+    Public,
+    // Requires the X-Admin-Token header, see `auth::is_admin`.
+    Admin,
+}
+
+/*
+How costly a route is to serve, for rate limiting to budget each class separately.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitClass {
+    Standard,
+    // Simulates or writes many records in a single request.
+    Expensive,
+    // Holds the connection open, as WebSockets, server-sent events and long-polling do.
+    Streaming,
+}
+
+// Cache-Control sent when the handler didn't set one, Unset leaves the response as it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    Unset,
+    NoStore,
+    MaxAge(u32),
+}
+
+impl CachePolicy {
+    fn header_value(&self) -> Option<String> {
+        match self {
+            CachePolicy::Unset => None,
+            CachePolicy::NoStore => Some("no-store".to_string()),
+            CachePolicy::MaxAge(seconds) => Some(format!("max-age={}", seconds)),
+        }
+    }
+}
+
+// Latency budgets of the routes not given one, by rate limit class. Streaming routes hold the connection open and have none.
+const STANDARD_LATENCY_BUDGET_MS: u64 = 250;
+const EXPENSIVE_LATENCY_BUDGET_MS: u64 = 5000;
+
+/*
+A handler with its metadata. `path` must be the pattern of the handler's macro, relative to the /api scope,
+so `enforce_route_metadata` finds the route a request was matched to.
+*/
+pub struct Route {
+    pub method: Method,
+    pub path: &'static str,
+    pub auth: Auth,
+    pub rate_limit: RateLimitClass,
+    pub cache: CachePolicy,
+    pub tags: &'static [&'static str],
+    // The p95 latency the route should stay under, see `latency_budget`.
+    pub budget_ms: Option<u64>,
+    register: fn(&mut web::ServiceConfig),
+}
+
+impl Route {
+    pub fn new(method: Method, path: &'static str, register: fn(&mut web::ServiceConfig)) -> Self {
+        Route { method, path, auth: Auth::Public, rate_limit: RateLimitClass::Standard, cache: CachePolicy::Unset, tags: &[], budget_ms: None, register }
+    }
+
+    // Admin routes are never cached.
+    pub fn admin(self) -> Self {
+        Route { auth: Auth::Admin, cache: CachePolicy::NoStore, ..self }
+    }
+
+    pub fn rate_limit(self, rate_limit: RateLimitClass) -> Self {
+        Route { rate_limit, ..self }
+    }
+
+    pub fn cache(self, cache: CachePolicy) -> Self {
+        Route { cache, ..self }
+    }
+
+    pub fn tags(self, tags: &'static [&'static str]) -> Self {
+        Route { tags, ..self }
+    }
+
+    pub fn budget(self, budget_ms: u64) -> Self {
+        Route { budget_ms: Some(budget_ms), ..self }
+    }
+
+    // The budget given to the route, or the one of its rate limit class.
+    pub fn latency_budget(&self) -> Option<Duration> {
+        let budget_ms = match (self.budget_ms, self.rate_limit) {
+            (Some(budget_ms), _) => budget_ms,
+            (None, RateLimitClass::Standard) => STANDARD_LATENCY_BUDGET_MS,
+            (None, RateLimitClass::Expensive) => EXPENSIVE_LATENCY_BUDGET_MS,
+            (None, RateLimitClass::Streaming) => return None,
+        };
+        Some(Duration::from_millis(budget_ms))
+    }
+
+    pub fn register(&self, cfg: &mut web::ServiceConfig) {
+        (self.register)(cfg);
+    }
+}
+
+/*
+Applies the metadata of the route matching the request: admin routes answer 403 without the admin token,
+the request is counted against its rate limit class when a `RateLimiter` is registered, answering 429 once
+the budget is spent, and the cache policy fills in Cache-Control. Requests to routes with a latency budget are
+timed along with their queries for the slow routes report. Requests matching no route pass through untouched.
+*/
+pub fn enforce_route_metadata<S, B>(routes: &'static [Route], prefix: &'static str) -> impl Fn(ServiceRequest, &S) -> LocalBoxFuture<'static, Result<ServiceResponse<BoxBody>, Error>> + Clone
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    move |req, srv| {
+        let route = req
+            .match_pattern()
+            .and_then(|pattern| pattern.strip_prefix(prefix).map(str::to_string))
+            .and_then(|path| routes.iter().find(|route| route.method == req.method() && route.path == path));
+        let Some(route) = route else {
+            return srv.call(req).map(|response| response.map(ServiceResponse::map_into_boxed_body)).boxed_local();
+        };
+        if route.auth == Auth::Admin && !auth::is_admin(req.request()) {
+            let response = req.into_response(HttpResponse::Forbidden().json("Admin token required"));
+            return future::ok(response).boxed_local();
+        }
+        let rate_limit = match req.app_data::<web::Data<RateLimiter>>().map(|rate_limiter| rate_limiter.check(req.request(), route.rate_limit)) {
+            Some(Ok(status)) => status,
+            Some(Err(RateLimitError::InvalidApiKey)) => {
+                let response = req.into_response(HttpResponse::Unauthorized().json("Invalid API key"));
+                return future::ok(response).boxed_local();
+            }
+            Some(Err(RateLimitError::Exceeded(status))) => {
+                let mut response = HttpResponse::TooManyRequests();
+                response.insert_header((header::RETRY_AFTER, status.reset.as_secs().max(1)));
+                let mut response = response.json("Rate limit exceeded");
+                status.insert_headers(response.headers_mut());
+                return future::ok(req.into_response(response)).boxed_local();
+            }
+            None => None,
+        };
+
+        let cache_control = route.cache.header_value();
+        let (method, path, budget) = (route.method.clone(), route.path, route.latency_budget());
+        let start = Instant::now();
+        latency::profile(srv.call(req)).map(move |(response, queries)| {
+            if let Some(budget) = budget {
+                LATENCY.record(method.as_str(), path, budget, start.elapsed(), queries);
+            }
+            let mut response = response?.map_into_boxed_body();
+            if let Some(status) = rate_limit {
+                status.insert_headers(response.headers_mut());
+            }
+            if let Some(cache_control) = cache_control {
+                if !response.headers().contains_key(header::CACHE_CONTROL) {
+                    response.headers_mut().insert(header::CACHE_CONTROL, header::HeaderValue::from_str(&cache_control).expect("Cache policies are valid headers"));
+                }
+            }
+            Ok(response)
+        })
+        .boxed_local()
+    }
+}
+
+/*
+A minimal OpenAPI document of the routes: operations with their tags, path parameters and the admin token
+requirement, plus the rate limit class as `x-rate-limit-class` and the latency budget as `x-latency-budget-ms`.
+*/
+pub fn openapi(routes: &[Route], prefix: &str) -> Value {
+    let mut paths: BTreeMap<String, serde_json::Map<String, Value>> = BTreeMap::new();
+    for route in routes {
+        let parameters: Vec<Value> = route
+            .path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+            .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } }))
+            .collect();
+        let mut operation = json!({
+            "tags": route.tags,
+            "parameters": parameters,
+            "responses": { "default": { "description": "See the handler" } },
+            "x-rate-limit-class": format!("{:?}", route.rate_limit).to_lowercase(),
+        });
+        if let Some(budget) = route.latency_budget() {
+            operation["x-latency-budget-ms"] = json!(budget.as_millis() as u64);
+        }
+        if route.auth == Auth::Admin {
+            operation["security"] = json!([{ "adminToken": [] }]);
+        }
+        paths
+            .entry(format!("{}{}", prefix, route.path))
+            .or_default()
+            .insert(route.method.as_str().to_lowercase(), operation);
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": { "title": "Battle of monsters", "version": env!("CARGO_PKG_VERSION") },
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                "adminToken": { "type": "apiKey", "in": "header", "name": auth::ADMIN_TOKEN_HEADER },
+            },
+        },
+    })
+}
+
+#[get("/openapi.json")]
+pub async fn get_openapi() -> HttpResponse {
+    HttpResponse::Ok().json(openapi(&ROUTES, API_PREFIX))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use crate::api::config::{config, repositories};
+    use crate::config::AppConfig;
+    use crate::repository::memory_repository::InMemoryRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_match_every_route_of_the_registry_to_its_handler_pattern() {
+        // Answers with the pattern a request was matched to, without calling the handler.
+        let app = App::new()
+            .wrap_fn(|req, _| {
+                let pattern = req.match_pattern().unwrap_or_default();
+                future::ok(req.into_response(HttpResponse::Ok().body(pattern)))
+            })
+            .configure(config);
+        let app = test::init_service(app).await;
+
+        for route in ROUTES.iter() {
+            let path: Vec<&str> = route.path.split('/').map(|segment| if segment.starts_with('{') { "x" } else { segment }).collect();
+            let req = test::TestRequest::default().method(route.method.clone()).uri(&format!("{}{}", API_PREFIX, path.join("/"))).to_request();
+            let pattern = test::call_and_read_body(&app, req).await;
+            assert_eq!(pattern, format!("{}{}", API_PREFIX, route.path), "{} {}", route.method, route.path);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_apply_the_auth_and_cache_metadata_of_the_routes() {
+        let app = App::new()
+            .app_data(web::Data::new(AppConfig::env().with_var("ADMIN_TOKEN", "test-admin-token")))
+            .configure(repositories(Arc::new(InMemoryRepository::new())))
+            .configure(config);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/api/webhooks").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::FORBIDDEN);
+
+        let req = test::TestRequest::get().uri("/api/webhooks").insert_header((auth::ADMIN_TOKEN_HEADER, "test-admin-token")).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert_eq!(resp.headers().get(header::CACHE_CONTROL).unwrap(), "no-store");
+
+        let req = test::TestRequest::get().uri("/api/openapi.json").to_request();
+        let document: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(document["paths"]["/api/webhooks/{id}"]["put"]["security"], json!([{ "adminToken": [] }]));
+        assert_eq!(document["paths"]["/api/battles/{id}"]["get"]["parameters"][0]["name"], "id");
+        assert_eq!(document["paths"]["/api/battles/bulk"]["post"]["x-rate-limit-class"], "expensive");
+        assert_eq!(document["paths"]["/api/battles/bulk"]["post"]["x-latency-budget-ms"], 5000);
+        assert!(document["paths"]["/api/battles/ws"]["get"].get("x-latency-budget-ms").is_none());
+    }
+}
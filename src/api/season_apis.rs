@@ -0,0 +1,166 @@
+use actix_web::{web, get, post, HttpResponse};
+use serde::{Deserialize, Serialize};
+use crate::models::season::Season;
+use crate::repository::season_repository::SeasonRepository;
+use super::error::repository_error_response;
+
+#[derive(Serialize, Deserialize)]
+pub struct SeasonRequest {
+    name: Option<String>,
+}
+
+// The season opened, with the one it closed.
+#[derive(Serialize, Deserialize)]
+pub struct OpenedSeason {
+    #[serde(flatten)]
+    season: Season,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    closed: Option<Season>,
+}
+
+#[get("/seasons")]
+pub async fn get_seasons(season_repository: web::Data<dyn SeasonRepository>) -> HttpResponse {
+    HttpResponse::Ok().json(season_repository.get_seasons())
+}
+
+// Opens a season, closing the one open so far.
+#[post("/seasons")]
+pub async fn open_season(season_repository: web::Data<dyn SeasonRepository>, request: web::Json<SeasonRequest>) -> HttpResponse {
+    let name = match request.into_inner().name.map(|name| name.trim().to_string()) {
+        Some(name) if !name.is_empty() => name,
+        _ => return HttpResponse::BadRequest().json("Name is required"),
+    };
+
+    match season_repository.open_season(Season::new(name)) {
+        Ok((season, closed)) => HttpResponse::Created().json(OpenedSeason { season, closed }),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[get("/seasons/current")]
+pub async fn get_current_season(season_repository: web::Data<dyn SeasonRepository>) -> HttpResponse {
+    match season_repository.get_open_season() {
+        Some(season) => HttpResponse::Ok().json(season),
+        None => HttpResponse::NotFound().json("No season is open"),
+    }
+}
+
+#[get("/seasons/{id}")]
+pub async fn get_season_by_id(season_repository: web::Data<dyn SeasonRepository>, id: web::Path<String>) -> HttpResponse {
+    match season_repository.get_season_by_id(&id) {
+        Some(season) => HttpResponse::Ok().json(season),
+        None => HttpResponse::NotFound().json("Season not found"),
+    }
+}
+
+#[post("/seasons/{id}/close")]
+pub async fn close_season(season_repository: web::Data<dyn SeasonRepository>, id: web::Path<String>) -> HttpResponse {
+    match season_repository.get_season_by_id(&id) {
+        Some(season) if season.ended_at.is_some() => return HttpResponse::Conflict().json("The season is already closed"),
+        Some(_) => {}
+        None => return HttpResponse::NotFound().json("Season not found"),
+    }
+
+    match season_repository.close_season(&id) {
+        Ok(Some(season)) => HttpResponse::Ok().json(season),
+        Ok(None) => HttpResponse::NotFound().json("Season not found"),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+// The leaderboard of the season, final once it closed.
+#[get("/seasons/{id}/standings")]
+pub async fn get_season_standings(season_repository: web::Data<dyn SeasonRepository>, id: web::Path<String>) -> HttpResponse {
+    if season_repository.get_season_by_id(&id).is_none() {
+        return HttpResponse::NotFound().json("Season not found");
+    }
+    HttpResponse::Ok().json(season_repository.get_season_standings(&id))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use serde_json::{json, Value};
+    use crate::api::config::repositories;
+    use crate::models::battle::{Battle, BattleOutcome};
+    use crate::models::monster::{Monster, Stats};
+    use crate::repository::battle_repository::BattleRepository;
+    use crate::repository::memory_repository::InMemoryRepository;
+    use crate::repository::monster_repository::MonsterRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_archive_the_standings_of_closed_seasons() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let new_monster = |name: &str| Monster { id: String::new(), name: name.to_string(), image_url: String::new(), stats: Stats { attack: 10, defense: 10, hp: 10, speed: 10 }, created_at: None, updated_at: None, last_battle_at: None, level: 1, xp: 0, owner_id: None, image_broken: false, external_id: None, version: 1 };
+        let monster_a = repository.create_monster(new_monster("monster-a")).unwrap();
+        let monster_b = repository.create_monster(new_monster("monster-b")).unwrap();
+        let battle = |winner: &Monster| Battle {
+            id: String::new(),
+            monster_a: monster_a.id.clone(),
+            monster_b: monster_b.id.clone(),
+            winner: Some(winner.id.clone()),
+            created_at: None,
+            updated_at: None,
+            outcome: BattleOutcome::Win,
+            manual: false,
+            season_id: None,
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        };
+        assert_eq!(repository.create_battle(battle(&monster_b)).unwrap().season_id, None);
+        let app = App::new()
+            .configure(repositories(repository.clone()))
+            .service(get_current_season)
+            .service(open_season)
+            .service(close_season)
+            .service(get_season_standings);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/seasons/current").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+        let req = test::TestRequest::post().uri("/seasons").set_json(json!({ "name": "Season 1" })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let first: Value = test::read_body_json(resp).await;
+        let first_id = first["id"].as_str().unwrap().to_string();
+
+        assert_eq!(repository.create_battle(battle(&monster_a)).unwrap().season_id, Some(first_id.clone()));
+        let req = test::TestRequest::get().uri(&format!("/seasons/{}/standings", first_id)).to_request();
+        let standings: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(standings, json!([
+            { "monster": monster_a.id, "rank": 1, "points": 3, "wins": 1, "losses": 0, "draws": 0 },
+            { "monster": monster_b.id, "rank": 2, "points": 0, "wins": 0, "losses": 1, "draws": 0 },
+        ]));
+
+        // Opening the next season closes this one, its standings start over.
+        let req = test::TestRequest::post().uri("/seasons").set_json(json!({ "name": "Season 2" })).to_request();
+        let second: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(second["closed"]["id"], first_id);
+        assert!(second["closed"]["endedAt"].is_string());
+        repository.create_battle(battle(&monster_b)).unwrap();
+        let req = test::TestRequest::get().uri(&format!("/seasons/{}/standings", first_id)).to_request();
+        let archived: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(archived, standings);
+        let req = test::TestRequest::get().uri(&format!("/seasons/{}/standings", second["id"].as_str().unwrap())).to_request();
+        let current: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(current[0]["monster"], monster_b.id);
+
+        let req = test::TestRequest::post().uri(&format!("/seasons/{}/close", first_id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CONFLICT);
+        let req = test::TestRequest::post().uri("/seasons").set_json(json!({ "name": " " })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+    }
+}
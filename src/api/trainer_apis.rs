@@ -0,0 +1,305 @@
+use actix_web::{web, get, post, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use crate::models::rate_limit::{hash_api_key, new_api_key, ApiKey};
+use crate::models::region::parse_region;
+use crate::models::trainer::{Trainer, MAX_TRAINER_NAME_LENGTH, TRAINER_TIER};
+use crate::repository::rate_limit_repository::RateLimitRepository;
+use crate::repository::trainer_repository::{OwnershipError, TrainerRepository};
+use super::auth;
+use super::error::repository_error_response;
+use super::region::LocalRegion;
+
+const TRAINER_REQUIRED: &str = "Only trainers can own monsters, register one with POST /trainers";
+
+#[derive(Serialize, Deserialize)]
+pub struct TrainerRequest {
+    name: Option<String>,
+    region: Option<String>,
+}
+
+// The key is only returned at registration, it is the one the trainer authenticates with from then on.
+#[derive(Serialize, Deserialize)]
+pub struct RegisteredTrainer {
+    #[serde(flatten)]
+    trainer: Trainer,
+    key: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TransferRequest {
+    to: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TradeRequest {
+    offered: Option<String>,
+    requested: Option<String>,
+}
+
+fn ownership_error_response(err: OwnershipError, not_found: &str) -> HttpResponse {
+    match err {
+        OwnershipError::NotFound => HttpResponse::NotFound().json(not_found),
+        OwnershipError::Forbidden => HttpResponse::Forbidden().json("The monster or the trade belongs to another trainer"),
+        OwnershipError::Conflict(message) => HttpResponse::Conflict().json(message),
+        OwnershipError::Database(err) => repository_error_response(&err),
+    }
+}
+
+// Registering issues the API key of the trainer, so it needs none. Trainers play from the region of the instance unless they pick one.
+#[post("/trainers")]
+pub async fn create_trainer(
+    trainer_repository: web::Data<dyn TrainerRepository>,
+    local_region: Option<web::Data<LocalRegion>>,
+    request: web::Json<TrainerRequest>,
+) -> HttpResponse {
+    let request = request.into_inner();
+    let region = match request.region.as_deref().map(parse_region).transpose() {
+        Ok(region) => region.or_else(|| local_region.as_ref().and_then(|region| region.name()).map(str::to_string)),
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+    let name = match request.name.map(|name| name.trim().to_string()) {
+        Some(name) if !name.is_empty() => name,
+        _ => return HttpResponse::BadRequest().json("Name is required"),
+    };
+    if name.chars().count() > MAX_TRAINER_NAME_LENGTH {
+        return HttpResponse::BadRequest().json(format!("Names are at most {} characters long", MAX_TRAINER_NAME_LENGTH));
+    }
+    let key = new_api_key();
+    let created_at = chrono::Utc::now().naive_utc();
+    let api_key = ApiKey { id: String::new(), name: format!("trainer:{}", name), key_hash: hash_api_key(&key), tier: TRAINER_TIER.to_string(), created_at };
+    let trainer = Trainer { id: String::new(), name, api_key_id: String::new(), created_at, region };
+    match trainer_repository.create_trainer(trainer, api_key) {
+        Ok((trainer, _)) => HttpResponse::Created().json(RegisteredTrainer { trainer, key }),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[get("/trainers/me")]
+pub async fn get_current_trainer(req: HttpRequest, rate_limit_repository: web::Data<dyn RateLimitRepository>, trainer_repository: web::Data<dyn TrainerRepository>) -> HttpResponse {
+    match auth::trainer(&req, rate_limit_repository.get_ref(), trainer_repository.get_ref()) {
+        Some(trainer) => HttpResponse::Ok().json(trainer),
+        None => HttpResponse::Unauthorized().json(TRAINER_REQUIRED),
+    }
+}
+
+#[get("/trainers/{id}")]
+pub async fn get_trainer_by_id(trainer_repository: web::Data<dyn TrainerRepository>, id: web::Path<String>) -> HttpResponse {
+    match trainer_repository.get_trainer_by_id(&id) {
+        Some(trainer) => HttpResponse::Ok().json(trainer),
+        None => HttpResponse::NotFound().json("Trainer not found"),
+    }
+}
+
+#[get("/trainers/{id}/monsters")]
+pub async fn get_trainer_monsters(trainer_repository: web::Data<dyn TrainerRepository>, id: web::Path<String>) -> HttpResponse {
+    match trainer_repository.get_trainer_by_id(&id) {
+        Some(trainer) => HttpResponse::Ok().json(trainer_repository.get_trainer_monsters(&trainer.id)),
+        None => HttpResponse::NotFound().json("Trainer not found"),
+    }
+}
+
+// Gives the monster away to another trainer, no monster being asked in return.
+#[post("/monsters/{id}/transfer")]
+pub async fn transfer_monster(req: HttpRequest, rate_limit_repository: web::Data<dyn RateLimitRepository>, trainer_repository: web::Data<dyn TrainerRepository>, id: web::Path<String>, request: web::Json<TransferRequest>) -> HttpResponse {
+    let Some(trainer) = auth::trainer(&req, rate_limit_repository.get_ref(), trainer_repository.get_ref()) else {
+        return HttpResponse::Unauthorized().json(TRAINER_REQUIRED);
+    };
+    let Some(to) = request.into_inner().to else {
+        return HttpResponse::BadRequest().json("The trainer to transfer the monster to is required");
+    };
+    match trainer_repository.transfer_monster(&id, &trainer.id, &to) {
+        Ok(monster) => HttpResponse::Ok().json(monster),
+        Err(err) => ownership_error_response(err, "Monster not found"),
+    }
+}
+
+#[get("/trades")]
+pub async fn get_trades(req: HttpRequest, rate_limit_repository: web::Data<dyn RateLimitRepository>, trainer_repository: web::Data<dyn TrainerRepository>) -> HttpResponse {
+    match auth::trainer(&req, rate_limit_repository.get_ref(), trainer_repository.get_ref()) {
+        Some(trainer) => HttpResponse::Ok().json(trainer_repository.get_trades(&trainer.id)),
+        None => HttpResponse::Unauthorized().json(TRAINER_REQUIRED),
+    }
+}
+
+// Proposes one of the monsters of the trainer for a monster of another trainer.
+#[post("/trades")]
+pub async fn create_trade(req: HttpRequest, rate_limit_repository: web::Data<dyn RateLimitRepository>, trainer_repository: web::Data<dyn TrainerRepository>, request: web::Json<TradeRequest>) -> HttpResponse {
+    let Some(trainer) = auth::trainer(&req, rate_limit_repository.get_ref(), trainer_repository.get_ref()) else {
+        return HttpResponse::Unauthorized().json(TRAINER_REQUIRED);
+    };
+    let TradeRequest { offered: Some(offered), requested: Some(requested) } = request.into_inner() else {
+        return HttpResponse::BadRequest().json("The offered and requested monsters are required");
+    };
+    match trainer_repository.create_trade(&trainer.id, &offered, &requested) {
+        Ok(trade) => HttpResponse::Created().json(trade),
+        Err(err) => ownership_error_response(err, "Monster not found"),
+    }
+}
+
+fn answer_trade(req: &HttpRequest, rate_limit_repository: &dyn RateLimitRepository, trainer_repository: &dyn TrainerRepository, trade_id: &str, accept: bool) -> HttpResponse {
+    let Some(trainer) = auth::trainer(req, rate_limit_repository, trainer_repository) else {
+        return HttpResponse::Unauthorized().json(TRAINER_REQUIRED);
+    };
+    match trainer_repository.answer_trade(trade_id, &trainer.id, accept) {
+        Ok(trade) => HttpResponse::Ok().json(trade),
+        Err(err) => ownership_error_response(err, "Trade not found"),
+    }
+}
+
+#[post("/trades/{id}/accept")]
+pub async fn accept_trade(req: HttpRequest, rate_limit_repository: web::Data<dyn RateLimitRepository>, trainer_repository: web::Data<dyn TrainerRepository>, id: web::Path<String>) -> HttpResponse {
+    answer_trade(&req, rate_limit_repository.get_ref(), trainer_repository.get_ref(), &id, true)
+}
+
+// Declined by the recipient, cancelled when the proposer declines it.
+#[post("/trades/{id}/decline")]
+pub async fn decline_trade(req: HttpRequest, rate_limit_repository: web::Data<dyn RateLimitRepository>, trainer_repository: web::Data<dyn TrainerRepository>, id: web::Path<String>) -> HttpResponse {
+    answer_trade(&req, rate_limit_repository.get_ref(), trainer_repository.get_ref(), &id, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use serde_json::json;
+    use crate::api::config::repositories;
+    use crate::api::monster_apis::{bulk_delete_monsters, create_monster, delete_monster_by_id, get_monsters, update_monster_by_id};
+    use crate::models::battle::{Battle, BattleOutcome};
+    use crate::models::monster::Monster;
+    use crate::models::trainer::{Trade, TradeStatus};
+    use crate::rate_limit::API_KEY_HEADER;
+    use crate::repository::battle_repository::BattleRepository;
+    use crate::repository::database::Database;
+    use crate::repository::memory_repository::InMemoryRepository;
+    use crate::repository::monster_repository::MonsterRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_trade_the_monsters_of_the_trainers() {
+        let db = Arc::new(Database::new().unwrap());
+        let app = App::new()
+            .configure(repositories(db.clone()))
+            .service(create_trainer)
+            .service(get_current_trainer)
+            .service(create_monster)
+            .service(get_monsters)
+            .service(transfer_monster)
+            .service(create_trade)
+            .service(accept_trade)
+            .service(decline_trade);
+        let app = test::init_service(app).await;
+
+        let mut trainers = Vec::new();
+        for name in ["ash", "gary"] {
+            let req = test::TestRequest::post().uri("/trainers").set_json(json!({ "name": format!("{}-{}", name, uuid::Uuid::new_v4()) })).to_request();
+            let registered: RegisteredTrainer = test::call_and_read_body_json(&app, req).await;
+            let req = test::TestRequest::post()
+                .uri("/monsters")
+                .insert_header((API_KEY_HEADER, registered.key.as_str()))
+                .set_json(json!({ "name": name, "image_url": "https://loremflickr.com/640/480", "attack": 40, "defense": 20, "hp": 50, "speed": 80, "ownerId": "someone" }))
+                .to_request();
+            let monster: Monster = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(monster.owner_id.as_ref(), Some(&registered.trainer.id));
+            trainers.push((registered, monster));
+        }
+        let [(ash, pikachu), (gary, eevee)] = [trainers.remove(0), trainers.remove(0)];
+        let req = test::TestRequest::post().uri("/trainers").set_json(json!({ "name": ash.trainer.name })).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+
+        let req = test::TestRequest::get().uri("/trainers/me").insert_header((API_KEY_HEADER, ash.key.as_str())).to_request();
+        let me: Trainer = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(me.id, ash.trainer.id);
+        let req = test::TestRequest::get().uri("/monsters").insert_header((API_KEY_HEADER, ash.key.as_str())).to_request();
+        let monsters: Vec<Monster> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(monsters.into_iter().map(|monster| monster.id).collect::<Vec<_>>(), vec![pikachu.id.clone()]);
+
+        let req = test::TestRequest::post().uri(&format!("/monsters/{}/transfer", eevee.id)).insert_header((API_KEY_HEADER, ash.key.as_str())).set_json(json!({ "to": ash.trainer.id })).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::FORBIDDEN);
+
+        let req = test::TestRequest::post().uri("/trades").insert_header((API_KEY_HEADER, ash.key.as_str())).set_json(json!({ "offered": pikachu.id, "requested": eevee.id })).to_request();
+        let trade: Trade = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((trade.recipient.as_str(), trade.status), (gary.trainer.id.as_str(), TradeStatus::Pending));
+        let req = test::TestRequest::post().uri(&format!("/trades/{}/accept", trade.id)).insert_header((API_KEY_HEADER, ash.key.as_str())).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::FORBIDDEN);
+        let req = test::TestRequest::post().uri(&format!("/trades/{}/accept", trade.id)).insert_header((API_KEY_HEADER, gary.key.as_str())).to_request();
+        let trade: Trade = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(trade.status, TradeStatus::Accepted);
+        let req = test::TestRequest::post().uri(&format!("/trades/{}/decline", trade.id)).insert_header((API_KEY_HEADER, gary.key.as_str())).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::CONFLICT);
+
+        let req = test::TestRequest::post().uri(&format!("/monsters/{}/transfer", pikachu.id)).insert_header((API_KEY_HEADER, gary.key.as_str())).set_json(json!({ "to": ash.trainer.id })).to_request();
+        let pikachu: Monster = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(pikachu.owner_id.as_ref(), Some(&ash.trainer.id));
+
+        let battle = db.create_battle(Battle {
+            id: String::new(),
+            monster_a: pikachu.id.clone(),
+            monster_b: eevee.id.clone(),
+            winner: Some(pikachu.id.clone()),
+            created_at: None,
+            updated_at: None,
+            outcome: BattleOutcome::Win,
+            manual: true,
+            season_id: None,
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        }).unwrap();
+        assert_eq!((battle.trainer_a, battle.trainer_b), (Some(ash.trainer.id.clone()), Some(ash.trainer.id)));
+    }
+
+    #[actix_rt::test]
+    async fn test_should_only_let_trainers_change_their_own_monsters() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let app = App::new()
+            .configure(repositories(repository.clone()))
+            .service(create_trainer)
+            .service(create_monster)
+            .service(bulk_delete_monsters)
+            .service(update_monster_by_id)
+            .service(delete_monster_by_id);
+        let app = test::init_service(app).await;
+        let mut keys = Vec::new();
+        for name in ["ash", "gary"] {
+            let req = test::TestRequest::post().uri("/trainers").set_json(json!({ "name": name })).to_request();
+            let registered: RegisteredTrainer = test::call_and_read_body_json(&app, req).await;
+            keys.push(registered.key);
+        }
+        let create = |key: Option<&str>, name: &str| {
+            let req = test::TestRequest::post().uri("/monsters").set_json(json!({ "name": name, "image_url": "https://loremflickr.com/640/480", "attack": 40, "defense": 20, "hp": 50, "speed": 80 }));
+            match key {
+                Some(key) => req.insert_header((API_KEY_HEADER, key.to_string())).to_request(),
+                None => req.to_request(),
+            }
+        };
+        let pikachu: Monster = test::call_and_read_body_json(&app, create(Some(&keys[0]), "pikachu")).await;
+        let wild: Monster = test::call_and_read_body_json(&app, create(None, "wild")).await;
+
+        let update = |key: Option<&str>| {
+            let req = test::TestRequest::put().uri(&format!("/monsters/{}", pikachu.id)).set_json(Monster { name: "renamed".to_string(), ..pikachu.clone() });
+            match key {
+                Some(key) => req.insert_header((API_KEY_HEADER, key.to_string())).to_request(),
+                None => req.to_request(),
+            }
+        };
+        assert_eq!(test::call_service(&app, update(None)).await.status(), http::StatusCode::FORBIDDEN);
+        assert_eq!(test::call_service(&app, update(Some(&keys[1]))).await.status(), http::StatusCode::FORBIDDEN);
+        let req = test::TestRequest::delete().uri(&format!("/monsters/{}", pikachu.id)).insert_header((API_KEY_HEADER, keys[1].as_str())).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::FORBIDDEN);
+        let req = test::TestRequest::delete().uri("/monsters/bulk").set_json(json!({ "ids": [pikachu.id, wild.id] })).to_request();
+        let report: Vec<serde_json::Value> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(report.iter().map(|result| result["status"].as_str().unwrap()).collect::<Vec<_>>(), ["forbidden", "deleted"]);
+        assert_eq!(repository.get_monster_by_id(&pikachu.id).unwrap().name, "pikachu");
+
+        assert_eq!(test::call_service(&app, update(Some(&keys[0]))).await.status(), http::StatusCode::OK);
+        let req = test::TestRequest::delete().uri(&format!("/monsters/{}", pikachu.id)).insert_header((API_KEY_HEADER, keys[0].as_str())).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), http::StatusCode::NO_CONTENT);
+    }
+}
@@ -0,0 +1,146 @@
+use actix_web::{web, get, post, put, HttpResponse};
+use serde::{Deserialize, Serialize};
+use crate::balance;
+use crate::models::monster::Stats;
+use crate::models::training::{with_hidden_stats, MonsterTraining};
+use crate::repository::monster_repository::MonsterRepository;
+use crate::repository::training_repository::{TrainingError, TrainingRepository};
+use super::error::repository_error_response;
+
+// Points for each stat, the ones left out taking none.
+#[derive(Serialize, Deserialize)]
+pub struct StatPointsRequest {
+    attack: Option<i32>,
+    defense: Option<i32>,
+    hp: Option<i32>,
+    speed: Option<i32>,
+}
+
+impl StatPointsRequest {
+    fn to_stats(&self) -> Stats {
+        Stats {
+            attack: self.attack.unwrap_or(0),
+            defense: self.defense.unwrap_or(0),
+            hp: self.hp.unwrap_or(0),
+            speed: self.speed.unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TrainingSheet {
+    #[serde(flatten)]
+    training: MonsterTraining,
+    hidden_stats: Stats,
+    // The stats the monster fights with, before its items and the stat decay.
+    effective_stats: Stats,
+}
+
+fn training_response(result: Result<MonsterTraining, TrainingError>, stats: Stats) -> HttpResponse {
+    match result {
+        Ok(training) => HttpResponse::Ok().json(training_sheet(training, stats)),
+        Err(TrainingError::NotFound) => HttpResponse::NotFound().json("Monster not found"),
+        Err(TrainingError::Invalid(message)) => HttpResponse::BadRequest().json(message),
+        Err(TrainingError::Database(err)) => repository_error_response(&err),
+    }
+}
+
+fn training_sheet(training: MonsterTraining, stats: Stats) -> TrainingSheet {
+    let hidden_stats = training.hidden_stats(&balance::current());
+    TrainingSheet { effective_stats: with_hidden_stats(&stats, &hidden_stats), hidden_stats, training }
+}
+
+#[get("/monsters/{id}/training")]
+pub async fn get_monster_training(monster_repository: web::Data<dyn MonsterRepository>, training_repository: web::Data<dyn TrainingRepository>, id: web::Path<String>) -> HttpResponse {
+    let Some(monster) = monster_repository.get_monster_by_id(&id) else {
+        return HttpResponse::NotFound().json("Monster not found");
+    };
+    let training = training_repository.get_training(&id).unwrap_or_else(|| MonsterTraining::new(&id));
+    HttpResponse::Ok().json(training_sheet(training, monster.stats))
+}
+
+// Spends training points the monster earned winning battles on its stats.
+#[post("/monsters/{id}/training/allocate")]
+pub async fn allocate_training_points(monster_repository: web::Data<dyn MonsterRepository>, training_repository: web::Data<dyn TrainingRepository>, id: web::Path<String>, request: web::Json<StatPointsRequest>) -> HttpResponse {
+    let Some(monster) = monster_repository.get_monster_by_id(&id) else {
+        return HttpResponse::NotFound().json("Monster not found");
+    };
+    training_response(training_repository.allocate_training_points(&id, request.to_stats()), monster.stats)
+}
+
+#[put("/monsters/{id}/training/individual_values")]
+pub async fn set_individual_values(monster_repository: web::Data<dyn MonsterRepository>, training_repository: web::Data<dyn TrainingRepository>, id: web::Path<String>, request: web::Json<StatPointsRequest>) -> HttpResponse {
+    let Some(monster) = monster_repository.get_monster_by_id(&id) else {
+        return HttpResponse::NotFound().json("Monster not found");
+    };
+    training_response(training_repository.set_individual_values(&id, request.to_stats()), monster.stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use serde_json::json;
+    use crate::api::config::repositories;
+    use crate::models::battle::{Battle, BattleOutcome};
+    use crate::repository::battle_repository::BattleRepository;
+    use crate::repository::database::Database;
+    use crate::utils::test_utils::init_test_monsters;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_allocate_the_training_points_won_in_battles() {
+        let db = Arc::new(Database::new().unwrap());
+        let monsters = init_test_monsters(db.as_ref()).await;
+        let (winner, loser) = (&monsters[0], &monsters[1]);
+        for _ in 0..2 {
+            db.create_battle(Battle {
+                id: String::new(),
+                monster_a: winner.id.clone(),
+                monster_b: loser.id.clone(),
+                winner: Some(winner.id.clone()),
+                created_at: None,
+                updated_at: None,
+                outcome: BattleOutcome::Win,
+                manual: true,
+                season_id: None,
+                rules: None,
+                trainer_a: None,
+                trainer_b: None,
+                flawless: false,
+                highlight_score: 0,
+                region: None,
+                balance_version: None,
+                moves_a: Vec::new(),
+                moves_b: Vec::new(),
+            }).unwrap();
+        }
+        let app = App::new().configure(repositories(db.clone())).service(get_monster_training).service(allocate_training_points).service(set_individual_values);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri(&format!("/monsters/{}/training", loser.id)).to_request();
+        let sheet: TrainingSheet = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((sheet.training.unspent_points, sheet.effective_stats), (0, loser.stats));
+
+        let req = test::TestRequest::post().uri(&format!("/monsters/{}/training/allocate", winner.id)).set_json(json!({ "attack": 9 })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let req = test::TestRequest::post().uri(&format!("/monsters/{}/training/allocate", winner.id)).set_json(json!({ "attack": 8 })).to_request();
+        let sheet: TrainingSheet = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((sheet.training.unspent_points, sheet.hidden_stats.attack), (0, 2));
+
+        let req = test::TestRequest::put().uri(&format!("/monsters/{}/training/individual_values", winner.id)).set_json(json!({ "hp": 32 })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let req = test::TestRequest::put().uri(&format!("/monsters/{}/training/individual_values", winner.id)).set_json(json!({ "attack": 3, "hp": 31 })).to_request();
+        let sheet: TrainingSheet = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(sheet.hidden_stats, Stats { attack: 5, defense: 0, hp: 31, speed: 0 });
+        let trained = db.get_monster_by_id(&winner.id).unwrap();
+        assert_eq!(sheet.effective_stats, with_hidden_stats(&trained.stats, &sheet.hidden_stats));
+
+        let req = test::TestRequest::post().uri("/monsters/123/training/allocate").set_json(json!({ "attack": 1 })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NOT_FOUND);
+    }
+}
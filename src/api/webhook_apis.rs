@@ -0,0 +1,183 @@
+use actix_web::{web, get, post, put, delete, HttpResponse};
+use serde::{Deserialize, Serialize};
+use crate::models::webhook::{Webhook, EVENT_TYPES};
+use crate::repository::webhook_repository::WebhookRepository;
+use super::error::repository_error_response;
+
+#[derive(Serialize, Deserialize)]
+pub struct WebhookRequest {
+    target_url: Option<String>,
+    event_types: Option<Vec<String>>,
+}
+
+impl WebhookRequest {
+    fn into_webhook(self, secret: String) -> Result<Webhook, String> {
+        let target_url = match self.target_url {
+            Some(target_url) if target_url.starts_with("http://") || target_url.starts_with("https://") => target_url,
+            Some(_) => return Err("Target URL must be an http or https URL".to_string()),
+            None => return Err("Target URL is required".to_string()),
+        };
+        let mut event_types = self.event_types.unwrap_or_default();
+        event_types.sort();
+        event_types.dedup();
+        if event_types.is_empty() {
+            return Err("At least one event type is required".to_string());
+        }
+        if let Some(unknown) = event_types.iter().find(|event_type| !EVENT_TYPES.contains(&event_type.as_str())) {
+            return Err(format!("Unknown event type {}, supported: {}", unknown, EVENT_TYPES.join(", ")));
+        }
+
+        Ok(Webhook {
+            id: String::new(),
+            target_url,
+            event_types,
+            secret,
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: None,
+        })
+    }
+}
+
+// The only response carrying the secret, used to check the X-Webhook-Signature of the callbacks.
+#[derive(Serialize, Deserialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    webhook: Webhook,
+    secret: String,
+}
+
+#[get("/webhooks")]
+pub async fn get_webhooks(webhook_repository: web::Data<dyn WebhookRepository>) -> HttpResponse {
+    HttpResponse::Ok().json(webhook_repository.get_webhooks())
+}
+
+#[get("/webhooks/{id}")]
+pub async fn get_webhook_by_id(webhook_repository: web::Data<dyn WebhookRepository>, id: web::Path<String>) -> HttpResponse {
+    match webhook_repository.get_webhook_by_id(&id) {
+        Some(webhook) => HttpResponse::Ok().json(webhook),
+        None => HttpResponse::NotFound().json("Webhook not found"),
+    }
+}
+
+#[post("/webhooks")]
+pub async fn create_webhook(webhook_repository: web::Data<dyn WebhookRepository>, request: web::Json<WebhookRequest>) -> HttpResponse {
+    let secret = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+    let webhook = match request.into_inner().into_webhook(secret) {
+        Ok(webhook) => webhook,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+
+    match webhook_repository.create_webhook(webhook) {
+        Ok(webhook) => HttpResponse::Created().json(CreatedWebhook { secret: webhook.secret.clone(), webhook }),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[put("/webhooks/{id}")]
+pub async fn update_webhook_by_id(webhook_repository: web::Data<dyn WebhookRepository>, id: web::Path<String>, request: web::Json<WebhookRequest>) -> HttpResponse {
+    let webhook = match request.into_inner().into_webhook(String::new()) {
+        Ok(webhook) => webhook,
+        Err(message) => return HttpResponse::BadRequest().json(message),
+    };
+
+    match webhook_repository.update_webhook_by_id(&id, webhook) {
+        Ok(Some(webhook)) => HttpResponse::Ok().json(webhook),
+        Ok(None) => HttpResponse::NotFound().json("Webhook not found"),
+        Err(err) => repository_error_response(&err),
+    }
+}
+
+#[delete("/webhooks/{id}")]
+pub async fn delete_webhook_by_id(webhook_repository: web::Data<dyn WebhookRepository>, id: web::Path<String>) -> HttpResponse {
+    match webhook_repository.delete_webhook_by_id(&id) {
+        Some(_) => HttpResponse::NoContent().finish(),
+        None => HttpResponse::NotFound().json("Webhook not found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{test, http, App};
+    use serde_json::json;
+    use crate::api::auth;
+    use crate::api::config::{config, repositories};
+    use crate::config::AppConfig;
+    use crate::models::webhook::BATTLE_COMPLETED;
+    use crate::repository::database::Database;
+    use crate::repository::memory_repository::InMemoryRepository;
+
+    use super::*;
+
+    const TEST_ADMIN_TOKEN: &str = "test-admin-token";
+
+    #[actix_rt::test]
+    async fn test_should_create_update_and_delete_a_webhook() {
+        let app = App::new()
+            .app_data(web::Data::new(AppConfig::env().with_var("ADMIN_TOKEN", TEST_ADMIN_TOKEN)))
+            .configure(repositories(Arc::new(Database::new().unwrap())))
+            .configure(config);
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/webhooks")
+            .insert_header((auth::ADMIN_TOKEN_HEADER, TEST_ADMIN_TOKEN))
+            .set_json(json!({ "target_url": "https://hooks.example/battles", "event_types": [BATTLE_COMPLETED, BATTLE_COMPLETED] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let created: CreatedWebhook = test::read_body_json(resp).await;
+        assert_eq!(created.webhook.event_types, vec![BATTLE_COMPLETED.to_string()]);
+        assert_eq!(created.secret.len(), 64);
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/webhooks/{}", created.webhook.id))
+            .insert_header((auth::ADMIN_TOKEN_HEADER, TEST_ADMIN_TOKEN))
+            .set_json(json!({ "target_url": "https://hooks.example/v2", "event_types": [BATTLE_COMPLETED] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/webhooks/{}", created.webhook.id))
+            .insert_header((auth::ADMIN_TOKEN_HEADER, TEST_ADMIN_TOKEN))
+            .to_request();
+        let webhook: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(webhook["target_url"], "https://hooks.example/v2");
+        assert!(webhook.get("secret").is_none());
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/webhooks/{}", created.webhook.id))
+            .insert_header((auth::ADMIN_TOKEN_HEADER, TEST_ADMIN_TOKEN))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::NO_CONTENT);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_reject_invalid_webhooks() {
+        let app = App::new()
+            .app_data(web::Data::new(AppConfig::env().with_var("ADMIN_TOKEN", TEST_ADMIN_TOKEN)))
+            .configure(repositories(Arc::new(InMemoryRepository::new())))
+            .configure(config);
+        let app = test::init_service(app).await;
+
+        for request in [
+            json!({ "target_url": "ftp://hooks.example", "event_types": [BATTLE_COMPLETED] }),
+            json!({ "target_url": "https://hooks.example", "event_types": [] }),
+            json!({ "target_url": "https://hooks.example", "event_types": ["monster.created"] }),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/api/webhooks")
+                .insert_header((auth::ADMIN_TOKEN_HEADER, TEST_ADMIN_TOKEN))
+                .set_json(request)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        }
+
+        let req = test::TestRequest::post().uri("/api/webhooks").set_json(json!({})).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::FORBIDDEN);
+    }
+}
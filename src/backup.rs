@@ -0,0 +1,49 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use crate::models::backup::{Backup, BackupLine};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// The backup as gzipped NDJSON, one `BackupLine` per line, for backups too large to handle as one JSON document.
+pub fn to_ndjson_gz(backup: &Backup) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let header = BackupLine::Header { version: backup.version, created_at: backup.created_at };
+    let lines = std::iter::once(header)
+        .chain(backup.monsters.iter().cloned().map(BackupLine::Monster))
+        .chain(backup.battles.iter().cloned().map(BackupLine::Battle));
+    for line in lines {
+        serde_json::to_writer(&mut encoder, &line).expect("Backups serialize to JSON");
+        encoder.write_all(b"\n").expect("Backups are compressed in memory");
+    }
+    encoder.finish().expect("Backups are compressed in memory")
+}
+
+// Reads a backup written by `GET /admin/backup` in either format, told apart by the gzip magic number.
+pub fn read_backup(path: &Path) -> Result<Backup, String> {
+    let mut magic = [0; 2];
+    let gzipped = File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && magic == GZIP_MAGIC;
+    let file = File::open(path).map_err(|err| err.to_string())?;
+    if !gzipped {
+        return serde_json::from_reader(BufReader::new(file)).map_err(|err| format!("Invalid JSON backup: {}", err));
+    }
+
+    let mut backup: Option<Backup> = None;
+    for (index, line) in BufReader::new(GzDecoder::new(file)).lines().enumerate() {
+        let line = line.map_err(|err| format!("Invalid gzipped backup: {}", err))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line: BackupLine = serde_json::from_str(&line).map_err(|err| format!("Invalid backup line {}: {}", index + 1, err))?;
+        match (&mut backup, line) {
+            (None, BackupLine::Header { version, created_at }) => backup = Some(Backup { version, created_at, monsters: Vec::new(), battles: Vec::new() }),
+            (Some(backup), BackupLine::Monster(monster)) => backup.monsters.push(monster),
+            (Some(backup), BackupLine::Battle(battle)) => backup.battles.push(battle),
+            _ => return Err(format!("Backup line {} is out of place, the header comes first and only once", index + 1)),
+        }
+    }
+    backup.ok_or_else(|| "The backup has no header".to_string())
+}
@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, RwLock};
+use serde::{Deserialize, Serialize};
+use toml_edit::{Document, Item, TableLike, Value};
+use crate::config::AppConfig;
+use crate::models::moves::Move;
+use crate::models::training::{MAX_STAT_TRAINING_POINTS, MAX_TRAINING_POINTS};
+
+// The balance file read when BALANCE_FILE isn't set, the built-in balance being used when it doesn't exist.
+pub const DEFAULT_BALANCE_FILE: &str = "balance.toml";
+// The version of the built-in balance, the one balance.toml ships with.
+pub const BUILTIN_BALANCE_VERSION: &str = "1";
+
+/*
+The tunable numbers of the game, read from balance.toml. Every section and number left out of the file keeps
+the built-in value, so a file only lists what it changes. `version` is recorded on every battle played with
+the balance, it must be bumped with every change for the battles to stay reproducible.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Balance {
+    pub version: String,
+    #[serde(default)]
+    pub damage: DamageBalance,
+    #[serde(default)]
+    pub crits: CritBalance,
+    #[serde(default)]
+    pub growth: GrowthBalance,
+    #[serde(default)]
+    pub training: TrainingBalance,
+    // The percentage of its damage a move of the attacking element deals to a defender of the defending one, 100 when unlisted.
+    #[serde(default)]
+    pub elements: BTreeMap<String, BTreeMap<String, i32>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DamageBalance {
+    // The damage of a hit whose attack doesn't beat the defense, and of the weakest seeded roll.
+    pub minimum: i32,
+    // Seeded battles roll each hit between this percentage of its damage and all of it.
+    pub min_roll_percent: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CritBalance {
+    // Only rolled in battles whose rules turn crits on.
+    pub chance_percent: i32,
+    pub damage_percent: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct GrowthBalance {
+    // XP a win against an opponent as strong as the winner grants.
+    pub battle_xp: i64,
+    // XP needed to leave level n is level_xp * n * (n + 1) / 2.
+    pub level_xp: i64,
+    // Each level up raises every stat by this percentage, by at least 1.
+    pub stat_growth_percent: i32,
+    // Softens the level ratio applied to damage, (attacker level + offset) / (defender level + offset).
+    pub level_damage_offset: i32,
+}
+
+// The budgets of the training points, the maximums being capped by the ones the database enforces.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrainingBalance {
+    pub points_per_win: i32,
+    // Training points raise a stat by one for every `points_per_stat_point` allocated to it.
+    pub points_per_stat_point: i32,
+    pub max_stat_points: i32,
+    pub max_points: i32,
+}
+
+impl Default for Balance {
+    fn default() -> Self {
+        Balance {
+            version: BUILTIN_BALANCE_VERSION.to_string(),
+            damage: DamageBalance::default(),
+            crits: CritBalance::default(),
+            growth: GrowthBalance::default(),
+            training: TrainingBalance::default(),
+            elements: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for DamageBalance {
+    fn default() -> Self {
+        DamageBalance { minimum: 1, min_roll_percent: 85 }
+    }
+}
+
+impl Default for CritBalance {
+    fn default() -> Self {
+        CritBalance { chance_percent: 10, damage_percent: 150 }
+    }
+}
+
+impl Default for GrowthBalance {
+    fn default() -> Self {
+        GrowthBalance { battle_xp: 50, level_xp: 100, stat_growth_percent: 5, level_damage_offset: 10 }
+    }
+}
+
+impl Default for TrainingBalance {
+    fn default() -> Self {
+        TrainingBalance { points_per_win: 4, points_per_stat_point: 4, max_stat_points: MAX_STAT_TRAINING_POINTS, max_points: MAX_TRAINING_POINTS }
+    }
+}
+
+impl Balance {
+    pub fn parse(toml: &str) -> Result<Self, String> {
+        let document = Document::parse(toml).map_err(|err| format!("Invalid balance file: {}", err))?;
+        let balance: Balance = serde_json::from_value(table_json(document.as_table()))
+            .map_err(|err| format!("Invalid balance file: {}", err))?;
+        balance.validate()?;
+        Ok(balance)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let checks = [
+            (!self.version.trim().is_empty(), "version must not be empty".to_string()),
+            (self.damage.minimum >= 1, "damage.minimum must be at least 1".to_string()),
+            ((1..=100).contains(&self.damage.min_roll_percent), "damage.min_roll_percent must be between 1 and 100".to_string()),
+            ((0..=100).contains(&self.crits.chance_percent), "crits.chance_percent must be between 0 and 100".to_string()),
+            (self.crits.damage_percent >= 1, "crits.damage_percent must be at least 1".to_string()),
+            (self.growth.battle_xp >= 1 && self.growth.level_xp >= 1, "growth.battle_xp and growth.level_xp must be at least 1".to_string()),
+            (self.growth.stat_growth_percent >= 0 && self.growth.level_damage_offset >= 0, "growth.stat_growth_percent and growth.level_damage_offset must not be negative".to_string()),
+            (self.training.points_per_win >= 0 && self.training.points_per_stat_point >= 1, "training.points_per_win must not be negative and training.points_per_stat_point be at least 1".to_string()),
+            ((0..=MAX_STAT_TRAINING_POINTS).contains(&self.training.max_stat_points), format!("training.max_stat_points must be between 0 and {}", MAX_STAT_TRAINING_POINTS)),
+            ((0..=MAX_TRAINING_POINTS).contains(&self.training.max_points), format!("training.max_points must be between 0 and {}", MAX_TRAINING_POINTS)),
+        ];
+        if let Some((_, message)) = checks.into_iter().find(|(valid, _)| !valid) {
+            return Err(format!("Invalid balance file: {}", message));
+        }
+        // Elements are stored lowercase on the moves.
+        for (attacking, defending) in &self.elements {
+            for (defending, percent) in defending {
+                if *attacking != attacking.to_lowercase() || *defending != defending.to_lowercase() || *percent < 0 {
+                    return Err(format!("Invalid balance file: elements.{}.{} must be a lowercase element with a non-negative percentage", attacking, defending));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /*
+    The damage of a hit with the move against a defender, scaled by the element matrix. A monster is of the
+    element of its first move that has one, monsters without moves or elements taking the damage unscaled.
+    */
+    pub fn element_damage(&self, damage: i32, used_move: &Move, defender_moves: &[Move]) -> i32 {
+        let defending = defender_moves.iter().find_map(|defender_move| defender_move.element.as_deref());
+        let percent = used_move.element.as_deref()
+            .zip(defending)
+            .and_then(|(attacking, defending)| self.elements.get(attacking)?.get(defending))
+            .copied()
+            .unwrap_or(100);
+        if percent == 100 {
+            return damage;
+        }
+        (damage as i64 * percent as i64 / 100).clamp(self.damage.minimum as i64, i32::MAX as i64) as i32
+    }
+}
+
+fn table_json(table: &dyn TableLike) -> serde_json::Value {
+    serde_json::Value::Object(table.iter().map(|(key, item)| (key.to_string(), item_json(item))).collect())
+}
+
+fn item_json(item: &Item) -> serde_json::Value {
+    match item {
+        Item::Value(value) => value_json(value),
+        Item::Table(table) => table_json(table),
+        Item::ArrayOfTables(tables) => serde_json::Value::Array(tables.iter().map(|table| table_json(table)).collect()),
+        Item::None => serde_json::Value::Null,
+    }
+}
+
+fn value_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::String(string) => serde_json::Value::from(string.value().as_str()),
+        Value::Integer(integer) => serde_json::Value::from(*integer.value()),
+        Value::Float(float) => serde_json::Value::from(*float.value()),
+        Value::Boolean(boolean) => serde_json::Value::from(*boolean.value()),
+        Value::Datetime(datetime) => serde_json::Value::from(datetime.value().to_string()),
+        Value::Array(array) => serde_json::Value::Array(array.iter().map(value_json).collect()),
+        Value::InlineTable(table) => table_json(table),
+    }
+}
+
+/*
+The balance in play and the file it was read from. Battles take the balance when they start and play with it
+to the end, reloading only changes the battles started after it.
+*/
+pub struct BalanceStore {
+    file: RwLock<Option<PathBuf>>,
+    current: RwLock<Arc<Balance>>,
+}
+
+// Shared with the reload handler as app data.
+pub static BALANCE: LazyLock<Arc<BalanceStore>> = LazyLock::new(|| Arc::new(BalanceStore::new()));
+
+// The balance battles, level ups and training are played with.
+pub fn current() -> Arc<Balance> {
+    BALANCE.current()
+}
+
+impl Default for BalanceStore {
+    fn default() -> Self {
+        BalanceStore::new()
+    }
+}
+
+impl BalanceStore {
+    // Starts with the built-in balance, until a file is loaded.
+    pub fn new() -> Self {
+        BalanceStore { file: RwLock::new(None), current: RwLock::new(Arc::new(Balance::default())) }
+    }
+
+    pub fn current(&self) -> Arc<Balance> {
+        self.current.read().expect("Balance lock poisoned").clone()
+    }
+
+    // Loads BALANCE_FILE, or balance.toml when it exists.
+    pub fn load_from_config(&self, config: &AppConfig) -> Result<Arc<Balance>, String> {
+        match config.var("BALANCE_FILE") {
+            Some(file) => self.load(PathBuf::from(file)),
+            None if std::path::Path::new(DEFAULT_BALANCE_FILE).exists() => self.load(PathBuf::from(DEFAULT_BALANCE_FILE)),
+            None => Ok(self.current()),
+        }
+    }
+
+    // Reads the file and plays with it from now on, the file being the one reloads read again.
+    pub fn load(&self, file: PathBuf) -> Result<Arc<Balance>, String> {
+        let balance = read_balance(&file)?;
+        *self.file.write().expect("Balance lock poisoned") = Some(file);
+        Ok(self.replace(balance))
+    }
+
+    // Reads the loaded file again. The balance in play is kept when the file is invalid, or when none was loaded.
+    pub fn reload(&self) -> Result<Arc<Balance>, String> {
+        let file = self.file.read().expect("Balance lock poisoned").clone();
+        let Some(file) = file else {
+            return Err("No balance file was loaded, the built-in balance is in play".to_string());
+        };
+        Ok(self.replace(read_balance(&file)?))
+    }
+
+    fn replace(&self, balance: Balance) -> Arc<Balance> {
+        let balance = Arc::new(balance);
+        *self.current.write().expect("Balance lock poisoned") = balance.clone();
+        balance
+    }
+}
+
+fn read_balance(file: &PathBuf) -> Result<Balance, String> {
+    let toml = std::fs::read_to_string(file).map_err(|err| format!("Can't read the balance file {}: {}", file.display(), err))?;
+    Balance::parse(&toml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fire_move(element: Option<&str>) -> Move {
+        Move { id: String::new(), name: "Ember".to_string(), power: 100, accuracy: 100, element: element.map(str::to_string), effect: None, effect_chance: 0, created_at: chrono::Utc::now().naive_utc(), updated_at: None }
+    }
+
+    #[test]
+    fn test_should_read_the_balance_file_over_the_builtin_balance() {
+        let shipped = Balance::parse(include_str!("../balance.toml")).unwrap();
+        assert_eq!(shipped, Balance::default());
+
+        let balance = Balance::parse("version = \"2\"\n[crits]\ndamage_percent = 200\n[elements.fire]\ngrass = 200\nwater = 50\n").unwrap();
+        assert_eq!((balance.version.as_str(), balance.crits, balance.damage), ("2", CritBalance { chance_percent: 10, damage_percent: 200 }, DamageBalance::default()));
+        let (fire, grass, water) = (fire_move(Some("fire")), fire_move(Some("grass")), fire_move(Some("water")));
+        assert_eq!(balance.element_damage(30, &fire, &[fire_move(None), grass.clone()]), 60);
+        assert_eq!(balance.element_damage(1, &fire, &[water]), 1);
+        assert_eq!(balance.element_damage(30, &grass, std::slice::from_ref(&fire)), 30);
+        assert_eq!(balance.element_damage(30, &fire, &[]), 30);
+
+        assert!(Balance::parse("[damage]\nminimum = 1\n").is_err());
+        assert!(Balance::parse("version = \"2\"\n[damage]\nminimum = 0\n").is_err());
+        assert!(Balance::parse("version = \"2\"\n[damage]\nfloor = 1\n").is_err());
+        assert!(Balance::parse("version = \"2\"\n[training]\nmax_points = 600\n").is_err());
+        assert!(Balance::parse("version = \"2\"\n[elements.Fire]\ngrass = 200\n").is_err());
+    }
+}
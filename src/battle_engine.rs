@@ -0,0 +1,866 @@
+use std::sync::Arc;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+use crate::balance::{self, Balance};
+use crate::config::AppConfig;
+use crate::highlights;
+use crate::models::fixed_point::Fixed;
+use crate::models::growth;
+use crate::models::item::Item;
+use crate::models::monster::{Monster, StatModifiers, Stats};
+use crate::models::moves::Move;
+use crate::models::rules::{handicapped_hp, BattleRules};
+use crate::models::status_effect::{StatusEffect, StatusEffectRule, StatusEffectRules};
+use crate::models::strategy::Strategy;
+use crate::models::training::with_hidden_stats;
+
+/*
+A battle to play, the monsters with what they fight with. Seeded battles, as the games of a series, replay the
+same turns, and battles without rules are played with the classic ones. Hidden stats, from the individual
+values and training of the monsters, add to their stats before anything else. Setups take the balance in play
+when they are made.
+*/
+pub struct BattleSetup<'a> {
+    pub monster_a: Monster,
+    pub monster_b: Monster,
+    pub moves: (Vec<Move>, Vec<Move>),
+    pub items: (Vec<Item>, Vec<Item>),
+    pub hidden_stats: (Stats, Stats),
+    pub strategies: (Strategy, Strategy),
+    pub status_effects: Option<&'a StatusEffectRules>,
+    pub rules: Option<BattleRules>,
+    pub seed: Option<u64>,
+    pub balance: Arc<Balance>,
+}
+
+impl BattleSetup<'_> {
+    pub fn new(monster_a: Monster, monster_b: Monster) -> Self {
+        BattleSetup {
+            monster_a,
+            monster_b,
+            moves: Default::default(),
+            items: Default::default(),
+            hidden_stats: Default::default(),
+            strategies: Default::default(),
+            status_effects: None,
+            rules: None,
+            seed: None,
+            balance: balance::current(),
+        }
+    }
+}
+
+/*
+Plays battles by a rule set. Handlers play them with the engine registered as app data, the classic one when
+none is, so other rule sets (typed, team battles...) ship as other implementations without touching them.
+Battles without a turn limit must end in a knockout.
+*/
+pub trait BattleEngine: Send + Sync {
+    // The turns as they are played, up to the knockout or the turn limit.
+    fn turns(&self, battle: BattleSetup<'_>) -> Box<dyn Iterator<Item = TurnEvent>>;
+    // The winner, none for a draw at the turn limit, and whether it won without losing HP.
+    fn simulate(&self, battle: BattleSetup<'_>) -> BattleResult;
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BattleResult {
+    pub winner: Option<String>,
+    // The winner didn't lose any HP, to hits or status effects.
+    pub flawless: bool,
+    // How exciting the battle was, out of 100, see `highlights::intensity`.
+    pub highlight_score: i32,
+    // The version of the balance the battle was played with.
+    pub balance_version: Option<String>,
+    // The monsters and the names of the moves they used, each pair once in the order they were first used.
+    pub used_moves: Vec<(String, String)>,
+}
+
+impl BattleResult {
+    pub fn from_turns(winner: Option<String>, turns: &[TurnEvent], balance: &Balance) -> Self {
+        let flawless = winner.as_deref().is_some_and(|winner| !turns.iter().any(|turn| turn.hurt(winner)));
+        let highlight_score = highlights::intensity(winner.as_deref(), turns).score;
+        let mut used_moves = Vec::new();
+        for turn in turns {
+            if let Some(name) = &turn.used_move {
+                let used_move = (turn.attacker.clone(), name.clone());
+                if !used_moves.contains(&used_move) {
+                    used_moves.push(used_move);
+                }
+            }
+        }
+        BattleResult { winner, flawless, highlight_score, balance_version: Some(balance.version.clone()), used_moves }
+    }
+
+    // The ids of the moves the monster used, looked up by name among the moves it knows.
+    pub fn moves_used_by(&self, monster_id: &str, known_moves: &[Move]) -> Vec<String> {
+        self.used_moves
+            .iter()
+            .filter(|(attacker, _)| attacker == monster_id)
+            .filter_map(|(_, name)| known_moves.iter().find(|known_move| &known_move.name == name))
+            .map(|known_move| known_move.id.clone())
+            .collect()
+    }
+}
+
+// The engine picked by BATTLE_ENGINE, classic by default.
+pub fn from_config(config: &AppConfig) -> Result<Arc<dyn BattleEngine>, String> {
+    match config.var("BATTLE_ENGINE").as_deref().map(str::trim) {
+        None | Some("classic") => Ok(Arc::new(ClassicEngine)),
+        Some(other) => Err(format!("Unknown battle engine {:?}, expected classic", other)),
+    }
+}
+
+// The rules below, the ones golden battles and test vectors are recorded with.
+pub struct ClassicEngine;
+
+impl ClassicEngine {
+    fn battle_turns(battle: BattleSetup<'_>) -> BattleTurns {
+        let (mut monster_a, mut monster_b) = (battle.monster_a, battle.monster_b);
+        monster_a.stats = with_hidden_stats(&monster_a.stats, &battle.hidden_stats.0);
+        monster_b.stats = with_hidden_stats(&monster_b.stats, &battle.hidden_stats.1);
+        let turns = match battle.seed {
+            Some(seed) => BattleTurns::seeded(monster_a, monster_b, seed),
+            None => BattleTurns::new(monster_a, monster_b),
+        };
+        turns
+            .with_balance(battle.balance)
+            .with_rules(battle.rules.unwrap_or_default())
+            .with_items(battle.items)
+            .with_moves(battle.moves)
+            .with_strategies(battle.strategies)
+            .with_status_effects(battle.status_effects)
+    }
+}
+
+impl BattleEngine for ClassicEngine {
+    fn turns(&self, battle: BattleSetup<'_>) -> Box<dyn Iterator<Item = TurnEvent>> {
+        Box::new(ClassicEngine::battle_turns(battle))
+    }
+
+    fn simulate(&self, battle: BattleSetup<'_>) -> BattleResult {
+        let mut turns = ClassicEngine::battle_turns(battle);
+        let played: Vec<TurnEvent> = turns.by_ref().collect();
+        let knockout = played.last().and_then(|turn| turn.winner().map(str::to_string));
+        BattleResult::from_turns(knockout.or_else(|| turns.decision()), &played, &turns.balance)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TurnEvent {
+    pub turn: u32,
+    pub attacker: String,
+    pub defender: String,
+    pub damage: i32,
+    pub defender_hp: i32,
+    // Status effects the attacker suffered this turn, with the hp they left it when they hurt it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub statuses: Vec<StatusTick>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attacker_hp: Option<i32>,
+    // The attacker was stunned and didn't attack.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+    // Name of the move the attacker used, monsters without moves make a plain attack.
+    #[serde(default, rename = "move", skip_serializing_if = "Option::is_none")]
+    pub used_move: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub missed: bool,
+    // Status effects the hit left on the defender.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inflicted: Vec<StatusEffect>,
+    // Monsters knocked out this turn that a consumable brought back, their hp above being the revived one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub revived: Vec<Revival>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub critical: bool,
+}
+
+impl TurnEvent {
+    // Set on the last turn, whose attacker either knocked the defender out or succumbed to its statuses.
+    pub fn winner(&self) -> Option<&str> {
+        if self.defender_hp == 0 {
+            Some(&self.attacker)
+        } else if self.attacker_hp == Some(0) {
+            Some(&self.defender)
+        } else {
+            None
+        }
+    }
+
+    // The monster took damage this turn, hit as the defender or hurt by its statuses as the attacker.
+    fn hurt(&self, monster_id: &str) -> bool {
+        (self.defender == monster_id && self.damage > 0) || (self.attacker == monster_id && self.statuses.iter().any(|tick| tick.damage > 0))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StatusTick {
+    pub effect: StatusEffect,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub damage: i32,
+    pub turns_left: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Revival {
+    pub monster: String,
+    pub item: String,
+}
+
+fn is_zero(damage: &i32) -> bool {
+    *damage == 0
+}
+
+/*
+Version of the rules below. Bump it whenever a change is meant to alter battle outcomes, then record the
+new outcomes with `UPDATE_GOLDEN_BATTLES=1 cargo test golden_battles`, which writes
+fixtures/golden_battles/v<ENGINE_VERSION>.json. `cargo test golden_battles` replays the fixtures of the
+current version and fails with a diff of every battle whose outcome changed.
+*/
+pub const ENGINE_VERSION: u32 = 1;
+
+/*
+- The monster with the highest speed makes the first attack, if both speeds are equal, the monster with the higher attack goes first.
+- For calculating the damage, subtract the defense from the attack (attack - defense); the difference is the damage; 
+- if the attack is equal to or lower than the defense, the damage is the minimum damage of the balance, 1 with the built-in one.
+Subtract the damage from the HP (HP = HP - damage).
+Monsters will battle in turns until one wins, each item being one attack.
+Who wins the battle is the monster who subtracted the enemy’s HP to zero
+Seeded battles (the games of a series) roll each hit between 85% and 100% of the damage, with a minimum of 1.
+The numbers above and below are the built-in balance (`Balance`), balance.toml may change them and scale the
+damage of moves by their element. Battles play with the balance they started with.
+All of the math is integer or fixed-point (`Fixed`), seeded battles replay the same on every platform, which
+`cargo test seeded_test_vectors` checks against fixtures/test_vectors/seeded_battles.json.
+With the status effects rule enabled, each damaging hit rolls the chance of every effect to afflict the defender.
+The statuses of a monster act when its turn starts, a monster poisoned down to zero HP loses the battle.
+Monsters that know moves use the one their strategy picks, by default the one with the highest power times accuracy,
+the first one on ties. A move deals its power in percent of the damage above, misses when its accuracy roll fails
+and may inflict its effect on a hit.
+Equipped items scale the attack, defense and speed of their monster for the whole battle. A monster knocked out
+while carrying an unused revive comes back with part of its starting HP instead, each consumable working once.
+Battles played with other rules than the classic ones (`BattleRules`) may start with handicapped HP, roll
+critical hits, deal more than 1 damage at least and end at a turn limit.
+*/
+struct BattleTurns {
+    monster_a: Combatant,
+    monster_b: Combatant,
+    monster_a_turn: bool,
+    turn: u32,
+    finished: bool,
+    damage_rolls: Option<ChaCha8Rng>,
+    status_effects: Vec<StatusEffectRule>,
+    // Accuracy, status effect and crit rolls, only set up when moves, status effects or crits are in play.
+    chance_rolls: Option<ChaCha8Rng>,
+    rules: BattleRules,
+    // Kept to the end of the battle when the balance is reloaded.
+    balance: Arc<Balance>,
+}
+
+struct Combatant {
+    monster: Monster,
+    starting_hp: i32,
+    statuses: Vec<ActiveStatus>,
+    moves: Vec<Move>,
+    strategy: Strategy,
+    // Consumables not used yet this battle.
+    consumables: Vec<Item>,
+}
+
+struct ActiveStatus {
+    effect: StatusEffect,
+    turns_left: u32,
+}
+
+impl Combatant {
+    fn new(monster: Monster) -> Self {
+        Combatant { starting_hp: monster.stats.hp, monster, statuses: Vec::new(), moves: Vec::new(), strategy: Strategy::default(), consumables: Vec::new() }
+    }
+
+    fn equip(&mut self, items: Vec<Item>) {
+        let (consumables, equipment): (Vec<Item>, Vec<Item>) = items.into_iter().partition(|item| item.consumable);
+        self.monster.stats = equipment.iter().fold(self.monster.stats, |stats, item| stats.with_modifiers(&item.modifiers()));
+        self.consumables = consumables;
+    }
+
+    // Uses up the first revive of a knocked out monster.
+    fn revive(&mut self) -> Option<Revival> {
+        if self.monster.stats.hp > 0 || self.consumables.is_empty() {
+            return None;
+        }
+        let revive = self.consumables.remove(0);
+        self.monster.stats.hp = revive.revived_hp(self.starting_hp);
+        Some(Revival { monster: self.monster.id.clone(), item: revive.name })
+    }
+
+    // Runs the turn start hook of every status, then drops the ones that wore off.
+    fn start_turn(&mut self) -> Vec<StatusTick> {
+        let ticks = self
+            .statuses
+            .iter_mut()
+            .map(|status| {
+                let damage = status.effect.turn_damage(self.starting_hp);
+                self.monster.stats.hp = (self.monster.stats.hp - damage).max(0);
+                status.turns_left -= 1;
+                StatusTick { effect: status.effect, damage, turns_left: status.turns_left }
+            })
+            .collect();
+        self.statuses.retain(|status| status.turns_left > 0);
+        ticks
+    }
+
+    // Inflicting an effect the monster already suffers restarts its duration.
+    fn inflict(&mut self, rule: &StatusEffectRule) {
+        match self.statuses.iter_mut().find(|status| status.effect == rule.effect) {
+            Some(status) => status.turns_left = rule.turns,
+            None => self.statuses.push(ActiveStatus { effect: rule.effect, turns_left: rule.turns }),
+        }
+    }
+}
+
+impl BattleTurns {
+    fn new(monster_a: Monster, monster_b: Monster) -> Self {
+        let monster_a_turn = monster_a.stats.attacks_before(&monster_b.stats);
+        BattleTurns {
+            monster_a: Combatant::new(monster_a),
+            monster_b: Combatant::new(monster_b),
+            monster_a_turn,
+            turn: 0,
+            finished: false,
+            damage_rolls: None,
+            status_effects: Vec::new(),
+            chance_rolls: None,
+            rules: BattleRules::default(),
+            balance: balance::current(),
+        }
+    }
+
+    fn seeded(monster_a: Monster, monster_b: Monster, seed: u64) -> Self {
+        BattleTurns { damage_rolls: Some(ChaCha8Rng::seed_from_u64(seed)), ..BattleTurns::new(monster_a, monster_b) }
+    }
+
+    fn with_balance(self, balance: Arc<Balance>) -> Self {
+        BattleTurns { balance, ..self }
+    }
+
+    fn with_status_effects(self, status_effects: Option<&StatusEffectRules>) -> Self {
+        let Some(status_effects) = status_effects else { return self };
+        BattleTurns { status_effects: status_effects.0.clone(), chance_rolls: Some(self.chance_rolls()), ..self }
+    }
+
+    // Handicaps the HP the monsters start with, so it comes before any turn is played.
+    fn with_rules(mut self, rules: BattleRules) -> Self {
+        for (combatant, hp_percent) in [(&mut self.monster_a, rules.monster_a_hp_percent), (&mut self.monster_b, rules.monster_b_hp_percent)] {
+            combatant.monster.stats.hp = handicapped_hp(combatant.monster.stats.hp, hp_percent);
+            combatant.starting_hp = combatant.monster.stats.hp;
+        }
+        let chance_rolls = if rules.crits { Some(self.chance_rolls()) } else { self.chance_rolls.take() };
+        BattleTurns { chance_rolls, rules, ..self }
+    }
+
+    // Once the turn limit is reached, the monster with the larger share of its starting HP left wins, none on equal shares.
+    fn decision(&self) -> Option<String> {
+        let (monster_a, monster_b) = (&self.monster_a, &self.monster_b);
+        let monster_a_share = monster_a.monster.stats.hp as i64 * monster_b.starting_hp as i64;
+        let monster_b_share = monster_b.monster.stats.hp as i64 * monster_a.starting_hp as i64;
+        match monster_a_share.cmp(&monster_b_share) {
+            std::cmp::Ordering::Greater => Some(monster_a.monster.id.clone()),
+            std::cmp::Ordering::Less => Some(monster_b.monster.id.clone()),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    // Applies the equipment to the stats the turn order is decided on, so it comes before any turn is played.
+    fn with_items(mut self, (monster_a_items, monster_b_items): (Vec<Item>, Vec<Item>)) -> Self {
+        self.monster_a.equip(monster_a_items);
+        self.monster_b.equip(monster_b_items);
+        self.monster_a_turn = self.monster_a.monster.stats.attacks_before(&self.monster_b.monster.stats);
+        self
+    }
+
+    fn with_moves(mut self, (monster_a_moves, monster_b_moves): (Vec<Move>, Vec<Move>)) -> Self {
+        if monster_a_moves.is_empty() && monster_b_moves.is_empty() {
+            return self;
+        }
+        self.monster_a.moves = monster_a_moves;
+        self.monster_b.moves = monster_b_moves;
+        BattleTurns { chance_rolls: Some(self.chance_rolls()), ..self }
+    }
+
+    fn with_strategies(mut self, (monster_a_strategy, monster_b_strategy): (Strategy, Strategy)) -> Self {
+        self.monster_a.strategy = monster_a_strategy;
+        self.monster_b.strategy = monster_b_strategy;
+        self
+    }
+
+    // Seeded battles roll chances on another stream of their seed, so they replay as well.
+    fn chance_rolls(&self) -> ChaCha8Rng {
+        if let Some(chance_rolls) = &self.chance_rolls {
+            return chance_rolls.clone();
+        }
+        match &self.damage_rolls {
+            Some(damage_rolls) => {
+                let mut chance_rolls = ChaCha8Rng::from_seed(damage_rolls.get_seed());
+                chance_rolls.set_stream(1);
+                chance_rolls
+            }
+            None => ChaCha8Rng::from_entropy(),
+        }
+    }
+}
+
+impl Iterator for BattleTurns {
+    type Item = TurnEvent;
+
+    fn next(&mut self) -> Option<TurnEvent> {
+        if self.finished {
+            return None;
+        }
+        let event = self.play_turn();
+        if self.rules.max_turns.is_some_and(|max_turns| self.turn >= max_turns) {
+            self.finished = true;
+        }
+        Some(event)
+    }
+}
+
+impl BattleTurns {
+    fn play_turn(&mut self) -> TurnEvent {
+        let (attacker, defender) = if self.monster_a_turn {
+            (&mut self.monster_a, &mut self.monster_b)
+        } else {
+            (&mut self.monster_b, &mut self.monster_a)
+        };
+        self.monster_a_turn = !self.monster_a_turn;
+        self.turn += 1;
+        let mut event = TurnEvent {
+            turn: self.turn,
+            attacker: attacker.monster.id.clone(),
+            defender: defender.monster.id.clone(),
+            ..TurnEvent::default()
+        };
+
+        let attack_modifiers: Vec<StatModifiers> = attacker.statuses.iter().map(|status| status.effect.modifiers()).collect();
+        event.skipped = attacker.statuses.iter().any(|status| status.effect.skips_turn());
+        event.statuses = attacker.start_turn();
+        event.revived.extend(attacker.revive());
+        if event.statuses.iter().any(|tick| tick.damage > 0) {
+            event.attacker_hp = Some(attacker.monster.stats.hp);
+        }
+        event.defender_hp = defender.monster.stats.hp;
+        if attacker.monster.stats.hp == 0 {
+            self.finished = true;
+            return event;
+        }
+        if event.skipped {
+            return event;
+        }
+
+        let attack = attack_modifiers.iter().fold(attacker.monster.stats, |stats, modifiers| stats.with_modifiers(modifiers));
+        let balance = &self.balance;
+        let mut damage = growth::scale_damage(attack.damage_against(&defender.monster.stats, balance.damage.minimum), attacker.monster.level, defender.monster.level, balance);
+        let used_move = self.chance_rolls.as_mut().and_then(|chance_rolls| {
+            let defender_statuses: Vec<StatusEffect> = defender.statuses.iter().map(|status| status.effect).collect();
+            attacker.strategy.battle_strategy().pick_move(&attacker.moves, &defender_statuses, chance_rolls)
+        });
+        if let Some(used_move) = used_move {
+            event.used_move = Some(used_move.name.clone());
+            let chance_rolls = self.chance_rolls.as_mut().expect("Battles with moves roll chances");
+            match used_move.hit(damage, chance_rolls) {
+                Some(move_damage) => damage = balance.element_damage(move_damage, used_move, &defender.moves),
+                None => {
+                    event.missed = true;
+                    return event;
+                }
+            }
+        }
+        if let Some(crit_chance) = self.rules.crit_chance(balance) {
+            let chance_rolls = self.chance_rolls.as_mut().expect("Battles with crits roll chances");
+            if crit_chance.roll(chance_rolls) {
+                event.critical = true;
+                damage = damage * balance.crits.damage_percent / 100;
+            }
+        }
+        event.damage = match self.damage_rolls.as_mut() {
+            Some(damage_rolls) => (damage * damage_rolls.gen_range(balance.damage.min_roll_percent..=100) / 100).max(balance.damage.minimum),
+            None => damage,
+        }
+        .max(self.rules.damage_floor);
+        defender.monster.stats.hp = (defender.monster.stats.hp - event.damage).max(0);
+        event.revived.extend(defender.revive());
+        event.defender_hp = defender.monster.stats.hp;
+        self.finished = defender.monster.stats.hp == 0;
+
+        if let Some(chance_rolls) = self.chance_rolls.as_mut().filter(|_| !self.finished) {
+            let move_effect = used_move.and_then(|used_move| {
+                let effect = used_move.effect?;
+                Some(StatusEffectRule { effect, chance: Fixed::from_percent(used_move.effect_chance), turns: effect.default_turns() })
+            });
+            for rule in self.status_effects.iter().chain(&move_effect) {
+                if rule.chance.roll(chance_rolls) {
+                    defender.inflict(rule);
+                    if !event.inflicted.contains(&rule.effect) {
+                        event.inflicted.push(rule.effect);
+                    }
+                }
+            }
+        }
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::monster::Stats;
+
+    use super::*;
+
+    fn new_monster(id: &str, stats: Stats) -> Monster {
+        Monster {
+            id: id.to_string(),
+            name: id.to_string(),
+            image_url: "https://loremflickr.com/640/480".to_string(),
+            stats,
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_simulate_a_battle_turn_by_turn() {
+        let monster_a = new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 });
+        let monster_b = new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 50, speed: 80 });
+
+        let turns: Vec<TurnEvent> = BattleTurns::new(monster_a, monster_b).collect();
+
+        assert_eq!(turns, vec![
+            TurnEvent { turn: 1, attacker: "monster-b".to_string(), defender: "monster-a".to_string(), damage: 1, defender_hp: 99, ..TurnEvent::default() },
+            TurnEvent { turn: 2, attacker: "monster-a".to_string(), defender: "monster-b".to_string(), damage: 50, defender_hp: 0, ..TurnEvent::default() },
+        ]);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_fight_with_the_hidden_stats_added() {
+        let monster_a = new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 });
+        let monster_b = new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 50, speed: 80 });
+        let hidden_stats = (Stats::default(), Stats { defense: 10, ..Stats::default() });
+
+        let turns: Vec<TurnEvent> = ClassicEngine.turns(BattleSetup { hidden_stats, ..BattleSetup::new(monster_a, monster_b) }).collect();
+
+        assert_eq!((turns[1].damage, turns[1].defender_hp), (40, 10));
+    }
+
+    #[actix_rt::test]
+    async fn test_should_log_the_status_effects_of_each_turn() {
+        let monster_a = new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 });
+        let monster_b = new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 50, speed: 80 });
+        let status_effects = StatusEffectRules(vec![
+            StatusEffectRule { effect: StatusEffect::Poison, chance: Fixed::ONE, turns: 2 },
+            StatusEffectRule { effect: StatusEffect::Burn, chance: Fixed::ONE, turns: 2 },
+        ]);
+        let ticks = |poison_damage, turns_left| vec![
+            StatusTick { effect: StatusEffect::Poison, damage: poison_damage, turns_left },
+            StatusTick { effect: StatusEffect::Burn, damage: 0, turns_left },
+        ];
+
+        let turns: Vec<TurnEvent> = BattleTurns::new(monster_a.clone(), monster_b.clone()).with_status_effects(Some(&status_effects)).collect();
+
+        let inflicted = vec![StatusEffect::Poison, StatusEffect::Burn];
+        assert_eq!(turns, vec![
+            TurnEvent { turn: 1, attacker: "monster-b".to_string(), defender: "monster-a".to_string(), damage: 1, defender_hp: 99, inflicted: inflicted.clone(), ..TurnEvent::default() },
+            // Burnt, monster A hits with half its attack.
+            TurnEvent { turn: 2, attacker: "monster-a".to_string(), defender: "monster-b".to_string(), damage: 20, defender_hp: 30, statuses: ticks(12, 1), attacker_hp: Some(87), inflicted: inflicted.clone(), ..TurnEvent::default() },
+            TurnEvent { turn: 3, attacker: "monster-b".to_string(), defender: "monster-a".to_string(), damage: 1, defender_hp: 86, statuses: ticks(6, 1), attacker_hp: Some(24), inflicted: inflicted.clone(), ..TurnEvent::default() },
+            TurnEvent { turn: 4, attacker: "monster-a".to_string(), defender: "monster-b".to_string(), damage: 20, defender_hp: 4, statuses: ticks(12, 1), attacker_hp: Some(74), inflicted, ..TurnEvent::default() },
+            TurnEvent { turn: 5, attacker: "monster-b".to_string(), defender: "monster-a".to_string(), damage: 0, defender_hp: 74, statuses: ticks(6, 1), attacker_hp: Some(0), ..TurnEvent::default() },
+        ]);
+        assert_eq!(turns[4].winner(), Some("monster-a"));
+
+        // Stunned after every hit, monster A never gets to attack.
+        let stun = StatusEffectRules(vec![StatusEffectRule { effect: StatusEffect::Stun, chance: Fixed::ONE, turns: 1 }]);
+        let turns: Vec<TurnEvent> = BattleTurns::new(monster_a.clone(), monster_b.clone()).with_status_effects(Some(&stun)).collect();
+        assert!(turns[1].skipped && turns[1].damage == 0);
+        assert_eq!(turns[1].statuses, vec![StatusTick { effect: StatusEffect::Stun, damage: 0, turns_left: 0 }]);
+        // Never hit back, monster B wins without losing any HP.
+        let result = ClassicEngine.simulate(BattleSetup { status_effects: Some(&stun), ..BattleSetup::new(monster_a, monster_b) });
+        assert_eq!(result, BattleResult { winner: Some("monster-b".to_string()), flawless: true, highlight_score: 0, balance_version: Some(balance::BUILTIN_BALANCE_VERSION.to_string()), used_moves: Vec::new() });
+    }
+
+    fn new_move(name: &str, power: i32, accuracy: i32, effect: Option<StatusEffect>) -> Move {
+        Move {
+            id: name.to_lowercase(),
+            name: name.to_string(),
+            power,
+            accuracy,
+            element: None,
+            effect,
+            effect_chance: if effect.is_some() { 100 } else { 0 },
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: None,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_fight_with_the_moves_of_the_monsters() {
+        let monster_a = new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 });
+        let monster_b = new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 200, speed: 80 });
+        let moves = (vec![new_move("Tackle", 100, 100, None), new_move("Crunch", 150, 100, Some(StatusEffect::Burn))], Vec::new());
+
+        let turns: Vec<TurnEvent> = BattleTurns::new(monster_a.clone(), monster_b.clone()).with_moves(moves.clone()).collect();
+
+        assert_eq!(turns.len(), 6);
+        assert_eq!(turns[0], TurnEvent { turn: 1, attacker: "monster-b".to_string(), defender: "monster-a".to_string(), damage: 1, defender_hp: 99, ..TurnEvent::default() });
+        assert_eq!(turns[1], TurnEvent {
+            turn: 2,
+            attacker: "monster-a".to_string(),
+            defender: "monster-b".to_string(),
+            damage: 75,
+            defender_hp: 125,
+            used_move: Some("Crunch".to_string()),
+            inflicted: vec![StatusEffect::Burn],
+            ..TurnEvent::default()
+        });
+        assert_eq!(turns[5].winner(), Some("monster-a"));
+        let result = BattleResult::from_turns(Some("monster-a".to_string()), &turns, &Balance::default());
+        assert_eq!(result.used_moves, vec![("monster-a".to_string(), "Crunch".to_string())]);
+        assert_eq!(result.moves_used_by("monster-a", &moves.0), vec!["crunch".to_string()]);
+        assert!(result.moves_used_by("monster-b", &moves.1).is_empty());
+
+        // Equally good moves are picked in slot order.
+        let moves = (vec![new_move("Slam", 80, 100, None), new_move("Strike", 100, 80, None)], Vec::new());
+        let turns: Vec<TurnEvent> = BattleTurns::new(monster_a.clone(), monster_b.clone()).with_moves(moves).collect();
+        assert_eq!(turns[1].used_move.as_deref(), Some("Slam"));
+
+        // Misses deal no damage, and seeded battles replay the same accuracy rolls.
+        let moves = (Vec::new(), vec![new_move("Long Shot", 100, 50, None)]);
+        let turns: Vec<TurnEvent> = BattleTurns::seeded(monster_a.clone(), monster_b.clone(), 7).with_moves(moves.clone()).collect();
+        assert!(turns.iter().any(|turn| turn.missed));
+        assert!(turns.iter().filter(|turn| turn.missed).all(|turn| turn.damage == 0 && turn.attacker == "monster-b"));
+        assert_eq!(BattleTurns::seeded(monster_a, monster_b, 7).with_moves(moves).collect::<Vec<_>>(), turns);
+    }
+
+    #[actix_rt::test]
+    async fn test_should_fight_with_the_items_of_the_monsters() {
+        let monster_a = new_monster("monster-a", Stats { attack: 30, defense: 10, hp: 50, speed: 40 });
+        let monster_b = new_monster("monster-b", Stats { attack: 30, defense: 10, hp: 50, speed: 50 });
+        let item = |name: &str, attack_bonus: i32, speed_bonus: i32, revive_hp: i32| Item {
+            id: name.to_string(),
+            name: name.to_string(),
+            attack_bonus,
+            defense_bonus: 0,
+            speed_bonus,
+            consumable: revive_hp > 0,
+            revive_hp,
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: None,
+        };
+        let items = (vec![item("Iron Claws", 50, 50, 0)], vec![item("Phoenix Down", 0, 0, 50)]);
+
+        let turns: Vec<TurnEvent> = BattleTurns::new(monster_a, monster_b).with_items(items).collect();
+
+        // The claws make monster A the fastest, the revive brings monster B back once with half of its HP.
+        assert_eq!(turns.len(), 5);
+        assert_eq!((turns[0].attacker.as_str(), turns[0].damage), ("monster-a", 35));
+        assert_eq!(turns[2].revived, vec![Revival { monster: "monster-b".to_string(), item: "Phoenix Down".to_string() }]);
+        assert_eq!((turns[2].defender_hp, turns[2].winner()), (25, None));
+        assert!(turns[4].revived.is_empty());
+        assert_eq!(turns[4].winner(), Some("monster-a"));
+    }
+
+    #[actix_rt::test]
+    async fn test_should_pick_the_moves_with_the_strategy_of_each_monster() {
+        let monster_a = new_monster("monster-a", Stats { attack: 60, defense: 30, hp: 100, speed: 40 });
+        let monster_b = new_monster("monster-b", Stats { attack: 20, defense: 10, hp: 200, speed: 80 });
+        let moves = (vec![new_move("Tackle", 100, 100, None), new_move("Crunch", 150, 90, Some(StatusEffect::Burn))], Vec::new());
+
+        let greedy: Vec<TurnEvent> = BattleTurns::new(monster_a.clone(), monster_b.clone()).with_moves(moves.clone()).collect();
+        assert_eq!(greedy[1].used_move.as_deref(), Some("Crunch"));
+
+        let defensive: Vec<TurnEvent> = BattleTurns::seeded(monster_a.clone(), monster_b.clone(), 7)
+            .with_moves(moves.clone())
+            .with_strategies((Strategy::Defensive, Strategy::Random))
+            .collect();
+        assert!(defensive.iter().filter(|turn| turn.attacker == "monster-a").all(|turn| turn.used_move.as_deref() == Some("Tackle")));
+        // Monsters without moves attack plainly whatever their strategy.
+        assert!(defensive.iter().filter(|turn| turn.attacker == "monster-b").all(|turn| turn.used_move.is_none()));
+
+        let strategies = (Strategy::Random, Strategy::GreedyDamage);
+        let random: Vec<TurnEvent> = BattleTurns::seeded(monster_a.clone(), monster_b.clone(), 7).with_moves(moves.clone()).with_strategies(strategies).collect();
+        assert_eq!(BattleTurns::seeded(monster_a, monster_b, 7).with_moves(moves).with_strategies(strategies).collect::<Vec<_>>(), random);
+    }
+
+    // Inputs and expected outcome of a golden battle, missing outcomes are filled in by UPDATE_GOLDEN_BATTLES.
+    #[derive(Serialize, Deserialize)]
+    struct GoldenBattle {
+        name: String,
+        monster_a: GoldenStats,
+        monster_b: GoldenStats,
+        #[serde(default)]
+        expected: Option<GoldenOutcome>,
+    }
+
+    // Only the base stats, the derived ones `Stats` serializes would be noise in the fixtures.
+    #[derive(Serialize, Deserialize, Clone, Copy)]
+    struct GoldenStats {
+        attack: i32,
+        defense: i32,
+        hp: i32,
+        speed: i32,
+    }
+
+    impl From<GoldenStats> for Stats {
+        fn from(GoldenStats { attack, defense, hp, speed }: GoldenStats) -> Self {
+            Stats { attack, defense, hp, speed }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct GoldenOutcome {
+        winner: String,
+        turns: u32,
+        monster_a_hp: i32,
+        monster_b_hp: i32,
+    }
+
+    fn golden_battles_path(engine_version: u32) -> String {
+        format!("{}/fixtures/golden_battles/v{}.json", env!("CARGO_MANIFEST_DIR"), engine_version)
+    }
+
+    fn replay(battle: &GoldenBattle) -> GoldenOutcome {
+        let (monster_a, monster_b) = (new_monster("monster_a", battle.monster_a.into()), new_monster("monster_b", battle.monster_b.into()));
+        let winner = ClassicEngine.simulate(BattleSetup::new(monster_a.clone(), monster_b.clone())).winner.expect("Classic battles end in a knockout");
+        let (mut monster_a_hp, mut monster_b_hp, mut turns) = (battle.monster_a.hp, battle.monster_b.hp, 0);
+        for turn in BattleTurns::new(monster_a, monster_b) {
+            if turn.defender == "monster_a" {
+                monster_a_hp = turn.defender_hp;
+            } else {
+                monster_b_hp = turn.defender_hp;
+            }
+            turns = turn.turn;
+        }
+        GoldenOutcome { winner, turns, monster_a_hp, monster_b_hp }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_replay_the_golden_battles() {
+        let path = golden_battles_path(ENGINE_VERSION);
+        let updating = std::env::var("UPDATE_GOLDEN_BATTLES").is_ok();
+        // A version bump starts from the previous version's battles.
+        let source = if updating && !std::path::Path::new(&path).exists() { golden_battles_path(ENGINE_VERSION - 1) } else { path.clone() };
+        let contents = std::fs::read_to_string(&source)
+            .unwrap_or_else(|_| panic!("No golden battles for engine version {} at {}", ENGINE_VERSION, source));
+        let mut battles: Vec<GoldenBattle> = serde_json::from_str(&contents).unwrap();
+
+        if updating {
+            for battle in battles.iter_mut() {
+                battle.expected = Some(replay(battle));
+            }
+            std::fs::write(&path, serde_json::to_string_pretty(&battles).unwrap() + "\n").unwrap();
+            return;
+        }
+
+        let changed: Vec<String> = battles
+            .iter()
+            .filter_map(|battle| {
+                let actual = replay(battle);
+                (battle.expected.as_ref() != Some(&actual))
+                    .then(|| format!("  {}\n    expected: {:?}\n    actual:   {:?}", battle.name, battle.expected, actual))
+            })
+            .collect();
+        assert!(
+            changed.is_empty(),
+            "{} of {} golden battles changed outcome under engine version {}:\n{}\n\
+             If the change is intended, bump ENGINE_VERSION and run UPDATE_GOLDEN_BATTLES=1 cargo test golden_battles",
+            changed.len(),
+            battles.len(),
+            ENGINE_VERSION,
+            changed.join("\n"),
+        );
+    }
+
+    /*
+    A seeded battle with status effects and the digest of its turn log. Seeded battles must replay bit for bit
+    wherever the engine runs, WASM clients included, so these vectors are shared with the other builds of the
+    engine. Missing expectations are filled in by UPDATE_TEST_VECTORS.
+    */
+    #[derive(Serialize, Deserialize)]
+    struct TestVector {
+        name: String,
+        seed: u64,
+        monster_a: GoldenStats,
+        monster_b: GoldenStats,
+        status_effects: Vec<TestVectorStatusEffect>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expected: Option<TestVectorOutcome>,
+    }
+
+    // The chance is a decimal string, the engine never sees it as a float.
+    #[derive(Serialize, Deserialize)]
+    struct TestVectorStatusEffect {
+        effect: StatusEffect,
+        chance: String,
+        turns: u32,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct TestVectorOutcome {
+        winner: String,
+        turns: u32,
+        // SHA-256 of the JSON turn log.
+        digest: String,
+    }
+
+    fn replay_test_vector(vector: &TestVector) -> TestVectorOutcome {
+        use sha2::{Digest, Sha256};
+
+        let rules = StatusEffectRules(vector.status_effects.iter().map(|status_effect| StatusEffectRule {
+            effect: status_effect.effect,
+            chance: status_effect.chance.parse().unwrap(),
+            turns: status_effect.turns,
+        }).collect());
+        let (monster_a, monster_b) = (new_monster("monster_a", vector.monster_a.into()), new_monster("monster_b", vector.monster_b.into()));
+        let events: Vec<TurnEvent> = BattleTurns::seeded(monster_a, monster_b, vector.seed).with_status_effects(Some(&rules)).collect();
+        let last = events.last().unwrap();
+        let winner = if last.defender_hp == 0 { last.attacker.clone() } else { last.defender.clone() };
+        let digest = Sha256::digest(serde_json::to_vec(&events).unwrap()).iter().map(|byte| format!("{:02x}", byte)).collect();
+        TestVectorOutcome { winner, turns: last.turn, digest }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_replay_the_seeded_test_vectors() {
+        let path = format!("{}/fixtures/test_vectors/seeded_battles.json", env!("CARGO_MANIFEST_DIR"));
+        let mut vectors: Vec<TestVector> = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        if std::env::var("UPDATE_TEST_VECTORS").is_ok() {
+            for vector in vectors.iter_mut() {
+                vector.expected = Some(replay_test_vector(vector));
+            }
+            std::fs::write(&path, serde_json::to_string_pretty(&vectors).unwrap() + "\n").unwrap();
+            return;
+        }
+
+        for vector in &vectors {
+            assert_eq!(vector.expected.as_ref(), Some(&replay_test_vector(vector)), "test vector {} changed", vector.name);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_roll_seeded_damage_between_85_and_100_percent() {
+        let monster_a = new_monster("monster-a", Stats { attack: 120, defense: 20, hp: 1000, speed: 50 });
+        let monster_b = new_monster("monster-b", Stats { attack: 120, defense: 20, hp: 1000, speed: 40 });
+
+        let damages: Vec<i32> = BattleTurns::seeded(monster_a.clone(), monster_b.clone(), 7).map(|turn| turn.damage).collect();
+        let replayed: Vec<i32> = BattleTurns::seeded(monster_a, monster_b, 7).map(|turn| turn.damage).collect();
+
+        assert_eq!(damages, replayed);
+        assert!(damages.iter().all(|damage| (85..=100).contains(damage)));
+        assert!(damages.iter().any(|damage| *damage != 100));
+    }
+
+}
@@ -0,0 +1,159 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+use crate::api::battle_apis::BattleStreamMessage;
+use crate::models::battle::Battle;
+use crate::repository::request_scope::RequestScope;
+
+const BATTLE_EVENTS_CAPACITY: usize = 256;
+const MAX_LOGGED_BATTLES: usize = 256;
+// Long enough for a client to reconnect and resume, battles last a few minutes at most.
+const BATTLE_EVENTS_TTL: Duration = Duration::from_secs(10 * 60);
+const FEED_REPLAY_CAPACITY: usize = 256;
+const FEED_REPLAY_TTL: Duration = Duration::from_secs(2 * 60);
+
+/*
+Turn by turn events of the battles fought over `GET /battles/ws`, published as they happen and kept for the
+last MAX_LOGGED_BATTLES battles, up to BATTLE_EVENTS_TTL after they started, so `GET /battles/{id}/events`
+pollers and resumed sockets can catch up from their cursor.
+*/
+pub static BATTLE_EVENTS: LazyLock<BattleEvents> = LazyLock::new(BattleEvents::new);
+
+// `cursor` numbers the events of a battle from 1.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BattleEvent {
+    pub cursor: usize,
+    #[serde(flatten)]
+    pub message: BattleStreamMessage,
+}
+
+impl BattleEvent {
+    // A battle ends when it finished or failed to be stored.
+    pub fn is_last(&self) -> bool {
+        matches!(self.message, BattleStreamMessage::Finished { .. } | BattleStreamMessage::Error { .. })
+    }
+}
+
+#[derive(Default)]
+struct BattleLog {
+    events: HashMap<String, Vec<BattleEvent>>,
+    // Logged battles with the time of their first event, oldest first.
+    battles: VecDeque<(String, Instant)>,
+}
+
+pub struct BattleEvents {
+    sender: broadcast::Sender<(String, BattleEvent)>,
+    log: Mutex<BattleLog>,
+}
+
+impl BattleEvents {
+    fn new() -> Self {
+        BattleEvents { sender: broadcast::channel(BATTLE_EVENTS_CAPACITY).0, log: Mutex::default() }
+    }
+
+    pub fn publish(&self, battle_id: &str, message: BattleStreamMessage) -> BattleEvent {
+        let mut log = self.log.lock().expect("Battle events lock poisoned");
+        if !log.events.contains_key(battle_id) {
+            while log.battles.len() == MAX_LOGGED_BATTLES || log.battles.front().is_some_and(|(_, started_at)| started_at.elapsed() > BATTLE_EVENTS_TTL) {
+                if let Some((oldest, _)) = log.battles.pop_front() {
+                    log.events.remove(&oldest);
+                }
+            }
+            log.battles.push_back((battle_id.to_string(), Instant::now()));
+        }
+        let events = log.events.entry(battle_id.to_string()).or_default();
+        let event = BattleEvent { cursor: events.len() + 1, message };
+        events.push(event.clone());
+        // Sending only fails when nobody is waiting.
+        let _ = self.sender.send((battle_id.to_string(), event.clone()));
+        event
+    }
+
+    // None when the battle isn't logged, either never streamed or evicted.
+    pub fn events_after(&self, battle_id: &str, after: usize) -> Option<Vec<BattleEvent>> {
+        let log = self.log.lock().expect("Battle events lock poisoned");
+        log.events.get(battle_id).map(|events| events.iter().skip(after).cloned().collect())
+    }
+
+    // Waits up to `wait` for events past `after`, returning as soon as there is at least one.
+    pub async fn wait_for_events(&self, battle_id: &str, after: usize, wait: Duration) -> Option<Vec<BattleEvent>> {
+        // Subscribing first so no event is missed between reading the log and waiting.
+        let mut receiver = self.sender.subscribe();
+        let events = self.events_after(battle_id, after)?;
+        if !events.is_empty() || self.has_ended(battle_id) {
+            return Some(events);
+        }
+
+        let arrived = actix_rt::time::timeout(wait, async {
+            loop {
+                match receiver.recv().await {
+                    Ok((id, _)) if id == battle_id => return,
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(_)) => return,
+                    Err(RecvError::Closed) => std::future::pending().await,
+                }
+            }
+        })
+        .await;
+        match arrived {
+            Ok(()) => self.events_after(battle_id, after),
+            Err(_) => Some(Vec::new()),
+        }
+    }
+
+    pub fn has_ended(&self, battle_id: &str) -> bool {
+        let log = self.log.lock().expect("Battle events lock poisoned");
+        log.events.get(battle_id).and_then(|events| events.last()).is_some_and(BattleEvent::is_last)
+    }
+}
+
+/*
+Battles stored through the APIs, pushed to `GET /battles/stream` subscribers and the webhook dispatcher. The
+last FEED_REPLAY_CAPACITY battles are kept for FEED_REPLAY_TTL, so subscribers reconnecting with the id of the
+last battle they received get the ones they missed.
+*/
+pub struct BattleFeed {
+    sender: broadcast::Sender<FeedBattle>,
+    recent: Mutex<VecDeque<(Instant, FeedBattle)>>,
+}
+
+// A battle of the feed with the database schema it was stored in, None being the default one.
+#[derive(Debug, Clone)]
+pub struct FeedBattle {
+    pub schema: Option<String>,
+    pub battle: Battle,
+}
+
+impl BattleFeed {
+    pub fn new(capacity: usize) -> Self {
+        BattleFeed { sender: broadcast::channel(capacity).0, recent: Mutex::default() }
+    }
+
+    // Published in the schema of the current request.
+    pub fn publish(&self, battle: Battle) {
+        let battle = FeedBattle { schema: RequestScope::current().schema, battle };
+        let mut recent = self.recent.lock().expect("Battle feed lock poisoned");
+        while recent.len() == FEED_REPLAY_CAPACITY || recent.front().is_some_and(|(published_at, _)| published_at.elapsed() > FEED_REPLAY_TTL) {
+            recent.pop_front();
+        }
+        recent.push_back((Instant::now(), battle.clone()));
+        // Sending only fails when nobody is subscribed.
+        let _ = self.sender.send(battle);
+    }
+
+    // Every battle of every schema, subscribers only passing on the ones of theirs.
+    pub fn subscribe(&self) -> broadcast::Receiver<FeedBattle> {
+        self.sender.subscribe()
+    }
+
+    // The battles of `schema` published after the given one, None when it isn't buffered anymore.
+    pub fn battles_after(&self, schema: &Option<String>, battle_id: &str) -> Option<Vec<Battle>> {
+        let recent = self.recent.lock().expect("Battle feed lock poisoned");
+        let position = recent
+            .iter()
+            .position(|(published_at, published)| published.battle.id == battle_id && published.schema == *schema && published_at.elapsed() <= FEED_REPLAY_TTL)?;
+        Some(recent.iter().skip(position + 1).filter(|(_, published)| published.schema == *schema).map(|(_, published)| published.battle.clone()).collect())
+    }
+}
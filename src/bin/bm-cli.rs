@@ -0,0 +1,212 @@
+use std::path::Path;
+use std::process::ExitCode;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use assessment_cc_rust_sr_01::balance;
+use assessment_cc_rust_sr_01::battle_engine::{self, BattleSetup};
+use assessment_cc_rust_sr_01::config::AppConfig;
+use assessment_cc_rust_sr_01::import::{collect_monsters, CsvOptions, ImportFormat, IMPORT_CHUNK_ROWS};
+use assessment_cc_rust_sr_01::models::monster::{ImportMode, ImportOutcome, Monster, Stats};
+use assessment_cc_rust_sr_01::models::status_effect::StatusEffectRules;
+use assessment_cc_rust_sr_01::repository::battle_repository::BattleRepository;
+use assessment_cc_rust_sr_01::repository::database::Database;
+use assessment_cc_rust_sr_01::repository::item_repository::ItemRepository;
+use assessment_cc_rust_sr_01::repository::monster_repository::MonsterRepository;
+use assessment_cc_rust_sr_01::repository::move_repository::MoveRepository;
+use assessment_cc_rust_sr_01::repository::training_repository::TrainingRepository;
+
+const USAGE: &str = "Usage: bm-cli <command>
+
+Commands:
+  import <file> [--mode create|upsert|skip_duplicates]
+              Imports the monsters of a CSV file, or of a JSON array or NDJSON file, and prints the counts.
+  export [--battles]
+              Prints all the monsters, or all the battles, as a JSON array that `import` reads back.
+  simulate <id_a> <id_b> [--n 1000]
+              Plays the two monsters against each other n times without storing the battles and prints the wins.
+  seed [--count 50]
+              Creates monsters with random names and stats.
+
+Every command works on the database at DATABASE_URL, read like the server reads it: from the environment,
+then from CONFIG_FILE or config.toml.";
+
+const DEFAULT_SIMULATIONS: usize = 1000;
+const DEFAULT_SEED_COUNT: usize = 50;
+const SEED_IMAGE_URL: &str = "https://loremflickr.com/640/480";
+const SEED_ADJECTIVES: &[&str] = &["Ancient", "Blazing", "Frozen", "Shadow", "Thunder", "Venom", "Iron", "Crystal"];
+const SEED_CREATURES: &[&str] = &["Drake", "Golem", "Wyrm", "Sprite", "Hydra", "Basilisk", "Phoenix", "Kraken"];
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Import { file: String, mode: ImportMode },
+    Export { battles: bool },
+    Simulate { monster_a: String, monster_b: String, n: usize },
+    Seed { count: usize },
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match parse_args(&args.iter().map(String::as_str).collect::<Vec<_>>()) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let config = match AppConfig::load() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::from(2);
+        }
+    };
+    let db = match Database::from_config(&config) {
+        Ok(db) => db,
+        Err(err) => {
+            eprintln!("Failed to connect to the database: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = match command {
+        Command::Import { file, mode } => run_import(&db, Path::new(&file), mode),
+        Command::Export { battles } => run_export(&db, battles),
+        Command::Simulate { monster_a, monster_b, n } => run_simulate(&db, &config, &monster_a, &monster_b, n),
+        Command::Seed { count } => run_seed(&db, count),
+    };
+    match result {
+        Ok(output) => {
+            println!("{}", serde_json::to_string_pretty(&output).expect("Results serialize"));
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: &[&str]) -> Result<Command, String> {
+    match args {
+        ["import", file] => Ok(Command::Import { file: file.to_string(), mode: ImportMode::Create }),
+        ["import", file, "--mode", mode] => {
+            let mode = serde_json::from_value(serde_json::Value::String(mode.to_string())).map_err(|_| format!("Unknown import mode {:?}", mode))?;
+            Ok(Command::Import { file: file.to_string(), mode })
+        }
+        ["export"] => Ok(Command::Export { battles: false }),
+        ["export", "--battles"] => Ok(Command::Export { battles: true }),
+        ["simulate", monster_a, monster_b] => Ok(Command::Simulate { monster_a: monster_a.to_string(), monster_b: monster_b.to_string(), n: DEFAULT_SIMULATIONS }),
+        ["simulate", monster_a, monster_b, "--n", n] => Ok(Command::Simulate { monster_a: monster_a.to_string(), monster_b: monster_b.to_string(), n: positive("--n", n)? }),
+        ["seed"] => Ok(Command::Seed { count: DEFAULT_SEED_COUNT }),
+        ["seed", "--count", count] => Ok(Command::Seed { count: positive("--count", count)? }),
+        _ => Err("Invalid arguments".to_string()),
+    }
+}
+
+fn positive(name: &str, value: &str) -> Result<usize, String> {
+    value.parse().ok().filter(|value| *value > 0).ok_or_else(|| format!("{} must be a positive integer, got {:?}", name, value))
+}
+
+// CSV files are told apart by their extension, anything else is read as JSON.
+fn run_import(db: &Database, file: &Path, mode: ImportMode) -> Result<serde_json::Value, String> {
+    let format = match file.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) if extension.eq_ignore_ascii_case("csv") => ImportFormat::Csv(CsvOptions::parse(None, None)?),
+        _ => ImportFormat::Json,
+    };
+    let monsters = collect_monsters(format.rows(file)?.collect(), &format, None)?;
+    let (mut created, mut updated, mut skipped, mut failed) = (0, 0, 0, 0);
+    for chunk in monsters.chunks(IMPORT_CHUNK_ROWS) {
+        for outcome in db.import_monsters(chunk.to_vec(), mode).map_err(|err| err.to_string())? {
+            match outcome {
+                Ok(ImportOutcome::Created(_)) => created += 1,
+                Ok(ImportOutcome::Updated(_)) => updated += 1,
+                Ok(ImportOutcome::Skipped) => skipped += 1,
+                Err(err) => {
+                    eprintln!("Failed to import a monster: {}", err);
+                    failed += 1;
+                }
+            }
+        }
+    }
+    Ok(serde_json::json!({ "imported": created + updated, "created": created, "updated": updated, "skipped": skipped, "failed": failed }))
+}
+
+fn run_export(db: &Database, battles: bool) -> Result<serde_json::Value, String> {
+    let exported = if battles { serde_json::to_value(db.get_battles()) } else { serde_json::to_value(db.get_monsters()) };
+    exported.map_err(|err| err.to_string())
+}
+
+/*
+Plays the battles the way the API does, with the moves, items and training of the monsters and the balance
+and status effects rules of the configuration, without storing them or leveling the monsters up.
+*/
+fn run_simulate(db: &Database, config: &AppConfig, monster_a_id: &str, monster_b_id: &str, n: usize) -> Result<serde_json::Value, String> {
+    let find = |id: &str| db.get_monster_by_id(id).ok_or_else(|| format!("Monster {} not found", id));
+    let (monster_a, monster_b) = (find(monster_a_id)?, find(monster_b_id)?);
+    balance::BALANCE.load_from_config(config)?;
+    let engine = battle_engine::from_config(config)?;
+    let status_effects = StatusEffectRules::from_config(config)?;
+    let moves = (db.get_monster_moves(&monster_a.id), db.get_monster_moves(&monster_b.id));
+    let items = (db.get_monster_items(&monster_a.id), db.get_monster_items(&monster_b.id));
+    let hidden_stats = |monster: &Monster| db.get_training(&monster.id).map(|training| training.hidden_stats(&balance::current())).unwrap_or_default();
+    let hidden_stats = (hidden_stats(&monster_a), hidden_stats(&monster_b));
+
+    let (mut wins_a, mut wins_b, mut draws) = (0, 0, 0);
+    for _ in 0..n {
+        let setup = BattleSetup {
+            moves: moves.clone(),
+            items: items.clone(),
+            hidden_stats,
+            status_effects: status_effects.as_ref(),
+            ..BattleSetup::new(monster_a.clone(), monster_b.clone())
+        };
+        match engine.simulate(setup).winner {
+            Some(winner) if winner == monster_a.id => wins_a += 1,
+            Some(_) => wins_b += 1,
+            None => draws += 1,
+        }
+    }
+    Ok(serde_json::json!({
+        "battles": n,
+        "monster_a": { "id": monster_a.id, "name": monster_a.name, "wins": wins_a, "win_rate": wins_a as f64 / n as f64 },
+        "monster_b": { "id": monster_b.id, "name": monster_b.name, "wins": wins_b, "win_rate": wins_b as f64 / n as f64 },
+        "draws": draws,
+    }))
+}
+
+fn run_seed(db: &Database, count: usize) -> Result<serde_json::Value, String> {
+    let mut rng = rand::thread_rng();
+    let monsters = (0..count)
+        .map(|_| {
+            let name = format!("{} {} {}", SEED_ADJECTIVES.choose(&mut rng).unwrap(), SEED_CREATURES.choose(&mut rng).unwrap(), rng.gen_range(1..=999));
+            let stats = Stats { attack: rng.gen_range(10..=100), defense: rng.gen_range(10..=100), hp: rng.gen_range(50..=150), speed: rng.gen_range(10..=100) };
+            serde_json::from_value::<Monster>(serde_json::json!({
+                "name": name,
+                "image_url": SEED_IMAGE_URL,
+                "attack": stats.attack,
+                "defense": stats.defense,
+                "hp": stats.hp,
+                "speed": stats.speed,
+            }))
+            .expect("Seeded monsters deserialize")
+        })
+        .collect();
+    let created: Vec<Monster> = db.create_monsters(monsters).map_err(|err| err.to_string())?.into_iter().filter_map(Result::ok).collect();
+    Ok(serde_json::json!({ "seeded": created.len(), "ids": created.iter().map(|monster| &monster.id).collect::<Vec<_>>() }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_parse_the_commands_and_their_options() {
+        assert_eq!(parse_args(&["import", "monsters.csv", "--mode", "skip_duplicates"]), Ok(Command::Import { file: "monsters.csv".to_string(), mode: ImportMode::SkipDuplicates }));
+        assert_eq!(parse_args(&["export", "--battles"]), Ok(Command::Export { battles: true }));
+        assert_eq!(parse_args(&["simulate", "a", "b"]), Ok(Command::Simulate { monster_a: "a".to_string(), monster_b: "b".to_string(), n: DEFAULT_SIMULATIONS }));
+        assert_eq!(parse_args(&["seed", "--count", "5"]), Ok(Command::Seed { count: 5 }));
+        assert!(parse_args(&["simulate", "a", "b", "--n", "0"]).is_err());
+        assert!(parse_args(&["import", "monsters.csv", "--mode", "merge"]).is_err());
+        assert!(parse_args(&[]).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+use std::process::ExitCode;
+use assessment_cc_rust_sr_01::config::AppConfig;
+use assessment_cc_rust_sr_01::repository::database::Database;
+use assessment_cc_rust_sr_01::validation;
+
+const USAGE: &str = "Usage: bmctl validate [--fix]
+
+Commands:
+  validate    Scans the database at DATABASE_URL and prints a JSON report of the problems found.
+              Exits with 1 when problems are left. --fix applies the fixes marked as fixable.";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["validate"] => run_validate(false),
+        ["validate", "--fix"] => run_validate(true),
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run_validate(fix: bool) -> ExitCode {
+    let db = match AppConfig::load().and_then(|config| Database::from_config(&config).map_err(|err| err.to_string())) {
+        Ok(db) => db,
+        Err(err) => {
+            eprintln!("Failed to connect to the database: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+    let report = match validation::validate(&mut db.get_connection(), fix) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("Failed to validate the database: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+
+    println!("{}", serde_json::to_string_pretty(&report).expect("Reports serialize"));
+    if report.remaining() > 0 { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
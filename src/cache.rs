@@ -0,0 +1,294 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::config::AppConfig;
+use crate::metrics::METRICS;
+
+/*
+A key-value cache for the reads hit on every request, like monster lists and the leaderboard. Values are JSON
+bytes. A failing cache must never fail a request, callers log its errors and go to the database as on a miss.
+*/
+pub trait Cache: Send + Sync {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String>;
+    fn set(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), String>;
+    fn delete(&self, keys: &[String]) -> Result<(), String>;
+}
+
+const DEFAULT_MEMORY_CACHE_TTL_SECONDS: u64 = 60;
+const DEFAULT_MEMORY_CACHE_CAPACITY: usize = 10_000;
+
+/*
+The cache picked by CACHE: none by default, `memory` for one in the instance itself, holding up to CACHE_CAPACITY
+values for CACHE_TTL_SECONDS at most, or `redis` at REDIS_URL, which requires building with the `redis-cache` feature.
+*/
+pub fn from_config(config: &AppConfig) -> Result<Option<Arc<dyn Cache>>, String> {
+    from_vars(|name| config.var(name))
+}
+
+fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Option<Arc<dyn Cache>>, String> {
+    match var("CACHE").as_deref().map(str::trim) {
+        None | Some("") | Some("none") => Ok(None),
+        Some("memory") => {
+            let parse = |name: &str| var(name).map(|value| value.trim().parse::<u64>().map_err(|_| format!("{} must be a positive integer, got {:?}", name, value))).transpose();
+            let ttl = parse("CACHE_TTL_SECONDS")?.unwrap_or(DEFAULT_MEMORY_CACHE_TTL_SECONDS);
+            let capacity = parse("CACHE_CAPACITY")?.map_or(DEFAULT_MEMORY_CACHE_CAPACITY, |capacity| capacity as usize);
+            if ttl == 0 || capacity == 0 {
+                return Err("CACHE_TTL_SECONDS and CACHE_CAPACITY must be greater than 0".to_string());
+            }
+            Ok(Some(Arc::new(MemoryCache::new(Duration::from_secs(ttl), capacity))))
+        }
+        Some("redis") => {
+            let url = var("REDIS_URL").filter(|url| !url.trim().is_empty()).ok_or("REDIS_URL is required with CACHE=redis")?;
+            redis_cache(&url)
+        }
+        Some(other) => Err(format!("Unknown cache {:?}, expected none, memory or redis", other)),
+    }
+}
+
+#[cfg(feature = "redis-cache")]
+fn redis_cache(url: &str) -> Result<Option<Arc<dyn Cache>>, String> {
+    Ok(Some(Arc::new(RedisCache::new(url)?)))
+}
+
+#[cfg(not(feature = "redis-cache"))]
+fn redis_cache(_url: &str) -> Result<Option<Arc<dyn Cache>>, String> {
+    Err("CACHE=redis requires building with the redis-cache feature".to_string())
+}
+
+/*
+The value cached under `key`, or else the one `load` returns, cached for `ttl`. Lookups are counted by `name`
+in the cache metrics, values the cache can't read back are loaded again.
+*/
+pub fn get_or_load<T: Serialize + DeserializeOwned>(cache: Option<&dyn Cache>, name: &str, key: &str, ttl: Duration, load: impl FnOnce() -> T) -> T {
+    let Some(cache) = cache else {
+        return load();
+    };
+    match cache.get(key) {
+        Ok(Some(cached)) => match serde_json::from_slice(&cached) {
+            Ok(value) => {
+                METRICS.observe_cache_lookup(name, true);
+                return value;
+            }
+            Err(err) => tracing::warn!(key, error = %err, "Failed to read cached value"),
+        },
+        Ok(None) => {}
+        Err(err) => tracing::warn!(key, error = %err, "Failed to read from the cache"),
+    }
+    METRICS.observe_cache_lookup(name, false);
+    let value = load();
+    let stored = serde_json::to_vec(&value).map_err(|err| err.to_string()).and_then(|bytes| cache.set(key, &bytes, ttl));
+    if let Err(err) = stored {
+        tracing::warn!(key, error = %err, "Failed to write to the cache");
+    }
+    value
+}
+
+// Drops the values of `keys`, for the next reads to load them again after a write.
+pub fn invalidate(cache: Option<&dyn Cache>, keys: &[String]) {
+    if let Some(Err(err)) = cache.map(|cache| cache.delete(keys)) {
+        tracing::warn!(keys = ?keys, error = %err, "Failed to invalidate cached values");
+    }
+}
+
+/*
+A cache in the memory of the instance, for deployments without Redis. Values live `ttl` at most, whatever the
+callers ask for, and past `capacity` values the least recently used one is evicted. Writes only invalidate the
+values of the instance they reach, so with several instances the others serve theirs until they expire.
+*/
+pub struct MemoryCache {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<MemoryEntries>,
+}
+
+#[derive(Default)]
+struct MemoryEntries {
+    values: HashMap<String, MemoryEntry>,
+    // Keys by the tick they were last used at, the least recently used first.
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+struct MemoryEntry {
+    value: Vec<u8>,
+    expires_at: Instant,
+    used_at: u64,
+}
+
+impl MemoryEntries {
+    fn remove(&mut self, key: &str) -> Option<MemoryEntry> {
+        let entry = self.values.remove(key)?;
+        self.recency.remove(&entry.used_at);
+        Some(entry)
+    }
+
+    fn touch(&mut self, key: &str) -> u64 {
+        self.tick += 1;
+        self.recency.insert(self.tick, key.to_string());
+        self.tick
+    }
+}
+
+impl MemoryCache {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        MemoryCache { ttl, capacity, entries: Mutex::default() }
+    }
+}
+
+impl Cache for MemoryCache {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let mut entries = self.entries.lock().expect("Memory cache lock poisoned");
+        let Some(entry) = entries.remove(key) else {
+            return Ok(None);
+        };
+        if entry.expires_at <= Instant::now() {
+            return Ok(None);
+        }
+        let used_at = entries.touch(key);
+        let value = entry.value.clone();
+        entries.values.insert(key.to_string(), MemoryEntry { used_at, ..entry });
+        Ok(Some(value))
+    }
+
+    fn set(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), String> {
+        let mut entries = self.entries.lock().expect("Memory cache lock poisoned");
+        entries.remove(key);
+        while entries.values.len() >= self.capacity {
+            let Some((_, oldest)) = entries.recency.pop_first() else { break };
+            entries.values.remove(&oldest);
+        }
+        let used_at = entries.touch(key);
+        let expires_at = Instant::now() + ttl.min(self.ttl);
+        entries.values.insert(key.to_string(), MemoryEntry { value: value.to_vec(), expires_at, used_at });
+        Ok(())
+    }
+
+    fn delete(&self, keys: &[String]) -> Result<(), String> {
+        let mut entries = self.entries.lock().expect("Memory cache lock poisoned");
+        keys.iter().for_each(|key| { entries.remove(key); });
+        Ok(())
+    }
+}
+
+#[cfg(feature = "redis-cache")]
+pub use self::redis_cache::RedisCache;
+
+#[cfg(feature = "redis-cache")]
+mod redis_cache {
+    use std::time::Duration;
+    use diesel::r2d2::{Pool, PooledConnection};
+    use redis::Commands;
+    use super::Cache;
+
+    const POOL_SIZE: u32 = 8;
+    // Requests fall back to the database rather than waiting long on a cache that's down.
+    const CONNECTION_TIMEOUT: Duration = Duration::from_millis(250);
+
+    pub struct RedisCache {
+        pool: Pool<redis::Client>,
+    }
+
+    impl RedisCache {
+        // Connections are opened lazily, so the server starts while Redis is down.
+        pub fn new(url: &str) -> Result<Self, String> {
+            let client = redis::Client::open(url).map_err(|err| format!("Invalid REDIS_URL: {}", err))?;
+            let pool = Pool::builder()
+                .max_size(POOL_SIZE)
+                .min_idle(Some(0))
+                .connection_timeout(CONNECTION_TIMEOUT)
+                .build_unchecked(client);
+            Ok(RedisCache { pool })
+        }
+
+        fn connection(&self) -> Result<PooledConnection<redis::Client>, String> {
+            self.pool.get().map_err(|err| err.to_string())
+        }
+    }
+
+    impl Cache for RedisCache {
+        fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+            self.connection()?.get::<_, Option<Vec<u8>>>(key).map_err(|err| err.to_string())
+        }
+
+        fn set(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), String> {
+            self.connection()?.set_ex::<_, _, ()>(key, value, ttl.as_secs().max(1)).map_err(|err| err.to_string())
+        }
+
+        fn delete(&self, keys: &[String]) -> Result<(), String> {
+            if keys.is_empty() {
+                return Ok(());
+            }
+            self.connection()?.del::<_, ()>(keys).map_err(|err| err.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MapCache(Mutex<HashMap<String, Vec<u8>>>);
+
+    impl Cache for MapCache {
+        fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+            Ok(self.0.lock().unwrap().get(key).cloned())
+        }
+
+        fn set(&self, key: &str, value: &[u8], _ttl: Duration) -> Result<(), String> {
+            self.0.lock().unwrap().insert(key.to_string(), value.to_vec());
+            Ok(())
+        }
+
+        fn delete(&self, keys: &[String]) -> Result<(), String> {
+            let mut values = self.0.lock().unwrap();
+            keys.iter().for_each(|key| { values.remove(key); });
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_should_load_values_missing_from_the_cache_once() {
+        let cache = MapCache::default();
+        let loads = Mutex::new(0);
+        let load = || {
+            *loads.lock().unwrap() += 1;
+            vec!["fire drake".to_string()]
+        };
+
+        assert_eq!(get_or_load(Some(&cache), "test", "names", Duration::from_secs(30), load), vec!["fire drake".to_string()]);
+        assert_eq!(get_or_load(Some(&cache), "test", "names", Duration::from_secs(30), load), vec!["fire drake".to_string()]);
+        assert_eq!(*loads.lock().unwrap(), 1);
+        invalidate(Some(&cache), &["names".to_string()]);
+        get_or_load(Some(&cache), "test", "names", Duration::from_secs(30), load);
+        assert_eq!(*loads.lock().unwrap(), 2);
+        assert!(from_vars(|name| (name == "CACHE").then(|| "memcached".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_should_evict_the_least_recently_used_and_expired_values_from_memory() {
+        let cache = MemoryCache::new(Duration::from_secs(60), 2);
+        let ttl = Duration::from_secs(30);
+        cache.set("a", b"1", ttl).unwrap();
+        cache.set("b", b"2", ttl).unwrap();
+        assert_eq!(cache.get("a").unwrap(), Some(b"1".to_vec()));
+        cache.set("c", b"3", ttl).unwrap();
+        assert_eq!(cache.get("b").unwrap(), None);
+        assert_eq!(cache.get("a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(cache.get("c").unwrap(), Some(b"3".to_vec()));
+
+        cache.set("a", b"4", Duration::ZERO).unwrap();
+        assert_eq!(cache.get("a").unwrap(), None);
+        let vars = |name: &str| match name {
+            "CACHE" => Some("memory".to_string()),
+            "CACHE_CAPACITY" => Some("0".to_string()),
+            _ => None,
+        };
+        assert!(from_vars(vars).is_err());
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::Mutex;
+use std::time::Duration;
+use actix_web::http::{header, Uri};
+use actix_web::web::{self, Bytes};
+use image::{imageops::FilterType, DynamicImage, ImageReader, Limits};
+use tiny_skia::{Color, ColorU8, FillRule, IntSize, Paint, PathBuilder, Pixmap, PixmapPaint, Rect, Transform};
+use crate::api::config::API_PREFIX;
+use crate::api::image_apis::image_key;
+use crate::models::monster::{Monster, Stats};
+use crate::storage::Storage;
+
+pub const CARD_WIDTH: u32 = 400;
+pub const CARD_HEIGHT: u32 = 560;
+const MARGIN: f32 = 24.0;
+const SPRITE_TOP: f32 = 88.0;
+const SPRITE_HEIGHT: u32 = 240;
+const BAR_MAX_STAT: i32 = 100;
+const MAX_NAME_CHARS: usize = 16;
+const SPRITE_TIMEOUT: Duration = Duration::from_secs(3);
+const MAX_SPRITE_BYTES: usize = 2 * 1024 * 1024;
+const MAX_SPRITE_DIMENSION: u32 = 4096;
+const MAX_IMAGE_REDIRECTS: usize = 5;
+const MAX_CACHED_CARDS: usize = 256;
+
+/*
+Rendered cards of the last MAX_CACHED_CARDS monsters, a card is rendered again once its monster was updated.
+*/
+#[derive(Default)]
+pub struct CardCache {
+    cards: Mutex<CachedCards>,
+}
+
+#[derive(Default)]
+struct CachedCards {
+    cards: HashMap<String, (Option<chrono::NaiveDateTime>, Bytes)>,
+    monsters: VecDeque<String>,
+}
+
+impl CardCache {
+    pub fn new() -> Self {
+        CardCache::default()
+    }
+
+    pub fn get(&self, monster: &Monster) -> Option<Bytes> {
+        let cached = self.cards.lock().expect("Card cache lock poisoned");
+        cached
+            .cards
+            .get(&monster.id)
+            .filter(|(updated_at, _)| *updated_at == monster.updated_at)
+            .map(|(_, card)| card.clone())
+    }
+
+    pub fn set(&self, monster: &Monster, card: Bytes) {
+        let mut cached = self.cards.lock().expect("Card cache lock poisoned");
+        if !cached.cards.contains_key(&monster.id) {
+            if cached.monsters.len() == MAX_CACHED_CARDS {
+                if let Some(oldest) = cached.monsters.pop_front() {
+                    cached.cards.remove(&oldest);
+                }
+            }
+            cached.monsters.push_back(monster.id.clone());
+        }
+        cached.cards.insert(monster.id.clone(), (monster.updated_at, card));
+    }
+}
+
+// Grade of a monster from its power score, shown as the badge of its card.
+pub fn tier(stats: &Stats) -> char {
+    match stats.power_score() {
+        score if score >= 400 => 'S',
+        score if score >= 320 => 'A',
+        score if score >= 240 => 'B',
+        score if score >= 160 => 'C',
+        _ => 'D',
+    }
+}
+
+// Loads and decodes the monster's image, None when it can't be loaded or isn't a PNG or JPEG.
+pub async fn fetch_sprite(monster: &Monster, storage: Option<&dyn Storage>) -> Option<DynamicImage> {
+    load_monster_image(monster, storage).await.ok()
+}
+
+/*
+Like `fetch_sprite`, with the reason the image couldn't be used. An image uploaded to `GET /monsters/{id}/image`
+is read from the storage, the others are downloaded with `fetch_image`.
+*/
+pub async fn load_monster_image(monster: &Monster, storage: Option<&dyn Storage>) -> Result<DynamicImage, String> {
+    let uploaded_path = format!("{}/monsters/{}/image", API_PREFIX, monster.id);
+    let uploaded = monster.image_url.parse::<Uri>().is_ok_and(|image_url| image_url.path() == uploaded_path);
+    match storage {
+        Some(storage) if uploaded => match storage.get(&image_key(&monster.id)).await? {
+            Some(body) => decode_image(body),
+            None => Err("The uploaded image is missing".to_string()),
+        },
+        _ => fetch_image(&monster.image_url).await,
+    }
+}
+
+/*
+Downloads an image, following up to MAX_IMAGE_REDIRECTS redirects. Every host is resolved first and refused when
+it has an internal address, like loopback, private or link-local ones, so image URLs can't reach the services
+around the server. Plain HTTP requests are sent to the address that was checked, so the host can't resolve to
+another one in between.
+*/
+pub async fn fetch_image(image_url: &str) -> Result<DynamicImage, String> {
+    let client = awc::Client::builder().timeout(SPRITE_TIMEOUT).disable_redirects().finish();
+    let mut url: Uri = image_url.parse().map_err(|_| format!("Not a valid image URL: {}", image_url))?;
+    let mut redirects = 0;
+    let mut response = loop {
+        let address = public_address(&url).await?;
+        let request = match (url.scheme_str(), url.authority()) {
+            (Some("http"), Some(authority)) => {
+                let path = url.path_and_query().map_or("/", |path| path.as_str());
+                let pinned = Uri::builder().scheme("http").authority(address.to_string()).path_and_query(path).build().map_err(|err| err.to_string())?;
+                client.get(pinned).insert_header((header::HOST, authority.as_str()))
+            }
+            _ => client.get(url.clone()),
+        };
+        let response = request.send().await.map_err(|err| format!("Can't download the image: {}", err))?;
+        if !response.status().is_redirection() {
+            break response;
+        }
+        if redirects == MAX_IMAGE_REDIRECTS {
+            return Err(format!("The image redirected more than {} times", MAX_IMAGE_REDIRECTS));
+        }
+        redirects += 1;
+        let location = response.headers().get(header::LOCATION).and_then(|location| location.to_str().ok()).ok_or("The image redirected without a location")?;
+        url = redirect_url(&url, location)?;
+    };
+    if !response.status().is_success() {
+        return Err(format!("The image answered {}", response.status()));
+    }
+    let body = response.body().limit(MAX_SPRITE_BYTES).await.map_err(|err| format!("Can't download the image: {}", err))?;
+    decode_image(body)
+}
+
+fn decode_image(body: Bytes) -> Result<DynamicImage, String> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SPRITE_DIMENSION);
+    limits.max_image_height = Some(MAX_SPRITE_DIMENSION);
+    let mut reader = ImageReader::new(std::io::Cursor::new(body)).with_guessed_format().map_err(|err| err.to_string())?;
+    reader.limits(limits);
+    reader.decode().map_err(|err| format!("Not a PNG or JPEG image: {}", err))
+}
+
+// The address to download from, when every address of the host is public.
+async fn public_address(url: &Uri) -> Result<SocketAddr, String> {
+    let port = match url.scheme_str() {
+        Some("http") => url.port_u16().unwrap_or(80),
+        Some("https") => url.port_u16().unwrap_or(443),
+        _ => return Err("Images must be downloaded over HTTP or HTTPS".to_string()),
+    };
+    let host = url.host().ok_or("The image URL has no host")?.trim_start_matches('[').trim_end_matches(']').to_string();
+    let addresses: Vec<SocketAddr> = web::block(move || (host.as_str(), port).to_socket_addrs().map(Iterator::collect))
+        .await
+        .map_err(|err| err.to_string())?
+        .map_err(|err| format!("Can't resolve the image host: {}", err))?;
+    if let Some(internal) = addresses.iter().find(|address| !is_public(address.ip())) {
+        return Err(format!("The image host has the internal address {}", internal.ip()));
+    }
+    addresses.first().copied().ok_or_else(|| "The image host has no address".to_string())
+}
+
+// Loopback, private, link-local, shared (CGNAT), unspecified, broadcast, multicast and documentation addresses aren't.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            !(ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast() || ip.is_multicast() || ip.is_documentation() || first == 0 || (first == 100 && (64..128).contains(&second)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            // Unique local (fc00::/7) and link-local (fe80::/10) addresses are the private ones of IPv6.
+            None => !(ip.is_loopback() || ip.is_unspecified() || ip.is_multicast() || (ip.segments()[0] & 0xfe00) == 0xfc00 || (ip.segments()[0] & 0xffc0) == 0xfe80),
+        },
+    }
+}
+
+// Where a redirect leads, its location being absolute or relative to the host.
+fn redirect_url(url: &Uri, location: &str) -> Result<Uri, String> {
+    let invalid = || format!("The image redirected to an invalid location: {}", location);
+    let location: Uri = location.parse().map_err(|_| invalid())?;
+    if location.scheme().is_some() {
+        return Ok(location);
+    }
+    let (Some(scheme), Some(authority), Some(path)) = (url.scheme(), url.authority(), location.path_and_query()) else {
+        return Err(invalid());
+    };
+    Uri::builder().scheme(scheme.clone()).authority(authority.clone()).path_and_query(path.clone()).build().map_err(|_| invalid())
+}
+
+/*
+Draws the card of a monster as a PNG: its name, the tier badge, the sprite (a placeholder without one)
+and a bar per stat, scaled up to BAR_MAX_STAT.
+*/
+pub fn render_card(monster: &Monster, sprite: Option<&DynamicImage>) -> Vec<u8> {
+    let mut card = Pixmap::new(CARD_WIDTH, CARD_HEIGHT).expect("Cards have a valid size");
+    let width = CARD_WIDTH as f32;
+    let tier = tier(&monster.stats);
+    let tier_color = tier_color(tier);
+
+    card.fill(tier_color);
+    fill_rect(&mut card, 8.0, 8.0, width - 16.0, CARD_HEIGHT as f32 - 16.0, Color::from_rgba8(250, 246, 236, 255));
+
+    let name: String = monster.name.to_uppercase().chars().take(MAX_NAME_CHARS).collect();
+    draw_text(&mut card, &name, MARGIN, 36.0, 3.0, Color::from_rgba8(40, 40, 48, 255));
+
+    let mut badge = PathBuilder::new();
+    badge.push_circle(width - MARGIN - 24.0, 48.0, 24.0);
+    let badge = badge.finish().expect("The badge is a valid circle");
+    card.fill_path(&badge, &paint(tier_color), FillRule::Winding, Transform::identity(), None);
+    draw_text(&mut card, &tier.to_string(), width - MARGIN - 24.0 - 7.5, 37.5, 3.0, Color::WHITE);
+
+    let sprite_width = CARD_WIDTH - 2 * MARGIN as u32;
+    fill_rect(&mut card, MARGIN, SPRITE_TOP, sprite_width as f32, SPRITE_HEIGHT as f32, Color::from_rgba8(210, 214, 222, 255));
+    match sprite.and_then(|sprite| sprite_pixmap(sprite, sprite_width, SPRITE_HEIGHT)) {
+        Some(sprite) => card.draw_pixmap(MARGIN as i32, SPRITE_TOP as i32, sprite.as_ref(), &PixmapPaint::default(), Transform::identity(), None),
+        None => draw_text(&mut card, "?", width / 2.0 - 20.0, SPRITE_TOP + SPRITE_HEIGHT as f32 / 2.0 - 28.0, 8.0, Color::from_rgba8(150, 156, 168, 255)),
+    }
+
+    let stats = [("ATK", monster.stats.attack), ("DEF", monster.stats.defense), ("HP", monster.stats.hp), ("SPD", monster.stats.speed)];
+    let bar_left = MARGIN + 64.0;
+    let bar_width = width - bar_left - MARGIN - 56.0;
+    for (index, (label, value)) in stats.into_iter().enumerate() {
+        let top = SPRITE_TOP + SPRITE_HEIGHT as f32 + 32.0 + index as f32 * 44.0;
+        draw_text(&mut card, label, MARGIN, top + 3.0, 2.0, Color::from_rgba8(40, 40, 48, 255));
+        fill_rect(&mut card, bar_left, top, bar_width, 20.0, Color::from_rgba8(210, 214, 222, 255));
+        let filled = bar_width * value.clamp(0, BAR_MAX_STAT) as f32 / BAR_MAX_STAT as f32;
+        if filled > 0.0 {
+            fill_rect(&mut card, bar_left, top, filled, 20.0, tier_color);
+        }
+        draw_text(&mut card, &value.to_string(), bar_left + bar_width + 12.0, top + 3.0, 2.0, Color::from_rgba8(40, 40, 48, 255));
+    }
+
+    card.encode_png().expect("Cards encode as PNG")
+}
+
+fn tier_color(tier: char) -> Color {
+    match tier {
+        'S' => Color::from_rgba8(214, 158, 46, 255),
+        'A' => Color::from_rgba8(196, 64, 64, 255),
+        'B' => Color::from_rgba8(120, 72, 180, 255),
+        'C' => Color::from_rgba8(52, 120, 196, 255),
+        _ => Color::from_rgba8(110, 116, 128, 255),
+    }
+}
+
+fn paint(color: Color) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color(color);
+    paint
+}
+
+fn fill_rect(card: &mut Pixmap, x: f32, y: f32, width: f32, height: f32, color: Color) {
+    if let Some(rect) = Rect::from_xywh(x, y, width, height) {
+        card.fill_rect(rect, &paint(color), Transform::identity(), None);
+    }
+}
+
+// Covers the sprite box with the image, cropping what overflows.
+fn sprite_pixmap(sprite: &DynamicImage, width: u32, height: u32) -> Option<Pixmap> {
+    let sprite = sprite.resize_to_fill(width, height, FilterType::Triangle).into_rgba8();
+    let pixels = sprite
+        .pixels()
+        .flat_map(|pixel| {
+            let color = ColorU8::from_rgba(pixel[0], pixel[1], pixel[2], pixel[3]).premultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    Pixmap::from_vec(pixels, IntSize::from_wh(width, height)?)
+}
+
+// Draws text with the 5x7 font below, each font pixel being a `scale` sized square.
+fn draw_text(card: &mut Pixmap, text: &str, x: f32, y: f32, scale: f32, color: Color) {
+    for (index, character) in text.chars().enumerate() {
+        let left = x + index as f32 * 6.0 * scale;
+        for (row, bits) in glyph(character).iter().enumerate() {
+            for column in 0..5 {
+                if bits & (0b10000 >> column) != 0 {
+                    fill_rect(card, left + column as f32 * scale, y + row as f32 * scale, scale, scale, color);
+                }
+            }
+        }
+    }
+}
+
+// Rows of a character from top to bottom, the highest of the 5 bits is the leftmost pixel.
+fn glyph(character: char) -> [u8; 7] {
+    match character {
+        'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+        'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+        'D' => [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110],
+        'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+        'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+        'G' => [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+        'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'J' => [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+        'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+        'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+        'N' => [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'P' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+        'Q' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'S' => [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+        'X' => [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+        'Y' => [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100],
+        'Z' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+        '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        ' ' => [0; 7],
+        '-' => [0, 0, 0, 0b11111, 0, 0, 0],
+        '.' => [0, 0, 0, 0, 0, 0b01100, 0b01100],
+        _ => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0, 0b00100],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::DiskStorage;
+
+    use super::*;
+
+    #[test]
+    fn test_should_only_take_public_addresses_as_public() {
+        for internal in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0", "::1", "fd00::1", "fe80::1", "::ffff:127.0.0.1"] {
+            assert!(!is_public(internal.parse().unwrap()), "{}", internal);
+        }
+        for public in ["93.184.216.34", "2606:2800:220:1::1"] {
+            assert!(is_public(public.parse().unwrap()), "{}", public);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_refuse_to_fetch_images_from_internal_addresses() {
+        for image_url in ["http://127.0.0.1:1/sprite.png", "http://169.254.169.254/latest/meta-data", "http://[::1]/sprite.png", "ftp://example.com/sprite.png"] {
+            assert!(fetch_image(image_url).await.is_err(), "{}", image_url);
+        }
+        assert_eq!(redirect_url(&"http://example.com/a/b.png".parse().unwrap(), "/c.png").unwrap(), "http://example.com/c.png");
+        assert!(redirect_url(&"http://example.com/a/b.png".parse().unwrap(), "c.png").is_err());
+        // Redirects are checked like the first URL, so a public server can't send the download to an internal one.
+        let redirected = redirect_url(&"http://example.com/a/b.png".parse().unwrap(), "http://127.0.0.1:1/sprite.png").unwrap();
+        assert!(public_address(&redirected).await.unwrap_err().contains("internal address"));
+    }
+
+    #[actix_rt::test]
+    async fn test_should_load_uploaded_images_from_the_storage() {
+        let mut png = Vec::new();
+        image::RgbaImage::new(4, 4).write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+        let directory = tempfile::tempdir().unwrap();
+        let storage = DiskStorage::new(directory.path());
+        storage.put(&image_key("m1"), "image/png", png.into()).await.unwrap();
+        let monster = Monster {
+            id: "m1".to_string(),
+            name: "m1".to_string(),
+            image_url: format!("http://localhost:8080{}/monsters/m1/image", API_PREFIX),
+            stats: Stats { attack: 40, defense: 20, hp: 50, speed: 80 },
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        };
+
+        assert!(load_monster_image(&monster, Some(&storage)).await.is_ok());
+        assert!(load_monster_image(&monster, None).await.unwrap_err().contains("internal address"));
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use figment::providers::{Format, Toml, Yaml};
+use figment::value::{Dict, Value};
+use figment::Figment;
+
+// The files looked for when CONFIG_FILE isn't set, the first one existing being loaded.
+pub const DEFAULT_CONFIG_FILES: &[&str] = &["config.toml", "config.yaml", "config.yml"];
+
+/*
+The settings of the server, from the environment first and then the configuration file, so a deployment can keep
+most of them in the file and override a few with env vars. Settings keep their env var names: keys of the file
+are the names in any case, and tables prefix the keys they hold, `pool_max_size` under `[database]` being
+DATABASE_POOL_MAX_SIZE. Arrays are read as comma separated lists.
+
+The modules read their settings from it with `from_config`, which validates them, so a bad value stops the
+server on startup rather than on first use.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct AppConfig {
+    file: Option<PathBuf>,
+    values: BTreeMap<String, String>,
+}
+
+impl AppConfig {
+    // The environment alone, for tests and tools without a configuration file.
+    pub fn env() -> Self {
+        AppConfig::default()
+    }
+
+    // Loads the .env file into the environment, then CONFIG_FILE or the first of DEFAULT_CONFIG_FILES found.
+    pub fn load() -> Result<Self, String> {
+        dotenvy::dotenv().ok();
+        match std::env::var("CONFIG_FILE") {
+            Ok(file) => AppConfig::from_file(Path::new(&file)),
+            Err(_) => match DEFAULT_CONFIG_FILES.iter().map(Path::new).find(|file| file.exists()) {
+                Some(file) => AppConfig::from_file(file),
+                None => Ok(AppConfig::env()),
+            },
+        }
+    }
+
+    // TOML unless the file ends in .yaml or .yml.
+    pub fn from_file(file: &Path) -> Result<Self, String> {
+        let yaml = file.extension().is_some_and(|extension| extension == "yaml" || extension == "yml");
+        let figment = if yaml { Figment::from(Yaml::file_exact(file)) } else { Figment::from(Toml::file_exact(file)) };
+        let dict: Dict = figment.extract().map_err(|err| format!("Invalid configuration file {}: {}", file.display(), err))?;
+        let mut values = BTreeMap::new();
+        flatten("", &dict, &mut values).map_err(|err| format!("Invalid configuration file {}: {}", file.display(), err))?;
+        Ok(AppConfig { file: Some(file.to_path_buf()), values })
+    }
+
+    // Sets `name` as the file would, for tests building their configuration in code.
+    pub fn with_var(mut self, name: &str, value: &str) -> Self {
+        self.values.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    // The env var `name` when set, the value of the file otherwise.
+    pub fn var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok().or_else(|| self.values.get(name).cloned())
+    }
+}
+
+fn flatten(prefix: &str, dict: &Dict, values: &mut BTreeMap<String, String>) -> Result<(), String> {
+    for (key, value) in dict {
+        let name = if prefix.is_empty() { key.to_uppercase() } else { format!("{}_{}", prefix, key.to_uppercase()) };
+        match value {
+            Value::Dict(_, dict) => flatten(&name, dict, values)?,
+            Value::Array(_, items) => {
+                let items = items.iter().map(|item| scalar(&name, item)).collect::<Result<Vec<_>, _>>()?;
+                values.insert(name, items.join(","));
+            }
+            value => {
+                let value = scalar(&name, value)?;
+                values.insert(name, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn scalar(name: &str, value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(_, value) => Ok(value.clone()),
+        Value::Char(_, value) => Ok(value.to_string()),
+        Value::Bool(_, value) => Ok(value.to_string()),
+        Value::Num(_, value) => Ok(value
+            .to_u128()
+            .map(|number| number.to_string())
+            .or_else(|| value.to_i128().map(|number| number.to_string()))
+            .or_else(|| value.to_f64().map(|number| number.to_string()))
+            .unwrap_or_default()),
+        Value::Empty(_, _) => Ok(String::new()),
+        Value::Dict(_, _) | Value::Array(_, _) => Err(format!("{} must be a string, a number, a boolean or a list of them", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_should_read_the_file_under_env_var_names_with_the_env_taking_precedence() {
+        let mut file = tempfile::Builder::new().suffix(".yaml").tempfile().unwrap();
+        writeln!(file, "cache: memory\nconfig_test_override: file\ndatabase:\n  pool_max_size: 20\nkafka_brokers: [\"a:9092\", \"b:9092\"]").unwrap();
+        std::env::set_var("CONFIG_TEST_OVERRIDE", "env");
+        let config = AppConfig::from_file(file.path()).unwrap();
+        assert_eq!(config.var("CACHE").as_deref(), Some("memory"));
+        assert_eq!(config.var("DATABASE_POOL_MAX_SIZE").as_deref(), Some("20"));
+        assert_eq!(config.var("KAFKA_BROKERS").as_deref(), Some("a:9092,b:9092"));
+        assert_eq!(config.var("CONFIG_TEST_OVERRIDE").as_deref(), Some("env"));
+        assert_eq!(config.var("CONFIG_TEST_MISSING"), None);
+
+        let mut invalid = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        writeln!(invalid, "cors_origins = [{{ url = \"https://example.com\" }}]").unwrap();
+        assert!(AppConfig::from_file(invalid.path()).is_err());
+    }
+}
@@ -0,0 +1,289 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use crate::config::AppConfig;
+
+pub const MONSTER_CREATED: &str = "monster.created";
+pub const MONSTER_UPDATED: &str = "monster.updated";
+pub use crate::models::webhook::BATTLE_COMPLETED;
+
+// Events queued past this many while the broker is slow or down are dropped.
+#[cfg(any(feature = "nats-events", feature = "kafka-events"))]
+const EVENT_QUEUE_CAPACITY: usize = 1024;
+
+// What the publisher threads are sent: events, and flushes acknowledged once the events queued before them are delivered.
+#[cfg(any(feature = "nats-events", feature = "kafka-events"))]
+enum Queued {
+    Event { topic: String, key: String, payload: Vec<u8> },
+    Flush(std::sync::mpsc::Sender<()>),
+}
+
+/*
+Where the domain events emitted by the repositories go, so other systems can react to writes without polling.
+Publishing must never hold back or fail the write: implementations queue the event and deliver it in the
+background, at most once.
+*/
+pub trait EventPublisher: Send + Sync {
+    // `key` is the id of the entity, the events of an entity being kept in order by it.
+    fn publish(&self, event: &str, key: &str, payload: Vec<u8>) -> Result<(), String>;
+
+    // Waits up to `timeout` for the queued events to be delivered, on shutdown. Blocks, call it off the async runtime.
+    fn flush(&self, _timeout: Duration) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// The default, for deployments without a broker.
+pub struct NoopPublisher;
+
+impl EventPublisher for NoopPublisher {
+    fn publish(&self, _event: &str, _key: &str, _payload: Vec<u8>) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// The payload of every event, the same envelope as webhook callbacks.
+#[derive(Serialize)]
+struct DomainEvent<'a, T: Serialize> {
+    id: String,
+    event: &'a str,
+    #[serde(rename = "createdAt")]
+    created_at: NaiveDateTime,
+    data: &'a T,
+}
+
+/*
+The publisher picked by EVENTS: none by default, `nats` for the NATS server at NATS_URL, the event being the
+subject, or `kafka` for the comma separated KAFKA_BROKERS, the event being the topic. Both require building
+with their feature, `nats-events` or `kafka-events`.
+*/
+pub fn from_config(config: &AppConfig) -> Result<Arc<dyn EventPublisher>, String> {
+    from_vars(|name| config.var(name))
+}
+
+fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Arc<dyn EventPublisher>, String> {
+    let required = |name: &str| var(name).filter(|value| !value.trim().is_empty()).ok_or_else(|| format!("{} is required with EVENTS={}", name, var("EVENTS").unwrap_or_default().trim()));
+    match var("EVENTS").as_deref().map(str::trim) {
+        None | Some("") | Some("none") => Ok(Arc::new(NoopPublisher)),
+        Some("nats") => nats_publisher(&required("NATS_URL")?),
+        Some("kafka") => {
+            let brokers: Vec<String> = required("KAFKA_BROKERS")?.split(',').map(|broker| broker.trim().to_string()).filter(|broker| !broker.is_empty()).collect();
+            kafka_publisher(brokers)
+        }
+        Some(other) => Err(format!("Unknown event publisher {:?}, expected none, nats or kafka", other)),
+    }
+}
+
+#[cfg(feature = "nats-events")]
+fn nats_publisher(url: &str) -> Result<Arc<dyn EventPublisher>, String> {
+    Ok(Arc::new(NatsPublisher::new(url)?))
+}
+
+#[cfg(not(feature = "nats-events"))]
+fn nats_publisher(_url: &str) -> Result<Arc<dyn EventPublisher>, String> {
+    Err("EVENTS=nats requires building with the nats-events feature".to_string())
+}
+
+#[cfg(feature = "kafka-events")]
+fn kafka_publisher(brokers: Vec<String>) -> Result<Arc<dyn EventPublisher>, String> {
+    Ok(Arc::new(KafkaPublisher::new(brokers)))
+}
+
+#[cfg(not(feature = "kafka-events"))]
+fn kafka_publisher(_brokers: Vec<String>) -> Result<Arc<dyn EventPublisher>, String> {
+    Err("EVENTS=kafka requires building with the kafka-events feature".to_string())
+}
+
+// Publishes `data` as `event`, logging the events that can't be queued rather than failing the caller.
+pub fn emit<T: Serialize>(publisher: &dyn EventPublisher, event: &str, key: &str, data: &T) {
+    let payload = DomainEvent {
+        id: uuid::Uuid::new_v4().to_string(),
+        event,
+        created_at: chrono::Utc::now().naive_utc(),
+        data,
+    };
+    let payload = serde_json::to_vec(&payload).expect("Domain events are serializable");
+    if let Err(err) = publisher.publish(event, key, payload) {
+        tracing::warn!(event, key, error = %err, "Failed to publish event");
+    }
+}
+
+#[cfg(feature = "nats-events")]
+pub use self::nats::NatsPublisher;
+
+#[cfg(feature = "nats-events")]
+mod nats {
+    use std::time::Duration;
+    use tokio::sync::mpsc::{self, error::TrySendError};
+    use super::{EventPublisher, Queued, EVENT_QUEUE_CAPACITY};
+
+    /*
+    Publishes from a thread of its own, connecting in the background and reconnecting whenever the server goes
+    away, so the server starts while NATS is down.
+    */
+    pub struct NatsPublisher {
+        sender: mpsc::Sender<Queued>,
+    }
+
+    impl NatsPublisher {
+        pub fn new(url: &str) -> Result<Self, String> {
+            let url: async_nats::ServerAddr = url.parse().map_err(|err| format!("Invalid NATS_URL: {}", err))?;
+            let (sender, mut receiver) = mpsc::channel::<Queued>(EVENT_QUEUE_CAPACITY);
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().map_err(|err| err.to_string())?;
+            std::thread::spawn(move || {
+                runtime.block_on(async move {
+                    let client = match async_nats::ConnectOptions::new().retry_on_initial_connect().connect(url).await {
+                        Ok(client) => client,
+                        Err(err) => return tracing::error!(error = %err, "Failed to connect to NATS, events are not published"),
+                    };
+                    while let Some(queued) = receiver.recv().await {
+                        match queued {
+                            Queued::Event { topic: subject, payload, .. } => {
+                                if let Err(err) = client.publish(subject.clone(), payload.into()).await {
+                                    tracing::warn!(subject, error = %err, "Failed to publish event to NATS");
+                                }
+                            }
+                            Queued::Flush(flushed) => {
+                                if let Err(err) = client.flush().await {
+                                    tracing::warn!(error = %err, "Failed to flush events to NATS");
+                                }
+                                let _ = flushed.send(());
+                            }
+                        }
+                    }
+                })
+            });
+            Ok(NatsPublisher { sender })
+        }
+    }
+
+    impl EventPublisher for NatsPublisher {
+        fn publish(&self, event: &str, key: &str, payload: Vec<u8>) -> Result<(), String> {
+            match self.sender.try_send(Queued::Event { topic: event.to_string(), key: key.to_string(), payload }) {
+                Ok(()) => Ok(()),
+                Err(TrySendError::Full(_)) => Err("The NATS queue is full".to_string()),
+                Err(TrySendError::Closed(_)) => Err("The NATS publisher stopped".to_string()),
+            }
+        }
+
+        fn flush(&self, timeout: Duration) -> Result<(), String> {
+            let (flushed, wait) = std::sync::mpsc::channel();
+            self.sender.blocking_send(Queued::Flush(flushed)).map_err(|_| "The NATS publisher stopped".to_string())?;
+            wait.recv_timeout(timeout).map_err(|_| "Timed out flushing the events to NATS".to_string())
+        }
+    }
+}
+
+#[cfg(feature = "kafka-events")]
+pub use self::kafka_publisher::KafkaPublisher;
+
+#[cfg(feature = "kafka-events")]
+mod kafka_publisher {
+    use std::sync::mpsc::{self, SyncSender, TrySendError};
+    use std::time::Duration;
+    use kafka::producer::{Producer, Record, RequiredAcks};
+    use super::{EventPublisher, Queued, EVENT_QUEUE_CAPACITY};
+
+    const ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /*
+    Produces from a thread of its own, the producer being created on the first event and again after a failed
+    send, so the server starts while the brokers are down. Events are keyed by entity id.
+    */
+    pub struct KafkaPublisher {
+        sender: SyncSender<Queued>,
+    }
+
+    impl KafkaPublisher {
+        pub fn new(brokers: Vec<String>) -> Self {
+            let (sender, receiver) = mpsc::sync_channel::<Queued>(EVENT_QUEUE_CAPACITY);
+            std::thread::spawn(move || {
+                let mut producer: Option<Producer> = None;
+                for queued in receiver {
+                    // Events are sent one at a time and acknowledged, so the ones before a flush are delivered already.
+                    let (topic, key, payload) = match queued {
+                        Queued::Event { topic, key, payload } => (topic, key, payload),
+                        Queued::Flush(flushed) => {
+                            let _ = flushed.send(());
+                            continue;
+                        }
+                    };
+                    if producer.is_none() {
+                        producer = Producer::from_hosts(brokers.clone())
+                            .with_ack_timeout(ACK_TIMEOUT)
+                            .with_required_acks(RequiredAcks::One)
+                            .create()
+                            .map_err(|err| tracing::warn!(error = %err, "Failed to connect to Kafka"))
+                            .ok();
+                    }
+                    let Some(connected) = producer.as_mut() else {
+                        tracing::warn!(topic, key, "Dropped event, Kafka is unreachable");
+                        continue;
+                    };
+                    if let Err(err) = connected.send(&Record::from_key_value(&topic, key.as_bytes(), payload.as_slice())) {
+                        tracing::warn!(topic, key, error = %err, "Failed to publish event to Kafka");
+                        producer = None;
+                    }
+                }
+            });
+            KafkaPublisher { sender }
+        }
+    }
+
+    impl EventPublisher for KafkaPublisher {
+        fn publish(&self, event: &str, key: &str, payload: Vec<u8>) -> Result<(), String> {
+            match self.sender.try_send(Queued::Event { topic: event.to_string(), key: key.to_string(), payload }) {
+                Ok(()) => Ok(()),
+                Err(TrySendError::Full(_)) => Err("The Kafka queue is full".to_string()),
+                Err(TrySendError::Disconnected(_)) => Err("The Kafka publisher stopped".to_string()),
+            }
+        }
+
+        fn flush(&self, timeout: Duration) -> Result<(), String> {
+            let (flushed, wait) = mpsc::channel();
+            self.sender.send(Queued::Flush(flushed)).map_err(|_| "The Kafka publisher stopped".to_string())?;
+            wait.recv_timeout(timeout).map_err(|_| "Timed out flushing the events to Kafka".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use serde_json::Value;
+    use crate::models::monster::Monster;
+    use crate::repository::database::Database;
+    use crate::repository::monster_repository::MonsterRepository;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingPublisher(Mutex<Vec<(String, String, Value)>>);
+
+    impl EventPublisher for RecordingPublisher {
+        fn publish(&self, event: &str, key: &str, payload: Vec<u8>) -> Result<(), String> {
+            self.0.lock().unwrap().push((event.to_string(), key.to_string(), serde_json::from_slice(&payload).unwrap()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_should_publish_the_monster_writes_once_committed() {
+        let publisher = Arc::new(RecordingPublisher::default());
+        let db = Database::new().unwrap().with_events(publisher.clone());
+        let monster: Monster = serde_json::from_value(serde_json::json!({ "name": "evented", "image_url": "https://example.com/monster.png", "attack": 40, "defense": 20, "hp": 50, "speed": 80 })).unwrap();
+        let created = db.create_monster(monster).unwrap();
+        db.update_monster_by_id(&created.id, Monster { name: "renamed".to_string(), ..created.clone() }).unwrap();
+        assert!(db.update_monster_by_id(&created.id, Monster { name: "stale".to_string(), ..created.clone() }).is_err());
+
+        let published = publisher.0.lock().unwrap();
+        let events: Vec<(&str, &str)> = published.iter().map(|(event, key, _)| (event.as_str(), key.as_str())).collect();
+        assert_eq!(events, vec![(MONSTER_CREATED, created.id.as_str()), (MONSTER_UPDATED, created.id.as_str())]);
+        let payload = &published[1].2;
+        assert_eq!(payload["event"], MONSTER_UPDATED);
+        assert_eq!(payload["data"]["name"], "renamed");
+        assert!(payload["createdAt"].is_string());
+        assert!(from_vars(|name| (name == "EVENTS").then(|| "rabbitmq".to_string())).is_err());
+    }
+}
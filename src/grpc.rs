@@ -0,0 +1,312 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use chrono::NaiveDateTime;
+use tonic::{Request, Response, Status};
+use crate::api::battle_apis::{find_monsters, monster_hidden_stats, monster_items, monster_moves, new_simulated_battle, store_battle};
+use crate::api::error::ApiError;
+use crate::battle_engine::{BattleEngine, BattleSetup};
+use crate::config::AppConfig;
+use crate::models::battle::{Battle, BattleOutcome};
+use crate::models::decay::StatDecay;
+use crate::models::monster::{Monster, Stats};
+use crate::models::page::{self, Page, PageCursor, DEFAULT_PAGE_SIZE};
+use crate::models::status_effect::StatusEffectRules;
+use crate::repository::battle_repository::BattleRepository;
+use crate::repository::error::RepositoryError;
+use crate::repository::item_repository::ItemRepository;
+use crate::repository::monster_repository::{DeleteMonsterError, MonsterRepository};
+use crate::repository::move_repository::MoveRepository;
+use crate::repository::training_repository::TrainingRepository;
+use self::proto::battle_service_server::{BattleService, BattleServiceServer};
+use self::proto::monster_service_server::{MonsterService, MonsterServiceServer};
+
+pub mod proto {
+    tonic::include_proto!("battlemonsters.v1");
+}
+
+/*
+The port of the gRPC API, from GRPC_PORT. Unset, only the REST API is served. Like it, the gRPC API listens on
+localhost, for the internal services running along the server.
+*/
+pub fn port_from_config(config: &AppConfig) -> Result<Option<u16>, String> {
+    port_from_vars(|name| config.var(name))
+}
+
+fn port_from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Option<u16>, String> {
+    match var("GRPC_PORT").as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(port) => port.parse().map(Some).map_err(|_| format!("GRPC_PORT must be a port number, got {:?}", port)),
+    }
+}
+
+/*
+Serves `MonsterService` and `BattleService` on `port` until the server stops. They read and write the same
+repositories as the REST handlers, and battles are simulated with the same engine and rules.
+*/
+pub async fn serve<R>(port: u16, repository: Arc<R>, engine: Arc<dyn BattleEngine>, decay: Option<StatDecay>, status_effects: Option<Arc<StatusEffectRules>>) -> Result<(), tonic::transport::Error>
+where
+    R: MonsterRepository + BattleRepository + MoveRepository + ItemRepository + TrainingRepository + 'static,
+{
+    let monsters = MonsterGrpc { monster_repository: repository.clone() };
+    let battles = BattleGrpc {
+        monster_repository: repository.clone(),
+        battle_repository: repository.clone(),
+        move_repository: repository.clone(),
+        item_repository: repository.clone(),
+        training_repository: repository,
+        engine,
+        decay,
+        status_effects,
+    };
+    tonic::transport::Server::builder()
+        .add_service(MonsterServiceServer::new(monsters))
+        .add_service(BattleServiceServer::new(battles))
+        .serve(SocketAddr::from(([127, 0, 0, 1], port)))
+        .await
+}
+
+// Same codes as the REST statuses: 422 is INVALID_ARGUMENT and 409 ABORTED, the write being retryable once read again.
+fn repository_error_status(err: &RepositoryError) -> Status {
+    let message = ApiError::from(err).message;
+    match err {
+        RepositoryError::Constraint(_) => Status::invalid_argument(message),
+        RepositoryError::StaleVersion => Status::aborted(message),
+        RepositoryError::HasDependents(_) => Status::failed_precondition(message),
+        RepositoryError::Database(_) => Status::internal(message),
+    }
+}
+
+// The page of `after` and `limit`, the first one and the default size when left empty.
+fn requested_page(after: &str, limit: i64) -> Result<(Option<PageCursor>, i64), String> {
+    let after = Some(after).filter(|after| !after.is_empty());
+    let limit = if limit == 0 { DEFAULT_PAGE_SIZE } else { limit };
+    let page = page::requested_page(after, Some(limit))?;
+    Ok(page.expect("A limit is always given"))
+}
+
+fn timestamp(date: Option<NaiveDateTime>) -> Option<String> {
+    date.map(|date| date.format("%Y-%m-%dT%H:%M:%S%.f").to_string())
+}
+
+impl From<Monster> for proto::Monster {
+    fn from(monster: Monster) -> Self {
+        proto::Monster {
+            id: monster.id,
+            name: monster.name,
+            image_url: monster.image_url,
+            attack: monster.stats.attack,
+            defense: monster.stats.defense,
+            hp: monster.stats.hp,
+            speed: monster.stats.speed,
+            level: monster.level,
+            xp: monster.xp,
+            owner_id: monster.owner_id,
+            created_at: timestamp(monster.created_at),
+            updated_at: timestamp(monster.updated_at),
+            version: monster.version,
+        }
+    }
+}
+
+impl From<Battle> for proto::Battle {
+    fn from(battle: Battle) -> Self {
+        let outcome = match battle.outcome {
+            BattleOutcome::Win => proto::BattleOutcome::Win,
+            BattleOutcome::Draw => proto::BattleOutcome::Draw,
+            BattleOutcome::Forfeit => proto::BattleOutcome::Forfeit,
+        };
+        proto::Battle {
+            id: battle.id,
+            monster_a: battle.monster_a,
+            monster_b: battle.monster_b,
+            winner: battle.winner,
+            outcome: outcome.into(),
+            manual: battle.manual,
+            created_at: timestamp(battle.created_at),
+            updated_at: timestamp(battle.updated_at),
+        }
+    }
+}
+
+impl proto::MonsterInput {
+    // The monster with these fields set, the others kept from `monster`.
+    fn apply(self, monster: Monster) -> Monster {
+        let stats = Stats { attack: self.attack, defense: self.defense, hp: self.hp, speed: self.speed };
+        Monster { name: self.name, image_url: self.image_url, stats, ..monster }
+    }
+}
+
+pub struct MonsterGrpc {
+    monster_repository: Arc<dyn MonsterRepository>,
+}
+
+#[tonic::async_trait]
+impl MonsterService for MonsterGrpc {
+    async fn list_monsters(&self, request: Request<proto::ListMonstersRequest>) -> Result<Response<proto::ListMonstersResponse>, Status> {
+        let request = request.into_inner();
+        let (after, limit) = requested_page(&request.after, request.limit).map_err(Status::invalid_argument)?;
+        let monsters = self.monster_repository.get_monsters_page(None, None, after.as_ref(), limit + 1);
+        let page = Page::new(monsters, limit, |monster| PageCursor { created_at: monster.created_at, id: monster.id.clone() });
+        let monsters = page.items.into_iter().map(proto::Monster::from).collect();
+        Ok(Response::new(proto::ListMonstersResponse { monsters, next_cursor: page.next_cursor }))
+    }
+
+    async fn get_monster(&self, request: Request<proto::GetMonsterRequest>) -> Result<Response<proto::Monster>, Status> {
+        match self.monster_repository.get_monster_by_id(&request.into_inner().id) {
+            Some(monster) => Ok(Response::new(monster.into())),
+            None => Err(Status::not_found("Monster not found")),
+        }
+    }
+
+    async fn create_monster(&self, request: Request<proto::CreateMonsterRequest>) -> Result<Response<proto::Monster>, Status> {
+        let input = request.into_inner().monster.ok_or_else(|| Status::invalid_argument("The monster is required"))?;
+        let monster = Monster {
+            id: String::new(),
+            name: String::new(),
+            image_url: String::new(),
+            stats: Stats::default(),
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        };
+        match self.monster_repository.create_monster(input.apply(monster)) {
+            Ok(monster) => Ok(Response::new(monster.into())),
+            Err(err) => Err(repository_error_status(&err)),
+        }
+    }
+
+    // Unlike PUT, the fields missing from `MonsterInput`, like the external id, are kept.
+    async fn update_monster(&self, request: Request<proto::UpdateMonsterRequest>) -> Result<Response<proto::Monster>, Status> {
+        let request = request.into_inner();
+        if request.version < 1 {
+            return Err(Status::invalid_argument("The version the monster was read at is required"));
+        }
+        let input = request.monster.ok_or_else(|| Status::invalid_argument("The monster is required"))?;
+        let Some(existing_monster) = self.monster_repository.get_monster_by_id(&request.id) else {
+            return Err(Status::not_found("Monster not found"));
+        };
+        let monster = Monster { version: request.version, ..input.apply(existing_monster) };
+        match self.monster_repository.update_monster_by_id(&request.id, monster) {
+            Ok(Some(monster)) => Ok(Response::new(monster.into())),
+            Ok(None) => Err(Status::not_found("Monster not found")),
+            Err(err) => Err(repository_error_status(&err)),
+        }
+    }
+
+    async fn delete_monster(&self, request: Request<proto::DeleteMonsterRequest>) -> Result<Response<proto::DeleteMonsterResponse>, Status> {
+        let request = request.into_inner();
+        match self.monster_repository.delete_monster_by_id(&request.id, request.cascade) {
+            Ok(_) => Ok(Response::new(proto::DeleteMonsterResponse {})),
+            Err(DeleteMonsterError::NotFound) => Err(Status::not_found("Monster not found")),
+            Err(DeleteMonsterError::HasBattles) => Err(Status::failed_precondition("Monster has battles, use cascade to delete them too")),
+            Err(DeleteMonsterError::Database(err)) => Err(repository_error_status(&err)),
+        }
+    }
+}
+
+pub struct BattleGrpc {
+    monster_repository: Arc<dyn MonsterRepository>,
+    battle_repository: Arc<dyn BattleRepository>,
+    move_repository: Arc<dyn MoveRepository>,
+    item_repository: Arc<dyn ItemRepository>,
+    training_repository: Arc<dyn TrainingRepository>,
+    engine: Arc<dyn BattleEngine>,
+    decay: Option<StatDecay>,
+    status_effects: Option<Arc<StatusEffectRules>>,
+}
+
+#[tonic::async_trait]
+impl BattleService for BattleGrpc {
+    async fn list_battles(&self, request: Request<proto::ListBattlesRequest>) -> Result<Response<proto::ListBattlesResponse>, Status> {
+        let request = request.into_inner();
+        let (after, limit) = requested_page(&request.after, request.limit).map_err(Status::invalid_argument)?;
+        let battles = self.battle_repository.get_battles_page(after.as_ref(), limit + 1);
+        let page = Page::new(battles, limit, |battle| PageCursor { created_at: battle.created_at, id: battle.id.clone() });
+        let battles = page.items.into_iter().map(proto::Battle::from).collect();
+        Ok(Response::new(proto::ListBattlesResponse { battles, next_cursor: page.next_cursor }))
+    }
+
+    async fn get_battle(&self, request: Request<proto::GetBattleRequest>) -> Result<Response<proto::Battle>, Status> {
+        match self.battle_repository.get_battle_by_id(&request.into_inner().id) {
+            Some(battle) => Ok(Response::new(battle.into())),
+            None => Err(Status::not_found("Battle not found")),
+        }
+    }
+
+    async fn create_battle(&self, request: Request<proto::CreateBattleRequest>) -> Result<Response<proto::Battle>, Status> {
+        let request = request.into_inner();
+        let ids = |id: String| Some(id).filter(|id| !id.is_empty());
+        let (monster_a, monster_b) = find_monsters(self.monster_repository.as_ref(), &ids(request.monster_a), &ids(request.monster_b)).map_err(Status::invalid_argument)?;
+        let moves = monster_moves(self.move_repository.as_ref(), &monster_a, &monster_b);
+        let items = monster_items(self.item_repository.as_ref(), &monster_a, &monster_b);
+        let hidden_stats = monster_hidden_stats(self.training_repository.as_ref(), &monster_a, &monster_b);
+        let setup = BattleSetup { moves, items, hidden_stats, status_effects: self.status_effects.as_deref(), ..BattleSetup::new(monster_a, monster_b) };
+        match store_battle(self.battle_repository.as_ref(), new_simulated_battle(self.engine.as_ref(), setup, self.decay.as_ref())) {
+            Ok(battle) => Ok(Response::new(battle.into())),
+            Err(err) => Err(repository_error_status(&err)),
+        }
+    }
+
+    async fn delete_battle(&self, request: Request<proto::DeleteBattleRequest>) -> Result<Response<proto::DeleteBattleResponse>, Status> {
+        match self.battle_repository.delete_battle_by_id(&request.into_inner().id) {
+            Some(_) => Ok(Response::new(proto::DeleteBattleResponse {})),
+            None => Err(Status::not_found("Battle not found")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+    use crate::battle_engine::ClassicEngine;
+    use crate::repository::memory_repository::InMemoryRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_serve_monsters_and_battles_over_grpc() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let monsters = MonsterGrpc { monster_repository: repository.clone() };
+        let battles = BattleGrpc {
+            monster_repository: repository.clone(),
+            battle_repository: repository.clone(),
+            move_repository: repository.clone(),
+            item_repository: repository.clone(),
+            training_repository: repository.clone(),
+            engine: Arc::new(ClassicEngine),
+            decay: None,
+            status_effects: None,
+        };
+        let create = |name: &str| {
+            let monster = proto::MonsterInput { name: name.to_string(), image_url: "https://example.com/monster.png".to_string(), attack: 60, defense: 40, hp: 100, speed: 70 };
+            Request::new(proto::CreateMonsterRequest { monster: Some(monster) })
+        };
+        let monster_a = monsters.create_monster(create("grpc-a")).await.unwrap().into_inner();
+        let monster_b = monsters.create_monster(create("grpc-b")).await.unwrap().into_inner();
+
+        let first = monsters.list_monsters(Request::new(proto::ListMonstersRequest { after: String::new(), limit: 1 })).await.unwrap().into_inner();
+        assert_eq!(first.monsters.len(), 1);
+        let second = monsters.list_monsters(Request::new(proto::ListMonstersRequest { after: first.next_cursor.unwrap(), limit: 1 })).await.unwrap().into_inner();
+        assert_eq!(second.monsters.len(), 1);
+        assert!(second.next_cursor.is_none());
+
+        let update = |version: i32| {
+            let monster = proto::MonsterInput { name: "grpc-renamed".to_string(), image_url: monster_a.image_url.clone(), attack: 60, defense: 40, hp: 100, speed: 70 };
+            Request::new(proto::UpdateMonsterRequest { id: monster_a.id.clone(), monster: Some(monster), version })
+        };
+        assert_eq!(monsters.update_monster(update(1)).await.unwrap().into_inner().version, 2);
+        assert_eq!(monsters.update_monster(update(1)).await.unwrap_err().code(), Code::Aborted);
+
+        let battle = battles.create_battle(Request::new(proto::CreateBattleRequest { monster_a: monster_a.id.clone(), monster_b: monster_b.id.clone() })).await.unwrap().into_inner();
+        assert_eq!(battles.get_battle(Request::new(proto::GetBattleRequest { id: battle.id })).await.unwrap().into_inner().monster_a, monster_a.id);
+        let delete = monsters.delete_monster(Request::new(proto::DeleteMonsterRequest { id: monster_a.id.clone(), cascade: false })).await;
+        assert_eq!(delete.unwrap_err().code(), Code::FailedPrecondition);
+        assert!(port_from_vars(|_| Some("grpc".to_string())).is_err());
+    }
+}
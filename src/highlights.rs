@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::battle_engine::TurnEvent;
+
+// Points each factor adds to the highlight score, capped so the score stays between 0 and 100.
+const LEAD_CHANGE_POINTS: i32 = 10;
+const MAX_LEAD_CHANGE_POINTS: i32 = 30;
+const NEAR_KNOCKOUT_POINTS: i32 = 15;
+const MAX_NEAR_KNOCKOUT_POINTS: i32 = 30;
+const CRIT_SWING_POINTS: i32 = 10;
+const MAX_CRIT_SWING_POINTS: i32 = 20;
+const MAX_CLOSENESS_POINTS: i32 = 20;
+// A monster dropping to this share of its HP or below and going on to win, or draw, was nearly knocked out.
+pub const NEAR_KNOCKOUT_PERCENT: i32 = 10;
+// Critical hits taking this share of the defender's HP or more swing the battle, like the ones taking the lead.
+pub const CRIT_SWING_PERCENT: i32 = 25;
+
+/*
+How exciting a battle was to watch. The lead goes to the monster with the largest share of its HP left, and
+closeness is the share of its HP the winner lost, or how even the monsters ended a draw, out of 100.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Intensity {
+    pub lead_changes: i32,
+    pub near_knockouts: i32,
+    pub crit_swings: i32,
+    pub closeness: i32,
+    pub score: i32,
+}
+
+// HP of a monster, its starting HP being known from the first turn it shows up in.
+struct Health {
+    start: i32,
+    hp: i32,
+}
+
+impl Health {
+    fn percent(&self) -> i32 {
+        if self.start <= 0 { 0 } else { self.hp * 100 / self.start }
+    }
+}
+
+// Tracks the HP of the monsters along the turns, in the order they showed up.
+#[derive(Default)]
+struct Tracker<'a> {
+    monsters: Vec<&'a str>,
+    health: HashMap<&'a str, Health>,
+    near_knockouts: HashMap<&'a str, i32>,
+}
+
+impl<'a> Tracker<'a> {
+    // Sets the HP of the monster, counting a near knock out when it just dropped to the threshold.
+    fn set_hp(&mut self, monster: &'a str, hp: i32, lost: i32) {
+        if !self.health.contains_key(monster) {
+            self.monsters.push(monster);
+        }
+        let health = self.health.entry(monster).or_insert(Health { start: hp + lost, hp: hp + lost });
+        let was_near = health.percent() <= NEAR_KNOCKOUT_PERCENT;
+        health.hp = hp;
+        if hp > 0 && !was_near && health.percent() <= NEAR_KNOCKOUT_PERCENT {
+            self.near_knockout(monster);
+        }
+    }
+
+    fn near_knockout(&mut self, monster: &'a str) {
+        *self.near_knockouts.entry(monster).or_default() += 1;
+    }
+
+    fn leader(&self) -> Option<&'a str> {
+        let [a, b] = self.monsters[..] else { return None };
+        match self.health[a].percent().cmp(&self.health[b].percent()) {
+            std::cmp::Ordering::Greater => Some(a),
+            std::cmp::Ordering::Less => Some(b),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+}
+
+// Analyzes the turns of a finished battle, none scoring 0.
+pub fn intensity(winner: Option<&str>, turns: &[TurnEvent]) -> Intensity {
+    let mut tracker = Tracker::default();
+    let mut intensity = Intensity::default();
+    let mut leader = None;
+    for turn in turns {
+        if let Some(attacker_hp) = turn.attacker_hp {
+            let status_damage: i32 = turn.statuses.iter().map(|tick| tick.damage).sum();
+            tracker.set_hp(&turn.attacker, attacker_hp, status_damage);
+        }
+        tracker.set_hp(&turn.defender, turn.defender_hp, turn.damage);
+        // Knocked out then brought back by a consumable.
+        for revival in &turn.revived {
+            tracker.near_knockout(&revival.monster);
+        }
+
+        let turn_leader = tracker.leader();
+        let lead_changed = leader.is_some() && turn_leader.is_some() && turn_leader != leader;
+        intensity.lead_changes += lead_changed as i32;
+        if turn.critical && turn.damage > 0 {
+            let defender_start = tracker.health[turn.defender.as_str()].start;
+            intensity.crit_swings += (lead_changed || turn.damage * 100 >= defender_start * CRIT_SWING_PERCENT) as i32;
+        }
+        leader = turn_leader.or(leader);
+    }
+
+    // The loser nearly knocked out then knocked out was never close to coming back.
+    intensity.near_knockouts = match winner {
+        Some(winner) => tracker.near_knockouts.get(winner).copied().unwrap_or_default(),
+        None => tracker.near_knockouts.values().sum(),
+    };
+    intensity.closeness = match (winner, &tracker.monsters[..]) {
+        (Some(winner), _) if tracker.health.contains_key(winner) => 100 - tracker.health[winner].percent(),
+        (None, [a, b]) => 100 - (tracker.health[a].percent() - tracker.health[b].percent()).abs(),
+        _ => 0,
+    };
+    intensity.score = (intensity.lead_changes * LEAD_CHANGE_POINTS).min(MAX_LEAD_CHANGE_POINTS)
+        + (intensity.near_knockouts * NEAR_KNOCKOUT_POINTS).min(MAX_NEAR_KNOCKOUT_POINTS)
+        + (intensity.crit_swings * CRIT_SWING_POINTS).min(MAX_CRIT_SWING_POINTS)
+        + intensity.closeness.clamp(0, 100) * MAX_CLOSENESS_POINTS / 100;
+    intensity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(turn: u32, attacker: &str, defender: &str, damage: i32, defender_hp: i32, critical: bool) -> TurnEvent {
+        TurnEvent { turn, attacker: attacker.to_string(), defender: defender.to_string(), damage, defender_hp, critical, ..TurnEvent::default() }
+    }
+
+    #[test]
+    fn test_should_score_close_battles_above_one_sided_ones() {
+        let one_sided = vec![
+            hit(1, "a", "b", 50, 50, false),
+            hit(2, "b", "a", 1, 99, false),
+            hit(3, "a", "b", 50, 0, false),
+        ];
+        assert_eq!(intensity(Some("a"), &one_sided), Intensity { lead_changes: 0, near_knockouts: 0, crit_swings: 0, closeness: 1, score: 0 });
+
+        let comeback = vec![
+            hit(1, "a", "b", 40, 60, false),
+            hit(2, "b", "a", 60, 40, true),
+            hit(3, "a", "b", 55, 5, false),
+            hit(4, "b", "a", 36, 4, false),
+            hit(5, "a", "b", 5, 0, false),
+        ];
+        assert_eq!(intensity(Some("a"), &comeback), Intensity { lead_changes: 3, near_knockouts: 1, crit_swings: 1, closeness: 96, score: 30 + 15 + 10 + 19 });
+        assert_eq!(intensity(None, &[]).score, 0);
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use csv::StringRecord;
+use prometheus::IntCounter;
+use crate::jobs::JobProgress;
+use crate::metrics::METRICS;
+use crate::models::job::{CSV_IMPORT, JSON_IMPORT};
+use crate::models::monster::{ImportMode, ImportOutcome, Monster};
+use crate::repository::monster_repository::MonsterRepository;
+
+pub const IMPORT_CHUNK_ROWS: usize = 100;
+// Delimiters detected in the header line of CSV files without one, the most frequent winning.
+const DETECTED_DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+// The monster fields columns can be mapped to.
+pub const MONSTER_FIELDS: [&str; 10] = ["id", "name", "image_url", "attack", "defense", "hp", "speed", "createdAt", "updatedAt", "externalId"];
+// Other names the fields go by in exports of other tools, compared lowercased with spaces and hyphens as underscores.
+const FIELD_ALIASES: [(&str, &[&str]); 7] = [
+    ("name", &["monster", "monster_name"]),
+    ("image_url", &["image", "imageurl", "img", "sprite", "url"]),
+    ("attack", &["atk", "att"]),
+    ("defense", &["def", "defence"]),
+    ("hp", &["health", "hit_points", "hitpoints"]),
+    ("speed", &["spd", "spe"]),
+    ("externalId", &["external_id", "ext_id"]),
+];
+
+pub type ImportRows = Box<dyn Iterator<Item = Result<Monster, String>>>;
+
+/*
+How the columns of a CSV file are read. The delimiter is detected from the header line unless given, and
+headers are matched to monster fields by `columns` first, then by name or alias.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CsvOptions {
+    pub delimiter: Option<u8>,
+    pub columns: HashMap<String, String>,
+}
+
+impl CsvOptions {
+    // From the form fields of an import: a one character delimiter and a JSON object of headers to fields.
+    pub fn parse(delimiter: Option<&str>, columns: Option<&str>) -> Result<Self, String> {
+        let delimiter = match delimiter.filter(|delimiter| !delimiter.is_empty()) {
+            Some(delimiter) if delimiter.len() == 1 && delimiter.is_ascii() && delimiter != "\"" => Some(delimiter.as_bytes()[0]),
+            Some(delimiter) => return Err(format!("Delimiter {:?} must be a single ASCII character other than a quote", delimiter)),
+            None => None,
+        };
+        let columns: HashMap<String, String> = match columns.filter(|columns| !columns.trim().is_empty()) {
+            Some(columns) => serde_json::from_str(columns).map_err(|err| format!("Columns must be a JSON object of headers to fields: {}", err))?,
+            None => HashMap::new(),
+        };
+        if let Some(field) = columns.values().find(|field| !MONSTER_FIELDS.contains(&field.as_str())) {
+            return Err(format!("Unknown monster field {:?}, expected one of {}", field, MONSTER_FIELDS.join(", ")));
+        }
+        Ok(CsvOptions { delimiter, columns })
+    }
+
+    // The monster field a header holds, the header itself when it isn't one.
+    pub fn field(&self, header: &str) -> String {
+        if let Some(field) = self.columns.get(header) {
+            return field.clone();
+        }
+        let normalized = header.trim().to_lowercase().replace([' ', '-'], "_");
+        MONSTER_FIELDS
+            .iter()
+            .find(|field| field.to_lowercase() == normalized)
+            .or_else(|| FIELD_ALIASES.iter().find(|(_, aliases)| aliases.contains(&normalized.as_str())).map(|(field, _)| field))
+            .map_or_else(|| header.to_string(), |field| field.to_string())
+    }
+}
+
+pub fn detect_delimiter(header_line: &str) -> u8 {
+    DETECTED_DELIMITERS
+        .into_iter()
+        .max_by_key(|delimiter| (header_line.bytes().filter(|byte| byte == delimiter).count(), std::cmp::Reverse(*delimiter == b',')))
+        .filter(|delimiter| header_line.as_bytes().contains(delimiter))
+        .unwrap_or(b',')
+}
+
+// The file formats of the imports, which share everything but the parsing of the rows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportFormat {
+    Csv(CsvOptions),
+    Json,
+}
+
+impl ImportFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImportFormat::Csv(_) => "CSV",
+            ImportFormat::Json => "JSON",
+        }
+    }
+
+    pub fn job_kind(&self) -> &'static str {
+        match self {
+            ImportFormat::Csv(_) => CSV_IMPORT,
+            ImportFormat::Json => JSON_IMPORT,
+        }
+    }
+
+    pub fn rows_imported(&self) -> &'static IntCounter {
+        match self {
+            ImportFormat::Csv(_) => &METRICS.csv_rows_imported,
+            ImportFormat::Json => &METRICS.json_rows_imported,
+        }
+    }
+
+    /*
+    The rows of the file, an error for each one that isn't a monster. JSON files are told apart by their first
+    character: an array is parsed whole, NDJSON line by line, blank lines being skipped.
+    */
+    pub fn rows(&self, path: &Path) -> Result<ImportRows, String> {
+        match self {
+            ImportFormat::Csv(options) => csv_rows(path, options),
+            ImportFormat::Json => json_rows(path),
+        }
+    }
+}
+
+fn csv_rows(path: &Path, options: &CsvOptions) -> Result<ImportRows, String> {
+    let delimiter = match options.delimiter {
+        Some(delimiter) => delimiter,
+        None => {
+            let mut header_line = String::new();
+            BufReader::new(File::open(path).map_err(|err| err.to_string())?).read_line(&mut header_line).map_err(|err| err.to_string())?;
+            detect_delimiter(&header_line)
+        }
+    };
+    let mut reader = csv::ReaderBuilder::new().delimiter(delimiter).from_path(path).map_err(|err| err.to_string())?;
+    let headers: StringRecord = reader.headers().map_err(|err| err.to_string())?.iter().map(|header| options.field(header)).collect();
+    reader.set_headers(headers);
+    Ok(Box::new(reader.into_deserialize::<Monster>().map(|row| row.map_err(|err| err.to_string()))))
+}
+
+fn json_rows(path: &Path) -> Result<ImportRows, String> {
+    let mut reader = BufReader::new(File::open(path).map_err(|err| err.to_string())?);
+    let first_byte = loop {
+        let buffer = reader.fill_buf().map_err(|err| err.to_string())?;
+        if buffer.is_empty() {
+            break None;
+        }
+        if let Some(byte) = buffer.iter().find(|byte| !byte.is_ascii_whitespace()) {
+            break Some(*byte);
+        }
+        let whitespace = buffer.len();
+        reader.consume(whitespace);
+    };
+    if first_byte == Some(b'[') {
+        let rows: Vec<serde_json::Value> = serde_json::from_reader(reader).map_err(|err| format!("Invalid JSON array: {}", err))?;
+        return Ok(Box::new(rows.into_iter().map(|row| serde_json::from_value::<Monster>(row).map_err(|err| err.to_string()))));
+    }
+    Ok(Box::new(
+        reader
+            .lines()
+            .filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+            .map(|line| serde_json::from_str::<Monster>(&line.map_err(|err| err.to_string())?).map_err(|err| err.to_string())),
+    ))
+}
+
+// The monsters of all the rows, owned by `owner_id`. An invalid row rejects the whole file.
+pub fn collect_monsters(rows: Vec<Result<Monster, String>>, format: &ImportFormat, owner_id: Option<&str>) -> Result<Vec<Monster>, String> {
+    let mut new_monsters = Vec::new();
+    for (index, row) in rows.into_iter().enumerate() {
+        match row {
+            Ok(monster) => new_monsters.push(Monster { owner_id: owner_id.map(str::to_string), ..monster }),
+            Err(err) => {
+                tracing::warn!(format = format.name(), row = index + 1, error = %err, "Invalid import row");
+                return Err("Incomplete data, check your file.".to_string());
+            }
+        }
+    }
+    if new_monsters.is_empty() {
+        return Err(format!("No valid monsters found in the {} file", format.name()));
+    }
+    Ok(new_monsters)
+}
+
+/*
+Imports the rows IMPORT_CHUNK_ROWS at a time, counting invalid rows as failed and recording the progress after each chunk.
+The result counts the monsters created, updated and skipped as duplicates, `imported` being the first two.
+*/
+pub fn import_chunks(monster_repository: &dyn MonsterRepository, path: &Path, format: &ImportFormat, mode: ImportMode, owner_id: Option<&str>, progress: &JobProgress) -> Result<serde_json::Value, String> {
+    let (mut processed, mut failed) = (0, 0);
+    let (mut created, mut updated, mut skipped) = (0, 0, 0);
+    let mut rows = format.rows(path)?.peekable();
+
+    while rows.peek().is_some() {
+        let mut chunk = Vec::new();
+        for row in rows.by_ref().take(IMPORT_CHUNK_ROWS) {
+            match row {
+                Ok(monster) => chunk.push(Monster { owner_id: owner_id.map(str::to_string), ..monster }),
+                Err(err) => {
+                    tracing::warn!(format = format.name(), row = processed + 1, error = %err, "Invalid import row");
+                    failed += 1;
+                }
+            }
+            processed += 1;
+        }
+        let imported = created + updated;
+        for outcome in monster_repository.import_monsters(chunk, mode).map_err(|err| err.to_string())? {
+            match outcome {
+                Ok(ImportOutcome::Created(_)) => created += 1,
+                Ok(ImportOutcome::Updated(_)) => updated += 1,
+                Ok(ImportOutcome::Skipped) => skipped += 1,
+                Err(_) => failed += 1,
+            }
+        }
+        format.rows_imported().inc_by((created + updated - imported) as u64);
+        progress.update(processed, failed);
+    }
+
+    if created + updated + skipped == 0 {
+        return Err(format!("No valid monsters found in the {} file", format.name()));
+    }
+    Ok(serde_json::json!({ "imported": created + updated, "created": created, "updated": updated, "skipped": skipped }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    #[test]
+    fn test_should_read_csv_files_with_other_delimiters_and_headers() {
+        assert_eq!(detect_delimiter("name;ATK;DEF;HP;SPD;image"), b';');
+        assert_eq!(detect_delimiter("name,attack"), b',');
+        assert_eq!(detect_delimiter("name"), b',');
+        assert!(CsvOptions::parse(Some(";;"), None).is_err());
+        assert!(CsvOptions::parse(None, Some(r#"{"Power": "power"}"#)).is_err());
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "Monster Name|ATK|DEF|Health|Velocity|Sprite\nlegacy|50|40|100|60|https://example.com/monster.png").unwrap();
+        let options = CsvOptions::parse(Some("|"), Some(r#"{"Monster Name": "name", "Velocity": "speed"}"#)).unwrap();
+        let monsters: Vec<Monster> = ImportFormat::Csv(options).rows(file.path()).unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!((monsters[0].name.as_str(), monsters[0].image_url.as_str()), ("legacy", "https://example.com/monster.png"));
+        assert_eq!((monsters[0].stats.attack, monsters[0].stats.defense, monsters[0].stats.hp, monsters[0].stats.speed), (50, 40, 100, 60));
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+use std::time::Instant;
+use actix_web::web;
+use serde_json::Value;
+use tokio::sync::Semaphore;
+use crate::config::AppConfig;
+use crate::models::job::Job;
+use crate::repository::error::RepositoryError;
+use crate::repository::job_repository::JobRepository;
+use crate::repository::request_scope::RequestScope;
+
+const DEFAULT_JOB_WORKERS: usize = 4;
+
+// Lets the work of a job record how many items it handled so far.
+pub struct JobProgress {
+    job_id: String,
+    job_repository: Arc<dyn JobRepository>,
+}
+
+impl JobProgress {
+    pub fn update(&self, processed: usize, failed: usize) {
+        if let Err(err) = self.job_repository.update_job_progress(&self.job_id, processed as i32, failed as i32) {
+            tracing::warn!(job_id = %self.job_id, error = %err, "Failed to record job progress");
+        }
+    }
+}
+
+/*
+Runs long work outside of the request that started it. Jobs are stored as queued, then run on the
+blocking thread pool once one of the worker slots is free, JOB_WORKERS (4 by default) at a time.
+*/
+#[derive(Clone)]
+pub struct JobQueue {
+    job_repository: Arc<dyn JobRepository>,
+    workers: Arc<Semaphore>,
+    worker_count: usize,
+}
+
+impl JobQueue {
+    pub fn new(job_repository: Arc<dyn JobRepository>, workers: usize) -> Self {
+        JobQueue { job_repository, workers: Arc::new(Semaphore::new(workers)), worker_count: workers }
+    }
+
+    pub fn from_config(job_repository: Arc<dyn JobRepository>, config: &AppConfig) -> Result<Self, String> {
+        let workers = match config.var("JOB_WORKERS") {
+            Some(workers) => match workers.trim().parse() {
+                Ok(workers) if workers > 0 => workers,
+                _ => return Err(format!("JOB_WORKERS must be a positive integer, got {:?}", workers)),
+            },
+            None => DEFAULT_JOB_WORKERS,
+        };
+        Ok(JobQueue::new(job_repository, workers))
+    }
+
+    // Stores the job and returns it right away, `work` resolves to the job result or an error message.
+    pub fn enqueue<F>(&self, kind: &str, total: usize, work: F) -> Result<Job, RepositoryError>
+    where
+        F: FnOnce(&JobProgress) -> Result<Value, String> + Send + 'static,
+    {
+        let job = self.job_repository.create_job(kind, total as i32)?;
+        let (job_repository, workers, job_id) = (self.job_repository.clone(), self.workers.clone(), job.id.clone());
+        // The job and its writes stay in the schema of the request, under its actor.
+        let scope = RequestScope::current();
+
+        actix_rt::spawn(scope.clone().scope(async move {
+            let Ok(_worker) = workers.acquire_owned().await else {
+                return tracing::warn!(job_id = %job_id, "Job left queued, the server is shutting down");
+            };
+            if let Err(err) = job_repository.start_job(&job_id) {
+                tracing::warn!(job_id = %job_id, error = %err, "Failed to mark job as running");
+            }
+            let progress = JobProgress { job_id: job_id.clone(), job_repository: job_repository.clone() };
+            let outcome = web::block(move || scope.sync_scope(|| work(&progress)))
+                .await
+                .unwrap_or_else(|_| Err("Job stopped unexpectedly".to_string()));
+            if let Err(err) = job_repository.finish_job(&job_id, outcome) {
+                tracing::error!(job_id = %job_id, error = %err, "Failed to record job outcome");
+            }
+        }));
+
+        Ok(job)
+    }
+
+    /*
+    Waits up to `deadline` for the running and queued jobs to finish, on shutdown, and returns whether they all did.
+    No job starts afterwards, the ones cut short by the exit stay recorded as running.
+    */
+    pub async fn drain(&self, deadline: Instant) -> bool {
+        let idle = self.workers.acquire_many(self.worker_count as u32);
+        let drained = matches!(actix_rt::time::timeout(deadline.saturating_duration_since(Instant::now()), idle).await, Ok(Ok(_)));
+        self.workers.close();
+        drained
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::job::JobStatus;
+    use crate::repository::audit_repository::with_actor;
+    use crate::repository::database::Database;
+    use crate::repository::memory_repository::InMemoryRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_run_the_job_in_the_schema_and_under_the_actor_of_the_request() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let job_queue = JobQueue::new(repository.clone(), 1);
+        let enqueue = async {
+            job_queue.enqueue("test", 1, |_| {
+                let scope = RequestScope::current();
+                Ok(serde_json::json!({ "schema": scope.schema, "actor": scope.actor }))
+            })
+        };
+        let job = Database::with_schema("scope_test".to_string(), with_actor("tester".to_string(), enqueue)).await.unwrap();
+
+        let mut job = repository.get_job_by_id(&job.id).unwrap();
+        while matches!(job.status, JobStatus::Queued | JobStatus::Running) {
+            actix_rt::time::sleep(std::time::Duration::from_millis(10)).await;
+            job = repository.get_job_by_id(&job.id).unwrap();
+        }
+        assert_eq!(job.status, JobStatus::Succeeded);
+        assert_eq!(job.result, Some(serde_json::json!({ "schema": "scope_test", "actor": "tester" })));
+    }
+}
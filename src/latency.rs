@@ -0,0 +1,231 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use chrono::NaiveDateTime;
+use diesel::connection::{Instrumentation, InstrumentationEvent};
+use serde::{Deserialize, Serialize};
+use crate::api::routes::Route;
+
+// Requests kept per route, the p95 is taken over the most recent ones.
+const MAX_SAMPLES: usize = 500;
+// Query spans listed per route breaching its budget.
+const TOP_QUERIES: usize = 5;
+
+tokio::task_local! {
+    static QUERY_SPANS: RefCell<Vec<QuerySpan>>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuerySpan {
+    // The SQL of the query, without its binds.
+    pub query: String,
+    pub elapsed: Duration,
+}
+
+struct Sample {
+    elapsed: Duration,
+    // Only kept for requests over the budget, the ones whose queries are reported.
+    queries: Vec<QuerySpan>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct QueryReport {
+    pub query: String,
+    pub count: usize,
+    pub total_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SlowRoute {
+    pub method: String,
+    pub route: String,
+    pub budget_ms: u64,
+    pub p95_ms: f64,
+    pub requests: usize,
+    pub slow_requests: usize,
+    // The queries of the requests over the budget, by the time they took in total.
+    pub top_queries: Vec<QueryReport>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SlowRouteReport {
+    pub generated_at: NaiveDateTime,
+    pub routes: Vec<SlowRoute>,
+}
+
+/*
+Recent request latencies of the routes with a latency budget, along with the database queries each slow
+request ran. A route whose p95 exceeds its budget is reported with the queries that took the most time,
+so regressions show up in `GET /admin/performance/slow_routes` without an external APM.
+*/
+pub struct LatencyTracker {
+    samples: Mutex<HashMap<(String, &'static str), VecDeque<Sample>>>,
+    latest: Mutex<Option<SlowRouteReport>>,
+}
+
+pub static LATENCY: LazyLock<LatencyTracker> = LazyLock::new(LatencyTracker::new);
+
+impl Default for LatencyTracker {
+    fn default() -> Self {
+        LatencyTracker::new()
+    }
+}
+
+impl LatencyTracker {
+    pub fn new() -> Self {
+        LatencyTracker { samples: Mutex::new(HashMap::new()), latest: Mutex::new(None) }
+    }
+
+    pub fn record(&self, method: &str, route: &'static str, budget: Duration, elapsed: Duration, queries: Vec<QuerySpan>) {
+        let queries = if elapsed > budget { queries } else { Vec::new() };
+        let mut samples = self.samples.lock().expect("Latency samples lock poisoned");
+        let route_samples = samples.entry((method.to_string(), route)).or_default();
+        if route_samples.len() == MAX_SAMPLES {
+            route_samples.pop_front();
+        }
+        route_samples.push_back(Sample { elapsed, queries });
+    }
+
+    // The routes of the registry whose p95 is over their budget, the furthest over it first.
+    pub fn report(&self, routes: &[Route]) -> SlowRouteReport {
+        let samples = self.samples.lock().expect("Latency samples lock poisoned");
+        let mut slow_routes: Vec<SlowRoute> = routes
+            .iter()
+            .filter_map(|route| {
+                let budget = route.latency_budget()?;
+                let route_samples = samples.get(&(route.method.to_string(), route.path))?;
+                let p95 = p95(route_samples);
+                (p95 > budget).then(|| SlowRoute {
+                    method: route.method.to_string(),
+                    route: route.path.to_string(),
+                    budget_ms: budget.as_millis() as u64,
+                    p95_ms: milliseconds(p95),
+                    requests: route_samples.len(),
+                    slow_requests: route_samples.iter().filter(|sample| sample.elapsed > budget).count(),
+                    top_queries: top_queries(route_samples),
+                })
+            })
+            .collect();
+        slow_routes.sort_by(|a, b| (b.p95_ms / b.budget_ms as f64).total_cmp(&(a.p95_ms / a.budget_ms as f64)));
+        SlowRouteReport { generated_at: chrono::Utc::now().naive_utc(), routes: slow_routes }
+    }
+
+    // Builds the periodic report, logging each route over its budget.
+    pub fn refresh_report(&self, routes: &[Route]) -> SlowRouteReport {
+        let report = self.report(routes);
+        for route in &report.routes {
+            tracing::warn!(method = %route.method, route = %route.route, budget_ms = route.budget_ms, p95_ms = route.p95_ms, "Route over its latency budget");
+        }
+        *self.latest.lock().expect("Latency report lock poisoned") = Some(report.clone());
+        report
+    }
+
+    // The last periodic report, built now when none was yet.
+    pub fn latest_report(&self, routes: &[Route]) -> SlowRouteReport {
+        let latest = self.latest.lock().expect("Latency report lock poisoned").clone();
+        latest.unwrap_or_else(|| self.refresh_report(routes))
+    }
+}
+
+fn p95(samples: &VecDeque<Sample>) -> Duration {
+    let mut elapsed: Vec<Duration> = samples.iter().map(|sample| sample.elapsed).collect();
+    elapsed.sort();
+    let index = (elapsed.len() * 95).div_ceil(100).saturating_sub(1);
+    elapsed.get(index).copied().unwrap_or_default()
+}
+
+fn top_queries(samples: &VecDeque<Sample>) -> Vec<QueryReport> {
+    let mut queries: HashMap<&str, QueryReport> = HashMap::new();
+    for span in samples.iter().flat_map(|sample| &sample.queries) {
+        let report = queries.entry(&span.query).or_insert_with(|| QueryReport { query: span.query.clone(), count: 0, total_ms: 0.0, max_ms: 0.0 });
+        report.count += 1;
+        report.total_ms += milliseconds(span.elapsed);
+        report.max_ms = report.max_ms.max(milliseconds(span.elapsed));
+    }
+    let mut queries: Vec<QueryReport> = queries.into_values().collect();
+    queries.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+    queries.truncate(TOP_QUERIES);
+    queries
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+// Runs the future, collecting the spans of the queries it ran on the current task.
+pub async fn profile<F: Future>(future: F) -> (F::Output, Vec<QuerySpan>) {
+    QUERY_SPANS
+        .scope(RefCell::new(Vec::new()), async move {
+            let output = future.await;
+            (output, QUERY_SPANS.with(|spans| spans.take()))
+        })
+        .await
+}
+
+/*
+Times the queries of a connection for `profile`. Queries run outside of a profiled future, such as in
+`web::block` or background jobs, aren't collected.
+*/
+#[derive(Default)]
+pub struct QueryTimer {
+    started: Option<Instant>,
+}
+
+impl Instrumentation for QueryTimer {
+    fn on_connection_event(&mut self, event: InstrumentationEvent<'_>) {
+        match event {
+            InstrumentationEvent::StartQuery { .. } => self.started = Some(Instant::now()),
+            InstrumentationEvent::FinishQuery { query, .. } => {
+                let Some(started) = self.started.take() else {
+                    return;
+                };
+                let query = query.to_string();
+                let query = query.split(" -- binds:").next().unwrap_or_default().to_string();
+                let _ = QUERY_SPANS.try_with(|spans| spans.borrow_mut().push(QuerySpan { query, elapsed: started.elapsed() }));
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::Method, web};
+    use crate::repository::database::Database;
+    use crate::repository::monster_repository::MonsterRepository;
+
+    use super::*;
+
+    fn span(query: &str, millis: u64) -> QuerySpan {
+        QuerySpan { query: query.to_string(), elapsed: Duration::from_millis(millis) }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_report_the_routes_over_their_budget_with_their_queries() {
+        let db = Database::new().unwrap();
+        let (monster, queries) = profile(async { db.get_monster_by_id("missing-monster") }).await;
+        assert!(monster.is_none());
+        assert!(queries.iter().any(|span| span.query.contains("\"monsters\"") && !span.query.contains("binds")));
+
+        let routes = vec![
+            Route::new(Method::GET, "/fast", |_: &mut web::ServiceConfig| {}).budget(100),
+            Route::new(Method::GET, "/slow", |_: &mut web::ServiceConfig| {}).budget(100),
+        ];
+        let tracker = LatencyTracker::new();
+        for _ in 0..19 {
+            tracker.record("GET", "/fast", Duration::from_millis(100), Duration::from_millis(20), vec![span("SELECT fast", 10)]);
+            tracker.record("GET", "/slow", Duration::from_millis(100), Duration::from_millis(250), vec![span("SELECT slow", 200), span("SELECT other", 5)]);
+        }
+        tracker.record("GET", "/fast", Duration::from_millis(100), Duration::from_millis(900), vec![span("SELECT outlier", 800)]);
+
+        let report = tracker.latest_report(&routes);
+        assert_eq!(report.routes.len(), 1);
+        let slow = &report.routes[0];
+        assert_eq!((slow.route.as_str(), slow.p95_ms, slow.requests, slow.slow_requests), ("/slow", 250.0, 19, 19));
+        assert_eq!(slow.top_queries.iter().map(|query| query.query.as_str()).collect::<Vec<_>>(), vec!["SELECT slow", "SELECT other"]);
+        assert_eq!((slow.top_queries[0].count, slow.top_queries[0].max_ms), (19, 200.0));
+    }
+}
@@ -1,3 +1,34 @@
+pub mod api;
+pub mod backup;
+pub mod balance;
+pub mod battle_engine;
+pub mod battle_events;
+pub mod cache;
+pub mod cards;
+pub mod config;
+pub mod events;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod highlights;
+pub mod import;
+pub mod jobs;
+pub mod latency;
+pub mod logging;
+pub mod maintenance;
+pub mod metrics;
+pub mod models;
+pub mod rate_limit;
+pub mod repository;
+pub mod rewards;
+pub mod seeds;
+pub mod shutdown;
+pub mod storage;
+pub mod thumbnails;
+pub mod tls;
+pub mod utils;
+pub mod validation;
+pub mod webhooks;
+
 pub fn add(left: usize, right: usize) -> usize {
     left + right
 }
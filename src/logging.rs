@@ -0,0 +1,97 @@
+use std::future::Future;
+use std::time::Instant;
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::Error;
+use tracing::Instrument;
+use tracing_subscriber::EnvFilter;
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/*
+Installs the global subscriber. The level comes from RUST_LOG (info by default)
+and LOG_FORMAT=json switches to one JSON object per line.
+*/
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    if std::env::var("LOG_FORMAT").is_ok_and(|format| format == "json") {
+        builder.json().init();
+    } else {
+        builder.init();
+    }
+}
+
+/*
+Runs the request inside a span carrying its id, taken from the X-Request-Id header or generated,
+echoes the id on the response and emits one access-log event once it completes.
+Middlewares below should answer with `req.error_response` rather than `Err`, so the id reaches error responses too.
+*/
+pub fn trace_request<S, B>(req: ServiceRequest, srv: &S) -> impl Future<Output = Result<ServiceResponse<BoxBody>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody + 'static,
+{
+    let request_id = req.headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let span = tracing::info_span!("request", request_id = %request_id);
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+    let start = Instant::now();
+    let response = srv.call(req).instrument(span.clone());
+
+    async move {
+        let mut response = response.await?.map_into_boxed_body();
+        if let Ok(value) = HeaderValue::from_str(&request_id) {
+            response.headers_mut().insert(HeaderName::from_static("x-request-id"), value);
+        }
+        span.in_scope(|| tracing::info!(
+            method = %method,
+            path = %path,
+            status = response.status().as_u16(),
+            elapsed_ms = start.elapsed().as_secs_f64() * 1000.0,
+            "request completed"
+        ));
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App, HttpResponse};
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_generate_a_request_id_when_missing() {
+        let app = App::new()
+            .wrap_fn(trace_request)
+            .route("/", web::get().to(HttpResponse::Ok));
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        let request_id = resp.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap();
+        assert!(uuid::Uuid::parse_str(request_id).is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn test_should_propagate_the_request_id_on_errors() {
+        let app = App::new()
+            .wrap_fn(trace_request)
+            .route("/", web::get().to(|| async { Err::<HttpResponse, _>(actix_web::error::ErrorBadRequest("Invalid")) }));
+        let app = test::init_service(app).await;
+
+        let req = test::TestRequest::get().uri("/").insert_header((REQUEST_ID_HEADER, "abc-123")).to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "abc-123");
+    }
+}
@@ -1,10 +1,14 @@
-use actix_web::{get, web, App, HttpResponse, HttpServer, Responder, Result};
+use std::sync::Arc;
+use actix_web::{dev::Service, get, middleware::Compress, web, App, HttpResponse, HttpServer, Responder, Result};
+use futures::future::{self, Either};
 use serde::{Serialize};
 
-mod api;
-mod models;
-mod repository;
-mod utils;
+use assessment_cc_rust_sr_01::{api, balance, battle_engine, cache, cards, config, events, jobs, latency, logging, maintenance, metrics, models, rate_limit, repository, rewards, seeds, shutdown, storage, thumbnails, tls, webhooks};
+
+const META_REFRESH_SECONDS: u64 = 300;
+const SLOW_ROUTES_REPORT_SECONDS: u64 = 60;
+const ADDRESS: &str = "127.0.0.1";
+const PORT: u16 = 8080;
 
 #[derive(Serialize)]
 pub struct Response {
@@ -27,20 +31,235 @@ async fn not_found() -> Result<HttpResponse> {
     Ok(HttpResponse::NotFound().json(response))
 }
 
+// The settings and services the server can't start without, logging why they're missing and exiting otherwise.
+fn or_exit<T, E: std::fmt::Display>(result: Result<T, E>) -> T {
+    result.unwrap_or_else(|err| {
+        tracing::error!(error = %err, "Failed to start");
+        std::process::exit(1)
+    })
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let todo_db = repository::database::Database::new();
-    let app_data = web::Data::new(todo_db);
+    logging::init();
+    let config = or_exit(config::AppConfig::load());
+    if let Some(file) = config.file() {
+        tracing::info!(file = %file.display(), "Loaded configuration file");
+    }
+    let cache = or_exit(cache::from_config(&config));
+    let events = or_exit(events::from_config(&config));
+    let todo_db = Arc::new(or_exit(repository::database::Database::from_config(&config)).with_cache(cache).with_events(events.clone()));
+    let migrations = or_exit(todo_db.migrate());
+    tracing::info!(migrations, "Migrated the database schemas");
+    let seed_profile = or_exit(seeds::SeedProfile::from_config(&config));
+    for scope in todo_db.schema_scopes() {
+        let schema = scope.schema.clone();
+        let seeds = scope.sync_scope(|| seeds::run_seeds(&mut todo_db.get_connection(), seed_profile));
+        let seeds = or_exit(seeds.map_err(|err| format!("Failed to run the {} seeds in the {} schema: {}", seed_profile, schema.as_deref().unwrap_or("default"), err)));
+        tracing::info!(profile = %seed_profile, schema = ?schema, seeds = seeds.len(), "Ran seeds");
+    }
+    let app_data = web::Data::from(todo_db.clone());
+    let cors_config = or_exit(api::cors::CorsConfig::from_config(&config));
+    let stat_decay = or_exit(models::decay::StatDecay::from_config(&config));
+    let status_effects = or_exit(models::status_effect::StatusEffectRules::from_config(&config)).map(web::Data::new);
+    let reward_pipeline = or_exit(rewards::RewardPipeline::from_config(&config)).map(web::Data::new);
+    let turn_delay = web::Data::new(or_exit(api::battle_apis::TurnDelay::from_config(&config)));
+    let heartbeat = web::Data::new(or_exit(api::battle_apis::StreamHeartbeat::from_config(&config)));
+    let turn_timeout = web::Data::new(or_exit(api::interactive_battle_apis::TurnTimeout::from_config(&config)));
+    let balance = or_exit(balance::BALANCE.load_from_config(&config));
+    let admin_endpoints = web::Data::new(or_exit(api::admin_apis::AdminEndpoints::from_config(&config)));
+    let payload_limits = or_exit(api::payload::PayloadLimits::from_config(&config));
+    let local_region = web::Data::new(or_exit(api::region::LocalRegion::from_config(&config)));
+    let engine = web::Data::from(or_exit(battle_engine::from_config(&config)));
+    let storage = web::Data::from(or_exit(storage::from_config(&config)));
+    let job_queue = web::Data::new(or_exit(jobs::JobQueue::from_config(todo_db.clone(), &config)));
+    let shutdown_timeout = or_exit(shutdown::timeout_from_config(&config));
+    let tls = or_exit(tls::from_config(&config));
+    #[cfg(feature = "grpc")]
+    let grpc_port = or_exit(assessment_cc_rust_sr_01::grpc::port_from_config(&config));
+    let production = config.var("APP_ENV").is_some_and(|env| env == "production");
+    let meta_cache = web::Data::new(api::analytics_apis::MetaCache::new(chrono::Duration::seconds(META_REFRESH_SECONDS as i64)));
+    let card_cache = web::Data::new(cards::CardCache::new());
+    let rate_limiter = web::Data::new(rate_limit::RateLimiter::new(todo_db.clone()));
+    let cors_origins = web::Data::new(api::cors::CorsOrigins::new(todo_db.clone()));
+    let balance_store = web::Data::from(balance::BALANCE.clone());
+
+    // Warmed before listening, so the first requests after a deploy don't all build the same snapshots.
+    for scope in todo_db.schema_scopes() {
+        let schema = scope.schema.clone();
+        let warmup = scope.sync_scope(|| api::cache_apis::warm_caches(todo_db.as_ref(), todo_db.as_ref(), todo_db.as_ref(), &meta_cache));
+        tracing::info!(schema = ?schema, meta_snapshots = warmup.meta_snapshots, duration_ms = warmup.duration_ms as u64, "Warmed caches");
+    }
 
-    HttpServer::new(move ||
+    // The scheduled work goes over the data of every schema.
+    for scope in todo_db.schema_scopes() {
+        let (job_db, job_cache) = (todo_db.clone(), meta_cache.clone());
+        actix_rt::spawn(scope.clone().scope(async move {
+            let refresh_period = std::time::Duration::from_secs(META_REFRESH_SECONDS);
+            let mut interval = actix_rt::time::interval_at(actix_rt::time::Instant::now() + refresh_period, refresh_period);
+            loop {
+                interval.tick().await;
+                api::analytics_apis::refresh_meta_snapshot(job_db.as_ref(), job_db.as_ref(), job_db.as_ref(), &job_cache);
+            }
+        }));
+        actix_rt::spawn(scope.clone().scope(maintenance::schedule_db_stats(job_queue.get_ref().clone(), todo_db.clone())));
+        actix_rt::spawn(scope.clone().scope(thumbnails::schedule_thumbnails(todo_db.clone(), storage.clone().into_inner())));
+        actix_rt::spawn(scope.scope(api::idempotency::purge_expired_idempotency_keys(todo_db.clone())));
+    }
+    actix_rt::spawn(async {
+        let report_period = std::time::Duration::from_secs(SLOW_ROUTES_REPORT_SECONDS);
+        let mut interval = actix_rt::time::interval_at(actix_rt::time::Instant::now() + report_period, report_period);
+        loop {
+            interval.tick().await;
+            latency::LATENCY.refresh_report(&api::config::ROUTES);
+        }
+    });
+    let reloaded_origins = cors_origins.clone();
+    actix_rt::spawn(async move {
+        let reload_period = std::time::Duration::from_secs(api::cors::ORIGINS_RELOAD_SECONDS);
+        let mut interval = actix_rt::time::interval_at(actix_rt::time::Instant::now() + reload_period, reload_period);
+        loop {
+            interval.tick().await;
+            reloaded_origins.reload();
+        }
+    });
+    // Battles are delivered to the webhooks of the schema they were stored in.
+    actix_rt::spawn(webhooks::dispatch_battles(todo_db.clone(), webhooks::FIRST_RETRY_DELAY));
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_port) = grpc_port {
+        let grpc = assessment_cc_rust_sr_01::grpc::serve(grpc_port, todo_db.clone(), engine.clone().into_inner(), stat_decay, status_effects.clone().map(|status_effects| status_effects.into_inner()));
+        actix_rt::spawn(async move {
+            if let Err(err) = grpc.await {
+                tracing::error!(error = %err, "gRPC server failed");
+            }
+        });
+        tracing::info!(port = grpc_port, "Serving gRPC");
+    }
+
+    tracing::info!(engine_version = battle_engine::ENGINE_VERSION, production, tls = tls.is_some(), cors_profile = ?cors_config.profile, region = local_region.name(), balance_version = %balance.version, "Starting server");
+    let app_config = web::Data::new(config);
+    let (drained_jobs, drained_db) = (job_queue.clone(), todo_db.clone());
+    let server = HttpServer::new(move ||
         App::new()
             .app_data(app_data.clone())
+            .app_data(meta_cache.clone())
+            .app_data(card_cache.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(cors_origins.clone())
+            .app_data(turn_delay.clone())
+            .app_data(heartbeat.clone())
+            .app_data(turn_timeout.clone())
+            .app_data(local_region.clone())
+            .app_data(admin_endpoints.clone())
+            .app_data(payload_limits.json_config())
+            .app_data(web::Data::new(payload_limits))
+            .app_data(app_config.clone())
+            .app_data(balance_store.clone())
+            .app_data(engine.clone())
+            .app_data(storage.clone())
+            .app_data(job_queue.clone())
+            .configure(|cfg| {
+                if let Some(stat_decay) = stat_decay {
+                    cfg.app_data(web::Data::new(stat_decay));
+                }
+                if let Some(status_effects) = status_effects.clone() {
+                    cfg.app_data(status_effects);
+                }
+                if let Some(reward_pipeline) = reward_pipeline.clone() {
+                    cfg.app_data(reward_pipeline);
+                }
+            })
+            .configure(api::config::repositories(todo_db.clone()))
             .configure(api::config::config)
+            .configure(|cfg| {
+                if !production {
+                    cfg.service(api::factory_apis::create_test_data);
+                }
+            })
             .service(healthcheck)
+            .service(api::metrics_apis::get_metrics)
             .default_service(web::route().to(not_found))
-            .wrap(actix_web::middleware::Logger::default())
+            .wrap_fn(api::payload::limit_multipart)
+            .wrap_fn({
+                let db = app_data.clone();
+                move |req, srv| {
+                    let requested = req.headers()
+                        .get(repository::database::SCHEMA_HEADER)
+                        .and_then(|value| value.to_str().ok());
+                    match db.request_schema(requested) {
+                        Ok(Some(schema)) => Either::Left(Either::Left(repository::database::Database::with_schema(schema, srv.call(req)))),
+                        Ok(None) => Either::Left(Either::Right(srv.call(req))),
+                        Err(message) => Either::Right(future::ready(Ok(req.error_response(actix_web::error::ErrorBadRequest(message))))),
+                    }
+                }
+            })
+            .wrap_fn({
+                let db = app_data.clone();
+                move |req, srv| {
+                    // Only writes are audited, reads skip looking up the credentials.
+                    let read = matches!(*req.method(), actix_web::http::Method::GET | actix_web::http::Method::HEAD | actix_web::http::Method::OPTIONS);
+                    let actor = if read { None } else { api::auth::actor(req.request(), db.get_ref(), db.get_ref()) };
+                    match actor {
+                        Some(actor) => Either::Left(repository::audit_repository::with_actor(actor, srv.call(req))),
+                        None => Either::Right(srv.call(req)),
+                    }
+                }
+            })
+            .wrap_fn(|req, srv| {
+                let method = req.method().to_string();
+                let route = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+                let start = std::time::Instant::now();
+                let response = srv.call(req);
+                async move {
+                    let response = response.await;
+                    let status = match &response {
+                        Ok(response) => response.status(),
+                        Err(err) => err.as_response_error().status_code(),
+                    };
+                    metrics::METRICS.observe_request(&method, &route, status.as_u16(), start.elapsed());
+                    response
+                }
+            })
+            .wrap(local_region.headers())
+            .wrap(cors_config.cors(&cors_origins))
+            // Gzip or brotli as the client accepts, streams and already compressed bodies opting out with Content-Encoding: identity.
+            .wrap(Compress::default())
+            .wrap_fn(logging::trace_request)
     )
-        .bind(("127.0.0.1", 8080))?
-        .run()
-        .await
+        .shutdown_timeout(shutdown_timeout.as_secs())
+        .disable_signals();
+    // Without the tls feature `tls::from_config` refuses the TLS settings, so the server binds plain HTTP.
+    let server = match &tls {
+        #[cfg(feature = "tls")]
+        Some(tls) => server.bind_rustls_0_23((ADDRESS, PORT), tls.server_config().map_err(std::io::Error::other)?)?,
+        _ => server.bind((ADDRESS, PORT))?,
+    }
+    .run();
+    let redirect = match tls.as_ref().and_then(|tls| tls.redirect_port) {
+        Some(redirect_port) => {
+            let redirect = tls::redirect_server(ADDRESS, redirect_port, PORT)?;
+            let redirect_handle = redirect.handle();
+            actix_rt::spawn(redirect);
+            tracing::info!(port = redirect_port, "Redirecting HTTP to HTTPS");
+            Some(redirect_handle)
+        }
+        None => None,
+    };
+
+    // Signals are handled here rather than by actix, for the jobs, events and connections to be drained after the requests.
+    let handle = server.handle();
+    let mut serving = actix_rt::spawn(server);
+    let signal = match future::select(&mut serving, Box::pin(shutdown::signal())).await {
+        Either::Left((served, _)) => return served.unwrap_or_else(|err| Err(std::io::Error::other(err))),
+        Either::Right((signal, _)) => signal,
+    };
+    let deadline = std::time::Instant::now() + shutdown_timeout;
+    tracing::info!(signal, timeout_seconds = shutdown_timeout.as_secs(), "Shutting down");
+    if let Some(redirect) = redirect {
+        redirect.stop(false).await;
+    }
+    handle.stop(true).await;
+    shutdown::drain(deadline, &drained_jobs, events, &drained_db).await;
+    tracing::info!("Shut down");
+    serving.await.unwrap_or_else(|err| Err(std::io::Error::other(err)))
 }
\ No newline at end of file
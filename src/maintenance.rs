@@ -0,0 +1,62 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{NaiveDateTime, Utc};
+use serde_json::Value;
+use crate::jobs::JobQueue;
+use crate::models::db_health::db_health;
+use crate::models::job::DB_STATS;
+use crate::repository::db_health_repository::DbHealthRepository;
+
+// The database statistics are snapshotted every night at this hour, when battles are the quietest.
+pub const DB_STATS_HOUR_UTC: u32 = 3;
+pub const DB_STATS_RETENTION_DAYS: i64 = 30;
+
+// Time left until the next run at `hour`, today's when it didn't pass yet.
+pub fn until_next_run(now: NaiveDateTime, hour: u32) -> Duration {
+    let today = now.date().and_hms_opt(hour, 0, 0).expect("Run hours are valid hours");
+    let next_run = if today > now { today } else { today + chrono::Duration::days(1) };
+    (next_run - now).to_std().expect("The next run is in the future")
+}
+
+// Takes the snapshot, the job result listing the warnings it raised, which are logged too.
+pub fn record_db_stats(repository: &dyn DbHealthRepository) -> Result<Value, String> {
+    let now = Utc::now().naive_utc();
+    let snapshot = repository
+        .take_db_stats_snapshot(now - chrono::Duration::days(DB_STATS_RETENTION_DAYS))
+        .map_err(|err| err.to_string())?;
+    let health = db_health(repository.get_db_stats_since(now - chrono::Duration::days(2)));
+    for warning in &health.warnings {
+        tracing::warn!(table = %warning.table, kind = ?warning.kind, "{}", warning.message);
+    }
+    Ok(serde_json::json!({ "tables": snapshot.len(), "warnings": health.warnings }))
+}
+
+// Enqueues the nightly statistics job, the jobs showing up at `GET /jobs/{id}` like the others.
+pub async fn schedule_db_stats(job_queue: JobQueue, repository: Arc<dyn DbHealthRepository>) {
+    loop {
+        actix_rt::time::sleep(until_next_run(Utc::now().naive_utc(), DB_STATS_HOUR_UTC)).await;
+        let repository = repository.clone();
+        if let Err(err) = job_queue.enqueue(DB_STATS, 1, move |progress| {
+            let result = record_db_stats(repository.as_ref());
+            progress.update(1, usize::from(result.is_err()));
+            result
+        }) {
+            tracing::error!(error = %err, "Failed to enqueue the database statistics job");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn test_should_wait_for_the_next_run_hour() {
+        let at = |hour, minute| NaiveDate::from_ymd_opt(2026, 10, 16).unwrap().and_hms_opt(hour, minute, 0).unwrap();
+        assert_eq!(until_next_run(at(1, 30), 3), Duration::from_secs(90 * 60));
+        assert_eq!(until_next_run(at(3, 0), 3), Duration::from_secs(24 * 3600));
+        assert_eq!(until_next_run(at(23, 0), 3), Duration::from_secs(4 * 3600));
+    }
+}
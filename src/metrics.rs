@@ -0,0 +1,109 @@
+use std::sync::LazyLock;
+use std::time::Duration;
+use diesel::r2d2::{event::CheckoutEvent, HandleEvent};
+use prometheus::{Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder};
+
+/*
+Process-wide metrics, rendered in the Prometheus text format by `GET /metrics`.
+Request metrics are labelled by route pattern rather than path, so ids don't create new series.
+*/
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    pub battles_simulated: IntCounter,
+    pub csv_rows_imported: IntCounter,
+    pub json_rows_imported: IntCounter,
+    pool_checkout_wait: Histogram,
+    cache_lookups: IntCounterVec,
+}
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by method, route and status"),
+            &["method", "route", "status"],
+        ).expect("Invalid http_requests_total metric");
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by method and route"),
+            &["method", "route"],
+        ).expect("Invalid http_request_duration_seconds metric");
+        let battles_simulated = IntCounter::new("battles_simulated_total", "Battles resolved by the simulator")
+            .expect("Invalid battles_simulated_total metric");
+        let csv_rows_imported = IntCounter::new("csv_rows_imported_total", "Monsters created from CSV imports")
+            .expect("Invalid csv_rows_imported_total metric");
+        let json_rows_imported = IntCounter::new("json_rows_imported_total", "Monsters created from JSON and NDJSON imports")
+            .expect("Invalid json_rows_imported_total metric");
+        let pool_checkout_wait = Histogram::with_opts(HistogramOpts::new(
+            "db_pool_checkout_wait_seconds",
+            "Time spent waiting for a pooled database connection",
+        ).buckets(vec![0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0]))
+            .expect("Invalid db_pool_checkout_wait_seconds metric");
+        let cache_lookups = IntCounterVec::new(
+            Opts::new("cache_lookups_total", "Cache lookups by cached value and result, hit or miss"),
+            &["cache", "result"],
+        ).expect("Invalid cache_lookups_total metric");
+
+        registry.register(Box::new(http_requests.clone())).expect("Failed to register http_requests_total");
+        registry.register(Box::new(http_request_duration.clone())).expect("Failed to register http_request_duration_seconds");
+        registry.register(Box::new(battles_simulated.clone())).expect("Failed to register battles_simulated_total");
+        registry.register(Box::new(csv_rows_imported.clone())).expect("Failed to register csv_rows_imported_total");
+        registry.register(Box::new(json_rows_imported.clone())).expect("Failed to register json_rows_imported_total");
+        registry.register(Box::new(pool_checkout_wait.clone())).expect("Failed to register db_pool_checkout_wait_seconds");
+        registry.register(Box::new(cache_lookups.clone())).expect("Failed to register cache_lookups_total");
+
+        Metrics { registry, http_requests, http_request_duration, battles_simulated, csv_rows_imported, json_rows_imported, pool_checkout_wait, cache_lookups }
+    }
+
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        self.http_requests
+            .with_label_values(&[method, route, status.to_string().as_str()])
+            .inc();
+        self.http_request_duration
+            .with_label_values(&[method, route])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_cache_lookup(&self, cache: &str, hit: bool) {
+        self.cache_lookups
+            .with_label_values(&[cache, if hit { "hit" } else { "miss" }])
+            .inc();
+    }
+
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("Failed to encode metrics");
+        String::from_utf8(buffer).expect("Metrics are not valid UTF-8")
+    }
+}
+
+// Feeds r2d2 checkout events into the pool wait histogram.
+#[derive(Debug)]
+pub struct PoolMetrics;
+
+impl HandleEvent for PoolMetrics {
+    fn handle_checkout(&self, event: CheckoutEvent) {
+        METRICS.pool_checkout_wait.observe(event.duration().as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_render_request_metrics_by_route() {
+        METRICS.observe_request("GET", "/api/monsters/{id}", 404, Duration::from_millis(3));
+
+        let rendered = METRICS.render();
+
+        assert!(rendered.contains(r#"http_requests_total{method="GET",route="/api/monsters/{id}",status="404"}"#));
+        assert!(rendered.contains("http_request_duration_seconds_bucket"));
+        assert!(rendered.contains("# TYPE battles_simulated_total counter"));
+    }
+}
@@ -0,0 +1,90 @@
+use std::io::Write;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use diesel::{Queryable, Insertable, AsExpression, FromSqlRow};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use crate::models::battle::Battle;
+
+// Wins in a row, the battle just won included, a monster needs for the win streak achievement.
+pub const WIN_STREAK_LENGTH: usize = 10;
+// Monsters a trainer owns once an import or a bulk creation earns it the collector achievement.
+pub const COLLECTOR_MONSTERS: i64 = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "snake_case")]
+pub enum AchievementKind {
+    FirstWin,
+    WinStreak,
+    FlawlessVictory,
+    Collector,
+}
+
+impl ToSql<Text, Pg> for AchievementKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let kind: &[u8] = match self {
+            AchievementKind::FirstWin => b"first_win",
+            AchievementKind::WinStreak => b"win_streak",
+            AchievementKind::FlawlessVictory => b"flawless_victory",
+            AchievementKind::Collector => b"collector",
+        };
+        out.write_all(kind)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for AchievementKind {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        match value.as_bytes() {
+            b"first_win" => Ok(AchievementKind::FirstWin),
+            b"win_streak" => Ok(AchievementKind::WinStreak),
+            b"flawless_victory" => Ok(AchievementKind::FlawlessVictory),
+            b"collector" => Ok(AchievementKind::Collector),
+            other => Err(format!("Unknown achievement kind: {}", String::from_utf8_lossy(other)).into()),
+        }
+    }
+}
+
+/*
+A badge earned once, by a monster winning battles or by a trainer for the monsters it owns. The achievements of
+monsters keep the trainer owning the monster when they were earned, so they are listed with the trainer's too.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::achievements)]
+pub struct Achievement {
+    pub id: String,
+    pub kind: AchievementKind,
+    #[serde(rename = "monster", skip_serializing_if = "Option::is_none")]
+    pub monster_id: Option<String>,
+    #[serde(rename = "trainer", skip_serializing_if = "Option::is_none")]
+    pub trainer_id: Option<String>,
+    #[serde(rename = "battle", skip_serializing_if = "Option::is_none")]
+    pub battle_id: Option<String>,
+    #[serde(rename = "earnedAt")]
+    pub earned_at: NaiveDateTime,
+}
+
+impl Achievement {
+    pub fn new(kind: AchievementKind, monster_id: Option<String>, trainer_id: Option<String>, battle_id: Option<String>, earned_at: NaiveDateTime) -> Self {
+        Achievement { id: uuid::Uuid::new_v4().to_string(), kind, monster_id, trainer_id, battle_id, earned_at }
+    }
+}
+
+/*
+The achievements the winner of the battle earns, given its latest battles newest first, this one included.
+Ones it already has are earned again, and left out when stored.
+*/
+pub fn battle_achievements(battle: &Battle, latest_battles: &[Battle]) -> Vec<AchievementKind> {
+    let Some(winner) = &battle.winner else { return Vec::new() };
+    let mut kinds = vec![AchievementKind::FirstWin];
+    if latest_battles.len() >= WIN_STREAK_LENGTH && latest_battles.iter().take(WIN_STREAK_LENGTH).all(|latest| latest.winner.as_ref() == Some(winner)) {
+        kinds.push(AchievementKind::WinStreak);
+    }
+    if battle.flawless {
+        kinds.push(AchievementKind::FlawlessVictory);
+    }
+    kinds
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MonsterUsage {
+    pub monster_id: String,
+    pub name: String,
+    pub battles: i64,
+    pub wins: i64,
+    pub usage_rate: f64,
+    pub win_rate: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MonsterMover {
+    pub monster_id: String,
+    pub name: String,
+    pub usage_rate_delta: f64,
+    pub win_rate_delta: f64,
+}
+
+// Counts the battles of the monsters knowing the move, the delta being against the prior period.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MoveUsage {
+    pub move_id: String,
+    pub name: String,
+    pub element: Option<String>,
+    pub battles: i64,
+    pub wins: i64,
+    pub usage_rate: f64,
+    pub win_rate: f64,
+    pub usage_rate_delta: f64,
+}
+
+// Counts the battles of the monsters knowing a move of the element, the delta being against the prior period.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ElementUsage {
+    pub element: String,
+    pub battles: i64,
+    pub wins: i64,
+    pub usage_rate: f64,
+    pub win_rate: f64,
+    pub usage_rate_delta: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MetaSnapshot {
+    pub days: i64,
+    pub manual: Option<bool>,
+    pub total_battles: i64,
+    pub most_used: Vec<MonsterUsage>,
+    pub biggest_movers: Vec<MonsterMover>,
+    pub most_used_moves: Vec<MoveUsage>,
+    pub most_used_elements: Vec<ElementUsage>,
+    #[serde(rename = "generatedAt")]
+    pub generated_at: chrono::NaiveDateTime,
+}
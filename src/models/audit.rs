@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+use diesel::{Queryable, Insertable};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::audit_log)]
+pub struct AuditEntry {
+    pub id: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub action: String,
+    pub actor: Option<String>,
+    pub diff: serde_json::Value,
+    #[serde(rename = "createdAt")]
+    pub created_at: chrono::NaiveDateTime,
+}
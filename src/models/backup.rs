@@ -0,0 +1,76 @@
+use std::collections::HashSet;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use crate::models::battle::{Battle, BattleOutcome};
+use crate::models::monster::Monster;
+
+// Bumped whenever restoring older backups needs converting them first.
+pub const BACKUP_VERSION: u32 = 1;
+
+// Every monster and battle as of one instant, as written by `GET /admin/backup`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Backup {
+    pub version: u32,
+    #[serde(rename = "createdAt")]
+    pub created_at: NaiveDateTime,
+    pub monsters: Vec<Monster>,
+    pub battles: Vec<Battle>,
+}
+
+// A line of NDJSON backups: the header first, then the monsters and the battles.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupLine {
+    Header {
+        version: u32,
+        #[serde(rename = "createdAt")]
+        created_at: NaiveDateTime,
+    },
+    Monster(Monster),
+    Battle(Battle),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreMode {
+    // Monsters and battles of the backup replace the ones with the same id, the others are kept.
+    #[default]
+    Merge,
+    /*
+    Every monster and battle is deleted first. Refused while rows the backup doesn't hold belong to them, like
+    moves, items, notes, training or trades, which deleting them would delete too.
+    */
+    Replace,
+    // Like `Replace`, deleting the rows belonging to the monsters and battles along with them.
+    ForceReplace,
+}
+
+impl Backup {
+    /*
+    Backups are restored whole or not at all, so they are checked before touching anything: ids are unique and
+    every battle is between monsters of the backup, won by one of them unless it's a draw.
+    */
+    pub fn check(&self) -> Result<(), String> {
+        if self.version > BACKUP_VERSION {
+            return Err(format!("Backup version {} is newer than the supported version {}", self.version, BACKUP_VERSION));
+        }
+        let mut monster_ids = HashSet::new();
+        if let Some(monster) = self.monsters.iter().find(|monster| !monster_ids.insert(monster.id.as_str())) {
+            return Err(format!("Monster {} appears twice in the backup", monster.id));
+        }
+        let mut battle_ids = HashSet::new();
+        for battle in &self.battles {
+            if !battle_ids.insert(battle.id.as_str()) {
+                return Err(format!("Battle {} appears twice in the backup", battle.id));
+            }
+            let participants = [battle.monster_a.as_str(), battle.monster_b.as_str()];
+            if let Some(monster_id) = participants.into_iter().chain(battle.winner.as_deref()).find(|monster_id| !monster_ids.contains(monster_id)) {
+                return Err(format!("Battle {} references monster {} missing from the backup", battle.id, monster_id));
+            }
+            if battle.winner.as_deref().is_some_and(|winner| !participants.contains(&winner)) || (battle.outcome == BattleOutcome::Draw) != battle.winner.is_none() {
+                return Err(format!("Battle {} has a winner that doesn't match its outcome", battle.id));
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,47 @@
+use std::io::Write;
 use serde::{Deserialize, Serialize};
-use diesel::{Queryable, Insertable, AsChangeset, Identifiable, Associations};
+use diesel::{Queryable, Insertable, AsChangeset, Identifiable, Associations, AsExpression, FromSqlRow};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
 use crate::models::monster::Monster;
+use crate::models::rules::BattleRules;
+
+/*
+How a battle ended. Draws have no winner, forfeits are won by the monster that did not forfeit.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum BattleOutcome {
+    Win,
+    Draw,
+    Forfeit,
+}
+
+impl ToSql<Text, Pg> for BattleOutcome {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let outcome: &[u8] = match self {
+            BattleOutcome::Win => b"win",
+            BattleOutcome::Draw => b"draw",
+            BattleOutcome::Forfeit => b"forfeit",
+        };
+        out.write_all(outcome)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for BattleOutcome {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        match value.as_bytes() {
+            b"win" => Ok(BattleOutcome::Win),
+            b"draw" => Ok(BattleOutcome::Draw),
+            b"forfeit" => Ok(BattleOutcome::Forfeit),
+            other => Err(format!("Unknown battle outcome: {}", String::from_utf8_lossy(other)).into()),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone, Queryable, Insertable, AsChangeset, Identifiable, Associations)]
 #[diesel(belongs_to(Monster, foreign_key = winner))]
@@ -9,9 +50,81 @@ pub struct Battle {
     pub id: String,
     pub monster_a: String,
     pub monster_b: String,
-    pub winner: String,
+    pub winner: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<chrono::NaiveDateTime>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<chrono::NaiveDateTime>,
+    pub outcome: BattleOutcome,
+    // Manual battles were played outside of the simulator and recorded with their result.
+    pub manual: bool,
+    // The season open when the battle was stored, set by the repository.
+    #[serde(rename = "season", default, skip_serializing_if = "Option::is_none")]
+    pub season_id: Option<String>,
+    // The rules of simulated battles not played with the classic ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rules: Option<BattleRules>,
+    // The trainers owning monster A and B when the battle was stored, set by the repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trainer_a: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trainer_b: Option<String>,
+    // The winner didn't lose any HP, only known for the battles played by the simulator or interactively.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub flawless: bool,
+    // How exciting the battle was to watch out of 100, only scored for the battles played by the simulator or interactively.
+    #[serde(rename = "highlightScore", default)]
+    pub highlight_score: i32,
+    // The region of the trainer owning monster A, or monster B's when it has none, set by the repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    // The version of the balance the battle was played with, none for manual battles and the ones played before balances.
+    #[serde(rename = "balanceVersion", default, skip_serializing_if = "Option::is_none")]
+    pub balance_version: Option<String>,
+    // The ids of the moves monster A and B used, empty for manual battles and the ones played without moves.
+    #[serde(rename = "movesA", default, skip_serializing_if = "Vec::is_empty")]
+    pub moves_a: Vec<String>,
+    #[serde(rename = "movesB", default, skip_serializing_if = "Vec::is_empty")]
+    pub moves_b: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExpandedBattle {
+    pub id: String,
+    pub monster_a: Option<Monster>,
+    pub monster_b: Option<Monster>,
+    pub winner: Option<Monster>,
     #[serde(rename = "createdAt")]
     pub created_at: Option<chrono::NaiveDateTime>,
     #[serde(rename = "updatedAt")]
     pub updated_at: Option<chrono::NaiveDateTime>,
+    pub outcome: BattleOutcome,
+    pub manual: bool,
+    #[serde(rename = "season", default, skip_serializing_if = "Option::is_none")]
+    pub season_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rules: Option<BattleRules>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trainer_a: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trainer_b: Option<String>,
+}
+
+impl From<(Battle, Option<Monster>, Option<Monster>, Option<Monster>)> for ExpandedBattle {
+    fn from((battle, monster_a, monster_b, winner): (Battle, Option<Monster>, Option<Monster>, Option<Monster>)) -> Self {
+        ExpandedBattle {
+            id: battle.id,
+            monster_a,
+            monster_b,
+            winner,
+            created_at: battle.created_at,
+            updated_at: battle.updated_at,
+            outcome: battle.outcome,
+            manual: battle.manual,
+            season_id: battle.season_id,
+            rules: battle.rules,
+            trainer_a: battle.trainer_a,
+            trainer_b: battle.trainer_b,
+        }
+    }
 }
\ No newline at end of file
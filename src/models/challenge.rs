@@ -0,0 +1,89 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use diesel::{Queryable, Insertable};
+use crate::models::rules::PRESETS;
+
+// Challengers listed with the challenge of the day, the best first.
+pub const LEADERBOARD_SIZE: usize = 10;
+
+// The featured opponent and rule preset every challenger fights on the day.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::daily_challenges)]
+pub struct DailyChallenge {
+    #[serde(rename = "date")]
+    pub challenge_date: NaiveDate,
+    pub opponent: String,
+    pub preset: String,
+    #[serde(skip_serializing, default)]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::challenge_attempts)]
+pub struct ChallengeAttempts {
+    #[serde(skip_serializing, default)]
+    pub challenge_date: NaiveDate,
+    #[serde(rename = "monster")]
+    pub monster_id: String,
+    pub attempts: i32,
+    pub wins: i32,
+    #[serde(rename = "lastAttemptAt")]
+    pub last_attempt_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChallengeStanding {
+    pub rank: i32,
+    #[serde(flatten)]
+    pub attempts: ChallengeAttempts,
+}
+
+/*
+Picks the challenge of the day among the monsters, seeded by the date so every instance picks the same
+one for the same monsters. The pick is stored on the first request of the day, monsters created later
+in the day leave it as it is. None without monsters to fight.
+*/
+pub fn pick_daily_challenge(date: NaiveDate, monster_ids: &[String], created_at: NaiveDateTime) -> Option<DailyChallenge> {
+    if monster_ids.is_empty() {
+        return None;
+    }
+    let mut monster_ids = monster_ids.to_vec();
+    monster_ids.sort();
+    let mut rolls = ChaCha8Rng::seed_from_u64(date.num_days_from_ce() as u64);
+    let opponent = monster_ids.swap_remove(rolls.gen_range(0..monster_ids.len()));
+    let preset = PRESETS[rolls.gen_range(0..PRESETS.len())].to_string();
+    Some(DailyChallenge { challenge_date: date, opponent, preset, created_at })
+}
+
+// Ranks every challenger by wins, then by the fewest attempts, the first to get there ranking first on ties.
+pub fn leaderboard(mut attempts: Vec<ChallengeAttempts>) -> Vec<ChallengeStanding> {
+    attempts.sort_by(|a, b| b.wins.cmp(&a.wins).then(a.attempts.cmp(&b.attempts)).then(a.last_attempt_at.cmp(&b.last_attempt_at)));
+    attempts
+        .into_iter()
+        .enumerate()
+        .map(|(position, attempts)| ChallengeStanding { rank: position as i32 + 1, attempts })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_pick_the_same_challenge_for_the_same_day() {
+        let created_at = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let monster_ids: Vec<String> = (0..20).map(|n| format!("monster-{:02}", n)).collect();
+        let mut shuffled = monster_ids.clone();
+        shuffled.reverse();
+        let days: Vec<DailyChallenge> = (0..10)
+            .map(|day| pick_daily_challenge(created_at.date() + chrono::Duration::days(day), &monster_ids, created_at).unwrap())
+            .collect();
+
+        assert_eq!(pick_daily_challenge(created_at.date(), &shuffled, created_at).as_ref(), Some(&days[0]));
+        assert!(days.iter().any(|day| day.opponent != days[0].opponent));
+        assert!(days.iter().all(|day| PRESETS.contains(&day.preset.as_str())));
+        assert_eq!(pick_daily_challenge(created_at.date(), &[], created_at), None);
+    }
+}
@@ -0,0 +1,32 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use diesel::{Insertable, Queryable};
+
+// An origin allowed cross-origin access in production.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::cors_origins)]
+pub struct CorsOrigin {
+    pub origin: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: NaiveDateTime,
+}
+
+/*
+Origins are compared as browsers send them: a scheme, a host and an optional port, without a path. They are
+lowercased so the same origin isn't stored twice.
+*/
+pub fn parse_origin(origin: &str) -> Result<String, String> {
+    let origin = origin.trim().to_lowercase();
+    let host = origin
+        .strip_prefix("https://")
+        .or_else(|| origin.strip_prefix("http://"))
+        .ok_or_else(|| format!("Origin {:?} must start with http:// or https://", origin))?;
+    let (name, port) = host.split_once(':').map_or((host, None), |(name, port)| (name, Some(port)));
+    let valid_name = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    let valid_port = port.is_none_or(|port| port.parse::<u16>().is_ok());
+    if !valid_name || !valid_port {
+        return Err(format!("Origin {:?} must be a scheme, a host and an optional port, without a path", origin));
+    }
+    Ok(origin)
+}
+
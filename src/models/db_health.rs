@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use diesel::{Queryable, QueryableByName, Insertable};
+
+// Tables smaller than this are expected to be scanned sequentially, the planner being right to skip their indexes.
+pub const HOT_TABLE_ROWS: i64 = 1000;
+// Sequential scans a hot table needs between two snapshots before their share counts.
+pub const MIN_SEQ_SCANS: i64 = 100;
+// Share of the scans of a hot table that may be sequential before it is reported.
+pub const MAX_SEQ_SCAN_PERCENT: i64 = 50;
+// Share of dead rows a table may hold before it is reported as bloated, when it holds at least HOT_TABLE_ROWS of them.
+pub const MAX_DEAD_ROW_PERCENT: i64 = 20;
+
+// The Postgres statistics of a table when the snapshot was taken.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, QueryableByName, Insertable)]
+#[diesel(table_name = crate::repository::schema::db_table_stats)]
+pub struct TableStats {
+    #[serde(rename = "takenAt")]
+    pub taken_at: NaiveDateTime,
+    #[serde(rename = "table")]
+    pub table_name: String,
+    pub live_rows: i64,
+    pub dead_rows: i64,
+    // Cumulative since the statistics were last reset.
+    pub seq_scans: i64,
+    pub index_scans: i64,
+    pub total_bytes: i64,
+    pub last_vacuum: Option<NaiveDateTime>,
+    pub last_autovacuum: Option<NaiveDateTime>,
+}
+
+// The scans made between a snapshot and the one before it, with the rows when it was taken.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TableTrend {
+    #[serde(rename = "takenAt")]
+    pub taken_at: NaiveDateTime,
+    pub seq_scans: i64,
+    pub index_scans: i64,
+    pub live_rows: i64,
+    pub dead_rows: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TableHealth {
+    #[serde(flatten)]
+    pub latest: TableStats,
+    pub dead_row_percent: i64,
+    // Oldest first, one entry per snapshot after the first one.
+    pub trend: Vec<TableTrend>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DbHealthWarningKind {
+    // Most scans of a hot table were sequential since the previous snapshot, its queries likely stopped using indexes.
+    SequentialScans,
+    DeadRows,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DbHealthWarning {
+    pub table: String,
+    pub kind: DbHealthWarningKind,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DbHealth {
+    // The latest snapshot, none before the first one was taken.
+    #[serde(rename = "takenAt")]
+    pub taken_at: Option<NaiveDateTime>,
+    pub tables: Vec<TableHealth>,
+    pub warnings: Vec<DbHealthWarning>,
+}
+
+fn percent(part: i64, total: i64) -> i64 {
+    if total == 0 { 0 } else { part * 100 / total }
+}
+
+// Counters going down were reset in between, the scans since then being the whole counter.
+fn since(previous: i64, current: i64) -> i64 {
+    if current >= previous { current - previous } else { current }
+}
+
+// Builds the health of every table from its snapshots, in any order, tables dropped since then being left out.
+pub fn db_health(snapshots: Vec<TableStats>) -> DbHealth {
+    let taken_at = snapshots.iter().map(|stats| stats.taken_at).max();
+    let mut by_table: BTreeMap<String, Vec<TableStats>> = BTreeMap::new();
+    for stats in snapshots {
+        by_table.entry(stats.table_name.clone()).or_default().push(stats);
+    }
+
+    let mut tables = Vec::new();
+    let mut warnings = Vec::new();
+    for (table, mut history) in by_table {
+        history.sort_by_key(|stats| stats.taken_at);
+        if Some(history[history.len() - 1].taken_at) != taken_at {
+            continue;
+        }
+        let trend: Vec<TableTrend> = history
+            .windows(2)
+            .map(|pair| TableTrend {
+                taken_at: pair[1].taken_at,
+                seq_scans: since(pair[0].seq_scans, pair[1].seq_scans),
+                index_scans: since(pair[0].index_scans, pair[1].index_scans),
+                live_rows: pair[1].live_rows,
+                dead_rows: pair[1].dead_rows,
+            })
+            .collect();
+        let latest = history.pop().expect("Tables have at least one snapshot");
+        let dead_row_percent = percent(latest.dead_rows, latest.live_rows + latest.dead_rows);
+
+        if let Some(last) = trend.last() {
+            let seq_scan_percent = percent(last.seq_scans, last.seq_scans + last.index_scans);
+            if latest.live_rows >= HOT_TABLE_ROWS && last.seq_scans >= MIN_SEQ_SCANS && seq_scan_percent > MAX_SEQ_SCAN_PERCENT {
+                let before = match trend.iter().rev().nth(1) {
+                    Some(before) => format!(", {}% the snapshot before", percent(before.seq_scans, before.seq_scans + before.index_scans)),
+                    None => String::new(),
+                };
+                warnings.push(DbHealthWarning {
+                    table: table.clone(),
+                    kind: DbHealthWarningKind::SequentialScans,
+                    message: format!("{}% of the scans of {} were sequential since the previous snapshot{}", seq_scan_percent, table, before),
+                });
+            }
+        }
+        if latest.dead_rows >= HOT_TABLE_ROWS && dead_row_percent > MAX_DEAD_ROW_PERCENT {
+            warnings.push(DbHealthWarning {
+                table: table.clone(),
+                kind: DbHealthWarningKind::DeadRows,
+                message: format!("{}% of the rows of {} are dead, it needs a vacuum", dead_row_percent, table),
+            });
+        }
+        tables.push(TableHealth { latest, dead_row_percent, trend });
+    }
+    DbHealth { taken_at, tables, warnings }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate};
+
+    use super::*;
+
+    fn stats(night: i64, table: &str, live_rows: i64, dead_rows: i64, seq_scans: i64, index_scans: i64) -> TableStats {
+        TableStats {
+            taken_at: NaiveDate::from_ymd_opt(2026, 10, 1).unwrap().and_hms_opt(3, 0, 0).unwrap() + Duration::days(night),
+            table_name: table.to_string(),
+            live_rows,
+            dead_rows,
+            seq_scans,
+            index_scans,
+            total_bytes: 8192,
+            last_vacuum: None,
+            last_autovacuum: None,
+        }
+    }
+
+    #[test]
+    fn test_should_warn_about_hot_tables_scanned_sequentially_and_dead_rows() {
+        let health = db_health(vec![
+            stats(0, "battles", 5000, 0, 10, 1000),
+            stats(1, "battles", 6000, 0, 20, 2000),
+            stats(2, "battles", 7000, 0, 520, 2100),
+            stats(2, "monsters", 50, 0, 10_000, 0),
+            stats(2, "jobs", 2000, 3000, 0, 0),
+            stats(1, "dropped", 2000, 0, 0, 0),
+        ]);
+
+        assert_eq!(health.tables.iter().map(|table| table.latest.table_name.as_str()).collect::<Vec<_>>(), vec!["battles", "jobs", "monsters"]);
+        let battles = &health.tables[0];
+        assert_eq!(battles.trend.iter().map(|trend| (trend.seq_scans, trend.index_scans)).collect::<Vec<_>>(), vec![(10, 1000), (500, 100)]);
+        assert_eq!(health.warnings.iter().map(|warning| (warning.table.as_str(), warning.kind)).collect::<Vec<_>>(), vec![
+            ("battles", DbHealthWarningKind::SequentialScans),
+            ("jobs", DbHealthWarningKind::DeadRows),
+        ]);
+        assert_eq!(health.warnings[0].message, "83% of the scans of battles were sequential since the previous snapshot, 0% the snapshot before");
+    }
+}
@@ -0,0 +1,122 @@
+use chrono::NaiveDateTime;
+use crate::config::AppConfig;
+use crate::models::fixed_point::Fixed;
+use crate::models::monster::{Monster, StatModifiers, Stats};
+
+const DEFAULT_DAILY_RATE: Fixed = Fixed::from_percent(1);
+const DEFAULT_MAX_DECAY: Fixed = Fixed::from_percent(30);
+
+/*
+Optional rule lowering the attack, defense and speed of monsters that haven't battled for
+`after_days`, by `daily_rate` per extra idle day and at most `max_decay`. It is computed when
+monsters are read or fought, the stored base stats are never changed.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatDecay {
+    pub after_days: i64,
+    pub daily_rate: Fixed,
+    pub max_decay: Fixed,
+}
+
+impl StatDecay {
+    // The rule is disabled (None) unless STAT_DECAY_AFTER_DAYS is set.
+    pub fn from_config(config: &AppConfig) -> Result<Option<Self>, String> {
+        StatDecay::from_vars(|name| config.var(name))
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Option<Self>, String> {
+        let after_days = match var("STAT_DECAY_AFTER_DAYS") {
+            Some(after_days) => parse(&after_days, "STAT_DECAY_AFTER_DAYS")?,
+            None => return Ok(None),
+        };
+        let daily_rate = var("STAT_DECAY_DAILY_RATE")
+            .map(|daily_rate| parse(&daily_rate, "STAT_DECAY_DAILY_RATE"))
+            .transpose()?
+            .unwrap_or(DEFAULT_DAILY_RATE);
+        let max_decay = var("STAT_DECAY_MAX")
+            .map(|max_decay| parse(&max_decay, "STAT_DECAY_MAX"))
+            .transpose()?
+            .unwrap_or(DEFAULT_MAX_DECAY);
+
+        if after_days < 0 || daily_rate < Fixed::ZERO || !(Fixed::ZERO..=Fixed::ONE).contains(&max_decay) {
+            return Err("Stat decay needs non-negative days and rate, and a max decay between 0 and 1".to_string());
+        }
+        Ok(Some(StatDecay { after_days, daily_rate, max_decay }))
+    }
+
+    // Idle time counts from the last battle, or from creation for monsters that never fought.
+    pub fn modifiers(&self, monster: &Monster, now: NaiveDateTime) -> StatModifiers {
+        let idle_since = match monster.last_battle_at.or(monster.created_at) {
+            Some(idle_since) => idle_since,
+            None => return StatModifiers::default(),
+        };
+        let decaying_days = (now - idle_since).num_days() - self.after_days;
+        if decaying_days <= 0 {
+            return StatModifiers::default();
+        }
+
+        let factor = Fixed::ONE - self.daily_rate.times(decaying_days).min(self.max_decay);
+        StatModifiers { attack: factor, defense: factor, speed: factor }
+    }
+
+    pub fn effective_stats(&self, monster: &Monster, now: NaiveDateTime) -> Stats {
+        monster.stats.with_modifiers(&self.modifiers(monster, now))
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, String> {
+    value.trim().parse().map_err(|_| format!("{} must be a number, got {:?}", name, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use chrono::{Duration, Utc};
+
+    use super::*;
+
+    fn decay_rule(vars: &[(&str, &str)]) -> Result<Option<StatDecay>, String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        StatDecay::from_vars(|name| vars.get(name).cloned())
+    }
+
+    fn monster(last_battle_at: Option<NaiveDateTime>) -> Monster {
+        Monster {
+            id: "id".to_string(),
+            image_url: "https://loremflickr.com/640/480".to_string(),
+            stats: Stats { attack: 40, defense: 20, hp: 50, speed: 80 },
+            created_at: None,
+            updated_at: None,
+            name: "monster".to_string(),
+            last_battle_at,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        }
+    }
+
+    #[test]
+    fn test_should_only_enable_the_rule_when_configured() {
+        assert_eq!(decay_rule(&[]), Ok(None));
+        assert_eq!(
+            decay_rule(&[("STAT_DECAY_AFTER_DAYS", "7"), ("STAT_DECAY_DAILY_RATE", "0.05")]),
+            Ok(Some(StatDecay { after_days: 7, daily_rate: Fixed::from_percent(5), max_decay: DEFAULT_MAX_DECAY }))
+        );
+        assert!(decay_rule(&[("STAT_DECAY_AFTER_DAYS", "a week")]).is_err());
+        assert!(decay_rule(&[("STAT_DECAY_AFTER_DAYS", "7"), ("STAT_DECAY_MAX", "1.5")]).is_err());
+    }
+
+    #[test]
+    fn test_should_decay_stats_after_the_idle_period_up_to_the_max() {
+        let decay = StatDecay { after_days: 7, daily_rate: Fixed::from_percent(10), max_decay: Fixed::from_percent(30) };
+        let now = Utc::now().naive_utc();
+
+        assert_eq!(decay.effective_stats(&monster(Some(now - Duration::days(7))), now), monster(None).stats);
+        assert_eq!(decay.effective_stats(&monster(Some(now - Duration::days(9))), now), Stats { attack: 32, defense: 16, hp: 50, speed: 64 });
+        assert_eq!(decay.effective_stats(&monster(Some(now - Duration::days(60))), now), Stats { attack: 28, defense: 14, hp: 50, speed: 56 });
+        assert_eq!(decay.effective_stats(&monster(None), now), monster(None).stats);
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use diesel::QueryableByName;
+use serde::{Deserialize, Serialize};
+use crate::models::monster::Monster;
+
+// Two monsters with identical stats whose names are at least `similarity` alike.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, QueryableByName)]
+pub struct DuplicatePair {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub monster_a: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub monster_b: String,
+    #[diesel(sql_type = diesel::sql_types::Float4)]
+    pub similarity: f32,
+}
+
+/*
+Monsters that are likely copies of each other. `keep` is the oldest of them, the one the others would be
+merged into, and `confidence` the mean name similarity of the pairs linking the cluster.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DuplicateCluster {
+    pub keep: String,
+    pub duplicates: Vec<String>,
+    pub confidence: f64,
+}
+
+/*
+Trigram similarity of two names as computed by pg_trgm: the words are lowercased and padded with two spaces in
+front and one behind, then the shared trigrams are divided by all the distinct trigrams of both names.
+*/
+pub fn trigram_similarity(a: &str, b: &str) -> f32 {
+    let (a, b) = (trigrams(a), trigrams(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let shared = a.intersection(&b).count();
+    shared as f32 / (a.len() + b.len() - shared) as f32
+}
+
+fn trigrams(name: &str) -> HashSet<[char; 3]> {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .flat_map(|word| {
+            let padded: Vec<char> = format!("  {} ", word.to_lowercase()).chars().collect();
+            padded.windows(3).map(|trigram| [trigram[0], trigram[1], trigram[2]]).collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+// Links the pairs into clusters of monsters, the most confident clusters first.
+pub fn cluster_duplicates(pairs: &[DuplicatePair], monsters: &[Monster]) -> Vec<DuplicateCluster> {
+    let mut parents: HashMap<&str, &str> = HashMap::new();
+    fn root<'a>(parents: &mut HashMap<&'a str, &'a str>, monster_id: &'a str) -> &'a str {
+        let parent = *parents.entry(monster_id).or_insert(monster_id);
+        if parent == monster_id {
+            return monster_id;
+        }
+        let root_id = root(parents, parent);
+        parents.insert(monster_id, root_id);
+        root_id
+    }
+    for pair in pairs {
+        let (root_a, root_b) = (root(&mut parents, &pair.monster_a), root(&mut parents, &pair.monster_b));
+        if root_a != root_b {
+            parents.insert(root_b, root_a);
+        }
+    }
+
+    let mut members: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    let monster_ids: Vec<&str> = parents.keys().copied().collect();
+    for monster_id in monster_ids {
+        let root_id = root(&mut parents, monster_id);
+        members.entry(root_id).or_default().push(monster_id);
+    }
+    let mut similarities: HashMap<&str, Vec<f32>> = HashMap::new();
+    for pair in pairs {
+        let root_id = root(&mut parents, &pair.monster_a);
+        similarities.entry(root_id).or_default().push(pair.similarity);
+    }
+
+    // Monsters without a creation date are considered the newest.
+    let created_at: HashMap<&str, _> = monsters.iter().map(|monster| (monster.id.as_str(), monster.created_at)).collect();
+    let mut clusters: Vec<DuplicateCluster> = members
+        .into_iter()
+        .map(|(root_id, mut monster_ids)| {
+            monster_ids.sort_by_key(|monster_id| {
+                let created_at = created_at.get(monster_id).copied().flatten();
+                (created_at.is_none(), created_at, *monster_id)
+            });
+            let similarities = &similarities[root_id];
+            let confidence = similarities.iter().map(|&similarity| similarity as f64).sum::<f64>() / similarities.len() as f64;
+            DuplicateCluster {
+                keep: monster_ids[0].to_string(),
+                duplicates: monster_ids[1..].iter().map(|monster_id| monster_id.to_string()).collect(),
+                confidence: (confidence * 1000.0).round() / 1000.0,
+            }
+        })
+        .collect();
+    clusters.sort_by(|a, b| b.confidence.total_cmp(&a.confidence).then(b.duplicates.len().cmp(&a.duplicates.len())).then(a.keep.cmp(&b.keep)));
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::monster::Stats;
+
+    use super::*;
+
+    fn pair(monster_a: &str, monster_b: &str, similarity: f32) -> DuplicatePair {
+        DuplicatePair { monster_a: monster_a.to_string(), monster_b: monster_b.to_string(), similarity }
+    }
+
+    #[test]
+    fn test_should_measure_name_similarity_with_trigrams() {
+        assert_eq!(trigram_similarity("Dead Unicorn", "dead unicorn"), 1.0);
+        assert_eq!(trigram_similarity("word", "two words"), 0.36363637);
+        assert!(trigram_similarity("Dead Unicorn", "Dead Unicorn 2") > 0.7);
+        assert_eq!(trigram_similarity("Dragon", "Slime"), 0.0);
+        assert_eq!(trigram_similarity("", "Slime"), 0.0);
+    }
+
+    #[test]
+    fn test_should_group_linked_pairs_into_clusters_keeping_the_oldest_monster() {
+        let monster = |id: &str, day: u32| Monster {
+            id: id.to_string(),
+            image_url: String::new(),
+            stats: Stats { attack: 10, defense: 10, hp: 10, speed: 10 },
+            created_at: chrono::NaiveDate::from_ymd_opt(2026, 10, day).and_then(|date| date.and_hms_opt(0, 0, 0)),
+            updated_at: None,
+            name: id.to_string(),
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        };
+        let monsters = vec![monster("a", 3), monster("b", 1), monster("c", 2), monster("d", 4), monster("e", 5)];
+        let pairs = vec![pair("a", "b", 0.8), pair("b", "c", 0.6), pair("d", "e", 0.9)];
+
+        assert_eq!(cluster_duplicates(&pairs, &monsters), vec![
+            DuplicateCluster { keep: "d".to_string(), duplicates: vec!["e".to_string()], confidence: 0.9 },
+            DuplicateCluster { keep: "b".to_string(), duplicates: vec!["c".to_string(), "a".to_string()], confidence: 0.7 },
+        ]);
+        assert!(cluster_duplicates(&[], &monsters).is_empty());
+    }
+}
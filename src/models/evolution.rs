@@ -0,0 +1,105 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use diesel::{Queryable, Insertable};
+use crate::balance;
+use crate::models::growth;
+use crate::models::monster::{Monster, Stats};
+
+/*
+The next form of a species, monsters being named after their species. A monster evolves once it reached
+`min_level` and won `min_wins` battles, taking the name, image and stats of the form. The stats are the
+form's at level 1, grown for every level the monster gained so evolving doesn't lose its progress.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::evolutions)]
+pub struct Evolution {
+    pub species: String,
+    pub evolves_to: String,
+    pub min_level: i32,
+    pub min_wins: i32,
+    pub image_url: String,
+    pub attack: i32,
+    pub defense: i32,
+    pub hp: i32,
+    pub speed: i32,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+// The requirement of the evolution a monster doesn't meet yet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EvolutionRequirement {
+    Level(i32),
+    Wins(i32),
+}
+
+impl Evolution {
+    pub fn stats(&self) -> Stats {
+        Stats { attack: self.attack, defense: self.defense, hp: self.hp, speed: self.speed }
+    }
+
+    pub fn unmet_requirement(&self, monster: &Monster, wins: i64) -> Option<EvolutionRequirement> {
+        if monster.level < self.min_level {
+            Some(EvolutionRequirement::Level(self.min_level))
+        } else if wins < self.min_wins as i64 {
+            Some(EvolutionRequirement::Wins(self.min_wins))
+        } else {
+            None
+        }
+    }
+
+    // Turns the monster into the next form, keeping its level and XP.
+    pub fn evolve(&self, monster: &mut Monster) {
+        monster.name = self.evolves_to.clone();
+        monster.image_url = self.image_url.clone();
+        let balance = balance::current();
+        monster.stats = (1..monster.level).fold(self.stats(), |stats, _| growth::grow(&stats, &balance));
+        monster.updated_at = Some(chrono::Utc::now().naive_utc());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_evolve_monsters_meeting_the_requirements() {
+        let evolution = Evolution {
+            species: "Pup".to_string(),
+            evolves_to: "Wolf".to_string(),
+            min_level: 3,
+            min_wins: 2,
+            image_url: "https://images/wolf.png".to_string(),
+            attack: 40,
+            defense: 20,
+            hp: 60,
+            speed: 50,
+            updated_at: None,
+        };
+        let mut monster = Monster {
+            id: "id".to_string(),
+            image_url: "https://images/pup.png".to_string(),
+            stats: Stats { attack: 20, defense: 10, hp: 30, speed: 40 },
+            created_at: None,
+            updated_at: None,
+            name: "Pup".to_string(),
+            last_battle_at: None,
+            level: 2,
+            xp: 150,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        };
+        assert_eq!(evolution.unmet_requirement(&monster, 5), Some(EvolutionRequirement::Level(3)));
+        monster.level = 3;
+        assert_eq!(evolution.unmet_requirement(&monster, 1), Some(EvolutionRequirement::Wins(2)));
+        assert_eq!(evolution.unmet_requirement(&monster, 2), None);
+
+        evolution.evolve(&mut monster);
+        assert_eq!((monster.name.as_str(), monster.image_url.as_str()), ("Wolf", "https://images/wolf.png"));
+        assert_eq!(monster.stats, Stats { attack: 44, defense: 22, hp: 66, speed: 54 });
+        assert_eq!((monster.level, monster.xp), (3, 150));
+        assert!(monster.updated_at.is_some());
+    }
+}
@@ -0,0 +1,71 @@
+use std::io::Write;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use diesel::{Queryable, Insertable, AsExpression, FromSqlRow};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportKind {
+    Monsters,
+    Battles,
+}
+
+impl ToSql<Text, Pg> for ExportKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let kind: &[u8] = match self {
+            ExportKind::Monsters => b"monsters",
+            ExportKind::Battles => b"battles",
+        };
+        out.write_all(kind)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for ExportKind {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        match value.as_bytes() {
+            b"monsters" => Ok(ExportKind::Monsters),
+            b"battles" => Ok(ExportKind::Battles),
+            other => Err(format!("Unknown export kind: {}", String::from_utf8_lossy(other)).into()),
+        }
+    }
+}
+
+/*
+The monsters or battles as they were when the export was taken, read in one repeatable-read transaction.
+Its pages are read from that copy, so writes made meanwhile don't shift rows between pages, until it expires.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::exports)]
+pub struct Export {
+    pub id: String,
+    pub kind: ExportKind,
+    pub total: i32,
+    #[serde(rename = "createdAt")]
+    pub created_at: NaiveDateTime,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::export_rows)]
+pub struct ExportRow {
+    pub export_id: String,
+    // Numbered from 1 in id order.
+    pub position: i32,
+    pub data: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExportPage {
+    #[serde(flatten)]
+    pub export: Export,
+    pub rows: Vec<serde_json::Value>,
+    // Sent back as `after` to get the next page, none past the last one.
+    pub cursor: Option<i32>,
+}
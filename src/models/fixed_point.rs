@@ -0,0 +1,123 @@
+use std::fmt;
+use std::ops::Sub;
+use std::str::FromStr;
+use rand::RngCore;
+
+// Decimals kept, a Fixed counts ten-thousandths.
+const SCALE: i64 = 10_000;
+const DECIMALS: usize = 4;
+
+/*
+A decimal number stored as a whole count of ten-thousandths. The engine uses it instead of floats for every
+modifier and chance, so that a seeded battle plays out bit for bit the same on x86, ARM and WASM builds.
+Products are rounded half away from zero, the way `f64::round` rounded the float modifiers it replaced.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Fixed(i64);
+
+impl Fixed {
+    pub const ZERO: Fixed = Fixed(0);
+    pub const ONE: Fixed = Fixed(SCALE);
+
+    // Truncated to ten-thousandths.
+    pub const fn from_ratio(numerator: i64, denominator: i64) -> Self {
+        Fixed(numerator * SCALE / denominator)
+    }
+
+    pub const fn from_percent(percent: i32) -> Self {
+        Fixed::from_ratio(percent as i64, 100)
+    }
+
+    pub fn times(self, count: i64) -> Self {
+        Fixed(self.0.saturating_mul(count))
+    }
+
+    // The value multiplied by the decimal, rounded to an integer.
+    pub fn scale(self, value: i32) -> i32 {
+        let product = value as i64 * self.0;
+        let rounded = (product.abs() + SCALE / 2) / SCALE * product.signum();
+        rounded.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+    }
+
+    /*
+    Whether an event with this chance happens. Draws like `Rng::gen_bool` did: nothing for a certainty, else one
+    u64 compared to the chance's share of the u64 range, so existing seeds keep their outcomes.
+    */
+    pub fn roll(self, rolls: &mut dyn RngCore) -> bool {
+        if self >= Fixed::ONE {
+            return true;
+        }
+        let threshold = (self.0.max(0) as u128 * (1u128 << 64) / SCALE as u128) as u64;
+        rolls.next_u64() < threshold
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+
+    fn sub(self, other: Fixed) -> Fixed {
+        Fixed(self.0 - other.0)
+    }
+}
+
+// Parses decimals like `0.25`, `1` or `-.5` digit by digit, rejecting more than four decimals.
+impl FromStr for Fixed {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("{:?} is not a decimal with at most {} decimals", value, DECIMALS);
+        let (negative, digits) = match value.trim().strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, value.trim()),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let all_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || fraction.len() > DECIMALS || !all_digits(whole) || !all_digits(fraction) {
+            return Err(invalid());
+        }
+        let whole: i64 = if whole.is_empty() { 0 } else { whole.parse().map_err(|_| invalid())? };
+        let fraction: i64 = format!("{:0<width$}", fraction, width = DECIMALS).parse().map_err(|_| invalid())?;
+        let units = whole.checked_mul(SCALE).and_then(|units| units.checked_add(fraction)).ok_or_else(invalid)?;
+        Ok(Fixed(if negative { -units } else { units }))
+    }
+}
+
+impl fmt::Display for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        write!(f, "{}{}.{:04}", sign, self.0.abs() / SCALE, self.0.abs() % SCALE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_should_compute_without_floats() {
+        let fixed = |value: &str| value.parse::<Fixed>().unwrap();
+        assert_eq!(fixed("0.25"), Fixed::from_ratio(1, 4));
+        assert_eq!(fixed("1"), Fixed::ONE);
+        assert_eq!(fixed(".5"), Fixed::from_percent(50));
+        assert_eq!(fixed("-0.1").to_string(), "-0.1000");
+        assert!("0.12345".parse::<Fixed>().is_err());
+        assert!("1e-3".parse::<Fixed>().is_err());
+        assert!(".".parse::<Fixed>().is_err());
+
+        // Halves round away from zero, like `f64::round`.
+        assert_eq!(fixed("0.5").scale(41), 21);
+        assert_eq!(fixed("0.5").scale(-41), -21);
+        assert_eq!((Fixed::ONE - fixed("0.01").times(20)).scale(40), 32);
+
+        // Rolls draw the same u64s as `gen_bool`, so seeded battles keep their outcomes.
+        let (mut rolls, mut float_rolls) = (ChaCha8Rng::seed_from_u64(7), ChaCha8Rng::seed_from_u64(7));
+        for chance in ["0", "0.2", "0.35", "0.9999", "1"] {
+            for _ in 0..100 {
+                assert_eq!(fixed(chance).roll(&mut rolls), float_rolls.gen_bool(chance.parse().unwrap()));
+            }
+        }
+    }
+}
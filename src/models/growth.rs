@@ -0,0 +1,100 @@
+use crate::balance::Balance;
+use crate::models::monster::{Monster, Stats, MAX_STAT};
+
+pub const MAX_LEVEL: i32 = 100;
+
+// With the built-in balance, 100 XP for level 1, 300 for level 2, 600 for level 3...
+pub fn xp_to_next_level(level: i32, balance: &Balance) -> i32 {
+    let level = level as i64;
+    (balance.growth.level_xp * level * (level + 1) / 2).min(i32::MAX as i64) as i32
+}
+
+// Scaled by how strong the loser was compared to the winner, at least 1.
+pub fn battle_xp(winner: &Stats, loser: &Stats, balance: &Balance) -> i32 {
+    let (winner_score, loser_score) = (winner.power_score().max(1), loser.power_score().max(0));
+    (balance.growth.battle_xp * loser_score / winner_score).clamp(1, i32::MAX as i64) as i32
+}
+
+// Stats stop growing at MAX_STAT.
+pub fn grow(stats: &Stats, balance: &Balance) -> Stats {
+    let grow = |stat: i32| stat.saturating_add((stat * balance.growth.stat_growth_percent / 100).max(1)).min(MAX_STAT.max(stat));
+    Stats { attack: grow(stats.attack), defense: grow(stats.defense), hp: grow(stats.hp), speed: grow(stats.speed) }
+}
+
+// Raises the level and the stats of the monster, keeping its XP. Returns false at the max level.
+pub fn level_up(monster: &mut Monster, balance: &Balance) -> bool {
+    if monster.level >= MAX_LEVEL {
+        return false;
+    }
+    monster.level += 1;
+    monster.stats = grow(&monster.stats, balance);
+    // Cards and other copies keyed by the update time are drawn again with the new stats.
+    monster.updated_at = Some(chrono::Utc::now().naive_utc());
+    true
+}
+
+// Adds the XP and levels up as many times as it covers, the XP left over counting towards the next level.
+pub fn gain_xp(monster: &mut Monster, xp: i32, balance: &Balance) -> i32 {
+    monster.xp = monster.xp.saturating_add(xp);
+    let mut levels = 0;
+    while monster.level < MAX_LEVEL && monster.xp >= xp_to_next_level(monster.level, balance) {
+        monster.xp -= xp_to_next_level(monster.level, balance);
+        level_up(monster, balance);
+        levels += 1;
+    }
+    levels
+}
+
+/*
+Damage scaled by the level ratio of the attacker and the defender, unchanged between monsters of the same level.
+With the built-in balance, a level 2 attacker deals 12/11 of the damage of a level 1 one.
+*/
+pub fn scale_damage(damage: i32, attacker_level: i32, defender_level: i32, balance: &Balance) -> i32 {
+    if attacker_level == defender_level {
+        return damage;
+    }
+    let offset = balance.growth.level_damage_offset;
+    let scaled = damage as i64 * (attacker_level + offset) as i64 / (defender_level + offset) as i64;
+    scaled.clamp(1, i32::MAX as i64) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_level_up_along_the_growth_curve() {
+        let mut monster = Monster {
+            id: "id".to_string(),
+            image_url: String::new(),
+            stats: Stats { attack: 40, defense: 10, hp: 50, speed: 80 },
+            created_at: None,
+            updated_at: None,
+            name: "monster".to_string(),
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        };
+        let balance = Balance::default();
+        assert_eq!(gain_xp(&mut monster, 99, &balance), 0);
+        assert_eq!(gain_xp(&mut monster, 350, &balance), 2);
+        assert_eq!((monster.level, monster.xp), (3, 49));
+        assert_eq!(monster.stats, Stats { attack: 44, defense: 12, hp: 54, speed: 88 });
+
+        monster.level = MAX_LEVEL;
+        assert!(!level_up(&mut monster, &balance));
+        assert_eq!(gain_xp(&mut monster, i32::MAX, &balance), 0);
+        assert_eq!(monster.level, MAX_LEVEL);
+
+        let weaker = Stats { attack: 20, defense: 10, hp: 50, speed: 80 };
+        assert_eq!(battle_xp(&monster.stats, &monster.stats, &balance), 50);
+        assert_eq!(battle_xp(&Stats { attack: 40, defense: 10, hp: 50, speed: 80 }, &weaker, &balance), 40);
+        assert_eq!(scale_damage(30, 4, 4, &balance), 30);
+        assert_eq!(scale_damage(30, 2, 1, &balance), 32);
+        assert_eq!(scale_damage(1, 1, 50, &balance), 1);
+    }
+}
@@ -0,0 +1,52 @@
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+// Set on the responses replayed for a key that was already used.
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "Idempotent-Replayed";
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+// Keys can be reused for another request once this old.
+pub const IDEMPOTENCY_KEY_TTL_HOURS: i64 = 24;
+
+/*
+A key a client sent along a POST, with the response to replay when the request is sent again. `status`,
+`content_type` and `body` are set once the first request is answered, keys with none are still in progress.
+Streamed responses can't be replayed, their keys are answered with a status and no body.
+*/
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::idempotency_keys)]
+pub struct IdempotencyKey {
+    pub client: String,
+    pub key: String,
+    pub method: String,
+    // With the query string, a key can't be reused for a request to another path.
+    pub path: String,
+    pub status: Option<i32>,
+    pub content_type: Option<String>,
+    pub body: Option<Vec<u8>>,
+    pub created_at: NaiveDateTime,
+    // The SHA-256 of the request body in hex, a key can't be reused for a request with another body either.
+    pub request_hash: Option<String>,
+}
+
+impl IdempotencyKey {
+    pub fn is_for(&self, method: &str, path: &str) -> bool {
+        self.method == method && self.path == path
+    }
+
+    // Keys claimed before their request hash was kept match any body.
+    pub fn has_body(&self, request_hash: &Option<String>) -> bool {
+        self.request_hash.is_none() || &self.request_hash == request_hash
+    }
+}
+
+// Keys are opaque to the server, like UUIDs, but must fit in a header and in the table.
+pub fn check_idempotency_key(key: &str) -> Result<(), String> {
+    if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LENGTH {
+        return Err(format!("The idempotency key must have 1 to {} characters", MAX_IDEMPOTENCY_KEY_LENGTH));
+    }
+    if !key.chars().all(|c| c.is_ascii_graphic()) {
+        return Err("The idempotency key must only have visible ASCII characters".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use diesel::{Queryable, Insertable, AsChangeset, Identifiable};
+use crate::models::battle::{Battle, BattleOutcome};
+use crate::models::monster::Monster;
+
+/*
+A battle played by two players, one per monster, who each submit the move of their monster for the turn.
+The turn is resolved once both did, and the battle ends when a monster is knocked out or a player lets
+`turn_deadline` pass: the player who did submit wins by forfeit, the battle is a draw when neither did.
+The moves submitted are kept from the opponent until the turn is resolved, `null` being a plain attack.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable, AsChangeset, Identifiable)]
+#[diesel(table_name = crate::repository::schema::interactive_battles)]
+#[diesel(treat_none_as_null = true)]
+pub struct InteractiveBattle {
+    pub id: String,
+    pub monster_a: String,
+    pub monster_b: String,
+    pub monster_a_hp: i32,
+    pub monster_b_hp: i32,
+    // The turn being played, numbered from 1.
+    pub turn: i32,
+    pub monster_a_ready: bool,
+    #[serde(skip_serializing, default)]
+    pub monster_a_move: Option<String>,
+    pub monster_b_ready: bool,
+    #[serde(skip_serializing, default)]
+    pub monster_b_move: Option<String>,
+    // The TurnEvents of the turns resolved so far.
+    pub turns: serde_json::Value,
+    pub finished: bool,
+    pub winner: Option<String>,
+    // The battle recorded once this one finished.
+    pub battle_id: Option<String>,
+    #[serde(rename = "turnDeadline")]
+    pub turn_deadline: NaiveDateTime,
+    #[serde(rename = "createdAt")]
+    pub created_at: NaiveDateTime,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TurnSubmissionError {
+    NotInBattle,
+    AlreadySubmitted,
+    Finished,
+}
+
+impl InteractiveBattle {
+    pub fn new(monster_a: &Monster, monster_b: &Monster, turn_deadline: NaiveDateTime) -> Self {
+        InteractiveBattle {
+            id: uuid::Uuid::new_v4().to_string(),
+            monster_a: monster_a.id.clone(),
+            monster_b: monster_b.id.clone(),
+            monster_a_hp: monster_a.stats.hp,
+            monster_b_hp: monster_b.stats.hp,
+            turn: 1,
+            monster_a_ready: false,
+            monster_a_move: None,
+            monster_b_ready: false,
+            monster_b_move: None,
+            turns: serde_json::Value::Array(Vec::new()),
+            finished: false,
+            winner: None,
+            battle_id: None,
+            turn_deadline,
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: None,
+        }
+    }
+
+    // Records the move of the monster for this turn, `move_id` having been checked to be one it knows.
+    pub fn submit(&mut self, monster_id: &str, move_id: Option<String>) -> Result<(), TurnSubmissionError> {
+        if self.finished {
+            return Err(TurnSubmissionError::Finished);
+        }
+        let (ready, submitted_move) = if monster_id == self.monster_a {
+            (&mut self.monster_a_ready, &mut self.monster_a_move)
+        } else if monster_id == self.monster_b {
+            (&mut self.monster_b_ready, &mut self.monster_b_move)
+        } else {
+            return Err(TurnSubmissionError::NotInBattle);
+        };
+        if *ready {
+            return Err(TurnSubmissionError::AlreadySubmitted);
+        }
+        *ready = true;
+        *submitted_move = move_id;
+        Ok(())
+    }
+
+    pub fn both_ready(&self) -> bool {
+        self.monster_a_ready && self.monster_b_ready
+    }
+
+    // Clears the submitted moves and opens the next turn, until the new deadline.
+    pub fn next_turn(&mut self, turn_deadline: NaiveDateTime) {
+        self.turn += 1;
+        self.monster_a_ready = false;
+        self.monster_a_move = None;
+        self.monster_b_ready = false;
+        self.monster_b_move = None;
+        self.turn_deadline = turn_deadline;
+    }
+
+    // Ends the battle once the deadline passed, returning the battle to record.
+    pub fn expire(&mut self, now: NaiveDateTime) -> Option<Battle> {
+        if self.finished || now <= self.turn_deadline {
+            return None;
+        }
+        match (self.monster_a_ready, self.monster_b_ready) {
+            (true, false) => Some(self.finish(Some(self.monster_a.clone()), BattleOutcome::Forfeit)),
+            (false, true) => Some(self.finish(Some(self.monster_b.clone()), BattleOutcome::Forfeit)),
+            _ => Some(self.finish(None, BattleOutcome::Draw)),
+        }
+    }
+
+    // Ends the battle, returning the battle to record.
+    pub fn finish(&mut self, winner: Option<String>, outcome: BattleOutcome) -> Battle {
+        self.finished = true;
+        self.winner = winner.clone();
+        self.monster_a_move = None;
+        self.monster_b_move = None;
+        Battle {
+            id: String::new(),
+            monster_a: self.monster_a.clone(),
+            monster_b: self.monster_b.clone(),
+            winner,
+            created_at: None,
+            updated_at: None,
+            outcome,
+            manual: false,
+            season_id: None,
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use crate::models::monster::Stats;
+
+    use super::*;
+
+    #[test]
+    fn test_should_end_expired_battles_in_favor_of_the_player_who_submitted() {
+        let monster = |id: &str| Monster {
+            id: id.to_string(),
+            image_url: String::new(),
+            stats: Stats { attack: 10, defense: 10, hp: 10, speed: 10 },
+            created_at: None,
+            updated_at: None,
+            name: id.to_string(),
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        };
+        let now = chrono::Utc::now().naive_utc();
+        let battle = InteractiveBattle::new(&monster("a"), &monster("b"), now);
+
+        let mut forfeited = battle.clone();
+        assert_eq!(forfeited.submit("b", None), Ok(()));
+        assert_eq!(forfeited.submit("b", None), Err(TurnSubmissionError::AlreadySubmitted));
+        assert_eq!(forfeited.submit("c", None), Err(TurnSubmissionError::NotInBattle));
+        assert!(forfeited.expire(now).is_none());
+        let record = forfeited.expire(now + Duration::seconds(1)).unwrap();
+        assert_eq!((record.winner.as_deref(), record.outcome), (Some("b"), BattleOutcome::Forfeit));
+        assert_eq!(forfeited.submit("a", None), Err(TurnSubmissionError::Finished));
+        assert!(forfeited.expire(now + Duration::seconds(2)).is_none());
+
+        let mut abandoned = battle;
+        let record = abandoned.expire(now + Duration::seconds(1)).unwrap();
+        assert_eq!((record.winner, record.outcome), (None, BattleOutcome::Draw));
+        assert!(abandoned.finished);
+    }
+}
@@ -0,0 +1,53 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use diesel::{Queryable, Insertable};
+use crate::models::fixed_point::Fixed;
+use crate::models::monster::StatModifiers;
+
+pub const MAX_MONSTER_ITEMS: usize = 3;
+
+/*
+Something a monster carries into its battles. Equipment raises its stats by the `*_bonus` percentages, or lowers
+them when negative, for the whole battle. Consumables are used once per battle instead: a revive brings its
+monster back with `revive_hp` percent of its starting HP the first time it is knocked out.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::items)]
+pub struct Item {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub attack_bonus: i32,
+    pub defense_bonus: i32,
+    pub speed_bonus: i32,
+    pub consumable: bool,
+    pub revive_hp: i32,
+    #[serde(rename = "createdAt")]
+    pub created_at: NaiveDateTime,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl Item {
+    pub fn modifiers(&self) -> StatModifiers {
+        StatModifiers {
+            attack: Fixed::from_percent(100 + self.attack_bonus),
+            defense: Fixed::from_percent(100 + self.defense_bonus),
+            speed: Fixed::from_percent(100 + self.speed_bonus),
+        }
+    }
+
+    // The HP a revive leaves its monster with, at least 1.
+    pub fn revived_hp(&self, starting_hp: i32) -> i32 {
+        (starting_hp * self.revive_hp / 100).max(1)
+    }
+}
+
+// An item in one of the MAX_MONSTER_ITEMS slots of a monster, numbered from 1.
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::monster_items)]
+pub struct MonsterItem {
+    pub monster_id: String,
+    pub slot: i32,
+    pub item_id: String,
+}
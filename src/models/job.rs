@@ -0,0 +1,72 @@
+use std::io::Write;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use diesel::{Queryable, Insertable, AsExpression, FromSqlRow};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+
+pub const BATTLE_BATCH: &str = "battle_batch";
+pub const CSV_IMPORT: &str = "csv_import";
+pub const DB_STATS: &str = "db_stats";
+pub const DUPLICATE_SCAN: &str = "duplicate_scan";
+pub const JSON_IMPORT: &str = "json_import";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl ToSql<Text, Pg> for JobStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let status: &[u8] = match self {
+            JobStatus::Queued => b"queued",
+            JobStatus::Running => b"running",
+            JobStatus::Succeeded => b"succeeded",
+            JobStatus::Failed => b"failed",
+        };
+        out.write_all(status)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for JobStatus {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        match value.as_bytes() {
+            b"queued" => Ok(JobStatus::Queued),
+            b"running" => Ok(JobStatus::Running),
+            b"succeeded" => Ok(JobStatus::Succeeded),
+            b"failed" => Ok(JobStatus::Failed),
+            other => Err(format!("Unknown job status: {}", String::from_utf8_lossy(other)).into()),
+        }
+    }
+}
+
+/*
+Long-running work started by a request and polled through `GET /jobs/{id}`.
+`processed` and `failed` count the items handled so far out of `total`.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::jobs)]
+pub struct Job {
+    pub id: String,
+    pub kind: String,
+    pub status: JobStatus,
+    pub total: i32,
+    pub processed: i32,
+    pub failed: i32,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: NaiveDateTime,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<NaiveDateTime>,
+    #[serde(rename = "finishedAt")]
+    pub finished_at: Option<NaiveDateTime>,
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use diesel::QueryableByName;
+use diesel::sql_types::{BigInt, Double, Text};
+use crate::models::battle::Battle;
+use crate::models::monster::Monster;
+
+// Battles a monster needs before it ranks by win rate, a single lucky win ranking first otherwise.
+pub const MIN_WINRATE_BATTLES: i64 = 10;
+// z-score of the 95% confidence the rating is computed with.
+const RATING_Z: f64 = 1.96;
+
+/*
+How the leaderboard ranks monsters: by `wins`, by `winrate` among the monsters with MIN_WINRATE_BATTLES
+battles, by `rating`, the lower bound of the Wilson score interval of the win rate that trusts a win rate
+more the more battles back it, or by `streak`, the wins in a row up to the latest battle.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LeaderboardMode {
+    #[default]
+    Wins,
+    Winrate,
+    Rating,
+    Streak,
+}
+
+impl LeaderboardMode {
+    // The ORDER BY of the leaderboard query, ties going to the monster with the most wins then the lowest id.
+    pub fn order_by(&self) -> &'static str {
+        match self {
+            LeaderboardMode::Wins => "wins DESC, win_rate DESC, monster_id",
+            LeaderboardMode::Winrate => "win_rate DESC, battles DESC, monster_id",
+            LeaderboardMode::Rating => "rating DESC, wins DESC, monster_id",
+            LeaderboardMode::Streak => "streak DESC, wins DESC, monster_id",
+        }
+    }
+
+    pub fn min_battles(&self) -> i64 {
+        if *self == LeaderboardMode::Winrate { MIN_WINRATE_BATTLES } else { 1 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, QueryableByName)]
+pub struct LeaderboardEntry {
+    #[diesel(sql_type = BigInt)]
+    pub rank: i64,
+    #[serde(rename = "monster")]
+    #[diesel(sql_type = Text)]
+    pub monster_id: String,
+    #[diesel(sql_type = Text)]
+    pub name: String,
+    #[diesel(sql_type = BigInt)]
+    pub battles: i64,
+    #[diesel(sql_type = BigInt)]
+    pub wins: i64,
+    #[diesel(sql_type = BigInt)]
+    pub losses: i64,
+    #[diesel(sql_type = BigInt)]
+    pub draws: i64,
+    // Between 0 and 1, draws counting as battles not won.
+    #[diesel(sql_type = Double)]
+    pub win_rate: f64,
+    #[diesel(sql_type = Double)]
+    pub rating: f64,
+    #[diesel(sql_type = BigInt)]
+    pub streak: i64,
+}
+
+// The lower bound of the Wilson score interval, the same formula the leaderboard query computes.
+pub fn rating(wins: i64, battles: i64) -> f64 {
+    if battles == 0 {
+        return 0.0;
+    }
+    let (n, p, z2) = (battles as f64, wins as f64 / battles as f64, RATING_Z * RATING_Z);
+    (p + z2 / (2.0 * n) - RATING_Z * ((p * (1.0 - p) + z2 / (4.0 * n)) / n).sqrt()) / (1.0 + z2 / n)
+}
+
+// Builds the leaderboard from every battle, for the repositories that can't aggregate them where they are stored.
+pub fn leaderboard(battles: &[Battle], monsters: &[Monster], mode: LeaderboardMode, limit: usize) -> Vec<LeaderboardEntry> {
+    let mut battles: Vec<&Battle> = battles.iter().collect();
+    battles.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+    // Battles, wins, draws and the streak, which stops growing at the first battle not won.
+    let mut records: HashMap<&str, (i64, i64, i64, i64, bool)> = HashMap::new();
+    for battle in battles {
+        for monster_id in [&battle.monster_a, &battle.monster_b] {
+            let (count, wins, draws, streak, streak_over) = records.entry(monster_id).or_insert((0, 0, 0, 0, false));
+            let won = battle.winner.as_ref() == Some(monster_id);
+            *count += 1;
+            *wins += won as i64;
+            *draws += battle.winner.is_none() as i64;
+            *streak_over |= !won;
+            *streak += !*streak_over as i64;
+        }
+    }
+
+    let mut entries: Vec<LeaderboardEntry> = monsters
+        .iter()
+        .filter_map(|monster| {
+            let &(battles, wins, draws, streak, _) = records.get(monster.id.as_str())?;
+            (battles >= mode.min_battles()).then(|| LeaderboardEntry {
+                rank: 0,
+                monster_id: monster.id.clone(),
+                name: monster.name.clone(),
+                battles,
+                wins,
+                losses: battles - wins - draws,
+                draws,
+                win_rate: wins as f64 / battles as f64,
+                rating: rating(wins, battles),
+                streak,
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        let by_mode = match mode {
+            LeaderboardMode::Wins => b.wins.cmp(&a.wins).then(b.win_rate.total_cmp(&a.win_rate)),
+            LeaderboardMode::Winrate => b.win_rate.total_cmp(&a.win_rate).then(b.battles.cmp(&a.battles)),
+            LeaderboardMode::Rating => b.rating.total_cmp(&a.rating).then(b.wins.cmp(&a.wins)),
+            LeaderboardMode::Streak => b.streak.cmp(&a.streak).then(b.wins.cmp(&a.wins)),
+        };
+        by_mode.then(a.monster_id.cmp(&b.monster_id))
+    });
+    entries.truncate(limit);
+    for (position, entry) in entries.iter_mut().enumerate() {
+        entry.rank = position as i64 + 1;
+    }
+    entries
+}
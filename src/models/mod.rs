@@ -1,2 +1,34 @@
 pub mod monster;
-pub mod battle;
\ No newline at end of file
+pub mod battle;
+pub mod achievement;
+pub mod analytics;
+pub mod backup;
+pub mod audit;
+pub mod challenge;
+pub mod cors;
+pub mod db_health;
+pub mod decay;
+pub mod duplicates;
+pub mod evolution;
+pub mod export;
+pub mod fixed_point;
+pub mod growth;
+pub mod idempotency;
+pub mod interactive_battle;
+pub mod item;
+pub mod job;
+pub mod leaderboard;
+pub mod moves;
+pub mod note;
+pub mod page;
+pub mod rate_limit;
+pub mod region;
+pub mod reward;
+pub mod rules;
+pub mod season;
+pub mod series;
+pub mod status_effect;
+pub mod strategy;
+pub mod trainer;
+pub mod training;
+pub mod webhook;
\ No newline at end of file
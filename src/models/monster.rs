@@ -1,19 +1,267 @@
-use serde::{Deserialize, Serialize};
-use diesel::{Queryable, Insertable, AsChangeset, Identifiable};
+use serde::{Deserialize, Serialize, Serializer, ser::SerializeStruct};
+use diesel::{Queryable, QueryableByName, Insertable, AsChangeset, Identifiable};
+use diesel::pg::Pg;
+use crate::models::fixed_point::Fixed;
+use crate::repository::schema::monsters;
 
-#[derive(Serialize, Deserialize, Debug, Clone, Queryable, Insertable, AsChangeset, Identifiable)]
-#[diesel(table_name = crate::repository::schema::monsters)]
+#[derive(Serialize, Deserialize, Debug, Clone, Insertable, AsChangeset, Identifiable, QueryableByName)]
+#[diesel(table_name = monsters)]
 pub struct Monster {
     #[serde(default)]
     pub id: String,
     pub image_url: String,
-    pub attack: i32,
-    pub defense: i32,
-    pub hp: i32,
-    pub speed: i32,
+    #[serde(flatten)]
+    #[diesel(embed)]
+    pub stats: Stats,
     #[serde(rename = "createdAt")]
     pub created_at: Option<chrono::NaiveDateTime>,
     #[serde(rename = "updatedAt")]
     pub updated_at: Option<chrono::NaiveDateTime>,
     pub name: String,
-}
\ No newline at end of file
+    // Maintained by battle creation, never taken from request bodies.
+    #[serde(rename = "lastBattleAt", default)]
+    pub last_battle_at: Option<chrono::NaiveDateTime>,
+    // Progress from winning battles, see `growth`. Left alone by updates, like the battle timestamp above.
+    #[serde(default = "first_level")]
+    #[diesel(skip_update)]
+    pub level: i32,
+    #[serde(default)]
+    #[diesel(skip_update)]
+    pub xp: i32,
+    // The trainer the monster belongs to, changed by transfers and trades only.
+    #[serde(rename = "ownerId", default)]
+    #[diesel(skip_update)]
+    pub owner_id: Option<String>,
+    // Set by the thumbnail task when `image_url` can't be downloaded or isn't an image, cleared when the URL changes.
+    #[serde(rename = "imageBroken", default)]
+    pub image_broken: bool,
+    // The id of the monster in the system it was imported from, unique when set.
+    #[serde(rename = "externalId", default)]
+    pub external_id: Option<String>,
+    /*
+    Starts at 1 and is bumped by every update, which has to send the version it read to be applied. Missing from
+    a body it reads as 0, which no monster has.
+    */
+    #[serde(default)]
+    #[diesel(skip_update)]
+    pub version: i32,
+}
+
+fn first_level() -> i32 {
+    1
+}
+
+// Stats are stored as plain columns, so rows are mapped by hand instead of nesting the select.
+impl Queryable<monsters::SqlType, Pg> for Monster {
+    type Row = (String, String, i32, i32, i32, i32, Option<chrono::NaiveDateTime>, Option<chrono::NaiveDateTime>, String, Option<chrono::NaiveDateTime>, i32, i32, Option<String>, bool, Option<String>, i32);
+
+    fn build((id, image_url, attack, defense, hp, speed, created_at, updated_at, name, last_battle_at, level, xp, owner_id, image_broken, external_id, version): Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(Monster {
+            id,
+            image_url,
+            stats: Stats { attack, defense, hp, speed },
+            created_at,
+            updated_at,
+            name,
+            last_battle_at,
+            level,
+            xp,
+            owner_id,
+            image_broken,
+            external_id,
+            version,
+        })
+    }
+}
+
+/*
+What imports do with rows matching an existing monster of the importer: the one with the same external id,
+or the same name regardless of case for rows without one.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    // Every row is a new monster, duplicates included.
+    #[default]
+    Create,
+    // Matching monsters are updated with the row.
+    Upsert,
+    SkipDuplicates,
+}
+
+#[derive(Debug, Clone)]
+pub enum ImportOutcome {
+    Created(Monster),
+    Updated(Monster),
+    Skipped,
+}
+
+// The highest value of a stat, HP being at least 1 and the other stats at least 0.
+pub const MAX_STAT: i32 = 10_000;
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Insertable, AsChangeset, QueryableByName)]
+#[diesel(table_name = monsters)]
+pub struct Stats {
+    pub attack: i32,
+    pub defense: i32,
+    pub hp: i32,
+    pub speed: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, QueryableByName)]
+pub struct MonsterSearchResult {
+    #[serde(flatten)]
+    #[diesel(embed)]
+    pub monster: Monster,
+    #[diesel(sql_type = diesel::sql_types::Float4)]
+    pub rank: f32,
+}
+
+// An opponent for matchmaking, `distance` being the sum of the differences between its stats and the monster's.
+#[derive(Serialize, Deserialize, Debug, Clone, QueryableByName)]
+pub struct MatchmakingCandidate {
+    #[serde(flatten)]
+    #[diesel(embed)]
+    pub monster: Monster,
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub distance: i32,
+    // The opponent's trainer plays from the region matchmaking preferred.
+    #[serde(rename = "sameRegion")]
+    #[diesel(sql_type = diesel::sql_types::Bool)]
+    pub same_region: bool,
+}
+
+/*
+Multipliers applied on top of the base stats, `Fixed::ONE` leaves a stat unchanged.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatModifiers {
+    pub attack: Fixed,
+    pub defense: Fixed,
+    pub speed: Fixed,
+}
+
+impl Default for StatModifiers {
+    fn default() -> Self {
+        StatModifiers { attack: Fixed::ONE, defense: Fixed::ONE, speed: Fixed::ONE }
+    }
+}
+
+impl Stats {
+    // Same rule as the `monsters_stats_range_check` constraint.
+    pub fn in_range(&self) -> bool {
+        (1..=MAX_STAT).contains(&self.hp) && [self.attack, self.defense, self.speed].iter().all(|stat| (0..=MAX_STAT).contains(stat))
+    }
+
+    // Computed in i64, so rows stored before the range check can't overflow them.
+    pub fn total(&self) -> i64 {
+        [self.attack, self.defense, self.hp, self.speed].into_iter().map(i64::from).sum()
+    }
+
+    // Attack weighs double since it is the only stat that shortens a battle.
+    pub fn power_score(&self) -> i64 {
+        i64::from(self.attack) + self.total()
+    }
+
+    pub fn effective_speed(&self, modifiers: &StatModifiers) -> i32 {
+        modifiers.speed.scale(self.speed)
+    }
+
+    // HP is never modified.
+    pub fn with_modifiers(&self, modifiers: &StatModifiers) -> Stats {
+        Stats {
+            attack: modifiers.attack.scale(self.attack),
+            defense: modifiers.defense.scale(self.defense),
+            hp: self.hp,
+            speed: self.effective_speed(modifiers),
+        }
+    }
+
+    pub fn distance(&self, other: &Stats) -> i32 {
+        (self.attack - other.attack).abs() + (self.defense - other.defense).abs() + (self.hp - other.hp).abs() + (self.speed - other.speed).abs()
+    }
+
+    // The fastest monster attacks first, ties go to the highest attack and then to the other monster.
+    pub fn attacks_before(&self, other: &Stats) -> bool {
+        self.speed > other.speed || (self.speed == other.speed && self.attack > other.attack)
+    }
+
+    // Damage is attack minus the defender's defense, with the minimum damage of the balance.
+    pub fn damage_against(&self, defender: &Stats, minimum: i32) -> i32 {
+        if self.attack > defender.defense {
+            self.attack - defender.defense
+        } else {
+            minimum
+        }
+    }
+}
+
+impl Serialize for Stats {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Stats", 6)?;
+        state.serialize_field("attack", &self.attack)?;
+        state.serialize_field("defense", &self.defense)?;
+        state.serialize_field("hp", &self.hp)?;
+        state.serialize_field("speed", &self.speed)?;
+        state.serialize_field("statTotal", &self.total())?;
+        state.serialize_field("powerScore", &self.power_score())?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> Stats {
+        Stats { attack: 40, defense: 20, hp: 50, speed: 80 }
+    }
+
+    #[test]
+    fn test_should_compute_the_derived_stats() {
+        assert_eq!(stats().total(), 190);
+        assert_eq!(stats().power_score(), 230);
+        let strongest = Stats { attack: i32::MAX, defense: i32::MAX, hp: i32::MAX, speed: i32::MAX };
+        assert_eq!(strongest.power_score(), 5 * i64::from(i32::MAX));
+        assert!(stats().in_range() && !strongest.in_range() && !Stats { hp: 0, ..stats() }.in_range());
+        assert_eq!(stats().effective_speed(&StatModifiers::default()), 80);
+        let half = Fixed::from_percent(50);
+        assert_eq!(stats().effective_speed(&StatModifiers { speed: half, ..StatModifiers::default() }), 40);
+        assert_eq!(stats().with_modifiers(&StatModifiers { attack: half, defense: half, speed: half }), Stats { attack: 20, defense: 10, hp: 50, speed: 40 });
+    }
+
+    #[test]
+    fn test_should_deal_at_least_one_damage() {
+        let defender = Stats { defense: 60, ..stats() };
+
+        assert_eq!(stats().damage_against(&Stats { defense: 10, ..stats() }, 1), 30);
+        assert_eq!(stats().damage_against(&defender, 1), 1);
+    }
+
+    #[test]
+    fn test_should_flatten_stats_and_computed_fields_when_serializing() {
+        let monster = Monster {
+            id: "id".to_string(),
+            image_url: "https://loremflickr.com/640/480".to_string(),
+            stats: stats(),
+            created_at: None,
+            updated_at: None,
+            name: "monster".to_string(),
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        };
+
+        let value = serde_json::to_value(&monster).unwrap();
+
+        assert_eq!(value["attack"], 40);
+        assert_eq!(value["statTotal"], 190);
+        assert_eq!(value["powerScore"], 230);
+        assert!(value.get("stats").is_none());
+        assert_eq!(serde_json::from_value::<Monster>(value).unwrap().stats, stats());
+    }
+}
@@ -0,0 +1,48 @@
+use chrono::NaiveDateTime;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use diesel::{Queryable, Insertable};
+use crate::models::status_effect::StatusEffect;
+
+pub const MAX_MONSTER_MOVES: usize = 4;
+
+/*
+An attack a monster can learn. `power` is the percentage of the attacker's usual damage it deals and
+`accuracy` the percentage of turns it hits. A hit inflicts `effect` with an `effect_chance` percent chance.
+`element` only labels the move for now.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::moves)]
+pub struct Move {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub power: i32,
+    pub accuracy: i32,
+    pub element: Option<String>,
+    pub effect: Option<StatusEffect>,
+    pub effect_chance: i32,
+    #[serde(rename = "createdAt")]
+    pub created_at: NaiveDateTime,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl Move {
+    // The damage of a hit with the move, given the attacker's usual damage, or None when its accuracy roll fails.
+    pub fn hit(&self, damage: i32, rolls: &mut dyn RngCore) -> Option<i32> {
+        if rolls.gen_range(1..=100) > self.accuracy {
+            return None;
+        }
+        Some((damage * self.power / 100).max(1))
+    }
+}
+
+// A move in one of the MAX_MONSTER_MOVES slots of a monster, numbered from 1.
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::monster_moves)]
+pub struct MonsterMove {
+    pub monster_id: String,
+    pub slot: i32,
+    pub move_id: String,
+}
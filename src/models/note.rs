@@ -0,0 +1,37 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use diesel::{Queryable, Insertable};
+
+pub const MAX_NOTE_LENGTH: usize = 10_000;
+
+/*
+A markdown note on a monster, private to the API key that wrote it. Pinned notes are listed first, and a note
+may point at battles of its monster it comments on.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::monster_notes)]
+pub struct MonsterNote {
+    #[serde(default)]
+    pub id: String,
+    #[serde(rename = "monster")]
+    pub monster_id: String,
+    #[serde(skip_serializing, default)]
+    pub owner: String,
+    pub body: String,
+    pub pinned: bool,
+    #[serde(rename = "battles")]
+    pub battle_ids: Vec<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: NaiveDateTime,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+// The notes of one page, out of the `total` matching ones.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NotesPage {
+    pub notes: Vec<MonsterNote>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+}
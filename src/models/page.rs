@@ -0,0 +1,83 @@
+use chrono::{DateTime, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PAGE_SIZE: i64 = 100;
+pub const MAX_PAGE_SIZE: i64 = 1000;
+// Carries `next_cursor` along the pages written as CSV, which have no envelope.
+pub const NEXT_CURSOR_HEADER: &str = "X-Next-Cursor";
+
+/*
+The position of the last row of a page in (created_at, id) order, rows without a creation date coming first.
+Unlike an offset, it points at the same place whatever is inserted before it meanwhile.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageCursor {
+    pub created_at: Option<NaiveDateTime>,
+    pub id: String,
+}
+
+impl PageCursor {
+    // Opaque to clients, so the order can change without breaking the cursors they hold.
+    pub fn encode(&self) -> String {
+        let created_at = self.created_at.map_or(String::new(), |created_at| created_at.and_utc().timestamp_micros().to_string());
+        format!("{}:{}", created_at, self.id).bytes().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, String> {
+        let invalid = || "The cursor is invalid, use the next_cursor of a page".to_string();
+        if !cursor.len().is_multiple_of(2) || !cursor.is_ascii() {
+            return Err(invalid());
+        }
+        let bytes = (0..cursor.len())
+            .step_by(2)
+            .map(|at| u8::from_str_radix(&cursor[at..at + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| invalid())?;
+        let decoded = String::from_utf8(bytes).map_err(|_| invalid())?;
+        let (created_at, id) = decoded.split_once(':').ok_or_else(invalid)?;
+        let created_at = match created_at {
+            "" => None,
+            micros => Some(micros.parse().ok().and_then(DateTime::from_timestamp_micros).ok_or_else(invalid)?.naive_utc()),
+        };
+        Ok(PageCursor { created_at, id: id.to_string() })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    // Sent back as `after` to get the next page, none past the last one.
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /*
+    The page of `rows`, fetched with one row past the limit to tell whether another page follows. This way the
+    last page has no cursor, rather than one leading to an empty page.
+    */
+    pub fn new(mut rows: Vec<T>, limit: i64, cursor: impl Fn(&T) -> PageCursor) -> Self {
+        let mut next_cursor = None;
+        if rows.len() as i64 > limit {
+            rows.truncate(limit as usize);
+            next_cursor = rows.last().map(|row| cursor(row).encode());
+        }
+        Page { items: rows, next_cursor }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(Vec<T>) -> Vec<U>) -> Page<U> {
+        Page { items: f(self.items), next_cursor: self.next_cursor }
+    }
+}
+
+// The page asked for with `after` and `limit`, none when neither is set and the whole list is wanted.
+pub fn requested_page(after: Option<&str>, limit: Option<i64>) -> Result<Option<(Option<PageCursor>, i64)>, String> {
+    if after.is_none() && limit.is_none() {
+        return Ok(None);
+    }
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit <= 0 || limit > MAX_PAGE_SIZE {
+        return Err(format!("Limit must be between 1 and {}", MAX_PAGE_SIZE));
+    }
+    let after = after.map(PageCursor::decode).transpose()?;
+    Ok(Some((after, limit)))
+}
@@ -0,0 +1,59 @@
+use chrono::NaiveDateTime;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use diesel::{Insertable, Queryable};
+
+// Requests without an API key, and with the admin token, are budgeted by these tiers.
+pub const ANONYMOUS_TIER: &str = "anonymous";
+pub const ADMIN_TIER: &str = "admin";
+
+// Requests a client may make per minute on the routes of each rate limit class.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::rate_limit_tiers)]
+pub struct RateLimitTier {
+    pub name: String,
+    pub standard_per_minute: i32,
+    pub expensive_per_minute: i32,
+    pub streaming_per_minute: i32,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl RateLimitTier {
+    // The tiers created by the `create_rate_limit_tiers` migration.
+    pub fn defaults() -> Vec<RateLimitTier> {
+        let tier = |name: &str, standard_per_minute, expensive_per_minute, streaming_per_minute| RateLimitTier {
+            name: name.to_string(),
+            standard_per_minute,
+            expensive_per_minute,
+            streaming_per_minute,
+            updated_at: None,
+        };
+        vec![tier(ANONYMOUS_TIER, 60, 5, 2), tier("free", 120, 10, 5), tier("partner", 1200, 100, 20), tier(ADMIN_TIER, 6000, 1000, 100)]
+    }
+}
+
+// Only the hash of the key is stored, the key itself is returned once, when it is created.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::api_keys)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing, default)]
+    pub key_hash: String,
+    pub tier: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: NaiveDateTime,
+}
+
+// A new random key, `bm_` followed by 48 hex digits.
+pub fn new_api_key() -> String {
+    let mut secret = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut secret);
+    format!("bm_{}", secret.iter().map(|byte| format!("{:02x}", byte)).collect::<String>())
+}
+
+pub fn hash_api_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use diesel::QueryableByName;
+use diesel::sql_types::{BigInt, Text};
+
+pub const MAX_REGION_LENGTH: usize = 32;
+
+// The trainers, the monsters they own and the battles tagged with a region.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, QueryableByName)]
+pub struct RegionStats {
+    #[diesel(sql_type = Text)]
+    pub region: String,
+    #[diesel(sql_type = BigInt)]
+    pub trainers: i64,
+    #[diesel(sql_type = BigInt)]
+    pub monsters: i64,
+    #[diesel(sql_type = BigInt)]
+    pub battles: i64,
+}
+
+impl RegionStats {
+    pub fn empty(region: &str) -> Self {
+        RegionStats { region: region.to_string(), trainers: 0, monsters: 0, battles: 0 }
+    }
+}
+
+/*
+Regions are named like the cloud regions the instances run in, `eu-west` or `us-east-1`: lowercase letters,
+digits and hyphens. They are lowercased so the same region isn't tagged twice.
+*/
+pub fn parse_region(region: &str) -> Result<String, String> {
+    let region = region.trim().to_lowercase();
+    let valid = !region.is_empty()
+        && region.len() <= MAX_REGION_LENGTH
+        && region.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && !region.starts_with('-')
+        && !region.ends_with('-');
+    if !valid {
+        return Err(format!("Region {:?} must be at most {} lowercase letters, digits and inner hyphens", region, MAX_REGION_LENGTH));
+    }
+    Ok(region)
+}
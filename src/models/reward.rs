@@ -0,0 +1,77 @@
+use std::io::Write;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use diesel::{Queryable, Insertable, AsExpression, FromSqlRow};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum RewardKind {
+    Xp,
+    Currency,
+    Achievement,
+    Item,
+}
+
+impl ToSql<Text, Pg> for RewardKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let kind: &[u8] = match self {
+            RewardKind::Xp => b"xp",
+            RewardKind::Currency => b"currency",
+            RewardKind::Achievement => b"achievement",
+            RewardKind::Item => b"item",
+        };
+        out.write_all(kind)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for RewardKind {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        match value.as_bytes() {
+            b"xp" => Ok(RewardKind::Xp),
+            b"currency" => Ok(RewardKind::Currency),
+            b"achievement" => Ok(RewardKind::Achievement),
+            b"item" => Ok(RewardKind::Item),
+            other => Err(format!("Unknown reward kind: {}", String::from_utf8_lossy(other)).into()),
+        }
+    }
+}
+
+// What a stage of the reward pipeline granted a monster for a battle. Achievements and items are named.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::battle_rewards)]
+pub struct Reward {
+    #[serde(skip_serializing, default)]
+    pub id: String,
+    #[serde(skip_serializing, default)]
+    pub battle_id: String,
+    #[serde(rename = "monster")]
+    pub monster_id: String,
+    pub stage: String,
+    pub kind: RewardKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub amount: i32,
+    #[serde(skip_serializing, default)]
+    pub created_at: NaiveDateTime,
+}
+
+impl Reward {
+    pub fn new(battle_id: &str, monster_id: &str, stage: &str, kind: RewardKind, name: Option<String>, amount: i32) -> Self {
+        Reward {
+            id: uuid::Uuid::new_v4().to_string(),
+            battle_id: battle_id.to_string(),
+            monster_id: monster_id.to_string(),
+            stage: stage.to_string(),
+            kind,
+            name,
+            amount,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use diesel::{AsExpression, FromSqlRow};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Jsonb;
+use serde::{Deserialize, Serialize};
+use crate::balance::Balance;
+use crate::models::fixed_point::Fixed;
+
+pub const MAX_HP_PERCENT: i32 = 1000;
+
+/*
+Handicaps and rules a simulated battle is played with, stored with it. The defaults are the classic rules:
+no turn limit, no crits, full HP and hits dealing at least 1 damage. A battle reaching `max_turns` goes to
+the monster with the larger share of its starting HP left, and is a draw when both shares are equal.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Jsonb)]
+pub struct BattleRules {
+    // The preset the rules started from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<u32>,
+    pub crits: bool,
+    // The HP each monster starts with, in percent of its own.
+    pub monster_a_hp_percent: i32,
+    pub monster_b_hp_percent: i32,
+    pub damage_floor: i32,
+}
+
+impl Default for BattleRules {
+    fn default() -> Self {
+        BattleRules { preset: None, max_turns: None, crits: false, monster_a_hp_percent: 100, monster_b_hp_percent: 100, damage_floor: 1 }
+    }
+}
+
+pub const PRESETS: [&str; 3] = ["classic", "chaos", "sudden-death"];
+
+impl BattleRules {
+    // The named presets: `classic`, `chaos` with crits and hard hits, and `sudden-death` at a tenth of the HP for 10 turns.
+    pub fn preset(name: &str) -> Option<Self> {
+        let rules = match name {
+            "classic" => BattleRules::default(),
+            "chaos" => BattleRules { crits: true, damage_floor: 10, ..BattleRules::default() },
+            "sudden-death" => BattleRules { max_turns: Some(10), crits: true, monster_a_hp_percent: 10, monster_b_hp_percent: 10, ..BattleRules::default() },
+            _ => return None,
+        };
+        Some(BattleRules { preset: Some(name.to_string()), ..rules })
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_turns == Some(0) {
+            return Err("Max turns must be at least 1".to_string());
+        }
+        if !(1..=MAX_HP_PERCENT).contains(&self.monster_a_hp_percent) || !(1..=MAX_HP_PERCENT).contains(&self.monster_b_hp_percent) {
+            return Err(format!("HP percents must be between 1 and {}", MAX_HP_PERCENT));
+        }
+        if self.damage_floor < 1 {
+            return Err("The damage floor must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    // Hits are critical with the crit chance of the balance when crits are on, one time in ten with the built-in one.
+    pub fn crit_chance(&self, balance: &Balance) -> Option<Fixed> {
+        self.crits.then(|| Fixed::from_percent(balance.crits.chance_percent))
+    }
+}
+
+// The HP a monster starts with under a handicap, at least 1.
+pub fn handicapped_hp(hp: i32, percent: i32) -> i32 {
+    (hp as i64 * percent as i64 / 100).clamp(1, i32::MAX as i64) as i32
+}
+
+impl ToSql<Jsonb, Pg> for BattleRules {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let value = serde_json::to_value(self)?;
+        <serde_json::Value as ToSql<Jsonb, Pg>>::to_sql(&value, &mut out.reborrow())
+    }
+}
+
+impl FromSql<Jsonb, Pg> for BattleRules {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        let value = <serde_json::Value as FromSql<Jsonb, Pg>>::from_sql(value)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_start_presets_from_the_classic_rules() {
+        assert_eq!(BattleRules::preset("classic").unwrap(), BattleRules { preset: Some("classic".to_string()), ..BattleRules::default() });
+        assert!(PRESETS.iter().all(|name| BattleRules::preset(name).unwrap().validate().is_ok()));
+        assert!(BattleRules::preset("rumble").is_none());
+        assert!(BattleRules { monster_b_hp_percent: 0, ..BattleRules::default() }.validate().is_err());
+        assert!(BattleRules { max_turns: Some(0), ..BattleRules::default() }.validate().is_err());
+        assert_eq!(handicapped_hp(50, 10), 5);
+        assert_eq!(handicapped_hp(5, 10), 1);
+    }
+}
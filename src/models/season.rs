@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use diesel::{Queryable, Insertable};
+use crate::models::battle::Battle;
+
+// Points a monster earns for each battle won or drawn during a season.
+pub const WIN_POINTS: i32 = 3;
+pub const DRAW_POINTS: i32 = 1;
+
+/*
+A ranked season. Battles stored while it is open are tagged with it and only they count towards its
+standings, so every monster starts the next season from zero. Closing it snapshots its final standings.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::seasons)]
+pub struct Season {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "startedAt")]
+    pub started_at: NaiveDateTime,
+    #[serde(rename = "endedAt")]
+    pub ended_at: Option<NaiveDateTime>,
+}
+
+impl Season {
+    pub fn new(name: String) -> Self {
+        Season {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            started_at: chrono::Utc::now().naive_utc(),
+            ended_at: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::season_standings)]
+pub struct SeasonStanding {
+    #[serde(skip_serializing, default)]
+    pub season_id: String,
+    #[serde(rename = "monster")]
+    pub monster_id: String,
+    pub rank: i32,
+    pub points: i32,
+    pub wins: i32,
+    pub losses: i32,
+    pub draws: i32,
+}
+
+// Ranks the monsters of the season's battles by points, then wins, ties sharing a rank.
+pub fn standings(season_id: &str, battles: &[Battle]) -> Vec<SeasonStanding> {
+    let mut records: HashMap<&str, (i32, i32, i32)> = HashMap::new();
+    for battle in battles.iter().filter(|battle| battle.season_id.as_deref() == Some(season_id)) {
+        for monster_id in [&battle.monster_a, &battle.monster_b] {
+            let (wins, losses, draws) = records.entry(monster_id).or_default();
+            match battle.winner.as_deref() {
+                None => *draws += 1,
+                Some(winner) if winner == monster_id => *wins += 1,
+                Some(_) => *losses += 1,
+            }
+        }
+    }
+
+    let mut standings: Vec<SeasonStanding> = records
+        .into_iter()
+        .map(|(monster_id, (wins, losses, draws))| SeasonStanding {
+            season_id: season_id.to_string(),
+            monster_id: monster_id.to_string(),
+            rank: 0,
+            points: wins * WIN_POINTS + draws * DRAW_POINTS,
+            wins,
+            losses,
+            draws,
+        })
+        .collect();
+    standings.sort_by(|a, b| b.points.cmp(&a.points).then(b.wins.cmp(&a.wins)).then(a.monster_id.cmp(&b.monster_id)));
+    for index in 0..standings.len() {
+        standings[index].rank = match index.checked_sub(1).map(|previous| &standings[previous]) {
+            Some(previous) if (previous.points, previous.wins) == (standings[index].points, standings[index].wins) => previous.rank,
+            _ => index as i32 + 1,
+        };
+    }
+    standings
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::battle::BattleOutcome;
+
+    use super::*;
+
+    #[test]
+    fn test_should_rank_the_monsters_of_the_season_by_points() {
+        let battle = |monster_a: &str, monster_b: &str, winner: Option<&str>, season_id: Option<&str>| Battle {
+            id: uuid::Uuid::new_v4().to_string(),
+            monster_a: monster_a.to_string(),
+            monster_b: monster_b.to_string(),
+            winner: winner.map(str::to_string),
+            created_at: None,
+            updated_at: None,
+            outcome: if winner.is_some() { BattleOutcome::Win } else { BattleOutcome::Draw },
+            manual: false,
+            season_id: season_id.map(str::to_string),
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        };
+        let battles = vec![
+            battle("a", "b", Some("a"), Some("s1")),
+            battle("b", "c", Some("c"), Some("s1")),
+            battle("a", "c", None, Some("s1")),
+            // Battles of other seasons, or of none, don't count.
+            battle("b", "c", Some("b"), Some("s0")),
+            battle("b", "a", Some("b"), None),
+        ];
+
+        let ranked: Vec<_> = standings("s1", &battles)
+            .into_iter()
+            .map(|standing| (standing.monster_id, standing.rank, standing.points, standing.wins, standing.losses, standing.draws))
+            .collect();
+        assert_eq!(ranked, vec![
+            ("a".to_string(), 1, 4, 1, 0, 1),
+            ("c".to_string(), 1, 4, 1, 0, 1),
+            ("b".to_string(), 3, 0, 0, 2, 0),
+        ]);
+        assert!(standings("s2", &battles).is_empty());
+    }
+}
@@ -0,0 +1,43 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use diesel::{Queryable, Insertable};
+use crate::models::battle::Battle;
+
+/*
+A best-of series between two monsters, won by the first one to win a majority of `best_of` games.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::battle_series)]
+pub struct BattleSeries {
+    pub id: String,
+    pub monster_a: String,
+    pub monster_b: String,
+    pub best_of: i32,
+    pub winner: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::battle_series_games)]
+pub struct BattleSeriesGame {
+    pub series_id: String,
+    pub game: i32,
+    pub battle_id: String,
+    pub seed: i64,
+}
+
+// A game of a series with its stored battle, `seed` replays the same damage rolls.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SeriesGame {
+    pub game: i32,
+    pub seed: i64,
+    pub battle: Battle,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExpandedBattleSeries {
+    #[serde(flatten)]
+    pub series: BattleSeries,
+    pub games: Vec<SeriesGame>,
+}
@@ -0,0 +1,182 @@
+use std::io::Write;
+use serde::{Deserialize, Serialize};
+use diesel::{AsExpression, FromSqlRow};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use crate::config::AppConfig;
+use crate::models::fixed_point::Fixed;
+use crate::models::monster::StatModifiers;
+
+const POISON_HP_DIVISOR: i32 = 8;
+const BURN_ATTACK_FACTOR: Fixed = Fixed::from_percent(50);
+
+/*
+Conditions a hit can leave on the defender for a number of its own turns. Each effect acts through the
+hooks below, a new effect only has to pick the ones it needs and get a default duration.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusEffect {
+    // Loses an eighth of its starting HP at the start of each turn, with a minimum of 1.
+    Poison,
+    // Skips its turns.
+    Stun,
+    // Attacks with half its attack.
+    Burn,
+}
+
+impl StatusEffect {
+    pub const ALL: [StatusEffect; 3] = [StatusEffect::Poison, StatusEffect::Stun, StatusEffect::Burn];
+
+    fn name(&self) -> &'static str {
+        match self {
+            StatusEffect::Poison => "POISON",
+            StatusEffect::Stun => "STUN",
+            StatusEffect::Burn => "BURN",
+        }
+    }
+
+    pub fn default_turns(&self) -> u32 {
+        match self {
+            StatusEffect::Poison => 3,
+            StatusEffect::Stun => 1,
+            StatusEffect::Burn => 3,
+        }
+    }
+
+    // Damage taken when the afflicted monster's turn starts.
+    pub fn turn_damage(&self, starting_hp: i32) -> i32 {
+        match self {
+            StatusEffect::Poison => (starting_hp / POISON_HP_DIVISOR).max(1),
+            _ => 0,
+        }
+    }
+
+    pub fn skips_turn(&self) -> bool {
+        matches!(self, StatusEffect::Stun)
+    }
+
+    // Applied to the stats the afflicted monster attacks with.
+    pub fn modifiers(&self) -> StatModifiers {
+        match self {
+            StatusEffect::Burn => StatModifiers { attack: BURN_ATTACK_FACTOR, ..StatModifiers::default() },
+            _ => StatModifiers::default(),
+        }
+    }
+}
+
+// Stored as the effect of moves.
+impl ToSql<Text, Pg> for StatusEffect {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let effect: &[u8] = match self {
+            StatusEffect::Poison => b"poison",
+            StatusEffect::Stun => b"stun",
+            StatusEffect::Burn => b"burn",
+        };
+        out.write_all(effect)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for StatusEffect {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        match value.as_bytes() {
+            b"poison" => Ok(StatusEffect::Poison),
+            b"stun" => Ok(StatusEffect::Stun),
+            b"burn" => Ok(StatusEffect::Burn),
+            other => Err(format!("Unknown status effect: {}", String::from_utf8_lossy(other)).into()),
+        }
+    }
+}
+
+// Chance of a damaging hit to inflict the effect, which then lasts `turns` turns of the defender.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatusEffectRule {
+    pub effect: StatusEffect,
+    pub chance: Fixed,
+    pub turns: u32,
+}
+
+/*
+Optional rule letting hits inflict status effects. An effect is enabled by STATUS_<EFFECT>_CHANCE, a
+probability between 0 and 1 with at most four decimals, and lasts STATUS_<EFFECT>_TURNS turns (e.g. STATUS_POISON_CHANCE=0.2).
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusEffectRules(pub Vec<StatusEffectRule>);
+
+impl StatusEffectRules {
+    // The rule is disabled (None) unless an effect has a chance set.
+    pub fn from_config(config: &AppConfig) -> Result<Option<Self>, String> {
+        StatusEffectRules::from_vars(|name| config.var(name))
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Option<Self>, String> {
+        let mut rules = Vec::new();
+        for effect in StatusEffect::ALL {
+            let chance_var = format!("STATUS_{}_CHANCE", effect.name());
+            let chance: Fixed = match var(&chance_var) {
+                Some(chance) => parse(&chance, &chance_var)?,
+                None => continue,
+            };
+            let turns_var = format!("STATUS_{}_TURNS", effect.name());
+            let turns = var(&turns_var)
+                .map(|turns| parse(&turns, &turns_var))
+                .transpose()?
+                .unwrap_or(effect.default_turns());
+
+            if !(Fixed::ZERO..=Fixed::ONE).contains(&chance) || turns == 0 {
+                return Err(format!("{} must be between 0 and 1 and {} at least 1", chance_var, turns_var));
+            }
+            rules.push(StatusEffectRule { effect, chance, turns });
+        }
+        Ok((!rules.is_empty()).then_some(StatusEffectRules(rules)))
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, String> {
+    value.trim().parse().map_err(|_| format!("{} must be a number, got {:?}", name, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::models::monster::Stats;
+
+    use super::*;
+
+    fn status_rules(vars: &[(&str, &str)]) -> Result<Option<StatusEffectRules>, String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        StatusEffectRules::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_should_only_enable_the_configured_effects() {
+        assert_eq!(status_rules(&[]), Ok(None));
+        assert_eq!(
+            status_rules(&[("STATUS_POISON_CHANCE", "0.2"), ("STATUS_BURN_CHANCE", "1"), ("STATUS_BURN_TURNS", "5")]),
+            Ok(Some(StatusEffectRules(vec![
+                StatusEffectRule { effect: StatusEffect::Poison, chance: Fixed::from_percent(20), turns: 3 },
+                StatusEffectRule { effect: StatusEffect::Burn, chance: Fixed::ONE, turns: 5 },
+            ])))
+        );
+        assert!(status_rules(&[("STATUS_STUN_CHANCE", "often")]).is_err());
+        assert!(status_rules(&[("STATUS_STUN_CHANCE", "1.5")]).is_err());
+        assert!(status_rules(&[("STATUS_STUN_CHANCE", "0.5"), ("STATUS_STUN_TURNS", "0")]).is_err());
+    }
+
+    #[test]
+    fn test_should_apply_the_hooks_of_each_effect() {
+        let stats = Stats { attack: 40, defense: 20, hp: 50, speed: 80 };
+
+        assert_eq!(StatusEffect::Poison.turn_damage(100), 12);
+        assert_eq!(StatusEffect::Poison.turn_damage(5), 1);
+        assert_eq!(StatusEffect::Burn.turn_damage(100), 0);
+        assert!(StatusEffect::Stun.skips_turn());
+        assert!(!StatusEffect::Poison.skips_turn());
+        assert_eq!(stats.with_modifiers(&StatusEffect::Burn.modifiers()), Stats { attack: 20, ..stats });
+        assert_eq!(stats.with_modifiers(&StatusEffect::Stun.modifiers()), stats);
+    }
+}
@@ -0,0 +1,113 @@
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use crate::models::moves::Move;
+use crate::models::status_effect::StatusEffect;
+
+/*
+How a monster picks the move it attacks with. Strategies only choose between the moves the monster knows,
+monsters without moves make a plain attack whatever their strategy.
+*/
+pub trait BattleStrategy: Sync {
+    // `defender_statuses` are the effects the defender suffers, `rolls` the battle's chance rolls.
+    fn pick_move<'a>(&self, moves: &'a [Move], defender_statuses: &[StatusEffect], rolls: &mut dyn RngCore) -> Option<&'a Move>;
+}
+
+// Any of the moves, with the same chance.
+pub struct RandomStrategy;
+
+// The move with the highest power times accuracy, the first one on ties.
+pub struct GreedyDamageStrategy;
+
+// The most accurate move, preferring effects the defender doesn't suffer yet and then power.
+pub struct DefensiveStrategy;
+
+impl BattleStrategy for RandomStrategy {
+    fn pick_move<'a>(&self, moves: &'a [Move], _: &[StatusEffect], rolls: &mut dyn RngCore) -> Option<&'a Move> {
+        if moves.is_empty() {
+            return None;
+        }
+        moves.get(rolls.gen_range(0..moves.len()))
+    }
+}
+
+impl BattleStrategy for GreedyDamageStrategy {
+    fn pick_move<'a>(&self, moves: &'a [Move], _: &[StatusEffect], _: &mut dyn RngCore) -> Option<&'a Move> {
+        // `max_by_key` keeps the last of equal moves, reversing makes it the first slot's.
+        moves.iter().rev().max_by_key(|known_move| known_move.power * known_move.accuracy)
+    }
+}
+
+impl BattleStrategy for DefensiveStrategy {
+    fn pick_move<'a>(&self, moves: &'a [Move], defender_statuses: &[StatusEffect], _: &mut dyn RngCore) -> Option<&'a Move> {
+        moves.iter().rev().max_by_key(|known_move| {
+            let new_effect = known_move.effect.is_some_and(|effect| !defender_statuses.contains(&effect));
+            (known_move.accuracy, new_effect, known_move.power)
+        })
+    }
+}
+
+// The strategies a battle request can pick for each monster.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    Random,
+    #[default]
+    GreedyDamage,
+    Defensive,
+}
+
+impl Strategy {
+    pub fn battle_strategy(&self) -> &'static dyn BattleStrategy {
+        match self {
+            Strategy::Random => &RandomStrategy,
+            Strategy::GreedyDamage => &GreedyDamageStrategy,
+            Strategy::Defensive => &DefensiveStrategy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    fn new_move(name: &str, power: i32, accuracy: i32, effect: Option<StatusEffect>) -> Move {
+        Move {
+            id: name.to_string(),
+            name: name.to_string(),
+            power,
+            accuracy,
+            element: None,
+            effect,
+            effect_chance: if effect.is_some() { 100 } else { 0 },
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: None,
+        }
+    }
+
+    fn picked(strategy: Strategy, moves: &[Move], defender_statuses: &[StatusEffect], rolls: &mut ChaCha8Rng) -> Option<String> {
+        strategy.battle_strategy().pick_move(moves, defender_statuses, rolls).map(|picked| picked.name.clone())
+    }
+
+    #[test]
+    fn test_should_pick_a_move_according_to_the_strategy() {
+        let moves = vec![
+            new_move("Tackle", 40, 100, None),
+            new_move("Thunder", 120, 70, Some(StatusEffect::Stun)),
+            new_move("Spark", 40, 100, Some(StatusEffect::Stun)),
+        ];
+        let mut rolls = ChaCha8Rng::seed_from_u64(7);
+
+        assert_eq!(picked(Strategy::GreedyDamage, &moves, &[], &mut rolls).as_deref(), Some("Thunder"));
+        assert_eq!(picked(Strategy::Defensive, &moves, &[], &mut rolls).as_deref(), Some("Spark"));
+        assert_eq!(picked(Strategy::Defensive, &moves, &[StatusEffect::Stun], &mut rolls).as_deref(), Some("Tackle"));
+        assert_eq!(picked(Strategy::Random, &[], &[], &mut rolls), None);
+
+        let mut picks: Vec<String> = (0..50).filter_map(|_| picked(Strategy::Random, &moves, &[], &mut rolls)).collect();
+        picks.sort();
+        picks.dedup();
+        assert_eq!(picks, vec!["Spark", "Tackle", "Thunder"]);
+    }
+}
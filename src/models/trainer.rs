@@ -0,0 +1,85 @@
+use std::io::Write;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use diesel::{Queryable, Insertable, AsExpression, FromSqlRow};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+
+// The rate limit tier of the API keys issued to trainers when they register.
+pub const TRAINER_TIER: &str = "free";
+pub const MAX_TRAINER_NAME_LENGTH: usize = 50;
+
+/*
+A player owning monsters. Trainers authenticate with the API key issued when they registered, and the monsters
+they create are theirs until they transfer or trade them.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::trainers)]
+pub struct Trainer {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing, default)]
+    pub api_key_id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: NaiveDateTime,
+    // The region the trainer plays from, the one of the instance it registered on unless it picked one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeStatus {
+    Pending,
+    Accepted,
+    Declined,
+    Cancelled,
+}
+
+impl ToSql<Text, Pg> for TradeStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let status: &[u8] = match self {
+            TradeStatus::Pending => b"pending",
+            TradeStatus::Accepted => b"accepted",
+            TradeStatus::Declined => b"declined",
+            TradeStatus::Cancelled => b"cancelled",
+        };
+        out.write_all(status)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for TradeStatus {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        match value.as_bytes() {
+            b"pending" => Ok(TradeStatus::Pending),
+            b"accepted" => Ok(TradeStatus::Accepted),
+            b"declined" => Ok(TradeStatus::Declined),
+            b"cancelled" => Ok(TradeStatus::Cancelled),
+            other => Err(format!("Unknown trade status: {}", String::from_utf8_lossy(other)).into()),
+        }
+    }
+}
+
+/*
+A swap of monsters proposed to the trainer owning the requested one. Accepting it swaps the owners of both
+monsters, as long as neither changed hands since it was proposed. The recipient declines it, the proposer
+cancels it.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = crate::repository::schema::trades)]
+pub struct Trade {
+    pub id: String,
+    pub proposer: String,
+    pub recipient: String,
+    pub offered_monster: String,
+    pub requested_monster: String,
+    pub status: TradeStatus,
+    #[serde(rename = "createdAt")]
+    pub created_at: NaiveDateTime,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<NaiveDateTime>,
+}
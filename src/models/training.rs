@@ -0,0 +1,146 @@
+use chrono::NaiveDateTime;
+use diesel::pg::Pg;
+use diesel::Queryable;
+use serde::{Deserialize, Serialize};
+use crate::balance::Balance;
+use crate::models::monster::Stats;
+use crate::repository::schema::monster_training;
+
+// The limits the database enforces, the training budgets of the balance can only lower them.
+pub const MAX_INDIVIDUAL_VALUE: i32 = 31;
+pub const MAX_STAT_TRAINING_POINTS: i32 = 252;
+// Allocated and unspent points together, wins past it earn nothing more.
+pub const MAX_TRAINING_POINTS: i32 = 510;
+
+/*
+Hidden modifiers of a monster, added to its stats in battles on top of its level and evolutions: individual
+values, set by admins, and training points, earned by winning battles then allocated to the stats by the
+players. Monsters that never trained have none, all of them at 0.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MonsterTraining {
+    #[serde(rename = "monster")]
+    pub monster_id: String,
+    pub individual_values: Stats,
+    pub training_points: Stats,
+    pub unspent_points: i32,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+// The IVs and training points are plain columns, like the stats of the monsters.
+impl Queryable<monster_training::SqlType, Pg> for MonsterTraining {
+    type Row = (String, i32, i32, i32, i32, i32, i32, i32, i32, i32, NaiveDateTime);
+
+    fn build((monster_id, iv_attack, iv_defense, iv_hp, iv_speed, tp_attack, tp_defense, tp_hp, tp_speed, unspent_points, updated_at): Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(MonsterTraining {
+            monster_id,
+            individual_values: Stats { attack: iv_attack, defense: iv_defense, hp: iv_hp, speed: iv_speed },
+            training_points: Stats { attack: tp_attack, defense: tp_defense, hp: tp_hp, speed: tp_speed },
+            unspent_points,
+            updated_at: Some(updated_at),
+        })
+    }
+}
+
+fn stats_within(stats: &Stats, max: i32) -> bool {
+    [stats.attack, stats.defense, stats.hp, stats.speed].iter().all(|stat| (0..=max).contains(stat))
+}
+
+impl MonsterTraining {
+    pub fn new(monster_id: &str) -> Self {
+        MonsterTraining {
+            monster_id: monster_id.to_string(),
+            individual_values: Stats::default(),
+            training_points: Stats::default(),
+            unspent_points: 0,
+            updated_at: None,
+        }
+    }
+
+    // What the modifiers add to each stat.
+    pub fn hidden_stats(&self, balance: &Balance) -> Stats {
+        let stat = |iv: i32, points: i32| iv + points / balance.training.points_per_stat_point;
+        Stats {
+            attack: stat(self.individual_values.attack, self.training_points.attack),
+            defense: stat(self.individual_values.defense, self.training_points.defense),
+            hp: stat(self.individual_values.hp, self.training_points.hp),
+            speed: stat(self.individual_values.speed, self.training_points.speed),
+        }
+    }
+
+    // Adds the points, up to the max points of the balance along with the allocated ones. Returns the points earned.
+    pub fn earn(&mut self, points: i32, balance: &Balance) -> i32 {
+        let room = (i64::from(balance.training.max_points) - self.training_points.total() - i64::from(self.unspent_points)).max(0);
+        let earned = i64::from(points).clamp(0, room) as i32;
+        self.unspent_points += earned;
+        earned
+    }
+
+    // Moves unspent points to the stats, each stat taking at most the max stat points of the balance.
+    pub fn allocate(&mut self, points: &Stats, balance: &Balance) -> Result<(), String> {
+        let max_stat_points = balance.training.max_stat_points;
+        if !stats_within(points, max_stat_points) || points.total() == 0 {
+            return Err(format!("Allocated points must be between 0 and {}, at least one of them positive", max_stat_points));
+        }
+        if points.total() > i64::from(self.unspent_points) {
+            return Err(format!("Only {} training points are left to allocate", self.unspent_points));
+        }
+        let allocated = Stats {
+            attack: self.training_points.attack + points.attack,
+            defense: self.training_points.defense + points.defense,
+            hp: self.training_points.hp + points.hp,
+            speed: self.training_points.speed + points.speed,
+        };
+        if !stats_within(&allocated, max_stat_points) {
+            return Err(format!("A stat takes at most {} training points", max_stat_points));
+        }
+        self.training_points = allocated;
+        self.unspent_points -= points.total() as i32;
+        Ok(())
+    }
+
+    pub fn set_individual_values(&mut self, individual_values: Stats) -> Result<(), String> {
+        if !stats_within(&individual_values, MAX_INDIVIDUAL_VALUE) {
+            return Err(format!("Individual values must be between 0 and {}", MAX_INDIVIDUAL_VALUE));
+        }
+        self.individual_values = individual_values;
+        Ok(())
+    }
+}
+
+// The stats a monster fights with given its hidden stats.
+pub fn with_hidden_stats(stats: &Stats, hidden_stats: &Stats) -> Stats {
+    Stats {
+        attack: stats.attack.saturating_add(hidden_stats.attack),
+        defense: stats.defense.saturating_add(hidden_stats.defense),
+        hp: stats.hp.saturating_add(hidden_stats.hp),
+        speed: stats.speed.saturating_add(hidden_stats.speed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_allocate_earned_points_within_the_caps() {
+        let balance = Balance::default();
+        let mut training = MonsterTraining::new("monster");
+        assert_eq!(training.earn(300, &balance), 300);
+        assert!(training.allocate(&Stats { attack: 253, ..Stats::default() }, &balance).is_err());
+        assert!(training.allocate(&Stats { attack: -1, defense: 2, ..Stats::default() }, &balance).is_err());
+        assert!(training.allocate(&Stats::default(), &balance).is_err());
+        assert!(training.allocate(&Stats { attack: 200, defense: 101, ..Stats::default() }, &balance).is_err());
+        training.allocate(&Stats { attack: 200, defense: 40, ..Stats::default() }, &balance).unwrap();
+        assert!(training.allocate(&Stats { attack: 53, ..Stats::default() }, &balance).is_err());
+        assert_eq!(training.unspent_points, 60);
+        assert_eq!(training.earn(300, &balance), 210);
+        assert_eq!(training.earn(balance.training.points_per_win, &balance), 0);
+
+        assert!(training.set_individual_values(Stats { hp: 32, ..Stats::default() }).is_err());
+        training.set_individual_values(Stats { attack: 31, defense: 0, hp: 10, speed: 5 }).unwrap();
+        assert_eq!(training.hidden_stats(&balance), Stats { attack: 81, defense: 10, hp: 10, speed: 5 });
+        assert_eq!(with_hidden_stats(&Stats { attack: 40, defense: 10, hp: 50, speed: 80 }, &training.hidden_stats(&balance)), Stats { attack: 121, defense: 20, hp: 60, speed: 85 });
+    }
+}
@@ -0,0 +1,21 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use diesel::{AsChangeset, Insertable, Queryable};
+
+pub const BATTLE_COMPLETED: &str = "battle.completed";
+pub const EVENT_TYPES: [&str; 1] = [BATTLE_COMPLETED];
+
+// The secret signing the callbacks is only returned once, when the webhook is created.
+#[derive(Serialize, Deserialize, Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[diesel(table_name = crate::repository::schema::webhooks)]
+pub struct Webhook {
+    pub id: String,
+    pub target_url: String,
+    pub event_types: Vec<String>,
+    #[serde(skip_serializing, default)]
+    pub secret: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: NaiveDateTime,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<NaiveDateTime>,
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::HttpRequest;
+use crate::api::auth;
+use crate::api::routes::RateLimitClass;
+use crate::models::rate_limit::{hash_api_key, ApiKey, RateLimitTier, ADMIN_TIER, ANONYMOUS_TIER};
+use crate::repository::rate_limit_repository::RateLimitRepository;
+use crate::repository::request_scope::RequestScope;
+
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+// The budget headers set on every rate limited response.
+pub const RATE_LIMIT_HEADERS: [&str; 4] = ["x-ratelimit-tier", "x-ratelimit-limit", "x-ratelimit-remaining", "x-ratelimit-reset"];
+const WINDOW: Duration = Duration::from_secs(60);
+/*
+Past this many clients, the windows that are over are dropped before tracking a new one, at most once per
+SWEEP_INTERVAL. Clients that still find no room share one window per tier and class until some frees up.
+*/
+const MAX_TRACKED_WINDOWS: usize = 10_000;
+const SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+const UNTRACKED_CLIENTS: &str = "untracked";
+// How long a key looked up stays cached, so a deleted key or a changed tier is picked up without a reload.
+const API_KEY_TTL: Duration = Duration::from_secs(60);
+
+// The budget left to a client after counting its request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitStatus {
+    pub tier: String,
+    pub limit: i32,
+    pub remaining: i32,
+    pub reset: Duration,
+}
+
+impl RateLimitStatus {
+    // Sets the X-RateLimit-* headers, `X-RateLimit-Reset` being the seconds until the budget is restored.
+    pub fn insert_headers(&self, headers: &mut HeaderMap) {
+        let reset_seconds = self.reset.as_secs() + u64::from(self.reset.subsec_nanos() > 0);
+        let values = [self.tier.clone(), self.limit.to_string(), self.remaining.to_string(), reset_seconds.to_string()];
+        for (name, value) in RATE_LIMIT_HEADERS.into_iter().zip(values) {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                headers.insert(HeaderName::from_static(name), value);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateLimitError {
+    InvalidApiKey,
+    Exceeded(RateLimitStatus),
+}
+
+struct Window {
+    started: Instant,
+    requests: i32,
+}
+
+struct CachedApiKey {
+    api_key: ApiKey,
+    looked_up: Instant,
+}
+
+struct Windows {
+    by_client: HashMap<(String, RateLimitClass), Window>,
+    swept: Instant,
+}
+
+/*
+Budgets the requests of each client per minute and rate limit class. A client is the admin token, an API key
+sent in X-Api-Key, or else the peer address, and gets the budget of its tier: `admin`, the tier of its key
+or `anonymous`, the anonymous budget also applying to the keys of a tier that no longer exists. Tiers are read once
+and cached, `reload` picks up the changes of the admin API. Keys are cached for API_KEY_TTL by the schema of
+the request they were looked up in.
+*/
+pub struct RateLimiter {
+    repository: Arc<dyn RateLimitRepository>,
+    tiers: RwLock<HashMap<String, RateLimitTier>>,
+    // Keys already looked up, by schema and hash.
+    api_keys: RwLock<HashMap<(Option<String>, String), CachedApiKey>>,
+    windows: Mutex<Windows>,
+}
+
+impl RateLimiter {
+    pub fn new(repository: Arc<dyn RateLimitRepository>) -> Self {
+        let windows = Mutex::new(Windows { by_client: HashMap::new(), swept: Instant::now() });
+        let rate_limiter = RateLimiter { repository, tiers: RwLock::default(), api_keys: RwLock::default(), windows };
+        rate_limiter.reload();
+        rate_limiter
+    }
+
+    pub fn reload(&self) {
+        let tiers = self.repository.get_rate_limit_tiers().into_iter().map(|tier| (tier.name.clone(), tier)).collect();
+        *self.tiers.write().expect("Rate limit tiers lock poisoned") = tiers;
+        self.api_keys.write().expect("API keys lock poisoned").clear();
+    }
+
+    // Counts the request against its client's budget. Only clients of an unknown tier when the anonymous one is gone too aren't limited.
+    pub fn check(&self, req: &HttpRequest, class: RateLimitClass) -> Result<Option<RateLimitStatus>, RateLimitError> {
+        let (client, tier_name) = self.client(req)?;
+        let tiers = self.tiers.read().expect("Rate limit tiers lock poisoned");
+        let Some(tier) = tiers.get(&tier_name).or_else(|| tiers.get(ANONYMOUS_TIER)) else {
+            return Ok(None);
+        };
+        let limit = match class {
+            RateLimitClass::Standard => tier.standard_per_minute,
+            RateLimitClass::Expensive => tier.expensive_per_minute,
+            RateLimitClass::Streaming => tier.streaming_per_minute,
+        };
+        let tier_name = tier.name.clone();
+        drop(tiers);
+
+        let now = Instant::now();
+        let mut windows = self.windows.lock().expect("Rate limit windows lock poisoned");
+        let Windows { by_client, swept } = &mut *windows;
+        let mut client = (client, class);
+        if by_client.len() >= MAX_TRACKED_WINDOWS && !by_client.contains_key(&client) {
+            if now.duration_since(*swept) >= SWEEP_INTERVAL {
+                by_client.retain(|_, window| now.duration_since(window.started) < WINDOW);
+                *swept = now;
+            }
+            if by_client.len() >= MAX_TRACKED_WINDOWS {
+                client = (format!("{}:{}", UNTRACKED_CLIENTS, tier_name), class);
+            }
+        }
+        let window = by_client.entry(client).or_insert(Window { started: now, requests: 0 });
+        if now.duration_since(window.started) >= WINDOW {
+            *window = Window { started: now, requests: 0 };
+        }
+        let reset = WINDOW.saturating_sub(now.duration_since(window.started));
+        let status = |requests: i32| RateLimitStatus { tier: tier_name.clone(), limit, remaining: (limit - requests).max(0), reset };
+        if window.requests >= limit {
+            return Err(RateLimitError::Exceeded(status(window.requests)));
+        }
+        window.requests += 1;
+        Ok(Some(status(window.requests)))
+    }
+
+    fn client(&self, req: &HttpRequest) -> Result<(String, String), RateLimitError> {
+        if auth::is_admin(req) {
+            return Ok((ADMIN_TIER.to_string(), ADMIN_TIER.to_string()));
+        }
+        let Some(key) = req.headers().get(API_KEY_HEADER) else {
+            let peer = req.peer_addr().map(|address| address.ip().to_string()).unwrap_or_default();
+            return Ok((format!("peer:{}", peer), ANONYMOUS_TIER.to_string()));
+        };
+
+        let cache_key = (RequestScope::current().schema, hash_api_key(key.to_str().map_err(|_| RateLimitError::InvalidApiKey)?));
+        let now = Instant::now();
+        let cached = self.api_keys
+            .read()
+            .expect("API keys lock poisoned")
+            .get(&cache_key)
+            .filter(|cached| now.duration_since(cached.looked_up) < API_KEY_TTL)
+            .map(|cached| cached.api_key.clone());
+        let api_key = match cached {
+            Some(api_key) => api_key,
+            None => {
+                // Unknown keys aren't cached, so sending random ones can't grow the cache. Caching a key drops the expired ones.
+                let api_key = self.repository.get_api_key_by_hash(&cache_key.1).ok_or(RateLimitError::InvalidApiKey)?;
+                let mut api_keys = self.api_keys.write().expect("API keys lock poisoned");
+                api_keys.retain(|_, cached| now.duration_since(cached.looked_up) < API_KEY_TTL);
+                api_keys.insert(cache_key, CachedApiKey { api_key: api_key.clone(), looked_up: now });
+                api_key
+            }
+        };
+        Ok((format!("key:{}", api_key.id), api_key.tier))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use crate::repository::memory_repository::InMemoryRepository;
+
+    use super::*;
+
+    #[test]
+    fn test_should_budget_the_keys_of_an_unknown_tier_as_anonymous() {
+        let repository = Arc::new(InMemoryRepository::new());
+        let rate_limiter = RateLimiter::new(repository.clone());
+        // Saved after the tiers were read, like a tier deleted since.
+        repository.save_rate_limit_tier(RateLimitTier { name: "tester".to_string(), standard_per_minute: 1000, expensive_per_minute: 0, streaming_per_minute: 0, updated_at: None }).unwrap();
+        let api_key = ApiKey { id: String::new(), name: "CI".to_string(), key_hash: hash_api_key("bm_test"), tier: "tester".to_string(), created_at: chrono::Utc::now().naive_utc() };
+        repository.create_api_key(api_key).unwrap();
+
+        let req = TestRequest::default().insert_header((API_KEY_HEADER, "bm_test")).to_http_request();
+        let status = rate_limiter.check(&req, RateLimitClass::Standard).unwrap().unwrap();
+
+        assert_eq!((status.tier.as_str(), status.limit), (ANONYMOUS_TIER, 60));
+    }
+
+    #[test]
+    fn test_should_track_a_bounded_number_of_windows() {
+        let rate_limiter = RateLimiter::new(Arc::new(InMemoryRepository::new()));
+        let peer = |n: usize| TestRequest::default().peer_addr(format!("10.{}.{}.{}:4000", n >> 16, (n >> 8) & 255, n & 255).parse().unwrap()).to_http_request();
+
+        for n in 0..MAX_TRACKED_WINDOWS + 10 {
+            rate_limiter.check(&peer(n), RateLimitClass::Standard).unwrap();
+        }
+
+        let windows = rate_limiter.windows.lock().unwrap();
+        assert_eq!(windows.by_client.len(), MAX_TRACKED_WINDOWS + 1);
+        assert_eq!(windows.by_client[&(format!("{}:{}", UNTRACKED_CLIENTS, ANONYMOUS_TIER), RateLimitClass::Standard)].requests, 10);
+    }
+}
@@ -0,0 +1,75 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use crate::models::achievement::{battle_achievements, Achievement, AchievementKind, COLLECTOR_MONSTERS, WIN_STREAK_LENGTH};
+use crate::models::battle::Battle;
+use crate::repository::schema::{achievements, battles, monsters};
+use crate::repository::database::Database;
+
+/*
+Achievements are earned along the events they reward, in the transaction storing the battle or the monsters
+created, so they are never missed nor earned twice.
+*/
+pub trait AchievementRepository: Send + Sync {
+    // Oldest first.
+    fn get_monster_achievements(&self, monster_id: &str) -> Vec<Achievement>;
+    // The achievements of the trainer and the ones its monsters earned while it owned them, oldest first.
+    fn get_trainer_achievements(&self, trainer_id: &str) -> Vec<Achievement>;
+}
+
+impl AchievementRepository for Database {
+    fn get_monster_achievements(&self, monster_id: &str) -> Vec<Achievement> {
+        let mut connection = self.get_connection();
+        achievements::table
+            .filter(achievements::monster_id.eq(monster_id))
+            .order(achievements::earned_at)
+            .load::<Achievement>(&mut connection)
+            .expect("Error loading monster achievements")
+    }
+
+    fn get_trainer_achievements(&self, trainer_id: &str) -> Vec<Achievement> {
+        let mut connection = self.get_connection();
+        achievements::table
+            .filter(achievements::trainer_id.eq(trainer_id))
+            .order(achievements::earned_at)
+            .load::<Achievement>(&mut connection)
+            .expect("Error loading trainer achievements")
+    }
+}
+
+// Grants the winner of the battle just stored what it earned, the battle being the latest of the winner.
+pub(crate) fn earn_battle_achievements(connection: &mut PgConnection, battle: &Battle) -> Result<(), diesel::result::Error> {
+    let Some(winner) = &battle.winner else { return Ok(()) };
+    let latest_battles = battles::table
+        .filter(battles::monster_a.eq(winner).or(battles::monster_b.eq(winner)))
+        .order(battles::created_at.desc())
+        .limit(WIN_STREAK_LENGTH as i64)
+        .load::<Battle>(connection)?;
+    let owner = if winner == &battle.monster_a { &battle.trainer_a } else { &battle.trainer_b };
+    let earned: Vec<Achievement> = battle_achievements(battle, &latest_battles)
+        .into_iter()
+        .map(|kind| Achievement::new(kind, Some(winner.clone()), owner.clone(), Some(battle.id.clone()), Utc::now().naive_utc()))
+        .collect();
+    save_achievements(connection, &earned)
+}
+
+// Grants the trainer the collector achievement once it owns enough monsters.
+pub(crate) fn earn_collector_achievement(connection: &mut PgConnection, trainer_id: &str) -> Result<(), diesel::result::Error> {
+    let owned = monsters::table
+        .filter(monsters::owner_id.eq(trainer_id))
+        .count()
+        .get_result::<i64>(connection)?;
+    if owned < COLLECTOR_MONSTERS {
+        return Ok(());
+    }
+    let achievement = Achievement::new(AchievementKind::Collector, None, Some(trainer_id.to_string()), None, Utc::now().naive_utc());
+    save_achievements(connection, &[achievement])
+}
+
+// Achievements already earned are left as they were.
+fn save_achievements(connection: &mut PgConnection, earned: &[Achievement]) -> Result<(), diesel::result::Error> {
+    diesel::insert_into(achievements::table)
+        .values(earned)
+        .on_conflict_do_nothing()
+        .execute(connection)?;
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use std::future::Future;
+use chrono::prelude::*;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use crate::models::audit::AuditEntry;
+use crate::repository::schema::audit_log;
+use crate::repository::database::Database;
+
+// Names the person behind the credentials of a request, see `auth::actor`.
+pub const ACTOR_HEADER: &str = "X-Actor";
+
+tokio::task_local! {
+    pub(crate) static ACTOR: String;
+}
+
+pub async fn with_actor<F: Future>(actor: String, future: F) -> F::Output {
+    ACTOR.scope(actor, future).await
+}
+
+/*
+Records a write on `connection`, so callers can keep it in the same transaction as the change itself.
+`before` is None for creations and `after` is None for deletions.
+*/
+pub fn record<T: Serialize>(
+    connection: &mut PgConnection,
+    entity_type: &str,
+    entity_id: &str,
+    action: &str,
+    before: Option<&T>,
+    after: Option<&T>,
+) -> QueryResult<()> {
+    let entry = AuditEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        entity_type: entity_type.to_string(),
+        entity_id: entity_id.to_string(),
+        action: action.to_string(),
+        actor: ACTOR.try_with(|actor| actor.clone()).ok(),
+        diff: diff(to_value(before), to_value(after)),
+        created_at: Utc::now().naive_utc(),
+    };
+    diesel::insert_into(audit_log::table)
+        .values(&entry)
+        .execute(connection)?;
+    Ok(())
+}
+
+pub fn get_audit_entries(db: &Database, entity_type: &str, entity_id: Option<&str>) -> Vec<AuditEntry> {
+    let mut connection = db.get_connection();
+    let mut query = audit_log::table
+        .filter(audit_log::entity_type.eq(entity_type))
+        .order(audit_log::created_at.desc())
+        .into_boxed();
+    if let Some(entity_id) = entity_id {
+        query = query.filter(audit_log::entity_id.eq(entity_id));
+    }
+    query
+        .load::<AuditEntry>(&mut connection)
+        .expect("Error loading audit entries")
+}
+
+fn to_value<T: Serialize>(entity: Option<&T>) -> Map<String, Value> {
+    match entity.map(serde_json::to_value) {
+        Some(Ok(Value::Object(fields))) => fields,
+        _ => Map::new(),
+    }
+}
+
+// Keeps only the fields that changed, as `{"field": {"from": old, "to": new}}`.
+fn diff(before: Map<String, Value>, after: Map<String, Value>) -> Value {
+    let mut changes = Map::new();
+    for (field, old) in &before {
+        let new = after.get(field).unwrap_or(&Value::Null);
+        if old != new {
+            changes.insert(field.clone(), json!({ "from": old, "to": new }));
+        }
+    }
+    for (field, new) in &after {
+        if !before.contains_key(field) {
+            changes.insert(field.clone(), json!({ "from": Value::Null, "to": new }));
+        }
+    }
+    Value::Object(changes)
+}
@@ -0,0 +1,127 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Text};
+use diesel::PgConnection;
+use crate::models::backup::{Backup, RestoreMode, BACKUP_VERSION};
+use crate::models::battle::Battle;
+use crate::models::monster::Monster;
+use crate::repository::schema::{battles, monsters};
+use crate::repository::database::Database;
+use crate::repository::audit_repository;
+use crate::repository::error::RepositoryError;
+
+pub trait BackupRepository: Send + Sync {
+    // Every monster and battle in id order, read from a single snapshot.
+    fn get_backup(&self) -> Result<Backup, RepositoryError>;
+    /*
+    Loads a backup checked with `Backup::check` in one transaction, the monsters keeping their level, XP and owner.
+    Nothing is restored when a row breaks a constraint, like an owner or season missing from the database.
+    */
+    fn restore_backup(&self, backup: &Backup, mode: RestoreMode) -> Result<(), RepositoryError>;
+    // Deletes every monster and battle, along with everything belonging to them, in one transaction. Returns how many of each there were.
+    fn reset(&self) -> Result<(usize, usize), RepositoryError>;
+}
+
+impl BackupRepository for Database {
+    fn get_backup(&self) -> Result<Backup, RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.build_transaction().repeatable_read().read_only().run(|connection| {
+            Ok::<_, diesel::result::Error>(Backup {
+                version: BACKUP_VERSION,
+                created_at: Utc::now().naive_utc(),
+                monsters: monsters::table.order(monsters::id).load::<Monster>(connection)?,
+                battles: battles::table.order(battles::id).load::<Battle>(connection)?,
+            })
+        })?)
+    }
+
+    fn restore_backup(&self, backup: &Backup, mode: RestoreMode) -> Result<(), RepositoryError> {
+        let mut connection = self.get_connection();
+        let replaced_ids = connection.transaction(|connection| {
+            if mode == RestoreMode::Replace {
+                let dependents = dependent_tables(connection)?;
+                if !dependents.is_empty() {
+                    return Err(RepositoryError::HasDependents(dependents));
+                }
+            }
+            let mut replaced_ids = Vec::new();
+            if mode != RestoreMode::Merge {
+                replaced_ids = monsters::table.select(monsters::id).load::<String>(connection)?;
+                diesel::delete(battles::table).execute(connection)?;
+                diesel::delete(monsters::table).execute(connection)?;
+            }
+            for monster in &backup.monsters {
+                diesel::insert_into(monsters::table)
+                    .values(monster)
+                    .on_conflict(monsters::id)
+                    .do_update()
+                    .set((monster, monsters::level.eq(monster.level), monsters::xp.eq(monster.xp), monsters::owner_id.eq(&monster.owner_id), monsters::version.eq(monster.version)))
+                    .execute(connection)?;
+            }
+            for battle in &backup.battles {
+                diesel::insert_into(battles::table)
+                    .values(battle)
+                    .on_conflict(battles::id)
+                    .do_update()
+                    .set(battle)
+                    .execute(connection)?;
+            }
+            let summary = serde_json::json!({ "mode": mode, "monsters": backup.monsters.len(), "battles": backup.battles.len() });
+            audit_repository::record(connection, "backup", &backup.created_at.to_string(), "restore", None, Some(&summary))?;
+            Ok::<_, RepositoryError>(replaced_ids)
+        })?;
+        // Like `reset`, the monsters deleted by the restore are dropped from the cache along with the restored ones.
+        self.invalidate_monsters(replaced_ids.iter().chain(backup.monsters.iter().map(|monster| &monster.id)).map(String::as_str));
+        self.invalidate_battles(&backup.battles);
+        Ok(())
+    }
+
+    fn reset(&self) -> Result<(usize, usize), RepositoryError> {
+        let mut connection = self.get_connection();
+        let (monster_ids, battles) = connection.transaction(|connection| {
+            diesel::sql_query("LOCK TABLE monsters, battles IN ACCESS EXCLUSIVE MODE").execute(connection)?;
+            let monster_ids: Vec<String> = monsters::table.select(monsters::id).load(connection)?;
+            let battles: i64 = battles::table.count().get_result(connection)?;
+            diesel::sql_query("TRUNCATE battles, monsters CASCADE").execute(connection)?;
+            let summary = serde_json::json!({ "monsters": monster_ids.len(), "battles": battles });
+            audit_repository::record(connection, "backup", &Utc::now().naive_utc().to_string(), "reset", Some(&summary), None)?;
+            Ok::<_, diesel::result::Error>((monster_ids, battles as usize))
+        })?;
+        self.invalidate_monsters(monster_ids.iter().map(String::as_str));
+        self.invalidate_battles(std::iter::empty());
+        Ok((monster_ids.len(), battles))
+    }
+}
+
+#[derive(QueryableByName)]
+struct DependentTable {
+    #[diesel(sql_type = Text)]
+    table_name: String,
+}
+
+#[derive(QueryableByName)]
+struct HasRows {
+    #[diesel(sql_type = Bool)]
+    has_rows: bool,
+}
+
+// The tables with rows that deleting the monsters and battles would delete too, through their cascading foreign keys.
+fn dependent_tables(connection: &mut PgConnection) -> QueryResult<Vec<String>> {
+    let tables = diesel::sql_query(
+        "SELECT DISTINCT conrelid::regclass::text AS table_name FROM pg_constraint \
+         WHERE contype = 'f' AND confdeltype = 'c' \
+         AND confrelid IN ('monsters'::regclass, 'battles'::regclass) \
+         AND conrelid NOT IN ('monsters'::regclass, 'battles'::regclass) \
+         ORDER BY table_name",
+    )
+    .load::<DependentTable>(connection)?;
+    let mut dependents = Vec::new();
+    for table in tables {
+        // The names come from the catalog, quoted by regclass when they need to be.
+        let query = format!("SELECT EXISTS (SELECT 1 FROM {}) AS has_rows", table.table_name);
+        if diesel::sql_query(query).get_result::<HasRows>(connection)?.has_rows {
+            dependents.push(table.table_name);
+        }
+    }
+    Ok(dependents)
+}
@@ -1,46 +1,424 @@
+use std::time::Duration;
+use chrono::prelude::*;
 use diesel::prelude::*;
-use crate::models::battle::Battle;
-use crate::repository::schema::battles::dsl::*;
+use crate::balance;
+use crate::cache;
+use crate::events::BATTLE_COMPLETED;
+use crate::models::battle::{Battle, ExpandedBattle};
+use crate::models::growth;
+use crate::models::interactive_battle::InteractiveBattle;
+use crate::models::leaderboard::{LeaderboardEntry, LeaderboardMode};
+use crate::models::monster::Monster;
+use crate::models::page::PageCursor;
+use crate::models::reward::Reward;
+use crate::models::series::{BattleSeries, BattleSeriesGame, ExpandedBattleSeries, SeriesGame};
+use crate::repository::schema::{self, battles::dsl::*};
 use crate::repository::database::Database;
+use crate::repository::audit_repository;
+use crate::repository::achievement_repository;
+use crate::repository::monster_repository;
+use crate::repository::training_repository;
+use crate::repository::error::RepositoryError;
 
-pub fn get_battles(db: &Database) -> Vec<Battle> {
-    let mut connection = db.get_connection();
-    battles
-        .load::<Battle>(&mut connection)
-        .expect("Error loading all battles")
-}
+// With a cache configured, battle writes drop the cached list, leaderboards are only left to expire.
+pub const BATTLES_CACHE_TTL: Duration = Duration::from_secs(30);
+pub const LEADERBOARD_CACHE_TTL: Duration = Duration::from_secs(10);
+const BATTLES_CACHE: &str = "battles";
+const LEADERBOARD_CACHE: &str = "leaderboard";
 
-pub fn get_battle_by_id(db: &Database, battle_id: &str) -> Option<Battle> {
-    let mut connection = db.get_connection();
-    match battles.find(battle_id).get_result::<Battle>(&mut connection) {
-        Ok(battle) => Some(battle),
-        Err(_) => None,
+impl Database {
+    // Drops the cached list of battles, and the cached fighters of `stored_battles`, whose XP and last battle changed.
+    pub(crate) fn invalidate_battles<'a>(&self, stored_battles: impl IntoIterator<Item = &'a Battle>) {
+        if self.cache().is_none() {
+            return;
+        }
+        cache::invalidate(self.cache(), &[self.cache_key(BATTLES_CACHE)]);
+        self.invalidate_monsters(stored_battles.into_iter().flat_map(|battle| [battle.monster_a.as_str(), battle.monster_b.as_str()]));
+    }
+
+    fn emit_completed_battles<'a>(&self, stored_battles: impl IntoIterator<Item = &'a Battle>) {
+        for battle in stored_battles {
+            self.emit(BATTLE_COMPLETED, &battle.id, battle);
+        }
     }
 }
 
+pub trait BattleRepository: Send + Sync {
+    fn get_battles(&self) -> Vec<Battle>;
+    // Up to `limit` battles past `after` in `PageCursor` order.
+    fn get_battles_page(&self, after: Option<&PageCursor>, limit: i64) -> Vec<Battle>;
+    fn get_battles_created_between(&self, from: NaiveDateTime, to: NaiveDateTime) -> Vec<Battle>;
+    fn get_battles_by_monster(&self, monster_id: &str) -> Vec<Battle>;
+    // The battles stored since then that scored a highlight, the most exciting first and the newest on ties.
+    fn get_highlight_battles(&self, since: NaiveDateTime, limit: i64) -> Vec<Battle>;
+    fn get_leaderboard(&self, mode: LeaderboardMode, limit: i64) -> Vec<LeaderboardEntry>;
+    fn get_expanded_battles(&self) -> Vec<ExpandedBattle>;
+    fn get_expanded_battles_page(&self, after: Option<&PageCursor>, limit: i64) -> Vec<ExpandedBattle>;
+    fn get_expanded_battle_by_id(&self, battle_id: &str) -> Option<ExpandedBattle>;
+    fn get_battle_by_id(&self, battle_id: &str) -> Option<Battle>;
+    fn delete_battle_by_id(&self, battle_id: &str) -> Option<usize>;
+    fn create_battle(&self, battle: Battle) -> Result<Battle, RepositoryError>;
+    // Stores the rewards granted for a ranked battle along with it, all or nothing.
+    fn create_rewarded_battle(&self, battle: Battle, rewards: Vec<Reward>) -> Result<(Battle, Vec<Reward>), RepositoryError>;
+    // Stores the battles of the games along with the series, all or nothing.
+    fn create_series(&self, series: BattleSeries, games: Vec<SeriesGame>) -> Result<ExpandedBattleSeries, RepositoryError>;
+    fn get_series_by_id(&self, series_id: &str) -> Option<ExpandedBattleSeries>;
+    fn create_interactive_battle(&self, battle: InteractiveBattle) -> Result<InteractiveBattle, RepositoryError>;
+    fn get_interactive_battle_by_id(&self, battle_id: &str) -> Option<InteractiveBattle>;
+    /*
+    Locks the interactive battle while `play` updates it, so the turns of both players are resolved one at a
+    time. The battle `play` returns, the record of an interactive battle that just finished, is stored with the
+    changes. Nothing is written when `play` leaves the interactive battle as it was.
+    */
+    fn play_interactive_battle(&self, battle_id: &str, play: &mut dyn FnMut(&mut InteractiveBattle) -> Option<Battle>) -> Result<Option<(InteractiveBattle, Option<Battle>)>, RepositoryError>;
+}
 
-pub fn delete_battle_by_id(db: &Database, battle_id: &str) -> Option<usize> {
-    let mut connection = db.get_connection();
-    match battles.find(battle_id).get_result::<Battle>(&mut connection) {
-        Ok(_) => {
-            let count = diesel::delete(battles.find(battle_id))
-                .execute(&mut connection)
+impl BattleRepository for Database {
+    fn get_battles(&self) -> Vec<Battle> {
+        cache::get_or_load(self.cache(), BATTLES_CACHE, &self.cache_key(BATTLES_CACHE), BATTLES_CACHE_TTL, || {
+            let mut connection = self.get_connection();
+            battles
+                .load::<Battle>(&mut connection)
+                .expect("Error loading all battles")
+        })
+    }
+
+    fn get_battles_page(&self, after: Option<&PageCursor>, limit: i64) -> Vec<Battle> {
+        let mut connection = self.get_connection();
+        let mut query = battles.order((monster_repository::page_order("battles"), id)).limit(limit).into_boxed();
+        if let Some(after) = after {
+            query = query.filter(monster_repository::past_cursor("battles", after));
+        }
+        query
+            .load::<Battle>(&mut connection)
+            .expect("Error loading a page of battles")
+    }
+
+    fn get_battles_created_between(&self, from: NaiveDateTime, to: NaiveDateTime) -> Vec<Battle> {
+        let mut connection = self.get_connection();
+        battles
+            .filter(created_at.ge(from))
+            .filter(created_at.lt(to))
+            .load::<Battle>(&mut connection)
+            .expect("Error loading battles by period")
+    }
+
+    // Newest first, covering battles on either side.
+    fn get_battles_by_monster(&self, monster_id: &str) -> Vec<Battle> {
+        let mut connection = self.get_connection();
+        battles
+            .filter(monster_a.eq(monster_id).or(monster_b.eq(monster_id)))
+            .order(created_at.desc())
+            .load::<Battle>(&mut connection)
+            .expect("Error loading battles by monster")
+    }
+
+    fn get_highlight_battles(&self, since: NaiveDateTime, limit: i64) -> Vec<Battle> {
+        let mut connection = self.get_connection();
+        battles
+            .filter(highlight_score.gt(0))
+            .filter(created_at.ge(since))
+            .order((highlight_score.desc(), created_at.desc()))
+            .limit(limit)
+            .load::<Battle>(&mut connection)
+            .expect("Error loading highlight battles")
+    }
+
+    /*
+    Aggregates every battle of each monster from both sides. A battle not won ends the streak, so the streak
+    counts the newest battles before the first one with a setback.
+    */
+    fn get_leaderboard(&self, mode: LeaderboardMode, limit: i64) -> Vec<LeaderboardEntry> {
+        let key = self.cache_key(&format!("{}:{:?}:{}", LEADERBOARD_CACHE, mode, limit));
+        cache::get_or_load(self.cache(), LEADERBOARD_CACHE, &key, LEADERBOARD_CACHE_TTL, || {
+            let mut connection = self.get_connection();
+            diesel::sql_query(format!(
+                "WITH participations AS ( \
+                    SELECT id, monster_a AS monster_id, winner, created_at FROM battles \
+                    UNION ALL \
+                    SELECT id, monster_b AS monster_id, winner, created_at FROM battles \
+                ), records AS ( \
+                    SELECT monster_id, coalesce(winner = monster_id, false) AS won, winner IS NULL AS drawn, \
+                        count(*) FILTER (WHERE winner IS DISTINCT FROM monster_id) \
+                            OVER (PARTITION BY monster_id ORDER BY created_at DESC NULLS LAST, id DESC) AS setbacks \
+                    FROM participations \
+                ), totals AS ( \
+                    SELECT monster_id, count(*) AS battles, count(*) FILTER (WHERE won) AS wins, \
+                        count(*) FILTER (WHERE drawn) AS draws, count(*) FILTER (WHERE setbacks = 0) AS streak \
+                    FROM records \
+                    GROUP BY monster_id \
+                    HAVING count(*) >= $1 \
+                ), scored AS ( \
+                    SELECT totals.*, monsters.name::text AS name, totals.battles - totals.wins - totals.draws AS losses, \
+                        totals.wins::float8 / totals.battles AS win_rate \
+                    FROM totals \
+                    JOIN monsters ON monsters.id = totals.monster_id \
+                ), rated AS ( \
+                    SELECT scored.*, \
+                        (win_rate + 1.9208 / battles - 1.96 * sqrt((win_rate * (1 - win_rate) + 0.9604 / battles) / battles)) \
+                            / (1 + 3.8416 / battles) AS rating \
+                    FROM scored \
+                ) \
+                SELECT row_number() OVER (ORDER BY {order}) AS rank, monster_id::text AS monster_id, name, battles, wins, losses, draws, win_rate, rating, streak \
+                FROM rated \
+                ORDER BY {order} \
+                LIMIT $2",
+                order = mode.order_by(),
+            ))
+                .bind::<diesel::sql_types::BigInt, _>(mode.min_battles())
+                .bind::<diesel::sql_types::BigInt, _>(limit)
+                .load::<LeaderboardEntry>(&mut connection)
+                .expect("Error loading the leaderboard")
+        })
+    }
+
+    fn get_expanded_battles(&self) -> Vec<ExpandedBattle> {
+        load_expanded_battles(self, None, None)
+    }
+
+    fn get_expanded_battles_page(&self, after: Option<&PageCursor>, limit: i64) -> Vec<ExpandedBattle> {
+        load_expanded_battles(self, None, Some((after, limit)))
+    }
+
+    fn get_expanded_battle_by_id(&self, battle_id: &str) -> Option<ExpandedBattle> {
+        load_expanded_battles(self, Some(battle_id), None).pop()
+    }
+
+    fn get_battle_by_id(&self, battle_id: &str) -> Option<Battle> {
+        let mut connection = self.get_connection();
+        battles.find(battle_id).get_result::<Battle>(&mut connection).ok()
+    }
+
+    fn delete_battle_by_id(&self, battle_id: &str) -> Option<usize> {
+        let mut connection = self.get_connection();
+        match battles.find(battle_id).get_result::<Battle>(&mut connection) {
+            Ok(existing_battle) => {
+                let count = connection.transaction(|connection| {
+                    let count = diesel::delete(battles.find(battle_id)).execute(connection)?;
+                    audit_repository::record(connection, "battle", battle_id, "delete", Some(&existing_battle), None)?;
+                    Ok::<_, diesel::result::Error>(count)
+                })
                 .expect("Error deleting battle by id");
-            Some(count)
+                self.invalidate_battles(std::iter::empty());
+                Some(count)
+            }
+            Err(_) => None,
         }
-        Err(_) => None,
+    }
+
+    fn create_battle(&self, battle: Battle) -> Result<Battle, RepositoryError> {
+        let mut connection = self.get_connection();
+        let battle = connection.transaction(|connection| insert_battle(connection, battle))?;
+        self.invalidate_battles([&battle]);
+        self.emit_completed_battles([&battle]);
+        Ok(battle)
+    }
+
+    fn create_rewarded_battle(&self, battle: Battle, rewards: Vec<Reward>) -> Result<(Battle, Vec<Reward>), RepositoryError> {
+        let mut connection = self.get_connection();
+        let (battle, rewards) = connection.transaction(|connection| {
+            let battle = insert_battle(connection, battle)?;
+            diesel::insert_into(schema::battle_rewards::table)
+                .values(&rewards)
+                .execute(connection)?;
+            Ok::<_, diesel::result::Error>((battle, rewards))
+        })?;
+        self.invalidate_battles([&battle]);
+        self.emit_completed_battles([&battle]);
+        Ok((battle, rewards))
+    }
+
+    fn create_series(&self, series: BattleSeries, games: Vec<SeriesGame>) -> Result<ExpandedBattleSeries, RepositoryError> {
+        let mut connection = self.get_connection();
+        let series = BattleSeries { created_at: Utc::now().naive_utc(), ..series };
+        let series = connection.transaction(|connection| {
+            diesel::insert_into(schema::battle_series::table)
+                .values(&series)
+                .execute(connection)?;
+            let mut stored_games = Vec::new();
+            for SeriesGame { game, seed, battle } in games {
+                let battle = insert_battle(connection, battle)?;
+                diesel::insert_into(schema::battle_series_games::table)
+                    .values(&BattleSeriesGame { series_id: series.id.clone(), game, battle_id: battle.id.clone(), seed })
+                    .execute(connection)?;
+                stored_games.push(SeriesGame { game, seed, battle });
+            }
+            audit_repository::record(connection, "battle_series", &series.id, "create", None, Some(&series))?;
+            Ok::<_, diesel::result::Error>(ExpandedBattleSeries { series, games: stored_games })
+        })?;
+        self.invalidate_battles(series.games.iter().map(|game| &game.battle));
+        self.emit_completed_battles(series.games.iter().map(|game| &game.battle));
+        Ok(series)
+    }
+
+    fn get_series_by_id(&self, series_id: &str) -> Option<ExpandedBattleSeries> {
+        let mut connection = self.get_connection();
+        let series = schema::battle_series::table.find(series_id).get_result::<BattleSeries>(&mut connection).ok()?;
+        let games = schema::battle_series_games::table
+            .inner_join(battles)
+            .filter(schema::battle_series_games::series_id.eq(series_id))
+            .order(schema::battle_series_games::game)
+            .select((schema::battle_series_games::all_columns, schema::battles::all_columns))
+            .load::<(BattleSeriesGame, Battle)>(&mut connection)
+            .expect("Error loading series games")
+            .into_iter()
+            .map(|(game, battle)| SeriesGame { game: game.game, seed: game.seed, battle })
+            .collect();
+        Some(ExpandedBattleSeries { series, games })
+    }
+
+    fn create_interactive_battle(&self, battle: InteractiveBattle) -> Result<InteractiveBattle, RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.transaction(|connection| {
+            diesel::insert_into(schema::interactive_battles::table)
+                .values(&battle)
+                .execute(connection)?;
+            audit_repository::record(connection, "interactive_battle", &battle.id, "create", None, Some(&battle))?;
+            Ok::<_, diesel::result::Error>(battle)
+        })?)
+    }
+
+    fn get_interactive_battle_by_id(&self, battle_id: &str) -> Option<InteractiveBattle> {
+        let mut connection = self.get_connection();
+        schema::interactive_battles::table.find(battle_id).get_result::<InteractiveBattle>(&mut connection).ok()
+    }
+
+    fn play_interactive_battle(&self, battle_id: &str, play: &mut dyn FnMut(&mut InteractiveBattle) -> Option<Battle>) -> Result<Option<(InteractiveBattle, Option<Battle>)>, RepositoryError> {
+        let mut connection = self.get_connection();
+        let played = connection.transaction(|connection| {
+            let Some(existing_battle) = schema::interactive_battles::table
+                .find(battle_id)
+                .for_update()
+                .get_result::<InteractiveBattle>(connection)
+                .optional()? else {
+                return Ok(None);
+            };
+            let mut battle = existing_battle.clone();
+            let record = play(&mut battle);
+            if battle == existing_battle {
+                return Ok(Some((battle, None)));
+            }
+
+            let record = record.map(|record| insert_battle(connection, record)).transpose()?;
+            battle.battle_id = record.as_ref().map(|record| record.id.clone()).or(battle.battle_id);
+            battle.updated_at = Some(Utc::now().naive_utc());
+            diesel::update(schema::interactive_battles::table.find(battle_id))
+                .set(&battle)
+                .execute(connection)?;
+            audit_repository::record(connection, "interactive_battle", battle_id, "update", Some(&existing_battle), Some(&battle))?;
+            Ok::<_, diesel::result::Error>(Some((battle, record)))
+        })?;
+        if let Some((_, Some(record))) = &played {
+            self.invalidate_battles([record]);
+            self.emit_completed_battles([record]);
+        }
+        Ok(played)
     }
 }
 
-pub fn create_battle(db: &Database, battle: Battle) -> Result<Battle, diesel::result::Error> {
-    let mut connection = db.get_connection();
+fn insert_battle(connection: &mut PgConnection, battle: Battle) -> Result<Battle, diesel::result::Error> {
+    // Shares the lock of the open season, so it can't close before the battle counts towards its standings.
+    let open_season = schema::seasons::table
+        .filter(schema::seasons::ended_at.is_null())
+        .select(schema::seasons::id)
+        .for_share()
+        .get_result::<String>(connection)
+        .optional()?;
+    let owners = (monster_owner(connection, &battle.monster_a)?, monster_owner(connection, &battle.monster_b)?);
+    let battle_region = match battle.region {
+        Some(battle_region) => Some(battle_region),
+        None => match trainer_region(connection, &owners.0)? {
+            Some(owner_region) => Some(owner_region),
+            None => trainer_region(connection, &owners.1)?,
+        },
+    };
+    // Battles streamed live keep the id their spectators already know.
     let battle = Battle {
-        id: uuid::Uuid::new_v4().to_string(),
+        id: Some(battle.id).filter(|battle_id| !battle_id.is_empty()).unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        created_at: Some(Utc::now().naive_utc()),
+        season_id: open_season,
+        trainer_a: owners.0,
+        trainer_b: owners.1,
+        region: battle_region,
         ..battle
     };
     diesel::insert_into(battles)
         .values(&battle)
-        .execute(&mut connection)
-        .expect("Error creating a new battle");
+        .execute(connection)?;
+    diesel::update(schema::monsters::table.filter(schema::monsters::id.eq_any([&battle.monster_a, &battle.monster_b])))
+        .set(schema::monsters::last_battle_at.eq(battle.created_at))
+        .execute(connection)?;
+    if let Some(winner_id) = &battle.winner {
+        let loser_id = if winner_id == &battle.monster_a { &battle.monster_b } else { &battle.monster_a };
+        let winner_monster = schema::monsters::table.find(winner_id).for_update().get_result::<Monster>(connection)?;
+        let loser_monster = schema::monsters::table.find(loser_id).get_result::<Monster>(connection)?;
+        let balance = balance::current();
+        let mut progressed_monster = winner_monster.clone();
+        let levels = growth::gain_xp(&mut progressed_monster, growth::battle_xp(&winner_monster.stats, &loser_monster.stats, &balance), &balance);
+        let progressed_monster = monster_repository::save_progress(connection, progressed_monster)?;
+        training_repository::earn_training_points(connection, winner_id, &balance)?;
+        achievement_repository::earn_battle_achievements(connection, &battle)?;
+        // Only level ups are audited, they are the ones changing stats.
+        if levels > 0 {
+            audit_repository::record(connection, "monster", winner_id, "update", Some(&winner_monster), Some(&progressed_monster))?;
+        }
+    }
+    audit_repository::record(connection, "battle", &battle.id, "create", None, Some(&battle))?;
     Ok(battle)
 }
+
+fn trainer_region(connection: &mut PgConnection, trainer_id: &Option<String>) -> Result<Option<String>, diesel::result::Error> {
+    let Some(trainer_id) = trainer_id else { return Ok(None) };
+    Ok(schema::trainers::table
+        .find(trainer_id)
+        .select(schema::trainers::region)
+        .get_result::<Option<String>>(connection)
+        .optional()?
+        .flatten())
+}
+
+// None when the monster has no owner or doesn't exist, the insert then failing on its foreign key.
+fn monster_owner(connection: &mut PgConnection, monster_id: &str) -> Result<Option<String>, diesel::result::Error> {
+    Ok(schema::monsters::table
+        .find(monster_id)
+        .select(schema::monsters::owner_id)
+        .get_result::<Option<String>>(connection)
+        .optional()?
+        .flatten())
+}
+
+// The expanded battles, all of them or only `battle_id`, or else the page of up to a limit past a cursor.
+fn load_expanded_battles(db: &Database, battle_id: Option<&str>, page: Option<(Option<&PageCursor>, i64)>) -> Vec<ExpandedBattle> {
+    let mut connection = db.get_connection();
+    let (monsters_a, monsters_b, winners) = diesel::alias!(
+        schema::monsters as monsters_a,
+        schema::monsters as monsters_b,
+        schema::monsters as winners
+    );
+    let mut query = battles
+        .left_join(monsters_a.on(monster_a.eq(monsters_a.field(schema::monsters::id))))
+        .left_join(monsters_b.on(monster_b.eq(monsters_b.field(schema::monsters::id))))
+        .left_join(winners.on(winner.eq(winners.field(schema::monsters::id).nullable())))
+        .select((
+            schema::battles::all_columns,
+            monsters_a.fields(schema::monsters::all_columns).nullable(),
+            monsters_b.fields(schema::monsters::all_columns).nullable(),
+            winners.fields(schema::monsters::all_columns).nullable(),
+        ))
+        .into_boxed();
+    if let Some(battle_id) = battle_id {
+        query = query.filter(id.eq(battle_id));
+    }
+    if let Some((after, limit)) = page {
+        query = query.order((monster_repository::page_order("battles"), id)).limit(limit);
+        if let Some(after) = after {
+            query = query.filter(monster_repository::past_cursor("battles", after));
+        }
+    }
+    query
+        .load::<(Battle, Option<Monster>, Option<Monster>, Option<Monster>)>(&mut connection)
+        .expect("Error loading expanded battles")
+        .into_iter()
+        .map(ExpandedBattle::from)
+        .collect()
+}
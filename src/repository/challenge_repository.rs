@@ -0,0 +1,61 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use crate::models::challenge::{ChallengeAttempts, DailyChallenge};
+use crate::repository::schema::{challenge_attempts, daily_challenges};
+use crate::repository::database::Database;
+use crate::repository::error::RepositoryError;
+
+pub trait ChallengeRepository: Send + Sync {
+    fn get_daily_challenge(&self, date: NaiveDate) -> Option<DailyChallenge>;
+    // Saving the challenge of a day that already has one returns the stored one, so concurrent first requests agree.
+    fn save_daily_challenge(&self, challenge: DailyChallenge) -> Result<DailyChallenge, RepositoryError>;
+    // Counts an attempt of the monster at the challenge of the day, and a win when it won.
+    fn record_challenge_attempt(&self, date: NaiveDate, monster_id: &str, won: bool) -> Result<ChallengeAttempts, RepositoryError>;
+    fn get_challenge_attempts(&self, date: NaiveDate) -> Vec<ChallengeAttempts>;
+}
+
+impl ChallengeRepository for Database {
+    fn get_daily_challenge(&self, date: NaiveDate) -> Option<DailyChallenge> {
+        let mut connection = self.get_connection();
+        daily_challenges::table
+            .find(date)
+            .get_result::<DailyChallenge>(&mut connection)
+            .optional()
+            .expect("Error loading daily challenge")
+    }
+
+    fn save_daily_challenge(&self, challenge: DailyChallenge) -> Result<DailyChallenge, RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.transaction(|connection| {
+            diesel::insert_into(daily_challenges::table)
+                .values(&challenge)
+                .on_conflict_do_nothing()
+                .execute(connection)?;
+            daily_challenges::table.find(challenge.challenge_date).get_result::<DailyChallenge>(connection)
+        })?)
+    }
+
+    fn record_challenge_attempt(&self, date: NaiveDate, monster_id: &str, won: bool) -> Result<ChallengeAttempts, RepositoryError> {
+        let mut connection = self.get_connection();
+        let now = Utc::now().naive_utc();
+        let wins = won as i32;
+        Ok(diesel::insert_into(challenge_attempts::table)
+            .values(&ChallengeAttempts { challenge_date: date, monster_id: monster_id.to_string(), attempts: 1, wins, last_attempt_at: now })
+            .on_conflict((challenge_attempts::challenge_date, challenge_attempts::monster_id))
+            .do_update()
+            .set((
+                challenge_attempts::attempts.eq(challenge_attempts::attempts + 1),
+                challenge_attempts::wins.eq(challenge_attempts::wins + wins),
+                challenge_attempts::last_attempt_at.eq(now),
+            ))
+            .get_result::<ChallengeAttempts>(&mut connection)?)
+    }
+
+    fn get_challenge_attempts(&self, date: NaiveDate) -> Vec<ChallengeAttempts> {
+        let mut connection = self.get_connection();
+        challenge_attempts::table
+            .filter(challenge_attempts::challenge_date.eq(date))
+            .load::<ChallengeAttempts>(&mut connection)
+            .expect("Error loading challenge attempts")
+    }
+}
@@ -0,0 +1,54 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use crate::models::cors::CorsOrigin;
+use crate::repository::schema::cors_origins;
+use crate::repository::database::Database;
+use crate::repository::audit_repository;
+use crate::repository::error::RepositoryError;
+
+pub trait CorsRepository: Send + Sync {
+    fn get_cors_origins(&self) -> Vec<CorsOrigin>;
+    // Adding an origin already allowed returns it as it was.
+    fn add_cors_origin(&self, origin: &str) -> Result<CorsOrigin, RepositoryError>;
+    fn delete_cors_origin(&self, origin: &str) -> Result<Option<usize>, RepositoryError>;
+}
+
+impl CorsRepository for Database {
+    fn get_cors_origins(&self) -> Vec<CorsOrigin> {
+        let mut connection = self.get_connection();
+        cors_origins::table
+            .order(cors_origins::origin)
+            .load::<CorsOrigin>(&mut connection)
+            .expect("Error loading CORS origins")
+    }
+
+    fn add_cors_origin(&self, origin: &str) -> Result<CorsOrigin, RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.transaction(|connection| {
+            let added = diesel::insert_into(cors_origins::table)
+                .values(&CorsOrigin { origin: origin.to_string(), created_at: Utc::now().naive_utc() })
+                .on_conflict_do_nothing()
+                .get_result::<CorsOrigin>(connection)
+                .optional()?;
+            match added {
+                Some(added) => {
+                    audit_repository::record(connection, "cors_origin", origin, "create", None, Some(&added))?;
+                    Ok::<_, diesel::result::Error>(added)
+                }
+                None => cors_origins::table.find(origin).get_result::<CorsOrigin>(connection),
+            }
+        })?)
+    }
+
+    fn delete_cors_origin(&self, origin: &str) -> Result<Option<usize>, RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.transaction(|connection| {
+            let Some(existing_origin) = cors_origins::table.find(origin).get_result::<CorsOrigin>(connection).optional()? else {
+                return Ok(None);
+            };
+            let count = diesel::delete(cors_origins::table.find(origin)).execute(connection)?;
+            audit_repository::record(connection, "cors_origin", origin, "delete", Some(&existing_origin), None)?;
+            Ok::<_, diesel::result::Error>(Some(count))
+        })?)
+    }
+}
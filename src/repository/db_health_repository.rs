@@ -0,0 +1,47 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use crate::models::db_health::TableStats;
+use crate::repository::schema::db_table_stats;
+use crate::repository::database::Database;
+use crate::repository::error::RepositoryError;
+
+pub trait DbHealthRepository: Send + Sync {
+    // Stores the statistics of every table of the schema as they are now, dropping the snapshots taken before `keep_since`.
+    fn take_db_stats_snapshot(&self, keep_since: NaiveDateTime) -> Result<Vec<TableStats>, RepositoryError>;
+    fn get_db_stats_since(&self, since: NaiveDateTime) -> Vec<TableStats>;
+}
+
+impl DbHealthRepository for Database {
+    fn take_db_stats_snapshot(&self, keep_since: NaiveDateTime) -> Result<Vec<TableStats>, RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.transaction(|connection| {
+            let snapshot = diesel::sql_query(
+                "SELECT $1 AS taken_at, relname::varchar AS table_name, n_live_tup AS live_rows, n_dead_tup AS dead_rows, \
+                    seq_scan AS seq_scans, coalesce(idx_scan, 0) AS index_scans, pg_total_relation_size(relid) AS total_bytes, \
+                    (last_vacuum AT TIME ZONE 'UTC') AS last_vacuum, (last_autovacuum AT TIME ZONE 'UTC') AS last_autovacuum \
+                FROM pg_stat_user_tables \
+                WHERE schemaname = current_schema() AND relname NOT LIKE '\\_\\_%' \
+                ORDER BY relname"
+            )
+                .bind::<diesel::sql_types::Timestamp, _>(Utc::now().naive_utc())
+                .load::<TableStats>(connection)?;
+            if !snapshot.is_empty() {
+                diesel::insert_into(db_table_stats::table)
+                    .values(&snapshot)
+                    .execute(connection)?;
+            }
+            diesel::delete(db_table_stats::table.filter(db_table_stats::taken_at.lt(keep_since)))
+                .execute(connection)?;
+            Ok::<_, diesel::result::Error>(snapshot)
+        })?)
+    }
+
+    fn get_db_stats_since(&self, since: NaiveDateTime) -> Vec<TableStats> {
+        let mut connection = self.get_connection();
+        db_table_stats::table
+            .filter(db_table_stats::taken_at.ge(since))
+            .order((db_table_stats::taken_at, db_table_stats::table_name))
+            .load::<TableStats>(&mut connection)
+            .expect("Error loading database statistics")
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+
+// Database constraints guarding monsters and battles, see the `add_battle_integrity_constraints` migration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Constraint {
+    MonsterNameNotEmpty,
+    BattleMonsterExists,
+    BattleWinnerIsParticipant,
+    BattleDrawHasNoWinner,
+    BattleOutcomeKnown,
+    MonsterMoveExists,
+    MonsterItemExists,
+    ApiKeyTierExists,
+    TrainerNameUnique,
+    MonsterOwnerExists,
+    ChallengeMonsterExists,
+    MonsterExternalIdUnique,
+    MonsterStatsInRange,
+}
+
+impl Constraint {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "monsters_name_not_empty_check" => Some(Constraint::MonsterNameNotEmpty),
+            "battles_monster_a_fkey" | "battles_monster_b_fkey" | "battles_winner_fkey" => Some(Constraint::BattleMonsterExists),
+            "battles_winner_participant_check" => Some(Constraint::BattleWinnerIsParticipant),
+            "battles_draw_winner_check" => Some(Constraint::BattleDrawHasNoWinner),
+            "battles_outcome_check" => Some(Constraint::BattleOutcomeKnown),
+            "monster_moves_move_id_fkey" => Some(Constraint::MonsterMoveExists),
+            "monster_items_item_id_fkey" => Some(Constraint::MonsterItemExists),
+            "api_keys_tier_fkey" => Some(Constraint::ApiKeyTierExists),
+            "trainers_name_key" => Some(Constraint::TrainerNameUnique),
+            "monsters_owner_id_fkey" => Some(Constraint::MonsterOwnerExists),
+            "daily_challenges_opponent_fkey" | "challenge_attempts_monster_id_fkey" => Some(Constraint::ChallengeMonsterExists),
+            "monsters_external_id_key" => Some(Constraint::MonsterExternalIdUnique),
+            "monsters_stats_range_check" => Some(Constraint::MonsterStatsInRange),
+            _ => None,
+        }
+    }
+}
+
+/*
+Error returned by the repositories. Violations of the known constraints are told apart
+so the handlers can answer with a specific error instead of a 500.
+*/
+#[derive(Debug)]
+pub enum RepositoryError {
+    Constraint(Constraint),
+    // The row was updated since the version the write was based on.
+    StaleVersion,
+    // Rows of these tables belong to the rows the write would delete, see `RestoreMode::Replace`.
+    HasDependents(Vec<String>),
+    Database(DieselError),
+}
+
+impl From<DieselError> for RepositoryError {
+    fn from(err: DieselError) -> Self {
+        if let DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation | DatabaseErrorKind::CheckViolation | DatabaseErrorKind::UniqueViolation, info) = &err {
+            if let Some(constraint) = info.constraint_name().and_then(Constraint::from_name) {
+                return RepositoryError::Constraint(constraint);
+            }
+        }
+        RepositoryError::Database(err)
+    }
+}
+
+impl fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepositoryError::Constraint(constraint) => write!(f, "Constraint violated: {:?}", constraint),
+            RepositoryError::StaleVersion => write!(f, "Stale version"),
+            RepositoryError::HasDependents(tables) => write!(f, "Rows of {} would be deleted", tables.join(", ")),
+            RepositoryError::Database(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for RepositoryError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::battle::{Battle, BattleOutcome};
+    use crate::repository::battle_repository::BattleRepository;
+    use crate::repository::database::Database;
+    use crate::utils::test_utils::init_test_monsters;
+
+    use super::*;
+
+    fn constraint(result: Result<Battle, RepositoryError>) -> Option<Constraint> {
+        match result {
+            Err(RepositoryError::Constraint(constraint)) => Some(constraint),
+            _ => None,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_map_battle_constraint_violations() {
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
+        let battle = Battle {
+            id: String::new(),
+            monster_a: test_monsters[0].id.clone(),
+            monster_b: test_monsters[1].id.clone(),
+            winner: Some(test_monsters[0].id.clone()),
+            created_at: None,
+            updated_at: None,
+            outcome: BattleOutcome::Win,
+            manual: true,
+            season_id: None,
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        };
+
+        let unknown_monster = Battle { monster_b: "99999".to_string(), ..battle.clone() };
+        assert_eq!(constraint(db.create_battle(unknown_monster)), Some(Constraint::BattleMonsterExists));
+        let outsider_winner = Battle { winner: Some(test_monsters[2].id.clone()), ..battle.clone() };
+        assert_eq!(constraint(db.create_battle(outsider_winner)), Some(Constraint::BattleWinnerIsParticipant));
+        let draw_with_winner = Battle { outcome: BattleOutcome::Draw, ..battle.clone() };
+        assert_eq!(constraint(db.create_battle(draw_with_winner)), Some(Constraint::BattleDrawHasNoWinner));
+        assert!(db.create_battle(battle).is_ok());
+    }
+}
@@ -0,0 +1,118 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use crate::models::evolution::{Evolution, EvolutionRequirement};
+use crate::models::monster::Monster;
+use crate::repository::schema::{battles, evolutions, monsters};
+use crate::repository::database::Database;
+use crate::repository::audit_repository;
+use crate::repository::error::RepositoryError;
+
+#[derive(Debug)]
+pub enum EvolveMonsterError {
+    NotFound,
+    // The species of the monster has no next form.
+    NoEvolution,
+    Unmet(EvolutionRequirement),
+    Database(RepositoryError),
+}
+
+impl From<diesel::result::Error> for EvolveMonsterError {
+    fn from(err: diesel::result::Error) -> Self {
+        EvolveMonsterError::Database(RepositoryError::from(err))
+    }
+}
+
+pub trait EvolutionRepository: Send + Sync {
+    fn get_evolutions(&self) -> Vec<Evolution>;
+    // Creates the evolution of the species, or replaces it.
+    fn save_evolution(&self, evolution: Evolution) -> Result<Evolution, RepositoryError>;
+    fn delete_evolution(&self, species: &str) -> Result<Option<usize>, RepositoryError>;
+    // Checks the requirements and evolves the monster, recording the evolution in its audit history.
+    fn evolve_monster(&self, monster_id: &str) -> Result<Monster, EvolveMonsterError>;
+}
+
+impl EvolutionRepository for Database {
+    fn get_evolutions(&self) -> Vec<Evolution> {
+        let mut connection = self.get_connection();
+        evolutions::table
+            .order(evolutions::species)
+            .load::<Evolution>(&mut connection)
+            .expect("Error loading evolutions")
+    }
+
+    fn save_evolution(&self, evolution: Evolution) -> Result<Evolution, RepositoryError> {
+        let mut connection = self.get_connection();
+        let evolution = Evolution { updated_at: Some(Utc::now().naive_utc()), ..evolution };
+        Ok(connection.transaction(|connection| {
+            let existing_evolution = evolutions::table.find(&evolution.species).get_result::<Evolution>(connection).optional()?;
+            let saved_evolution = diesel::insert_into(evolutions::table)
+                .values(&evolution)
+                .on_conflict(evolutions::species)
+                .do_update()
+                .set((
+                    evolutions::evolves_to.eq(excluded(evolutions::evolves_to)),
+                    evolutions::min_level.eq(excluded(evolutions::min_level)),
+                    evolutions::min_wins.eq(excluded(evolutions::min_wins)),
+                    evolutions::image_url.eq(excluded(evolutions::image_url)),
+                    evolutions::attack.eq(excluded(evolutions::attack)),
+                    evolutions::defense.eq(excluded(evolutions::defense)),
+                    evolutions::hp.eq(excluded(evolutions::hp)),
+                    evolutions::speed.eq(excluded(evolutions::speed)),
+                    evolutions::updated_at.eq(excluded(evolutions::updated_at)),
+                ))
+                .get_result::<Evolution>(connection)?;
+            let action = if existing_evolution.is_some() { "update" } else { "create" };
+            audit_repository::record(connection, "evolution", &evolution.species, action, existing_evolution.as_ref(), Some(&saved_evolution))?;
+            Ok::<_, diesel::result::Error>(saved_evolution)
+        })?)
+    }
+
+    fn delete_evolution(&self, species: &str) -> Result<Option<usize>, RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.transaction(|connection| {
+            let Some(existing_evolution) = evolutions::table.find(species).get_result::<Evolution>(connection).optional()? else {
+                return Ok(None);
+            };
+            let count = diesel::delete(evolutions::table.find(species)).execute(connection)?;
+            audit_repository::record(connection, "evolution", species, "delete", Some(&existing_evolution), None)?;
+            Ok::<_, diesel::result::Error>(Some(count))
+        })?)
+    }
+
+    // The monster row is locked so a concurrent evolution or level up can't be overwritten.
+    fn evolve_monster(&self, monster_id: &str) -> Result<Monster, EvolveMonsterError> {
+        let mut connection = self.get_connection();
+        connection.transaction(|connection| {
+            let Some(existing_monster) = monsters::table.find(monster_id).for_update().get_result::<Monster>(connection).optional()? else {
+                return Err(EvolveMonsterError::NotFound);
+            };
+            let Some(evolution) = evolutions::table.find(&existing_monster.name).get_result::<Evolution>(connection).optional()? else {
+                return Err(EvolveMonsterError::NoEvolution);
+            };
+            let wins = battles::table
+                .filter(battles::winner.eq(monster_id))
+                .count()
+                .get_result::<i64>(connection)?;
+            if let Some(requirement) = evolution.unmet_requirement(&existing_monster, wins) {
+                return Err(EvolveMonsterError::Unmet(requirement));
+            }
+
+            let mut monster = existing_monster.clone();
+            evolution.evolve(&mut monster);
+            let evolved_monster = diesel::update(monsters::table.find(monster_id))
+                .set((
+                    monsters::name.eq(&monster.name),
+                    monsters::image_url.eq(&monster.image_url),
+                    monsters::attack.eq(monster.stats.attack),
+                    monsters::defense.eq(monster.stats.defense),
+                    monsters::hp.eq(monster.stats.hp),
+                    monsters::speed.eq(monster.stats.speed),
+                    monsters::updated_at.eq(monster.updated_at),
+                ))
+                .get_result::<Monster>(connection)?;
+            audit_repository::record(connection, "monster", monster_id, "evolve", Some(&existing_monster), Some(&evolved_monster))?;
+            Ok(evolved_monster)
+        })
+    }
+}
@@ -0,0 +1,73 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use serde_json::Value;
+use crate::models::battle::Battle;
+use crate::models::export::{Export, ExportKind, ExportRow};
+use crate::models::monster::Monster;
+use crate::repository::schema::{battles, export_rows, exports, monsters};
+use crate::repository::database::Database;
+use crate::repository::error::RepositoryError;
+
+// Rows inserted per statement, Postgres taking at most 65535 bind parameters in one.
+const EXPORT_ROWS_PER_INSERT: usize = 10_000;
+
+pub trait ExportRepository: Send + Sync {
+    // Copies the monsters or battles in id order as of one instant, dropping the exports that expired on the way.
+    fn create_export(&self, kind: ExportKind, expires_at: NaiveDateTime) -> Result<Export, RepositoryError>;
+    // The export with up to `limit` of its rows past the `after` position, none once it expired.
+    fn get_export_page(&self, export_id: &str, after: i32, limit: i64) -> Option<(Export, Vec<Value>)>;
+}
+
+impl ExportRepository for Database {
+    fn create_export(&self, kind: ExportKind, expires_at: NaiveDateTime) -> Result<Export, RepositoryError> {
+        let mut connection = self.get_connection();
+        let now = Utc::now().naive_utc();
+        // Repeatable read so the rows are read from a single snapshot, however long copying them takes.
+        Ok(connection.build_transaction().repeatable_read().run(|connection| {
+            diesel::delete(exports::table.filter(exports::expires_at.le(now))).execute(connection)?;
+            let data: Vec<Value> = match kind {
+                ExportKind::Monsters => monsters::table
+                    .order(monsters::id)
+                    .load::<Monster>(connection)?
+                    .iter()
+                    .map(|monster| serde_json::to_value(monster).expect("Monsters serialize to JSON"))
+                    .collect(),
+                ExportKind::Battles => battles::table
+                    .order(battles::id)
+                    .load::<Battle>(connection)?
+                    .iter()
+                    .map(|battle| serde_json::to_value(battle).expect("Battles serialize to JSON"))
+                    .collect(),
+            };
+            let export = Export { id: uuid::Uuid::new_v4().to_string(), kind, total: data.len() as i32, created_at: now, expires_at };
+            diesel::insert_into(exports::table).values(&export).execute(connection)?;
+            let rows: Vec<ExportRow> = data
+                .into_iter()
+                .enumerate()
+                .map(|(index, data)| ExportRow { export_id: export.id.clone(), position: index as i32 + 1, data })
+                .collect();
+            for chunk in rows.chunks(EXPORT_ROWS_PER_INSERT) {
+                diesel::insert_into(export_rows::table).values(chunk).execute(connection)?;
+            }
+            Ok::<_, diesel::result::Error>(export)
+        })?)
+    }
+
+    fn get_export_page(&self, export_id: &str, after: i32, limit: i64) -> Option<(Export, Vec<Value>)> {
+        let mut connection = self.get_connection();
+        let export = exports::table
+            .find(export_id)
+            .filter(exports::expires_at.gt(Utc::now().naive_utc()))
+            .get_result::<Export>(&mut connection)
+            .ok()?;
+        let rows = export_rows::table
+            .filter(export_rows::export_id.eq(export_id))
+            .filter(export_rows::position.gt(after))
+            .order(export_rows::position)
+            .limit(limit)
+            .select(export_rows::data)
+            .load::<Value>(&mut connection)
+            .expect("Error loading export rows");
+        Some((export, rows))
+    }
+}
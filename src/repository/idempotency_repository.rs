@@ -0,0 +1,60 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use crate::models::idempotency::IdempotencyKey;
+use crate::repository::schema::idempotency_keys;
+use crate::repository::database::Database;
+use crate::repository::error::RepositoryError;
+
+pub trait IdempotencyRepository: Send + Sync {
+    /*
+    Claims the key of `claim` for its request, None when it was free, a key created before `expired_before`
+    counting as free. Otherwise returns the key as it was claimed, answered or not.
+    */
+    fn claim_idempotency_key(&self, claim: &IdempotencyKey, expired_before: NaiveDateTime) -> Result<Option<IdempotencyKey>, RepositoryError>;
+    // Stores the response to replay for a claimed key, without a body for the streamed ones that can't be replayed.
+    fn complete_idempotency_key(&self, client: &str, key: &str, status: i32, content_type: Option<&str>, body: Option<&[u8]>) -> Result<(), RepositoryError>;
+    // Frees a claimed key whose request failed, so it can be retried.
+    fn release_idempotency_key(&self, client: &str, key: &str) -> Result<(), RepositoryError>;
+    fn delete_expired_idempotency_keys(&self, expired_before: NaiveDateTime) -> Result<usize, RepositoryError>;
+}
+
+impl IdempotencyRepository for Database {
+    fn claim_idempotency_key(&self, claim: &IdempotencyKey, expired_before: NaiveDateTime) -> Result<Option<IdempotencyKey>, RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.transaction(|connection| {
+            let claimed_key = idempotency_keys::table.find((&claim.client, &claim.key));
+            diesel::delete(claimed_key.filter(idempotency_keys::created_at.lt(expired_before))).execute(connection)?;
+            let inserted = diesel::insert_into(idempotency_keys::table)
+                .values(claim)
+                .on_conflict_do_nothing()
+                .execute(connection)?;
+            if inserted > 0 {
+                return Ok(None);
+            }
+            claimed_key.get_result::<IdempotencyKey>(connection).map(Some)
+        })?)
+    }
+
+    fn complete_idempotency_key(&self, client: &str, key: &str, status: i32, content_type: Option<&str>, body: Option<&[u8]>) -> Result<(), RepositoryError> {
+        let mut connection = self.get_connection();
+        diesel::update(idempotency_keys::table.find((client, key)))
+            .set((
+                idempotency_keys::status.eq(status),
+                idempotency_keys::content_type.eq(content_type),
+                idempotency_keys::body.eq(body),
+            ))
+            .execute(&mut connection)?;
+        Ok(())
+    }
+
+    fn release_idempotency_key(&self, client: &str, key: &str) -> Result<(), RepositoryError> {
+        let mut connection = self.get_connection();
+        diesel::delete(idempotency_keys::table.find((client, key))).execute(&mut connection)?;
+        Ok(())
+    }
+
+    fn delete_expired_idempotency_keys(&self, expired_before: NaiveDateTime) -> Result<usize, RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(diesel::delete(idempotency_keys::table.filter(idempotency_keys::created_at.lt(expired_before))).execute(&mut connection)?)
+    }
+}
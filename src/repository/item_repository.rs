@@ -0,0 +1,113 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use crate::models::item::{Item, MonsterItem};
+use crate::repository::schema::{items, monster_items};
+use crate::repository::database::Database;
+use crate::repository::audit_repository;
+use crate::repository::error::RepositoryError;
+
+pub trait ItemRepository: Send + Sync {
+    fn get_items(&self) -> Vec<Item>;
+    fn get_item_by_id(&self, item_id: &str) -> Option<Item>;
+    fn create_item(&self, item: Item) -> Result<Item, RepositoryError>;
+    fn update_item_by_id(&self, item_id: &str, item: Item) -> Result<Option<Item>, RepositoryError>;
+    // Deleting an item also takes it away from the monsters that carried it.
+    fn delete_item_by_id(&self, item_id: &str) -> Option<usize>;
+    // The items of a monster in slot order.
+    fn get_monster_items(&self, monster_id: &str) -> Vec<Item>;
+    // Replaces the items of a monster, the first id taking the first slot.
+    fn set_monster_items(&self, monster_id: &str, item_ids: &[String]) -> Result<Vec<Item>, RepositoryError>;
+}
+
+impl ItemRepository for Database {
+    fn get_items(&self) -> Vec<Item> {
+        let mut connection = self.get_connection();
+        items::table
+            .order(items::created_at)
+            .load::<Item>(&mut connection)
+            .expect("Error loading all items")
+    }
+
+    fn get_item_by_id(&self, item_id: &str) -> Option<Item> {
+        let mut connection = self.get_connection();
+        items::table.find(item_id).get_result::<Item>(&mut connection).ok()
+    }
+
+    fn create_item(&self, item: Item) -> Result<Item, RepositoryError> {
+        let mut connection = self.get_connection();
+        let item = Item {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            ..item
+        };
+        Ok(connection.transaction(|connection| {
+            diesel::insert_into(items::table)
+                .values(&item)
+                .execute(connection)?;
+            audit_repository::record(connection, "item", &item.id, "create", None, Some(&item))?;
+            Ok::<_, diesel::result::Error>(item)
+        })?)
+    }
+
+    fn update_item_by_id(&self, item_id: &str, item: Item) -> Result<Option<Item>, RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.transaction(|connection| {
+            let existing_item = match items::table.find(item_id).get_result::<Item>(connection).optional()? {
+                Some(existing_item) => existing_item,
+                None => return Ok(None),
+            };
+            let updated_item = diesel::update(items::table.find(item_id))
+                .set((
+                    items::name.eq(&item.name),
+                    items::attack_bonus.eq(item.attack_bonus),
+                    items::defense_bonus.eq(item.defense_bonus),
+                    items::speed_bonus.eq(item.speed_bonus),
+                    items::consumable.eq(item.consumable),
+                    items::revive_hp.eq(item.revive_hp),
+                    items::updated_at.eq(Some(Utc::now().naive_utc())),
+                ))
+                .get_result::<Item>(connection)?;
+            audit_repository::record(connection, "item", item_id, "update", Some(&existing_item), Some(&updated_item))?;
+            Ok::<_, diesel::result::Error>(Some(updated_item))
+        })?)
+    }
+
+    fn delete_item_by_id(&self, item_id: &str) -> Option<usize> {
+        let mut connection = self.get_connection();
+        let existing_item = items::table.find(item_id).get_result::<Item>(&mut connection).ok()?;
+        let count = connection.transaction(|connection| {
+            let count = diesel::delete(items::table.find(item_id)).execute(connection)?;
+            audit_repository::record(connection, "item", item_id, "delete", Some(&existing_item), None)?;
+            Ok::<_, diesel::result::Error>(count)
+        })
+        .expect("Error deleting item by id");
+        Some(count)
+    }
+
+    fn get_monster_items(&self, monster_id: &str) -> Vec<Item> {
+        let mut connection = self.get_connection();
+        monster_items::table
+            .inner_join(items::table)
+            .filter(monster_items::monster_id.eq(monster_id))
+            .order(monster_items::slot)
+            .select(items::all_columns)
+            .load::<Item>(&mut connection)
+            .expect("Error loading monster items")
+    }
+
+    fn set_monster_items(&self, monster_id: &str, item_ids: &[String]) -> Result<Vec<Item>, RepositoryError> {
+        let mut connection = self.get_connection();
+        let slots: Vec<MonsterItem> = item_ids
+            .iter()
+            .enumerate()
+            .map(|(slot, item_id)| MonsterItem { monster_id: monster_id.to_string(), slot: slot as i32 + 1, item_id: item_id.clone() })
+            .collect();
+        connection.transaction(|connection| {
+            diesel::delete(monster_items::table.filter(monster_items::monster_id.eq(monster_id))).execute(connection)?;
+            diesel::insert_into(monster_items::table).values(&slots).execute(connection)?;
+            Ok::<_, diesel::result::Error>(())
+        })?;
+        Ok(self.get_monster_items(monster_id))
+    }
+}
@@ -0,0 +1,73 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use serde_json::Value;
+use crate::models::job::{Job, JobStatus};
+use crate::repository::schema::jobs::dsl::*;
+use crate::repository::database::Database;
+use crate::repository::error::RepositoryError;
+
+pub trait JobRepository: Send + Sync {
+    fn create_job(&self, job_kind: &str, job_total: i32) -> Result<Job, RepositoryError>;
+    fn get_job_by_id(&self, job_id: &str) -> Option<Job>;
+    fn start_job(&self, job_id: &str) -> Result<(), RepositoryError>;
+    fn update_job_progress(&self, job_id: &str, job_processed: i32, job_failed: i32) -> Result<(), RepositoryError>;
+    // Succeeds with the job result or fails with an error message.
+    fn finish_job(&self, job_id: &str, outcome: Result<Value, String>) -> Result<(), RepositoryError>;
+}
+
+impl JobRepository for Database {
+    fn create_job(&self, job_kind: &str, job_total: i32) -> Result<Job, RepositoryError> {
+        let mut connection = self.get_connection();
+        let job = Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind: job_kind.to_string(),
+            status: JobStatus::Queued,
+            total: job_total,
+            processed: 0,
+            failed: 0,
+            result: None,
+            error: None,
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            finished_at: None,
+        };
+        diesel::insert_into(jobs)
+            .values(&job)
+            .execute(&mut connection)?;
+        Ok(job)
+    }
+
+    fn get_job_by_id(&self, job_id: &str) -> Option<Job> {
+        let mut connection = self.get_connection();
+        jobs.find(job_id).get_result::<Job>(&mut connection).ok()
+    }
+
+    fn start_job(&self, job_id: &str) -> Result<(), RepositoryError> {
+        let mut connection = self.get_connection();
+        diesel::update(jobs.find(job_id))
+            .set((status.eq(JobStatus::Running), updated_at.eq(Some(Utc::now().naive_utc()))))
+            .execute(&mut connection)?;
+        Ok(())
+    }
+
+    fn update_job_progress(&self, job_id: &str, job_processed: i32, job_failed: i32) -> Result<(), RepositoryError> {
+        let mut connection = self.get_connection();
+        diesel::update(jobs.find(job_id))
+            .set((processed.eq(job_processed), failed.eq(job_failed), updated_at.eq(Some(Utc::now().naive_utc()))))
+            .execute(&mut connection)?;
+        Ok(())
+    }
+
+    fn finish_job(&self, job_id: &str, outcome: Result<Value, String>) -> Result<(), RepositoryError> {
+        let mut connection = self.get_connection();
+        let now = Some(Utc::now().naive_utc());
+        let (job_status, job_result, job_error) = match outcome {
+            Ok(job_result) => (JobStatus::Succeeded, Some(job_result), None),
+            Err(job_error) => (JobStatus::Failed, None, Some(job_error)),
+        };
+        diesel::update(jobs.find(job_id))
+            .set((status.eq(job_status), result.eq(job_result), error.eq(job_error), updated_at.eq(now), finished_at.eq(now)))
+            .execute(&mut connection)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,1532 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+use chrono::prelude::*;
+use crate::balance;
+use crate::models::achievement::{battle_achievements, Achievement, AchievementKind, COLLECTOR_MONSTERS, WIN_STREAK_LENGTH};
+use crate::models::backup::{Backup, RestoreMode, BACKUP_VERSION};
+use crate::models::battle::{Battle, BattleOutcome, ExpandedBattle};
+use crate::models::interactive_battle::InteractiveBattle;
+use crate::models::item::Item;
+use crate::models::job::{Job, JobStatus};
+use crate::models::leaderboard::{leaderboard, LeaderboardEntry, LeaderboardMode};
+use crate::models::challenge::{ChallengeAttempts, DailyChallenge};
+use crate::models::cors::CorsOrigin;
+use crate::models::db_health::TableStats;
+use crate::models::duplicates::{trigram_similarity, DuplicatePair};
+use crate::models::evolution::Evolution;
+use crate::models::export::{Export, ExportKind};
+use crate::models::growth;
+use crate::models::idempotency::IdempotencyKey;
+use crate::models::monster::{ImportMode, ImportOutcome, MatchmakingCandidate, Monster, MonsterSearchResult, Stats};
+use crate::models::moves::Move;
+use crate::models::note::MonsterNote;
+use crate::models::page::PageCursor;
+use crate::models::rate_limit::{ApiKey, RateLimitTier};
+use crate::models::region::RegionStats;
+use crate::models::reward::Reward;
+use crate::models::season::{standings, Season, SeasonStanding};
+use crate::models::series::{BattleSeries, BattleSeriesGame, ExpandedBattleSeries, SeriesGame};
+use crate::models::trainer::{Trade, TradeStatus, Trainer};
+use crate::models::training::MonsterTraining;
+use crate::models::webhook::Webhook;
+use crate::repository::achievement_repository::AchievementRepository;
+use crate::repository::backup_repository::BackupRepository;
+use crate::repository::battle_repository::BattleRepository;
+use crate::repository::challenge_repository::ChallengeRepository;
+use crate::repository::cors_repository::CorsRepository;
+use crate::repository::db_health_repository::DbHealthRepository;
+use crate::repository::error::{Constraint, RepositoryError};
+use crate::repository::evolution_repository::{EvolutionRepository, EvolveMonsterError};
+use crate::repository::export_repository::ExportRepository;
+use crate::repository::idempotency_repository::IdempotencyRepository;
+use crate::repository::item_repository::ItemRepository;
+use crate::repository::job_repository::JobRepository;
+use crate::repository::monster_repository::{DeleteMonsterError, MonsterRepository};
+use crate::repository::move_repository::MoveRepository;
+use crate::repository::note_repository::NoteRepository;
+use crate::repository::rate_limit_repository::RateLimitRepository;
+use crate::repository::season_repository::SeasonRepository;
+use crate::repository::trainer_repository::{trade_answer, OwnershipError, TrainerRepository};
+use crate::repository::thumbnail_repository::ThumbnailRepository;
+use crate::repository::training_repository::{TrainingError, TrainingRepository};
+use crate::repository::webhook_repository::WebhookRepository;
+
+/*
+Keeps monsters and battles in memory, mirroring the behaviour of the Diesel implementation
+(generated ids, cascade rules, timestamps, constraints) so handlers can run without Postgres.
+*/
+#[allow(dead_code)]
+#[derive(Default)]
+pub struct InMemoryRepository {
+    monsters: RwLock<HashMap<String, Monster>>,
+    battles: RwLock<HashMap<String, Battle>>,
+    webhooks: RwLock<HashMap<String, Webhook>>,
+    jobs: RwLock<HashMap<String, Job>>,
+    series: RwLock<HashMap<String, (BattleSeries, Vec<BattleSeriesGame>)>>,
+    interactive_battles: RwLock<HashMap<String, InteractiveBattle>>,
+    battle_rewards: RwLock<Vec<Reward>>,
+    moves: RwLock<HashMap<String, Move>>,
+    // Move ids of each monster in slot order.
+    monster_moves: RwLock<HashMap<String, Vec<String>>>,
+    items: RwLock<HashMap<String, Item>>,
+    // Item ids of each monster in slot order.
+    monster_items: RwLock<HashMap<String, Vec<String>>>,
+    rate_limit_tiers: RwLock<HashMap<String, RateLimitTier>>,
+    api_keys: RwLock<HashMap<String, ApiKey>>,
+    seasons: RwLock<HashMap<String, Season>>,
+    // Snapshots of the closed seasons.
+    season_standings: RwLock<HashMap<String, Vec<SeasonStanding>>>,
+    evolutions: RwLock<HashMap<String, Evolution>>,
+    notes: RwLock<HashMap<String, MonsterNote>>,
+    // Exports with their rows in id order.
+    exports: RwLock<HashMap<String, (Export, Vec<serde_json::Value>)>>,
+    trainings: RwLock<HashMap<String, MonsterTraining>>,
+    trainers: RwLock<HashMap<String, Trainer>>,
+    trades: RwLock<HashMap<String, Trade>>,
+    achievements: RwLock<Vec<Achievement>>,
+    daily_challenges: RwLock<HashMap<NaiveDate, DailyChallenge>>,
+    challenge_attempts: RwLock<Vec<ChallengeAttempts>>,
+    cors_origins: RwLock<HashMap<String, CorsOrigin>>,
+    db_table_stats: RwLock<Vec<TableStats>>,
+    // Image URL each monster's thumbnails were last fetched from.
+    thumbnails: RwLock<HashMap<String, String>>,
+    // By client and key.
+    idempotency_keys: RwLock<HashMap<(String, String), IdempotencyKey>>,
+}
+
+#[allow(dead_code)]
+impl InMemoryRepository {
+    // Starts with the default rate limit tiers, like the database once migrated.
+    pub fn new() -> Self {
+        let repository = InMemoryRepository::default();
+        repository.rate_limit_tiers.write().expect("Rate limit tiers lock poisoned").extend(RateLimitTier::defaults().into_iter().map(|tier| (tier.name.clone(), tier)));
+        repository
+    }
+
+    fn remove_monster(&self, monsters: &mut HashMap<String, Monster>, battles: &mut HashMap<String, Battle>, monster_id: &str, cascade: bool) -> Result<usize, DeleteMonsterError> {
+        if !monsters.contains_key(monster_id) {
+            return Err(DeleteMonsterError::NotFound);
+        }
+
+        let in_battle = |battle: &Battle| battle.monster_a == monster_id || battle.monster_b == monster_id || battle.winner.as_deref() == Some(monster_id);
+        if cascade {
+            battles.retain(|_, battle| !in_battle(battle));
+        } else if battles.values().any(in_battle) {
+            return Err(DeleteMonsterError::HasBattles);
+        }
+
+        monsters.remove(monster_id);
+        self.series.write().expect("Series lock poisoned").retain(|_, (series, _)| series.monster_a != monster_id && series.monster_b != monster_id);
+        self.monster_moves.write().expect("Monster moves lock poisoned").remove(monster_id);
+        self.monster_items.write().expect("Monster items lock poisoned").remove(monster_id);
+        self.notes.write().expect("Notes lock poisoned").retain(|_, note| note.monster_id != monster_id);
+        self.trainings.write().expect("Trainings lock poisoned").remove(monster_id);
+        self.thumbnails.write().expect("Thumbnails lock poisoned").remove(monster_id);
+        self.achievements.write().expect("Achievements lock poisoned").retain(|achievement| achievement.monster_id.as_deref() != Some(monster_id));
+        let mut daily_challenges = self.daily_challenges.write().expect("Daily challenges lock poisoned");
+        daily_challenges.retain(|_, challenge| challenge.opponent != monster_id);
+        self.challenge_attempts.write().expect("Challenge attempts lock poisoned").retain(|attempts| attempts.monster_id != monster_id && daily_challenges.contains_key(&attempts.challenge_date));
+        self.trades.write().expect("Trades lock poisoned").retain(|_, trade| trade.offered_monster != monster_id && trade.requested_monster != monster_id);
+        self.interactive_battles.write().expect("Interactive battles lock poisoned").retain(|_, battle| battle.monster_a != monster_id && battle.monster_b != monster_id);
+        self.battle_rewards.write().expect("Battle rewards lock poisoned").retain(|reward| reward.monster_id != monster_id && battles.contains_key(&reward.battle_id));
+        for standings in self.season_standings.write().expect("Season standings lock poisoned").values_mut() {
+            standings.retain(|standing| standing.monster_id != monster_id);
+        }
+        Ok(1)
+    }
+
+    // The tables `remove_monster` deletes rows of, named like the database ones, that hold any.
+    fn monster_dependents(&self) -> Vec<String> {
+        let tables = [
+            ("achievements", self.achievements.read().expect("Achievements lock poisoned").iter().any(|achievement| achievement.monster_id.is_some())),
+            ("battle_rewards", !self.battle_rewards.read().expect("Battle rewards lock poisoned").is_empty()),
+            ("battle_series", !self.series.read().expect("Series lock poisoned").is_empty()),
+            ("challenge_attempts", !self.challenge_attempts.read().expect("Challenge attempts lock poisoned").is_empty()),
+            ("daily_challenges", !self.daily_challenges.read().expect("Daily challenges lock poisoned").is_empty()),
+            ("interactive_battles", !self.interactive_battles.read().expect("Interactive battles lock poisoned").is_empty()),
+            ("monster_items", self.monster_items.read().expect("Monster items lock poisoned").values().any(|items| !items.is_empty())),
+            ("monster_moves", self.monster_moves.read().expect("Monster moves lock poisoned").values().any(|moves| !moves.is_empty())),
+            ("monster_notes", !self.notes.read().expect("Notes lock poisoned").is_empty()),
+            ("monster_thumbnails", !self.thumbnails.read().expect("Thumbnails lock poisoned").is_empty()),
+            ("monster_training", !self.trainings.read().expect("Trainings lock poisoned").is_empty()),
+            ("season_standings", self.season_standings.read().expect("Season standings lock poisoned").values().any(|standings| !standings.is_empty())),
+            ("trades", !self.trades.read().expect("Trades lock poisoned").is_empty()),
+        ];
+        tables.into_iter().filter(|(_, has_rows)| *has_rows).map(|(table, _)| table.to_string()).collect()
+    }
+
+    // Same rules as the monsters and battles table constraints.
+    fn check_monster(&self, monster: &Monster) -> Result<(), RepositoryError> {
+        if monster.name.trim().is_empty() {
+            return Err(RepositoryError::Constraint(Constraint::MonsterNameNotEmpty));
+        }
+        if !monster.stats.in_range() {
+            return Err(RepositoryError::Constraint(Constraint::MonsterStatsInRange));
+        }
+        if monster.owner_id.as_ref().is_some_and(|owner_id| !self.trainers.read().expect("Trainers lock poisoned").contains_key(owner_id)) {
+            return Err(RepositoryError::Constraint(Constraint::MonsterOwnerExists));
+        }
+        Ok(())
+    }
+
+    fn check_external_id(monsters: &HashMap<String, Monster>, monster: &Monster, monster_id: &str) -> Result<(), RepositoryError> {
+        if monster.external_id.is_some() && monsters.values().any(|existing| existing.id != monster_id && existing.external_id == monster.external_id) {
+            return Err(RepositoryError::Constraint(Constraint::MonsterExternalIdUnique));
+        }
+        Ok(())
+    }
+
+    // Same rules as the unique indexes of the achievements, earning one again keeps the first.
+    fn earn_achievements(&self, earned: Vec<Achievement>) {
+        let mut achievements = self.achievements.write().expect("Achievements lock poisoned");
+        for achievement in earned {
+            let already_earned = achievements.iter().any(|existing| existing.kind == achievement.kind && existing.monster_id == achievement.monster_id && (achievement.monster_id.is_some() || existing.trainer_id == achievement.trainer_id));
+            if !already_earned {
+                achievements.push(achievement);
+            }
+        }
+    }
+
+    fn earn_collector_achievements<'a>(&self, owners: impl Iterator<Item = &'a String>) {
+        let mut owners: Vec<&String> = owners.collect();
+        owners.sort();
+        owners.dedup();
+        for owner in owners {
+            if self.get_trainer_monsters(owner).len() as i64 >= COLLECTOR_MONSTERS {
+                self.earn_achievements(vec![Achievement::new(AchievementKind::Collector, None, Some(owner.clone()), None, Utc::now().naive_utc())]);
+            }
+        }
+    }
+
+    fn check_battle(monsters: &HashMap<String, Monster>, battle: &Battle) -> Result<(), RepositoryError> {
+        let participants = [&battle.monster_a, &battle.monster_b];
+        if participants.into_iter().chain(&battle.winner).any(|monster_id| !monsters.contains_key(monster_id)) {
+            return Err(RepositoryError::Constraint(Constraint::BattleMonsterExists));
+        }
+        if battle.winner.as_ref().is_some_and(|winner| !participants.contains(&winner)) {
+            return Err(RepositoryError::Constraint(Constraint::BattleWinnerIsParticipant));
+        }
+        if (battle.outcome == BattleOutcome::Draw) != battle.winner.is_none() {
+            return Err(RepositoryError::Constraint(Constraint::BattleDrawHasNoWinner));
+        }
+        Ok(())
+    }
+
+    fn update_training(&self, monster_id: &str, update: impl FnOnce(&mut MonsterTraining) -> Result<(), String>) -> Result<MonsterTraining, TrainingError> {
+        if !self.monsters.read().expect("Monsters lock poisoned").contains_key(monster_id) {
+            return Err(TrainingError::NotFound);
+        }
+        let mut trainings = self.trainings.write().expect("Trainings lock poisoned");
+        let mut training = trainings.get(monster_id).cloned().unwrap_or_else(|| MonsterTraining::new(monster_id));
+        update(&mut training).map_err(TrainingError::Invalid)?;
+        training.updated_at = Some(Utc::now().naive_utc());
+        trainings.insert(monster_id.to_string(), training.clone());
+        Ok(training)
+    }
+
+    fn expand(&self, battle: Battle) -> ExpandedBattle {
+        let monster_a = self.get_monster_by_id(&battle.monster_a);
+        let monster_b = self.get_monster_by_id(&battle.monster_b);
+        let winner = battle.winner.as_deref().and_then(|winner| self.get_monster_by_id(winner));
+        ExpandedBattle::from((battle, monster_a, monster_b, winner))
+    }
+}
+
+impl MonsterRepository for InMemoryRepository {
+    fn get_monsters(&self) -> Vec<Monster> {
+        self.monsters.read().expect("Monsters lock poisoned").values().cloned().collect()
+    }
+
+    fn get_monsters_page(&self, owner: Option<&str>, broken_image: Option<bool>, after: Option<&PageCursor>, limit: i64) -> Vec<Monster> {
+        let mut monsters: Vec<Monster> = self
+            .get_monsters()
+            .into_iter()
+            .filter(|monster| owner.is_none_or(|owner| monster.owner_id.as_deref() == Some(owner)))
+            .filter(|monster| broken_image.is_none_or(|broken_image| monster.image_broken == broken_image))
+            .filter(|monster| after.is_none_or(|after| (monster.created_at, &monster.id) > (after.created_at, &after.id)))
+            .collect();
+        monsters.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        monsters.truncate(limit as usize);
+        monsters
+    }
+
+    fn get_monsters_by_ids(&self, monster_ids: &[String]) -> Vec<Monster> {
+        let monsters = self.monsters.read().expect("Monsters lock poisoned");
+        monster_ids
+            .iter()
+            .filter_map(|monster_id| monsters.get(monster_id).cloned())
+            .collect()
+    }
+
+    // Ranks by the share of search words found in the name, ignoring case.
+    fn search_monsters(&self, search: &str, limit: i64) -> Vec<MonsterSearchResult> {
+        let words: Vec<String> = search.split_whitespace().map(str::to_lowercase).collect();
+        let mut results: Vec<MonsterSearchResult> = self.get_monsters()
+            .into_iter()
+            .filter_map(|monster| {
+                let name = monster.name.to_lowercase();
+                let matches = words.iter().filter(|word| name.contains(word.as_str())).count();
+                if matches == 0 {
+                    return None;
+                }
+                Some(MonsterSearchResult { monster, rank: matches as f32 / words.len() as f32 })
+            })
+            .collect();
+        results.sort_by(|a, b| b.rank.total_cmp(&a.rank).then(a.monster.name.cmp(&b.monster.name)));
+        results.truncate(limit.max(0) as usize);
+        results
+    }
+
+    fn find_matchmaking_candidates(&self, monster_id: &str, recent_battles: i64, region: Option<&str>, limit: i64) -> Vec<MatchmakingCandidate> {
+        let Some(target) = self.get_monster_by_id(monster_id) else { return Vec::new() };
+        let trainers = self.trainers.read().expect("Trainers lock poisoned");
+        let owner_region = |monster: &Monster| monster.owner_id.as_ref().and_then(|owner_id| trainers.get(owner_id)).and_then(|trainer| trainer.region.clone());
+        let region = owner_region(&target).or(region.map(str::to_string));
+        let recent_opponents: Vec<String> = self.get_battles_by_monster(monster_id)
+            .into_iter()
+            .take(recent_battles.max(0) as usize)
+            .map(|battle| if battle.monster_a == monster_id { battle.monster_b } else { battle.monster_a })
+            .collect();
+        let mut candidates: Vec<MatchmakingCandidate> = self.get_monsters()
+            .into_iter()
+            .filter(|monster| monster.id != monster_id && !recent_opponents.contains(&monster.id))
+            .map(|monster| MatchmakingCandidate {
+                distance: monster.stats.distance(&target.stats),
+                same_region: region.is_some() && owner_region(&monster) == region,
+                monster,
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.same_region.cmp(&a.same_region).then(a.distance.cmp(&b.distance)).then(a.monster.id.cmp(&b.monster.id)));
+        candidates.truncate(limit.max(0) as usize);
+        candidates
+    }
+
+    fn find_duplicate_pairs(&self, min_similarity: f32) -> Vec<DuplicatePair> {
+        let mut monsters = self.get_monsters();
+        monsters.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut pairs = Vec::new();
+        for (index, monster_a) in monsters.iter().enumerate() {
+            for monster_b in monsters[index + 1..].iter().filter(|monster_b| monster_b.stats == monster_a.stats) {
+                let similarity = trigram_similarity(&monster_a.name, &monster_b.name);
+                if similarity >= min_similarity {
+                    pairs.push(DuplicatePair { monster_a: monster_a.id.clone(), monster_b: monster_b.id.clone(), similarity });
+                }
+            }
+        }
+        pairs
+    }
+
+    fn get_monster_by_id(&self, monster_id: &str) -> Option<Monster> {
+        self.monsters.read().expect("Monsters lock poisoned").get(monster_id).cloned()
+    }
+
+    fn create_monster(&self, monster: Monster) -> Result<Monster, RepositoryError> {
+        self.check_monster(&monster)?;
+        let monster = Monster {
+            id: uuid::Uuid::new_v4().to_string(),
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            image_broken: false,
+            version: 1,
+            ..monster
+        };
+        let mut monsters = self.monsters.write().expect("Monsters lock poisoned");
+        Self::check_external_id(&monsters, &monster, &monster.id)?;
+        monsters.insert(monster.id.clone(), monster.clone());
+        Ok(monster)
+    }
+
+    fn create_monsters(&self, new_monsters: Vec<Monster>) -> Result<Vec<Result<Monster, RepositoryError>>, RepositoryError> {
+        let results: Vec<Result<Monster, RepositoryError>> = new_monsters
+            .into_iter()
+            .map(|monster| self.create_monster(monster))
+            .collect();
+        self.earn_collector_achievements(results.iter().flatten().filter_map(|monster| monster.owner_id.as_ref()));
+        Ok(results)
+    }
+
+    fn import_monsters(&self, new_monsters: Vec<Monster>, mode: ImportMode) -> Result<Vec<Result<ImportOutcome, RepositoryError>>, RepositoryError> {
+        let mut results = Vec::new();
+        for monster in new_monsters {
+            let mut duplicates: Vec<Monster> = match mode {
+                ImportMode::Create => Vec::new(),
+                _ => self.get_monsters()
+                    .into_iter()
+                    .filter(|existing| existing.owner_id == monster.owner_id)
+                    .filter(|existing| match &monster.external_id {
+                        Some(_) => existing.external_id == monster.external_id,
+                        None => existing.name.to_lowercase() == monster.name.trim().to_lowercase(),
+                    })
+                    .collect(),
+            };
+            duplicates.sort_by_key(|existing| (existing.created_at.is_none(), existing.created_at, existing.id.clone()));
+            results.push(match (duplicates.into_iter().next(), mode) {
+                (None, _) | (_, ImportMode::Create) => self.create_monster(monster).map(ImportOutcome::Created),
+                (Some(_), ImportMode::SkipDuplicates) => Ok(ImportOutcome::Skipped),
+                (Some(existing_monster), _) => self
+                    .update_monster_by_id(&existing_monster.id, Monster { created_at: existing_monster.created_at, version: existing_monster.version, ..monster })
+                    .map(|updated| ImportOutcome::Updated(updated.expect("Duplicate found above"))),
+            });
+        }
+        self.earn_collector_achievements(results.iter().flatten().filter_map(|outcome| match outcome {
+            ImportOutcome::Created(monster) => monster.owner_id.as_ref(),
+            _ => None,
+        }));
+        Ok(results)
+    }
+
+    fn update_monster_by_id(&self, monster_id: &str, monster: Monster) -> Result<Option<Monster>, RepositoryError> {
+        let mut monsters = self.monsters.write().expect("Monsters lock poisoned");
+        if !monsters.contains_key(monster_id) {
+            return Ok(None);
+        }
+        self.check_monster(&monster)?;
+        Self::check_external_id(&monsters, &monster, monster_id)?;
+        let existing_monster = monsters.get_mut(monster_id).expect("Monster checked above");
+        if monster.version != existing_monster.version {
+            return Err(RepositoryError::StaleVersion);
+        }
+        *existing_monster = Monster {
+            id: existing_monster.id.clone(),
+            created_at: monster.created_at.or(existing_monster.created_at),
+            updated_at: Some(Utc::now().naive_utc()),
+            last_battle_at: existing_monster.last_battle_at,
+            level: existing_monster.level,
+            xp: existing_monster.xp,
+            owner_id: existing_monster.owner_id.clone(),
+            image_broken: existing_monster.image_broken && existing_monster.image_url == monster.image_url,
+            external_id: monster.external_id.or(existing_monster.external_id.clone()),
+            version: existing_monster.version + 1,
+            ..monster
+        };
+        Ok(Some(existing_monster.clone()))
+    }
+
+    fn set_monster_image_url(&self, monster_id: &str, image_url: &str) -> Result<Option<Monster>, RepositoryError> {
+        let mut monsters = self.monsters.write().expect("Monsters lock poisoned");
+        Ok(monsters.get_mut(monster_id).map(|monster| {
+            monster.image_url = image_url.to_string();
+            monster.image_broken = false;
+            monster.updated_at = Some(Utc::now().naive_utc());
+            monster.clone()
+        }))
+    }
+
+    fn level_up_monster(&self, monster_id: &str) -> Result<Option<Monster>, RepositoryError> {
+        let mut monsters = self.monsters.write().expect("Monsters lock poisoned");
+        Ok(monsters.get_mut(monster_id).map(|monster| {
+            growth::level_up(monster, &balance::current());
+            monster.clone()
+        }))
+    }
+
+    fn delete_monster_by_id(&self, monster_id: &str, cascade: bool) -> Result<usize, DeleteMonsterError> {
+        let mut monsters = self.monsters.write().expect("Monsters lock poisoned");
+        let mut battles = self.battles.write().expect("Battles lock poisoned");
+        self.remove_monster(&mut monsters, &mut battles, monster_id, cascade)
+    }
+
+    fn delete_monsters(&self, monster_ids: &[String], cascade: bool) -> Result<Vec<Result<usize, DeleteMonsterError>>, RepositoryError> {
+        let mut monsters = self.monsters.write().expect("Monsters lock poisoned");
+        let mut battles = self.battles.write().expect("Battles lock poisoned");
+        Ok(monster_ids
+            .iter()
+            .map(|monster_id| self.remove_monster(&mut monsters, &mut battles, monster_id, cascade))
+            .collect())
+    }
+}
+
+impl BattleRepository for InMemoryRepository {
+    fn get_battles(&self) -> Vec<Battle> {
+        self.battles.read().expect("Battles lock poisoned").values().cloned().collect()
+    }
+
+    fn get_battles_page(&self, after: Option<&PageCursor>, limit: i64) -> Vec<Battle> {
+        let mut battles: Vec<Battle> = self
+            .get_battles()
+            .into_iter()
+            .filter(|battle| after.is_none_or(|after| (battle.created_at, &battle.id) > (after.created_at, &after.id)))
+            .collect();
+        battles.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        battles.truncate(limit as usize);
+        battles
+    }
+
+    fn get_battles_created_between(&self, from: NaiveDateTime, to: NaiveDateTime) -> Vec<Battle> {
+        self.get_battles()
+            .into_iter()
+            .filter(|battle| battle.created_at.is_some_and(|created_at| created_at >= from && created_at < to))
+            .collect()
+    }
+
+    fn get_battles_by_monster(&self, monster_id: &str) -> Vec<Battle> {
+        let mut battles: Vec<Battle> = self.get_battles()
+            .into_iter()
+            .filter(|battle| battle.monster_a == monster_id || battle.monster_b == monster_id)
+            .collect();
+        battles.sort_by_key(|battle| std::cmp::Reverse(battle.created_at));
+        battles
+    }
+
+    fn get_highlight_battles(&self, since: NaiveDateTime, limit: i64) -> Vec<Battle> {
+        let mut battles: Vec<Battle> = self.get_battles()
+            .into_iter()
+            .filter(|battle| battle.highlight_score > 0 && battle.created_at.is_some_and(|created_at| created_at >= since))
+            .collect();
+        battles.sort_by_key(|battle| std::cmp::Reverse((battle.highlight_score, battle.created_at)));
+        battles.truncate(limit as usize);
+        battles
+    }
+
+    fn get_leaderboard(&self, mode: LeaderboardMode, limit: i64) -> Vec<LeaderboardEntry> {
+        leaderboard(&self.get_battles(), &self.get_monsters(), mode, limit.max(0) as usize)
+    }
+
+    fn get_expanded_battles(&self) -> Vec<ExpandedBattle> {
+        self.get_battles()
+            .into_iter()
+            .map(|battle| self.expand(battle))
+            .collect()
+    }
+
+    fn get_expanded_battles_page(&self, after: Option<&PageCursor>, limit: i64) -> Vec<ExpandedBattle> {
+        self.get_battles_page(after, limit)
+            .into_iter()
+            .map(|battle| self.expand(battle))
+            .collect()
+    }
+
+    fn get_expanded_battle_by_id(&self, battle_id: &str) -> Option<ExpandedBattle> {
+        self.get_battle_by_id(battle_id).map(|battle| self.expand(battle))
+    }
+
+    fn get_battle_by_id(&self, battle_id: &str) -> Option<Battle> {
+        self.battles.read().expect("Battles lock poisoned").get(battle_id).cloned()
+    }
+
+    fn delete_battle_by_id(&self, battle_id: &str) -> Option<usize> {
+        self.battle_rewards.write().expect("Battle rewards lock poisoned").retain(|reward| reward.battle_id != battle_id);
+        self.battles.write().expect("Battles lock poisoned").remove(battle_id).map(|_| 1)
+    }
+
+    fn create_battle(&self, battle: Battle) -> Result<Battle, RepositoryError> {
+        // Battles streamed live keep the id their spectators already know.
+        let battle = Battle {
+            id: Some(battle.id).filter(|battle_id| !battle_id.is_empty()).unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            created_at: Some(Utc::now().naive_utc()),
+            season_id: self.get_open_season().map(|season| season.id),
+            ..battle
+        };
+        let mut monsters = self.monsters.write().expect("Monsters lock poisoned");
+        InMemoryRepository::check_battle(&monsters, &battle)?;
+        let (trainer_a, trainer_b) = (monsters[&battle.monster_a].owner_id.clone(), monsters[&battle.monster_b].owner_id.clone());
+        let trainer_region = |trainer_id: &Option<String>| trainer_id.as_ref().and_then(|trainer_id| self.get_trainer_by_id(trainer_id)).and_then(|trainer| trainer.region);
+        let region = battle.region.clone().or_else(|| trainer_region(&trainer_a)).or_else(|| trainer_region(&trainer_b));
+        let battle = Battle { trainer_a, trainer_b, region, ..battle };
+        for monster_id in [&battle.monster_a, &battle.monster_b] {
+            if let Some(monster) = monsters.get_mut(monster_id) {
+                monster.last_battle_at = battle.created_at;
+            }
+        }
+        if let Some(winner_id) = &battle.winner {
+            let loser_id = if winner_id == &battle.monster_a { &battle.monster_b } else { &battle.monster_a };
+            let loser_stats = monsters[loser_id].stats;
+            let winner = monsters.get_mut(winner_id).expect("Battle monsters were checked");
+            let balance = balance::current();
+            let xp = growth::battle_xp(&winner.stats, &loser_stats, &balance);
+            growth::gain_xp(winner, xp, &balance);
+            self.trainings
+                .write()
+                .expect("Trainings lock poisoned")
+                .entry(winner_id.clone())
+                .or_insert_with(|| MonsterTraining::new(winner_id))
+                .earn(balance.training.points_per_win, &balance);
+        }
+        self.battles.write().expect("Battles lock poisoned").insert(battle.id.clone(), battle.clone());
+        if let Some(winner_id) = &battle.winner {
+            let latest_battles: Vec<Battle> = self.get_battles_by_monster(winner_id).into_iter().take(WIN_STREAK_LENGTH).collect();
+            let owner = if winner_id == &battle.monster_a { &battle.trainer_a } else { &battle.trainer_b };
+            self.earn_achievements(battle_achievements(&battle, &latest_battles)
+                .into_iter()
+                .map(|kind| Achievement::new(kind, Some(winner_id.clone()), owner.clone(), Some(battle.id.clone()), Utc::now().naive_utc()))
+                .collect());
+        }
+        Ok(battle)
+    }
+
+    fn create_rewarded_battle(&self, battle: Battle, rewards: Vec<Reward>) -> Result<(Battle, Vec<Reward>), RepositoryError> {
+        let battle = self.create_battle(battle)?;
+        self.battle_rewards.write().expect("Battle rewards lock poisoned").extend(rewards.iter().cloned());
+        Ok((battle, rewards))
+    }
+
+    fn create_series(&self, series: BattleSeries, games: Vec<SeriesGame>) -> Result<ExpandedBattleSeries, RepositoryError> {
+        {
+            let monsters = self.monsters.read().expect("Monsters lock poisoned");
+            for game in &games {
+                InMemoryRepository::check_battle(&monsters, &game.battle)?;
+            }
+        }
+        let series = BattleSeries { created_at: Utc::now().naive_utc(), ..series };
+        let mut stored_games = Vec::new();
+        for SeriesGame { game, seed, battle } in games {
+            stored_games.push(SeriesGame { game, seed, battle: self.create_battle(battle)? });
+        }
+        let rows = stored_games
+            .iter()
+            .map(|game| BattleSeriesGame { series_id: series.id.clone(), game: game.game, battle_id: game.battle.id.clone(), seed: game.seed })
+            .collect();
+        self.series.write().expect("Series lock poisoned").insert(series.id.clone(), (series.clone(), rows));
+        Ok(ExpandedBattleSeries { series, games: stored_games })
+    }
+
+    // Games whose battle was deleted are left out, like the cascading foreign key does.
+    fn get_series_by_id(&self, series_id: &str) -> Option<ExpandedBattleSeries> {
+        let (series, rows) = self.series.read().expect("Series lock poisoned").get(series_id).cloned()?;
+        let games = rows
+            .into_iter()
+            .filter_map(|row| self.get_battle_by_id(&row.battle_id).map(|battle| SeriesGame { game: row.game, seed: row.seed, battle }))
+            .collect();
+        Some(ExpandedBattleSeries { series, games })
+    }
+
+    fn create_interactive_battle(&self, battle: InteractiveBattle) -> Result<InteractiveBattle, RepositoryError> {
+        let monsters = self.monsters.read().expect("Monsters lock poisoned");
+        if [&battle.monster_a, &battle.monster_b].into_iter().any(|monster_id| !monsters.contains_key(monster_id)) {
+            return Err(RepositoryError::Constraint(Constraint::BattleMonsterExists));
+        }
+        self.interactive_battles.write().expect("Interactive battles lock poisoned").insert(battle.id.clone(), battle.clone());
+        Ok(battle)
+    }
+
+    fn get_interactive_battle_by_id(&self, battle_id: &str) -> Option<InteractiveBattle> {
+        self.interactive_battles.read().expect("Interactive battles lock poisoned").get(battle_id).cloned()
+    }
+
+    fn play_interactive_battle(&self, battle_id: &str, play: &mut dyn FnMut(&mut InteractiveBattle) -> Option<Battle>) -> Result<Option<(InteractiveBattle, Option<Battle>)>, RepositoryError> {
+        let mut interactive_battles = self.interactive_battles.write().expect("Interactive battles lock poisoned");
+        let Some(existing_battle) = interactive_battles.get(battle_id) else {
+            return Ok(None);
+        };
+        let mut battle = existing_battle.clone();
+        let record = play(&mut battle);
+        if &battle == existing_battle {
+            return Ok(Some((battle, None)));
+        }
+
+        let record = record.map(|record| self.create_battle(record)).transpose()?;
+        battle.battle_id = record.as_ref().map(|record| record.id.clone()).or(battle.battle_id);
+        battle.updated_at = Some(Utc::now().naive_utc());
+        interactive_battles.insert(battle_id.to_string(), battle.clone());
+        Ok(Some((battle, record)))
+    }
+}
+
+impl WebhookRepository for InMemoryRepository {
+    fn get_webhooks(&self) -> Vec<Webhook> {
+        let mut webhooks: Vec<Webhook> = self.webhooks.read().expect("Webhooks lock poisoned").values().cloned().collect();
+        webhooks.sort_by_key(|webhook| webhook.created_at);
+        webhooks
+    }
+
+    fn get_webhooks_for_event(&self, event_type: &str) -> Vec<Webhook> {
+        self.get_webhooks()
+            .into_iter()
+            .filter(|webhook| webhook.event_types.iter().any(|webhook_event_type| webhook_event_type == event_type))
+            .collect()
+    }
+
+    fn get_webhook_by_id(&self, webhook_id: &str) -> Option<Webhook> {
+        self.webhooks.read().expect("Webhooks lock poisoned").get(webhook_id).cloned()
+    }
+
+    fn create_webhook(&self, webhook: Webhook) -> Result<Webhook, RepositoryError> {
+        let webhook = Webhook {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            ..webhook
+        };
+        self.webhooks.write().expect("Webhooks lock poisoned").insert(webhook.id.clone(), webhook.clone());
+        Ok(webhook)
+    }
+
+    fn update_webhook_by_id(&self, webhook_id: &str, webhook: Webhook) -> Result<Option<Webhook>, RepositoryError> {
+        let mut webhooks = self.webhooks.write().expect("Webhooks lock poisoned");
+        let existing_webhook = match webhooks.get_mut(webhook_id) {
+            Some(existing_webhook) => existing_webhook,
+            None => return Ok(None),
+        };
+        existing_webhook.target_url = webhook.target_url;
+        existing_webhook.event_types = webhook.event_types;
+        existing_webhook.updated_at = Some(Utc::now().naive_utc());
+        Ok(Some(existing_webhook.clone()))
+    }
+
+    fn delete_webhook_by_id(&self, webhook_id: &str) -> Option<usize> {
+        self.webhooks.write().expect("Webhooks lock poisoned").remove(webhook_id).map(|_| 1)
+    }
+}
+
+impl MoveRepository for InMemoryRepository {
+    fn get_moves(&self) -> Vec<Move> {
+        let mut moves: Vec<Move> = self.moves.read().expect("Moves lock poisoned").values().cloned().collect();
+        moves.sort_by_key(|existing_move| existing_move.created_at);
+        moves
+    }
+
+    fn get_move_by_id(&self, move_id: &str) -> Option<Move> {
+        self.moves.read().expect("Moves lock poisoned").get(move_id).cloned()
+    }
+
+    fn create_move(&self, new_move: Move) -> Result<Move, RepositoryError> {
+        let new_move = Move {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            ..new_move
+        };
+        self.moves.write().expect("Moves lock poisoned").insert(new_move.id.clone(), new_move.clone());
+        Ok(new_move)
+    }
+
+    fn update_move_by_id(&self, move_id: &str, updated_move: Move) -> Result<Option<Move>, RepositoryError> {
+        let mut moves = self.moves.write().expect("Moves lock poisoned");
+        let existing_move = match moves.get_mut(move_id) {
+            Some(existing_move) => existing_move,
+            None => return Ok(None),
+        };
+        *existing_move = Move {
+            id: existing_move.id.clone(),
+            created_at: existing_move.created_at,
+            updated_at: Some(Utc::now().naive_utc()),
+            ..updated_move
+        };
+        Ok(Some(existing_move.clone()))
+    }
+
+    fn delete_move_by_id(&self, move_id: &str) -> Option<usize> {
+        let removed = self.moves.write().expect("Moves lock poisoned").remove(move_id)?;
+        for move_ids in self.monster_moves.write().expect("Monster moves lock poisoned").values_mut() {
+            move_ids.retain(|known_move_id| *known_move_id != removed.id);
+        }
+        Some(1)
+    }
+
+    fn get_monster_moves(&self, monster_id: &str) -> Vec<Move> {
+        let moves = self.moves.read().expect("Moves lock poisoned");
+        let monster_moves = self.monster_moves.read().expect("Monster moves lock poisoned");
+        monster_moves
+            .get(monster_id)
+            .map(|move_ids| move_ids.iter().filter_map(|move_id| moves.get(move_id).cloned()).collect())
+            .unwrap_or_default()
+    }
+
+    fn set_monster_moves(&self, monster_id: &str, move_ids: &[String]) -> Result<Vec<Move>, RepositoryError> {
+        {
+            let moves = self.moves.read().expect("Moves lock poisoned");
+            if move_ids.iter().any(|move_id| !moves.contains_key(move_id)) {
+                return Err(RepositoryError::Constraint(Constraint::MonsterMoveExists));
+            }
+        }
+        self.monster_moves.write().expect("Monster moves lock poisoned").insert(monster_id.to_string(), move_ids.to_vec());
+        Ok(self.get_monster_moves(monster_id))
+    }
+}
+
+impl ItemRepository for InMemoryRepository {
+    fn get_items(&self) -> Vec<Item> {
+        let mut items: Vec<Item> = self.items.read().expect("Items lock poisoned").values().cloned().collect();
+        items.sort_by_key(|item| item.created_at);
+        items
+    }
+
+    fn get_item_by_id(&self, item_id: &str) -> Option<Item> {
+        self.items.read().expect("Items lock poisoned").get(item_id).cloned()
+    }
+
+    fn create_item(&self, item: Item) -> Result<Item, RepositoryError> {
+        let item = Item {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            ..item
+        };
+        self.items.write().expect("Items lock poisoned").insert(item.id.clone(), item.clone());
+        Ok(item)
+    }
+
+    fn update_item_by_id(&self, item_id: &str, item: Item) -> Result<Option<Item>, RepositoryError> {
+        let mut items = self.items.write().expect("Items lock poisoned");
+        let existing_item = match items.get_mut(item_id) {
+            Some(existing_item) => existing_item,
+            None => return Ok(None),
+        };
+        *existing_item = Item {
+            id: existing_item.id.clone(),
+            created_at: existing_item.created_at,
+            updated_at: Some(Utc::now().naive_utc()),
+            ..item
+        };
+        Ok(Some(existing_item.clone()))
+    }
+
+    fn delete_item_by_id(&self, item_id: &str) -> Option<usize> {
+        let removed = self.items.write().expect("Items lock poisoned").remove(item_id)?;
+        for item_ids in self.monster_items.write().expect("Monster items lock poisoned").values_mut() {
+            item_ids.retain(|carried_item_id| *carried_item_id != removed.id);
+        }
+        Some(1)
+    }
+
+    fn get_monster_items(&self, monster_id: &str) -> Vec<Item> {
+        let items = self.items.read().expect("Items lock poisoned");
+        let monster_items = self.monster_items.read().expect("Monster items lock poisoned");
+        monster_items
+            .get(monster_id)
+            .map(|item_ids| item_ids.iter().filter_map(|item_id| items.get(item_id).cloned()).collect())
+            .unwrap_or_default()
+    }
+
+    fn set_monster_items(&self, monster_id: &str, item_ids: &[String]) -> Result<Vec<Item>, RepositoryError> {
+        {
+            let items = self.items.read().expect("Items lock poisoned");
+            if item_ids.iter().any(|item_id| !items.contains_key(item_id)) {
+                return Err(RepositoryError::Constraint(Constraint::MonsterItemExists));
+            }
+        }
+        self.monster_items.write().expect("Monster items lock poisoned").insert(monster_id.to_string(), item_ids.to_vec());
+        Ok(self.get_monster_items(monster_id))
+    }
+}
+
+impl RateLimitRepository for InMemoryRepository {
+    fn get_rate_limit_tiers(&self) -> Vec<RateLimitTier> {
+        let mut tiers: Vec<RateLimitTier> = self.rate_limit_tiers.read().expect("Rate limit tiers lock poisoned").values().cloned().collect();
+        tiers.sort_by(|a, b| a.name.cmp(&b.name));
+        tiers
+    }
+
+    fn save_rate_limit_tier(&self, tier: RateLimitTier) -> Result<RateLimitTier, RepositoryError> {
+        let tier = RateLimitTier { updated_at: Some(Utc::now().naive_utc()), ..tier };
+        self.rate_limit_tiers.write().expect("Rate limit tiers lock poisoned").insert(tier.name.clone(), tier.clone());
+        Ok(tier)
+    }
+
+    fn delete_rate_limit_tier(&self, tier_name: &str) -> Result<Option<usize>, RepositoryError> {
+        if self.api_keys.read().expect("API keys lock poisoned").values().any(|api_key| api_key.tier == tier_name) {
+            return Err(RepositoryError::Constraint(Constraint::ApiKeyTierExists));
+        }
+        Ok(self.rate_limit_tiers.write().expect("Rate limit tiers lock poisoned").remove(tier_name).map(|_| 1))
+    }
+
+    fn get_api_keys(&self) -> Vec<ApiKey> {
+        let mut api_keys: Vec<ApiKey> = self.api_keys.read().expect("API keys lock poisoned").values().cloned().collect();
+        api_keys.sort_by_key(|api_key| api_key.created_at);
+        api_keys
+    }
+
+    fn get_api_key_by_hash(&self, key_hash: &str) -> Option<ApiKey> {
+        self.api_keys.read().expect("API keys lock poisoned").values().find(|api_key| api_key.key_hash == key_hash).cloned()
+    }
+
+    fn create_api_key(&self, api_key: ApiKey) -> Result<ApiKey, RepositoryError> {
+        if !self.rate_limit_tiers.read().expect("Rate limit tiers lock poisoned").contains_key(&api_key.tier) {
+            return Err(RepositoryError::Constraint(Constraint::ApiKeyTierExists));
+        }
+        let api_key = ApiKey {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: Utc::now().naive_utc(),
+            ..api_key
+        };
+        self.api_keys.write().expect("API keys lock poisoned").insert(api_key.id.clone(), api_key.clone());
+        Ok(api_key)
+    }
+
+    fn delete_api_key_by_id(&self, api_key_id: &str) -> Option<usize> {
+        let removed = self.api_keys.write().expect("API keys lock poisoned").remove(api_key_id)?;
+        self.notes.write().expect("Notes lock poisoned").retain(|_, note| note.owner != removed.id);
+        if let Some(trainer) = self.get_trainer_by_api_key(api_key_id) {
+            self.trainers.write().expect("Trainers lock poisoned").remove(&trainer.id);
+            self.trades.write().expect("Trades lock poisoned").retain(|_, trade| trade.proposer != trainer.id && trade.recipient != trainer.id);
+            self.achievements.write().expect("Achievements lock poisoned").retain(|achievement| achievement.trainer_id.as_ref() != Some(&trainer.id));
+            for monster in self.monsters.write().expect("Monsters lock poisoned").values_mut().filter(|monster| monster.owner_id.as_ref() == Some(&trainer.id)) {
+                monster.owner_id = None;
+            }
+        }
+        Some(1)
+    }
+}
+
+impl CorsRepository for InMemoryRepository {
+    fn get_cors_origins(&self) -> Vec<CorsOrigin> {
+        let mut origins: Vec<CorsOrigin> = self.cors_origins.read().expect("CORS origins lock poisoned").values().cloned().collect();
+        origins.sort_by(|a, b| a.origin.cmp(&b.origin));
+        origins
+    }
+
+    fn add_cors_origin(&self, origin: &str) -> Result<CorsOrigin, RepositoryError> {
+        Ok(self.cors_origins
+            .write()
+            .expect("CORS origins lock poisoned")
+            .entry(origin.to_string())
+            .or_insert_with(|| CorsOrigin { origin: origin.to_string(), created_at: Utc::now().naive_utc() })
+            .clone())
+    }
+
+    fn delete_cors_origin(&self, origin: &str) -> Result<Option<usize>, RepositoryError> {
+        Ok(self.cors_origins.write().expect("CORS origins lock poisoned").remove(origin).map(|_| 1))
+    }
+}
+
+impl IdempotencyRepository for InMemoryRepository {
+    fn claim_idempotency_key(&self, claim: &IdempotencyKey, expired_before: NaiveDateTime) -> Result<Option<IdempotencyKey>, RepositoryError> {
+        let mut idempotency_keys = self.idempotency_keys.write().expect("Idempotency keys lock poisoned");
+        let claimed_key = (claim.client.clone(), claim.key.clone());
+        match idempotency_keys.get(&claimed_key) {
+            Some(existing_key) if existing_key.created_at >= expired_before => Ok(Some(existing_key.clone())),
+            _ => {
+                idempotency_keys.insert(claimed_key, claim.clone());
+                Ok(None)
+            }
+        }
+    }
+
+    fn complete_idempotency_key(&self, client: &str, key: &str, status: i32, content_type: Option<&str>, body: Option<&[u8]>) -> Result<(), RepositoryError> {
+        if let Some(claimed_key) = self.idempotency_keys.write().expect("Idempotency keys lock poisoned").get_mut(&(client.to_string(), key.to_string())) {
+            claimed_key.status = Some(status);
+            claimed_key.content_type = content_type.map(str::to_string);
+            claimed_key.body = body.map(<[u8]>::to_vec);
+        }
+        Ok(())
+    }
+
+    fn release_idempotency_key(&self, client: &str, key: &str) -> Result<(), RepositoryError> {
+        self.idempotency_keys.write().expect("Idempotency keys lock poisoned").remove(&(client.to_string(), key.to_string()));
+        Ok(())
+    }
+
+    fn delete_expired_idempotency_keys(&self, expired_before: NaiveDateTime) -> Result<usize, RepositoryError> {
+        let mut idempotency_keys = self.idempotency_keys.write().expect("Idempotency keys lock poisoned");
+        let count = idempotency_keys.len();
+        idempotency_keys.retain(|_, claimed_key| claimed_key.created_at >= expired_before);
+        Ok(count - idempotency_keys.len())
+    }
+}
+
+impl ThumbnailRepository for InMemoryRepository {
+    fn get_pending_thumbnails(&self, limit: i64) -> Vec<Monster> {
+        let monsters = self.monsters.read().expect("Monsters lock poisoned");
+        let thumbnails = self.thumbnails.read().expect("Thumbnails lock poisoned");
+        let mut pending: Vec<Monster> = monsters.values().filter(|monster| thumbnails.get(&monster.id) != Some(&monster.image_url)).cloned().collect();
+        pending.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        pending.truncate(limit.max(0) as usize);
+        pending
+    }
+
+    fn record_thumbnails(&self, monster_id: &str, image_url: &str, error: Option<&str>) -> Result<(), RepositoryError> {
+        let mut monsters = self.monsters.write().expect("Monsters lock poisoned");
+        let Some(monster) = monsters.get_mut(monster_id).filter(|monster| monster.image_url == image_url) else {
+            return Ok(());
+        };
+        monster.image_broken = error.is_some();
+        self.thumbnails.write().expect("Thumbnails lock poisoned").insert(monster_id.to_string(), image_url.to_string());
+        Ok(())
+    }
+}
+
+impl ChallengeRepository for InMemoryRepository {
+    fn get_daily_challenge(&self, date: NaiveDate) -> Option<DailyChallenge> {
+        self.daily_challenges.read().expect("Daily challenges lock poisoned").get(&date).cloned()
+    }
+
+    fn save_daily_challenge(&self, challenge: DailyChallenge) -> Result<DailyChallenge, RepositoryError> {
+        if !self.monsters.read().expect("Monsters lock poisoned").contains_key(&challenge.opponent) {
+            return Err(RepositoryError::Constraint(Constraint::ChallengeMonsterExists));
+        }
+        Ok(self.daily_challenges
+            .write()
+            .expect("Daily challenges lock poisoned")
+            .entry(challenge.challenge_date)
+            .or_insert(challenge)
+            .clone())
+    }
+
+    fn record_challenge_attempt(&self, date: NaiveDate, monster_id: &str, won: bool) -> Result<ChallengeAttempts, RepositoryError> {
+        if !self.monsters.read().expect("Monsters lock poisoned").contains_key(monster_id) {
+            return Err(RepositoryError::Constraint(Constraint::ChallengeMonsterExists));
+        }
+        let now = Utc::now().naive_utc();
+        let mut challenge_attempts = self.challenge_attempts.write().expect("Challenge attempts lock poisoned");
+        let index = match challenge_attempts.iter().position(|attempts| attempts.challenge_date == date && attempts.monster_id == monster_id) {
+            Some(index) => index,
+            None => {
+                challenge_attempts.push(ChallengeAttempts { challenge_date: date, monster_id: monster_id.to_string(), attempts: 0, wins: 0, last_attempt_at: now });
+                challenge_attempts.len() - 1
+            }
+        };
+        let attempts = &mut challenge_attempts[index];
+        attempts.attempts += 1;
+        attempts.wins += won as i32;
+        attempts.last_attempt_at = now;
+        Ok(attempts.clone())
+    }
+
+    fn get_challenge_attempts(&self, date: NaiveDate) -> Vec<ChallengeAttempts> {
+        self.challenge_attempts.read().expect("Challenge attempts lock poisoned").iter().filter(|attempts| attempts.challenge_date == date).cloned().collect()
+    }
+}
+
+impl BackupRepository for InMemoryRepository {
+    fn get_backup(&self) -> Result<Backup, RepositoryError> {
+        let monsters = self.monsters.read().expect("Monsters lock poisoned");
+        let battles = self.battles.read().expect("Battles lock poisoned");
+        let mut backup = Backup {
+            version: BACKUP_VERSION,
+            created_at: Utc::now().naive_utc(),
+            monsters: monsters.values().cloned().collect(),
+            battles: battles.values().cloned().collect(),
+        };
+        backup.monsters.sort_by(|a, b| a.id.cmp(&b.id));
+        backup.battles.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(backup)
+    }
+
+    // Every row is checked before anything changes, like a failing statement rolls the database transaction back.
+    fn restore_backup(&self, backup: &Backup, mode: RestoreMode) -> Result<(), RepositoryError> {
+        for monster in &backup.monsters {
+            self.check_monster(monster)?;
+        }
+        let mut monsters = self.monsters.write().expect("Monsters lock poisoned");
+        let mut battles = self.battles.write().expect("Battles lock poisoned");
+        if mode == RestoreMode::Replace {
+            let dependents = self.monster_dependents();
+            if !dependents.is_empty() {
+                return Err(RepositoryError::HasDependents(dependents));
+            }
+        }
+        let mut restored_monsters = if mode == RestoreMode::Merge { monsters.clone() } else { HashMap::new() };
+        for monster in &backup.monsters {
+            Self::check_external_id(&restored_monsters, monster, &monster.id)?;
+            restored_monsters.insert(monster.id.clone(), monster.clone());
+        }
+        for battle in &backup.battles {
+            Self::check_battle(&restored_monsters, battle)?;
+        }
+
+        if mode != RestoreMode::Merge {
+            let monster_ids: Vec<String> = monsters.keys().cloned().collect();
+            for monster_id in monster_ids {
+                self.remove_monster(&mut monsters, &mut battles, &monster_id, true).expect("Existing monsters are removed with their battles");
+            }
+        }
+        monsters.extend(backup.monsters.iter().map(|monster| (monster.id.clone(), monster.clone())));
+        battles.extend(backup.battles.iter().map(|battle| (battle.id.clone(), battle.clone())));
+        Ok(())
+    }
+
+    fn reset(&self) -> Result<(usize, usize), RepositoryError> {
+        let mut monsters = self.monsters.write().expect("Monsters lock poisoned");
+        let mut battles = self.battles.write().expect("Battles lock poisoned");
+        let counts = (monsters.len(), battles.len());
+        let monster_ids: Vec<String> = monsters.keys().cloned().collect();
+        for monster_id in monster_ids {
+            self.remove_monster(&mut monsters, &mut battles, &monster_id, true).expect("Existing monsters are removed with their battles");
+        }
+        battles.clear();
+        Ok(counts)
+    }
+}
+
+// Without Postgres there are no statistics to take, snapshots stay empty.
+impl DbHealthRepository for InMemoryRepository {
+    fn take_db_stats_snapshot(&self, keep_since: NaiveDateTime) -> Result<Vec<TableStats>, RepositoryError> {
+        self.db_table_stats.write().expect("Database statistics lock poisoned").retain(|stats| stats.taken_at >= keep_since);
+        Ok(Vec::new())
+    }
+
+    fn get_db_stats_since(&self, since: NaiveDateTime) -> Vec<TableStats> {
+        self.db_table_stats.read().expect("Database statistics lock poisoned").iter().filter(|stats| stats.taken_at >= since).cloned().collect()
+    }
+}
+
+impl ExportRepository for InMemoryRepository {
+    fn create_export(&self, kind: ExportKind, expires_at: NaiveDateTime) -> Result<Export, RepositoryError> {
+        let now = Utc::now().naive_utc();
+        let mut exports = self.exports.write().expect("Exports lock poisoned");
+        exports.retain(|_, (export, _)| export.expires_at > now);
+        let data: Vec<serde_json::Value> = match kind {
+            ExportKind::Monsters => {
+                let mut monsters: Vec<Monster> = self.get_monsters();
+                monsters.sort_by(|a, b| a.id.cmp(&b.id));
+                monsters.iter().map(|monster| serde_json::to_value(monster).expect("Monsters serialize to JSON")).collect()
+            }
+            ExportKind::Battles => {
+                let mut battles: Vec<Battle> = self.get_battles();
+                battles.sort_by(|a, b| a.id.cmp(&b.id));
+                battles.iter().map(|battle| serde_json::to_value(battle).expect("Battles serialize to JSON")).collect()
+            }
+        };
+        let export = Export { id: uuid::Uuid::new_v4().to_string(), kind, total: data.len() as i32, created_at: now, expires_at };
+        exports.insert(export.id.clone(), (export.clone(), data));
+        Ok(export)
+    }
+
+    fn get_export_page(&self, export_id: &str, after: i32, limit: i64) -> Option<(Export, Vec<serde_json::Value>)> {
+        let exports = self.exports.read().expect("Exports lock poisoned");
+        let (export, data) = exports.get(export_id).filter(|(export, _)| export.expires_at > Utc::now().naive_utc())?;
+        Some((export.clone(), data.iter().skip(after.max(0) as usize).take(limit.max(0) as usize).cloned().collect()))
+    }
+}
+
+impl NoteRepository for InMemoryRepository {
+    fn get_notes(&self, owner: &str, monster_id: Option<&str>, search: Option<&str>, offset: i64, limit: i64) -> (Vec<MonsterNote>, i64) {
+        let search = search.map(str::to_lowercase);
+        let mut notes: Vec<MonsterNote> = self.notes
+            .read()
+            .expect("Notes lock poisoned")
+            .values()
+            .filter(|note| note.owner == owner && monster_id.is_none_or(|monster_id| note.monster_id == monster_id))
+            .filter(|note| search.as_deref().is_none_or(|search| note.body.to_lowercase().contains(search)))
+            .cloned()
+            .collect();
+        notes.sort_by(|a, b| b.pinned.cmp(&a.pinned).then(b.created_at.cmp(&a.created_at)).then(a.id.cmp(&b.id)));
+        let total = notes.len() as i64;
+        (notes.into_iter().skip(offset.max(0) as usize).take(limit.max(0) as usize).collect(), total)
+    }
+
+    fn get_note_by_id(&self, owner: &str, note_id: &str) -> Option<MonsterNote> {
+        self.notes.read().expect("Notes lock poisoned").get(note_id).filter(|note| note.owner == owner).cloned()
+    }
+
+    fn create_note(&self, note: MonsterNote) -> Result<MonsterNote, RepositoryError> {
+        let note = MonsterNote {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            ..note
+        };
+        self.notes.write().expect("Notes lock poisoned").insert(note.id.clone(), note.clone());
+        Ok(note)
+    }
+
+    fn update_note_by_id(&self, owner: &str, note_id: &str, note: MonsterNote) -> Result<Option<MonsterNote>, RepositoryError> {
+        let mut notes = self.notes.write().expect("Notes lock poisoned");
+        let existing_note = match notes.get_mut(note_id).filter(|existing_note| existing_note.owner == owner) {
+            Some(existing_note) => existing_note,
+            None => return Ok(None),
+        };
+        existing_note.body = note.body;
+        existing_note.pinned = note.pinned;
+        existing_note.battle_ids = note.battle_ids;
+        existing_note.updated_at = Some(Utc::now().naive_utc());
+        Ok(Some(existing_note.clone()))
+    }
+
+    fn delete_note_by_id(&self, owner: &str, note_id: &str) -> Option<usize> {
+        let mut notes = self.notes.write().expect("Notes lock poisoned");
+        notes.get(note_id).filter(|note| note.owner == owner)?;
+        notes.remove(note_id).map(|_| 1)
+    }
+}
+
+impl JobRepository for InMemoryRepository {
+    fn create_job(&self, kind: &str, total: i32) -> Result<Job, RepositoryError> {
+        let job = Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind: kind.to_string(),
+            status: JobStatus::Queued,
+            total,
+            processed: 0,
+            failed: 0,
+            result: None,
+            error: None,
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            finished_at: None,
+        };
+        self.jobs.write().expect("Jobs lock poisoned").insert(job.id.clone(), job.clone());
+        Ok(job)
+    }
+
+    fn get_job_by_id(&self, job_id: &str) -> Option<Job> {
+        self.jobs.read().expect("Jobs lock poisoned").get(job_id).cloned()
+    }
+
+    fn start_job(&self, job_id: &str) -> Result<(), RepositoryError> {
+        if let Some(job) = self.jobs.write().expect("Jobs lock poisoned").get_mut(job_id) {
+            job.status = JobStatus::Running;
+            job.updated_at = Some(Utc::now().naive_utc());
+        }
+        Ok(())
+    }
+
+    fn update_job_progress(&self, job_id: &str, processed: i32, failed: i32) -> Result<(), RepositoryError> {
+        if let Some(job) = self.jobs.write().expect("Jobs lock poisoned").get_mut(job_id) {
+            job.processed = processed;
+            job.failed = failed;
+            job.updated_at = Some(Utc::now().naive_utc());
+        }
+        Ok(())
+    }
+
+    fn finish_job(&self, job_id: &str, outcome: Result<serde_json::Value, String>) -> Result<(), RepositoryError> {
+        if let Some(job) = self.jobs.write().expect("Jobs lock poisoned").get_mut(job_id) {
+            let now = Some(Utc::now().naive_utc());
+            (job.status, job.result, job.error) = match outcome {
+                Ok(result) => (JobStatus::Succeeded, Some(result), None),
+                Err(error) => (JobStatus::Failed, None, Some(error)),
+            };
+            job.updated_at = now;
+            job.finished_at = now;
+        }
+        Ok(())
+    }
+}
+
+impl SeasonRepository for InMemoryRepository {
+    fn get_seasons(&self) -> Vec<Season> {
+        let mut seasons: Vec<Season> = self.seasons.read().expect("Seasons lock poisoned").values().cloned().collect();
+        seasons.sort_by_key(|season| std::cmp::Reverse(season.started_at));
+        seasons
+    }
+
+    fn get_season_by_id(&self, season_id: &str) -> Option<Season> {
+        self.seasons.read().expect("Seasons lock poisoned").get(season_id).cloned()
+    }
+
+    fn get_open_season(&self) -> Option<Season> {
+        self.seasons.read().expect("Seasons lock poisoned").values().find(|season| season.ended_at.is_none()).cloned()
+    }
+
+    fn open_season(&self, season: Season) -> Result<(Season, Option<Season>), RepositoryError> {
+        let closed_season = match self.get_open_season() {
+            Some(open_season) => self.close_season(&open_season.id)?,
+            None => None,
+        };
+        self.seasons.write().expect("Seasons lock poisoned").insert(season.id.clone(), season.clone());
+        Ok((season, closed_season))
+    }
+
+    fn close_season(&self, season_id: &str) -> Result<Option<Season>, RepositoryError> {
+        let Some(season) = self.get_season_by_id(season_id) else {
+            return Ok(None);
+        };
+        if season.ended_at.is_some() {
+            return Ok(Some(season));
+        }
+        let final_standings = standings(season_id, &self.get_battles());
+        self.season_standings.write().expect("Season standings lock poisoned").insert(season_id.to_string(), final_standings);
+        let ended_season = Season { ended_at: Some(Utc::now().naive_utc()), ..season };
+        self.seasons.write().expect("Seasons lock poisoned").insert(season_id.to_string(), ended_season.clone());
+        Ok(Some(ended_season))
+    }
+
+    fn get_season_standings(&self, season_id: &str) -> Vec<SeasonStanding> {
+        match self.get_season_by_id(season_id) {
+            Some(season) if season.ended_at.is_some() => self.season_standings.read().expect("Season standings lock poisoned").get(season_id).cloned().unwrap_or_default(),
+            Some(_) => standings(season_id, &self.get_battles()),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl EvolutionRepository for InMemoryRepository {
+    fn get_evolutions(&self) -> Vec<Evolution> {
+        let mut evolutions: Vec<Evolution> = self.evolutions.read().expect("Evolutions lock poisoned").values().cloned().collect();
+        evolutions.sort_by(|a, b| a.species.cmp(&b.species));
+        evolutions
+    }
+
+    fn save_evolution(&self, evolution: Evolution) -> Result<Evolution, RepositoryError> {
+        let evolution = Evolution { updated_at: Some(Utc::now().naive_utc()), ..evolution };
+        self.evolutions.write().expect("Evolutions lock poisoned").insert(evolution.species.clone(), evolution.clone());
+        Ok(evolution)
+    }
+
+    fn delete_evolution(&self, species: &str) -> Result<Option<usize>, RepositoryError> {
+        Ok(self.evolutions.write().expect("Evolutions lock poisoned").remove(species).map(|_| 1))
+    }
+
+    fn evolve_monster(&self, monster_id: &str) -> Result<Monster, EvolveMonsterError> {
+        let mut monsters = self.monsters.write().expect("Monsters lock poisoned");
+        let monster = monsters.get_mut(monster_id).ok_or(EvolveMonsterError::NotFound)?;
+        let evolution = self.evolutions.read().expect("Evolutions lock poisoned").get(&monster.name).cloned().ok_or(EvolveMonsterError::NoEvolution)?;
+        let wins = self.battles.read().expect("Battles lock poisoned").values().filter(|battle| battle.winner.as_deref() == Some(monster_id)).count();
+        if let Some(requirement) = evolution.unmet_requirement(monster, wins as i64) {
+            return Err(EvolveMonsterError::Unmet(requirement));
+        }
+        evolution.evolve(monster);
+        Ok(monster.clone())
+    }
+}
+
+impl TrainingRepository for InMemoryRepository {
+    fn get_training(&self, monster_id: &str) -> Option<MonsterTraining> {
+        self.trainings.read().expect("Trainings lock poisoned").get(monster_id).cloned()
+    }
+
+    fn allocate_training_points(&self, monster_id: &str, points: Stats) -> Result<MonsterTraining, TrainingError> {
+        self.update_training(monster_id, |training| training.allocate(&points, &balance::current()))
+    }
+
+    fn set_individual_values(&self, monster_id: &str, individual_values: Stats) -> Result<MonsterTraining, TrainingError> {
+        self.update_training(monster_id, |training| training.set_individual_values(individual_values))
+    }
+}
+
+impl AchievementRepository for InMemoryRepository {
+    fn get_monster_achievements(&self, monster_id: &str) -> Vec<Achievement> {
+        let mut achievements: Vec<Achievement> = self.achievements.read().expect("Achievements lock poisoned").iter().filter(|achievement| achievement.monster_id.as_deref() == Some(monster_id)).cloned().collect();
+        achievements.sort_by_key(|achievement| achievement.earned_at);
+        achievements
+    }
+
+    fn get_trainer_achievements(&self, trainer_id: &str) -> Vec<Achievement> {
+        let mut achievements: Vec<Achievement> = self.achievements.read().expect("Achievements lock poisoned").iter().filter(|achievement| achievement.trainer_id.as_deref() == Some(trainer_id)).cloned().collect();
+        achievements.sort_by_key(|achievement| achievement.earned_at);
+        achievements
+    }
+}
+
+impl TrainerRepository for InMemoryRepository {
+    fn get_trainer_by_id(&self, trainer_id: &str) -> Option<Trainer> {
+        self.trainers.read().expect("Trainers lock poisoned").get(trainer_id).cloned()
+    }
+
+    fn get_trainer_by_api_key(&self, api_key_id: &str) -> Option<Trainer> {
+        self.trainers.read().expect("Trainers lock poisoned").values().find(|trainer| trainer.api_key_id == api_key_id).cloned()
+    }
+
+    fn create_trainer(&self, trainer: Trainer, api_key: ApiKey) -> Result<(Trainer, ApiKey), RepositoryError> {
+        let mut trainers = self.trainers.write().expect("Trainers lock poisoned");
+        if trainers.values().any(|existing_trainer| existing_trainer.name == trainer.name) {
+            return Err(RepositoryError::Constraint(Constraint::TrainerNameUnique));
+        }
+        let api_key = self.create_api_key(api_key)?;
+        let trainer = Trainer { id: uuid::Uuid::new_v4().to_string(), api_key_id: api_key.id.clone(), created_at: api_key.created_at, ..trainer };
+        trainers.insert(trainer.id.clone(), trainer.clone());
+        Ok((trainer, api_key))
+    }
+
+    fn get_trainer_monsters(&self, trainer_id: &str) -> Vec<Monster> {
+        let mut monsters: Vec<Monster> = self.get_monsters().into_iter().filter(|monster| monster.owner_id.as_deref() == Some(trainer_id)).collect();
+        monsters.sort_by_key(|monster| monster.created_at);
+        monsters
+    }
+
+    fn transfer_monster(&self, monster_id: &str, from: &str, to: &str) -> Result<Monster, OwnershipError> {
+        let mut monsters = self.monsters.write().expect("Monsters lock poisoned");
+        let monster = monsters.get_mut(monster_id).ok_or(OwnershipError::NotFound)?;
+        if monster.owner_id.as_deref() != Some(from) {
+            return Err(OwnershipError::Forbidden);
+        }
+        if from == to {
+            return Err(OwnershipError::Conflict("The monster already belongs to the trainer".to_string()));
+        }
+        if !self.trainers.read().expect("Trainers lock poisoned").contains_key(to) {
+            return Err(RepositoryError::Constraint(Constraint::MonsterOwnerExists).into());
+        }
+        monster.owner_id = Some(to.to_string());
+        monster.updated_at = Some(Utc::now().naive_utc());
+        Ok(monster.clone())
+    }
+
+    fn get_trades(&self, trainer_id: &str) -> Vec<Trade> {
+        let mut trades: Vec<Trade> = self.trades
+            .read()
+            .expect("Trades lock poisoned")
+            .values()
+            .filter(|trade| trade.proposer == trainer_id || trade.recipient == trainer_id)
+            .cloned()
+            .collect();
+        trades.sort_by_key(|trade| std::cmp::Reverse(trade.created_at));
+        trades
+    }
+
+    fn create_trade(&self, proposer: &str, offered_monster: &str, requested_monster: &str) -> Result<Trade, OwnershipError> {
+        let monsters = self.monsters.read().expect("Monsters lock poisoned");
+        let (Some(offered), Some(requested)) = (monsters.get(offered_monster), monsters.get(requested_monster)) else {
+            return Err(OwnershipError::NotFound);
+        };
+        if offered.owner_id.as_deref() != Some(proposer) {
+            return Err(OwnershipError::Forbidden);
+        }
+        let recipient = match &requested.owner_id {
+            Some(recipient) if recipient != proposer => recipient.clone(),
+            _ => return Err(OwnershipError::Conflict("The requested monster must belong to another trainer".to_string())),
+        };
+        let trade = Trade {
+            id: uuid::Uuid::new_v4().to_string(),
+            proposer: proposer.to_string(),
+            recipient,
+            offered_monster: offered_monster.to_string(),
+            requested_monster: requested_monster.to_string(),
+            status: TradeStatus::Pending,
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+        };
+        self.trades.write().expect("Trades lock poisoned").insert(trade.id.clone(), trade.clone());
+        Ok(trade)
+    }
+
+    fn answer_trade(&self, trade_id: &str, trainer_id: &str, accept: bool) -> Result<Trade, OwnershipError> {
+        let mut monsters = self.monsters.write().expect("Monsters lock poisoned");
+        let mut trades = self.trades.write().expect("Trades lock poisoned");
+        let trade = trades.get_mut(trade_id).ok_or(OwnershipError::NotFound)?;
+        let status = trade_answer(trade, trainer_id, accept)?;
+        if status == TradeStatus::Accepted {
+            let owned_by = |monster_id: &str, owner: &str| monsters.get(monster_id).is_some_and(|monster| monster.owner_id.as_deref() == Some(owner));
+            if !owned_by(&trade.offered_monster, &trade.proposer) || !owned_by(&trade.requested_monster, &trade.recipient) {
+                return Err(OwnershipError::Conflict("The monsters changed hands since the trade was proposed".to_string()));
+            }
+            for (monster_id, owner) in [(&trade.offered_monster, &trade.recipient), (&trade.requested_monster, &trade.proposer)] {
+                let monster = monsters.get_mut(monster_id).expect("Traded monsters were checked");
+                monster.owner_id = Some(owner.clone());
+                monster.updated_at = Some(Utc::now().naive_utc());
+            }
+        }
+        trade.status = status;
+        trade.updated_at = Some(Utc::now().naive_utc());
+        Ok(trade.clone())
+    }
+
+    fn get_region_stats(&self) -> Vec<RegionStats> {
+        let trainer_regions: HashMap<String, String> = self.trainers.read().expect("Trainers lock poisoned")
+            .values()
+            .filter_map(|trainer| Some((trainer.id.clone(), trainer.region.clone()?)))
+            .collect();
+        let mut regions: BTreeMap<String, RegionStats> = BTreeMap::new();
+        for region in trainer_regions.values() {
+            regions.entry(region.clone()).or_insert_with(|| RegionStats::empty(region)).trainers += 1;
+        }
+        for monster in self.monsters.read().expect("Monsters lock poisoned").values() {
+            if let Some(region) = monster.owner_id.as_ref().and_then(|owner_id| trainer_regions.get(owner_id)) {
+                regions.entry(region.clone()).or_insert_with(|| RegionStats::empty(region)).monsters += 1;
+            }
+        }
+        for battle in self.battles.read().expect("Battles lock poisoned").values() {
+            if let Some(region) = &battle.region {
+                regions.entry(region.clone()).or_insert_with(|| RegionStats::empty(region)).battles += 1;
+            }
+        }
+        regions.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::monster::Stats;
+
+    use super::*;
+
+    fn new_monster(name: &str, stats: Stats) -> Monster {
+        Monster {
+            id: String::new(),
+            name: name.to_string(),
+            image_url: "https://loremflickr.com/640/480".to_string(),
+            stats,
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        }
+    }
+
+    fn new_battle(monster_a: &Monster, monster_b: &Monster) -> Battle {
+        Battle {
+            id: String::new(),
+            monster_a: monster_a.id.clone(),
+            monster_b: monster_b.id.clone(),
+            winner: Some(monster_a.id.clone()),
+            created_at: None,
+            updated_at: None,
+            outcome: BattleOutcome::Win,
+            manual: false,
+            season_id: None,
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_should_create_update_and_get_monsters() {
+        let repository = InMemoryRepository::new();
+        let stats = Stats { attack: 40, defense: 20, hp: 50, speed: 80 };
+        let monster = repository.create_monster(new_monster("fire drake", stats)).unwrap();
+
+        let updated = repository.update_monster_by_id(&monster.id, new_monster("ice drake", stats)).unwrap().unwrap();
+
+        assert_eq!(updated.id, monster.id);
+        assert!(updated.updated_at.is_some());
+        assert_eq!(repository.get_monster_by_id(&monster.id).unwrap().name, "ice drake");
+        assert_eq!(repository.search_monsters("ICE", 10).len(), 1);
+        assert!(repository.update_monster_by_id("99999", new_monster("ghost", stats)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_should_only_delete_monsters_with_battles_on_cascade() {
+        let repository = InMemoryRepository::new();
+        let stats = Stats { attack: 40, defense: 20, hp: 50, speed: 80 };
+        let monster_a = repository.create_monster(new_monster("monster-a", stats)).unwrap();
+        let monster_b = repository.create_monster(new_monster("monster-b", stats)).unwrap();
+        let battle = repository.create_battle(new_battle(&monster_a, &monster_b)).unwrap();
+
+        assert!(matches!(repository.delete_monster_by_id(&monster_b.id, false), Err(DeleteMonsterError::HasBattles)));
+        assert!(matches!(repository.delete_monster_by_id(&monster_b.id, true), Ok(1)));
+        assert!(repository.get_battle_by_id(&battle.id).is_none());
+        assert!(matches!(repository.delete_monster_by_id(&monster_b.id, true), Err(DeleteMonsterError::NotFound)));
+    }
+
+    #[test]
+    fn test_should_expand_battle_monsters() {
+        let repository = InMemoryRepository::new();
+        let stats = Stats { attack: 40, defense: 20, hp: 50, speed: 80 };
+        let monster_a = repository.create_monster(new_monster("monster-a", stats)).unwrap();
+        let monster_b = repository.create_monster(new_monster("monster-b", stats)).unwrap();
+        let battle = repository.create_battle(new_battle(&monster_a, &monster_b)).unwrap();
+
+        let expanded = repository.get_expanded_battle_by_id(&battle.id).unwrap();
+
+        assert_eq!(expanded.monster_b.map(|monster| monster.id), Some(monster_b.id));
+        assert_eq!(expanded.winner.map(|monster| monster.id), Some(monster_a.id));
+    }
+
+    #[test]
+    fn test_should_reject_writes_breaking_the_table_constraints() {
+        let repository = InMemoryRepository::new();
+        let stats = Stats { attack: 40, defense: 20, hp: 50, speed: 80 };
+        let monster_a = repository.create_monster(new_monster("monster-a", stats)).unwrap();
+        let monster_b = repository.create_monster(new_monster("monster-b", stats)).unwrap();
+        let outsider = repository.create_monster(new_monster("outsider", stats)).unwrap();
+        let constraint = |err| match err {
+            RepositoryError::Constraint(constraint) => Some(constraint),
+            RepositoryError::StaleVersion | RepositoryError::HasDependents(_) | RepositoryError::Database(_) => None,
+        };
+
+        assert_eq!(repository.create_monster(new_monster("  ", stats)).err().and_then(constraint), Some(Constraint::MonsterNameNotEmpty));
+        assert_eq!(repository.update_monster_by_id(&monster_a.id, new_monster("", stats)).err().and_then(constraint), Some(Constraint::MonsterNameNotEmpty));
+        assert_eq!(repository.create_monster(new_monster("brute", Stats { attack: i32::MAX, ..stats })).err().and_then(constraint), Some(Constraint::MonsterStatsInRange));
+
+        let battle = Battle { monster_b: "99999".to_string(), ..new_battle(&monster_a, &monster_b) };
+        assert_eq!(repository.create_battle(battle).err().and_then(constraint), Some(Constraint::BattleMonsterExists));
+        let battle = Battle { winner: Some(outsider.id.clone()), ..new_battle(&monster_a, &monster_b) };
+        assert_eq!(repository.create_battle(battle).err().and_then(constraint), Some(Constraint::BattleWinnerIsParticipant));
+        let battle = Battle { outcome: BattleOutcome::Draw, ..new_battle(&monster_a, &monster_b) };
+        assert_eq!(repository.create_battle(battle).err().and_then(constraint), Some(Constraint::BattleDrawHasNoWinner));
+        assert!(repository.get_battles().is_empty());
+    }
+}
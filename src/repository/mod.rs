@@ -1,4 +1,26 @@
 pub mod database;
+pub mod error;
 pub mod monster_repository;
 pub mod battle_repository;
+pub mod audit_repository;
+pub mod webhook_repository;
+pub mod job_repository;
+pub mod move_repository;
+pub mod item_repository;
+pub mod note_repository;
+pub mod rate_limit_repository;
+pub mod season_repository;
+pub mod evolution_repository;
+pub mod export_repository;
+pub mod training_repository;
+pub mod thumbnail_repository;
+pub mod trainer_repository;
+pub mod achievement_repository;
+pub mod backup_repository;
+pub mod idempotency_repository;
+pub mod challenge_repository;
+pub mod cors_repository;
+pub mod db_health_repository;
+pub mod memory_repository;
+pub mod request_scope;
 pub mod schema;
\ No newline at end of file
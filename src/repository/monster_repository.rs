@@ -1,67 +1,503 @@
+use std::time::Duration;
 use chrono::prelude::*;
 use diesel::prelude::*;
-use crate::models::monster::Monster;
+use diesel::dsl::sql;
+use diesel::expression::SqlLiteral;
+use diesel::pg::Pg;
+use diesel::sql_types::{Bool, Nullable, Text, Timestamp};
+use diesel::PgConnection;
+use crate::balance;
+use crate::cache;
+use crate::events::{MONSTER_CREATED, MONSTER_UPDATED};
+use crate::models::battle::Battle;
+use crate::models::duplicates::DuplicatePair;
+use crate::models::growth;
+use crate::models::monster::{ImportMode, ImportOutcome, MatchmakingCandidate, Monster, MonsterSearchResult};
+use crate::models::page::PageCursor;
 use crate::repository::schema::monsters::dsl::*;
+use crate::repository::schema::battles;
 use crate::repository::database::Database;
+use crate::repository::audit_repository;
+use crate::repository::achievement_repository;
+use crate::repository::error::{Constraint, RepositoryError};
 
-pub fn get_monsters(db: &Database) -> Vec<Monster> {
-    let mut connection = db.get_connection();
-    monsters
-        .load::<Monster>(&mut connection)
-        .expect("Error loading all monsters")
-}
+diesel::define_sql_function!(fn lower(text: diesel::sql_types::Text) -> diesel::sql_types::Text);
 
-pub fn create_monster(db: &Database, monster: Monster) -> Result<Monster, diesel::result::Error> {
-    let mut connection = db.get_connection();
-    let monster = Monster {
-        id: uuid::Uuid::new_v4().to_string(),
-        ..monster
-    };
-    diesel::insert_into(monsters)
-        .values(&monster)
-        .execute(&mut connection)
-        .expect("Error creating a new monster");
-    Ok(monster)
+#[derive(Debug)]
+pub enum DeleteMonsterError {
+    NotFound,
+    HasBattles,
+    Database(RepositoryError),
 }
 
-pub fn get_monster_by_id(db: &Database, monster_id: &str) -> Option<Monster> {
-    let mut connection = db.get_connection();
-    match monsters.find(monster_id).get_result::<Monster>(&mut connection) {
-        Ok(monster) => Some(monster),
-        Err(_) => None,
+// A battle foreign key firing means a battle was added after the `HasBattles` check.
+impl From<diesel::result::Error> for DeleteMonsterError {
+    fn from(err: diesel::result::Error) -> Self {
+        match RepositoryError::from(err) {
+            RepositoryError::Constraint(Constraint::BattleMonsterExists) => DeleteMonsterError::HasBattles,
+            err => DeleteMonsterError::Database(err),
+        }
     }
 }
 
-pub fn delete_monster_by_id(db: &Database, monster_id: &str) -> Option<usize> {
-    let mut connection = db.get_connection();
+/*
+Monster persistence used by the handlers. `Database` is the Diesel-backed implementation,
+`InMemoryRepository` keeps everything in memory for tests and Postgres-free setups.
+*/
+pub trait MonsterRepository: Send + Sync {
+    fn get_monsters(&self) -> Vec<Monster>;
+    // Up to `limit` monsters past `after` in `PageCursor` order, only those of `owner` and with `broken_image` when set.
+    fn get_monsters_page(&self, owner: Option<&str>, broken_image: Option<bool>, after: Option<&PageCursor>, limit: i64) -> Vec<Monster>;
+    fn get_monsters_by_ids(&self, monster_ids: &[String]) -> Vec<Monster>;
+    fn search_monsters(&self, search: &str, limit: i64) -> Vec<MonsterSearchResult>;
+    // Pairs of monsters with identical stats and a name trigram similarity of at least `min_similarity`.
+    fn find_duplicate_pairs(&self, min_similarity: f32) -> Vec<DuplicatePair>;
+    /*
+    Opponents closest in stats to the monster first, leaving out those it fought in its last `recent_battles` battles.
+    Opponents whose trainer plays from the region of the monster's trainer, or `region` when it has none, come first.
+    */
+    fn find_matchmaking_candidates(&self, monster_id: &str, recent_battles: i64, region: Option<&str>, limit: i64) -> Vec<MatchmakingCandidate>;
+    fn get_monster_by_id(&self, monster_id: &str) -> Option<Monster>;
+    fn create_monster(&self, monster: Monster) -> Result<Monster, RepositoryError>;
+    fn create_monsters(&self, new_monsters: Vec<Monster>) -> Result<Vec<Result<Monster, RepositoryError>>, RepositoryError>;
+    /*
+    Like `create_monsters`, except that with `mode` other than `Create` a monster of the same owner with the same external id,
+    or the same name ignoring case when the row has none, is updated or left alone instead. The oldest one wins among several.
+    */
+    fn import_monsters(&self, new_monsters: Vec<Monster>, mode: ImportMode) -> Result<Vec<Result<ImportOutcome, RepositoryError>>, RepositoryError>;
+    // Applies only to the version of the monster sent, bumping it, `RepositoryError::StaleVersion` otherwise.
+    fn update_monster_by_id(&self, monster_id: &str, monster: Monster) -> Result<Option<Monster>, RepositoryError>;
+    // Points the monster at an uploaded image, leaving the rest of it alone.
+    fn set_monster_image_url(&self, monster_id: &str, image_url: &str) -> Result<Option<Monster>, RepositoryError>;
+    // Levels the monster up once whatever its XP, monsters at the max level are returned as they are.
+    fn level_up_monster(&self, monster_id: &str) -> Result<Option<Monster>, RepositoryError>;
+    fn delete_monster_by_id(&self, monster_id: &str, cascade: bool) -> Result<usize, DeleteMonsterError>;
+    fn delete_monsters(&self, monster_ids: &[String], cascade: bool) -> Result<Vec<Result<usize, DeleteMonsterError>>, RepositoryError>;
+}
 
-    if let Ok(_existing_monster) = monsters.find(monster_id).get_result::<Monster>(&mut connection) {
-        let count = diesel::delete(monsters.find(monster_id))
-            .execute(&mut connection)
-            .expect("Error deleting monster by id");
+/*
+How long monster reads stay cached when a cache is configured. The writes of this repository and battles drop
+the values they change, this bounds how stale the changes made elsewhere get, like evolutions and transfers.
+*/
+pub const MONSTER_CACHE_TTL: Duration = Duration::from_secs(30);
+const MONSTERS_CACHE: &str = "monsters";
+const MONSTER_CACHE: &str = "monster";
 
-        Some(count)
-    } else {
-        None
+impl Database {
+    // Drops the cached list of monsters, along with the cached monsters of `monster_ids`.
+    pub(crate) fn invalidate_monsters<'a>(&self, monster_ids: impl IntoIterator<Item = &'a str>) {
+        if self.cache().is_none() {
+            return;
+        }
+        let keys: Vec<String> = std::iter::once(self.cache_key(MONSTERS_CACHE))
+            .chain(monster_ids.into_iter().map(|monster_id| self.cache_key(&format!("{}:{}", MONSTER_CACHE, monster_id))))
+            .collect();
+        cache::invalidate(self.cache(), &keys);
     }
 }
 
-pub fn update_monster_by_id(
-    db: &Database,
-    monster_id: &str,
-    mut monster: Monster,
-) -> Option<Monster> {
-    let mut connection = db.get_connection();
+// The creation date of the rows of `table` in `PageCursor` order, those without one first, as in its page index.
+fn page_key(table: &str) -> String {
+    format!("coalesce({}.created_at, '-infinity'::timestamp)", table)
+}
+
+pub(crate) fn page_order(table: &str) -> SqlLiteral<Timestamp> {
+    sql(&page_key(table))
+}
+
+// The rows of `table` past `after` in `page_order`.
+pub(crate) fn past_cursor<QS>(table: &str, after: &PageCursor) -> Box<dyn BoxableExpression<QS, Pg, SqlType = Bool>> {
+    Box::new(
+        sql::<Bool>(&format!("({}, {}.id) > (coalesce(", page_key(table), table))
+            .bind::<Nullable<Timestamp>, _>(after.created_at)
+            .sql(", '-infinity'::timestamp), ")
+            .bind::<Text, _>(after.id.clone())
+            .sql(")"),
+    )
+}
+
+impl MonsterRepository for Database {
+    fn get_monsters(&self) -> Vec<Monster> {
+        cache::get_or_load(self.cache(), MONSTERS_CACHE, &self.cache_key(MONSTERS_CACHE), MONSTER_CACHE_TTL, || {
+            let mut connection = self.get_connection();
+            monsters
+                .load::<Monster>(&mut connection)
+                .expect("Error loading all monsters")
+        })
+    }
+
+    fn get_monsters_page(&self, owner: Option<&str>, broken_image: Option<bool>, after: Option<&PageCursor>, limit: i64) -> Vec<Monster> {
+        let mut connection = self.get_connection();
+        let mut query = monsters.order((page_order("monsters"), id)).limit(limit).into_boxed();
+        if let Some(owner) = owner {
+            query = query.filter(owner_id.eq(owner));
+        }
+        if let Some(broken_image) = broken_image {
+            query = query.filter(image_broken.eq(broken_image));
+        }
+        if let Some(after) = after {
+            query = query.filter(past_cursor("monsters", after));
+        }
+        query
+            .load::<Monster>(&mut connection)
+            .expect("Error loading a page of monsters")
+    }
+
+    fn get_monsters_by_ids(&self, monster_ids: &[String]) -> Vec<Monster> {
+        let mut connection = self.get_connection();
+        monsters
+            .filter(id.eq_any(monster_ids))
+            .load::<Monster>(&mut connection)
+            .expect("Error loading monsters by ids")
+    }
+
+    /*
+    Matches whole words through the full-text index and typos or partial words through trigrams,
+    ranking by the sum of both scores.
+    */
+    fn search_monsters(&self, search: &str, limit: i64) -> Vec<MonsterSearchResult> {
+        let mut connection = self.get_connection();
+        diesel::sql_query(
+            "SELECT monsters.*, \
+                (ts_rank(to_tsvector('simple', name), plainto_tsquery('simple', $1)) \
+                    + greatest(similarity(name, $1), word_similarity($1, name)))::real AS rank \
+            FROM monsters \
+            WHERE to_tsvector('simple', name) @@ plainto_tsquery('simple', $1) OR name % $1 OR $1 <% name \
+            ORDER BY rank DESC, name \
+            LIMIT $2"
+        )
+            .bind::<diesel::sql_types::Text, _>(search)
+            .bind::<diesel::sql_types::BigInt, _>(limit)
+            .load::<MonsterSearchResult>(&mut connection)
+            .expect("Error searching monsters")
+    }
+
+    fn find_duplicate_pairs(&self, min_similarity: f32) -> Vec<DuplicatePair> {
+        let mut connection = self.get_connection();
+        diesel::sql_query(
+            "SELECT a.id AS monster_a, b.id AS monster_b, similarity(a.name, b.name) AS similarity \
+            FROM monsters a \
+            JOIN monsters b ON a.attack = b.attack AND a.defense = b.defense AND a.hp = b.hp AND a.speed = b.speed AND a.id < b.id \
+            WHERE similarity(a.name, b.name) >= $1 \
+            ORDER BY a.id, b.id"
+        )
+            .bind::<diesel::sql_types::Float4, _>(min_similarity)
+            .load::<DuplicatePair>(&mut connection)
+            .expect("Error finding duplicate monsters")
+    }
+
+    fn find_matchmaking_candidates(&self, monster_id: &str, recent_battles: i64, region: Option<&str>, limit: i64) -> Vec<MatchmakingCandidate> {
+        let mut connection = self.get_connection();
+        diesel::sql_query(
+            "WITH recent_opponents AS ( \
+                SELECT CASE WHEN monster_a = $1 THEN monster_b ELSE monster_a END AS opponent \
+                FROM battles \
+                WHERE monster_a = $1 OR monster_b = $1 \
+                ORDER BY created_at DESC NULLS LAST \
+                LIMIT $2 \
+            ) \
+            SELECT monsters.*, \
+                abs(monsters.attack - target.attack) + abs(monsters.defense - target.defense) \
+                    + abs(monsters.hp - target.hp) + abs(monsters.speed - target.speed) AS distance, \
+                coalesce(owners.region = coalesce(target_owner.region, $3), false) AS same_region \
+            FROM monsters \
+            JOIN monsters target ON target.id = $1 \
+            LEFT JOIN trainers owners ON owners.id = monsters.owner_id \
+            LEFT JOIN trainers target_owner ON target_owner.id = target.owner_id \
+            WHERE monsters.id <> $1 AND monsters.id NOT IN (SELECT opponent FROM recent_opponents) \
+            ORDER BY same_region DESC, distance, monsters.id \
+            LIMIT $4"
+        )
+            .bind::<diesel::sql_types::Text, _>(monster_id)
+            .bind::<diesel::sql_types::BigInt, _>(recent_battles)
+            .bind::<diesel::sql_types::Nullable<diesel::sql_types::Text>, _>(region)
+            .bind::<diesel::sql_types::BigInt, _>(limit)
+            .load::<MatchmakingCandidate>(&mut connection)
+            .expect("Error finding matchmaking candidates")
+    }
+
+    fn create_monster(&self, monster: Monster) -> Result<Monster, RepositoryError> {
+        let mut connection = self.get_connection();
+        let created_monster = insert_monster(&mut connection, monster)?;
+        self.invalidate_monsters([created_monster.id.as_str()]);
+        self.emit(MONSTER_CREATED, &created_monster.id, &created_monster);
+        Ok(created_monster)
+    }
+
+    /*
+    Inserts all monsters in one transaction. Each insert runs in its own savepoint,
+    so a failing monster is reported in its slot without discarding the others.
+    The trainers owning the monsters may earn the collector achievement.
+    */
+    fn create_monsters(&self, new_monsters: Vec<Monster>) -> Result<Vec<Result<Monster, RepositoryError>>, RepositoryError> {
+        let mut connection = self.get_connection();
+        let results = connection.transaction(|connection| {
+            let results: Vec<Result<Monster, RepositoryError>> = new_monsters
+                .into_iter()
+                .map(|monster| insert_monster(connection, monster).map_err(RepositoryError::from))
+                .collect();
+            let mut owners: Vec<&String> = results.iter().flatten().filter_map(|monster| monster.owner_id.as_ref()).collect();
+            owners.sort();
+            owners.dedup();
+            for owner in owners {
+                achievement_repository::earn_collector_achievement(connection, owner)?;
+            }
+            Ok::<_, diesel::result::Error>(results)
+        })?;
+        self.invalidate_monsters(results.iter().flatten().map(|monster| monster.id.as_str()));
+        for monster in results.iter().flatten() {
+            self.emit(MONSTER_CREATED, &monster.id, monster);
+        }
+        Ok(results)
+    }
+
+    fn import_monsters(&self, new_monsters: Vec<Monster>, mode: ImportMode) -> Result<Vec<Result<ImportOutcome, RepositoryError>>, RepositoryError> {
+        if mode == ImportMode::Create {
+            return Ok(self.create_monsters(new_monsters)?.into_iter().map(|monster| monster.map(ImportOutcome::Created)).collect());
+        }
+        let mut connection = self.get_connection();
+        let results = connection.transaction(|connection| {
+            let results: Vec<Result<ImportOutcome, RepositoryError>> = new_monsters
+                .into_iter()
+                .map(|monster| import_monster(connection, monster, mode).map_err(RepositoryError::from))
+                .collect();
+            let mut owners: Vec<&String> = results
+                .iter()
+                .flatten()
+                .filter_map(|outcome| match outcome {
+                    ImportOutcome::Created(monster) => monster.owner_id.as_ref(),
+                    _ => None,
+                })
+                .collect();
+            owners.sort();
+            owners.dedup();
+            for owner in owners {
+                achievement_repository::earn_collector_achievement(connection, owner)?;
+            }
+            Ok::<_, diesel::result::Error>(results)
+        })?;
+        self.invalidate_monsters(results.iter().flatten().filter_map(|outcome| match outcome {
+            ImportOutcome::Created(monster) | ImportOutcome::Updated(monster) => Some(monster.id.as_str()),
+            ImportOutcome::Skipped => None,
+        }));
+        for outcome in results.iter().flatten() {
+            match outcome {
+                ImportOutcome::Created(monster) => self.emit(MONSTER_CREATED, &monster.id, monster),
+                ImportOutcome::Updated(monster) => self.emit(MONSTER_UPDATED, &monster.id, monster),
+                ImportOutcome::Skipped => {}
+            }
+        }
+        Ok(results)
+    }
+
+    fn get_monster_by_id(&self, monster_id: &str) -> Option<Monster> {
+        let key = self.cache_key(&format!("{}:{}", MONSTER_CACHE, monster_id));
+        cache::get_or_load(self.cache(), MONSTER_CACHE, &key, MONSTER_CACHE_TTL, || {
+            let mut connection = self.get_connection();
+            monsters.find(monster_id).get_result::<Monster>(&mut connection).ok()
+        })
+    }
+
+    /*
+    A monster referenced by battles can only be deleted with `cascade`,
+    in which case its battles are deleted in the same transaction.
+    */
+    fn delete_monster_by_id(&self, monster_id: &str, cascade: bool) -> Result<usize, DeleteMonsterError> {
+        let mut connection = self.get_connection();
+        let count = remove_monster(&mut connection, monster_id, cascade)?;
+        self.invalidate_monsters([monster_id]);
+        if cascade {
+            self.invalidate_battles(std::iter::empty());
+        }
+        Ok(count)
+    }
+
+    // Deletes every id in one transaction, reporting the outcome of each one like `create_monsters`.
+    fn delete_monsters(&self, monster_ids: &[String], cascade: bool) -> Result<Vec<Result<usize, DeleteMonsterError>>, RepositoryError> {
+        let mut connection = self.get_connection();
+        let results = connection.transaction(|connection| {
+            Ok::<_, diesel::result::Error>(monster_ids
+                .iter()
+                .map(|monster_id| remove_monster(connection, monster_id, cascade))
+                .collect())
+        })?;
+        self.invalidate_monsters(monster_ids.iter().map(String::as_str));
+        if cascade {
+            self.invalidate_battles(std::iter::empty());
+        }
+        Ok(results)
+    }
+
+    fn update_monster_by_id(
+        &self,
+        monster_id: &str,
+        mut monster: Monster,
+    ) -> Result<Option<Monster>, RepositoryError> {
+        let mut connection = self.get_connection();
 
-    if let Ok(_existing_monster) = monsters.find(monster_id).get_result::<Monster>(&mut connection) {
-        monster.updated_at = Some(Utc::now().naive_utc());
-        let updated_monster = diesel::update(monsters.find(monster_id))
-            .set(&monster)
-            .get_result::<Monster>(&mut connection)
-            .expect("Error updating monster by id");
+        let updated_monster = connection.transaction(|connection| {
+            let existing_monster = match monsters.find(monster_id).get_result::<Monster>(connection).optional()? {
+                Some(existing_monster) => existing_monster,
+                None => return Ok(None),
+            };
+            monster.updated_at = Some(Utc::now().naive_utc());
+            monster.last_battle_at = None;
+            monster.image_broken = existing_monster.image_broken && existing_monster.image_url == monster.image_url;
+            monster.external_id = monster.external_id.or(existing_monster.external_id.clone());
+            // Checked again by the update itself, another one may have been committed since the read.
+            let Some(updated_monster) = diesel::update(monsters.find(monster_id).filter(version.eq(monster.version)))
+                .set((&monster, version.eq(version + 1)))
+                .get_result::<Monster>(connection)
+                .optional()?
+            else {
+                return Err(RepositoryError::StaleVersion);
+            };
+            audit_repository::record(connection, "monster", monster_id, "update", Some(&existing_monster), Some(&updated_monster))?;
+
+            Ok(Some(updated_monster))
+        })?;
+        self.invalidate_monsters([monster_id]);
+        if let Some(updated_monster) = &updated_monster {
+            self.emit(MONSTER_UPDATED, monster_id, updated_monster);
+        }
+        Ok(updated_monster)
+    }
 
-        Some(updated_monster)
-    } else {
-        None
+    fn set_monster_image_url(&self, monster_id: &str, new_image_url: &str) -> Result<Option<Monster>, RepositoryError> {
+        let mut connection = self.get_connection();
+        let updated_monster = connection.transaction(|connection| {
+            let Some(existing_monster) = monsters.find(monster_id).for_update().get_result::<Monster>(connection).optional()? else {
+                return Ok(None);
+            };
+            let updated_monster = diesel::update(monsters.find(monster_id))
+                .set((image_url.eq(new_image_url), image_broken.eq(false), updated_at.eq(Some(Utc::now().naive_utc()))))
+                .get_result::<Monster>(connection)?;
+            audit_repository::record(connection, "monster", monster_id, "update", Some(&existing_monster), Some(&updated_monster))?;
+            Ok::<_, diesel::result::Error>(Some(updated_monster))
+        })?;
+        self.invalidate_monsters([monster_id]);
+        if let Some(updated_monster) = &updated_monster {
+            self.emit(MONSTER_UPDATED, monster_id, updated_monster);
+        }
+        Ok(updated_monster)
     }
+
+    fn level_up_monster(&self, monster_id: &str) -> Result<Option<Monster>, RepositoryError> {
+        let mut connection = self.get_connection();
+        let leveled_monster = connection.transaction(|connection| {
+            let Some(existing_monster) = monsters.find(monster_id).for_update().get_result::<Monster>(connection).optional()? else {
+                return Ok(None);
+            };
+            let mut monster = existing_monster.clone();
+            if !growth::level_up(&mut monster, &balance::current()) {
+                return Ok(Some((monster, false)));
+            }
+            let leveled_monster = save_progress(connection, monster)?;
+            audit_repository::record(connection, "monster", monster_id, "update", Some(&existing_monster), Some(&leveled_monster))?;
+            Ok::<_, diesel::result::Error>(Some((leveled_monster, true)))
+        })?;
+        self.invalidate_monsters([monster_id]);
+        if let Some((leveled_monster, true)) = &leveled_monster {
+            self.emit(MONSTER_UPDATED, monster_id, leveled_monster);
+        }
+        Ok(leveled_monster.map(|(leveled_monster, _)| leveled_monster))
+    }
+}
+
+// Stores the level, XP and stats of the monster, which updates leave alone.
+pub(crate) fn save_progress(connection: &mut PgConnection, monster: Monster) -> Result<Monster, diesel::result::Error> {
+    diesel::update(monsters.find(&monster.id))
+        .set((
+            attack.eq(monster.stats.attack),
+            defense.eq(monster.stats.defense),
+            hp.eq(monster.stats.hp),
+            speed.eq(monster.stats.speed),
+            level.eq(monster.level),
+            xp.eq(monster.xp),
+            updated_at.eq(monster.updated_at),
+        ))
+        .get_result::<Monster>(connection)
+}
+
+fn insert_monster(connection: &mut PgConnection, monster: Monster) -> Result<Monster, diesel::result::Error> {
+    let monster = Monster {
+        id: uuid::Uuid::new_v4().to_string(),
+        last_battle_at: None,
+        level: 1,
+        xp: 0,
+        image_broken: false,
+        version: 1,
+        ..monster
+    };
+    connection.transaction(|connection| {
+        diesel::insert_into(monsters)
+            .values(&monster)
+            .execute(connection)?;
+        audit_repository::record(connection, "monster", &monster.id, "create", None, Some(&monster))?;
+        Ok(monster)
+    })
+}
+
+// Inserts the monster unless it duplicates one, which is then locked and updated in place with `ImportMode::Upsert`.
+fn import_monster(connection: &mut PgConnection, monster: Monster, mode: ImportMode) -> Result<ImportOutcome, diesel::result::Error> {
+    connection.transaction(|connection| {
+        let duplicates = monsters.filter(owner_id.is_not_distinct_from(monster.owner_id.clone())).order((created_at.asc(), id.asc()));
+        let duplicate = match &monster.external_id {
+            Some(monster_external_id) => duplicates.filter(external_id.eq(monster_external_id)).for_update().first::<Monster>(connection).optional()?,
+            None => duplicates.filter(lower(name).eq(monster.name.trim().to_lowercase())).for_update().first::<Monster>(connection).optional()?,
+        };
+        let Some(existing_monster) = duplicate else {
+            return insert_monster(connection, monster).map(ImportOutcome::Created);
+        };
+        if mode == ImportMode::SkipDuplicates {
+            return Ok(ImportOutcome::Skipped);
+        }
+        let merged_monster = Monster {
+            id: existing_monster.id.clone(),
+            created_at: existing_monster.created_at,
+            updated_at: Some(Utc::now().naive_utc()),
+            last_battle_at: existing_monster.last_battle_at,
+            level: existing_monster.level,
+            xp: existing_monster.xp,
+            owner_id: existing_monster.owner_id.clone(),
+            image_broken: existing_monster.image_broken && existing_monster.image_url == monster.image_url,
+            external_id: monster.external_id.or(existing_monster.external_id.clone()),
+            ..monster
+        };
+        let updated_monster = diesel::insert_into(monsters)
+            .values(&merged_monster)
+            .on_conflict(id)
+            .do_update()
+            .set((&merged_monster, version.eq(version + 1)))
+            .get_result::<Monster>(connection)?;
+        audit_repository::record(connection, "monster", &updated_monster.id, "update", Some(&existing_monster), Some(&updated_monster))?;
+        Ok(ImportOutcome::Updated(updated_monster))
+    })
+}
+
+fn remove_monster(connection: &mut PgConnection, monster_id: &str, cascade: bool) -> Result<usize, DeleteMonsterError> {
+    connection.transaction(|connection| {
+        let existing_monster = match monsters.find(monster_id).get_result::<Monster>(connection) {
+            Ok(existing_monster) => existing_monster,
+            Err(_) => return Err(DeleteMonsterError::NotFound),
+        };
+
+        let monster_battles = battles::table.filter(
+            battles::monster_a.eq(monster_id)
+                .or(battles::monster_b.eq(monster_id))
+                .or(battles::winner.eq(monster_id))
+        );
+        if cascade {
+            for battle in monster_battles.load::<Battle>(connection)? {
+                diesel::delete(battles::table.find(&battle.id)).execute(connection)?;
+                audit_repository::record(connection, "battle", &battle.id, "delete", Some(&battle), None)?;
+            }
+        } else if diesel::select(diesel::dsl::exists(monster_battles)).get_result::<bool>(connection)? {
+            return Err(DeleteMonsterError::HasBattles);
+        }
+
+        let count = diesel::delete(monsters.find(monster_id)).execute(connection)?;
+        audit_repository::record(connection, "monster", monster_id, "delete", Some(&existing_monster), None)?;
+        Ok(count)
+    })
 }
@@ -0,0 +1,113 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use crate::models::moves::{MonsterMove, Move};
+use crate::repository::schema::{monster_moves, moves};
+use crate::repository::database::Database;
+use crate::repository::audit_repository;
+use crate::repository::error::RepositoryError;
+
+pub trait MoveRepository: Send + Sync {
+    fn get_moves(&self) -> Vec<Move>;
+    fn get_move_by_id(&self, move_id: &str) -> Option<Move>;
+    fn create_move(&self, new_move: Move) -> Result<Move, RepositoryError>;
+    fn update_move_by_id(&self, move_id: &str, updated_move: Move) -> Result<Option<Move>, RepositoryError>;
+    // Deleting a move also takes it away from the monsters that knew it.
+    fn delete_move_by_id(&self, move_id: &str) -> Option<usize>;
+    // The moves of a monster in slot order.
+    fn get_monster_moves(&self, monster_id: &str) -> Vec<Move>;
+    // Replaces the moves of a monster, the first id taking the first slot.
+    fn set_monster_moves(&self, monster_id: &str, move_ids: &[String]) -> Result<Vec<Move>, RepositoryError>;
+}
+
+impl MoveRepository for Database {
+    fn get_moves(&self) -> Vec<Move> {
+        let mut connection = self.get_connection();
+        moves::table
+            .order(moves::created_at)
+            .load::<Move>(&mut connection)
+            .expect("Error loading all moves")
+    }
+
+    fn get_move_by_id(&self, move_id: &str) -> Option<Move> {
+        let mut connection = self.get_connection();
+        moves::table.find(move_id).get_result::<Move>(&mut connection).ok()
+    }
+
+    fn create_move(&self, new_move: Move) -> Result<Move, RepositoryError> {
+        let mut connection = self.get_connection();
+        let new_move = Move {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            ..new_move
+        };
+        Ok(connection.transaction(|connection| {
+            diesel::insert_into(moves::table)
+                .values(&new_move)
+                .execute(connection)?;
+            audit_repository::record(connection, "move", &new_move.id, "create", None, Some(&new_move))?;
+            Ok::<_, diesel::result::Error>(new_move)
+        })?)
+    }
+
+    fn update_move_by_id(&self, move_id: &str, updated_move: Move) -> Result<Option<Move>, RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.transaction(|connection| {
+            let existing_move = match moves::table.find(move_id).get_result::<Move>(connection).optional()? {
+                Some(existing_move) => existing_move,
+                None => return Ok(None),
+            };
+            let updated_move = diesel::update(moves::table.find(move_id))
+                .set((
+                    moves::name.eq(&updated_move.name),
+                    moves::power.eq(updated_move.power),
+                    moves::accuracy.eq(updated_move.accuracy),
+                    moves::element.eq(&updated_move.element),
+                    moves::effect.eq(updated_move.effect),
+                    moves::effect_chance.eq(updated_move.effect_chance),
+                    moves::updated_at.eq(Some(Utc::now().naive_utc())),
+                ))
+                .get_result::<Move>(connection)?;
+            audit_repository::record(connection, "move", move_id, "update", Some(&existing_move), Some(&updated_move))?;
+            Ok::<_, diesel::result::Error>(Some(updated_move))
+        })?)
+    }
+
+    fn delete_move_by_id(&self, move_id: &str) -> Option<usize> {
+        let mut connection = self.get_connection();
+        let existing_move = moves::table.find(move_id).get_result::<Move>(&mut connection).ok()?;
+        let count = connection.transaction(|connection| {
+            let count = diesel::delete(moves::table.find(move_id)).execute(connection)?;
+            audit_repository::record(connection, "move", move_id, "delete", Some(&existing_move), None)?;
+            Ok::<_, diesel::result::Error>(count)
+        })
+        .expect("Error deleting move by id");
+        Some(count)
+    }
+
+    fn get_monster_moves(&self, monster_id: &str) -> Vec<Move> {
+        let mut connection = self.get_connection();
+        monster_moves::table
+            .inner_join(moves::table)
+            .filter(monster_moves::monster_id.eq(monster_id))
+            .order(monster_moves::slot)
+            .select(moves::all_columns)
+            .load::<Move>(&mut connection)
+            .expect("Error loading monster moves")
+    }
+
+    fn set_monster_moves(&self, monster_id: &str, move_ids: &[String]) -> Result<Vec<Move>, RepositoryError> {
+        let mut connection = self.get_connection();
+        let slots: Vec<MonsterMove> = move_ids
+            .iter()
+            .enumerate()
+            .map(|(slot, move_id)| MonsterMove { monster_id: monster_id.to_string(), slot: slot as i32 + 1, move_id: move_id.clone() })
+            .collect();
+        connection.transaction(|connection| {
+            diesel::delete(monster_moves::table.filter(monster_moves::monster_id.eq(monster_id))).execute(connection)?;
+            diesel::insert_into(monster_moves::table).values(&slots).execute(connection)?;
+            Ok::<_, diesel::result::Error>(())
+        })?;
+        Ok(self.get_monster_moves(monster_id))
+    }
+}
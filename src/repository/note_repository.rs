@@ -0,0 +1,114 @@
+use chrono::prelude::*;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use crate::models::note::MonsterNote;
+use crate::repository::schema::monster_notes;
+use crate::repository::database::Database;
+use crate::repository::audit_repository;
+use crate::repository::error::RepositoryError;
+
+/*
+Notes are only ever reached through their owner, the notes of other owners being as good as missing.
+*/
+pub trait NoteRepository: Send + Sync {
+    // A page of the notes of the owner, pinned ones first then the newest, with the count of all that match.
+    // Only lists those of the monster when set, and those containing `search` whatever the case when set.
+    fn get_notes(&self, owner: &str, monster_id: Option<&str>, search: Option<&str>, offset: i64, limit: i64) -> (Vec<MonsterNote>, i64);
+    fn get_note_by_id(&self, owner: &str, note_id: &str) -> Option<MonsterNote>;
+    fn create_note(&self, note: MonsterNote) -> Result<MonsterNote, RepositoryError>;
+    // Replaces the body, pin and battles of the note.
+    fn update_note_by_id(&self, owner: &str, note_id: &str, note: MonsterNote) -> Result<Option<MonsterNote>, RepositoryError>;
+    fn delete_note_by_id(&self, owner: &str, note_id: &str) -> Option<usize>;
+}
+
+// `search` taken literally by ILIKE.
+fn like_pattern(search: &str) -> String {
+    format!("%{}%", search.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"))
+}
+
+impl NoteRepository for Database {
+    fn get_notes(&self, owner: &str, monster_id: Option<&str>, search: Option<&str>, offset: i64, limit: i64) -> (Vec<MonsterNote>, i64) {
+        let mut connection = self.get_connection();
+        let matching = || {
+            let mut query = monster_notes::table.filter(monster_notes::owner.eq(owner.to_string())).into_boxed::<Pg>();
+            if let Some(monster_id) = monster_id {
+                query = query.filter(monster_notes::monster_id.eq(monster_id.to_string()));
+            }
+            if let Some(search) = search {
+                query = query.filter(monster_notes::body.ilike(like_pattern(search)));
+            }
+            query
+        };
+        let total = matching()
+            .count()
+            .get_result::<i64>(&mut connection)
+            .expect("Error counting notes");
+        let notes = matching()
+            .order((monster_notes::pinned.desc(), monster_notes::created_at.desc(), monster_notes::id))
+            .offset(offset)
+            .limit(limit)
+            .load::<MonsterNote>(&mut connection)
+            .expect("Error loading notes");
+        (notes, total)
+    }
+
+    fn get_note_by_id(&self, owner: &str, note_id: &str) -> Option<MonsterNote> {
+        let mut connection = self.get_connection();
+        monster_notes::table
+            .find(note_id)
+            .filter(monster_notes::owner.eq(owner))
+            .get_result::<MonsterNote>(&mut connection)
+            .ok()
+    }
+
+    fn create_note(&self, note: MonsterNote) -> Result<MonsterNote, RepositoryError> {
+        let mut connection = self.get_connection();
+        let note = MonsterNote {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            ..note
+        };
+        Ok(connection.transaction(|connection| {
+            diesel::insert_into(monster_notes::table)
+                .values(&note)
+                .execute(connection)?;
+            audit_repository::record(connection, "note", &note.id, "create", None, Some(&note))?;
+            Ok::<_, diesel::result::Error>(note)
+        })?)
+    }
+
+    fn update_note_by_id(&self, owner: &str, note_id: &str, note: MonsterNote) -> Result<Option<MonsterNote>, RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.transaction(|connection| {
+            let owned_note = monster_notes::table.find(note_id).filter(monster_notes::owner.eq(owner));
+            let existing_note = match owned_note.get_result::<MonsterNote>(connection).optional()? {
+                Some(existing_note) => existing_note,
+                None => return Ok(None),
+            };
+            let updated_note = diesel::update(owned_note)
+                .set((
+                    monster_notes::body.eq(&note.body),
+                    monster_notes::pinned.eq(note.pinned),
+                    monster_notes::battle_ids.eq(&note.battle_ids),
+                    monster_notes::updated_at.eq(Some(Utc::now().naive_utc())),
+                ))
+                .get_result::<MonsterNote>(connection)?;
+            audit_repository::record(connection, "note", note_id, "update", Some(&existing_note), Some(&updated_note))?;
+            Ok::<_, diesel::result::Error>(Some(updated_note))
+        })?)
+    }
+
+    fn delete_note_by_id(&self, owner: &str, note_id: &str) -> Option<usize> {
+        let mut connection = self.get_connection();
+        let owned_note = monster_notes::table.find(note_id).filter(monster_notes::owner.eq(owner));
+        let existing_note = owned_note.get_result::<MonsterNote>(&mut connection).ok()?;
+        let count = connection.transaction(|connection| {
+            let count = diesel::delete(owned_note).execute(connection)?;
+            audit_repository::record(connection, "note", note_id, "delete", Some(&existing_note), None)?;
+            Ok::<_, diesel::result::Error>(count)
+        })
+        .expect("Error deleting note by id");
+        Some(count)
+    }
+}
@@ -0,0 +1,109 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use crate::models::rate_limit::{ApiKey, RateLimitTier};
+use crate::repository::schema::{api_keys, rate_limit_tiers};
+use crate::repository::database::Database;
+use crate::repository::audit_repository;
+use crate::repository::error::RepositoryError;
+
+pub trait RateLimitRepository: Send + Sync {
+    fn get_rate_limit_tiers(&self) -> Vec<RateLimitTier>;
+    // Creates the tier, or updates the budgets of the tier with the same name.
+    fn save_rate_limit_tier(&self, tier: RateLimitTier) -> Result<RateLimitTier, RepositoryError>;
+    // Tiers still given to API keys can't be deleted.
+    fn delete_rate_limit_tier(&self, tier_name: &str) -> Result<Option<usize>, RepositoryError>;
+    fn get_api_keys(&self) -> Vec<ApiKey>;
+    fn get_api_key_by_hash(&self, key_hash: &str) -> Option<ApiKey>;
+    fn create_api_key(&self, api_key: ApiKey) -> Result<ApiKey, RepositoryError>;
+    fn delete_api_key_by_id(&self, api_key_id: &str) -> Option<usize>;
+}
+
+impl RateLimitRepository for Database {
+    fn get_rate_limit_tiers(&self) -> Vec<RateLimitTier> {
+        let mut connection = self.get_connection();
+        rate_limit_tiers::table
+            .order(rate_limit_tiers::name)
+            .load::<RateLimitTier>(&mut connection)
+            .expect("Error loading rate limit tiers")
+    }
+
+    fn save_rate_limit_tier(&self, tier: RateLimitTier) -> Result<RateLimitTier, RepositoryError> {
+        let mut connection = self.get_connection();
+        let tier = RateLimitTier { updated_at: Some(Utc::now().naive_utc()), ..tier };
+        Ok(connection.transaction(|connection| {
+            let existing_tier = rate_limit_tiers::table.find(&tier.name).get_result::<RateLimitTier>(connection).optional()?;
+            let saved_tier = diesel::insert_into(rate_limit_tiers::table)
+                .values(&tier)
+                .on_conflict(rate_limit_tiers::name)
+                .do_update()
+                .set((
+                    rate_limit_tiers::standard_per_minute.eq(excluded(rate_limit_tiers::standard_per_minute)),
+                    rate_limit_tiers::expensive_per_minute.eq(excluded(rate_limit_tiers::expensive_per_minute)),
+                    rate_limit_tiers::streaming_per_minute.eq(excluded(rate_limit_tiers::streaming_per_minute)),
+                    rate_limit_tiers::updated_at.eq(excluded(rate_limit_tiers::updated_at)),
+                ))
+                .get_result::<RateLimitTier>(connection)?;
+            let action = if existing_tier.is_some() { "update" } else { "create" };
+            audit_repository::record(connection, "rate_limit_tier", &tier.name, action, existing_tier.as_ref(), Some(&saved_tier))?;
+            Ok::<_, diesel::result::Error>(saved_tier)
+        })?)
+    }
+
+    fn delete_rate_limit_tier(&self, tier_name: &str) -> Result<Option<usize>, RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.transaction(|connection| {
+            let existing_tier = match rate_limit_tiers::table.find(tier_name).get_result::<RateLimitTier>(connection).optional()? {
+                Some(existing_tier) => existing_tier,
+                None => return Ok(None),
+            };
+            let count = diesel::delete(rate_limit_tiers::table.find(tier_name)).execute(connection)?;
+            audit_repository::record(connection, "rate_limit_tier", tier_name, "delete", Some(&existing_tier), None)?;
+            Ok::<_, diesel::result::Error>(Some(count))
+        })?)
+    }
+
+    fn get_api_keys(&self) -> Vec<ApiKey> {
+        let mut connection = self.get_connection();
+        api_keys::table
+            .order(api_keys::created_at)
+            .load::<ApiKey>(&mut connection)
+            .expect("Error loading all API keys")
+    }
+
+    fn get_api_key_by_hash(&self, key_hash: &str) -> Option<ApiKey> {
+        let mut connection = self.get_connection();
+        api_keys::table
+            .filter(api_keys::key_hash.eq(key_hash))
+            .get_result::<ApiKey>(&mut connection)
+            .ok()
+    }
+
+    fn create_api_key(&self, api_key: ApiKey) -> Result<ApiKey, RepositoryError> {
+        let mut connection = self.get_connection();
+        let api_key = ApiKey {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: Utc::now().naive_utc(),
+            ..api_key
+        };
+        Ok(connection.transaction(|connection| {
+            diesel::insert_into(api_keys::table)
+                .values(&api_key)
+                .execute(connection)?;
+            audit_repository::record(connection, "api_key", &api_key.id, "create", None, Some(&api_key))?;
+            Ok::<_, diesel::result::Error>(api_key)
+        })?)
+    }
+
+    fn delete_api_key_by_id(&self, api_key_id: &str) -> Option<usize> {
+        let mut connection = self.get_connection();
+        let existing_api_key = api_keys::table.find(api_key_id).get_result::<ApiKey>(&mut connection).ok()?;
+        let count = connection.transaction(|connection| {
+            let count = diesel::delete(api_keys::table.find(api_key_id)).execute(connection)?;
+            audit_repository::record(connection, "api_key", api_key_id, "delete", Some(&existing_api_key), None)?;
+            Ok::<_, diesel::result::Error>(count)
+        })
+        .expect("Error deleting API key by id");
+        Some(count)
+    }
+}
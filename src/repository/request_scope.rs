@@ -0,0 +1,62 @@
+use std::future::Future;
+use crate::repository::audit_repository::ACTOR;
+use crate::repository::database::REQUEST_SCHEMA;
+
+/*
+The database schema and audit actor of the current request. Both are task-locals, lost by the work a request
+hands to `actix_rt::spawn`, `web::block` or `spawn_blocking`, so it is captured before and scoped around it again
+for the writes to land in the schema of the request and be recorded under its actor.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestScope {
+    pub schema: Option<String>,
+    pub actor: Option<String>,
+}
+
+impl RequestScope {
+    pub fn current() -> Self {
+        RequestScope {
+            schema: REQUEST_SCHEMA.try_with(|schema| schema.clone()).ok(),
+            actor: ACTOR.try_with(|actor| actor.clone()).ok(),
+        }
+    }
+
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        match (self.schema, self.actor) {
+            (Some(schema), Some(actor)) => REQUEST_SCHEMA.scope(schema, ACTOR.scope(actor, future)).await,
+            (Some(schema), None) => REQUEST_SCHEMA.scope(schema, future).await,
+            (None, Some(actor)) => ACTOR.scope(actor, future).await,
+            (None, None) => future.await,
+        }
+    }
+
+    pub fn sync_scope<R>(self, f: impl FnOnce() -> R) -> R {
+        match (self.schema, self.actor) {
+            (Some(schema), Some(actor)) => REQUEST_SCHEMA.sync_scope(schema, || ACTOR.sync_scope(actor, f)),
+            (Some(schema), None) => REQUEST_SCHEMA.sync_scope(schema, f),
+            (None, Some(actor)) => ACTOR.sync_scope(actor, f),
+            (None, None) => f(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::audit_repository::with_actor;
+    use crate::repository::database::Database;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_carry_the_schema_and_actor_over_to_spawned_and_blocking_work() {
+        let scope = Database::with_schema("scope_test".to_string(), with_actor("tester".to_string(), async { RequestScope::current() })).await;
+        assert_eq!(scope, RequestScope { schema: Some("scope_test".to_string()), actor: Some("tester".to_string()) });
+
+        let spawned = actix_rt::spawn(scope.clone().scope(async { RequestScope::current() })).await.unwrap();
+        assert_eq!(spawned, scope);
+        let blocking = scope.clone();
+        let blocked = actix_web::web::block(move || blocking.sync_scope(RequestScope::current)).await.unwrap();
+        assert_eq!(blocked, scope);
+        assert_eq!(RequestScope::current(), RequestScope::default());
+    }
+}
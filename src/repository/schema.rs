@@ -1,13 +1,284 @@
 // @generated automatically by Diesel CLI.
 
 diesel::table! {
-    battles (id) {
+    achievements (id) {
+        id -> Varchar,
+        kind -> Varchar,
+        monster_id -> Nullable<Varchar>,
+        trainer_id -> Nullable<Varchar>,
+        battle_id -> Nullable<Varchar>,
+        earned_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    api_keys (id) {
+        id -> Varchar,
+        name -> Varchar,
+        key_hash -> Varchar,
+        tier -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    audit_log (id) {
+        id -> Varchar,
+        entity_type -> Varchar,
+        entity_id -> Varchar,
+        action -> Varchar,
+        actor -> Nullable<Varchar>,
+        diff -> Jsonb,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    battle_rewards (id) {
+        id -> Varchar,
+        battle_id -> Varchar,
+        monster_id -> Varchar,
+        stage -> Varchar,
+        kind -> Varchar,
+        name -> Nullable<Varchar>,
+        amount -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    battle_series (id) {
         id -> Varchar,
         monster_a -> Varchar,
         monster_b -> Varchar,
+        best_of -> Int4,
         winner -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    battle_series_games (series_id, game) {
+        series_id -> Varchar,
+        game -> Int4,
+        battle_id -> Varchar,
+        seed -> Int8,
+    }
+}
+
+diesel::table! {
+    battles (id) {
+        id -> Varchar,
+        monster_a -> Varchar,
+        monster_b -> Varchar,
+        winner -> Nullable<Varchar>,
         created_at -> Nullable<Timestamp>,
         updated_at -> Nullable<Timestamp>,
+        outcome -> Varchar,
+        manual -> Bool,
+        season_id -> Nullable<Varchar>,
+        rules -> Nullable<Jsonb>,
+        trainer_a -> Nullable<Varchar>,
+        trainer_b -> Nullable<Varchar>,
+        flawless -> Bool,
+        highlight_score -> Int4,
+        region -> Nullable<Varchar>,
+        balance_version -> Nullable<Varchar>,
+        moves_a -> Array<Text>,
+        moves_b -> Array<Text>,
+    }
+}
+
+diesel::table! {
+    challenge_attempts (challenge_date, monster_id) {
+        challenge_date -> Date,
+        monster_id -> Varchar,
+        attempts -> Int4,
+        wins -> Int4,
+        last_attempt_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    cors_origins (origin) {
+        origin -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    daily_challenges (challenge_date) {
+        challenge_date -> Date,
+        opponent -> Varchar,
+        preset -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    db_table_stats (taken_at, table_name) {
+        taken_at -> Timestamp,
+        table_name -> Varchar,
+        live_rows -> Int8,
+        dead_rows -> Int8,
+        seq_scans -> Int8,
+        index_scans -> Int8,
+        total_bytes -> Int8,
+        last_vacuum -> Nullable<Timestamp>,
+        last_autovacuum -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    evolutions (species) {
+        species -> Varchar,
+        evolves_to -> Varchar,
+        min_level -> Int4,
+        min_wins -> Int4,
+        image_url -> Varchar,
+        attack -> Int4,
+        defense -> Int4,
+        hp -> Int4,
+        speed -> Int4,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    export_rows (export_id, position) {
+        export_id -> Varchar,
+        position -> Int4,
+        data -> Jsonb,
+    }
+}
+
+diesel::table! {
+    exports (id) {
+        id -> Varchar,
+        kind -> Varchar,
+        total -> Int4,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    interactive_battles (id) {
+        id -> Varchar,
+        monster_a -> Varchar,
+        monster_b -> Varchar,
+        monster_a_hp -> Int4,
+        monster_b_hp -> Int4,
+        turn -> Int4,
+        monster_a_ready -> Bool,
+        monster_a_move -> Nullable<Varchar>,
+        monster_b_ready -> Bool,
+        monster_b_move -> Nullable<Varchar>,
+        turns -> Jsonb,
+        finished -> Bool,
+        winner -> Nullable<Varchar>,
+        battle_id -> Nullable<Varchar>,
+        turn_deadline -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    idempotency_keys (client, key) {
+        client -> Varchar,
+        key -> Varchar,
+        method -> Varchar,
+        path -> Varchar,
+        status -> Nullable<Int4>,
+        content_type -> Nullable<Varchar>,
+        body -> Nullable<Bytea>,
+        created_at -> Timestamp,
+        request_hash -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    items (id) {
+        id -> Varchar,
+        name -> Varchar,
+        attack_bonus -> Int4,
+        defense_bonus -> Int4,
+        speed_bonus -> Int4,
+        consumable -> Bool,
+        revive_hp -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    jobs (id) {
+        id -> Varchar,
+        kind -> Varchar,
+        status -> Varchar,
+        total -> Int4,
+        processed -> Int4,
+        failed -> Int4,
+        result -> Nullable<Jsonb>,
+        error -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Nullable<Timestamp>,
+        finished_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    monster_items (monster_id, slot) {
+        monster_id -> Varchar,
+        slot -> Int4,
+        item_id -> Varchar,
+    }
+}
+
+diesel::table! {
+    monster_moves (monster_id, slot) {
+        monster_id -> Varchar,
+        slot -> Int4,
+        move_id -> Varchar,
+    }
+}
+
+diesel::table! {
+    monster_notes (id) {
+        id -> Varchar,
+        monster_id -> Varchar,
+        owner -> Varchar,
+        body -> Text,
+        pinned -> Bool,
+        battle_ids -> Array<Text>,
+        created_at -> Timestamp,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    monster_thumbnails (monster_id) {
+        monster_id -> Varchar,
+        image_url -> Varchar,
+        error -> Nullable<Text>,
+        fetched_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    monster_training (monster_id) {
+        monster_id -> Varchar,
+        iv_attack -> Int4,
+        iv_defense -> Int4,
+        iv_hp -> Int4,
+        iv_speed -> Int4,
+        tp_attack -> Int4,
+        tp_defense -> Int4,
+        tp_hp -> Int4,
+        tp_speed -> Int4,
+        unspent_points -> Int4,
+        updated_at -> Timestamp,
     }
 }
 
@@ -22,12 +293,153 @@ diesel::table! {
         created_at -> Nullable<Timestamp>,
         updated_at -> Nullable<Timestamp>,
         name -> Text,
+        last_battle_at -> Nullable<Timestamp>,
+        level -> Int4,
+        xp -> Int4,
+        owner_id -> Nullable<Varchar>,
+        image_broken -> Bool,
+        external_id -> Nullable<Varchar>,
+        version -> Int4,
+    }
+}
+
+diesel::table! {
+    moves (id) {
+        id -> Varchar,
+        name -> Varchar,
+        power -> Int4,
+        accuracy -> Int4,
+        element -> Nullable<Varchar>,
+        effect -> Nullable<Varchar>,
+        effect_chance -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    rate_limit_tiers (name) {
+        name -> Varchar,
+        standard_per_minute -> Int4,
+        expensive_per_minute -> Int4,
+        streaming_per_minute -> Int4,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    season_standings (season_id, monster_id) {
+        season_id -> Varchar,
+        monster_id -> Varchar,
+        rank -> Int4,
+        points -> Int4,
+        wins -> Int4,
+        losses -> Int4,
+        draws -> Int4,
+    }
+}
+
+diesel::table! {
+    seasons (id) {
+        id -> Varchar,
+        name -> Varchar,
+        started_at -> Timestamp,
+        ended_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    trades (id) {
+        id -> Varchar,
+        proposer -> Varchar,
+        recipient -> Varchar,
+        offered_monster -> Varchar,
+        requested_monster -> Varchar,
+        status -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    trainers (id) {
+        id -> Varchar,
+        name -> Varchar,
+        api_key_id -> Varchar,
+        created_at -> Timestamp,
+        region -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    webhooks (id) {
+        id -> Varchar,
+        target_url -> Text,
+        event_types -> Array<Text>,
+        secret -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Nullable<Timestamp>,
     }
 }
 
+diesel::joinable!(achievements -> battles (battle_id));
+diesel::joinable!(achievements -> monsters (monster_id));
+diesel::joinable!(achievements -> trainers (trainer_id));
+diesel::joinable!(api_keys -> rate_limit_tiers (tier));
+diesel::joinable!(battle_rewards -> battles (battle_id));
+diesel::joinable!(battle_rewards -> monsters (monster_id));
+diesel::joinable!(battle_series_games -> battle_series (series_id));
+diesel::joinable!(battle_series_games -> battles (battle_id));
 diesel::joinable!(battles -> monsters (winner));
+diesel::joinable!(challenge_attempts -> daily_challenges (challenge_date));
+diesel::joinable!(challenge_attempts -> monsters (monster_id));
+diesel::joinable!(battles -> seasons (season_id));
+diesel::joinable!(daily_challenges -> monsters (opponent));
+diesel::joinable!(export_rows -> exports (export_id));
+diesel::joinable!(interactive_battles -> battles (battle_id));
+diesel::joinable!(monster_items -> items (item_id));
+diesel::joinable!(monster_items -> monsters (monster_id));
+diesel::joinable!(monster_moves -> monsters (monster_id));
+diesel::joinable!(monster_moves -> moves (move_id));
+diesel::joinable!(monster_notes -> api_keys (owner));
+diesel::joinable!(monster_notes -> monsters (monster_id));
+diesel::joinable!(monster_thumbnails -> monsters (monster_id));
+diesel::joinable!(monster_training -> monsters (monster_id));
+diesel::joinable!(monsters -> trainers (owner_id));
+diesel::joinable!(season_standings -> monsters (monster_id));
+diesel::joinable!(season_standings -> seasons (season_id));
+diesel::joinable!(trainers -> api_keys (api_key_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    achievements,
+    api_keys,
+    audit_log,
+    battle_rewards,
+    battle_series,
+    battle_series_games,
     battles,
+    challenge_attempts,
+    cors_origins,
+    daily_challenges,
+    db_table_stats,
+    evolutions,
+    export_rows,
+    exports,
+    idempotency_keys,
+    interactive_battles,
+    items,
+    jobs,
+    monster_items,
+    monster_moves,
+    monster_notes,
+    monster_thumbnails,
+    monster_training,
     monsters,
+    moves,
+    rate_limit_tiers,
+    season_standings,
+    seasons,
+    trades,
+    trainers,
+    webhooks,
 );
@@ -0,0 +1,106 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use crate::models::battle::Battle;
+use crate::models::season::{standings, Season, SeasonStanding};
+use crate::repository::schema::{battles, season_standings, seasons};
+use crate::repository::database::Database;
+use crate::repository::audit_repository;
+use crate::repository::error::RepositoryError;
+
+pub trait SeasonRepository: Send + Sync {
+    // Newest first.
+    fn get_seasons(&self) -> Vec<Season>;
+    fn get_season_by_id(&self, season_id: &str) -> Option<Season>;
+    fn get_open_season(&self) -> Option<Season>;
+    // Closes the open season, if any, and opens the new one, all or nothing. Returns the season closed.
+    fn open_season(&self, season: Season) -> Result<(Season, Option<Season>), RepositoryError>;
+    // Ends the season and snapshots its standings, seasons already closed are returned as they are.
+    fn close_season(&self, season_id: &str) -> Result<Option<Season>, RepositoryError>;
+    // Computed from the battles of the season while it is open, the snapshot taken when it closed after.
+    fn get_season_standings(&self, season_id: &str) -> Vec<SeasonStanding>;
+}
+
+impl SeasonRepository for Database {
+    fn get_seasons(&self) -> Vec<Season> {
+        let mut connection = self.get_connection();
+        seasons::table
+            .order(seasons::started_at.desc())
+            .load::<Season>(&mut connection)
+            .expect("Error loading seasons")
+    }
+
+    fn get_season_by_id(&self, season_id: &str) -> Option<Season> {
+        let mut connection = self.get_connection();
+        seasons::table.find(season_id).get_result::<Season>(&mut connection).ok()
+    }
+
+    fn get_open_season(&self) -> Option<Season> {
+        let mut connection = self.get_connection();
+        seasons::table.filter(seasons::ended_at.is_null()).get_result::<Season>(&mut connection).ok()
+    }
+
+    fn open_season(&self, season: Season) -> Result<(Season, Option<Season>), RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.transaction(|connection| {
+            let open_season = seasons::table
+                .filter(seasons::ended_at.is_null())
+                .for_update()
+                .get_result::<Season>(connection)
+                .optional()?;
+            let closed_season = open_season.map(|open_season| end_season(connection, open_season)).transpose()?;
+            diesel::insert_into(seasons::table)
+                .values(&season)
+                .execute(connection)?;
+            audit_repository::record(connection, "season", &season.id, "create", None, Some(&season))?;
+            Ok::<_, diesel::result::Error>((season, closed_season))
+        })?)
+    }
+
+    fn close_season(&self, season_id: &str) -> Result<Option<Season>, RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.transaction(|connection| {
+            let Some(season) = seasons::table.find(season_id).for_update().get_result::<Season>(connection).optional()? else {
+                return Ok(None);
+            };
+            if season.ended_at.is_some() {
+                return Ok(Some(season));
+            }
+            Ok::<_, diesel::result::Error>(Some(end_season(connection, season)?))
+        })?)
+    }
+
+    fn get_season_standings(&self, season_id: &str) -> Vec<SeasonStanding> {
+        let mut connection = self.get_connection();
+        let Some(season) = seasons::table.find(season_id).get_result::<Season>(&mut connection).ok() else {
+            return Vec::new();
+        };
+        if season.ended_at.is_some() {
+            return season_standings::table
+                .filter(season_standings::season_id.eq(season_id))
+                .order((season_standings::rank, season_standings::monster_id))
+                .load::<SeasonStanding>(&mut connection)
+                .expect("Error loading season standings");
+        }
+        let season_battles = battles::table
+            .filter(battles::season_id.eq(season_id))
+            .load::<Battle>(&mut connection)
+            .expect("Error loading season battles");
+        standings(season_id, &season_battles)
+    }
+}
+
+// The season row must be locked, battles being stored wait for it to be closed before picking their season.
+fn end_season(connection: &mut PgConnection, season: Season) -> Result<Season, diesel::result::Error> {
+    let season_battles = battles::table
+        .filter(battles::season_id.eq(&season.id))
+        .load::<Battle>(connection)?;
+    diesel::insert_into(season_standings::table)
+        .values(&standings(&season.id, &season_battles))
+        .execute(connection)?;
+    let ended_season = Season { ended_at: Some(Utc::now().naive_utc()), ..season.clone() };
+    diesel::update(seasons::table.find(&season.id))
+        .set(seasons::ended_at.eq(ended_season.ended_at))
+        .execute(connection)?;
+    audit_repository::record(connection, "season", &season.id, "update", Some(&season), Some(&ended_season))?;
+    Ok(ended_season)
+}
@@ -0,0 +1,73 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use crate::models::monster::Monster;
+use crate::repository::schema::{monster_thumbnails, monsters};
+use crate::repository::database::Database;
+use crate::repository::audit_repository;
+use crate::repository::error::RepositoryError;
+
+/*
+Bookkeeping of the thumbnail task: which image each monster's thumbnails were made from, so a monster is
+fetched again only once its `image_url` changes.
+*/
+pub trait ThumbnailRepository: Send + Sync {
+    // Monsters whose current image wasn't fetched yet, the oldest first.
+    fn get_pending_thumbnails(&self, limit: i64) -> Vec<Monster>;
+    /*
+    Records fetching `image_url` for the monster, flagging its image as broken when it failed with `error`.
+    Nothing is recorded when the monster changed image in the meantime, the new one is still pending.
+    */
+    fn record_thumbnails(&self, monster_id: &str, image_url: &str, error: Option<&str>) -> Result<(), RepositoryError>;
+}
+
+impl ThumbnailRepository for Database {
+    fn get_pending_thumbnails(&self, limit: i64) -> Vec<Monster> {
+        let mut connection = self.get_connection();
+        diesel::sql_query(
+            "SELECT monsters.* FROM monsters \
+            LEFT JOIN monster_thumbnails ON monster_thumbnails.monster_id = monsters.id \
+            WHERE monster_thumbnails.image_url IS DISTINCT FROM monsters.image_url \
+            ORDER BY monsters.created_at NULLS FIRST, monsters.id \
+            LIMIT $1",
+        )
+        .bind::<BigInt, _>(limit)
+        .load::<Monster>(&mut connection)
+        .expect("Error loading monsters pending thumbnails")
+    }
+
+    fn record_thumbnails(&self, monster_id: &str, image_url: &str, error: Option<&str>) -> Result<(), RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.transaction(|connection| {
+            let Some(existing_monster) = monsters::table.find(monster_id).for_update().get_result::<Monster>(connection).optional()? else {
+                return Ok(());
+            };
+            if existing_monster.image_url != image_url {
+                return Ok(());
+            }
+            let fetched_at = Utc::now().naive_utc();
+            diesel::insert_into(monster_thumbnails::table)
+                .values((
+                    monster_thumbnails::monster_id.eq(monster_id),
+                    monster_thumbnails::image_url.eq(image_url),
+                    monster_thumbnails::error.eq(error),
+                    monster_thumbnails::fetched_at.eq(fetched_at),
+                ))
+                .on_conflict(monster_thumbnails::monster_id)
+                .do_update()
+                .set((
+                    monster_thumbnails::image_url.eq(image_url),
+                    monster_thumbnails::error.eq(error),
+                    monster_thumbnails::fetched_at.eq(fetched_at),
+                ))
+                .execute(connection)?;
+            if existing_monster.image_broken != error.is_some() {
+                let updated_monster = diesel::update(monsters::table.find(monster_id))
+                    .set(monsters::image_broken.eq(error.is_some()))
+                    .get_result::<Monster>(connection)?;
+                audit_repository::record(connection, "monster", monster_id, "update", Some(&existing_monster), Some(&updated_monster))?;
+            }
+            Ok::<_, diesel::result::Error>(())
+        })?)
+    }
+}
@@ -0,0 +1,225 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use crate::models::monster::Monster;
+use crate::models::rate_limit::ApiKey;
+use crate::models::region::RegionStats;
+use crate::models::trainer::{Trade, TradeStatus, Trainer};
+use crate::repository::schema::{api_keys, monsters, trades, trainers};
+use crate::repository::database::Database;
+use crate::repository::audit_repository;
+use crate::repository::error::RepositoryError;
+
+#[derive(Debug)]
+pub enum OwnershipError {
+    NotFound,
+    // The trainer doesn't own the monster, or isn't the one who can answer the trade.
+    Forbidden,
+    // The trade was already answered or can't be made, or its monsters changed hands since it was proposed.
+    Conflict(String),
+    Database(RepositoryError),
+}
+
+impl From<diesel::result::Error> for OwnershipError {
+    fn from(err: diesel::result::Error) -> Self {
+        OwnershipError::Database(RepositoryError::from(err))
+    }
+}
+
+impl From<RepositoryError> for OwnershipError {
+    fn from(err: RepositoryError) -> Self {
+        OwnershipError::Database(err)
+    }
+}
+
+/*
+Monsters only change owners through transfers and trades, both locking the monsters they move so a monster
+is never handed over twice.
+*/
+pub trait TrainerRepository: Send + Sync {
+    fn get_trainer_by_id(&self, trainer_id: &str) -> Option<Trainer>;
+    fn get_trainer_by_api_key(&self, api_key_id: &str) -> Option<Trainer>;
+    // Stores the API key the trainer authenticates with along with it, all or nothing.
+    fn create_trainer(&self, trainer: Trainer, api_key: ApiKey) -> Result<(Trainer, ApiKey), RepositoryError>;
+    fn get_trainer_monsters(&self, trainer_id: &str) -> Vec<Monster>;
+    // Hands the monster `from` owns over to `to`.
+    fn transfer_monster(&self, monster_id: &str, from: &str, to: &str) -> Result<Monster, OwnershipError>;
+    // The trades the trainer proposed or received, newest first.
+    fn get_trades(&self, trainer_id: &str) -> Vec<Trade>;
+    // Proposes the monster the proposer owns for the one of another trainer, who becomes the recipient.
+    fn create_trade(&self, proposer: &str, offered_monster: &str, requested_monster: &str) -> Result<Trade, OwnershipError>;
+    // The recipient accepts or declines the pending trade, the proposer can only cancel it.
+    fn answer_trade(&self, trade_id: &str, trainer_id: &str, accept: bool) -> Result<Trade, OwnershipError>;
+    // Every region trainers play from or battles were tagged with, by name.
+    fn get_region_stats(&self) -> Vec<RegionStats>;
+}
+
+// The status the answer of the trainer gives the trade.
+pub(crate) fn trade_answer(trade: &Trade, trainer_id: &str, accept: bool) -> Result<TradeStatus, OwnershipError> {
+    if trade.status != TradeStatus::Pending {
+        return Err(OwnershipError::Conflict("The trade was already answered".to_string()));
+    }
+    match (trainer_id == trade.recipient, trainer_id == trade.proposer, accept) {
+        (true, _, true) => Ok(TradeStatus::Accepted),
+        (true, _, false) => Ok(TradeStatus::Declined),
+        (false, true, false) => Ok(TradeStatus::Cancelled),
+        _ => Err(OwnershipError::Forbidden),
+    }
+}
+
+impl TrainerRepository for Database {
+    fn get_trainer_by_id(&self, trainer_id: &str) -> Option<Trainer> {
+        let mut connection = self.get_connection();
+        trainers::table.find(trainer_id).get_result::<Trainer>(&mut connection).ok()
+    }
+
+    fn get_trainer_by_api_key(&self, api_key_id: &str) -> Option<Trainer> {
+        let mut connection = self.get_connection();
+        trainers::table
+            .filter(trainers::api_key_id.eq(api_key_id))
+            .get_result::<Trainer>(&mut connection)
+            .ok()
+    }
+
+    fn create_trainer(&self, trainer: Trainer, api_key: ApiKey) -> Result<(Trainer, ApiKey), RepositoryError> {
+        let mut connection = self.get_connection();
+        let created_at = Utc::now().naive_utc();
+        let api_key = ApiKey { id: uuid::Uuid::new_v4().to_string(), created_at, ..api_key };
+        let trainer = Trainer { id: uuid::Uuid::new_v4().to_string(), api_key_id: api_key.id.clone(), created_at, ..trainer };
+        Ok(connection.transaction(|connection| {
+            diesel::insert_into(api_keys::table)
+                .values(&api_key)
+                .execute(connection)?;
+            diesel::insert_into(trainers::table)
+                .values(&trainer)
+                .execute(connection)?;
+            audit_repository::record(connection, "api_key", &api_key.id, "create", None, Some(&api_key))?;
+            audit_repository::record(connection, "trainer", &trainer.id, "create", None, Some(&trainer))?;
+            Ok::<_, diesel::result::Error>((trainer, api_key))
+        })?)
+    }
+
+    fn get_trainer_monsters(&self, trainer_id: &str) -> Vec<Monster> {
+        let mut connection = self.get_connection();
+        monsters::table
+            .filter(monsters::owner_id.eq(trainer_id))
+            .order(monsters::created_at)
+            .load::<Monster>(&mut connection)
+            .expect("Error loading trainer monsters")
+    }
+
+    fn transfer_monster(&self, monster_id: &str, from: &str, to: &str) -> Result<Monster, OwnershipError> {
+        let mut connection = self.get_connection();
+        connection.transaction(|connection| {
+            let monster = monsters::table.find(monster_id).for_update().get_result::<Monster>(connection).optional()?.ok_or(OwnershipError::NotFound)?;
+            if monster.owner_id.as_deref() != Some(from) {
+                return Err(OwnershipError::Forbidden);
+            }
+            if from == to {
+                return Err(OwnershipError::Conflict("The monster already belongs to the trainer".to_string()));
+            }
+            let transferred_monster = set_owner(connection, monster_id, to)?;
+            audit_repository::record(connection, "monster", monster_id, "update", Some(&monster), Some(&transferred_monster))?;
+            Ok(transferred_monster)
+        })
+    }
+
+    fn get_trades(&self, trainer_id: &str) -> Vec<Trade> {
+        let mut connection = self.get_connection();
+        trades::table
+            .filter(trades::proposer.eq(trainer_id).or(trades::recipient.eq(trainer_id)))
+            .order(trades::created_at.desc())
+            .load::<Trade>(&mut connection)
+            .expect("Error loading trades")
+    }
+
+    fn create_trade(&self, proposer: &str, offered_monster: &str, requested_monster: &str) -> Result<Trade, OwnershipError> {
+        let mut connection = self.get_connection();
+        connection.transaction(|connection| {
+            let owners = monsters::table
+                .filter(monsters::id.eq_any([offered_monster, requested_monster]))
+                .select((monsters::id, monsters::owner_id))
+                .load::<(String, Option<String>)>(connection)?;
+            let owner = |monster_id: &str| owners.iter().find(|(id, _)| id == monster_id).map(|(_, owner_id)| owner_id.clone());
+            let (Some(offered_owner), Some(requested_owner)) = (owner(offered_monster), owner(requested_monster)) else {
+                return Err(OwnershipError::NotFound);
+            };
+            if offered_owner.as_deref() != Some(proposer) {
+                return Err(OwnershipError::Forbidden);
+            }
+            let recipient = match requested_owner {
+                Some(recipient) if recipient != proposer => recipient,
+                _ => return Err(OwnershipError::Conflict("The requested monster must belong to another trainer".to_string())),
+            };
+            let trade = Trade {
+                id: uuid::Uuid::new_v4().to_string(),
+                proposer: proposer.to_string(),
+                recipient,
+                offered_monster: offered_monster.to_string(),
+                requested_monster: requested_monster.to_string(),
+                status: TradeStatus::Pending,
+                created_at: Utc::now().naive_utc(),
+                updated_at: None,
+            };
+            diesel::insert_into(trades::table)
+                .values(&trade)
+                .execute(connection)?;
+            audit_repository::record(connection, "trade", &trade.id, "create", None, Some(&trade))?;
+            Ok(trade)
+        })
+    }
+
+    fn answer_trade(&self, trade_id: &str, trainer_id: &str, accept: bool) -> Result<Trade, OwnershipError> {
+        let mut connection = self.get_connection();
+        connection.transaction(|connection| {
+            let trade = trades::table.find(trade_id).for_update().get_result::<Trade>(connection).optional()?.ok_or(OwnershipError::NotFound)?;
+            let status = trade_answer(&trade, trainer_id, accept)?;
+            if status == TradeStatus::Accepted {
+                // Locked in id order, so trades of the same monsters can't deadlock.
+                let locked_monsters = monsters::table
+                    .filter(monsters::id.eq_any([&trade.offered_monster, &trade.requested_monster]))
+                    .order(monsters::id)
+                    .for_update()
+                    .load::<Monster>(connection)?;
+                let owned_by = |monster_id: &str, owner: &str| locked_monsters.iter().any(|monster| monster.id == monster_id && monster.owner_id.as_deref() == Some(owner));
+                if !owned_by(&trade.offered_monster, &trade.proposer) || !owned_by(&trade.requested_monster, &trade.recipient) {
+                    return Err(OwnershipError::Conflict("The monsters changed hands since the trade was proposed".to_string()));
+                }
+                for (monster_id, owner) in [(&trade.offered_monster, &trade.recipient), (&trade.requested_monster, &trade.proposer)] {
+                    let monster = locked_monsters.iter().find(|monster| &monster.id == monster_id);
+                    let traded_monster = set_owner(connection, monster_id, owner)?;
+                    audit_repository::record(connection, "monster", monster_id, "update", monster, Some(&traded_monster))?;
+                }
+            }
+            let answered_trade = diesel::update(trades::table.find(trade_id))
+                .set((trades::status.eq(status), trades::updated_at.eq(Utc::now().naive_utc())))
+                .get_result::<Trade>(connection)?;
+            audit_repository::record(connection, "trade", trade_id, "update", Some(&trade), Some(&answered_trade))?;
+            Ok(answered_trade)
+        })
+    }
+
+    fn get_region_stats(&self) -> Vec<RegionStats> {
+        let mut connection = self.get_connection();
+        diesel::sql_query(
+            "WITH regions AS ( \
+                SELECT region FROM trainers WHERE region IS NOT NULL \
+                UNION \
+                SELECT region FROM battles WHERE region IS NOT NULL \
+            ) \
+            SELECT regions.region::text AS region, \
+                (SELECT count(*) FROM trainers WHERE trainers.region = regions.region) AS trainers, \
+                (SELECT count(*) FROM monsters JOIN trainers ON trainers.id = monsters.owner_id WHERE trainers.region = regions.region) AS monsters, \
+                (SELECT count(*) FROM battles WHERE battles.region = regions.region) AS battles \
+            FROM regions \
+            ORDER BY regions.region"
+        )
+            .load::<RegionStats>(&mut connection)
+            .expect("Error loading region stats")
+    }
+}
+
+fn set_owner(connection: &mut PgConnection, monster_id: &str, owner: &str) -> Result<Monster, diesel::result::Error> {
+    diesel::update(monsters::table.find(monster_id))
+        .set((monsters::owner_id.eq(owner), monsters::updated_at.eq(Utc::now().naive_utc())))
+        .get_result::<Monster>(connection)
+}
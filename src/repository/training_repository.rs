@@ -0,0 +1,108 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use crate::balance::{self, Balance};
+use crate::models::monster::Stats;
+use crate::models::training::MonsterTraining;
+use crate::repository::schema::{monster_training, monsters};
+use crate::repository::database::Database;
+use crate::repository::audit_repository;
+use crate::repository::error::RepositoryError;
+
+#[derive(Debug)]
+pub enum TrainingError {
+    NotFound,
+    // The values are out of their caps, or more points are allocated than are left.
+    Invalid(String),
+    Database(RepositoryError),
+}
+
+impl From<diesel::result::Error> for TrainingError {
+    fn from(err: diesel::result::Error) -> Self {
+        TrainingError::Database(RepositoryError::from(err))
+    }
+}
+
+pub trait TrainingRepository: Send + Sync {
+    // The hidden modifiers of the monster, none when it never trained.
+    fn get_training(&self, monster_id: &str) -> Option<MonsterTraining>;
+    fn allocate_training_points(&self, monster_id: &str, points: Stats) -> Result<MonsterTraining, TrainingError>;
+    fn set_individual_values(&self, monster_id: &str, individual_values: Stats) -> Result<MonsterTraining, TrainingError>;
+}
+
+impl TrainingRepository for Database {
+    fn get_training(&self, monster_id: &str) -> Option<MonsterTraining> {
+        let mut connection = self.get_connection();
+        monster_training::table.find(monster_id).get_result::<MonsterTraining>(&mut connection).ok()
+    }
+
+    fn allocate_training_points(&self, monster_id: &str, points: Stats) -> Result<MonsterTraining, TrainingError> {
+        update_training(self, monster_id, |training| training.allocate(&points, &balance::current()))
+    }
+
+    fn set_individual_values(&self, monster_id: &str, individual_values: Stats) -> Result<MonsterTraining, TrainingError> {
+        update_training(self, monster_id, |training| training.set_individual_values(individual_values))
+    }
+}
+
+// Changes the training of the monster under the lock of its row, so concurrent wins and allocations add up.
+fn update_training(db: &Database, monster_id: &str, update: impl FnOnce(&mut MonsterTraining) -> Result<(), String>) -> Result<MonsterTraining, TrainingError> {
+    let mut connection = db.get_connection();
+    connection.transaction(|connection| {
+        if monsters::table.find(monster_id).for_update().select(monsters::id).get_result::<String>(connection).optional()?.is_none() {
+            return Err(TrainingError::NotFound);
+        }
+        let existing_training = monster_training::table.find(monster_id).get_result::<MonsterTraining>(connection).optional()?;
+        let mut training = existing_training.clone().unwrap_or_else(|| MonsterTraining::new(monster_id));
+        update(&mut training).map_err(TrainingError::Invalid)?;
+        let saved_training = save_training(connection, &training)?;
+        audit_repository::record(connection, "monster_training", monster_id, "update", existing_training.as_ref(), Some(&saved_training))?;
+        Ok(saved_training)
+    })
+}
+
+// Grants the points of a win to the monster, whose row the battle already locked.
+pub(crate) fn earn_training_points(connection: &mut PgConnection, monster_id: &str, balance: &Balance) -> Result<(), diesel::result::Error> {
+    let mut training = monster_training::table
+        .find(monster_id)
+        .get_result::<MonsterTraining>(connection)
+        .optional()?
+        .unwrap_or_else(|| MonsterTraining::new(monster_id));
+    if training.earn(balance.training.points_per_win, balance) > 0 {
+        save_training(connection, &training)?;
+    }
+    Ok(())
+}
+
+fn save_training(connection: &mut PgConnection, training: &MonsterTraining) -> Result<MonsterTraining, diesel::result::Error> {
+    let (individual_values, training_points) = (training.individual_values, training.training_points);
+    diesel::insert_into(monster_training::table)
+        .values((
+            monster_training::monster_id.eq(&training.monster_id),
+            monster_training::iv_attack.eq(individual_values.attack),
+            monster_training::iv_defense.eq(individual_values.defense),
+            monster_training::iv_hp.eq(individual_values.hp),
+            monster_training::iv_speed.eq(individual_values.speed),
+            monster_training::tp_attack.eq(training_points.attack),
+            monster_training::tp_defense.eq(training_points.defense),
+            monster_training::tp_hp.eq(training_points.hp),
+            monster_training::tp_speed.eq(training_points.speed),
+            monster_training::unspent_points.eq(training.unspent_points),
+            monster_training::updated_at.eq(Utc::now().naive_utc()),
+        ))
+        .on_conflict(monster_training::monster_id)
+        .do_update()
+        .set((
+            monster_training::iv_attack.eq(excluded(monster_training::iv_attack)),
+            monster_training::iv_defense.eq(excluded(monster_training::iv_defense)),
+            monster_training::iv_hp.eq(excluded(monster_training::iv_hp)),
+            monster_training::iv_speed.eq(excluded(monster_training::iv_speed)),
+            monster_training::tp_attack.eq(excluded(monster_training::tp_attack)),
+            monster_training::tp_defense.eq(excluded(monster_training::tp_defense)),
+            monster_training::tp_hp.eq(excluded(monster_training::tp_hp)),
+            monster_training::tp_speed.eq(excluded(monster_training::tp_speed)),
+            monster_training::unspent_points.eq(excluded(monster_training::unspent_points)),
+            monster_training::updated_at.eq(excluded(monster_training::updated_at)),
+        ))
+        .get_result::<MonsterTraining>(connection)
+}
@@ -0,0 +1,88 @@
+use chrono::prelude::*;
+use diesel::prelude::*;
+use crate::models::webhook::Webhook;
+use crate::repository::schema::webhooks::dsl::*;
+use crate::repository::database::Database;
+use crate::repository::audit_repository;
+use crate::repository::error::RepositoryError;
+
+pub trait WebhookRepository: Send + Sync {
+    fn get_webhooks(&self) -> Vec<Webhook>;
+    fn get_webhooks_for_event(&self, event_type: &str) -> Vec<Webhook>;
+    fn get_webhook_by_id(&self, webhook_id: &str) -> Option<Webhook>;
+    fn create_webhook(&self, webhook: Webhook) -> Result<Webhook, RepositoryError>;
+    fn update_webhook_by_id(&self, webhook_id: &str, webhook: Webhook) -> Result<Option<Webhook>, RepositoryError>;
+    fn delete_webhook_by_id(&self, webhook_id: &str) -> Option<usize>;
+}
+
+impl WebhookRepository for Database {
+    fn get_webhooks(&self) -> Vec<Webhook> {
+        let mut connection = self.get_connection();
+        webhooks
+            .order(created_at)
+            .load::<Webhook>(&mut connection)
+            .expect("Error loading all webhooks")
+    }
+
+    fn get_webhooks_for_event(&self, event_type: &str) -> Vec<Webhook> {
+        let mut connection = self.get_connection();
+        webhooks
+            .filter(event_types.contains(vec![event_type]))
+            .load::<Webhook>(&mut connection)
+            .expect("Error loading webhooks by event type")
+    }
+
+    fn get_webhook_by_id(&self, webhook_id: &str) -> Option<Webhook> {
+        let mut connection = self.get_connection();
+        webhooks.find(webhook_id).get_result::<Webhook>(&mut connection).ok()
+    }
+
+    fn create_webhook(&self, webhook: Webhook) -> Result<Webhook, RepositoryError> {
+        let mut connection = self.get_connection();
+        let webhook = Webhook {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            ..webhook
+        };
+        Ok(connection.transaction(|connection| {
+            diesel::insert_into(webhooks)
+                .values(&webhook)
+                .execute(connection)?;
+            audit_repository::record(connection, "webhook", &webhook.id, "create", None, Some(&webhook))?;
+            Ok::<_, diesel::result::Error>(webhook)
+        })?)
+    }
+
+    // Only the target and the event types can change, the secret stays the same.
+    fn update_webhook_by_id(&self, webhook_id: &str, webhook: Webhook) -> Result<Option<Webhook>, RepositoryError> {
+        let mut connection = self.get_connection();
+        Ok(connection.transaction(|connection| {
+            let existing_webhook = match webhooks.find(webhook_id).get_result::<Webhook>(connection).optional()? {
+                Some(existing_webhook) => existing_webhook,
+                None => return Ok(None),
+            };
+            let updated_webhook = diesel::update(webhooks.find(webhook_id))
+                .set((
+                    target_url.eq(&webhook.target_url),
+                    event_types.eq(&webhook.event_types),
+                    updated_at.eq(Some(Utc::now().naive_utc())),
+                ))
+                .get_result::<Webhook>(connection)?;
+            audit_repository::record(connection, "webhook", webhook_id, "update", Some(&existing_webhook), Some(&updated_webhook))?;
+            Ok::<_, diesel::result::Error>(Some(updated_webhook))
+        })?)
+    }
+
+    fn delete_webhook_by_id(&self, webhook_id: &str) -> Option<usize> {
+        let mut connection = self.get_connection();
+        let existing_webhook = webhooks.find(webhook_id).get_result::<Webhook>(&mut connection).ok()?;
+        let count = connection.transaction(|connection| {
+            let count = diesel::delete(webhooks.find(webhook_id)).execute(connection)?;
+            audit_repository::record(connection, "webhook", webhook_id, "delete", Some(&existing_webhook), None)?;
+            Ok::<_, diesel::result::Error>(count)
+        })
+        .expect("Error deleting webhook by id");
+        Some(count)
+    }
+}
@@ -0,0 +1,266 @@
+use rand::{Rng, RngCore};
+use crate::config::AppConfig;
+use crate::models::battle::Battle;
+use crate::models::monster::Monster;
+use crate::models::reward::{Reward, RewardKind};
+
+const DEFAULT_XP_BASE: i32 = 100;
+const DEFAULT_CURRENCY_WIN: i32 = 50;
+const DEFAULT_CURRENCY_LOSS: i32 = 10;
+const DEFAULT_ACHIEVEMENT_WINS: &str = "1,10,50,100";
+const DEFAULT_DROP_TABLE: &str = "potion:20,elixir:5,none:75";
+// Drop table entry that drops nothing.
+const NO_DROP: &str = "none";
+
+// What the stages know of a ranked battle that was won.
+pub struct RewardContext<'a> {
+    pub battle: &'a Battle,
+    pub winner: &'a Monster,
+    pub loser: &'a Monster,
+    // Battles the winner won, this one included.
+    pub winner_wins: usize,
+}
+
+pub trait RewardStage: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn grant(&self, context: &RewardContext, rolls: &mut dyn RngCore) -> Vec<Reward>;
+}
+
+/*
+The stages granting rewards after ranked battles, run in order. BATTLE_REWARD_STAGES lists them, out of
+`xp`, `currency`, `achievements` and `drops`, and the REWARD_* variables tune each one. The rewards are
+stored along with the battle, draws grant none.
+*/
+pub struct RewardPipeline {
+    stages: Vec<Box<dyn RewardStage>>,
+}
+
+impl RewardPipeline {
+    pub fn new(stages: Vec<Box<dyn RewardStage>>) -> Self {
+        RewardPipeline { stages }
+    }
+
+    pub fn from_config(config: &AppConfig) -> Result<Option<Self>, String> {
+        RewardPipeline::from_vars(|name| config.var(name))
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Option<Self>, String> {
+        let Some(stage_names) = var("BATTLE_REWARD_STAGES") else { return Ok(None) };
+        let mut stages: Vec<Box<dyn RewardStage>> = Vec::new();
+        for stage_name in stage_names.split(',').map(str::trim).filter(|stage_name| !stage_name.is_empty()) {
+            if stages.iter().any(|stage| stage.name() == stage_name) {
+                return Err(format!("BATTLE_REWARD_STAGES lists the {} stage twice", stage_name));
+            }
+            let setting = |name: &str, default: &str| var(name).unwrap_or_else(|| default.to_string());
+            let stage: Box<dyn RewardStage> = match stage_name {
+                "xp" => Box::new(XpStage { base: parse_amount(&setting("REWARD_XP_BASE", &DEFAULT_XP_BASE.to_string()), "REWARD_XP_BASE")? }),
+                "currency" => Box::new(CurrencyStage {
+                    win: parse_amount(&setting("REWARD_CURRENCY_WIN", &DEFAULT_CURRENCY_WIN.to_string()), "REWARD_CURRENCY_WIN")?,
+                    loss: parse_amount(&setting("REWARD_CURRENCY_LOSS", &DEFAULT_CURRENCY_LOSS.to_string()), "REWARD_CURRENCY_LOSS")?,
+                }),
+                "achievements" => Box::new(AchievementStage {
+                    wins: setting("REWARD_ACHIEVEMENT_WINS", DEFAULT_ACHIEVEMENT_WINS)
+                        .split(',')
+                        .map(|wins| wins.trim().parse().map_err(|_| format!("REWARD_ACHIEVEMENT_WINS must list win counts, got {:?}", wins)))
+                        .collect::<Result<_, _>>()?,
+                }),
+                "drops" => Box::new(DropStage { table: parse_drop_table(&setting("REWARD_DROP_TABLE", DEFAULT_DROP_TABLE))? }),
+                other => return Err(format!("Unknown reward stage {:?}, expected xp, currency, achievements or drops", other)),
+            };
+            stages.push(stage);
+        }
+        Ok((!stages.is_empty()).then(|| RewardPipeline::new(stages)))
+    }
+
+    pub fn run(&self, context: &RewardContext, rolls: &mut dyn RngCore) -> Vec<Reward> {
+        // Stages configured to grant nothing, like a zero loss payout, are left out.
+        self.stages
+            .iter()
+            .flat_map(|stage| stage.grant(context, rolls))
+            .filter(|reward| reward.amount > 0)
+            .collect()
+    }
+}
+
+fn parse_amount(value: &str, name: &str) -> Result<i32, String> {
+    value.trim().parse().ok().filter(|amount: &i32| *amount >= 0).ok_or_else(|| format!("{} must be a non-negative integer, got {:?}", name, value))
+}
+
+// Entries are `item:weight`, the `none` item standing for no drop.
+fn parse_drop_table(table: &str) -> Result<Vec<(String, u32)>, String> {
+    let entries: Vec<(String, u32)> = table
+        .split(',')
+        .map(|entry| {
+            let (item, weight) = entry.split_once(':').ok_or_else(|| format!("REWARD_DROP_TABLE entries are item:weight, got {:?}", entry))?;
+            let weight = weight.trim().parse().map_err(|_| format!("REWARD_DROP_TABLE weights must be integers, got {:?}", entry))?;
+            Ok((item.trim().to_string(), weight))
+        })
+        .collect::<Result<_, String>>()?;
+    if entries.iter().map(|(_, weight)| weight).sum::<u32>() == 0 {
+        return Err("REWARD_DROP_TABLE weights must not all be 0".to_string());
+    }
+    Ok(entries)
+}
+
+// XP for both monsters, the winner's scaled by how strong its opponent was and the loser's a quarter of the base.
+pub struct XpStage {
+    pub base: i32,
+}
+
+impl RewardStage for XpStage {
+    fn name(&self) -> &'static str {
+        "xp"
+    }
+
+    fn grant(&self, context: &RewardContext, _rolls: &mut dyn RngCore) -> Vec<Reward> {
+        let (winner_score, loser_score) = (context.winner.stats.power_score().max(1), context.loser.stats.power_score().max(0));
+        let winner_xp = (self.base as i64 * loser_score / winner_score).clamp(1, i32::MAX as i64) as i32;
+        vec![
+            Reward::new(&context.battle.id, &context.winner.id, self.name(), RewardKind::Xp, None, winner_xp),
+            Reward::new(&context.battle.id, &context.loser.id, self.name(), RewardKind::Xp, None, self.base / 4),
+        ]
+    }
+}
+
+pub struct CurrencyStage {
+    pub win: i32,
+    pub loss: i32,
+}
+
+impl RewardStage for CurrencyStage {
+    fn name(&self) -> &'static str {
+        "currency"
+    }
+
+    fn grant(&self, context: &RewardContext, _rolls: &mut dyn RngCore) -> Vec<Reward> {
+        vec![
+            Reward::new(&context.battle.id, &context.winner.id, self.name(), RewardKind::Currency, None, self.win),
+            Reward::new(&context.battle.id, &context.loser.id, self.name(), RewardKind::Currency, None, self.loss),
+        ]
+    }
+}
+
+// Unlocks `wins-<n>` when the winner reaches each of the win counts.
+pub struct AchievementStage {
+    pub wins: Vec<usize>,
+}
+
+impl RewardStage for AchievementStage {
+    fn name(&self) -> &'static str {
+        "achievements"
+    }
+
+    fn grant(&self, context: &RewardContext, _rolls: &mut dyn RngCore) -> Vec<Reward> {
+        self.wins
+            .iter()
+            .filter(|&&wins| wins == context.winner_wins)
+            .map(|wins| Reward::new(&context.battle.id, &context.winner.id, self.name(), RewardKind::Achievement, Some(format!("wins-{}", wins)), 1))
+            .collect()
+    }
+}
+
+// Rolls the drop table once for the winner, each item dropping with a chance proportional to its weight.
+pub struct DropStage {
+    pub table: Vec<(String, u32)>,
+}
+
+impl RewardStage for DropStage {
+    fn name(&self) -> &'static str {
+        "drops"
+    }
+
+    fn grant(&self, context: &RewardContext, rolls: &mut dyn RngCore) -> Vec<Reward> {
+        let mut roll = rolls.gen_range(0..self.table.iter().map(|(_, weight)| weight).sum::<u32>());
+        let dropped = self.table.iter().find(|(_, weight)| {
+            let hit = roll < *weight;
+            roll = roll.saturating_sub(*weight);
+            hit
+        });
+        match dropped {
+            Some((item, _)) if item != NO_DROP => vec![Reward::new(&context.battle.id, &context.winner.id, self.name(), RewardKind::Item, Some(item.clone()), 1)],
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use crate::models::battle::BattleOutcome;
+    use crate::models::monster::Stats;
+
+    use super::*;
+
+    fn pipeline(vars: &[(&str, &str)]) -> Result<Option<RewardPipeline>, String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        RewardPipeline::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_should_read_the_stages_from_the_environment() {
+        assert!(pipeline(&[]).unwrap().is_none());
+        let stages = pipeline(&[("BATTLE_REWARD_STAGES", "currency, xp")]).unwrap().unwrap().stages;
+        assert_eq!(stages.iter().map(|stage| stage.name()).collect::<Vec<_>>(), vec!["currency", "xp"]);
+        assert!(pipeline(&[("BATTLE_REWARD_STAGES", "xp,xp")]).is_err());
+        assert!(pipeline(&[("BATTLE_REWARD_STAGES", "loot")]).is_err());
+        assert!(pipeline(&[("BATTLE_REWARD_STAGES", "xp"), ("REWARD_XP_BASE", "-1")]).is_err());
+        assert!(pipeline(&[("BATTLE_REWARD_STAGES", "drops"), ("REWARD_DROP_TABLE", "none:0")]).is_err());
+    }
+
+    #[test]
+    fn test_should_run_the_stages_in_order() {
+        let monster = |id: &str, attack: i32| Monster {
+            id: id.to_string(),
+            image_url: String::new(),
+            stats: Stats { attack, defense: 10, hp: 50, speed: 10 },
+            created_at: None,
+            updated_at: None,
+            name: id.to_string(),
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        };
+        let (winner, loser) = (monster("winner", 20), monster("loser", 35));
+        let battle = Battle {
+            id: "battle".to_string(),
+            monster_a: winner.id.clone(),
+            monster_b: loser.id.clone(),
+            winner: Some(winner.id.clone()),
+            created_at: None,
+            updated_at: None,
+            outcome: BattleOutcome::Win,
+            manual: false,
+            season_id: None,
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        };
+        let context = RewardContext { battle: &battle, winner: &winner, loser: &loser, winner_wins: 10 };
+        let pipeline = pipeline(&[("BATTLE_REWARD_STAGES", "xp,currency,achievements,drops"), ("REWARD_CURRENCY_LOSS", "0"), ("REWARD_DROP_TABLE", "potion:1")]).unwrap().unwrap();
+
+        let granted: Vec<_> = pipeline
+            .run(&context, &mut ChaCha8Rng::seed_from_u64(1))
+            .into_iter()
+            .map(|reward| (reward.monster_id, reward.kind, reward.name, reward.amount))
+            .collect();
+        assert_eq!(granted, vec![
+            ("winner".to_string(), RewardKind::Xp, None, 127),
+            ("loser".to_string(), RewardKind::Xp, None, 25),
+            ("winner".to_string(), RewardKind::Currency, None, 50),
+            ("winner".to_string(), RewardKind::Achievement, Some("wins-10".to_string()), 1),
+            ("winner".to_string(), RewardKind::Item, Some("potion".to_string()), 1),
+        ]);
+    }
+}
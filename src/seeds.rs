@@ -0,0 +1,170 @@
+use std::fmt;
+use diesel::connection::SimpleConnection;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use crate::config::AppConfig;
+use crate::models::rate_limit::{hash_api_key, new_api_key};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedProfile {
+    Dev,
+    Demo,
+    Test,
+    Prod,
+}
+
+impl fmt::Display for SeedProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeedProfile::Dev => write!(f, "dev"),
+            SeedProfile::Demo => write!(f, "demo"),
+            SeedProfile::Test => write!(f, "test"),
+            SeedProfile::Prod => write!(f, "prod"),
+        }
+    }
+}
+
+impl SeedProfile {
+    pub fn parse(profile: &str) -> Result<Self, String> {
+        match profile.trim() {
+            "dev" => Ok(SeedProfile::Dev),
+            "demo" => Ok(SeedProfile::Demo),
+            "test" => Ok(SeedProfile::Test),
+            "prod" => Ok(SeedProfile::Prod),
+            other => Err(format!("Unknown seed profile {:?}, expected dev, demo, test or prod", other)),
+        }
+    }
+
+    pub fn from_config(config: &AppConfig) -> Result<Self, String> {
+        SeedProfile::from_vars(|name| config.var(name))
+    }
+
+    // SEED_PROFILE picks the profile, prod by default. Sample data is never seeded when APP_ENV is production.
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let profile = var("SEED_PROFILE").map(|profile| SeedProfile::parse(&profile)).transpose()?.unwrap_or(SeedProfile::Prod);
+        if profile != SeedProfile::Prod && var("APP_ENV").is_some_and(|env| env == "production") {
+            return Err(format!("SEED_PROFILE {} can't be used when APP_ENV is production", profile));
+        }
+        Ok(profile)
+    }
+}
+
+/*
+A data migration from the seeds directory, only run for its profiles. The API keys it inserts, by id, are issued
+random keys once it ran, logged that once, so no sample key is known beyond the setup it was issued for.
+*/
+pub struct Seed {
+    pub version: &'static str,
+    pub name: &'static str,
+    pub profiles: &'static [SeedProfile],
+    pub sql: &'static str,
+    pub api_keys: &'static [&'static str],
+}
+
+macro_rules! seed {
+    ($version:literal, $name:literal, $profiles:expr) => {
+        seed!($version, $name, $profiles, &[])
+    };
+    ($version:literal, $name:literal, $profiles:expr, $api_keys:expr) => {
+        Seed { version: $version, name: $name, profiles: $profiles, sql: include_str!(concat!("../seeds/", $version, "_", $name, "/up.sql")), api_keys: $api_keys }
+    };
+}
+
+/*
+The seeds embedded in the binary in the order they run. Schema migrations are applied by `Database::migrate` to
+every profile, prod applying them only: the seeds add sample data on top, for dev, demo and test setups.
+*/
+pub static SEEDS: &[Seed] = &[
+    seed!("2026-10-16-040000", "sample_monsters", &[SeedProfile::Dev, SeedProfile::Demo, SeedProfile::Test]),
+    seed!("2026-10-16-040100", "sample_users", &[SeedProfile::Dev, SeedProfile::Demo], &["seed-key-player", "seed-key-partner"]),
+    seed!("2026-10-16-040200", "demo_tournament", &[SeedProfile::Demo]),
+];
+
+/*
+Runs the seeds of the profile not run yet, each in its own transaction along with recording it in
+`__seed_migrations`, the way diesel records migrations. Returns the versions run.
+*/
+pub fn run_seeds(connection: &mut PgConnection, profile: SeedProfile) -> Result<Vec<&'static str>, diesel::result::Error> {
+    connection.batch_execute(
+        "CREATE TABLE IF NOT EXISTS __seed_migrations (\
+            version varchar PRIMARY KEY, \
+            name varchar NOT NULL, \
+            run_on TIMESTAMP NOT NULL DEFAULT now()\
+        )",
+    )?;
+
+    let mut run = Vec::new();
+    for seed in SEEDS.iter().filter(|seed| seed.profiles.contains(&profile)) {
+        let ran = connection.transaction(|connection| {
+            // Another instance starting at the same time waits on the version, then finds it recorded.
+            let recorded = diesel::sql_query("INSERT INTO __seed_migrations (version, name) VALUES ($1, $2) ON CONFLICT (version) DO NOTHING")
+                .bind::<Text, _>(seed.version)
+                .bind::<Text, _>(seed.name)
+                .execute(connection)?;
+            if recorded == 1 {
+                connection.batch_execute(seed.sql)?;
+                issue_api_keys(connection, seed.api_keys)?;
+            }
+            Ok::<_, diesel::result::Error>(recorded == 1)
+        })?;
+        if ran {
+            run.push(seed.version);
+        }
+    }
+    Ok(run)
+}
+
+// Replaces the placeholder hashes of the seeded keys, only logging the keys once they're stored.
+fn issue_api_keys(connection: &mut PgConnection, api_key_ids: &[&str]) -> Result<(), diesel::result::Error> {
+    for api_key_id in api_key_ids {
+        let key = new_api_key();
+        let issued = diesel::sql_query("UPDATE api_keys SET key_hash = $1 WHERE id = $2 AND key_hash = 'unissued:' || id")
+            .bind::<Text, _>(hash_api_key(&key))
+            .bind::<Text, _>(api_key_id)
+            .execute(connection)?;
+        if issued == 1 {
+            tracing::warn!(api_key_id, key = %key, "Issued a sample API key, it won't be shown again");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::models::monster::Monster;
+    use crate::repository::database::Database;
+    use crate::repository::schema::{api_keys, monsters};
+
+    use super::*;
+
+    fn profile(vars: &[(&str, &str)]) -> Result<SeedProfile, String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        SeedProfile::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_should_run_the_seeds_of_the_profile_once() {
+        assert_eq!(profile(&[]), Ok(SeedProfile::Prod));
+        assert_eq!(profile(&[("SEED_PROFILE", "demo")]), Ok(SeedProfile::Demo));
+        assert!(profile(&[("SEED_PROFILE", "staging")]).is_err());
+        assert!(profile(&[("SEED_PROFILE", "dev"), ("APP_ENV", "production")]).is_err());
+        assert_eq!(profile(&[("SEED_PROFILE", "prod"), ("APP_ENV", "production")]), Ok(SeedProfile::Prod));
+
+        let db = Database::new().unwrap();
+        db.get_connection().test_transaction::<_, diesel::result::Error, _>(|connection| {
+            assert_eq!(run_seeds(connection, SeedProfile::Prod)?, Vec::<&str>::new());
+            assert_eq!(run_seeds(connection, SeedProfile::Test)?, vec!["2026-10-16-040000"]);
+            assert_eq!(run_seeds(connection, SeedProfile::Demo)?, vec!["2026-10-16-040100", "2026-10-16-040200"]);
+            assert_eq!(run_seeds(connection, SeedProfile::Demo)?, Vec::<&str>::new());
+
+            let seeded = monsters::table.filter(monsters::id.like("seed-monster-%")).load::<Monster>(connection)?;
+            assert_eq!(seeded.len(), 6);
+            // Sample keys are random, no placeholder or well-known key is left to authenticate with.
+            let hashes = api_keys::table.filter(api_keys::id.like("seed-key-%")).select(api_keys::key_hash).load::<String>(connection)?;
+            assert_eq!(hashes.len(), 2);
+            assert!(hashes.iter().all(|hash| hash.len() == 64 && *hash != hash_api_key("bm_sample_player")));
+            Ok(())
+        });
+    }
+}
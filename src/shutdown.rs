@@ -0,0 +1,110 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use actix_web::web;
+use crate::config::AppConfig;
+use crate::events::EventPublisher;
+use crate::jobs::JobQueue;
+use crate::repository::database::Database;
+
+const DEFAULT_SHUTDOWN_TIMEOUT_SECONDS: u64 = 30;
+
+/*
+How long shutting down may take from the signal on, read from SHUTDOWN_TIMEOUT_SECONDS. In-flight requests may
+take all of it, background jobs run meanwhile, and whatever is left goes to flushing the events and returning the
+database connections.
+*/
+pub fn timeout_from_config(config: &AppConfig) -> Result<Duration, String> {
+    timeout_from_vars(|name| config.var(name))
+}
+
+fn timeout_from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Duration, String> {
+    match var("SHUTDOWN_TIMEOUT_SECONDS") {
+        Some(timeout) => match timeout.trim().parse::<u64>() {
+            Ok(timeout) if timeout > 0 => Ok(Duration::from_secs(timeout)),
+            _ => Err(format!("SHUTDOWN_TIMEOUT_SECONDS must be a positive integer, got {:?}", timeout)),
+        },
+        None => Ok(Duration::from_secs(DEFAULT_SHUTDOWN_TIMEOUT_SECONDS)),
+    }
+}
+
+// Resolves on the first SIGTERM or Ctrl-C, with the name of the signal.
+pub async fn signal() -> &'static str {
+    let interrupt = Box::pin(async {
+        if let Err(err) = actix_rt::signal::ctrl_c().await {
+            tracing::error!(error = %err, "Failed to listen for SIGINT");
+            std::future::pending::<()>().await;
+        }
+        "SIGINT"
+    });
+    #[cfg(unix)]
+    let terminate = Box::pin(async {
+        use actix_rt::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+        "SIGTERM"
+    });
+    #[cfg(not(unix))]
+    let terminate = Box::pin(std::future::pending::<&'static str>());
+    futures::future::select(interrupt, terminate).await.factor_first().0
+}
+
+/*
+Once the server stopped taking requests: waits for the background jobs to finish, then for the queued events to
+be delivered and the database connections to be returned, all by `deadline`. What misses it is logged, the
+process exiting regardless.
+*/
+pub async fn drain(deadline: Instant, job_queue: &JobQueue, events: Arc<dyn EventPublisher>, db: &Database) {
+    if !job_queue.drain(deadline).await {
+        tracing::warn!("Shut down with jobs still running");
+    }
+    let timeout = deadline.saturating_duration_since(Instant::now());
+    match actix_rt::time::timeout(timeout, web::block(move || events.flush(timeout))).await {
+        Ok(Ok(Ok(()))) => {}
+        Ok(Ok(Err(err))) => tracing::warn!(error = %err, "Failed to flush events"),
+        Ok(Err(_)) | Err(_) => tracing::warn!("Timed out flushing events"),
+    }
+    if !db.wait_idle(deadline).await {
+        tracing::warn!("Shut down with database connections still in use");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use crate::repository::memory_repository::InMemoryRepository;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_wait_for_running_jobs_and_start_no_more() {
+        let job_queue = JobQueue::new(Arc::new(InMemoryRepository::new()), 1);
+        let finished = Arc::new(Mutex::new(Vec::new()));
+        for job in ["running", "queued"] {
+            let finished = finished.clone();
+            job_queue.enqueue("test", 1, move |_| {
+                std::thread::sleep(Duration::from_millis(50));
+                finished.lock().unwrap().push(job);
+                Ok(serde_json::Value::Null)
+            }).unwrap();
+        }
+        actix_rt::task::yield_now().await;
+        assert!(job_queue.drain(Instant::now() + Duration::from_secs(5)).await);
+        assert_eq!(*finished.lock().unwrap(), vec!["running", "queued"]);
+
+        let late = finished.clone();
+        job_queue.enqueue("test", 1, move |_| {
+            late.lock().unwrap().push("late");
+            Ok(serde_json::Value::Null)
+        }).unwrap();
+        actix_rt::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(finished.lock().unwrap().len(), 2);
+        assert!(timeout_from_vars(|name| (name == "SHUTDOWN_TIMEOUT_SECONDS").then(|| "0".to_string())).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Duration;
+use actix_web::web::{self, Bytes};
+use image::{DynamicImage, ImageFormat};
+use crate::cards;
+use crate::repository::thumbnail_repository::ThumbnailRepository;
+use crate::storage::Storage;
+
+pub const THUMBNAIL_SIZES: [u32; 3] = [64, 128, 256];
+pub const DEFAULT_THUMBNAIL_SIZE: u32 = 128;
+// How often new and changed images are looked for.
+pub const THUMBNAIL_PERIOD: Duration = Duration::from_secs(60);
+const THUMBNAIL_BATCH: i64 = 20;
+
+pub fn thumbnail_key(monster_id: &str, size: u32) -> String {
+    format!("thumbnails/{}/{}.png", monster_id, size)
+}
+
+// A PNG per size, the image scaled down to fit a square of that size with its aspect ratio kept.
+pub fn render_thumbnails(image: &DynamicImage) -> Vec<(u32, Vec<u8>)> {
+    THUMBNAIL_SIZES
+        .iter()
+        .map(|&size| {
+            let mut png = Vec::new();
+            image.thumbnail(size, size).write_to(&mut Cursor::new(&mut png), ImageFormat::Png).expect("Thumbnails are encoded in memory");
+            (size, png)
+        })
+        .collect()
+}
+
+/*
+Downloads the images of a batch of pending monsters and stores their thumbnails, returning how many were recorded.
+Images that can't be downloaded or decoded flag their monster as broken. Monsters whose thumbnails couldn't be
+stored are left pending, the storage is likely to blame.
+*/
+pub async fn refresh_thumbnails(repository: &dyn ThumbnailRepository, storage: &dyn Storage) -> usize {
+    let mut recorded = 0;
+    'monsters: for monster in repository.get_pending_thumbnails(THUMBNAIL_BATCH) {
+        let error = match cards::load_monster_image(&monster, Some(storage)).await {
+            Ok(image) => {
+                let thumbnails = match web::block(move || render_thumbnails(&image)).await {
+                    Ok(thumbnails) => thumbnails,
+                    Err(err) => {
+                        tracing::error!(monster_id = %monster.id, error = %err, "Failed to render thumbnails");
+                        continue;
+                    }
+                };
+                for (size, png) in thumbnails {
+                    if let Err(err) = storage.put(&thumbnail_key(&monster.id, size), "image/png", Bytes::from(png)).await {
+                        tracing::error!(monster_id = %monster.id, error = %err, "Failed to store thumbnail");
+                        continue 'monsters;
+                    }
+                }
+                None
+            }
+            Err(err) => {
+                tracing::warn!(monster_id = %monster.id, image_url = %monster.image_url, error = %err, "Broken monster image");
+                Some(err)
+            }
+        };
+        match repository.record_thumbnails(&monster.id, &monster.image_url, error.as_deref()) {
+            Ok(()) => recorded += 1,
+            Err(err) => tracing::error!(monster_id = %monster.id, error = %err, "Failed to record thumbnails"),
+        }
+    }
+    recorded
+}
+
+// Refreshes the thumbnails every THUMBNAIL_PERIOD, working through a backlog batch after batch.
+pub async fn schedule_thumbnails(repository: Arc<dyn ThumbnailRepository>, storage: Arc<dyn Storage>) {
+    let mut interval = actix_rt::time::interval(THUMBNAIL_PERIOD);
+    loop {
+        interval.tick().await;
+        while refresh_thumbnails(repository.as_ref(), storage.as_ref()).await == THUMBNAIL_BATCH as usize {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App, HttpResponse};
+    use crate::api::config::repositories;
+    use crate::api::image_apis::{get_monster_thumbnail, image_key};
+    use crate::api::monster_apis::get_monsters;
+    use crate::models::monster::{Monster, Stats};
+    use crate::repository::memory_repository::InMemoryRepository;
+    use crate::repository::monster_repository::MonsterRepository;
+    use crate::storage::DiskStorage;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_store_thumbnails_and_flag_broken_images() {
+        let mut png = Vec::new();
+        image::RgbaImage::new(512, 256).write_to(&mut Cursor::new(&mut png), ImageFormat::Png).unwrap();
+        let served = png.clone();
+        let server = actix_test::start(move || {
+            let png = served.clone();
+            App::new()
+                .route("/sprite.png", web::get().to(move || {
+                    let png = png.clone();
+                    async move { HttpResponse::Ok().content_type("image/png").body(png) }
+                }))
+        });
+        let directory = tempfile::tempdir().unwrap();
+        let storage: Arc<dyn Storage> = Arc::new(DiskStorage::new(directory.path()));
+
+        let repository = Arc::new(InMemoryRepository::new());
+        let create_monster = |name: &str, image_url: String| repository.create_monster(Monster {
+            id: String::new(),
+            name: name.to_string(),
+            image_url,
+            stats: Stats { attack: 40, defense: 20, hp: 50, speed: 80 },
+            created_at: None,
+            updated_at: None,
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
+        }).unwrap();
+        let uploaded = |monster: &Monster| format!("http://localhost:8080/api/monsters/{}/image", monster.id);
+        let pictured = create_monster("pictured", "http://localhost:8080/api/monsters/pending/image".to_string());
+        let pictured = repository.set_monster_image_url(&pictured.id, &uploaded(&pictured)).unwrap().unwrap();
+        storage.put(&image_key(&pictured.id), "image/png", png.clone().into()).await.unwrap();
+        // Images on internal addresses are refused, even when the server behind them answers.
+        let broken = create_monster("broken", server.url("/sprite.png"));
+
+        assert_eq!(refresh_thumbnails(repository.as_ref(), storage.as_ref()).await, 2);
+        assert!(repository.get_pending_thumbnails(10).is_empty());
+        assert!(!repository.get_monster_by_id(&pictured.id).unwrap().image_broken);
+        assert!(repository.get_monster_by_id(&broken.id).unwrap().image_broken);
+
+        let app = App::new()
+            .configure(repositories(repository.clone()))
+            .app_data(web::Data::from(storage.clone()))
+            .service(get_monsters)
+            .service(get_monster_thumbnail);
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get().uri(&format!("/monsters/{}/thumbnail?size=128", pictured.id)).to_request();
+        let thumbnail = image::load_from_memory(&test::call_and_read_body(&app, req).await).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (128, 64));
+        for uri in [format!("/monsters/{}/thumbnail?size=100", pictured.id), format!("/monsters/{}/thumbnail", broken.id)] {
+            let resp = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+            assert!(resp.status().is_client_error(), "{}", uri);
+        }
+        let req = test::TestRequest::get().uri("/monsters?scope=all&image_broken=true").to_request();
+        let monsters: Vec<Monster> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(monsters.iter().map(|monster| monster.id.as_str()).collect::<Vec<_>>(), [broken.id.as_str()]);
+
+        // A new image is loaded again and clears the flag.
+        storage.put(&image_key(&broken.id), "image/png", png.into()).await.unwrap();
+        repository.update_monster_by_id(&broken.id, Monster { image_url: uploaded(&broken), ..broken.clone() }).unwrap();
+        assert_eq!(refresh_thumbnails(repository.as_ref(), storage.as_ref()).await, 1);
+        assert!(!repository.get_monster_by_id(&broken.id).unwrap().image_broken);
+    }
+}
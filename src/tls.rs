@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+use actix_web::{dev::Server, http::header, web, App, HttpRequest, HttpResponse, HttpServer};
+use crate::config::AppConfig;
+
+const HTTPS_PORT: u16 = 443;
+
+/*
+TLS for the server itself, so small deployments need no reverse proxy to serve HTTPS. Enabled by setting both
+TLS_CERT_PATH, the PEM certificate chain, and TLS_KEY_PATH, its PEM private key. TLS_REDIRECT_PORT optionally
+serves plain HTTP on another port, redirecting every request to HTTPS.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    pub redirect_port: Option<u16>,
+}
+
+// Requires building with the `tls` feature, the files being read on startup for bad ones to stop the server.
+pub fn from_config(config: &AppConfig) -> Result<Option<TlsConfig>, String> {
+    let tls = from_vars(|name| config.var(name))?;
+    if let Some(tls) = &tls {
+        check(tls)?;
+    }
+    Ok(tls)
+}
+
+fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Option<TlsConfig>, String> {
+    let path = |name: &str| var(name).map(|path| path.trim().to_string()).filter(|path| !path.is_empty()).map(PathBuf::from);
+    let (cert_path, key_path) = match (path("TLS_CERT_PATH"), path("TLS_KEY_PATH")) {
+        (Some(cert_path), Some(key_path)) => (cert_path, key_path),
+        (None, None) => return Ok(None),
+        _ => return Err("TLS_CERT_PATH and TLS_KEY_PATH must be set together".to_string()),
+    };
+    let redirect_port = match var("TLS_REDIRECT_PORT").as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(port) => Some(port.parse().map_err(|_| format!("TLS_REDIRECT_PORT must be a port number, got {:?}", port))?),
+    };
+    Ok(Some(TlsConfig { cert_path, key_path, redirect_port }))
+}
+
+#[cfg(feature = "tls")]
+fn check(tls: &TlsConfig) -> Result<(), String> {
+    tls.server_config().map(|_| ())
+}
+
+#[cfg(not(feature = "tls"))]
+fn check(_tls: &TlsConfig) -> Result<(), String> {
+    Err("TLS_CERT_PATH requires building with the tls feature".to_string())
+}
+
+#[cfg(feature = "tls")]
+impl TlsConfig {
+    pub fn server_config(&self) -> Result<rustls::ServerConfig, String> {
+        use std::fs::File;
+        use std::io::BufReader;
+        use std::sync::Arc;
+
+        let open = |path: &PathBuf| File::open(path).map(BufReader::new).map_err(|err| format!("Failed to read {}: {}", path.display(), err));
+        let certs = rustls_pemfile::certs(&mut open(&self.cert_path)?)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("Invalid certificate in {}: {}", self.cert_path.display(), err))?;
+        if certs.is_empty() {
+            return Err(format!("No certificate found in {}", self.cert_path.display()));
+        }
+        let key = rustls_pemfile::private_key(&mut open(&self.key_path)?)
+            .map_err(|err| format!("Invalid private key in {}: {}", self.key_path.display(), err))?
+            .ok_or_else(|| format!("No private key found in {}", self.key_path.display()))?;
+        rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|err| err.to_string())?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|err| format!("The certificate and private key don't match: {}", err))
+    }
+}
+
+/*
+A server on `port` answering every request with a permanent redirect to the same URL over HTTPS on `https_port`.
+Signals are left to the caller, which stops it along with the main server.
+*/
+pub fn redirect_server(address: &str, port: u16, https_port: u16) -> std::io::Result<Server> {
+    Ok(HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::new(https_port))
+            .default_service(web::to(redirect_to_https))
+    })
+    .workers(1)
+    .disable_signals()
+    .bind((address, port))?
+    .run())
+}
+
+async fn redirect_to_https(req: HttpRequest, https_port: web::Data<u16>) -> HttpResponse {
+    HttpResponse::PermanentRedirect()
+        .insert_header((header::LOCATION, https_location(&req, **https_port)))
+        .finish()
+}
+
+// The Host header alone is trusted, X-Forwarded-Host and Forwarded being set by whoever sends the request.
+fn https_location(req: &HttpRequest, https_port: u16) -> String {
+    let host = req.headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .or_else(|| req.uri().host())
+        .unwrap_or_else(|| req.app_config().host());
+    // The port is dropped from the host, IPv6 addresses like [::1]:8080 keeping their brackets.
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !name.is_empty() && port.bytes().all(|byte| byte.is_ascii_digit()) => name,
+        _ => host,
+    };
+    let path = req.uri().path_and_query().map_or("/", |path| path.as_str());
+    if https_port == HTTPS_PORT {
+        format!("https://{}{}", host, path)
+    } else {
+        format!("https://{}:{}{}", host, https_port, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, http};
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_redirect_plain_http_to_the_same_url_over_https() {
+        let app = test::init_service(App::new().app_data(web::Data::new(8443u16)).default_service(web::to(redirect_to_https))).await;
+        let req = test::TestRequest::post().uri("/api/monsters?limit=5").insert_header((header::HOST, "monsters.example:8080")).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), http::StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(resp.headers().get(header::LOCATION).unwrap(), "https://monsters.example:8443/api/monsters?limit=5");
+        let req = test::TestRequest::get()
+            .uri("/health")
+            .insert_header((header::HOST, "monsters.example"))
+            .insert_header(("X-Forwarded-Host", "evil.example"))
+            .insert_header((header::FORWARDED, "host=evil.example"))
+            .to_http_request();
+        assert_eq!(https_location(&req, HTTPS_PORT), "https://monsters.example/health");
+
+        let req = test::TestRequest::get().uri("/health").insert_header((header::HOST, "[::1]:8080")).to_http_request();
+        assert_eq!(https_location(&req, HTTPS_PORT), "https://[::1]/health");
+        let vars = |name: &str| (name == "TLS_CERT_PATH").then(|| "cert.pem".to_string());
+        assert!(from_vars(vars).is_err());
+        assert_eq!(from_vars(|_| None), Ok(None));
+    }
+}
@@ -1,8 +1,8 @@
 use diesel::prelude::*;
 use chrono::prelude::*;
 use crate::repository::database::Database;
-use crate::models::monster::Monster;
-use crate::models::battle::Battle;
+use crate::models::monster::{Monster, Stats};
+use crate::models::battle::{Battle, BattleOutcome};
 use crate::repository::schema::monsters::dsl::monsters;
 use crate::repository::schema::battles::dsl::battles;
 use diesel::associations::HasTable;
@@ -16,78 +16,106 @@ pub async fn init_test_monsters(db: &Database) -> Vec<Monster> {
             id: uuid::Uuid::new_v4().to_string(),
             name: "monster-1".to_string(),
             image_url: "https://loremflickr.com/640/480".to_string(),
-            attack: 40,
-            defense: 20,
-            hp: 50,
-            speed: 80,
+            stats: Stats { attack: 40, defense: 20, hp: 50, speed: 80 },
             created_at: Some(current_time),
-            updated_at: Some(current_time)
+            updated_at: Some(current_time),
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
         },
         Monster {
             id: uuid::Uuid::new_v4().to_string(),
             name: "monster-2".to_string(),
             image_url: "https://loremflickr.com/640/480".to_string(),
-            attack: 70,
-            defense: 20,
-            hp: 40,
-            speed: 40,
+            stats: Stats { attack: 70, defense: 20, hp: 40, speed: 40 },
             created_at: Some(current_time),
-            updated_at: Some(current_time)
+            updated_at: Some(current_time),
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
         },
         Monster {
             id: uuid::Uuid::new_v4().to_string(),
             name: "monster-3".to_string(),
             image_url: "https://loremflickr.com/640/480".to_string(),
-            attack: 40,
-            defense: 25,
-            hp: 50,
-            speed: 80,
+            stats: Stats { attack: 40, defense: 25, hp: 50, speed: 80 },
             created_at: Some(current_time),
-            updated_at: Some(current_time)
+            updated_at: Some(current_time),
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
         },
         Monster {
             id: uuid::Uuid::new_v4().to_string(),
             name: "monster-4".to_string(),
             image_url: "https://loremflickr.com/640/480".to_string(),
-            attack: 70,
-            defense: 20,
-            hp: 50,
-            speed: 40,
+            stats: Stats { attack: 70, defense: 20, hp: 50, speed: 40 },
             created_at: Some(current_time),
-            updated_at: Some(current_time)
+            updated_at: Some(current_time),
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
         },
         Monster {
             id: uuid::Uuid::new_v4().to_string(),
             name: "monster-5".to_string(),
             image_url: "https://loremflickr.com/640/480".to_string(),
-            attack: 40,
-            defense: 20,
-            hp: 100,
-            speed: 40,
+            stats: Stats { attack: 40, defense: 20, hp: 100, speed: 40 },
             created_at: Some(current_time),
-            updated_at: Some(current_time)
+            updated_at: Some(current_time),
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
         },
         Monster {
             id: uuid::Uuid::new_v4().to_string(),
             name: "monster-6".to_string(),
             image_url: "https://loremflickr.com/640/480".to_string(),
-            attack: 10,
-            defense: 10,
-            hp: 100,
-            speed: 80,
+            stats: Stats { attack: 10, defense: 10, hp: 100, speed: 80 },
             created_at: Some(current_time),
-            updated_at: Some(current_time)
+            updated_at: Some(current_time),
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
         },
         Monster {
             id: uuid::Uuid::new_v4().to_string(),
             name: "monster-7".to_string(),
             image_url: "https://loremflickr.com/640/480".to_string(),
-            attack: 60,
-            defense: 10,
-            hp: 150,
-            speed: 40,
+            stats: Stats { attack: 60, defense: 10, hp: 150, speed: 40 },
             created_at: Some(current_time),
-            updated_at: Some(current_time)
+            updated_at: Some(current_time),
+            last_battle_at: None,
+            level: 1,
+            xp: 0,
+            owner_id: None,
+            image_broken: false,
+            external_id: None,
+            version: 1,
         }
     ];
 
@@ -120,9 +148,21 @@ pub async fn init_test_battle(db: &Database) -> Battle {
         id: uuid::Uuid::new_v4().to_string(),
         monster_a: test_monsters[0].id.clone(),
         monster_b: test_monsters[1].id.clone(),
-        winner: test_monsters[0].id.clone(),
+        winner: Some(test_monsters[0].id.clone()),
         created_at: Some(current_time),
         updated_at: Some(current_time),
+        outcome: BattleOutcome::Win,
+        manual: false,
+        season_id: None,
+        rules: None,
+        trainer_a: None,
+        trainer_b: None,
+        flawless: false,
+        highlight_score: 0,
+        region: None,
+        balance_version: None,
+        moves_a: Vec::new(),
+        moves_b: Vec::new(),
     };
 
     match diesel::insert_into(battles::table())
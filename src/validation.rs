@@ -0,0 +1,252 @@
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use serde::Serialize;
+use crate::models::battle::Battle;
+use crate::models::monster::MAX_STAT;
+use crate::models::season::standings;
+use crate::repository::schema::{battles, monsters, season_standings};
+
+/*
+A problem `validate` found in the data. Fixable problems have a repair that loses nothing worth keeping,
+the others only get a suggestion for the operator.
+*/
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub check: &'static str,
+    pub entity: &'static str,
+    pub id: String,
+    pub problem: String,
+    pub suggestion: String,
+    pub fixable: bool,
+    pub fixed: bool,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ValidationSummary {
+    pub issues: usize,
+    pub fixable: usize,
+    pub fixed: usize,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ValidationReport {
+    pub issues: Vec<Issue>,
+    pub summary: ValidationSummary,
+}
+
+impl ValidationReport {
+    // Issues left once the fixes were applied.
+    pub fn remaining(&self) -> usize {
+        self.summary.issues - self.summary.fixed
+    }
+}
+
+#[derive(QueryableByName)]
+struct Id {
+    #[diesel(sql_type = Text)]
+    id: String,
+}
+
+/*
+Checks the whole database for data the constraints of older deployments let in: battles whose monsters are
+gone, impossible battle results, stats out of range, image URLs that can't be loaded and closed seasons without
+their standings snapshot. With `fix`, the fixable issues are repaired in the same transaction.
+*/
+pub fn validate(connection: &mut PgConnection, fix: bool) -> Result<ValidationReport, diesel::result::Error> {
+    connection.transaction(|connection| {
+        let mut issues = Vec::new();
+
+        let orphaned_battles = diesel::sql_query(
+            "SELECT battles.id FROM battles \
+            LEFT JOIN monsters monsters_a ON monsters_a.id = battles.monster_a \
+            LEFT JOIN monsters monsters_b ON monsters_b.id = battles.monster_b \
+            LEFT JOIN monsters winners ON winners.id = battles.winner \
+            WHERE monsters_a.id IS NULL OR monsters_b.id IS NULL OR (battles.winner IS NOT NULL AND winners.id IS NULL) \
+            ORDER BY battles.id"
+        ).load::<Id>(connection)?;
+        for Id { id } in orphaned_battles {
+            if fix {
+                diesel::delete(battles::table.find(&id)).execute(connection)?;
+            }
+            issues.push(Issue {
+                check: "orphaned_battle",
+                entity: "battle",
+                id,
+                problem: "The battle refers to a monster that doesn't exist".to_string(),
+                suggestion: "Delete the battle, it can't be shown or replayed".to_string(),
+                fixable: true,
+                fixed: fix,
+            });
+        }
+
+        let invalid_results = diesel::sql_query(
+            "SELECT id FROM battles \
+            WHERE outcome NOT IN ('win', 'draw', 'forfeit') \
+                OR (winner IS NULL) <> (outcome = 'draw') \
+                OR (winner IS NOT NULL AND winner NOT IN (monster_a, monster_b)) \
+            ORDER BY id"
+        ).load::<Id>(connection)?;
+        issues.extend(invalid_results.into_iter().map(|Id { id }| Issue {
+            check: "battle_result",
+            entity: "battle",
+            id,
+            problem: "The outcome and winner of the battle don't match, or the winner didn't take part".to_string(),
+            suggestion: "Set the outcome and winner from the battle's history, or delete it".to_string(),
+            fixable: false,
+            fixed: false,
+        }));
+
+        let invalid_stats = monsters::table
+            .filter(monsters::hp.le(0).or(monsters::attack.lt(0)).or(monsters::defense.lt(0)).or(monsters::speed.lt(0)))
+            .or_filter(monsters::hp.gt(MAX_STAT).or(monsters::attack.gt(MAX_STAT)).or(monsters::defense.gt(MAX_STAT)).or(monsters::speed.gt(MAX_STAT)))
+            .select(monsters::id)
+            .order(monsters::id)
+            .load::<String>(connection)?;
+        for id in invalid_stats {
+            if fix {
+                diesel::update(monsters::table.find(&id))
+                    .set((
+                        monsters::hp.eq(diesel::dsl::sql::<diesel::sql_types::Integer>(&format!("least(greatest(hp, 1), {})", MAX_STAT))),
+                        monsters::attack.eq(diesel::dsl::sql::<diesel::sql_types::Integer>(&format!("least(greatest(attack, 0), {})", MAX_STAT))),
+                        monsters::defense.eq(diesel::dsl::sql::<diesel::sql_types::Integer>(&format!("least(greatest(defense, 0), {})", MAX_STAT))),
+                        monsters::speed.eq(diesel::dsl::sql::<diesel::sql_types::Integer>(&format!("least(greatest(speed, 0), {})", MAX_STAT))),
+                    ))
+                    .execute(connection)?;
+            }
+            issues.push(Issue {
+                check: "invalid_stats",
+                entity: "monster",
+                id,
+                problem: format!("The monster has no HP, a negative stat or one over {}", MAX_STAT),
+                suggestion: format!("Raise HP to 1 and negative stats to 0, lower the others to {}", MAX_STAT),
+                fixable: true,
+                fixed: fix,
+            });
+        }
+
+        let image_urls = monsters::table
+            .select((monsters::id, monsters::image_url))
+            .order(monsters::id)
+            .load::<(String, String)>(connection)?;
+        issues.extend(image_urls.into_iter().filter_map(|(id, image_url)| {
+            let problem = image_url_problem(&image_url)?;
+            Some(Issue {
+                check: "image_url",
+                entity: "monster",
+                id,
+                problem: format!("The image URL {:?} {}", image_url, problem),
+                suggestion: "Upload the image again or point the monster to a reachable http(s) URL".to_string(),
+                fixable: false,
+                fixed: false,
+            })
+        }));
+
+        let missing_snapshots = diesel::sql_query(
+            "SELECT seasons.id FROM seasons \
+            WHERE seasons.ended_at IS NOT NULL \
+                AND NOT EXISTS (SELECT 1 FROM season_standings WHERE season_standings.season_id = seasons.id) \
+                AND EXISTS (SELECT 1 FROM battles WHERE battles.season_id = seasons.id) \
+            ORDER BY seasons.id"
+        ).load::<Id>(connection)?;
+        for Id { id } in missing_snapshots {
+            if fix {
+                let season_battles = battles::table.filter(battles::season_id.eq(&id)).load::<Battle>(connection)?;
+                diesel::insert_into(season_standings::table)
+                    .values(&standings(&id, &season_battles))
+                    .execute(connection)?;
+            }
+            issues.push(Issue {
+                check: "missing_snapshot",
+                entity: "season",
+                id,
+                problem: "The season is closed but its final standings were never snapshotted".to_string(),
+                suggestion: "Snapshot the standings from the battles of the season".to_string(),
+                fixable: true,
+                fixed: fix,
+            });
+        }
+
+        let summary = ValidationSummary {
+            issues: issues.len(),
+            fixable: issues.iter().filter(|issue| issue.fixable).count(),
+            fixed: issues.iter().filter(|issue| issue.fixed).count(),
+        };
+        Ok(ValidationReport { issues, summary })
+    })
+}
+
+// Only the form of the URL is checked, the scan runs offline.
+fn image_url_problem(image_url: &str) -> Option<&'static str> {
+    let Some(rest) = image_url.strip_prefix("https://").or_else(|| image_url.strip_prefix("http://")) else {
+        return Some(if image_url.trim().is_empty() { "is empty" } else { "is not an http(s) URL" });
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    if host.is_empty() || image_url.chars().any(char::is_whitespace) {
+        return Some("is malformed");
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use crate::models::season::Season;
+    use crate::repository::database::Database;
+    use crate::repository::monster_repository::MonsterRepository;
+    use crate::repository::schema::seasons;
+    use crate::utils::test_utils::init_test_monsters;
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_should_report_and_fix_the_issues_found() {
+        assert_eq!(image_url_problem("https://loremflickr.com/640/480"), None);
+        assert_eq!(image_url_problem(""), Some("is empty"));
+        assert_eq!(image_url_problem("ftp://images/monster.png"), Some("is not an http(s) URL"));
+        assert_eq!(image_url_problem("https:///monster.png"), Some("is malformed"));
+
+        let db = Database::new().unwrap();
+        let test_monsters = init_test_monsters(&db).await;
+        let broken_image = db.create_monster(crate::models::monster::Monster { image_url: "images/monster.png".to_string(), ..test_monsters[0].clone() }).unwrap();
+        let season = Season { ended_at: Some(Utc::now().naive_utc()), ..Season::new("Unsnapshotted".to_string()) };
+        let battle = Battle {
+            id: uuid::Uuid::new_v4().to_string(),
+            monster_a: test_monsters[0].id.clone(),
+            monster_b: test_monsters[1].id.clone(),
+            winner: Some(test_monsters[0].id.clone()),
+            created_at: Some(Utc::now().naive_utc()),
+            updated_at: None,
+            outcome: crate::models::battle::BattleOutcome::Win,
+            manual: true,
+            season_id: Some(season.id.clone()),
+            rules: None,
+            trainer_a: None,
+            trainer_b: None,
+            flawless: false,
+            highlight_score: 0,
+            region: None,
+            balance_version: None,
+            moves_a: Vec::new(),
+            moves_b: Vec::new(),
+        };
+        let mut connection = db.get_connection();
+        diesel::insert_into(seasons::table).values(&season).execute(&mut connection).unwrap();
+        diesel::insert_into(battles::table).values(&battle).execute(&mut connection).unwrap();
+
+        let found = |report: &ValidationReport, check: &str, id: &str| report.issues.iter().find(|issue| issue.check == check && issue.id == id).cloned();
+        let report = validate(&mut connection, false).unwrap();
+        assert!(found(&report, "image_url", &broken_image.id).is_some_and(|issue| !issue.fixable));
+        assert!(found(&report, "missing_snapshot", &season.id).is_some_and(|issue| issue.fixable && !issue.fixed));
+
+        let report = validate(&mut connection, true).unwrap();
+        assert!(found(&report, "missing_snapshot", &season.id).is_some_and(|issue| issue.fixed));
+        let snapshot = season_standings::table
+            .filter(season_standings::season_id.eq(&season.id))
+            .select((season_standings::monster_id, season_standings::rank))
+            .order(season_standings::rank)
+            .load::<(String, i32)>(&mut connection)
+            .unwrap();
+        assert_eq!(snapshot, vec![(test_monsters[0].id.clone(), 1), (test_monsters[1].id.clone(), 2)]);
+        assert!(found(&validate(&mut connection, false).unwrap(), "missing_snapshot", &season.id).is_none());
+    }
+}
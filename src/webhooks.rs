@@ -0,0 +1,197 @@
+use std::sync::Arc;
+use std::time::Duration;
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::web::Bytes;
+use chrono::NaiveDateTime;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::sync::broadcast::error::RecvError;
+use crate::api::battle_apis::BATTLE_FEED;
+use crate::models::webhook::{Webhook, BATTLE_COMPLETED};
+use crate::repository::request_scope::RequestScope;
+use crate::repository::webhook_repository::WebhookRepository;
+
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_ATTEMPTS: u32 = 5;
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize)]
+struct WebhookEvent<'a, T: Serialize> {
+    id: String,
+    event: &'a str,
+    #[serde(rename = "createdAt")]
+    created_at: NaiveDateTime,
+    data: &'a T,
+}
+
+/*
+Sends a `battle.completed` callback to the subscribed webhooks for every battle published on the battle feed,
+the webhooks being looked up in the schema the battle was stored in. Each delivery runs on its own task so a
+slow target doesn't hold back the others.
+*/
+pub async fn dispatch_battles(webhook_repository: Arc<dyn WebhookRepository>, first_retry_delay: Duration) {
+    let mut battles = BATTLE_FEED.subscribe();
+    loop {
+        let published = match battles.recv().await {
+            Ok(published) => published,
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::warn!(skipped, "Webhook dispatcher fell behind, battles were not delivered");
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+
+        let (scope, battle) = (RequestScope { schema: published.schema, actor: None }, published.battle);
+        let subscribed = scope.sync_scope(|| webhook_repository.get_webhooks_for_event(BATTLE_COMPLETED));
+        if subscribed.is_empty() {
+            continue;
+        }
+        let event = WebhookEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            event: BATTLE_COMPLETED,
+            created_at: chrono::Utc::now().naive_utc(),
+            data: &battle,
+        };
+        let body = Bytes::from(serde_json::to_vec(&event).expect("Webhook events are serializable"));
+        for webhook in subscribed {
+            actix_rt::spawn(deliver(webhook, BATTLE_COMPLETED, body.clone(), first_retry_delay));
+        }
+    }
+}
+
+/*
+Posts `body` signed with the webhook secret as `X-Webhook-Signature: sha256=<hex HMAC>`.
+Anything but a 2xx is retried up to MAX_ATTEMPTS times, doubling the delay after each attempt.
+*/
+pub async fn deliver(webhook: Webhook, event: &'static str, body: Bytes, first_retry_delay: Duration) -> bool {
+    let client = awc::Client::builder().timeout(DELIVERY_TIMEOUT).finish();
+    let signature = sign(&webhook.secret, &body);
+    let mut delay = first_retry_delay;
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        let response = client
+            .post(&webhook.target_url)
+            .insert_header((CONTENT_TYPE, "application/json"))
+            .insert_header((EVENT_HEADER, event))
+            .insert_header((SIGNATURE_HEADER, signature.as_str()))
+            .send_body(body.clone())
+            .await;
+        match response {
+            Ok(response) if response.status().is_success() => return true,
+            Ok(response) => tracing::warn!(webhook_id = %webhook.id, attempt, status = response.status().as_u16(), "Webhook delivery failed"),
+            Err(err) => tracing::warn!(webhook_id = %webhook.id, attempt, error = %err, "Webhook delivery failed"),
+        }
+        if attempt < MAX_ATTEMPTS {
+            actix_rt::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+
+    tracing::error!(webhook_id = %webhook.id, event, "Webhook delivery abandoned");
+    false
+}
+
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    let digest: String = mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256={}", digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use actix_web::{web, App, HttpRequest, HttpResponse};
+    use crate::repository::error::RepositoryError;
+
+    use super::*;
+
+    #[test]
+    fn test_should_sign_bodies_with_hmac_sha256() {
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_should_retry_failed_deliveries_with_the_signature() {
+        let received: Arc<Mutex<Vec<String>>> = Arc::default();
+        let server_received = received.clone();
+        let server = actix_test::start(move || {
+            let received = server_received.clone();
+            App::new().route("/hook", web::post().to(move |req: HttpRequest| {
+                let received = received.clone();
+                async move {
+                    let mut received = received.lock().unwrap();
+                    received.push(req.headers().get(SIGNATURE_HEADER).unwrap().to_str().unwrap().to_string());
+                    // Fails the first attempt.
+                    if received.len() == 1 { HttpResponse::ServiceUnavailable().finish() } else { HttpResponse::Ok().finish() }
+                }
+            }))
+        });
+        let webhook = Webhook {
+            id: "webhook".to_string(),
+            target_url: server.url("/hook"),
+            event_types: vec![BATTLE_COMPLETED.to_string()],
+            secret: "secret".to_string(),
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: None,
+        };
+
+        let delivered = deliver(webhook, BATTLE_COMPLETED, Bytes::from_static(b"{}"), Duration::from_millis(1)).await;
+
+        assert!(delivered);
+        assert_eq!(*received.lock().unwrap(), vec![sign("secret", b"{}"); 2]);
+    }
+
+    // Records the schema webhooks are looked up in.
+    #[derive(Default)]
+    struct SchemaRecorder(Mutex<Vec<Option<String>>>);
+
+    impl WebhookRepository for SchemaRecorder {
+        fn get_webhooks(&self) -> Vec<Webhook> {
+            Vec::new()
+        }
+
+        fn get_webhooks_for_event(&self, _event_type: &str) -> Vec<Webhook> {
+            self.0.lock().unwrap().push(RequestScope::current().schema);
+            Vec::new()
+        }
+
+        fn get_webhook_by_id(&self, _webhook_id: &str) -> Option<Webhook> {
+            None
+        }
+
+        fn create_webhook(&self, webhook: Webhook) -> Result<Webhook, RepositoryError> {
+            Ok(webhook)
+        }
+
+        fn update_webhook_by_id(&self, _webhook_id: &str, _webhook: Webhook) -> Result<Option<Webhook>, RepositoryError> {
+            Ok(None)
+        }
+
+        fn delete_webhook_by_id(&self, _webhook_id: &str) -> Option<usize> {
+            None
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_should_look_up_the_webhooks_in_the_schema_of_the_battle() {
+        use crate::api::battle_apis::{new_manual_battle, ManualOutcome, BATTLE_FEED};
+        use crate::repository::database::Database;
+
+        let recorder = Arc::new(SchemaRecorder::default());
+        let dispatcher = actix_rt::spawn(dispatch_battles(recorder.clone(), Duration::from_millis(1)));
+        let battle = new_manual_battle("monster-a".to_string(), "monster-b".to_string(), ManualOutcome::Win);
+        // Published until the dispatcher, subscribing on its own task, has seen it.
+        while !recorder.0.lock().unwrap().contains(&Some("webhook_test".to_string())) {
+            Database::with_schema("webhook_test".to_string(), async { BATTLE_FEED.publish(battle.clone()) }).await;
+            actix_rt::time::sleep(Duration::from_millis(10)).await;
+        }
+        dispatcher.abort();
+    }
+}